@@ -0,0 +1,122 @@
+#import bevy_pbr::forward_io::VertexOutput
+
+@group(2) @binding(0) var<uniform> crater_color: vec4<f32>;
+@group(2) @binding(1) var<uniform> crack_color: vec4<f32>;
+@group(2) @binding(2) var<uniform> leaf_color: vec4<f32>;
+
+// Positions in `DecalKind::ALL`, as stored in the vertex color's red channel.
+const DECAL_CRACK: u32 = 1u;
+const DECAL_LEAVES: u32 = 2u;
+
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+fn hash2(p: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(hash(p), hash(p + vec2<f32>(17.31, 5.17)));
+}
+
+// Smoothly interpolated value noise in [0, 1].
+fn value_noise(p: vec2<f32>) -> f32 {
+    let cell = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+    let north = mix(hash(cell), hash(cell + vec2<f32>(1.0, 0.0)), u.x);
+    let south = mix(hash(cell + vec2<f32>(0.0, 1.0)), hash(cell + vec2<f32>(1.0, 1.0)), u.x);
+    return mix(north, south, u.y);
+}
+
+// A scorched bowl with a ragged edge, ringed by a fainter band where the
+// rim was thrown up. `p` runs from -1 to 1 across the decal.
+fn crater_coverage(p: vec2<f32>, seed: f32) -> f32 {
+    let direction = p / max(length(p), 1e-4);
+    let radius = length(p) + (value_noise(direction * 2.0 + seed * 37.0) - 0.5) * 0.3;
+    let bowl = 1.0 - smoothstep(0.3, 0.55, radius);
+    let rim = smoothstep(0.5, 0.62, radius) * (1.0 - smoothstep(0.7, 0.95, radius));
+    return max(bowl, rim * 0.35);
+}
+
+// Difference between the distances to the nearest and second nearest of a
+// set of jittered points, which is small along the borders between them.
+fn cell_border_distance(p: vec2<f32>, seed: f32) -> f32 {
+    let cell = floor(p);
+    var nearest = 8.0;
+    var second = 8.0;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let neighbor = cell + vec2<f32>(f32(x), f32(y));
+            let distance = length(neighbor + hash2(neighbor + seed * 31.0) - p);
+            if distance < nearest {
+                second = nearest;
+                nearest = distance;
+            } else if distance < second {
+                second = distance;
+            }
+        }
+    }
+    return second - nearest;
+}
+
+// A web of cracks along warped cell borders, thinning out towards the edge.
+fn crack_coverage(p: vec2<f32>, seed: f32) -> f32 {
+    let warp = vec2<f32>(value_noise(p * 3.0 + seed * 11.0), value_noise(p * 3.0 + seed * 23.0 + 7.0));
+    let border = cell_border_distance(p * 2.5 + (warp - 0.5) * 0.8, seed);
+    let crack = 1.0 - smoothstep(0.02, 0.07, border);
+    return crack * (1.0 - smoothstep(0.45, 1.0, length(p)));
+}
+
+// Leaves scattered over a jittered grid, each a lens turned at random. The
+// second value scales the leaf color so the leaves don't all match.
+fn leaf_coverage(p: vec2<f32>, seed: f32) -> vec2<f32> {
+    let q = p * 3.0;
+    let cell = floor(q);
+    var coverage = vec2<f32>(0.0, 1.0);
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let neighbor = cell + vec2<f32>(f32(x), f32(y));
+            let random = hash2(neighbor + seed * 19.0);
+            if hash(neighbor + seed * 7.0) > 0.7 {
+                continue;
+            }
+            let angle = random.x * 6.2832;
+            let offset = q - (neighbor + 0.2 + random * 0.6);
+            let local = vec2<f32>(
+                offset.x * cos(angle) + offset.y * sin(angle),
+                offset.y * cos(angle) - offset.x * sin(angle),
+            );
+            // The overlap of two circles either side of the leaf's midrib.
+            let edge = max(length(local - vec2<f32>(0.0, 0.2)), length(local + vec2<f32>(0.0, 0.2)));
+            let leaf = 1.0 - smoothstep(0.27, 0.3, edge);
+            if leaf > coverage.x {
+                coverage = vec2<f32>(leaf, 0.75 + random.y * 0.5);
+            }
+        }
+    }
+    coverage.x *= 1.0 - smoothstep(0.75, 1.0, length(p));
+    return coverage;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let kind = u32(round(in.color.r));
+    let seed = in.color.b;
+    var tint = crater_color.rgb;
+    var coverage = 0.0;
+    switch kind {
+        case DECAL_CRACK: {
+            tint = crack_color.rgb;
+            coverage = crack_coverage(in.uv, seed);
+        }
+        case DECAL_LEAVES: {
+            let leaves = leaf_coverage(in.uv, seed);
+            tint = leaf_color.rgb * leaves.y;
+            coverage = leaves.x;
+        }
+        default: {
+            coverage = crater_coverage(in.uv, seed);
+        }
+    }
+    // Multiplicative blending scales the ground by mix(1, tint, alpha).
+    let alpha = clamp(coverage * in.color.g, 0.0, 1.0);
+    return vec4<f32>(tint * alpha, alpha);
+}
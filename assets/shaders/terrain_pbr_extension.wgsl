@@ -22,6 +22,8 @@
 #import bevy_pbr::meshlet_visibility_buffer_resolve::resolve_vertex_output
 #endif
 
+#import dprmapedit::terrain_user_overrides::{terrain_user_albedo, terrain_user_normal}
+
 struct TerrainMaterialExtension {
     uv_scale: f32,
     layer_count: u32,
@@ -34,12 +36,19 @@ struct TerrainMaterialExtension {
     wall_enabled: u32,
     wall_has_normal: u32,
     wall_has_roughness: u32,
-    _padding: vec2<f32>,
+    lightmap_enabled: u32,
+    // Cosines of the slopes where top faces start fading into the cliff
+    // layer (x) and are fully cliff (y); zeros leave top faces alone.
+    cliff_slope: vec2<f32>,
 }
 
 @group(2) @binding(100)
 var<uniform> terrain_material_extension: TerrainMaterialExtension;
 
+// Values for the user shader hooks; see terrain_user_overrides.wgsl.
+@group(2) @binding(113)
+var<uniform> terrain_user: array<vec4<f32>, 4>;
+
 #ifdef TERRAIN_MATERIAL_EXTENSION_BASE_COLOR_ARRAY
 @group(2) @binding(101)
 var terrain_base_color_array: texture_2d_array<f32>;
@@ -63,11 +72,36 @@ var terrain_roughness_sampler: sampler;
 
 #ifdef TERRAIN_MATERIAL_EXTENSION_SPLAT_MAP
 @group(2) @binding(107)
-var terrain_splat_map: texture_2d<f32>;
+var terrain_splat_map: texture_2d_array<f32>;
 @group(2) @binding(108)
 var terrain_splat_sampler: sampler;
 #endif
 
+#ifdef TERRAIN_MATERIAL_EXTENSION_LIGHTMAP
+@group(2) @binding(109)
+var terrain_lightmap: texture_2d<f32>;
+@group(2) @binding(110)
+var terrain_lightmap_sampler: sampler;
+#endif
+
+#ifdef TERRAIN_MATERIAL_EXTENSION_VARIATION_MAP
+@group(2) @binding(111)
+var terrain_variation_map: texture_2d<f32>;
+@group(2) @binding(112)
+var terrain_variation_sampler: sampler;
+#endif
+
+#ifdef TERRAIN_MATERIAL_EXTENSION_ROAD_MAP
+@group(2) @binding(114)
+var terrain_road_map: texture_2d<f32>;
+@group(2) @binding(115)
+var terrain_road_sampler: sampler;
+#endif
+
+// How dark fully shadowed texels get relative to sunlit ones when a baked
+// lightmap is used in place of realtime shadows.
+const LIGHTMAP_SHADOW_FLOOR: f32 = 0.45;
+
 fn triplanar_sample(
     tex: texture_2d<f32>,
     samp: sampler,
@@ -212,44 +246,135 @@ fn triplanar_sample_layer_scalar(
 }
 #endif
 
-const MAX_TERRAIN_LAYERS: u32 = 4u;
+// The splatmap stores four layer weights per array page.
+const SPLAT_LAYERS_PER_PAGE: u32 = 4u;
+const MAX_SPLAT_PAGES: u32 = 4u;
+// Must match the length of the weight arrays below and
+// `terrain::splatmap::MAX_LAYERS`.
+const MAX_TERRAIN_LAYERS: u32 = 16u;
 
-fn assign_weight(weights: vec4<f32>, index: u32, value: f32) -> vec4<f32> {
-    var result = weights;
-    switch index {
-        case 0u: {
-            result.x = value;
-        }
+// Per-tile texture variation as (offset seed, quarter turns, mirrored).
+fn tile_variation(world_position: vec3<f32>) -> vec3<u32> {
+#ifdef TERRAIN_MATERIAL_EXTENSION_VARIATION_MAP
+    let safe_tile = max(terrain_material_extension.tile_size, 0.0001);
+    let map_size_i = vec2<i32>(max(terrain_material_extension.map_size, vec2<f32>(1.0, 1.0)));
+    let tile = clamp(
+        vec2<i32>(floor(world_position.xz / safe_tile)),
+        vec2<i32>(0, 0),
+        map_size_i - vec2<i32>(1, 1),
+    );
+    let texel = textureLoad(terrain_variation_map, tile, 0);
+    return vec3<u32>(
+        u32(round(texel.r * 255.0)),
+        u32(round(texel.g * 255.0)) % 4u,
+        u32(round(texel.b * 255.0)),
+    );
+#else
+    return vec3<u32>(0u, 0u, 0u);
+#endif
+}
+
+fn rotate_quarter_turns(v: vec2<f32>, turns: u32) -> vec2<f32> {
+    switch turns % 4u {
         case 1u: {
-            result.y = value;
+            return vec2<f32>(-v.y, v.x);
         }
         case 2u: {
-            result.z = value;
+            return -v;
+        }
+        case 3u: {
+            return vec2<f32>(v.y, -v.x);
         }
         default: {
-            result.w = value;
+            return v;
         }
     }
-    return result;
 }
 
-fn weight_component(weights: vec4<f32>, index: u32) -> f32 {
-    switch index {
-        case 0u: {
-            return weights.x;
+// Moves a top-face sample position so each tile reads a different, rotated
+// and possibly mirrored part of the texture.
+fn varied_top_position(world_position: vec3<f32>, variation: vec3<u32>) -> vec3<f32> {
+    let safe_tile = max(terrain_material_extension.tile_size, 0.0001);
+    let centre = (floor(world_position.xz / safe_tile) + vec2<f32>(0.5, 0.5)) * safe_tile;
+    var local = world_position.xz - centre;
+    if (variation.z != 0u) {
+        local.x = -local.x;
+    }
+    let rotated = centre + rotate_quarter_turns(local, variation.y);
+    // Irrational steps spread successive seeds evenly over one texture repeat.
+    let period = 1.0 / max(terrain_material_extension.uv_scale, 0.0001);
+    let offset = fract(vec2<f32>(0.618034, 0.754878) * f32(variation.x)) * period;
+    return vec3<f32>(rotated.x + offset.x, world_position.y, rotated.y + offset.y);
+}
+
+// Road surfaces are numbered as in `RoadSurface::ALL`.
+const ROAD_SURFACE_COUNT: u32 = 2u;
+// Half a road's width, and the width of its soft edge, in tiles.
+const ROAD_HALF_WIDTH: f32 = 0.28;
+const ROAD_EDGE: f32 = 0.04;
+
+// Linear colour (rgb) and roughness (a) of a road surface.
+fn road_surface_look(surface: u32) -> vec4<f32> {
+    switch surface {
+        case 1u: {
+            return vec4<f32>(0.09, 0.09, 0.1, 0.7);
         }
+        default: {
+            return vec4<f32>(0.23, 0.15, 0.08, 0.95);
+        }
+    }
+}
+
+// Distance in tiles from `q`, relative to the tile centre, to the middle of
+// an unturned road piece, numbered as in `RoadPiece::ALL`. North is -y.
+fn road_piece_distance(piece: u32, q: vec2<f32>) -> f32 {
+    switch piece {
         case 1u: {
-            return weights.y;
+            return length(q - vec2<f32>(0.0, clamp(q.y, -0.5, 0.0)));
         }
         case 2u: {
-            return weights.z;
+            return abs(q.x);
+        }
+        case 3u: {
+            // A quarter circle around the north-east corner.
+            return abs(length(q - vec2<f32>(0.5, -0.5)) - 0.5);
+        }
+        case 4u: {
+            return min(abs(q.x), length(q - vec2<f32>(clamp(q.x, 0.0, 0.5), 0.0)));
+        }
+        case 5u: {
+            return min(abs(q.x), abs(q.y));
         }
         default: {
-            return weights.w;
+            return length(q);
         }
     }
 }
 
+// The road surface under a point (x) and how much of the point it covers
+// (y).
+fn road_coverage(world_position: vec3<f32>) -> vec2<f32> {
+#ifdef TERRAIN_MATERIAL_EXTENSION_ROAD_MAP
+    let safe_tile = max(terrain_material_extension.tile_size, 0.0001);
+    let map_size_i = vec2<i32>(max(terrain_material_extension.map_size, vec2<f32>(1.0, 1.0)));
+    let tile_space = world_position.xz / safe_tile;
+    let tile = clamp(vec2<i32>(floor(tile_space)), vec2<i32>(0, 0), map_size_i - vec2<i32>(1, 1));
+    let texel = textureLoad(terrain_road_map, tile, 0);
+    let code = u32(round(texel.r * 255.0));
+    if (code == 0u || code > ROAD_SURFACE_COUNT) {
+        return vec2<f32>(0.0, 0.0);
+    }
+    let piece = u32(round(texel.g * 255.0));
+    let turns = u32(round(texel.b * 255.0)) % 4u;
+    let local = tile_space - vec2<f32>(tile) - vec2<f32>(0.5, 0.5);
+    let distance = road_piece_distance(piece, rotate_quarter_turns(local, 4u - turns));
+    let coverage = 1.0 - smoothstep(ROAD_HALF_WIDTH - ROAD_EDGE, ROAD_HALF_WIDTH + ROAD_EDGE, distance);
+    return vec2<f32>(f32(code - 1u), coverage);
+#else
+    return vec2<f32>(0.0, 0.0);
+#endif
+}
+
 fn world_to_splat_uv(world_position: vec3<f32>) -> vec2<f32> {
     let safe_tile = max(terrain_material_extension.tile_size, 0.0001);
     let safe_map = max(terrain_material_extension.map_size, vec2<f32>(1.0, 1.0));
@@ -258,6 +383,23 @@ fn world_to_splat_uv(world_position: vec3<f32>) -> vec2<f32> {
     return clamp(uv, vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 1.0));
 }
 
+#ifdef TERRAIN_MATERIAL_EXTENSION_SPLAT_MAP
+// Weight of every layer at `uv`, gathered from each page of the splatmap.
+fn sample_splat_weights(uv: vec2<f32>) -> array<f32, 16> {
+    var weights = array<f32, 16>();
+    let pages = min(textureNumLayers(terrain_splat_map), MAX_SPLAT_PAGES);
+    for (var page = 0u; page < pages; page = page + 1u) {
+        let texel = textureSampleLevel(terrain_splat_map, terrain_splat_sampler, uv, page, 0.0);
+        let first = page * SPLAT_LAYERS_PER_PAGE;
+        weights[first] = texel.x;
+        weights[first + 1u] = texel.y;
+        weights[first + 2u] = texel.z;
+        weights[first + 3u] = texel.w;
+    }
+    return weights;
+}
+#endif
+
 fn clamp_layer_index(layer: i32, available_layers: u32) -> i32 {
     if (available_layers == 0u) {
         return 0;
@@ -318,11 +460,7 @@ fn fragment(
 #endif
 
 #ifdef TERRAIN_MATERIAL_EXTENSION_SPLAT_MAP
-    var weights = textureSample(
-        terrain_splat_map,
-        terrain_splat_sampler,
-        world_to_splat_uv(pbr_input.world_position.xyz),
-    );
+    var weights = sample_splat_weights(world_to_splat_uv(pbr_input.world_position.xyz));
 #ifdef VERTEX_COLORS
     let is_top_face = abs(pbr_input.world_normal.y) >= 0.5;
     if (is_top_face && in.color.r < -1.5) {
@@ -363,26 +501,44 @@ fn fragment(
             if (needs_adjustment) {
                 let sample_uv = (tile_base + adjusted) / safe_map;
                 let clamped_uv = clamp(sample_uv, vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 1.0));
-                weights = textureSampleLevel(
-                    terrain_splat_map,
-                    terrain_splat_sampler,
-                    clamped_uv,
-                    0.0,
-                );
+                weights = sample_splat_weights(clamped_uv);
             }
         }
     }
 #endif
 #else
-    var weights = vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    var weights = array<f32, 16>();
 #endif
 
     let available_layers = min(terrain_material_extension.layer_count, MAX_TERRAIN_LAYERS);
-    var weight_total = weights.x + weights.y + weights.z + weights.w;
+
+#ifdef VERTEX_COLORS
+#ifdef VERTEX_UVS_B
+    // Tiles on upper layers carry their own texture; the splat map only
+    // describes the ground beneath them.
+    if (in.color.a > 0.5 && available_layers > 0u) {
+        weights = array<f32, 16>();
+        weights[u32(clamp_layer_index(i32(round(in.uv_b.x)), available_layers))] = 1.0;
+    }
+#endif
+#endif
+
+    // Top faces sample through the tile's variation; walls keep world space.
+    let top_surface = abs(pbr_input.world_normal.y) >= 0.5;
+    let variation = tile_variation(pbr_input.world_position.xyz);
+    var layer_position = pbr_input.world_position.xyz;
+    if (top_surface) {
+        layer_position = varied_top_position(layer_position, variation);
+    }
+    var weight_total = 0.0;
+    for (var layer = 0u; layer < MAX_TERRAIN_LAYERS; layer = layer + 1u) {
+        weight_total += weights[layer];
+    }
 
     if (weight_total <= 0.0001) {
+        weights = array<f32, 16>();
         if (available_layers == 0u) {
-            weights = vec4<f32>(1.0, 0.0, 0.0, 0.0);
+            weights[0] = 1.0;
         } else {
 #ifdef VERTEX_UVS_B
             let fallback_source = in.uv_b.x;
@@ -390,11 +546,13 @@ fn fragment(
             let fallback_source = 0.0;
 #endif
             let fallback_layer = clamp_layer_index(i32(round(fallback_source)), available_layers);
-            weights = assign_weight(vec4<f32>(0.0, 0.0, 0.0, 0.0), u32(fallback_layer), 1.0);
+            weights[u32(fallback_layer)] = 1.0;
         }
         weight_total = 1.0;
     } else {
-        weights = weights / weight_total;
+        for (var layer = 0u; layer < MAX_TERRAIN_LAYERS; layer = layer + 1u) {
+            weights[layer] = weights[layer] / weight_total;
+        }
     }
 
 #ifdef TERRAIN_MATERIAL_EXTENSION_BASE_COLOR_ARRAY
@@ -402,7 +560,7 @@ fn fragment(
         var color_accum = vec3<f32>(0.0, 0.0, 0.0);
         var color_weight = 0.0;
         for (var layer = 0u; layer < available_layers; layer = layer + 1u) {
-            let weight = weight_component(weights, layer);
+            let weight = weights[layer];
             if (weight <= 0.0001) {
                 continue;
             }
@@ -410,7 +568,7 @@ fn fragment(
             let sampled = triplanar_sample_layer(
                 terrain_base_color_array,
                 terrain_base_color_sampler,
-                pbr_input.world_position.xyz,
+                layer_position,
                 pbr_input.world_normal.xyz,
                 scale,
                 i32(layer),
@@ -430,7 +588,7 @@ fn fragment(
         var normal_accum = vec3<f32>(0.0, 0.0, 0.0);
         var normal_weight = 0.0;
         for (var layer = 0u; layer < available_layers; layer = layer + 1u) {
-            let weight = weight_component(weights, layer);
+            let weight = weights[layer];
             if (weight <= 0.0001) {
                 continue;
             }
@@ -438,12 +596,22 @@ fn fragment(
             let world_normal = triplanar_sample_layer_normal(
                 terrain_normal_array,
                 terrain_normal_sampler,
-                pbr_input.world_position.xyz,
+                layer_position,
                 pbr_input.world_normal.xyz,
                 scale,
                 i32(layer),
             );
-            normal_accum += world_normal * weight;
+            var layer_normal = world_normal;
+            if (top_surface) {
+                // The texture was turned and mirrored by the variation, so
+                // its normals turn back the other way and mirror again.
+                var turned = rotate_quarter_turns(world_normal.xz, 4u - variation.y);
+                if (variation.z != 0u) {
+                    turned.x = -turned.x;
+                }
+                layer_normal = vec3<f32>(turned.x, world_normal.y, turned.y);
+            }
+            normal_accum += layer_normal * weight;
             normal_weight += weight;
         }
 
@@ -460,7 +628,7 @@ fn fragment(
         var roughness_accum = 0.0;
         var roughness_weight = 0.0;
         for (var layer = 0u; layer < available_layers; layer = layer + 1u) {
-            let weight = weight_component(weights, layer);
+            let weight = weights[layer];
             if (weight <= 0.0001) {
                 continue;
             }
@@ -468,7 +636,7 @@ fn fragment(
             let sampled = triplanar_sample_layer_scalar(
                 terrain_roughness_array,
                 terrain_roughness_sampler,
-                pbr_input.world_position.xyz,
+                layer_position,
                 pbr_input.world_normal.xyz,
                 scale,
                 i32(layer),
@@ -488,6 +656,77 @@ fn fragment(
     }
 #endif
 
+    // Steep top faces, like sculpted slopes, fade into the cliff layer as
+    // they approach vertical, going by the geometric normal.
+    let cliff_slope = terrain_material_extension.cliff_slope;
+    if (top_surface && terrain_material_extension.wall_enabled == 1u && cliff_slope.x > cliff_slope.y) {
+        let steepness = 1.0 - smoothstep(cliff_slope.y, cliff_slope.x, abs(pbr_input.world_normal.y));
+        let wall_layer = i32(terrain_material_extension.wall_layer_index);
+        if (steepness > 0.0001) {
+#ifdef TERRAIN_MATERIAL_EXTENSION_BASE_COLOR_ARRAY
+            let cliff_color = triplanar_sample_layer(
+                terrain_base_color_array,
+                terrain_base_color_sampler,
+                pbr_input.world_position.xyz,
+                pbr_input.world_normal.xyz,
+                scale,
+                wall_layer,
+            );
+            base_color = vec4<f32>(mix(base_color.rgb, cliff_color.rgb, steepness), 1.0);
+#endif
+#ifdef TERRAIN_MATERIAL_EXTENSION_NORMAL_ARRAY
+            if (terrain_material_extension.wall_has_normal == 1u) {
+                let cliff_normal = triplanar_sample_layer_normal(
+                    terrain_normal_array,
+                    terrain_normal_sampler,
+                    pbr_input.world_position.xyz,
+                    pbr_input.world_normal.xyz,
+                    scale,
+                    wall_layer,
+                );
+                let blended_normal = normalize(mix(pbr_input.N, cliff_normal, steepness));
+                pbr_input.N = blended_normal;
+                pbr_input.clearcoat_N = blended_normal;
+            }
+#endif
+#ifdef TERRAIN_MATERIAL_EXTENSION_ROUGHNESS_ARRAY
+            if (terrain_material_extension.wall_has_roughness == 1u) {
+                let cliff_rough = triplanar_sample_layer_scalar(
+                    terrain_roughness_array,
+                    terrain_roughness_sampler,
+                    pbr_input.world_position.xyz,
+                    pbr_input.world_normal.xyz,
+                    scale,
+                    wall_layer,
+                );
+                let remapped = clamp(mix(0.2, 0.9, clamp(cliff_rough, 0.0, 1.0)), 0.045, 1.0);
+                pbr_input.material.perceptual_roughness =
+                    mix(pbr_input.material.perceptual_roughness, remapped, steepness);
+            }
+#endif
+        }
+    }
+
+    // Roads lie on the ground's top faces; upper layers pass over them.
+    var on_ground = top_surface;
+#ifdef VERTEX_COLORS
+    on_ground = on_ground && in.color.a < 0.5;
+#endif
+    if (on_ground) {
+        let road = road_coverage(pbr_input.world_position.xyz);
+        if (road.y > 0.0001) {
+            let look = road_surface_look(u32(road.x));
+            // The ground's own texture shows through as wear.
+            let detail = 0.6 + 0.8 * dot(base_color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+            base_color = vec4<f32>(mix(base_color.rgb, look.rgb * detail, road.y), 1.0);
+            pbr_input.material.perceptual_roughness =
+                mix(pbr_input.material.perceptual_roughness, look.a, road.y);
+            let flattened = normalize(mix(pbr_input.N, pbr_input.world_normal.xyz, road.y));
+            pbr_input.N = flattened;
+            pbr_input.clearcoat_N = flattened;
+        }
+    }
+
     if (abs(pbr_input.world_normal.y) < 0.5 && available_layers > 0u) {
 #ifdef VERTEX_UVS_B
         let fallback_source = in.uv_b.x;
@@ -732,6 +971,33 @@ fn fragment(
 #endif
     }
 
+#ifdef TERRAIN_MATERIAL_EXTENSION_LIGHTMAP
+    if (terrain_material_extension.lightmap_enabled == 1u) {
+        let baked = textureSample(
+            terrain_lightmap,
+            terrain_lightmap_sampler,
+            world_to_splat_uv(pbr_input.world_position.xyz),
+        );
+        let sun = mix(LIGHTMAP_SHADOW_FLOOR, 1.0, baked.r);
+        base_color = vec4<f32>(base_color.rgb * sun, base_color.a);
+        pbr_input.diffuse_occlusion = pbr_input.diffuse_occlusion * baked.g;
+    }
+#endif
+
+    let user_normal = terrain_user_normal(
+        pbr_input.N,
+        pbr_input.world_position.xyz,
+        terrain_user,
+    );
+    pbr_input.N = user_normal;
+    pbr_input.clearcoat_N = user_normal;
+    base_color = terrain_user_albedo(
+        base_color,
+        pbr_input.world_position.xyz,
+        pbr_input.world_normal.xyz,
+        terrain_user,
+    );
+
     pbr_input.material.base_color = alpha_discard(pbr_input.material, base_color);
 
 
@@ -764,6 +1030,7 @@ fn fragment(
         terrain_splat_map,
         terrain_splat_sampler,
         world_to_splat_uv(pbr_input.world_position.xyz),
+        0,
     );
     out.color = vec4<f32>(splat.rgb, 1.0);
 #endif
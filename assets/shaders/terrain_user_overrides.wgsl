@@ -0,0 +1,26 @@
+// Hooks the terrain material calls so games can change its look without
+// editing terrain_pbr_extension.wgsl. To override them, copy this file into
+// your assets, keep the import path and signatures, and point
+// `TerrainShaderHooks` at it.
+//
+// `user` holds the four values of `TerrainUserUniforms`, free for any use.
+#define_import_path dprmapedit::terrain_user_overrides
+
+// Final surface color, after layer blending, cliffs and the baked lightmap.
+fn terrain_user_albedo(
+    color: vec4<f32>,
+    world_position: vec3<f32>,
+    world_normal: vec3<f32>,
+    user: array<vec4<f32>, 4>,
+) -> vec4<f32> {
+    return color;
+}
+
+// Shading normal after the layers' normal maps have been blended.
+fn terrain_user_normal(
+    normal: vec3<f32>,
+    world_position: vec3<f32>,
+    user: array<vec4<f32>, 4>,
+) -> vec3<f32> {
+    return normal;
+}
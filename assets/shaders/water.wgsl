@@ -0,0 +1,52 @@
+#import bevy_pbr::{
+    forward_io::VertexOutput,
+    mesh_view_bindings::{globals, view},
+}
+
+@group(2) @binding(0) var<uniform> water_color: vec4<f32>;
+@group(2) @binding(1) var<uniform> sky_color: vec4<f32>;
+// Drift per second (xy), ripples per world unit (z) and the depth over which
+// the water fades in from the shore (w).
+@group(2) @binding(2) var<uniform> ripples: vec4<f32>;
+
+// The surface is unlit apart from glints off a fixed, high sun.
+const SUN_DIRECTION: vec3<f32> = vec3<f32>(0.35, 0.85, 0.4);
+
+// Slope of a few sine waves travelling in different directions, summed into
+// a height gradient. `p` is already scrolled and scaled.
+fn ripple_gradient(p: vec2<f32>, time: f32) -> vec2<f32> {
+    var directions = array<vec2<f32>, 4>(
+        vec2<f32>(1.0, 0.3),
+        vec2<f32>(-0.4, 1.0),
+        vec2<f32>(0.7, -0.8),
+        vec2<f32>(-1.0, -0.25),
+    );
+    var gradient = vec2<f32>(0.0);
+    for (var i = 0; i < 4; i++) {
+        let direction = normalize(directions[i]);
+        let frequency = 1.0 + f32(i) * 0.7;
+        let phase = dot(direction, p) * frequency + time * (1.0 + f32(i) * 0.35);
+        gradient += direction * frequency * cos(phase) / f32(i + 1);
+    }
+    return gradient * 0.08;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let time = globals.time;
+    let p = (in.world_position.xz + ripples.xy * time) * ripples.z;
+    let gradient = ripple_gradient(p, time) + ripple_gradient(p.yx * 2.3, time * 1.4) * 0.5;
+    let normal = normalize(vec3<f32>(-gradient.x, 1.0, -gradient.y));
+
+    let to_eye = normalize(view.world_position - in.world_position.xyz);
+    let facing = clamp(dot(normal, to_eye), 0.0, 1.0);
+    // Schlick's approximation with water's reflectance at normal incidence.
+    let fresnel = 0.02 + 0.98 * pow(1.0 - facing, 5.0);
+    let glint = pow(max(dot(reflect(-normalize(SUN_DIRECTION), normal), to_eye), 0.0), 96.0);
+
+    let color = mix(water_color.rgb, sky_color.rgb, fresnel) + vec3<f32>(glint);
+    // Shallow water lets the terrain beneath show through.
+    let depth = clamp(in.uv.x / max(ripples.w, 1e-4), 0.0, 1.0);
+    let alpha = mix(water_color.a, 1.0, fresnel) * smoothstep(0.0, 1.0, depth);
+    return vec4<f32>(color, clamp(alpha + glint, 0.0, 1.0));
+}
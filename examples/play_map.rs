@@ -0,0 +1,233 @@
+//! Loads a saved map through the asset server, renders it with the runtime
+//! terrain and lets you click to walk a unit across it along the map's
+//! reachability graph. Right-click blasts a crater into the terrain.
+//!
+//! ```sh
+//! cargo run --example play_map -- maps/example.tme
+//! ```
+//!
+//! The path is relative to `assets/`.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use dprmapedit::picking::{HeightfieldPicker, HeightfieldPickingPlugin};
+use dprmapedit::runtime::{RuntimeMap, RuntimeMapHandle, RuntimePlugin, TerrainEdit};
+use dprmapedit::terrain::{CornerHeightCache, TerrainMeshSet};
+use dprmapedit::texture::TexturePlugin;
+use dprmapedit::texture::material::TerrainMaterial;
+use dprmapedit::texture::registry::TerrainTextureRegistry;
+use dprmapedit::types::{TileKind, TileMap};
+
+const DEFAULT_MAP: &str = "maps/example.tme";
+const UNIT_SPEED: f32 = 4.0;
+const UNIT_RADIUS: f32 = 0.4;
+const UNIT_HEIGHT: f32 = 1.2;
+const CRATER_RADIUS: u32 = 2;
+
+fn main() {
+    let map_path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_MAP.to_string());
+
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            TexturePlugin,
+            RuntimePlugin,
+            HeightfieldPickingPlugin,
+        ))
+        .insert_resource(MapPath(map_path))
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (place_unit, order_move, blast_crater)
+                .chain()
+                .after(TerrainMeshSet::Material),
+        )
+        .add_systems(Update, move_unit.after(order_move))
+        .run();
+}
+
+#[derive(Resource)]
+struct MapPath(String);
+
+/// A unit standing on `tile`, walking through `path` one tile at a time.
+#[derive(Component, Default)]
+struct Unit {
+    tile: Option<(u32, u32)>,
+    path: VecDeque<(u32, u32)>,
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    map_path: Res<MapPath>,
+    mut textures: ResMut<TerrainTextureRegistry>,
+    mut terrain_materials: ResMut<Assets<TerrainMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    textures.load_defaults(&asset_server, &mut terrain_materials);
+    commands.insert_resource(RuntimeMapHandle(asset_server.load(map_path.0.clone())));
+
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(-12.0, 36.0, 70.0)
+            .looking_at(Vec3::new(24.0, 0.0, 24.0), Vec3::Y),
+        ..default()
+    });
+    commands.spawn(DirectionalLightBundle {
+        directional_light: DirectionalLight {
+            illuminance: 20_000.0,
+            ..default()
+        },
+        transform: Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -1.2, -0.8, 0.0)),
+        ..default()
+    });
+
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Capsule3d::new(UNIT_RADIUS, UNIT_HEIGHT - 2.0 * UNIT_RADIUS)),
+            material: materials.add(Color::srgb(0.9, 0.2, 0.2)),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        Unit::default(),
+    ));
+}
+
+/// Drops the unit on the first player start, or the first solid tile, each
+/// time a map is loaded.
+fn place_unit(
+    mut loaded: EventReader<AssetEvent<TileMap>>,
+    runtime_map: Res<RuntimeMap>,
+    mut units: Query<(&mut Unit, &mut Transform, &mut Visibility)>,
+) {
+    let reloaded = loaded.read().any(|event| {
+        matches!(
+            event,
+            AssetEvent::LoadedWithDependencies { .. } | AssetEvent::Modified { .. }
+        )
+    });
+    if !reloaded {
+        return;
+    }
+
+    let map = &runtime_map.map;
+    let start = map
+        .starts
+        .first()
+        .map(|start| (start.x, start.y))
+        .or_else(|| {
+            (0..map.height)
+                .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+                .find(|&(x, y)| map.get(x, y).kind != TileKind::Empty)
+        });
+    for (mut unit, mut transform, mut visibility) in &mut units {
+        unit.tile = start;
+        unit.path.clear();
+        if let Some((x, y)) = start {
+            transform.translation.x = (x as f32 + 0.5) * map.tile_size;
+            transform.translation.z = (y as f32 + 0.5) * map.tile_size;
+        }
+        *visibility = if start.is_some() {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+/// The tile under the mouse cursor.
+fn cursor_tile(
+    windows: &Query<&Window>,
+    cameras: &Query<(&Camera, &GlobalTransform)>,
+    picker: &HeightfieldPicker,
+) -> Option<(u32, u32)> {
+    let window = windows.get_single().ok()?;
+    let (camera, camera_transform) = cameras.get_single().ok()?;
+    let ray = camera.viewport_to_world(camera_transform, window.cursor_position()?)?;
+    picker.raycast(ray.origin, *ray.direction)
+}
+
+/// Left-click a tile to walk there along the cheapest reachable route.
+fn order_move(
+    buttons: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    picker: Res<HeightfieldPicker>,
+    runtime_map: Res<RuntimeMap>,
+    mut units: Query<&mut Unit>,
+) {
+    if !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Some(target) = cursor_tile(&windows, &cameras, &picker) else {
+        return;
+    };
+
+    let reachability = runtime_map.map.reachability();
+    for mut unit in &mut units {
+        let Some(tile) = unit.tile else {
+            continue;
+        };
+        // Finish the step in progress before turning onto the new route.
+        let from = unit.path.front().copied().unwrap_or(tile);
+        match reachability.path(from, target) {
+            Some(path) => {
+                let first_step = unit.path.pop_front();
+                unit.path = first_step
+                    .into_iter()
+                    .chain(path.into_iter().skip(1))
+                    .collect();
+            }
+            None => info!("Tile {target:?} is not reachable"),
+        }
+    }
+}
+
+/// Right-click to lower the terrain around a tile. The runtime patches the
+/// splatmap for just the touched tiles.
+fn blast_crater(
+    buttons: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    picker: Res<HeightfieldPicker>,
+    mut edits: EventWriter<TerrainEdit>,
+) {
+    if !buttons.just_pressed(MouseButton::Right) {
+        return;
+    }
+    if let Some((x, y)) = cursor_tile(&windows, &cameras, &picker) {
+        edits.send(TerrainEdit::Crater {
+            x,
+            y,
+            radius: CRATER_RADIUS,
+            depth: 1,
+        });
+    }
+}
+
+fn move_unit(
+    time: Res<Time>,
+    heights: Res<CornerHeightCache>,
+    mut units: Query<(&mut Unit, &mut Transform)>,
+) {
+    for (mut unit, mut transform) in &mut units {
+        let Some(tile) = unit.tile else {
+            continue;
+        };
+        let goal = unit.path.front().copied().unwrap_or(tile);
+        let goal_xz = Vec2::new(goal.0 as f32 + 0.5, goal.1 as f32 + 0.5) * heights.tile_size();
+        let next_xz = transform
+            .translation
+            .xz()
+            .move_towards(goal_xz, UNIT_SPEED * time.delta_seconds());
+        if next_xz == goal_xz && !unit.path.is_empty() {
+            unit.tile = unit.path.pop_front();
+        }
+
+        let ground = heights.height_at(next_xz.x, next_xz.y).unwrap_or(0.0);
+        transform.translation = Vec3::new(next_xz.x, ground + UNIT_HEIGHT * 0.5, next_xz.y);
+    }
+}
@@ -0,0 +1,288 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+use crate::localization::Localization;
+use crate::notifications::Notify;
+use crate::texture::material::TerrainMaterial;
+use crate::texture::registry::TerrainTextureRegistry;
+use crate::types::TileType;
+
+/// Folder scanned for textures, relative to the asset root.
+const TEXTURE_DIR: &str = "textures";
+const ASSET_ROOT: &str = "assets";
+const IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+const THUMBNAIL_SIZE: f32 = 48.0;
+/// Id of the cliff wall texture, as registered by `load_defaults`.
+const WALL_ID: &str = "wall";
+
+/// A window showing every image under `assets/textures`. Dragging one onto a
+/// terrain slot or the wall slot registers it as that slot's base color,
+/// replacing the bundled texture for the session and in exports. Dragging
+/// one onto the new layer slot adds it as the next paintable layer.
+pub struct AssetBrowserPlugin;
+
+impl Plugin for AssetBrowserPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AssetBrowser>()
+            .add_systems(Update, asset_browser_window);
+    }
+}
+
+#[derive(Resource, Default)]
+struct AssetBrowser {
+    /// `None` until the window is first expanded.
+    images: Option<Vec<AssetImage>>,
+}
+
+struct AssetImage {
+    /// Path relative to the asset root, as the asset server expects.
+    path: String,
+    name: String,
+    handle: Handle<Image>,
+}
+
+/// What is carried while an image is dragged.
+struct DraggedTexture(usize);
+
+#[derive(Clone, Copy)]
+enum TextureSlot {
+    Terrain(TileType),
+    Wall,
+}
+
+/// Every image below `dir`, as paths relative to the asset root, sorted.
+fn find_images(dir: &Path) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let is_image = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| {
+                    IMAGE_EXTENSIONS
+                        .iter()
+                        .any(|known| ext.eq_ignore_ascii_case(known))
+                });
+            let relative = path.strip_prefix(ASSET_ROOT).ok().and_then(Path::to_str);
+            if let Some(relative) = relative.filter(|_| is_image) {
+                found.push(relative.replace('\\', "/"));
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+fn file_stem(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn asset_browser_window(
+    mut egui: EguiContexts,
+    mut browser: ResMut<AssetBrowser>,
+    mut registry: ResMut<TerrainTextureRegistry>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+    images: Res<Assets<Image>>,
+    asset_server: Res<AssetServer>,
+    mut notify: EventWriter<Notify>,
+    localization: Res<Localization>,
+) {
+    let loc = &*localization;
+    let ctx = egui.ctx_mut().clone();
+
+    // Only egui ids are needed while drawing, so look them up first.
+    let browser_textures: Vec<egui::TextureId> = browser
+        .images
+        .iter()
+        .flatten()
+        .map(|image| egui.add_image(image.handle.clone_weak()))
+        .collect();
+    let mut slots: Vec<(TextureSlot, String, Option<egui::TextureId>)> = registry
+        .layers()
+        .map(|tile_type| {
+            let entry = registry.get(tile_type);
+            (
+                TextureSlot::Terrain(tile_type),
+                entry.map_or_else(|| tile_type.identifier().to_string(), |e| e.name.clone()),
+                entry.map(|entry| egui.add_image(entry.preview.clone_weak())),
+            )
+        })
+        .collect();
+    if let Some(next) = registry.next_layer() {
+        slots.push((
+            TextureSlot::Terrain(next),
+            loc.tr("assets.new_layer").to_string(),
+            None,
+        ));
+    }
+    let wall = registry.wall_texture();
+    slots.push((
+        TextureSlot::Wall,
+        wall.map_or_else(|| loc.tr("assets.wall").to_string(), |w| w.name.clone()),
+        wall.map(|wall| egui.add_image(wall.base_color.clone_weak())),
+    ));
+
+    let mut rescan = false;
+    let mut dropped: Option<(TextureSlot, usize)> = None;
+    egui::Window::new(loc.tr("assets.title"))
+        .id(egui::Id::new("asset_browser"))
+        .default_open(false)
+        .default_width(360.0)
+        .show(&ctx, |ui| {
+            if browser.images.is_none() {
+                rescan = true;
+            }
+            ui.label(loc.tr("assets.slots.hint"));
+            ui.horizontal_wrapped(|ui| {
+                for (slot, name, texture) in &slots {
+                    let (_, payload) = ui.dnd_drop_zone::<DraggedTexture, _>(
+                        egui::Frame::group(ui.style()),
+                        |ui| {
+                            ui.vertical(|ui| {
+                                ui.set_width(THUMBNAIL_SIZE);
+                                match texture {
+                                    Some(id) => {
+                                        ui.image(egui::load::SizedTexture::new(
+                                            *id,
+                                            egui::Vec2::splat(THUMBNAIL_SIZE),
+                                        ));
+                                    }
+                                    None => {
+                                        ui.allocate_space(egui::Vec2::splat(THUMBNAIL_SIZE));
+                                    }
+                                }
+                                ui.small(name);
+                            });
+                        },
+                    );
+                    if let Some(payload) = payload {
+                        dropped = Some((*slot, payload.0));
+                    }
+                }
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label(loc.format("assets.folder", &[("path", &TEXTURE_DIR)]));
+                if ui.small_button(loc.tr("browser.refresh")).clicked() {
+                    rescan = true;
+                }
+            });
+            let Some(found) = browser.images.as_ref() else {
+                return;
+            };
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    ui.horizontal_wrapped(|ui| {
+                        for (index, (image, texture)) in
+                            found.iter().zip(&browser_textures).enumerate()
+                        {
+                            let id = egui::Id::new(("asset_image", &image.path));
+                            ui.dnd_drag_source(id, DraggedTexture(index), |ui| {
+                                ui.image(egui::load::SizedTexture::new(
+                                    *texture,
+                                    egui::Vec2::splat(THUMBNAIL_SIZE),
+                                ))
+                            })
+                            .response
+                            .on_hover_text(&image.path);
+                        }
+                    });
+                });
+        });
+
+    if rescan {
+        let root = Path::new(ASSET_ROOT).join(TEXTURE_DIR);
+        browser.images = Some(
+            find_images(&root)
+                .into_iter()
+                .map(|path| AssetImage {
+                    name: file_stem(&path),
+                    handle: asset_server.load(path.clone()),
+                    path,
+                })
+                .collect(),
+        );
+    }
+
+    let Some((slot, index)) = dropped else {
+        return;
+    };
+    let Some(image) = browser.images.as_ref().and_then(|found| found.get(index)) else {
+        return;
+    };
+    // Terrain layers are stacked into texture arrays, which need every layer
+    // to share one size and format.
+    let reference = registry
+        .iter()
+        .find_map(|entry| images.get(&entry.preview))
+        .map(|existing| {
+            (
+                existing.texture_descriptor.size,
+                existing.texture_descriptor.format,
+            )
+        });
+    let Some(candidate) = images.get(&image.handle) else {
+        notify.send(Notify::warning(
+            loc.format("notify.asset_loading", &[("name", &image.name)]),
+        ));
+        return;
+    };
+    let mismatch = reference.filter(|&(size, format)| {
+        candidate.texture_descriptor.size != size || candidate.texture_descriptor.format != format
+    });
+    if let Some((size, _)) = mismatch {
+        notify.send(Notify::error(loc.format(
+            "notify.asset_mismatch",
+            &[
+                ("name", &image.name),
+                ("width", &size.width),
+                ("height", &size.height),
+            ],
+        )));
+        return;
+    }
+
+    match slot {
+        TextureSlot::Terrain(tile_type) => {
+            registry.load_and_register(
+                tile_type,
+                image.name.clone(),
+                &asset_server,
+                &mut materials,
+                &image.path,
+                None,
+                None,
+                None,
+            );
+        }
+        TextureSlot::Wall => {
+            registry.load_and_register_wall(
+                WALL_ID,
+                image.name.clone(),
+                &asset_server,
+                &image.path,
+                None,
+                None,
+            );
+        }
+    }
+    notify.send(Notify::info(
+        loc.format("notify.asset_assigned", &[("name", &image.name)]),
+    ));
+}
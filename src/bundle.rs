@@ -0,0 +1,403 @@
+//! Map bundles: a folder holding a map together with copies of the textures
+//! it was edited with, so it opens with the same textures on any machine.
+//!
+//! A bundle contains `map.tme`, the splatmap as `splatmap.png` (plus
+//! `splatmap_<page>.png` for further pages), the textures under `textures/`
+//! and a [`TextureManifest`] in `textures.json` naming them by paths
+//! relative to the bundle.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, ensure};
+use bevy::prelude::*;
+use bevy::tasks::{IoTaskPool, Task, block_on};
+use rfd::AsyncFileDialog;
+use serde::{Deserialize, Serialize};
+
+use crate::editor::{EditorState, MapEdit};
+use crate::export;
+use crate::io;
+use crate::localization::Localization;
+use crate::notifications::Notify;
+use crate::texture::material::TerrainMaterial;
+use crate::texture::registry::TerrainTextureRegistry;
+use crate::types::{TileMap, TileType};
+
+pub const BUNDLE_EXTENSION: &str = "tmebundle";
+const MAP_FILE: &str = "map.tme";
+const MANIFEST_FILE: &str = "textures.json";
+const TEXTURE_DIR: &str = "textures";
+
+/// Saves and opens bundles, requested from the File menu with a
+/// `BundleRequest` event.
+pub struct BundlePlugin;
+
+impl Plugin for BundlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BundleRequest>()
+            .init_resource::<Bundles>()
+            .add_systems(
+                Update,
+                (open_bundle_dialog, poll_bundle_dialog, poll_bundle_save).chain(),
+            );
+    }
+}
+
+#[derive(Event, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BundleRequest {
+    Save,
+    Open,
+}
+
+/// Every texture registered when the bundle was saved.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TextureManifest {
+    pub textures: Vec<ManifestTexture>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wall: Option<ManifestWall>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ManifestTexture {
+    pub tile_type: TileType,
+    pub name: String,
+    pub diffuse: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normal: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roughness: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dispersion: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ManifestWall {
+    pub id: String,
+    pub name: String,
+    pub diffuse: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normal: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roughness: Option<String>,
+}
+
+impl TextureManifest {
+    /// The registered textures, with their asset paths as the registry
+    /// holds them.
+    pub fn from_registry(registry: &TerrainTextureRegistry) -> Self {
+        Self {
+            textures: registry
+                .iter()
+                .map(|entry| ManifestTexture {
+                    tile_type: entry.tile_type,
+                    name: entry.name.clone(),
+                    diffuse: entry.diffuse_path.clone(),
+                    normal: entry.normal_path.clone(),
+                    roughness: entry.roughness_path.clone(),
+                    dispersion: entry.dispersion_path.clone(),
+                })
+                .collect(),
+            wall: registry.wall_texture().map(|wall| ManifestWall {
+                id: wall.id.clone(),
+                name: wall.name.clone(),
+                diffuse: wall.diffuse_path.clone(),
+                normal: wall.normal_path.clone(),
+                roughness: wall.roughness_path.clone(),
+            }),
+        }
+    }
+
+    /// The manifest of a bundle at `dir` with its paths made absolute, so
+    /// the asset server loads them from outside the asset folder.
+    pub fn resolve(&self, dir: &Path) -> Result<Self> {
+        let path = |relative: &str| bundle_path(dir, relative);
+        let optional = |relative: &Option<String>| relative.as_deref().map(path).transpose();
+        Ok(Self {
+            textures: self
+                .textures
+                .iter()
+                .map(|texture| {
+                    Ok(ManifestTexture {
+                        tile_type: texture.tile_type,
+                        name: texture.name.clone(),
+                        diffuse: path(&texture.diffuse)?,
+                        normal: optional(&texture.normal)?,
+                        roughness: optional(&texture.roughness)?,
+                        dispersion: optional(&texture.dispersion)?,
+                    })
+                })
+                .collect::<Result<_>>()?,
+            wall: self
+                .wall
+                .as_ref()
+                .map(|wall| {
+                    Ok::<_, anyhow::Error>(ManifestWall {
+                        id: wall.id.clone(),
+                        name: wall.name.clone(),
+                        diffuse: path(&wall.diffuse)?,
+                        normal: optional(&wall.normal)?,
+                        roughness: optional(&wall.roughness)?,
+                    })
+                })
+                .transpose()?,
+        })
+    }
+
+    /// Registers the textures, replacing those of the same layers.
+    pub fn register(
+        &self,
+        registry: &mut TerrainTextureRegistry,
+        asset_server: &AssetServer,
+        materials: &mut Assets<TerrainMaterial>,
+    ) {
+        for texture in &self.textures {
+            registry.load_and_register(
+                texture.tile_type,
+                texture.name.clone(),
+                asset_server,
+                materials,
+                &texture.diffuse,
+                texture.normal.as_deref(),
+                texture.roughness.as_deref(),
+                texture.dispersion.as_deref(),
+            );
+        }
+        if let Some(wall) = &self.wall {
+            registry.load_and_register_wall(
+                wall.id.clone(),
+                wall.name.clone(),
+                asset_server,
+                &wall.diffuse,
+                wall.normal.as_deref(),
+                wall.roughness.as_deref(),
+            );
+        }
+    }
+}
+
+/// The absolute path of a file named in a manifest, which must stay inside
+/// the bundle.
+fn bundle_path(dir: &Path, relative: &str) -> Result<String> {
+    let relative_path = Path::new(relative);
+    ensure!(
+        relative_path
+            .components()
+            .all(|component| matches!(component, std::path::Component::Normal(_))),
+        "bundle texture path {relative} leaves the bundle"
+    );
+    let path = std::path::absolute(dir.join(relative_path))?;
+    ensure!(
+        path.is_file(),
+        "bundle texture {} is missing",
+        path.display()
+    );
+    path.to_str()
+        .map(str::to_string)
+        .context("bundle path is not valid UTF-8")
+}
+
+/// Writes `map` and the textures of `manifest`, whose paths are asset
+/// paths, as a bundle at `dir`. Files already in the folder are replaced.
+pub fn save_bundle(dir: &Path, map: &TileMap, manifest: &TextureManifest) -> Result<()> {
+    std::fs::create_dir_all(dir.join(TEXTURE_DIR))
+        .with_context(|| format!("failed to create bundle {}", dir.display()))?;
+    io::save_map(dir.join(MAP_FILE), map)?;
+    export::export_splatmap(&dir.join("splatmap.png"), map)?;
+
+    let copy = |id: &str, kind: &str, source: &str| -> Result<String> {
+        let source = export::resolve_asset_path(source)?;
+        let extension = source
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("png")
+            .to_ascii_lowercase();
+        let relative = format!("{TEXTURE_DIR}/{id}_{kind}.{extension}");
+        let target = dir.join(&relative);
+        // Saving an opened bundle over itself must not truncate its textures.
+        let same_file = std::fs::canonicalize(&target)
+            .is_ok_and(|target| std::fs::canonicalize(&source).is_ok_and(|src| src == target));
+        if !same_file {
+            std::fs::copy(&source, &target)
+                .with_context(|| format!("failed to copy texture {}", source.display()))?;
+        }
+        Ok(relative)
+    };
+    let copy_optional = |id: &str, kind: &str, source: &Option<String>| {
+        source
+            .as_deref()
+            .map(|source| copy(id, kind, source))
+            .transpose()
+    };
+
+    let mut bundled = TextureManifest::default();
+    for texture in &manifest.textures {
+        let id = texture.tile_type.identifier();
+        bundled.textures.push(ManifestTexture {
+            tile_type: texture.tile_type,
+            name: texture.name.clone(),
+            diffuse: copy(id, "diffuse", &texture.diffuse)?,
+            normal: copy_optional(id, "normal", &texture.normal)?,
+            roughness: copy_optional(id, "roughness", &texture.roughness)?,
+            dispersion: copy_optional(id, "dispersion", &texture.dispersion)?,
+        });
+    }
+    if let Some(wall) = &manifest.wall {
+        bundled.wall = Some(ManifestWall {
+            id: wall.id.clone(),
+            name: wall.name.clone(),
+            diffuse: copy(&wall.id, "diffuse", &wall.diffuse)?,
+            normal: copy_optional(&wall.id, "normal", &wall.normal)?,
+            roughness: copy_optional(&wall.id, "roughness", &wall.roughness)?,
+        });
+    }
+    std::fs::write(
+        dir.join(MANIFEST_FILE),
+        serde_json::to_vec_pretty(&bundled)?,
+    )?;
+    Ok(())
+}
+
+/// Reads the map and texture manifest of the bundle at `dir`, the manifest
+/// resolved against it.
+pub fn load_bundle(dir: &Path) -> Result<(TileMap, TextureManifest)> {
+    let map = io::load_map(dir.join(MAP_FILE))?;
+    let manifest_path = dir.join(MANIFEST_FILE);
+    let manifest = std::fs::read(&manifest_path)
+        .with_context(|| format!("failed to read {}", manifest_path.display()))?;
+    let manifest: TextureManifest =
+        serde_json::from_slice(&manifest).context("invalid bundle texture manifest")?;
+    Ok((map, manifest.resolve(dir)?))
+}
+
+#[derive(Resource, Default)]
+struct Bundles {
+    dialog_task: Option<Task<Option<(BundleRequest, PathBuf)>>>,
+    save_task: Option<Task<Result<PathBuf>>>,
+}
+
+fn open_bundle_dialog(
+    mut requests: EventReader<BundleRequest>,
+    mut bundles: ResMut<Bundles>,
+    state: Res<EditorState>,
+    localization: Res<Localization>,
+) {
+    let Some(&request) = requests.read().last() else {
+        return;
+    };
+    if bundles.dialog_task.is_some() || bundles.save_task.is_some() {
+        return;
+    }
+    let mut dialog = AsyncFileDialog::new();
+    if let Some(parent) = state.current_file_path.as_ref().and_then(|p| p.parent()) {
+        dialog = dialog.set_directory(parent);
+    }
+    let task = match request {
+        BundleRequest::Save => {
+            let stem = state
+                .current_file_path
+                .as_ref()
+                .and_then(|path| path.file_stem())
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("map");
+            let dialog = dialog
+                .set_title(localization.tr("dialog.save_bundle"))
+                .set_file_name(format!("{stem}.{BUNDLE_EXTENSION}"));
+            IoTaskPool::get().spawn(async move {
+                let file = dialog.save_file().await?;
+                Some((request, file.path().to_path_buf()))
+            })
+        }
+        BundleRequest::Open => {
+            let dialog = dialog.set_title(localization.tr("dialog.open_bundle"));
+            IoTaskPool::get().spawn(async move {
+                let folder = dialog.pick_folder().await?;
+                Some((request, folder.path().to_path_buf()))
+            })
+        }
+    };
+    bundles.dialog_task = Some(task);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn poll_bundle_dialog(
+    mut bundles: ResMut<Bundles>,
+    mut state: ResMut<EditorState>,
+    mut registry: ResMut<TerrainTextureRegistry>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+    mut edits: EventWriter<MapEdit>,
+    mut notify: EventWriter<Notify>,
+    localization: Res<Localization>,
+) {
+    let Some(task) = bundles.dialog_task.as_mut() else {
+        return;
+    };
+    if !task.is_finished() {
+        return;
+    }
+    let Some((request, path)) = block_on(bundles.dialog_task.take().unwrap()) else {
+        return;
+    };
+    match request {
+        BundleRequest::Save => {
+            let path = if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(BUNDLE_EXTENSION))
+            {
+                path
+            } else {
+                path.with_extension(BUNDLE_EXTENSION)
+            };
+            let map = state.map.clone();
+            let manifest = TextureManifest::from_registry(&registry);
+            bundles.save_task = Some(IoTaskPool::get().spawn(async move {
+                save_bundle(&path, &map, &manifest)?;
+                Ok(path)
+            }));
+        }
+        BundleRequest::Open => {
+            match load_bundle(&path) {
+                Ok((map, manifest)) => {
+                    manifest.register(&mut registry, &asset_server, &mut materials);
+                    edits.send(MapEdit::Replace(Box::new(map)));
+                    // Plain saves go to the map inside the bundle.
+                    state.current_file_path = Some(path.join(MAP_FILE));
+                    state.delta_base = None;
+                    notify.send(Notify::info(
+                        localization.format("notify.bundle_opened", &[("path", &path.display())]),
+                    ));
+                }
+                Err(err) => {
+                    notify.send(Notify::error(
+                        localization.format("notify.load_failed", &[("error", &err)]),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn poll_bundle_save(
+    mut bundles: ResMut<Bundles>,
+    mut state: ResMut<EditorState>,
+    mut notify: EventWriter<Notify>,
+    localization: Res<Localization>,
+) {
+    let Some(task) = bundles.save_task.as_mut() else {
+        return;
+    };
+    if !task.is_finished() {
+        return;
+    }
+    match block_on(bundles.save_task.take().unwrap()) {
+        Ok(path) => {
+            state.unsaved_changes = false;
+            notify.send(Notify::info(
+                localization.format("notify.bundle_saved", &[("path", &path.display())]),
+            ))
+        }
+        Err(err) => notify.send(Notify::error(
+            localization.format("notify.save_failed", &[("error", &err)]),
+        )),
+    };
+}
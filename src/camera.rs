@@ -1,12 +1,32 @@
 use bevy::prelude::*;
+use bevy::render::camera::{ClearColorConfig, Viewport};
+use bevy::window::PrimaryWindow;
+
+use crate::runtime::RuntimeMap;
 
 pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_camera);
+        app.init_resource::<SplitView>()
+            .add_systems(Startup, spawn_camera)
+            .add_systems(Update, (layout_viewports, frame_top_down_camera));
     }
 }
 
+/// The isometric camera the editor is navigated with.
+#[derive(Component)]
+pub struct MainCamera;
+
+/// Locked top-down camera shown beside the main view in split view.
+#[derive(Component)]
+pub struct TopDownCamera;
+
+/// Whether the window is split between the main and top-down views.
+#[derive(Resource, Default)]
+pub struct SplitView {
+    pub enabled: bool,
+}
+
 // spawn a camera looking down on the XZ plane
 fn spawn_camera(mut commands: Commands) {
     // Spawn orthographic isometric camera
@@ -15,14 +35,118 @@ fn spawn_camera(mut commands: Commands) {
         * Quat::from_rotation_x(-35.264_f32.to_radians());
     const MIN_SCALE: f32 = 0.02;
 
-    commands.spawn((Camera3dBundle {
-        transform,
-        projection: Projection::Orthographic(OrthographicProjection {
-            scale: MIN_SCALE,
-            near: -500.0,
-            far: 500.0,
+    commands.spawn((
+        Camera3dBundle {
+            transform,
+            projection: Projection::Orthographic(OrthographicProjection {
+                scale: MIN_SCALE,
+                near: -500.0,
+                far: 500.0,
+                ..default()
+            }),
             ..default()
-        }),
-        ..default()
-    },));
+        },
+        MainCamera,
+    ));
+
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                order: 1,
+                is_active: false,
+                // The main camera already cleared the whole window.
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 100.0, 0.0).looking_to(Vec3::NEG_Y, Vec3::NEG_Z),
+            projection: Projection::Orthographic(OrthographicProjection {
+                near: -500.0,
+                far: 500.0,
+                ..default()
+            }),
+            ..default()
+        },
+        TopDownCamera,
+    ));
+}
+
+/// Gives each camera its half of the window in split view, or the whole
+/// window to the main camera otherwise.
+fn layout_viewports(
+    split: Res<SplitView>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut main: Query<&mut Camera, (With<MainCamera>, Without<TopDownCamera>)>,
+    mut top_down: Query<&mut Camera, (With<TopDownCamera>, Without<MainCamera>)>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let (Ok(mut main), Ok(mut top_down)) = (main.get_single_mut(), top_down.get_single_mut())
+    else {
+        return;
+    };
+    let size = window.physical_size();
+    let half = UVec2::new(size.x / 2, size.y);
+
+    let (main_viewport, top_down_viewport) = if split.enabled && half.x > 0 && half.y > 0 {
+        (
+            Some(Viewport {
+                physical_position: UVec2::ZERO,
+                physical_size: half,
+                ..default()
+            }),
+            Some(Viewport {
+                physical_position: UVec2::new(half.x, 0),
+                physical_size: UVec2::new(size.x - half.x, size.y),
+                ..default()
+            }),
+        )
+    } else {
+        (None, None)
+    };
+
+    if viewport_bounds(&main.viewport) != viewport_bounds(&main_viewport) {
+        main.viewport = main_viewport;
+    }
+    if viewport_bounds(&top_down.viewport) != viewport_bounds(&top_down_viewport) {
+        top_down.is_active = top_down_viewport.is_some();
+        top_down.viewport = top_down_viewport;
+    }
+}
+
+// `Viewport` is not comparable, so compare its pixel rectangle instead.
+fn viewport_bounds(viewport: &Option<Viewport>) -> Option<(UVec2, UVec2)> {
+    viewport
+        .as_ref()
+        .map(|viewport| (viewport.physical_position, viewport.physical_size))
+}
+
+/// Centres the top-down camera over the map and zooms it to fit.
+fn frame_top_down_camera(
+    runtime: Res<RuntimeMap>,
+    mut cameras: Query<(&Camera, &mut Transform, &mut Projection), With<TopDownCamera>>,
+) {
+    let Ok((camera, mut transform, mut projection)) = cameras.get_single_mut() else {
+        return;
+    };
+    let Some(viewport) = camera.logical_viewport_size() else {
+        return;
+    };
+    let Projection::Orthographic(current) = projection.as_ref() else {
+        return;
+    };
+    let extent =
+        Vec2::new(runtime.map.width as f32, runtime.map.height as f32) * runtime.map.tile_size;
+    let centre = Vec3::new(extent.x * 0.5, transform.translation.y, extent.y * 0.5);
+    // A little margin so the map edges stay visible.
+    let scale = (extent / viewport.max(Vec2::ONE)).max_element().max(0.001) * 1.1;
+
+    if transform.translation != centre {
+        transform.translation = centre;
+    }
+    if current.scale != scale {
+        let mut ortho = current.clone();
+        ortho.scale = scale;
+        *projection = Projection::Orthographic(ortho);
+    }
 }
@@ -0,0 +1,711 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+use bincode::{Decode, Encode, config, decode_from_slice, encode_to_vec};
+
+use crate::editor::{EditorState, LocalEdits, MapEdit, apply_map_edits};
+use crate::localization::Localization;
+use crate::notifications::Notify;
+use crate::players::player_color;
+use crate::terrain::{CornerHeightCache, TerrainMeshSet};
+use crate::tools::outline_tile;
+use crate::types::{
+    MapDecal, MapLight, MapProp, RoadSurface, StartLocation, Tile, TileMap, TriggerArea,
+    Walkability, Weather,
+};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+/// Frames larger than this are treated as a broken connection.
+const MAX_FRAME: usize = 64 * 1024 * 1024;
+/// Peer id of the hosting instance; joining peers count up from 1.
+const HOST_PEER: u32 = 0;
+/// How long joining waits for the host before giving up, since it blocks
+/// the frame.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Lets several editor instances work on one map: one hosts, the others
+/// join over TCP. Each instance sends the tiles and layers it changed, the
+/// host applies them in arrival order and relays them to every peer, so the
+/// last write to reach the host wins. Undo only reverts tiles nobody else
+/// has written since. Remote cursors are outlined in each peer's player
+/// color.
+pub struct CollabPlugin;
+
+impl Plugin for CollabPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Collab>()
+            .add_systems(
+                Update,
+                sync_session
+                    .in_set(TerrainMeshSet::Apply)
+                    .after(apply_map_edits),
+            )
+            .add_systems(
+                Update,
+                (collab_window, draw_remote_cursors).after(TerrainMeshSet::Prepare),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct Collab {
+    pub address: String,
+    session: Option<Session>,
+}
+
+impl Default for Collab {
+    fn default() -> Self {
+        Self {
+            address: DEFAULT_ADDRESS.to_string(),
+            session: None,
+        }
+    }
+}
+
+struct Session {
+    listener: Option<TcpListener>,
+    /// Joined peers for a host, or the host for a joining peer.
+    connections: Vec<Connection>,
+    /// This instance's peer id; `None` until the host has welcomed us.
+    peer: Option<u32>,
+    next_peer: u32,
+    /// Whether this instance holds the shared map; a joining peer keeps
+    /// its edits to itself until the host's map has arrived.
+    synced: bool,
+    sent_hover: Option<(u32, u32)>,
+    cursors: Vec<(u32, (u32, u32))>,
+}
+
+impl Session {
+    fn is_host(&self) -> bool {
+        self.listener.is_some()
+    }
+}
+
+/// Everything stored for one tile position.
+#[derive(Encode, Decode, Clone)]
+struct TileState {
+    x: u32,
+    y: u32,
+    tile: Tile,
+    /// The tile on each upper layer, lowest first.
+    layers: Vec<Tile>,
+    vegetation: u8,
+    road: u8,
+    walkability: u8,
+}
+
+impl TileState {
+    fn of(map: &TileMap, x: u32, y: u32) -> Self {
+        let idx = map.idx(x, y);
+        Self {
+            x,
+            y,
+            tile: map.tiles[idx].clone(),
+            layers: map
+                .upper_layers
+                .iter()
+                .map(|layer| layer[idx].clone())
+                .collect(),
+            vegetation: map.vegetation(x, y),
+            road: map.road(x, y).map_or(0, RoadSurface::code),
+            walkability: map.walkability(x, y).map_or(0, Walkability::code),
+        }
+    }
+}
+
+/// The tiles and layers one peer changed. Applied through
+/// `MapEdit::Remote`, so local history can let go of those tiles.
+#[derive(Encode, Decode, Clone)]
+pub struct Changes {
+    tiles: Vec<TileState>,
+    triggers: Option<Vec<TriggerArea>>,
+    starts: Option<Vec<StartLocation>>,
+    lights: Option<Vec<MapLight>>,
+    weather: Option<Weather>,
+    decals: Option<Vec<MapDecal>>,
+    props: Option<Vec<MapProp>>,
+    tile_size: Option<f32>,
+    water_level: Option<Option<f32>>,
+    /// Sent when layers were added or removed; applied before `tiles`.
+    upper_layers: Option<Vec<Vec<Tile>>>,
+}
+
+impl Changes {
+    /// What this frame's local edits changed.
+    fn local(map: &TileMap, edits: &LocalEdits) -> Self {
+        let mut tiles: Vec<(u32, u32)> = edits
+            .tiles
+            .tiles()
+            .filter(|&(x, y)| x < map.width && y < map.height)
+            .collect();
+        tiles.sort_unstable();
+        tiles.dedup();
+        Self {
+            tiles: tiles
+                .into_iter()
+                .map(|(x, y)| TileState::of(map, x, y))
+                .collect(),
+            triggers: edits.triggers.then(|| map.triggers.clone()),
+            starts: edits.starts.then(|| map.starts.clone()),
+            lights: edits.lights.then(|| map.lights.clone()),
+            weather: edits.weather.then(|| map.weather.clone()),
+            decals: edits.decals.then(|| map.decals.clone()),
+            props: edits.props.then(|| map.props.clone()),
+            tile_size: edits.tile_size.then_some(map.tile_size),
+            water_level: edits.water_level.then_some(map.water_level),
+            upper_layers: edits.upper_layers.then(|| map.upper_layers.clone()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+            && self.triggers.is_none()
+            && self.starts.is_none()
+            && self.lights.is_none()
+            && self.weather.is_none()
+            && self.decals.is_none()
+            && self.props.is_none()
+            && self.tile_size.is_none()
+            && self.water_level.is_none()
+            && self.upper_layers.is_none()
+    }
+
+    /// Refuses changes that do not fit a `width` by `height` map with
+    /// `layers` upper layers, as a broken or hostile peer could send.
+    fn check(&self, width: u32, height: u32, layers: usize) -> anyhow::Result<()> {
+        let cells = width as usize * height as usize;
+        if let Some(upper_layers) = &self.upper_layers {
+            anyhow::ensure!(
+                upper_layers.iter().all(|layer| layer.len() == cells),
+                "upper layers do not fit a {width}x{height} map"
+            );
+        }
+        let layers = self.upper_layers.as_ref().map_or(layers, Vec::len);
+        for state in &self.tiles {
+            anyhow::ensure!(
+                state.x < width && state.y < height,
+                "tile {},{} is outside a {width}x{height} map",
+                state.x,
+                state.y
+            );
+            anyhow::ensure!(
+                state.layers.len() == layers,
+                "tile {},{} has {} upper layers, not {layers}",
+                state.x,
+                state.y,
+                state.layers.len()
+            );
+        }
+        Ok(())
+    }
+
+    /// Every tile position the changes overwrite.
+    pub fn tiles(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.tiles.iter().map(|state| (state.x, state.y))
+    }
+
+    /// Whether upper layers were added or removed.
+    pub fn replaces_layers(&self) -> bool {
+        self.upper_layers.is_some()
+    }
+
+    /// Whether the tile size changed, rescaling the whole terrain.
+    pub fn rescales(&self) -> bool {
+        self.tile_size.is_some()
+    }
+
+    pub fn moves_terrain(&self) -> bool {
+        !self.tiles.is_empty()
+            || self.tile_size.is_some()
+            || self.water_level.is_some()
+            || self.upper_layers.is_some()
+    }
+
+    pub fn moves_triggers(&self) -> bool {
+        self.triggers.is_some()
+    }
+
+    /// Overwrites the map with the changes, whatever it held before.
+    pub fn apply(&self, map: &mut TileMap) {
+        if let Some(upper_layers) = &self.upper_layers {
+            map.upper_layers.clone_from(upper_layers);
+        }
+        for state in &self.tiles {
+            let (x, y) = (state.x, state.y);
+            if x >= map.width || y >= map.height {
+                continue;
+            }
+            let idx = map.idx(x, y);
+            map.tiles[idx].clone_from(&state.tile);
+            for (layer, tile) in map.upper_layers.iter_mut().zip(&state.layers) {
+                layer[idx].clone_from(tile);
+            }
+            map.set_vegetation(x, y, state.vegetation);
+            map.set_road(x, y, RoadSurface::from_code(state.road));
+            map.set_walkability(x, y, Walkability::from_code(state.walkability));
+        }
+        if let Some(triggers) = &self.triggers {
+            map.triggers.clone_from(triggers);
+        }
+        if let Some(starts) = &self.starts {
+            map.starts.clone_from(starts);
+        }
+        if let Some(lights) = &self.lights {
+            map.lights.clone_from(lights);
+        }
+        if let Some(weather) = &self.weather {
+            map.weather.clone_from(weather);
+        }
+        if let Some(decals) = &self.decals {
+            map.decals.clone_from(decals);
+        }
+        if let Some(props) = &self.props {
+            map.props.clone_from(props);
+        }
+        if let Some(tile_size) = self.tile_size {
+            map.tile_size = tile_size;
+        }
+        if let Some(water_level) = self.water_level {
+            map.water_level = water_level;
+        }
+    }
+}
+
+#[derive(Encode, Decode, Clone)]
+enum Message {
+    /// Sent by the host to a peer that just joined.
+    Welcome {
+        peer: u32,
+    },
+    /// The whole map, on joining or after a peer loads or resizes one.
+    Map {
+        /// Peer the map came from.
+        peer: u32,
+        map: TileMap,
+    },
+    Changes {
+        /// Peer that made the changes.
+        peer: u32,
+        changes: Changes,
+    },
+    Cursor {
+        peer: u32,
+        tile: Option<(u32, u32)>,
+    },
+    Left {
+        peer: u32,
+    },
+}
+
+/// A non-blocking stream of length-prefixed bincode messages.
+struct Connection {
+    stream: TcpStream,
+    peer: u32,
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream, peer: u32) -> std::io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            peer,
+            incoming: Vec::new(),
+            outgoing: Vec::new(),
+        })
+    }
+
+    fn queue(&mut self, message: &Message) -> anyhow::Result<()> {
+        let body = encode_to_vec(message, config::standard())?;
+        self.outgoing
+            .extend_from_slice(&(body.len() as u32).to_le_bytes());
+        self.outgoing.extend(body);
+        Ok(())
+    }
+
+    /// Writes as much queued data as the socket accepts without blocking.
+    fn flush(&mut self) -> std::io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Every complete message that has arrived. Errors mean the connection
+    /// is closed or unusable.
+    fn receive(&mut self) -> anyhow::Result<Vec<Message>> {
+        let mut buffer = [0; 16 * 1024];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => anyhow::bail!("connection closed"),
+                Ok(read) => self.incoming.extend_from_slice(&buffer[..read]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let mut messages = Vec::new();
+        while let Some(header) = self.incoming.first_chunk::<4>() {
+            let len = u32::from_le_bytes(*header) as usize;
+            anyhow::ensure!(len <= MAX_FRAME, "message of {len} bytes is too large");
+            if self.incoming.len() < 4 + len {
+                break;
+            }
+            let (message, _len): (Message, usize) = decode_from_slice(
+                &self.incoming[4..4 + len],
+                config::standard().with_limit::<MAX_FRAME>(),
+            )?;
+            self.incoming.drain(..4 + len);
+            messages.push(message);
+        }
+        Ok(messages)
+    }
+}
+
+impl Collab {
+    pub fn is_active(&self) -> bool {
+        self.session.is_some()
+    }
+
+    /// Starts hosting the edited map on `address`.
+    pub fn host(&mut self) -> anyhow::Result<()> {
+        let listener = TcpListener::bind(&self.address)?;
+        listener.set_nonblocking(true)?;
+        self.session = Some(Session {
+            listener: Some(listener),
+            connections: Vec::new(),
+            peer: Some(HOST_PEER),
+            next_peer: HOST_PEER + 1,
+            synced: true,
+            sent_hover: None,
+            cursors: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Connects to a host at `address`. The local map is replaced by the
+    /// host's once it arrives.
+    pub fn join(&mut self) -> anyhow::Result<()> {
+        let address = self
+            .address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("{} has no address", self.address))?;
+        let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+        self.session = Some(Session {
+            listener: None,
+            connections: vec![Connection::new(stream, HOST_PEER)?],
+            peer: None,
+            next_peer: 0,
+            synced: false,
+            sent_hover: None,
+            cursors: Vec::new(),
+        });
+        Ok(())
+    }
+
+    pub fn leave(&mut self) {
+        self.session = None;
+    }
+}
+
+/// Exchanges map changes and cursors with the other peers. Runs after local
+/// edits are applied, so this frame's are sent; remote changes are sent on
+/// as `MapEdit`s and merged in next frame.
+fn sync_session(
+    mut collab: ResMut<Collab>,
+    state: Res<EditorState>,
+    local: Res<LocalEdits>,
+    mut edits: EventWriter<MapEdit>,
+    mut notify: EventWriter<Notify>,
+    localization: Res<Localization>,
+) {
+    let Some(session) = collab.session.as_mut() else {
+        return;
+    };
+
+    // New peers get the current map and everyone's cursors.
+    if let Some(listener) = session.listener.as_ref() {
+        loop {
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    warn!("Failed to accept collaborator: {err}");
+                    break;
+                }
+            };
+            let peer = session.next_peer;
+            session.next_peer += 1;
+            let Ok(mut connection) = Connection::new(stream, peer) else {
+                continue;
+            };
+            let mut greeting = vec![
+                Message::Welcome { peer },
+                Message::Map {
+                    peer: HOST_PEER,
+                    map: state.map.clone(),
+                },
+            ];
+            greeting.extend(
+                session
+                    .cursors
+                    .iter()
+                    .map(|&(peer, tile)| Message::Cursor {
+                        peer,
+                        tile: Some(tile),
+                    })
+                    .chain(std::iter::once(Message::Cursor {
+                        peer: HOST_PEER,
+                        tile: state.hover,
+                    })),
+            );
+            if greeting
+                .iter()
+                .try_for_each(|message| connection.queue(message))
+                .is_ok()
+            {
+                notify.send(Notify::info(
+                    localization.format("notify.collab_joined", &[("peer", &peer)]),
+                ));
+                session.connections.push(connection);
+            }
+        }
+    }
+
+    let mut outgoing = Vec::new();
+    if let Some(peer) = session.peer {
+        if session.synced {
+            if local.tiles.is_all() {
+                outgoing.push(Message::Map {
+                    peer,
+                    map: state.map.clone(),
+                });
+            } else {
+                let changes = Changes::local(&state.map, &local);
+                if !changes.is_empty() {
+                    outgoing.push(Message::Changes { peer, changes });
+                }
+            }
+        }
+        if session.sent_hover != state.hover {
+            session.sent_hover = state.hover;
+            outgoing.push(Message::Cursor {
+                peer,
+                tile: state.hover,
+            });
+        }
+    }
+
+    // Received changes must fit the map as it will be when they apply,
+    // after any maps received before them.
+    let mut shape = (
+        state.map.width,
+        state.map.height,
+        state.map.upper_layers.len(),
+    );
+    let mut closed = Vec::new();
+    for index in 0..session.connections.len() {
+        let received = match session.connections[index].receive() {
+            Ok(received) => received,
+            Err(err) => {
+                closed.push((index, err));
+                continue;
+            }
+        };
+        let from = session.connections[index].peer;
+        for mut message in received {
+            // Only the host knows who really sent what it relays.
+            if let (
+                true,
+                Message::Map { peer, .. }
+                | Message::Changes { peer, .. }
+                | Message::Cursor { peer, .. }
+                | Message::Left { peer },
+            ) = (session.is_host(), &mut message)
+            {
+                *peer = from;
+            }
+            let fits = match &message {
+                Message::Map { map, .. } => map.check_shape().map(|()| {
+                    shape = (map.width, map.height, map.upper_layers.len());
+                }),
+                Message::Changes { changes, .. } => {
+                    let (width, height, layers) = shape;
+                    changes.check(width, height, layers).map(|()| {
+                        shape.2 = changes.upper_layers.as_ref().map_or(layers, Vec::len);
+                    })
+                }
+                _ => Ok(()),
+            };
+            if let Err(err) = fits {
+                closed.push((index, err.context("malformed map data")));
+                break;
+            }
+            match &message {
+                Message::Welcome { peer } => session.peer = Some(*peer),
+                Message::Map { peer, map } => {
+                    session.synced = true;
+                    edits.send(MapEdit::RemoteMap {
+                        map: Box::new(map.clone()),
+                        own: session.peer == Some(*peer),
+                    });
+                }
+                Message::Changes { peer, changes } => {
+                    edits.send(MapEdit::Remote {
+                        changes: Box::new(changes.clone()),
+                        own: session.peer == Some(*peer),
+                    });
+                }
+                Message::Cursor { peer, tile } => {
+                    session.cursors.retain(|(other, _)| other != peer);
+                    session.cursors.extend(tile.map(|tile| (*peer, tile)));
+                }
+                Message::Left { peer } => {
+                    session.cursors.retain(|(other, _)| other != peer);
+                }
+            }
+            // The host relays everything, back to the sender too, so all
+            // peers see changes in the host's order and end up agreeing.
+            if session.is_host() && !matches!(message, Message::Welcome { .. }) {
+                let relay_to_sender =
+                    matches!(message, Message::Changes { .. } | Message::Map { .. });
+                for connection in &mut session.connections {
+                    if connection.peer != from || relay_to_sender {
+                        connection.queue(&message).ok();
+                    }
+                }
+            }
+        }
+    }
+
+    for message in &outgoing {
+        for connection in &mut session.connections {
+            connection.queue(message).ok();
+        }
+    }
+    for (index, connection) in session.connections.iter_mut().enumerate() {
+        if let Err(err) = connection.flush() {
+            closed.push((index, err.into()));
+        }
+    }
+
+    let mut lost_host = false;
+    closed.sort_by_key(|(index, _)| std::cmp::Reverse(*index));
+    closed.dedup_by_key(|(index, _)| *index);
+    for (index, err) in closed {
+        let connection = session.connections.remove(index);
+        session.cursors.retain(|(peer, _)| *peer != connection.peer);
+        if session.is_host() {
+            let left = Message::Left {
+                peer: connection.peer,
+            };
+            for other in &mut session.connections {
+                other.queue(&left).ok();
+            }
+            notify.send(Notify::info(
+                localization.format("notify.collab_left", &[("peer", &connection.peer)]),
+            ));
+        } else {
+            notify.send(Notify::warning(
+                localization.format("notify.collab_disconnected", &[("error", &err)]),
+            ));
+            lost_host = true;
+            break;
+        }
+    }
+    if lost_host {
+        collab.session = None;
+    }
+}
+
+/// Outlines the tile under each remote peer's cursor.
+fn draw_remote_cursors(mut gizmos: Gizmos, collab: Res<Collab>, heights: Res<CornerHeightCache>) {
+    let Some(session) = collab.session.as_ref() else {
+        return;
+    };
+    for &(peer, (x, y)) in &session.cursors {
+        outline_tile(&mut gizmos, &heights, x, y, player_color(peer as u8));
+    }
+}
+
+fn collab_window(
+    mut egui: EguiContexts,
+    mut collab: ResMut<Collab>,
+    localization: Res<Localization>,
+    mut notify: EventWriter<Notify>,
+) {
+    let loc = &*localization;
+    egui::Window::new(loc.tr("collab.title"))
+        .id(egui::Id::new("collaboration"))
+        .default_open(false)
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(12.0, -12.0))
+        .resizable(false)
+        .show(egui.ctx_mut(), |ui| {
+            let Some(session) = collab.session.as_ref() else {
+                ui.horizontal(|ui| {
+                    ui.label(loc.tr("collab.address"));
+                    ui.text_edit_singleline(&mut collab.address);
+                });
+                ui.horizontal(|ui| {
+                    if ui.button(loc.tr("collab.host")).clicked() {
+                        let hosted = collab.host();
+                        if let Err(err) = hosted {
+                            notify.send(Notify::error(
+                                loc.format("notify.collab_host_failed", &[("error", &err)]),
+                            ));
+                        }
+                    }
+                    if ui
+                        .button(loc.tr("collab.join"))
+                        .on_hover_text(loc.tr("collab.join.hint"))
+                        .clicked()
+                    {
+                        let joined = collab.join();
+                        if let Err(err) = joined {
+                            notify.send(Notify::error(
+                                loc.format("notify.collab_join_failed", &[("error", &err)]),
+                            ));
+                        }
+                    }
+                });
+                return;
+            };
+
+            let status = match (session.is_host(), session.peer) {
+                (true, _) => loc.format(
+                    "collab.hosting",
+                    &[
+                        ("address", &collab.address),
+                        ("count", &session.connections.len()),
+                    ],
+                ),
+                (false, Some(peer)) => loc.format("collab.connected", &[("peer", &peer)]),
+                (false, None) => loc.tr("collab.connecting").to_string(),
+            };
+            ui.label(status);
+            for &(peer, (x, y)) in &session.cursors {
+                let [r, g, b, _] = player_color(peer as u8).to_srgba().to_u8_array();
+                ui.colored_label(
+                    egui::Color32::from_rgb(r, g, b),
+                    loc.format("collab.cursor", &[("peer", &peer), ("x", &x), ("y", &y)]),
+                );
+            }
+            if ui.button(loc.tr("collab.leave")).clicked() {
+                collab.leave();
+            }
+        });
+}
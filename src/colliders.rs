@@ -0,0 +1,143 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+
+use crate::runtime::{RuntimeMap, RuntimeTerrainVisual, TerrainMeshesReady};
+use crate::terrain::{self, CornerHeightCache};
+use crate::types::TileMap;
+
+/// Keeps a `TerrainCollider` on the `RuntimeTerrain` entity, rebuilt whenever
+/// new terrain meshes are swapped in. Added by `RuntimePlugin` when the `colliders` feature
+/// is on.
+///
+/// The collider is plain geometry rather than a physics engine's type, so a
+/// game turns it into its engine's collider, for example with
+/// `Collider::trimesh(vertices, indices)` in Rapier or Avian.
+pub struct TerrainColliderPlugin;
+
+impl Plugin for TerrainColliderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TerrainColliderKind>().add_systems(
+            Update,
+            update_terrain_collider.in_set(terrain::TerrainMeshSet::Material),
+        );
+    }
+}
+
+/// Which shape `TerrainCollider` is built as.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TerrainColliderKind {
+    /// The rendered terrain's triangles, cliffs and all.
+    #[default]
+    Trimesh,
+    /// Heights at tile corners. Cheaper to collide with, but cliffs become
+    /// one-tile slopes.
+    Heightfield,
+}
+
+/// Collision geometry for the runtime terrain, in the terrain entity's local
+/// space.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub enum TerrainCollider {
+    Trimesh {
+        vertices: Vec<Vec3>,
+        indices: Vec<[u32; 3]>,
+    },
+    /// `terrain::corner_grid`: `columns` along +X and `rows` along +Z,
+    /// spaced `tile_size` apart from the map's north-west corner at the
+    /// origin.
+    Heightfield {
+        rows: u32,
+        columns: u32,
+        heights: Vec<f32>,
+        tile_size: f32,
+    },
+}
+
+impl TerrainCollider {
+    /// The triangles of a terrain mesh such as `terrain::build_combined_mesh`
+    /// makes; `None` without positions or indices.
+    pub fn trimesh(mesh: &Mesh) -> Option<Self> {
+        Self::trimesh_from_chunks([mesh])
+    }
+
+    /// The triangles of every mesh in `meshes` together, such as the chunks
+    /// of `terrain::build_chunk_meshes`, skipping those without positions or
+    /// indices; `None` when all are skipped.
+    pub fn trimesh_from_chunks<'a>(meshes: impl IntoIterator<Item = &'a Mesh>) -> Option<Self> {
+        let mut vertices = Vec::new();
+        let mut triangles = Vec::new();
+        let mut any = false;
+        for mesh in meshes {
+            let Some(VertexAttributeValues::Float32x3(positions)) =
+                mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+            else {
+                continue;
+            };
+            let Some(mesh_indices) = mesh.indices() else {
+                continue;
+            };
+            let indices: Vec<u32> = match mesh_indices {
+                Indices::U16(indices) => indices.iter().map(|&i| u32::from(i)).collect(),
+                Indices::U32(indices) => indices.clone(),
+            };
+            let first = vertices.len() as u32;
+            vertices.extend(positions.iter().map(|&p| Vec3::from_array(p)));
+            triangles.extend(indices.chunks_exact(3).map(|triangle| {
+                [
+                    first + triangle[0],
+                    first + triangle[1],
+                    first + triangle[2],
+                ]
+            }));
+            any = true;
+        }
+        any.then_some(Self::Trimesh {
+            vertices,
+            indices: triangles,
+        })
+    }
+
+    pub fn heightfield(map: &TileMap, heights: &CornerHeightCache) -> Self {
+        Self::Heightfield {
+            rows: map.height + 1,
+            columns: map.width + 1,
+            heights: terrain::corner_grid(map, heights),
+            tile_size: map.tile_size,
+        }
+    }
+}
+
+fn update_terrain_collider(
+    mut commands: Commands,
+    runtime_map: Res<RuntimeMap>,
+    heights: Res<CornerHeightCache>,
+    kind: Res<TerrainColliderKind>,
+    mut ready: EventReader<TerrainMeshesReady>,
+    runtime: Option<Res<RuntimeTerrainVisual>>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    if ready.is_empty() && !kind.is_changed() {
+        return;
+    }
+    ready.clear();
+    let Some(runtime) = runtime else {
+        return;
+    };
+
+    let collider = match *kind {
+        TerrainColliderKind::Trimesh => TerrainCollider::trimesh_from_chunks(
+            runtime
+                .chunk_meshes()
+                .into_iter()
+                .filter_map(|mesh| meshes.get(mesh)),
+        ),
+        TerrainColliderKind::Heightfield => {
+            Some(TerrainCollider::heightfield(&runtime_map.map, &heights))
+        }
+    };
+    let mut entity = commands.entity(runtime.entity);
+    match collider {
+        Some(collider) => entity.insert(collider),
+        None => entity.remove::<TerrainCollider>(),
+    };
+}
@@ -1,16 +1,19 @@
-use bevy::input::mouse::MouseWheel;
+use bevy::input::mouse::{MouseMotion, MouseWheel};
 use bevy::prelude::*;
 use bevy_egui::EguiContexts;
 
+use crate::camera::MainCamera;
+use crate::editor::{EditorState, EditorTool};
+
 pub struct ControlsPlugin;
 impl Plugin for ControlsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, camera_move);
+        app.add_systems(Update, (camera_move, camera_pan));
     }
 }
 
 fn camera_move(
-    mut q_cam: Query<(&mut Transform, &mut Projection), With<Camera3d>>,
+    mut q_cam: Query<(&mut Transform, &mut Projection), With<MainCamera>>,
     keys: Res<ButtonInput<KeyCode>>,
     mut scroll: EventReader<MouseWheel>,
     mut egui: EguiContexts,
@@ -58,3 +61,31 @@ fn camera_move(
         }
     }
 }
+
+/// Drags the view with the pan tool, keeping the ground under the cursor
+/// fixed for the orthographic camera.
+fn camera_pan(
+    mut q_cam: Query<(&mut Transform, &Projection), With<MainCamera>>,
+    state: Res<EditorState>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    mut egui: EguiContexts,
+) {
+    let delta: Vec2 = motion.read().map(|event| event.delta).sum();
+    if state.current_tool != EditorTool::Pan || !buttons.pressed(MouseButton::Left) {
+        return;
+    }
+    if egui.ctx_mut().wants_pointer_input() || delta == Vec2::ZERO {
+        return;
+    }
+    let Ok((mut t, proj)) = q_cam.get_single_mut() else {
+        return;
+    };
+    // World units per logical pixel.
+    let scale = match proj {
+        Projection::Orthographic(ortho) => ortho.scale,
+        Projection::Perspective(_) => 0.05,
+    };
+    let offset = (t.right() * -delta.x + t.up() * delta.y) * scale;
+    t.translation += offset;
+}
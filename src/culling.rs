@@ -0,0 +1,277 @@
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+
+use crate::camera::{MainCamera, SplitView};
+use crate::editor::EditorState;
+use crate::runtime::TerrainChunk;
+use crate::terrain::{CornerHeightCache, TerrainChanged, TerrainMeshSet};
+
+/// Small lift applied to a chunk's top so coplanar terrain does not count as
+/// an occluder.
+const OCCLUSION_EPSILON: f32 = 0.05;
+
+const DRAWN_COLOR: Color = Color::srgba(0.3, 0.9, 0.3, 0.8);
+const FRUSTUM_CULLED_COLOR: Color = Color::srgba(0.9, 0.2, 0.2, 0.8);
+const OCCLUDED_COLOR: Color = Color::srgba(1.0, 0.6, 0.1, 0.8);
+
+pub struct TerrainCullingPlugin;
+
+impl Plugin for TerrainCullingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                add_chunk_occlusion,
+                update_chunk_height_bounds,
+                cull_occluded_chunks,
+            )
+                .chain()
+                .after(TerrainMeshSet::Rebuild),
+        )
+        .add_systems(Update, draw_chunk_culling.after(cull_occluded_chunks));
+    }
+}
+
+/// A rectangular block of terrain tiles that may be hidden when higher terrain
+/// sits between it and the camera. Added to every `TerrainChunk`.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ChunkOcclusion {
+    pub min_tile: UVec2,
+    pub max_tile: UVec2,
+    pub max_height: f32,
+    pub occluded: bool,
+}
+
+impl ChunkOcclusion {
+    pub fn new(min_tile: UVec2, max_tile: UVec2) -> Self {
+        Self {
+            min_tile,
+            max_tile,
+            max_height: f32::INFINITY,
+            occluded: false,
+        }
+    }
+
+    /// The chunk's top face as a world XZ rectangle and its height, from
+    /// its mesh bounds when it has them, which take in upper layers, or
+    /// from its tiles and ground heights otherwise.
+    fn top_face(&self, bounds: Option<(&Aabb, &GlobalTransform)>, tile_size: f32) -> (Rect, f32) {
+        if let Some((bounds, transform)) = bounds {
+            let min = transform.transform_point(bounds.min().into());
+            let max = transform.transform_point(bounds.max().into());
+            return (Rect::new(min.x, min.z, max.x, max.z), max.y);
+        }
+        let min = self.min_tile.as_vec2() * tile_size;
+        let max = (self.max_tile + UVec2::ONE).as_vec2() * tile_size;
+        (Rect::from_corners(min, max), self.max_height)
+    }
+}
+
+/// How the camera sees the terrain, which decides where lines of sight run.
+enum View {
+    /// Towards the camera at this position.
+    Perspective(Vec3),
+    /// Along this direction, pointing back at the camera.
+    Orthographic(Vec3),
+}
+
+fn add_chunk_occlusion(
+    mut commands: Commands,
+    chunks: Query<(Entity, &TerrainChunk), Without<ChunkOcclusion>>,
+) {
+    for (entity, chunk) in &chunks {
+        commands
+            .entity(entity)
+            .insert(ChunkOcclusion::new(chunk.min_tile, chunk.max_tile));
+    }
+}
+
+fn update_chunk_height_bounds(
+    heights: Res<CornerHeightCache>,
+    mut changed: EventReader<TerrainChanged>,
+    mut chunks: Query<&mut ChunkOcclusion>,
+) {
+    let terrain_changed = !changed.is_empty();
+    changed.clear();
+
+    for mut chunk in &mut chunks {
+        if !terrain_changed && chunk.max_height.is_finite() {
+            continue;
+        }
+        let mut max_height = f32::NEG_INFINITY;
+        for y in chunk.min_tile.y..=chunk.max_tile.y {
+            for x in chunk.min_tile.x..=chunk.max_tile.x {
+                if let Some(corners) = heights.corners(x, y) {
+                    max_height = corners.into_iter().fold(max_height, f32::max);
+                }
+            }
+        }
+        chunk.max_height = max_height;
+    }
+}
+
+/// Hides chunks that terrain hides completely from the camera. Lines of
+/// sight from lower points of a chunk run below those from its top, so it is
+/// enough that the ground blocks every line from its top face; a chunk only
+/// partly covered stays visible. Visibility is shared by every camera, so
+/// nothing is culled in split view.
+fn cull_occluded_chunks(
+    heights: Res<CornerHeightCache>,
+    split: Res<SplitView>,
+    cameras: Query<(&GlobalTransform, &Projection), With<MainCamera>>,
+    mut chunks: Query<(
+        &mut ChunkOcclusion,
+        &mut Visibility,
+        Option<&Aabb>,
+        &GlobalTransform,
+    )>,
+) {
+    if chunks.is_empty() {
+        return;
+    }
+
+    let Some((camera_transform, projection)) = cameras.iter().next() else {
+        return;
+    };
+
+    let ceiling = chunks
+        .iter()
+        .map(|(chunk, ..)| chunk.max_height)
+        .filter(|height| height.is_finite())
+        .fold(f32::NEG_INFINITY, f32::max);
+    if !ceiling.is_finite() {
+        return;
+    }
+
+    let view = match projection {
+        Projection::Orthographic(_) => View::Orthographic(-camera_transform.forward().as_vec3()),
+        _ => View::Perspective(camera_transform.translation()),
+    };
+
+    for (mut chunk, mut visibility, bounds, transform) in &mut chunks {
+        if !chunk.max_height.is_finite() {
+            continue;
+        }
+
+        let occluded = !split.enabled && {
+            let (face, height) = chunk.top_face(bounds.zip(Some(transform)), heights.tile_size());
+            face_hidden(&heights, face, height + OCCLUSION_EPSILON, &view, ceiling)
+        };
+
+        if chunk.occluded != occluded {
+            chunk.occluded = occluded;
+            *visibility = if occluded {
+                Visibility::Hidden
+            } else {
+                Visibility::Inherited
+            };
+        }
+    }
+}
+
+/// Boxes every terrain chunk's bounds while the overlay is on: green when it
+/// was drawn last frame, red when it lay outside every camera's view and
+/// orange when hidden behind terrain.
+fn draw_chunk_culling(
+    mut gizmos: Gizmos,
+    state: Res<EditorState>,
+    chunks: Query<
+        (
+            &Aabb,
+            &GlobalTransform,
+            &ViewVisibility,
+            Option<&ChunkOcclusion>,
+        ),
+        With<TerrainChunk>,
+    >,
+) {
+    if !state.show_chunk_culling {
+        return;
+    }
+    for (bounds, transform, view_visibility, occlusion) in &chunks {
+        let color = if occlusion.is_some_and(|chunk| chunk.occluded) {
+            OCCLUDED_COLOR
+        } else if view_visibility.get() {
+            DRAWN_COLOR
+        } else {
+            FRUSTUM_CULLED_COLOR
+        };
+        let local = Transform::from_translation(bounds.center.into())
+            .with_scale(Vec3::from(bounds.half_extents) * 2.0);
+        gizmos.cuboid(transform.mul_transform(local), color);
+    }
+}
+
+/// Whether the ground blocks every line of sight from the horizontal `face`
+/// at `height`. Moves the face along those lines a step at a time, as each
+/// point of it would move towards the camera, until it climbs above
+/// `ceiling`; the face is hidden once the ground under all of it rises
+/// above it at some step.
+fn face_hidden(
+    heights: &CornerHeightCache,
+    face: Rect,
+    height: f32,
+    view: &View,
+    ceiling: f32,
+) -> bool {
+    let step = heights.tile_size() * 0.5;
+    let mut travelled = step;
+    loop {
+        let (moved, y) = match *view {
+            View::Orthographic(towards) => {
+                if towards.y <= 0.0 {
+                    return false;
+                }
+                let offset = towards * travelled;
+                let shift = Vec2::new(offset.x, offset.z);
+                (
+                    Rect::from_corners(face.min + shift, face.max + shift),
+                    height + offset.y,
+                )
+            }
+            View::Perspective(camera) => {
+                let centre = face.center();
+                let to_camera = camera - Vec3::new(centre.x, height, centre.y);
+                if to_camera.y <= 0.0 {
+                    return false;
+                }
+                // Every point of the face covers the same share of its way
+                // to the camera, so the face shrinks towards it.
+                let share = travelled / to_camera.length();
+                if share >= 1.0 {
+                    return false;
+                }
+                let target = Vec2::new(camera.x, camera.z);
+                (
+                    Rect::from_corners(face.min.lerp(target, share), face.max.lerp(target, share)),
+                    height + to_camera.y * share,
+                )
+            }
+        };
+        if y > ceiling {
+            return false;
+        }
+        if ground_above(heights, moved, y) {
+            return true;
+        }
+        travelled += step;
+    }
+}
+
+/// Whether every tile under `area` lies wholly above `height`. Off the map
+/// and over holes nothing blocks the view.
+fn ground_above(heights: &CornerHeightCache, area: Rect, height: f32) -> bool {
+    if area.min.x < 0.0 || area.min.y < 0.0 {
+        return false;
+    }
+    let tile_size = heights.tile_size();
+    let min = (area.min / tile_size).floor().as_uvec2();
+    let max = (area.max / tile_size).floor().as_uvec2();
+    (min.y..=max.y).all(|y| {
+        (min.x..=max.x).all(|x| {
+            heights
+                .corners(x, y)
+                .is_some_and(|corners| corners.into_iter().all(|corner| corner > height))
+        })
+    })
+}
@@ -0,0 +1,225 @@
+use bevy::pbr::{MaterialMeshBundle, MaterialPlugin, NotShadowCaster};
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{AsBindGroup, ShaderRef};
+
+use crate::runtime::RuntimeMap;
+use crate::terrain::{self, CornerHeightCache};
+use crate::types::{DecalKind, MapDecal, TileMap};
+
+/// Grid cells along each tile a decal spans. The grid is draped over the
+/// terrain, so finer cells follow slopes and sculpted corners more closely.
+const CELLS_PER_TILE: f32 = 4.0;
+const MAX_CELLS: u32 = 64;
+/// World units decals float above the terrain, so they don't flicker against
+/// it and ground that bends between grid points doesn't poke through them.
+const LIFT: f32 = 0.02;
+
+/// Draws the map's decals over the terrain. Each decal is a grid laid over
+/// the ground beneath it, and the decal shader projects its shape straight
+/// down onto that grid and darkens the terrain under it.
+pub struct DecalPlugin;
+
+impl Plugin for DecalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MaterialPlugin::<DecalMaterial>::default())
+            .add_systems(Startup, setup_decals)
+            .add_systems(
+                Update,
+                rebuild_decal_mesh.in_set(terrain::TerrainMeshSet::Rebuild),
+            );
+    }
+}
+
+/// Looks of the decals, passed to `shaders/decal.wgsl`. The ground under a
+/// decal is multiplied by its kind's color, so decals darken and tint the lit
+/// terrain instead of covering it.
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug)]
+pub struct DecalMaterial {
+    /// Linear color at the bottom of a crater.
+    #[uniform(0)]
+    pub crater_color: Vec4,
+    #[uniform(1)]
+    pub crack_color: Vec4,
+    /// Linear color of fallen leaves; each leaf varies it a little.
+    #[uniform(2)]
+    pub leaf_color: Vec4,
+}
+
+impl Default for DecalMaterial {
+    fn default() -> Self {
+        Self {
+            crater_color: Vec4::new(0.12, 0.1, 0.08, 1.0),
+            crack_color: Vec4::new(0.08, 0.07, 0.06, 1.0),
+            leaf_color: Vec4::new(0.75, 0.4, 0.12, 1.0),
+        }
+    }
+}
+
+impl Material for DecalMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/decal.wgsl".into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        AlphaMode::Multiply
+    }
+}
+
+#[derive(Resource)]
+pub struct DecalVisual {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<DecalMaterial>,
+    pub entity: Entity,
+    /// The decals the mesh was built from.
+    decals: Vec<MapDecal>,
+}
+
+fn setup_decals(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<DecalMaterial>>,
+) {
+    let mesh = meshes.add(terrain::empty_mesh());
+    let material = materials.add(DecalMaterial::default());
+    let entity = commands
+        .spawn((
+            MaterialMeshBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            NotShadowCaster,
+            Name::new("Decals"),
+        ))
+        .id();
+    commands.insert_resource(DecalVisual {
+        mesh,
+        material,
+        entity,
+        decals: Vec::new(),
+    });
+}
+
+/// Rebuilds the decal mesh when the decals change or the terrain under them
+/// moves.
+fn rebuild_decal_mesh(
+    runtime_map: Res<RuntimeMap>,
+    heights: Res<CornerHeightCache>,
+    mut changed: EventReader<terrain::TerrainChanged>,
+    visual: Option<ResMut<DecalVisual>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut visibility: Query<&mut Visibility>,
+) {
+    let moved = !changed.is_empty();
+    changed.clear();
+    let Some(mut visual) = visual else {
+        return;
+    };
+    if !moved && visual.decals == runtime_map.map.decals {
+        return;
+    }
+    visual.decals.clone_from(&runtime_map.map.decals);
+
+    let mesh = build_decal_mesh(&runtime_map.map, &heights);
+    if let Ok(mut visibility) = visibility.get_mut(visual.entity) {
+        *visibility = if mesh.is_some() {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+    if let (Some(mesh), Some(existing)) = (mesh, meshes.get_mut(&visual.mesh)) {
+        *existing = mesh;
+    }
+}
+
+/// Every decal as a grid draped over the terrain, or `None` when there is
+/// nothing to draw. Grid cells over holes or off the map are left out.
+///
+/// The first UV coordinate runs from -1 to 1 across the decal, east and
+/// south before it is turned. The vertex color holds the decal's index in
+/// `DecalKind::ALL` (red), its opacity (green) and a seed that varies its
+/// shape (blue).
+pub fn build_decal_mesh(map: &TileMap, heights: &CornerHeightCache) -> Option<Mesh> {
+    let tile_size = map.tile_size;
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut colors = Vec::new();
+    let mut indices = Vec::new();
+    for decal in &map.decals {
+        if !(decal.size.is_finite() && decal.size > 0.0) {
+            continue;
+        }
+        let cells = ((decal.size * CELLS_PER_TILE).ceil() as u32).clamp(1, MAX_CELLS);
+        let half_size = decal.size * tile_size * 0.5;
+        let centre = Vec2::new(decal.x, decal.y) * tile_size;
+        // Clockwise from north seen from above, with +X east and +Z south.
+        let (sin, cos) = decal.rotation.to_radians().sin_cos();
+        let east = Vec2::new(cos, sin) * half_size;
+        let south = Vec2::new(-sin, cos) * half_size;
+        let kind = DecalKind::ALL
+            .iter()
+            .position(|&kind| kind == decal.kind)
+            .unwrap_or(0);
+        let color = [
+            kind as f32,
+            decal.opacity.clamp(0.0, 1.0),
+            decal_seed(decal),
+            1.0,
+        ];
+
+        let base = positions.len() as u32;
+        let mut solid = Vec::with_capacity(((cells + 1) * (cells + 1)) as usize);
+        for row in 0..=cells {
+            for column in 0..=cells {
+                let uv = Vec2::new(column as f32, row as f32) / cells as f32 * 2.0 - 1.0;
+                let ground = centre + east * uv.x + south * uv.y;
+                let height = heights.height_at(ground.x, ground.y);
+                solid.push(height.is_some());
+                positions.push([ground.x, height.unwrap_or(0.0) + LIFT, ground.y]);
+                uvs.push(uv.to_array());
+                colors.push(color);
+            }
+        }
+        let vertex = |column: u32, row: u32| row * (cells + 1) + column;
+        for row in 0..cells {
+            for column in 0..cells {
+                let corners = [
+                    vertex(column, row),
+                    vertex(column + 1, row),
+                    vertex(column, row + 1),
+                    vertex(column + 1, row + 1),
+                ];
+                if corners.iter().all(|&corner| solid[corner as usize]) {
+                    let [nw, ne, sw, se] = corners;
+                    indices.extend([nw, sw, ne, ne, sw, se].map(|i| base + i));
+                }
+            }
+        }
+    }
+    if indices.is_empty() {
+        return None;
+    }
+
+    let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.insert_indices(Indices::U32(indices));
+    Some(mesh)
+}
+
+/// A number in [0, 1) that differs between decals placed apart, so two
+/// decals of a kind don't look identical.
+fn decal_seed(decal: &MapDecal) -> f32 {
+    let bits = decal.x.to_bits() ^ decal.y.to_bits().rotate_left(16);
+    (bits.wrapping_mul(0x9e37_79b9) >> 8) as f32 / (1 << 24) as f32
+}
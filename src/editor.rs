@@ -1,11 +1,25 @@
+use crate::collab;
+use crate::generators::GeneratorPreview;
+use crate::input;
+use crate::io;
+use crate::new_map::NewMapSettings;
+use crate::picking::HeightfieldPicker;
+use crate::props::PlacedProp;
+use crate::runtime::{DirtyTiles, RuntimeMap};
+use crate::selection::WandSettings;
+use crate::spatial::SpatialIndex;
 use crate::terrain;
 use crate::texture::material::TerrainMaterial;
 use crate::texture::registry::TerrainTextureRegistry;
+use crate::tools::scatter::ScatterSettings;
+use crate::tools::transform::MapTransform;
+use crate::tools::{self, BrushShape, Falloff};
+use crate::triggers::TriggersChanged;
 use crate::types::*;
-use bevy::pbr::MaterialMeshBundle;
 use bevy::prelude::*;
 use bevy::tasks::Task;
 use bevy_egui::EguiContexts;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::PathBuf;
 
 pub enum ExportStatus {
@@ -17,6 +31,13 @@ pub struct EditorPlugin;
 impl Plugin for EditorPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<EditorState>()
+            .init_resource::<Stroke>()
+            .init_resource::<EditHistory>()
+            .init_resource::<LocalEdits>()
+            .add_event::<MapEdit>()
+            .add_event::<StrokeFinished>()
+            .add_event::<terrain::TerrainChanged>()
+            .add_event::<TriggersChanged>()
             .init_gizmo_group::<HoverGizmoGroup>()
             .add_systems(Startup, spawn_editor_assets)
             .add_systems(Startup, configure_hover_gizmos)
@@ -24,27 +45,668 @@ impl Plugin for EditorPlugin {
                 Update,
                 (
                     update_hover,
+                    track_stroke,
+                    history_shortcuts,
                     paint_tiles,
+                    erase_tiles,
+                    pick_tile,
                     rotate_ramps,
-                    draw_hover_highlight,
+                    rotate_textures,
                 )
-                    .before(terrain::TerrainMeshSet::Rebuild),
+                    .chain()
+                    .in_set(terrain::TerrainMeshSet::Edit),
             )
             .add_systems(
                 Update,
-                rebuild_terrain_mesh.in_set(terrain::TerrainMeshSet::Rebuild),
+                apply_map_edits.in_set(terrain::TerrainMeshSet::Apply),
             )
             .add_systems(
                 Update,
-                mark_map_clean.in_set(terrain::TerrainMeshSet::Cleanup),
+                sync_runtime_map
+                    .in_set(terrain::TerrainMeshSet::Apply)
+                    .after(apply_map_edits),
+            )
+            .add_systems(
+                Update,
+                draw_hover_highlight.after(terrain::TerrainMeshSet::Prepare),
             );
     }
 }
 
+/// A requested change to the edited map. Tools and UI send these instead of
+/// writing to `EditorState::map`, so every mutation happens in
+/// `apply_map_edits` before any derived data is rebuilt.
+#[derive(Event, Clone)]
+pub enum MapEdit {
+    /// Ignored for locked tiles; the tile's lock flag is never changed.
+    SetTile {
+        x: u32,
+        y: u32,
+        tile: Tile,
+    },
+    SetLocked {
+        x: u32,
+        y: u32,
+        locked: bool,
+    },
+    /// Adds the tile to, or removes it from, the trigger at `trigger`.
+    SetTriggerTile {
+        trigger: usize,
+        x: u32,
+        y: u32,
+        inside: bool,
+    },
+    /// Replaces the trigger list, for adding, renaming or removing triggers.
+    SetTriggers(Vec<TriggerArea>),
+    /// Replaces every start location of `player`, or removes them on `None`.
+    SetStart {
+        player: u8,
+        start: Option<StartLocation>,
+    },
+    /// Places a light on the tile, replacing any there, or removes it on
+    /// `None`.
+    SetLight {
+        x: u32,
+        y: u32,
+        light: Option<MapLight>,
+    },
+    /// Replaces the light list, for editing or removing lights.
+    SetLights(Vec<MapLight>),
+    SetWeather(Weather),
+    /// Changes the world units per tile, rescaling the whole terrain.
+    SetTileSize(f32),
+    /// Floods the map up to a level in elevation steps, or drains it on
+    /// `None`.
+    SetWaterLevel(Option<f32>),
+    /// Like `SetTile`, on upper layer `layer` counting from 1.
+    SetLayerTile {
+        layer: usize,
+        x: u32,
+        y: u32,
+        tile: Tile,
+    },
+    /// Replaces the upper layers, for adding or removing them.
+    SetUpperLayers(Vec<Vec<Tile>>),
+    SetVegetation {
+        x: u32,
+        y: u32,
+        density: u8,
+    },
+    /// Draws a road on a tile, or removes it with `None`.
+    SetRoad {
+        x: u32,
+        y: u32,
+        surface: Option<RoadSurface>,
+    },
+    /// Overrides whether a tile can be walked on, or hands it back to the
+    /// terrain with `None`.
+    SetWalkability {
+        x: u32,
+        y: u32,
+        walkability: Option<Walkability>,
+    },
+    /// Lays a decal over the others.
+    PlaceDecal(MapDecal),
+    /// Replaces the decal list, for removing decals.
+    SetDecals(Vec<MapDecal>),
+    PlaceProp(MapProp),
+    /// Moves or turns the prop at `index`, or removes it on `None`.
+    SetProp {
+        index: usize,
+        prop: Option<MapProp>,
+    },
+    /// Turns or flips the whole map with everything on it.
+    Transform(MapTransform),
+    /// Replaces the whole map, as when opening a file; clears the history.
+    Replace(Box<TileMap>),
+    /// Like `Replace`, with a map a collaborator sent, which is left to be
+    /// saved. `own` marks this instance's map returned by the host, which
+    /// keeps the history.
+    RemoteMap {
+        map: Box<TileMap>,
+        own: bool,
+    },
+    /// Changes a collaborator made. They overwrite the tiles whether or not
+    /// they are locked, and local history of those tiles is dropped so
+    /// undoing cannot revert them. `own` marks this instance's changes
+    /// returned by the host, which keep their history.
+    Remote {
+        changes: Box<collab::Changes>,
+        own: bool,
+    },
+    /// Reverts the newest entry of the `EditHistory`.
+    Undo,
+    /// Reapplies the newest undone entry of the `EditHistory`.
+    Redo,
+}
+
+pub const DEFAULT_HISTORY_DEPTH: usize = 100;
+pub const MAX_HISTORY_DEPTH: usize = 1000;
+/// Label of history entries made outside a stroke.
+const EDIT_LABEL: &str = "history.edit";
+
+/// Reversible record of tile changes. `apply_map_edits` is the only place
+/// tiles are written, and it records each change here as one entry per
+/// stroke, or per frame for edits made outside a stroke.
+#[derive(Resource)]
+pub struct EditHistory {
+    depth: usize,
+    /// Oldest first.
+    undo: VecDeque<HistoryEntry>,
+    /// Newest undone last.
+    redo: Vec<HistoryEntry>,
+}
+
+struct HistoryEntry {
+    /// Localization key naming the action.
+    label: &'static str,
+    stroke: Option<u64>,
+    /// Every changed tile as it was before the entry and after it.
+    tiles: HashMap<(u32, u32), (Tile, Tile)>,
+    /// The same for tiles on upper layers, keyed by layer number first.
+    layer_tiles: HashMap<(usize, u32, u32), (Tile, Tile)>,
+    /// The same for the road layer.
+    roads: HashMap<(u32, u32), (Option<RoadSurface>, Option<RoadSurface>)>,
+    /// The same for vegetation density.
+    vegetation: HashMap<(u32, u32), (u8, u8)>,
+    /// The same for walkability overrides.
+    walkability: HashMap<(u32, u32), (Option<Walkability>, Option<Walkability>)>,
+    /// A turn or flip of the whole map, recorded on its own entry; undone by
+    /// its inverse.
+    transform: Option<MapTransform>,
+}
+
+impl HistoryEntry {
+    fn new(label: &'static str, stroke: Option<u64>) -> Self {
+        Self {
+            label,
+            stroke,
+            tiles: HashMap::new(),
+            layer_tiles: HashMap::new(),
+            roads: HashMap::new(),
+            vegetation: HashMap::new(),
+            walkability: HashMap::new(),
+            transform: None,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+            && self.layer_tiles.is_empty()
+            && self.roads.is_empty()
+            && self.vegetation.is_empty()
+            && self.walkability.is_empty()
+            && self.transform.is_none()
+    }
+
+    /// Every tile whose ground, roads, vegetation or walkability the entry
+    /// changes.
+    fn tile_keys(&self) -> impl Iterator<Item = &(u32, u32)> {
+        self.tiles
+            .keys()
+            .chain(self.roads.keys())
+            .chain(self.vegetation.keys())
+            .chain(self.walkability.keys())
+    }
+
+    /// Adds a change, keeping the earliest before-state of a tile changed
+    /// more than once.
+    fn record(&mut self, x: u32, y: u32, before: Tile, after: Tile) {
+        self.tiles
+            .entry((x, y))
+            .and_modify(|(_, latest)| *latest = after.clone())
+            .or_insert((before, after));
+    }
+
+    fn record_layer(&mut self, layer: usize, x: u32, y: u32, before: Tile, after: Tile) {
+        self.layer_tiles
+            .entry((layer, x, y))
+            .and_modify(|(_, latest)| *latest = after.clone())
+            .or_insert((before, after));
+    }
+
+    fn record_road(
+        &mut self,
+        x: u32,
+        y: u32,
+        before: Option<RoadSurface>,
+        after: Option<RoadSurface>,
+    ) {
+        self.roads
+            .entry((x, y))
+            .and_modify(|(_, latest)| *latest = after)
+            .or_insert((before, after));
+    }
+
+    fn record_vegetation(&mut self, x: u32, y: u32, before: u8, after: u8) {
+        self.vegetation
+            .entry((x, y))
+            .and_modify(|(_, latest)| *latest = after)
+            .or_insert((before, after));
+    }
+
+    fn record_walkability(
+        &mut self,
+        x: u32,
+        y: u32,
+        before: Option<Walkability>,
+        after: Option<Walkability>,
+    ) {
+        self.walkability
+            .entry((x, y))
+            .and_modify(|(_, latest)| *latest = after)
+            .or_insert((before, after));
+    }
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self {
+            depth: DEFAULT_HISTORY_DEPTH,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+        }
+    }
+}
+
+impl EditHistory {
+    /// Most entries kept for undo.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Changes the depth, dropping the oldest entries beyond it.
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth.clamp(1, MAX_HISTORY_DEPTH);
+        while self.undo.len() > self.depth {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Localization key of the action Undo would revert.
+    pub fn undo_label(&self) -> Option<&'static str> {
+        self.undo.back().map(|entry| entry.label)
+    }
+
+    /// Localization key of the action Redo would reapply.
+    pub fn redo_label(&self) -> Option<&'static str> {
+        self.redo.last().map(|entry| entry.label)
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Drops every recorded change to `tiles`, and to all upper layer tiles
+    /// when `layers` is set, so undo and redo leave what someone else wrote
+    /// there since alone.
+    fn forget(&mut self, tiles: &HashSet<(u32, u32)>, layers: bool) {
+        if tiles.is_empty() && !layers {
+            return;
+        }
+        for entry in self.undo.iter_mut().chain(self.redo.iter_mut()) {
+            entry.tiles.retain(|tile, _| !tiles.contains(tile));
+            entry.roads.retain(|tile, _| !tiles.contains(tile));
+            entry.vegetation.retain(|tile, _| !tiles.contains(tile));
+            entry.walkability.retain(|tile, _| !tiles.contains(tile));
+            entry
+                .layer_tiles
+                .retain(|&(_, x, y), _| !layers && !tiles.contains(&(x, y)));
+        }
+        self.undo.retain(|entry| !entry.is_empty());
+        self.redo.retain(|entry| !entry.is_empty());
+    }
+
+    /// Adds `entry`, merging it into the newest one when both belong to the
+    /// same stroke. New changes make the undone entries unreachable.
+    fn push(&mut self, entry: HistoryEntry) {
+        if entry.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        match self.undo.back_mut() {
+            Some(newest) if entry.stroke.is_some() && newest.stroke == entry.stroke => {
+                for ((x, y), (before, after)) in entry.tiles {
+                    newest.record(x, y, before, after);
+                }
+                for ((layer, x, y), (before, after)) in entry.layer_tiles {
+                    newest.record_layer(layer, x, y, before, after);
+                }
+                for ((x, y), (before, after)) in entry.roads {
+                    newest.record_road(x, y, before, after);
+                }
+                for ((x, y), (before, after)) in entry.vegetation {
+                    newest.record_vegetation(x, y, before, after);
+                }
+                for ((x, y), (before, after)) in entry.walkability {
+                    newest.record_walkability(x, y, before, after);
+                }
+            }
+            _ => {
+                self.undo.push_back(entry);
+                self.set_depth(self.depth);
+            }
+        }
+    }
+}
+
+/// The drag in progress, from pressing the left button over the terrain to
+/// releasing it. Every edit sent in between belongs to the stroke, so they
+/// can be treated as one action named after the tool that made them.
+#[derive(Resource, Default)]
+pub struct Stroke {
+    /// Tool the stroke started with; `None` between strokes.
+    pub tool: Option<EditorTool>,
+    /// Incremented as each stroke starts.
+    pub id: u64,
+}
+
+/// Sent on the frame a stroke ends. It arrives in `TerrainMeshSet::Apply`
+/// together with the stroke's last edits.
+#[derive(Event, Clone, Copy)]
+pub struct StrokeFinished {
+    pub id: u64,
+    pub tool: EditorTool,
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum EditorTool {
     Paint,
     RotateRamp,
+    /// Turns a tile's texture a quarter turn; with Shift, mirrors it.
+    RotateTexture,
+    Road,
+    EdgeRamp,
+    Plateau,
+    /// Fills a dragged rectangle with the paint settings.
+    RectFill,
+    Clone,
+    Select,
+    Wand,
+    /// Stamps the clipboard; Ctrl+V switches to it.
+    Paste,
+    /// Stamps a map chosen with "Import map here…".
+    Import,
+    Gradient,
+    /// Lays a walkable slope along a drag between two elevations.
+    AutoRamp,
+    Noise,
+    Sculpt,
+    Smooth,
+    /// Raises and lowers single tile corners.
+    Vertex,
+    /// Paints textures onto a random share of the brush.
+    Scatter,
+    Lock,
+    MovementCost,
+    /// Paints walkability overrides; Ctrl-drag hands tiles back to the
+    /// terrain.
+    Walkability,
+    Trigger,
+    Start,
+    Path,
+    Light,
+    Vegetation,
+    /// Places decals at the cursor; Ctrl-click removes the top one there.
+    Decal,
+    /// Places, moves, turns and removes props.
+    Prop,
+    /// Replays the macro chosen in the macro window.
+    Macro,
+    /// Turns tiles into holes; held Ctrl switches to it.
+    Erase,
+    /// Picks the paint settings from a tile; held Alt switches to it.
+    Eyedropper,
+    /// Drags the camera; held Space switches to it.
+    Pan,
+}
+
+impl EditorTool {
+    /// Localization key of the tool's name.
+    pub fn label_key(self) -> &'static str {
+        match self {
+            EditorTool::Paint => "tool.paint",
+            EditorTool::RotateRamp => "tool.rotate_ramp",
+            EditorTool::RotateTexture => "tool.rotate_texture",
+            EditorTool::Road => "tool.road",
+            EditorTool::EdgeRamp => "tool.edge_ramp",
+            EditorTool::Plateau => "tool.plateau",
+            EditorTool::RectFill => "tool.rect_fill",
+            EditorTool::Clone => "tool.clone",
+            EditorTool::Select => "tool.select",
+            EditorTool::Wand => "tool.wand",
+            EditorTool::Paste => "tool.paste",
+            EditorTool::Import => "tool.import",
+            EditorTool::Gradient => "tool.gradient",
+            EditorTool::AutoRamp => "tool.auto_ramp",
+            EditorTool::Noise => "tool.noise",
+            EditorTool::Sculpt => "tool.sculpt",
+            EditorTool::Smooth => "tool.smooth",
+            EditorTool::Vertex => "tool.vertex",
+            EditorTool::Scatter => "tool.scatter",
+            EditorTool::Lock => "tool.lock",
+            EditorTool::MovementCost => "tool.cost",
+            EditorTool::Walkability => "tool.walkability",
+            EditorTool::Trigger => "tool.trigger",
+            EditorTool::Start => "tool.start",
+            EditorTool::Path => "tool.path",
+            EditorTool::Light => "tool.light",
+            EditorTool::Vegetation => "tool.vegetation",
+            EditorTool::Decal => "tool.decal",
+            EditorTool::Prop => "tool.prop",
+            EditorTool::Macro => "tool.macro",
+            EditorTool::Erase => "tool.erase",
+            EditorTool::Eyedropper => "tool.pick",
+            EditorTool::Pan => "tool.pan",
+        }
+    }
+}
+
+pub const MAX_PAINT_RADIUS: u32 = 16;
+
+/// How edits are repeated across the map for symmetric layouts.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Symmetry {
+    #[default]
+    Off,
+    /// Mirrors left to right across the vertical centre line.
+    MirrorX,
+    /// Mirrors top to bottom across the horizontal centre line.
+    MirrorY,
+    /// Mirrors across both centre lines, for four copies.
+    MirrorBoth,
+    /// Rotates about the map centre: four quarter turns on square maps, two
+    /// half turns otherwise.
+    Radial,
+}
+
+/// Where a symmetric copy of an edit lands and how it is turned.
+#[derive(Clone, Copy, Debug)]
+pub struct SymmetryImage {
+    pub x: u32,
+    pub y: u32,
+    flip_x: bool,
+    flip_y: bool,
+    /// Clockwise quarter turns, applied after the flips.
+    quarter_turns: u8,
+}
+
+impl SymmetryImage {
+    /// `tile` moved to the image, with its ramp turned to match.
+    pub fn apply(&self, tile: &Tile) -> Tile {
+        let ramp_direction = tile.ramp_direction.and_then(|direction| {
+            let (mut dx, mut dy) = direction.offset();
+            if self.flip_x {
+                dx = -dx;
+            }
+            if self.flip_y {
+                dy = -dy;
+            }
+            for _ in 0..self.quarter_turns {
+                (dx, dy) = (-dy, dx);
+            }
+            RampDirection::from_offset(dx, dy)
+        });
+        // Corners are stored north-west, north-east, south-west, south-east.
+        let [mut nw, mut ne, mut sw, mut se] = tile.corner_offsets;
+        if self.flip_x {
+            (nw, ne, sw, se) = (ne, nw, se, sw);
+        }
+        if self.flip_y {
+            (nw, ne, sw, se) = (sw, se, nw, ne);
+        }
+        for _ in 0..self.quarter_turns {
+            (nw, ne, sw, se) = (sw, nw, se, ne);
+        }
+        // A mirror reverses the texture's turn as well as flipping it; a
+        // flip across y is a flip across x followed by a half turn.
+        let (mut rotation, mut mirrored) = (tile.rotation % 4, tile.mirrored);
+        if self.flip_x {
+            (rotation, mirrored) = ((4 - rotation) % 4, !mirrored);
+        }
+        if self.flip_y {
+            (rotation, mirrored) = ((6 - rotation) % 4, !mirrored);
+        }
+        Tile {
+            x: self.x,
+            y: self.y,
+            ramp_direction,
+            rotation: (rotation + self.quarter_turns) % 4,
+            mirrored,
+            corner_offsets: [nw, ne, sw, se],
+            ..tile.clone()
+        }
+    }
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 5] = [
+        Symmetry::Off,
+        Symmetry::MirrorX,
+        Symmetry::MirrorY,
+        Symmetry::MirrorBoth,
+        Symmetry::Radial,
+    ];
+
+    pub fn identifier(self) -> &'static str {
+        match self {
+            Symmetry::Off => "off",
+            Symmetry::MirrorX => "x",
+            Symmetry::MirrorY => "y",
+            Symmetry::MirrorBoth => "both",
+            Symmetry::Radial => "radial",
+        }
+    }
+
+    /// The copies of an edit at (`x`, `y`) on a `width` by `height` map,
+    /// not counting the edit itself. Tiles on an axis map onto themselves
+    /// and are left out.
+    pub fn images(self, width: u32, height: u32, x: u32, y: u32) -> Vec<SymmetryImage> {
+        let flips: &[(bool, bool, u8)] = match self {
+            Symmetry::Off => &[],
+            Symmetry::MirrorX => &[(true, false, 0)],
+            Symmetry::MirrorY => &[(false, true, 0)],
+            Symmetry::MirrorBoth => &[(true, false, 0), (false, true, 0), (true, true, 0)],
+            Symmetry::Radial if width == height => {
+                &[(false, false, 1), (false, false, 2), (false, false, 3)]
+            }
+            Symmetry::Radial => &[(true, true, 0)],
+        };
+        let mut images: Vec<SymmetryImage> = Vec::with_capacity(flips.len());
+        for &(flip_x, flip_y, quarter_turns) in flips {
+            let (mut ix, mut iy) = (x, y);
+            if flip_x {
+                ix = width - 1 - ix;
+            }
+            if flip_y {
+                iy = height - 1 - iy;
+            }
+            for _ in 0..quarter_turns {
+                (ix, iy) = (height - 1 - iy, ix);
+            }
+            let seen =
+                (ix, iy) == (x, y) || images.iter().any(|image| (image.x, image.y) == (ix, iy));
+            if !seen {
+                images.push(SymmetryImage {
+                    x: ix,
+                    y: iy,
+                    flip_x,
+                    flip_y,
+                    quarter_turns,
+                });
+            }
+        }
+        images
+    }
+}
+
+/// Parts of `EditorState::map` changed since the runtime terrain last
+/// caught up with it. Whatever changes the map records what it touched
+/// here, and `sync_runtime_map` copies only those parts across.
+#[derive(Clone, Debug, Default)]
+pub struct DirtyRegions {
+    rects: Vec<terrain::TileRect>,
+    all: bool,
+}
+
+impl DirtyRegions {
+    pub fn mark(&mut self, x: u32, y: u32) {
+        let tile = UVec2::new(x, y);
+        self.mark_rect(terrain::TileRect {
+            min: tile,
+            max: tile,
+        });
+    }
+
+    pub fn mark_rect(&mut self, rect: terrain::TileRect) {
+        if self.all {
+            return;
+        }
+        // Brushes touch neighbouring tiles one after another, so repeats
+        // usually fall inside the last rectangle.
+        let covered = self
+            .rects
+            .last()
+            .is_some_and(|last| last.min.cmple(rect.min).all() && last.max.cmpge(rect.max).all());
+        if !covered {
+            self.rects.push(rect);
+        }
+    }
+
+    pub fn mark_all(&mut self) {
+        self.all = true;
+        self.rects.clear();
+    }
+
+    /// Whether the whole map must be treated as changed.
+    pub fn is_all(&self) -> bool {
+        self.all
+    }
+
+    /// Every tile of every region; tiles in overlapping regions repeat.
+    pub fn tiles(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.rects.iter().flat_map(|rect| rect.iter())
+    }
+}
+
+/// What local edits changed during the current frame, so collaborators can
+/// be sent just that. Edits received from collaborators are left out.
+#[derive(Resource, Default)]
+pub struct LocalEdits {
+    /// Tiles whose ground, upper layers, road, vegetation or walkability
+    /// changed; all of them when the map was replaced or transformed.
+    pub tiles: DirtyRegions,
+    pub triggers: bool,
+    pub starts: bool,
+    pub lights: bool,
+    pub weather: bool,
+    pub decals: bool,
+    pub props: bool,
+    pub tile_size: bool,
+    pub water_level: bool,
+    /// Upper layers were added or removed.
+    pub upper_layers: bool,
 }
 
 #[derive(Resource)]
@@ -53,13 +715,85 @@ pub struct EditorState {
     pub current_kind: TileKind,
     pub current_elev: i8, // -1..3
     pub current_texture: TileType,
+    /// Surface the Road tool draws.
+    pub current_road: RoadSurface,
+    /// Override the Walkability tool paints.
+    pub current_walkability: Walkability,
+    pub plateau_steps: i8,
+    pub gradient_from: i8,
+    pub gradient_to: i8,
+    pub noise_radius: u32,
+    pub noise_amplitude: i8,
+    pub noise_seed: u64,
+    /// Tiles from the centre of the paint brush to its edge, counting the
+    /// centre, from 1 to `MAX_PAINT_RADIUS`.
+    pub paint_radius: u32,
+    pub paint_shape: BrushShape,
+    /// Map layer that painting and erasing apply to; 0 is the ground and
+    /// higher numbers are `TileMap::upper_layers`.
+    pub active_layer: usize,
+    /// Repeats every tile edit at its mirrored positions.
+    pub symmetry: Symmetry,
+    pub sculpt_radius: u32,
+    pub sculpt_strength: i8,
+    pub smooth_radius: u32,
+    /// Fraction of the way towards the neighbourhood mean a smoothed tile
+    /// moves at the brush centre.
+    pub smooth_strength: f32,
+    /// How sculpt, smooth and noise brushes fade from their centre to their edge.
+    pub brush_falloff: Falloff,
+    pub variation_type: TileType,
+    /// Texture swapped out by the toolbar's replace control, and its
+    /// replacement.
+    pub replace_from: TileType,
+    pub replace_to: TileType,
+    pub variation_seed: u64,
+    pub wand: WandSettings,
+    pub scatter: ScatterSettings,
+    pub movement_cost: u8,
+    pub active_trigger: Option<usize>,
+    pub active_player: u8,
+    pub start_team: u8,
+    pub start_facing: f32,
+    /// Settings for newly placed lights; its tile is ignored.
+    pub light: MapLight,
+    pub vegetation_density: u8,
+    pub vegetation_radius: u32,
+    /// Settings for newly placed decals; its position is ignored.
+    pub decal: MapDecal,
+    /// Turns each placed decal to a random angle instead of `decal`'s.
+    pub decal_random_rotation: bool,
+    /// Settings for newly placed props; its position is ignored.
+    pub prop: MapProp,
+    /// Index in `TileMap::props` of the prop the Prop tool's handles are on.
+    pub selected_prop: Option<usize>,
     pub hover: Option<(u32, u32)>,
+    /// World position under the cursor on the hovered tile.
+    pub hover_point: Option<Vec3>,
     pub map: TileMap,
-    pub map_dirty: bool,
     pub show_grid: bool,
+    pub show_rulers: bool,
+    pub show_locks: bool,
+    pub show_triggers: bool,
+    /// Whether tiles units cannot stand on or reach are marked.
+    pub show_walkability: bool,
+    /// Whether terrain chunks are boxed by whether they were culled.
+    pub show_chunk_culling: bool,
+    pub show_lights: bool,
+    /// Whether the map's weather is previewed with particles.
+    pub show_weather: bool,
+    pub show_vegetation: bool,
     pub current_file_path: Option<PathBuf>,
+    /// Whether the map changed since it was last opened or saved.
+    pub unsaved_changes: bool,
+    /// Where the map changed since the runtime terrain last caught up.
+    pub dirty_regions: DirtyRegions,
+    /// Base map the current file is saved against as a delta.
+    pub delta_base: Option<PathBuf>,
     pub save_dialog_task: Option<Task<Option<PathBuf>>>,
     pub load_dialog_task: Option<Task<Option<PathBuf>>>,
+    /// Picks a base map, then where to save the delta against it.
+    pub delta_dialog_task: Option<Task<Option<(PathBuf, PathBuf)>>>,
     pub export_dialog_task: Option<Task<Option<PathBuf>>>,
     pub export_task: Option<Task<anyhow::Result<PathBuf>>>,
     pub last_export_status: Option<ExportStatus>,
@@ -71,11 +805,82 @@ impl Default for EditorState {
             current_kind: TileKind::Floor,
             current_elev: 0,
             current_texture: TileType::default(),
+            current_road: RoadSurface::Dirt,
+            current_walkability: Walkability::Blocked,
+            plateau_steps: 1,
+            gradient_from: 0,
+            gradient_to: 3,
+            noise_radius: 3,
+            noise_amplitude: 1,
+            noise_seed: 0,
+            paint_radius: 1,
+            paint_shape: BrushShape::default(),
+            active_layer: 0,
+            symmetry: Symmetry::default(),
+            sculpt_radius: 3,
+            sculpt_strength: 1,
+            smooth_radius: 2,
+            smooth_strength: 1.0,
+            brush_falloff: Falloff::default(),
+            variation_type: TileType::default(),
+            replace_from: TileType::Grass,
+            replace_to: TileType::Dirt,
+            variation_seed: 0,
+            wand: WandSettings::default(),
+            scatter: ScatterSettings::default(),
+            movement_cost: 1,
+            active_trigger: None,
+            active_player: 0,
+            start_team: 0,
+            start_facing: 0.0,
+            light: MapLight {
+                kind: LightKind::Point,
+                x: 0,
+                y: 0,
+                height: 1.5,
+                color: [255, 200, 140],
+                intensity: 400_000.0,
+                range: 12.0,
+                facing: 0.0,
+                pitch: 45.0,
+                cone: 60.0,
+            },
+            vegetation_density: 160,
+            vegetation_radius: 1,
+            decal: MapDecal {
+                kind: DecalKind::Crater,
+                x: 0.0,
+                y: 0.0,
+                size: 1.5,
+                rotation: 0.0,
+                opacity: 0.8,
+            },
+            decal_random_rotation: true,
+            prop: MapProp {
+                asset: String::new(),
+                x: 0.0,
+                y: 0.0,
+                rotation: 0.0,
+                scale: 1.0,
+            },
+            selected_prop: None,
             hover: None,
-            map: TileMap::new(64, 64),
-            map_dirty: true,
+            hover_point: None,
+            map: NewMapSettings::default().build(),
             show_grid: true,
+            show_rulers: false,
+            show_locks: true,
+            show_triggers: true,
+            show_walkability: false,
+            show_chunk_culling: false,
+            show_lights: true,
+            show_weather: true,
+            show_vegetation: true,
             current_file_path: None,
+            unsaved_changes: false,
+            dirty_regions: DirtyRegions::default(),
+            delta_base: None,
+            delta_dialog_task: None,
             save_dialog_task: None,
             load_dialog_task: None,
             export_dialog_task: None,
@@ -85,21 +890,36 @@ impl Default for EditorState {
     }
 }
 
-#[derive(Resource)]
-struct TerrainVisual {
-    layers: std::collections::HashMap<TileType, TerrainLayer>,
-}
+impl EditorState {
+    /// The active upper layer as a map of its own, or `None` on the ground.
+    pub(crate) fn active_layer_map(&self) -> Option<TileMap> {
+        match self.active_layer {
+            0 => None,
+            layer => self.map.layer_map(layer),
+        }
+    }
 
-impl Default for TerrainVisual {
-    fn default() -> Self {
-        Self {
-            layers: std::collections::HashMap::new(),
+    /// The edit that sets a tile on the active layer.
+    pub(crate) fn layer_edit(&self, x: u32, y: u32, tile: Tile) -> MapEdit {
+        match self.active_layer {
+            0 => MapEdit::SetTile { x, y, tile },
+            layer => MapEdit::SetLayerTile { layer, x, y, tile },
         }
     }
-}
 
-struct TerrainLayer {
-    mesh: Handle<Mesh>,
+    /// Loads the map saved at `path` for editing, remembering the path and,
+    /// for delta saves, the base map they are saved against.
+    pub fn open_map(
+        &mut self,
+        path: PathBuf,
+        edits: &mut EventWriter<MapEdit>,
+    ) -> anyhow::Result<()> {
+        let map = io::load_map(&path)?;
+        edits.send(MapEdit::Replace(Box::new(map)));
+        self.delta_base = io::delta_base(&path).unwrap_or_default();
+        self.current_file_path = Some(path);
+        Ok(())
+    }
 }
 
 #[derive(Default, Reflect, GizmoConfigGroup)]
@@ -112,206 +932,285 @@ fn configure_hover_gizmos(mut configs: ResMut<GizmoConfigStore>) {
 }
 
 fn spawn_editor_assets(
-    mut commands: Commands,
+    mut changed: EventWriter<terrain::TerrainChanged>,
     mut mats: ResMut<Assets<TerrainMaterial>>,
-    mut meshes: ResMut<Assets<Mesh>>,
     asset_server: Res<AssetServer>,
     mut textures: ResMut<TerrainTextureRegistry>,
 ) {
-    let texture_defs = [
-        (
-            TileType::Grass,
-            "Rocky Terrain",
-            "textures/terrain/rocky_terrain_02_diff_1k.png",
-            Some("textures/terrain/rocky_terrain_02_nor_gl_1k_fixed.exr"),
-            Some("textures/terrain/roughness_l8.png"),
-            Some("textures/terrain/rocky_terrain_02_disp_1k.png"),
-        ),
-        (
-            TileType::Dirt,
-            "Worn Soil",
-            "textures/terrain/rocky_terrain_02_diff_1k.png",
-            Some("textures/terrain/rocky_terrain_02_nor_gl_1k_fixed.exr"),
-            Some("textures/terrain/roughness_l8.png"),
-            Some("textures/terrain/rocky_terrain_02_disp_1k.png"),
-        ),
-        (
-            TileType::Sand,
-            "Sandstone",
-            "textures/terrain/rock/aerial_ground_rock_diff_1k.png",
-            Some("textures/terrain/rock/aerial_ground_rock_nor_gl_1k_fixed.exr"),
-            Some("textures/terrain/rock/roughness_in_G.png"),
-            Some("textures/terrain/rock/aerial_ground_rock_disp_1k.png"),
-        ),
-        (
-            TileType::Rock,
-            "Ground Rock",
-            "textures/terrain/rock/aerial_ground_rock_diff_1k.png",
-            Some("textures/terrain/rock/aerial_ground_rock_nor_gl_1k_fixed.exr"),
-            Some("textures/terrain/rock/roughness_in_G.png"),
-            Some("textures/terrain/rock/aerial_ground_rock_disp_1k.png"),
-        ),
-    ];
-
-    for (tile_type, name, base, normal, roughness, dispersion) in texture_defs {
-        textures.load_and_register(
-            tile_type,
-            name,
-            &asset_server,
-            &mut mats,
-            base,
-            normal,
-            roughness,
-            dispersion,
-        );
-    }
-
-    textures.load_and_register_wall(
-        "wall",
-        "Cliff Wall",
-        &asset_server,
-        "textures/terrain/rock/aerial_ground_rock_diff_1k.png",
-        Some("textures/terrain/rock/aerial_ground_rock_nor_gl_1k_fixed.exr"),
-        Some("textures/terrain/rock/roughness_in_G.png"),
-    );
-
-    let mut visual = TerrainVisual::default();
-
-    for entry in textures.iter() {
-        let tile_type = entry.tile_type;
-        let mesh = meshes.add(terrain::empty_mesh());
-        commands.spawn((
-            MaterialMeshBundle {
-                mesh: mesh.clone(),
-                material: entry.material.clone(),
-                transform: Transform::default(),
-                visibility: Visibility::Hidden,
-                ..default()
-            },
-            Name::new(format!("EditorTerrain::{tile_type:?}")),
-        ));
+    textures.load_defaults(&asset_server, &mut mats);
+    changed.send(terrain::TerrainChanged);
+}
 
-        visual.layers.insert(tile_type, TerrainLayer { mesh });
+/// Starts a stroke when the left button goes down over the terrain and
+/// finishes it when the button is released, wherever the cursor is by then.
+fn track_stroke(
+    buttons: Res<ButtonInput<MouseButton>>,
+    state: Res<EditorState>,
+    mut stroke: ResMut<Stroke>,
+    mut finished: EventWriter<StrokeFinished>,
+) {
+    if let Some(tool) = stroke.tool {
+        if buttons.pressed(MouseButton::Left) {
+            return;
+        }
+        finished.send(StrokeFinished {
+            id: stroke.id,
+            tool,
+        });
+        stroke.tool = None;
+    }
+    // `update_hover` clears the hover while the pointer is over a panel.
+    if buttons.just_pressed(MouseButton::Left) && state.hover.is_some() {
+        stroke.id += 1;
+        stroke.tool = Some(state.current_tool);
     }
-
-    commands.insert_resource(visual);
 }
 
-// Raycast to ground plane at chosen elevation (use current_elev for edit layer)
-fn update_hover(
+// Raycast against the terrain surface, walls included
+pub(crate) fn update_hover(
     mut state: ResMut<EditorState>,
+    picker: Res<HeightfieldPicker>,
     windows: Query<&Window>,
     cameras: Query<(&Camera, &GlobalTransform)>,
     mut egui: EguiContexts,
 ) {
-    let (cam, cam_xform) = cameras.single();
     let win = windows.single();
 
     if egui.ctx_mut().wants_pointer_input() {
         state.hover = None;
+        state.hover_point = None;
         return;
     }
 
     let Some(cursor) = win.cursor_position() else {
         state.hover = None;
+        state.hover_point = None;
         return;
     };
 
-    if let Some(ray) = cam.viewport_to_world(cam_xform, cursor) {
-        // --- Step 1: flat projection (y = 0) candidate
-        let guess_hit = ray.origin + ray.direction * ((0.0 - ray.origin.y) / ray.direction.y);
-        let tx = (guess_hit.x / TILE_SIZE).floor() as i32;
-        let ty = (guess_hit.z / TILE_SIZE).floor() as i32;
+    // In split view the cursor may be over either camera's viewport.
+    let Some((cam, cam_xform, viewport)) = cameras.iter().find_map(|(cam, xform)| {
+        let rect = cam.logical_viewport_rect()?;
+        (cam.is_active && rect.contains(cursor)).then_some((cam, xform, rect))
+    }) else {
+        state.hover = None;
+        state.hover_point = None;
+        return;
+    };
 
-        if tx >= 0 && ty >= 0 && (tx as u32) < state.map.width && (ty as u32) < state.map.height {
-            // Look up elevation at this flat tile
-            let idx = (ty as u32 * state.map.width + tx as u32) as usize;
-            let elev = state.map.tiles[idx].elevation as f32 * TILE_HEIGHT;
+    let hit = cam
+        .viewport_to_world(cam_xform, cursor - viewport.min)
+        .and_then(|ray| {
+            // Holes have no surface to hit; fall back to the ground plane so
+            // they can still be painted over.
+            picker
+                .raycast_point(ray.origin, *ray.direction)
+                .or_else(|| ground_plane_tile(&state.map, ray.origin, *ray.direction))
+        });
+    let hover = hit.map(|(tile, _)| tile);
+    if state.hover != hover {
+        state.hover = hover;
+    }
+    let point = hit.map(|(_, point)| point);
+    if state.hover_point != point {
+        state.hover_point = point;
+    }
+}
 
-            // --- Step 2: recompute ray-plane hit at elevation
-            let t = (elev - ray.origin.y) / ray.direction.y;
-            if t.is_finite() && t > 0.0 {
-                let hit = ray.origin + ray.direction * t;
-                let x2 = (hit.x / TILE_SIZE).floor() as i32;
-                let y2 = (hit.z / TILE_SIZE).floor() as i32;
+fn ground_plane_tile(map: &TileMap, origin: Vec3, direction: Vec3) -> Option<((u32, u32), Vec3)> {
+    let t = -origin.y / direction.y;
+    if !t.is_finite() || t < 0.0 {
+        return None;
+    }
+    let hit = origin + direction * t;
+    let (tx, ty) = (
+        (hit.x / map.tile_size).floor() as i32,
+        (hit.z / map.tile_size).floor() as i32,
+    );
+    (tx >= 0 && ty >= 0 && (tx as u32) < map.width && (ty as u32) < map.height)
+        .then_some(((tx as u32, ty as u32), hit))
+}
 
-                // Prefer elevated if it resolves to the same tile coords
-                if x2 == tx && y2 == ty {
-                    state.hover = Some((x2 as u32, y2 as u32));
-                    return;
+/// Holding Shift while painting changes only elevation and holding Ctrl
+/// only the texture; other tile fields are left as they are. The brush is
+/// swept along the line from the previous frame's tile, so fast drags leave
+/// no gaps.
+fn paint_tiles(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<EditorState>,
+    mut last: Local<Option<(u32, u32)>>,
+    mut edits: EventWriter<MapEdit>,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Paint || !buttons.pressed(MouseButton::Left) {
+        *last = None;
+        return;
+    }
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let Some(centre) = state.hover else {
+        return;
+    };
+    let mut centres = last
+        .map(|last| tools::orthogonal_steps(last, centre))
+        .unwrap_or_default();
+    if centres.is_empty() {
+        centres.push(centre);
+    }
+    *last = Some(centre);
+    let mut footprint: Vec<(u32, u32)> = centres
+        .into_iter()
+        .flat_map(|centre| {
+            tools::brush_footprint(&state.map, centre, state.paint_radius, state.paint_shape)
+        })
+        .collect();
+    footprint.sort_unstable();
+    footprint.dedup();
+
+    let elevation_only = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let texture_only = input::erase_held(&keys);
+    let layer = state.active_layer_map();
+    let map = layer.as_ref().unwrap_or(&state.map);
+    for (x, y) in footprint {
+        let current = map.get(x, y);
+        let tile = if elevation_only || texture_only {
+            // Holes have neither a height nor a texture to change.
+            if current.kind == TileKind::Empty {
+                continue;
+            }
+            let mut tile = current.clone();
+            if elevation_only {
+                tile.elevation = state.current_elev;
+                if tile.kind == TileKind::Ramp {
+                    tile.ramp_direction =
+                        ramp_direction_on(map, x, y, tile.elevation, current.ramp_direction);
                 }
             }
-
-            // --- Fallback to flat tile
-            state.hover = Some((tx as u32, ty as u32));
-            return;
+            if texture_only {
+                tile.tile_type = state.current_texture;
+            }
+            (tile != *current).then_some(tile)
+        } else {
+            painted_tile_on(&state, map, x, y)
+        };
+        if let Some(tile) = tile {
+            edits.send(state.layer_edit(x, y, tile));
         }
     }
+}
 
-    // --- Nothing hit
-    state.hover = None;
+/// The tile at (`x`, `y`) as painting it with the current kind, elevation
+/// and texture would leave it, or `None` when it already looks like that.
+pub(crate) fn painted_tile(state: &EditorState, x: u32, y: u32) -> Option<Tile> {
+    painted_tile_on(state, &state.map, x, y)
 }
 
-fn paint_tiles(
+/// Direction a ramp at (`x`, `y`) of `map` rising from `elevation` takes:
+/// `existing` while it still leads somewhere, otherwise the first neighbour
+/// it can climb to.
+fn ramp_direction_on(
+    map: &TileMap,
+    x: u32,
+    y: u32,
+    elevation: i8,
+    existing: Option<RampDirection>,
+) -> Option<RampDirection> {
+    let base = elevation as f32 * map.tile_height();
+    let candidates = ramp_targets(map, x, y, base);
+    existing
+        .filter(|existing| candidates.contains(existing))
+        .or_else(|| candidates.first().copied())
+}
+
+/// `painted_tile` for a tile of `map`, which may hold an upper layer.
+fn painted_tile_on(state: &EditorState, map: &TileMap, x: u32, y: u32) -> Option<Tile> {
+    let kind = state.current_kind;
+    let elevation = state.current_elev;
+    let tile_type = state.current_texture;
+    let current = map.get(x, y);
+    let target_ramp_direction = if kind == TileKind::Ramp {
+        ramp_direction_on(map, x, y, elevation, current.ramp_direction)
+    } else {
+        None
+    };
+
+    let unchanged = current.kind == kind
+        && current.elevation == elevation
+        && current.ramp_direction == target_ramp_direction
+        && current.tile_type == tile_type;
+    (!unchanged).then_some(Tile {
+        kind,
+        elevation,
+        tile_type,
+        x,
+        y,
+        ramp_direction: target_ramp_direction,
+        variation: current.variation,
+        rotation: current.rotation,
+        mirrored: current.mirrored,
+        locked: current.locked,
+        movement_cost: current.movement_cost,
+        corner_offsets: current.corner_offsets,
+    })
+}
+
+/// Turns dragged-over tiles into holes, keeping their other fields so
+/// painting them back restores the texture.
+fn erase_tiles(
     buttons: Res<ButtonInput<MouseButton>>,
-    mut state: ResMut<EditorState>,
+    state: Res<EditorState>,
+    mut edits: EventWriter<MapEdit>,
     mut egui: EguiContexts,
 ) {
+    if state.current_tool != EditorTool::Erase || !buttons.pressed(MouseButton::Left) {
+        return;
+    }
     if egui.ctx_mut().wants_pointer_input() {
         return;
     }
-    if state.current_tool != EditorTool::Paint {
+    let Some((x, y)) = state.hover else {
         return;
+    };
+    let layer = state.active_layer_map();
+    let current = layer.as_ref().unwrap_or(&state.map).get(x, y);
+    if current.kind != TileKind::Empty {
+        let tile = Tile {
+            kind: TileKind::Empty,
+            ramp_direction: None,
+            ..current.clone()
+        };
+        edits.send(state.layer_edit(x, y, tile));
     }
-    if buttons.pressed(MouseButton::Left) {
-        if let Some((x, y)) = state.hover {
-            let kind = state.current_kind;
-            let elevation = state.current_elev;
-            let tile_type = state.current_texture;
-            let state_ref = &mut *state;
-            let current = state_ref.map.get(x, y);
-            let target_ramp_direction = if kind == TileKind::Ramp {
-                let base = elevation as f32 * TILE_HEIGHT;
-                let candidates = ramp_targets(&state_ref.map, x, y, base);
-                if let Some(existing) = current.ramp_direction {
-                    if candidates.contains(&existing) {
-                        Some(existing)
-                    } else {
-                        candidates.first().copied()
-                    }
-                } else {
-                    candidates.first().copied()
-                }
-            } else {
-                None
-            };
-
-            if current.kind != kind
-                || current.elevation != elevation
-                || current.ramp_direction != target_ramp_direction
-                || current.tile_type != tile_type
-            {
-                state_ref.map.set(
-                    x,
-                    y,
-                    Tile {
-                        kind,
-                        elevation,
-                        tile_type,
-                        x,
-                        y,
-                        ramp_direction: target_ramp_direction,
-                    },
-                );
-                state_ref.map_dirty = true;
-            }
-        }
+}
+
+/// Copies the clicked tile's kind, elevation and texture into the paint
+/// settings.
+fn pick_tile(
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut state: ResMut<EditorState>,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Eyedropper || !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
     }
+    let Some((x, y)) = state.hover else {
+        return;
+    };
+    let tile = state.map.get(x, y).clone();
+    state.current_kind = tile.kind;
+    state.current_elev = tile.elevation;
+    state.current_texture = tile.tile_type;
 }
 
 fn rotate_ramps(
     buttons: Res<ButtonInput<MouseButton>>,
-    mut state: ResMut<EditorState>,
+    state: Res<EditorState>,
+    mut edits: EventWriter<MapEdit>,
     mut egui: EguiContexts,
 ) {
     if egui.ctx_mut().wants_pointer_input() {
@@ -327,16 +1226,56 @@ fn rotate_ramps(
     let Some((x, y)) = state.hover else {
         return;
     };
+    if let Some(tile) = rotated_ramp(&state.map, x, y) {
+        edits.send(MapEdit::SetTile { x, y, tile });
+    }
+}
 
-    let base_tile = state.map.get(x, y).clone();
-    if base_tile.kind != TileKind::Ramp {
+fn rotate_textures(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<EditorState>,
+    mut edits: EventWriter<MapEdit>,
+    mut egui: EguiContexts,
+) {
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    if state.current_tool != EditorTool::RotateTexture {
+        return;
+    }
+    if !buttons.just_pressed(MouseButton::Left) {
         return;
     }
 
-    let base_height = base_tile.elevation as f32 * TILE_HEIGHT;
-    let candidates = ramp_targets(&state.map, x, y, base_height);
-    if candidates.is_empty() {
+    let Some((x, y)) = state.hover else {
         return;
+    };
+    let layer_map = state.active_layer_map();
+    let mut tile = layer_map.as_ref().unwrap_or(&state.map).get(x, y).clone();
+    if tile.kind == TileKind::Empty {
+        return;
+    }
+    if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        tile.mirrored = !tile.mirrored;
+    } else {
+        tile.rotation = (tile.rotation + 1) % 4;
+    }
+    edits.send(state.layer_edit(x, y, tile));
+}
+
+/// The ramp at (`x`, `y`) turned to face its next lower neighbour, or `None`
+/// when it is not a ramp or has nowhere else to face.
+pub(crate) fn rotated_ramp(map: &TileMap, x: u32, y: u32) -> Option<Tile> {
+    let base_tile = map.get(x, y).clone();
+    if base_tile.kind != TileKind::Ramp {
+        return None;
+    }
+
+    let base_height = base_tile.elevation as f32 * map.tile_height();
+    let candidates = ramp_targets(map, x, y, base_height);
+    if candidates.is_empty() {
+        return None;
     }
 
     let next_direction = match base_tile.ramp_direction {
@@ -351,18 +1290,17 @@ fn rotate_ramps(
     };
 
     if base_tile.ramp_direction == Some(next_direction) {
-        return;
+        return None;
     }
 
     let mut updated = base_tile;
     updated.ramp_direction = Some(next_direction);
-    state.map.set(x, y, updated);
-    state.map_dirty = true;
+    Some(updated)
 }
 
-fn ramp_targets(map: &TileMap, x: u32, y: u32, base: f32) -> Vec<RampDirection> {
+pub(crate) fn ramp_targets(map: &TileMap, x: u32, y: u32, base: f32) -> Vec<RampDirection> {
     let mut results = Vec::new();
-    for dir in RampDirection::ALL {
+    for dir in RampDirection::RAMPS {
         let (dx, dy) = dir.offset();
         let nx = x as i32 + dx;
         let ny = y as i32 + dy;
@@ -374,7 +1312,10 @@ fn ramp_targets(map: &TileMap, x: u32, y: u32, base: f32) -> Vec<RampDirection>
             continue;
         }
         let neighbor = map.get(ux, uy);
-        let height = neighbor.elevation as f32 * TILE_HEIGHT;
+        if neighbor.kind == TileKind::Empty {
+            continue;
+        }
+        let height = neighbor.elevation as f32 * map.tile_height();
         if height < base {
             results.push(dir);
         }
@@ -382,52 +1323,623 @@ fn ramp_targets(map: &TileMap, x: u32, y: u32, base: f32) -> Vec<RampDirection>
     results
 }
 
-fn rebuild_terrain_mesh(
-    state: Res<EditorState>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    visual: Res<TerrainVisual>,
+/// Ctrl+Z undoes, Ctrl+Y or Ctrl+Shift+Z redoes.
+fn history_shortcuts(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut edits: EventWriter<MapEdit>,
+    mut egui: EguiContexts,
 ) {
-    if !state.map_dirty {
+    if egui.ctx_mut().wants_keyboard_input()
+        || !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+    {
         return;
     }
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if keys.just_pressed(KeyCode::KeyY) || (shift && keys.just_pressed(KeyCode::KeyZ)) {
+        edits.send(MapEdit::Redo);
+    } else if keys.just_pressed(KeyCode::KeyZ) {
+        edits.send(MapEdit::Undo);
+    }
+}
 
-    let mesh_map = terrain::build_map_meshes(&state.map);
-
-    for (tile_type, layer) in &visual.layers {
-        let mesh = mesh_map
-            .get(tile_type)
-            .cloned()
-            .unwrap_or_else(terrain::empty_mesh);
+/// Writes the before (`undo`) or after state of each tile of `entry`.
+fn restore_entry(
+    map: &mut TileMap,
+    heights: &mut terrain::CornerHeightCache,
+    entry: &HistoryEntry,
+    undo: bool,
+) {
+    if let Some(transform) = entry.transform {
+        let transform = if undo { transform.inverse() } else { transform };
+        *map = transform.apply(map);
+        heights.invalidate_all();
+    }
+    for (&(x, y), (before, after)) in &entry.tiles {
+        if x < map.width && y < map.height {
+            map.set(x, y, if undo { before } else { after }.clone());
+            heights.invalidate_tile(x, y);
+        }
+    }
+    for (&(layer, x, y), (before, after)) in &entry.layer_tiles {
+        let idx = map.idx(x, y);
+        if let Some(tile) = map
+            .upper_layers
+            .get_mut(layer - 1)
+            .and_then(|tiles| tiles.get_mut(idx))
+        {
+            *tile = if undo { before } else { after }.clone();
+        }
+    }
+    for (&(x, y), &(before, after)) in &entry.roads {
+        if x < map.width && y < map.height {
+            map.set_road(x, y, if undo { before } else { after });
+        }
+    }
+    for (&(x, y), &(before, after)) in &entry.vegetation {
+        if x < map.width && y < map.height {
+            map.set_vegetation(x, y, if undo { before } else { after });
+        }
+    }
+    for (&(x, y), &(before, after)) in &entry.walkability {
+        if x < map.width && y < map.height {
+            map.set_walkability(x, y, if undo { before } else { after });
+        }
+    }
+}
 
-        if let Some(existing) = meshes.get_mut(&layer.mesh) {
-            *existing = mesh;
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn apply_map_edits(
+    mut edits: EventReader<MapEdit>,
+    mut state: ResMut<EditorState>,
+    mut heights: ResMut<terrain::CornerHeightCache>,
+    mut history: ResMut<EditHistory>,
+    mut local: ResMut<LocalEdits>,
+    stroke: Res<Stroke>,
+    mut finished: EventReader<StrokeFinished>,
+    mut changed: EventWriter<terrain::TerrainChanged>,
+    mut triggers_changed: EventWriter<TriggersChanged>,
+    mut placed: Query<(Entity, &mut PlacedProp)>,
+    mut spatial: Option<ResMut<SpatialIndex>>,
+) {
+    // A stroke released this frame still owns the frame's edits.
+    let (label, stroke_id) = match finished.read().last() {
+        Some(finished) => (finished.tool.label_key(), Some(finished.id)),
+        None => match stroke.tool {
+            Some(tool) => (tool.label_key(), Some(stroke.id)),
+            None => (EDIT_LABEL, None),
+        },
+    };
+    let mut entry = HistoryEntry::new(label, stroke_id);
+    *local = LocalEdits::default();
+    let mut any_changed = false;
+    let mut any_triggers_changed = false;
+    // Whether the map differs from what was last opened or saved.
+    let mut edited = false;
+    for edit in edits.read() {
+        match edit {
+            MapEdit::SetTile { x, y, tile } => {
+                let (x, y) = (*x, *y);
+                if x >= state.map.width || y >= state.map.height {
+                    continue;
+                }
+                // Symmetric painting repeats brush edits at each mirrored
+                // tile; map-wide operations already cover every tile.
+                let symmetry = match stroke_id {
+                    Some(_) => state.symmetry,
+                    None => Symmetry::Off,
+                };
+                let images = symmetry.images(state.map.width, state.map.height, x, y);
+                let copies = std::iter::once(Tile {
+                    x,
+                    y,
+                    ..tile.clone()
+                })
+                .chain(images.into_iter().map(|image| image.apply(tile)));
+                for mut tile in copies {
+                    let (x, y) = (tile.x, tile.y);
+                    let current = state.map.get(x, y);
+                    if current.locked {
+                        continue;
+                    }
+                    tile.locked = false;
+                    if *current == tile {
+                        continue;
+                    }
+                    entry.record(x, y, current.clone(), tile.clone());
+                    state.map.set(x, y, tile);
+                    state.dirty_regions.mark(x, y);
+                    local.tiles.mark(x, y);
+                    heights.invalidate_tile(x, y);
+                    any_changed = true;
+                    edited = true;
+                }
+            }
+            MapEdit::SetLocked { x, y, locked } => {
+                let (x, y) = (*x, *y);
+                if x < state.map.width && y < state.map.height {
+                    let current = state.map.get(x, y);
+                    if current.locked != *locked {
+                        let tile = Tile {
+                            locked: *locked,
+                            ..current.clone()
+                        };
+                        entry.record(x, y, current.clone(), tile.clone());
+                        state.map.set(x, y, tile);
+                        state.dirty_regions.mark(x, y);
+                        local.tiles.mark(x, y);
+                        edited = true;
+                    }
+                }
+            }
+            MapEdit::SetTriggerTile {
+                trigger,
+                x,
+                y,
+                inside,
+            } => {
+                let (x, y) = (*x, *y);
+                if x >= state.map.width || y >= state.map.height {
+                    continue;
+                }
+                let Some(area) = state.map.triggers.get_mut(*trigger) else {
+                    continue;
+                };
+                let moved = if *inside {
+                    area.tiles.insert((x, y))
+                } else {
+                    area.tiles.remove(&(x, y))
+                };
+                local.triggers |= moved;
+                any_triggers_changed |= moved;
+                edited |= moved;
+            }
+            MapEdit::SetTriggers(triggers) => {
+                if state.map.triggers != *triggers {
+                    state.map.triggers = triggers.clone();
+                    local.triggers = true;
+                    any_triggers_changed = true;
+                    edited = true;
+                }
+            }
+            MapEdit::SetStart { player, start } => {
+                let mut starts = state.map.starts.clone();
+                starts.retain(|existing| existing.player != *player);
+                starts.extend(start.clone());
+                starts.sort_by_key(|start| start.player);
+                if state.map.starts != starts {
+                    state.map.starts = starts;
+                    local.starts = true;
+                    edited = true;
+                }
+            }
+            MapEdit::SetLight { x, y, light } => {
+                let (x, y) = (*x, *y);
+                if x >= state.map.width || y >= state.map.height {
+                    continue;
+                }
+                let mut lights = state.map.lights.clone();
+                lights.retain(|existing| (existing.x, existing.y) != (x, y));
+                lights.extend(light.clone().map(|light| MapLight { x, y, ..light }));
+                if state.map.lights != lights {
+                    state.map.lights = lights;
+                    local.lights = true;
+                    edited = true;
+                }
+            }
+            MapEdit::SetLights(lights) => {
+                if state.map.lights != *lights {
+                    state.map.lights = lights.clone();
+                    local.lights = true;
+                    edited = true;
+                }
+            }
+            MapEdit::SetWeather(weather) => {
+                if state.map.weather != *weather {
+                    state.map.weather = weather.clone();
+                    local.weather = true;
+                    edited = true;
+                }
+            }
+            MapEdit::SetTileSize(tile_size) => {
+                let tile_size = tile_size.clamp(MIN_TILE_SIZE, MAX_TILE_SIZE);
+                if state.map.tile_size != tile_size {
+                    state.map.tile_size = tile_size;
+                    state.dirty_regions.mark_all();
+                    local.tile_size = true;
+                    heights.invalidate_all();
+                    any_changed = true;
+                    edited = true;
+                }
+            }
+            MapEdit::SetWaterLevel(level) => {
+                if state.map.water_level != *level {
+                    state.map.water_level = *level;
+                    local.water_level = true;
+                    any_changed = true;
+                    edited = true;
+                }
+            }
+            MapEdit::SetLayerTile { layer, x, y, tile } => {
+                let (layer, x, y) = (*layer, *x, *y);
+                if x >= state.map.width || y >= state.map.height {
+                    continue;
+                }
+                let idx = state.map.idx(x, y);
+                let Some(tiles) = layer
+                    .checked_sub(1)
+                    .and_then(|upper| state.map.upper_layers.get_mut(upper))
+                else {
+                    continue;
+                };
+                let tile = Tile {
+                    x,
+                    y,
+                    locked: false,
+                    ..tile.clone()
+                };
+                if tiles[idx] != tile {
+                    let before = std::mem::replace(&mut tiles[idx], tile.clone());
+                    entry.record_layer(layer, x, y, before, tile);
+                    state.dirty_regions.mark(x, y);
+                    local.tiles.mark(x, y);
+                    any_changed = true;
+                    edited = true;
+                }
+            }
+            MapEdit::SetUpperLayers(layers) => {
+                if state.map.upper_layers != *layers {
+                    state.map.upper_layers.clone_from(layers);
+                    state.dirty_regions.mark_all();
+                    local.upper_layers = true;
+                    any_changed = true;
+                    edited = true;
+                }
+            }
+            MapEdit::SetVegetation { x, y, density } => {
+                let (x, y) = (*x, *y);
+                if x >= state.map.width || y >= state.map.height {
+                    continue;
+                }
+                let before = state.map.vegetation(x, y);
+                if before != *density {
+                    state.map.set_vegetation(x, y, *density);
+                    entry.record_vegetation(x, y, before, *density);
+                    local.tiles.mark(x, y);
+                    edited = true;
+                }
+            }
+            MapEdit::SetRoad { x, y, surface } => {
+                let (x, y) = (*x, *y);
+                if x >= state.map.width || y >= state.map.height {
+                    continue;
+                }
+                let before = state.map.road(x, y);
+                if before != *surface {
+                    state.map.set_road(x, y, *surface);
+                    entry.record_road(x, y, before, *surface);
+                    state.dirty_regions.mark(x, y);
+                    local.tiles.mark(x, y);
+                    any_changed = true;
+                    edited = true;
+                }
+            }
+            MapEdit::SetWalkability { x, y, walkability } => {
+                let (x, y) = (*x, *y);
+                if x >= state.map.width || y >= state.map.height {
+                    continue;
+                }
+                let before = state.map.walkability(x, y);
+                if before != *walkability {
+                    state.map.set_walkability(x, y, *walkability);
+                    entry.record_walkability(x, y, before, *walkability);
+                    local.tiles.mark(x, y);
+                    edited = true;
+                }
+            }
+            MapEdit::PlaceDecal(decal) => {
+                state.map.decals.push(decal.clone());
+                local.decals = true;
+                edited = true;
+            }
+            MapEdit::SetDecals(decals) => {
+                if state.map.decals != *decals {
+                    state.map.decals.clone_from(decals);
+                    local.decals = true;
+                    edited = true;
+                }
+            }
+            MapEdit::PlaceProp(prop) => {
+                state.map.props.push(prop.clone());
+                local.props = true;
+                edited = true;
+            }
+            MapEdit::SetProp { index, prop } => {
+                let index = *index;
+                if index >= state.map.props.len() || prop.as_ref() == Some(&state.map.props[index])
+                {
+                    continue;
+                }
+                local.props = true;
+                edited = true;
+                match prop {
+                    Some(prop) => state.map.props[index] = prop.clone(),
+                    None => {
+                        state.map.props.remove(index);
+                        // The scenes are only respawned later in the frame,
+                        // so keep picking them from landing on the wrong prop.
+                        for (entity, mut placed) in &mut placed {
+                            if placed.0 == index {
+                                if let Some(spatial) = spatial.as_mut() {
+                                    spatial.remove(entity);
+                                }
+                            } else if placed.0 > index {
+                                placed.0 -= 1;
+                            }
+                        }
+                        state.selected_prop = match state.selected_prop {
+                            Some(selected) if selected == index => None,
+                            Some(selected) if selected > index => Some(selected - 1),
+                            selected => selected,
+                        };
+                    }
+                }
+            }
+            MapEdit::Replace(map) | MapEdit::RemoteMap { map, .. } => {
+                // Only opening a map replaces it, and leaves nothing to save.
+                if matches!(edit, MapEdit::Replace(_)) {
+                    local.tiles.mark_all();
+                    state.unsaved_changes = false;
+                    edited = false;
+                } else {
+                    edited = true;
+                }
+                state.map = (**map).clone();
+                state.selected_prop = None;
+                state.dirty_regions.mark_all();
+                heights.invalidate_all();
+                any_changed = true;
+                any_triggers_changed = true;
+                // History of this instance's own map still applies to it.
+                if !matches!(edit, MapEdit::RemoteMap { own: true, .. }) {
+                    entry.tiles.clear();
+                    entry.layer_tiles.clear();
+                    entry.roads.clear();
+                    history.clear();
+                }
+            }
+            MapEdit::Transform(transform) => {
+                // Tiles recorded so far this frame are in the old layout.
+                history.push(std::mem::replace(
+                    &mut entry,
+                    HistoryEntry::new(label, stroke_id),
+                ));
+                state.map = transform.apply(&state.map);
+                state.selected_prop = None;
+                state.dirty_regions.mark_all();
+                local.tiles.mark_all();
+                heights.invalidate_all();
+                any_changed = true;
+                any_triggers_changed = true;
+                edited = true;
+                history.push(HistoryEntry {
+                    transform: Some(*transform),
+                    ..HistoryEntry::new(transform.label_key(), None)
+                });
+            }
+            MapEdit::Undo | MapEdit::Redo => {
+                // Earlier edits this frame come before the one undone.
+                history.push(std::mem::replace(
+                    &mut entry,
+                    HistoryEntry::new(label, stroke_id),
+                ));
+                let undo = matches!(edit, MapEdit::Undo);
+                let restored = if undo {
+                    history.undo.pop_back()
+                } else {
+                    history.redo.pop()
+                };
+                let Some(restored) = restored else {
+                    continue;
+                };
+                restore_entry(&mut state.map, &mut heights, &restored, undo);
+                for regions in [&mut state.dirty_regions, &mut local.tiles] {
+                    if restored.transform.is_some() {
+                        regions.mark_all();
+                    }
+                    for &(x, y) in restored.tile_keys() {
+                        regions.mark(x, y);
+                    }
+                    for &(_, x, y) in restored.layer_tiles.keys() {
+                        regions.mark(x, y);
+                    }
+                }
+                edited = true;
+                if undo {
+                    history.redo.push(restored);
+                } else {
+                    history.undo.push_back(restored);
+                }
+                any_changed = true;
+            }
+            MapEdit::Remote { changes, own } => {
+                // Earlier edits this frame are recorded before theirs are
+                // dropped from the history.
+                history.push(std::mem::replace(
+                    &mut entry,
+                    HistoryEntry::new(label, stroke_id),
+                ));
+                changes.apply(&mut state.map);
+                let tiles: HashSet<(u32, u32)> = changes.tiles().collect();
+                if !own {
+                    history.forget(&tiles, changes.replaces_layers());
+                }
+                for &(x, y) in &tiles {
+                    state.dirty_regions.mark(x, y);
+                    heights.invalidate_tile(x, y);
+                }
+                if changes.rescales() || changes.replaces_layers() {
+                    state.dirty_regions.mark_all();
+                }
+                if changes.rescales() {
+                    heights.invalidate_all();
+                }
+                let props = state.map.props.len();
+                state.selected_prop = state.selected_prop.filter(|&index| index < props);
+                any_changed |= changes.moves_terrain();
+                any_triggers_changed |= changes.moves_triggers();
+                edited |= !changes.is_empty();
+            }
         }
     }
+    history.push(entry);
+    if edited {
+        state.unsaved_changes = true;
+    }
+
+    if any_changed {
+        changed.send(terrain::TerrainChanged);
+    }
+    if any_triggers_changed {
+        triggers_changed.send(TriggersChanged);
+    }
 }
 
-fn mark_map_clean(mut state: ResMut<EditorState>) {
-    if state.map_dirty {
-        state.map_dirty = false;
+/// Mirrors the edited map, or a generator preview of it, into the runtime
+/// terrain whenever it changes. Only the recorded `DirtyRegions` are
+/// copied, and the tiles among them that differ are marked so derived data
+/// is patched in place.
+pub(crate) fn sync_runtime_map(
+    mut state: ResMut<EditorState>,
+    preview: Res<GeneratorPreview>,
+    mut changed: EventReader<terrain::TerrainChanged>,
+    mut runtime_map: ResMut<RuntimeMap>,
+    mut heights: ResMut<terrain::CornerHeightCache>,
+    mut dirty: ResMut<DirtyTiles>,
+) {
+    let map = preview.map().unwrap_or(&state.map);
+    // Decals and props lie on top of the terrain without changing it, so
+    // they are copied as soon as they differ.
+    if runtime_map.map.decals != map.decals {
+        runtime_map.map.decals.clone_from(&map.decals);
+    }
+    if runtime_map.map.props != map.props {
+        runtime_map.map.props.clone_from(&map.props);
+    }
+    if runtime_map.map.walkability != map.walkability {
+        runtime_map.map.walkability.clone_from(&map.walkability);
+    }
+    if changed.is_empty() {
+        return;
+    }
+    changed.clear();
+    // Regions recorded without a terrain change wait for the next one.
+    let regions = std::mem::take(&mut state.dirty_regions);
+    let map = preview.map().unwrap_or(&state.map);
+
+    let runtime = &mut runtime_map.map;
+    if regions.is_all()
+        || runtime.width != map.width
+        || runtime.height != map.height
+        || runtime.tile_size != map.tile_size
+        || runtime.upper_layers.len() != map.upper_layers.len()
+    {
+        *runtime = map.clone();
+        // A generator preview changes the shown map without going through
+        // `apply_map_edits`, so heights are invalidated here as well.
+        heights.invalidate_all();
+        dirty.mark_all();
+        return;
     }
+    runtime.triggers.clone_from(&map.triggers);
+    runtime.starts.clone_from(&map.starts);
+    runtime.lights.clone_from(&map.lights);
+    runtime.weather.clone_from(&map.weather);
+    runtime.vegetation.clone_from(&map.vegetation);
+    runtime.water_level = map.water_level;
+    for (x, y) in regions.tiles() {
+        if x >= map.width || y >= map.height {
+            continue;
+        }
+        let idx = map.idx(x, y);
+        let mut differs = false;
+        if runtime.tiles[idx] != map.tiles[idx] {
+            runtime.tiles[idx].clone_from(&map.tiles[idx]);
+            differs = true;
+        }
+        if runtime.roads.len() == map.roads.len() && runtime.roads[idx] != map.roads[idx] {
+            runtime.roads[idx] = map.roads[idx];
+            differs = true;
+        }
+        for (edited, shown) in map.upper_layers.iter().zip(runtime.upper_layers.iter_mut()) {
+            if shown[idx] != edited[idx] {
+                shown[idx].clone_from(&edited[idx]);
+                differs = true;
+            }
+        }
+        if differs {
+            heights.invalidate_tile(x, y);
+            dirty.mark(x, y);
+        }
+    }
+    if runtime.roads.len() != map.roads.len() {
+        runtime.roads.clone_from(&map.roads);
+    }
+}
+
+/// Outlines the hovered tile, or the whole footprint of the paint brush.
+fn draw_hover_highlight(
+    mut gizmos: Gizmos<HoverGizmoGroup>,
+    state: Res<EditorState>,
+    corner_heights: Res<terrain::CornerHeightCache>,
+) {
+    let Some(centre) = state.hover else {
+        return;
+    };
+    let footprint = if state.current_tool == EditorTool::Paint {
+        tools::brush_footprint(&state.map, centre, state.paint_radius, state.paint_shape)
+    } else {
+        vec![centre]
+    };
+    tools::outline_region(
+        &mut gizmos,
+        &corner_heights,
+        footprint,
+        0.02,
+        Color::srgb(0.0, 1.0, 0.0),
+    );
 }
 
-fn draw_hover_highlight(mut gizmos: Gizmos<HoverGizmoGroup>, state: Res<EditorState>) {
-    if let Some((x, y)) = state.hover {
-        let heights = terrain::tile_corner_heights(&state.map, x, y);
-        let offset = 0.02;
-        let x0 = x as f32 * TILE_SIZE;
-        let x1 = x0 + TILE_SIZE;
-        let z0 = y as f32 * TILE_SIZE;
-        let z1 = z0 + TILE_SIZE;
-        gizmos.linestrip(
-            [
-                Vec3::new(x0, heights[terrain::CORNER_NW] + offset, z0),
-                Vec3::new(x1, heights[terrain::CORNER_NE] + offset, z0),
-                Vec3::new(x1, heights[terrain::CORNER_SE] + offset, z1),
-                Vec3::new(x0, heights[terrain::CORNER_SW] + offset, z1),
-                Vec3::new(x0, heights[terrain::CORNER_NW] + offset, z0),
-            ],
-            Color::srgb(0.0, 1.0, 0.0),
-        );
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::splatmap;
+    use crate::{io, text_map};
+
+    #[test]
+    fn added_layers_paint_and_survive_saving() {
+        let mut state = EditorState {
+            current_texture: TileType::Layer5,
+            ..default()
+        };
+        state.map = TileMap::new(3, 2);
+        let tile = painted_tile(&state, 2, 1).expect("the tile changes layer");
+        state.map.set(2, 1, tile);
+
+        for extension in ["tme", text_map::EXTENSION, io::JSON_EXTENSION] {
+            let path = std::env::temp_dir().join(format!("dprmapedit_layer5.{extension}"));
+            io::save_map(&path, &state.map).unwrap();
+            let loaded = io::load_map(&path);
+            std::fs::remove_file(&path).ok();
+            assert_eq!(
+                loaded.unwrap().get(2, 1).tile_type,
+                TileType::Layer5,
+                "{extension}"
+            );
+        }
+
+        // Layer 5 is weighted by the green channel of the second page.
+        let splat = splatmap::create(&state.map);
+        let texel = splatmap::page_len(splat.texture_descriptor.size) + state.map.idx(2, 1) * 4;
+        assert_eq!(splat.data[texel..texel + 4], [0, 255, 0, 0]);
     }
 }
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
 use std::fs::File;
 use std::io::Write;
 use std::path::{Path, PathBuf};
@@ -12,10 +14,17 @@ use serde_json::json;
 use zip::CompressionMethod;
 use zip::write::FileOptions;
 
+use crate::lightmap::LIGHTMAP_FILE;
+use crate::lights;
+use crate::players;
+use crate::roads::RoadNetwork;
 use crate::terrain;
-use crate::terrain::splatmap;
-use crate::texture::registry::TerrainTextureRegistry;
-use crate::types::{TILE_SIZE, TileMap, TileType};
+use crate::terrain::{NormalSmoothing, splatmap, visibility_mask};
+use crate::texture::registry::{DEFAULT_TEXTURES, DEFAULT_WALL_TEXTURE, TerrainTextureRegistry};
+use crate::types::{
+    CORNER_OFFSET_STEPS, HOLE_DEPTH, LightKind, MAX_CORNER_OFFSET, MAX_ELEVATION, MIN_ELEVATION,
+    MapLight, TileKind, TileMap, TileType, TriggerArea, Weather, WeatherKind,
+};
 
 const VERTEX_BUFFER_TARGET: u32 = 34962;
 const INDEX_BUFFER_TARGET: u32 = 34963;
@@ -52,6 +61,9 @@ struct MetadataTextureEntry {
     normal: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     roughness: Option<String>,
+    /// Index into `ExportMetadata::splatmaps` of the image holding this
+    /// layer's weight.
+    splatmap_page: usize,
     splatmap_channel: usize,
 }
 
@@ -72,12 +84,187 @@ struct ExportMetadata {
     height: u32,
     tile_size: f32,
     textures: Vec<MetadataTextureEntry>,
+    /// First splatmap image, weighting layers 0 to 3.
     splatmap: String,
+    /// Every splatmap image in order, each weighting the next four layers.
+    splatmaps: Vec<String>,
     mesh: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     tilemap: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     wall_texture: Option<MetadataWallTexture>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lightmap: Option<String>,
+    movement_cost: String,
+    navigation: String,
+    visibility_mask: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    players: Vec<MetadataPlayer>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    lights: Vec<MetadataLight>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    zones: Vec<MetadataZone>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weather: Option<MetadataWeather>,
+    /// Grayscale image with one pixel per tile holding its vegetation
+    /// density; absent when no vegetation was painted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vegetation: Option<String>,
+    /// Road graph for traffic to follow; absent when no roads were paved.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    roads: Option<String>,
+}
+
+#[derive(Serialize)]
+struct MetadataPlayer {
+    index: u8,
+    team: u8,
+    start: MetadataStart,
+}
+
+/// Start position in world units at the tile center, with the facing in
+/// degrees clockwise from north (-Z).
+#[derive(Serialize)]
+struct MetadataStart {
+    tile: [u32; 2],
+    position: [f32; 2],
+    facing: f32,
+}
+
+/// A placed light in world units. Colors are sRGB in 0..1, intensity is in
+/// lumens, and spot lights add the direction they shine along and their full
+/// cone angle in degrees.
+#[derive(Serialize)]
+struct MetadataLight {
+    kind: &'static str,
+    tile: [u32; 2],
+    position: [f32; 3],
+    color: [f32; 3],
+    intensity: f32,
+    range: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    direction: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cone: Option<f32>,
+}
+
+impl MetadataLight {
+    fn new(light: &MapLight, heights: &terrain::CornerHeightCache) -> Option<Self> {
+        let spot = light.kind == LightKind::Spot;
+        Some(Self {
+            kind: if spot { "spot" } else { "point" },
+            tile: [light.x, light.y],
+            position: lights::light_position(light, heights)?.to_array(),
+            color: light.color.map(|channel| channel as f32 / 255.0),
+            intensity: light.intensity,
+            range: light.range,
+            direction: spot.then(|| lights::light_direction(light).to_array()),
+            cone: spot.then_some(light.cone),
+        })
+    }
+}
+
+/// A named trigger area for game logic, such as a base or a no-build zone.
+/// The color is the sRGB overlay color in 0..1 and `tiles` lists the covered
+/// tiles row by row.
+#[derive(Serialize)]
+struct MetadataZone {
+    name: String,
+    color: [f32; 3],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    player: Option<u8>,
+    tiles: Vec<[u32; 2]>,
+}
+
+impl MetadataZone {
+    fn new(area: &TriggerArea) -> Self {
+        let mut tiles: Vec<[u32; 2]> = area.tiles.iter().map(|&(x, y)| [x, y]).collect();
+        tiles.sort_by_key(|&[x, y]| (y, x));
+        Self {
+            name: area.name.clone(),
+            color: area.color.map(|channel| channel as f32 / 255.0),
+            player: area.player,
+            tiles,
+        }
+    }
+}
+
+/// The conditions the map is meant to be played in, with the wind direction
+/// in degrees clockwise from north (-Z) and its speed in world units per
+/// second. Left out for clear weather.
+#[derive(Serialize)]
+struct MetadataWeather {
+    kind: &'static str,
+    intensity: f32,
+    wind_direction: f32,
+    wind_speed: f32,
+}
+
+impl MetadataWeather {
+    fn new(weather: &Weather) -> Option<Self> {
+        let kind = match weather.kind {
+            WeatherKind::Clear => return None,
+            WeatherKind::Rain => "rain",
+            WeatherKind::Snow => "snow",
+            WeatherKind::Dust => "dust",
+        };
+        Some(Self {
+            kind,
+            intensity: weather.intensity,
+            wind_direction: weather.wind_direction,
+            wind_speed: weather.wind_speed,
+        })
+    }
+}
+
+/// Row-major effective movement cost per tile, 0 marking holes and tiles
+/// that are not walkable.
+#[derive(Serialize)]
+struct MovementCostGrid {
+    width: u32,
+    height: u32,
+    costs: Vec<u8>,
+}
+
+/// Walkable links between tiles for a game's pathfinding. `edges` holds one
+/// bitmask per tile (bits for north, east, south, west) and `regions` the
+/// connected-component label of each tile, `null` for holes and tiles that
+/// are not walkable.
+#[derive(Serialize)]
+struct NavigationGraph {
+    width: u32,
+    height: u32,
+    edges: Vec<u8>,
+    regions: Vec<Option<u32>>,
+}
+
+impl NavigationGraph {
+    fn from_map(map: &TileMap) -> Self {
+        let reachability = map.reachability();
+        Self {
+            width: map.width,
+            height: map.height,
+            edges: (0..map.height)
+                .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+                .map(|(x, y)| reachability.edge_mask(x, y))
+                .collect(),
+            regions: reachability.regions(),
+        }
+    }
+}
+
+impl MovementCostGrid {
+    fn from_map(map: &TileMap) -> Self {
+        let reachability = map.reachability();
+        Self {
+            width: map.width,
+            height: map.height,
+            costs: (0..map.height)
+                .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+                .map(|(x, y)| reachability.cost(x, y))
+                .collect(),
+        }
+    }
 }
 
 pub fn collect_texture_descriptors(
@@ -86,6 +273,99 @@ pub fn collect_texture_descriptors(
 ) -> Result<(
     Vec<TextureExportDescriptor>,
     Option<WallTextureExportDescriptor>,
+)> {
+    describe_textures(
+        map,
+        |tile_type| {
+            let entry = registry.get(tile_type)?;
+            Some(TexturePaths {
+                diffuse: &entry.diffuse_path,
+                normal: entry.normal_path.as_deref(),
+                roughness: entry.roughness_path.as_deref(),
+            })
+        },
+        registry.wall_texture().map(|entry| {
+            (
+                entry.id.as_str(),
+                TexturePaths {
+                    diffuse: &entry.diffuse_path,
+                    normal: entry.normal_path.as_deref(),
+                    roughness: entry.roughness_path.as_deref(),
+                },
+            )
+        }),
+    )
+}
+
+/// Like `collect_texture_descriptors` for the bundled textures, so maps can
+/// be exported without a running app to register them.
+pub fn default_texture_descriptors(
+    map: &TileMap,
+) -> Result<(
+    Vec<TextureExportDescriptor>,
+    Option<WallTextureExportDescriptor>,
+)> {
+    let (wall_id, _, diffuse, normal, roughness) = DEFAULT_WALL_TEXTURE;
+    describe_textures(
+        map,
+        |tile_type| {
+            DEFAULT_TEXTURES
+                .iter()
+                .find(|texture| texture.tile_type == tile_type)
+                .map(|texture| TexturePaths {
+                    diffuse: texture.base_color,
+                    normal: texture.normal,
+                    roughness: texture.roughness,
+                })
+        },
+        Some((
+            wall_id,
+            TexturePaths {
+                diffuse,
+                normal,
+                roughness,
+            },
+        )),
+    )
+}
+
+/// Asset paths of a texture's maps.
+struct TexturePaths<'a> {
+    diffuse: &'a str,
+    normal: Option<&'a str>,
+    roughness: Option<&'a str>,
+}
+
+impl TexturePaths<'_> {
+    fn resolve(
+        &self,
+    ) -> Result<(
+        TextureFileDescriptor,
+        Option<TextureFileDescriptor>,
+        Option<TextureFileDescriptor>,
+    )> {
+        let file = |path: &str| -> Result<TextureFileDescriptor> {
+            Ok(TextureFileDescriptor {
+                source_path: resolve_asset_path(path)?,
+            })
+        };
+        Ok((
+            file(self.diffuse)?,
+            self.normal.map(file).transpose()?,
+            self.roughness.map(file).transpose()?,
+        ))
+    }
+}
+
+/// Descriptors for the textures of every tile type on the map, looked up
+/// with `paths`, and for the wall texture.
+fn describe_textures<'a>(
+    map: &TileMap,
+    paths: impl Fn(TileType) -> Option<TexturePaths<'a>>,
+    wall: Option<(&str, TexturePaths<'a>)>,
+) -> Result<(
+    Vec<TextureExportDescriptor>,
+    Option<WallTextureExportDescriptor>,
 )> {
     use std::collections::HashSet;
 
@@ -100,28 +380,9 @@ pub fn collect_texture_descriptors(
             continue;
         }
 
-        let entry = registry
-            .get(tile_type)
-            .ok_or_else(|| anyhow!("No terrain texture registered for {tile_type:?}"))?;
-
-        let diffuse = TextureFileDescriptor {
-            source_path: resolve_asset_path(&entry.diffuse_path)?,
-        };
-
-        let normal = match &entry.normal_path {
-            Some(path) => Some(TextureFileDescriptor {
-                source_path: resolve_asset_path(path)?,
-            }),
-            None => None,
-        };
-
-        let roughness = match &entry.roughness_path {
-            Some(path) => Some(TextureFileDescriptor {
-                source_path: resolve_asset_path(path)?,
-            }),
-            None => None,
-        };
-
+        let (diffuse, normal, roughness) = paths(tile_type)
+            .ok_or_else(|| anyhow!("No terrain texture registered for {tile_type:?}"))?
+            .resolve()?;
         descriptors.push(TextureExportDescriptor {
             tile_type,
             identifier: tile_type.identifier().to_string(),
@@ -131,29 +392,11 @@ pub fn collect_texture_descriptors(
         });
     }
 
-    let wall_descriptor = registry
-        .wall_texture()
-        .map(|entry| -> Result<WallTextureExportDescriptor> {
-            let diffuse = TextureFileDescriptor {
-                source_path: resolve_asset_path(&entry.diffuse_path)?,
-            };
-
-            let normal = match &entry.normal_path {
-                Some(path) => Some(TextureFileDescriptor {
-                    source_path: resolve_asset_path(path)?,
-                }),
-                None => None,
-            };
-
-            let roughness = match &entry.roughness_path {
-                Some(path) => Some(TextureFileDescriptor {
-                    source_path: resolve_asset_path(path)?,
-                }),
-                None => None,
-            };
-
+    let wall_descriptor = wall
+        .map(|(id, paths)| -> Result<WallTextureExportDescriptor> {
+            let (diffuse, normal, roughness) = paths.resolve()?;
             Ok(WallTextureExportDescriptor {
-                identifier: entry.id.clone(),
+                identifier: id.to_string(),
                 diffuse,
                 normal,
                 roughness,
@@ -164,13 +407,24 @@ pub fn collect_texture_descriptors(
     Ok((descriptors, wall_descriptor))
 }
 
+/// The files of an exported map, by their path in the package.
+struct PackageContents {
+    /// Everything but the metadata and textures, in package order.
+    files: Vec<(String, Vec<u8>)>,
+    metadata: ExportMetadata,
+    textures: Vec<(String, Vec<u8>)>,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn export_package(
     output_path: &Path,
     map: TileMap,
     map_name: String,
     textures: Vec<TextureExportDescriptor>,
     wall_texture: Option<WallTextureExportDescriptor>,
-    splat_png: Vec<u8>,
+    splat_pngs: Vec<Vec<u8>>,
+    lightmap_png: Option<Vec<u8>>,
+    smoothing: NormalSmoothing,
 ) -> Result<()> {
     if let Some(parent) = output_path.parent() {
         if !parent.as_os_str().is_empty() {
@@ -180,48 +434,35 @@ pub fn export_package(
         }
     }
 
-    let mesh = terrain::build_combined_mesh(&map);
-    let mesh_bytes = mesh_to_glb(&mesh)?;
-
-    let tilemap_json = serde_json::to_vec_pretty(&map)?;
-
-    let (metadata, texture_files, wall_texture_metadata) =
-        build_metadata_and_files(&textures, wall_texture)?;
-    let metadata = ExportMetadata {
-        name: map_name,
-        width: map.width,
-        height: map.height,
-        tile_size: TILE_SIZE,
-        textures: metadata,
-        splatmap: "splatmap.png".to_string(),
-        mesh: "mesh.glb".to_string(),
-        tilemap: Some("tilemap.json".to_string()),
-        wall_texture: wall_texture_metadata,
-    };
-    let metadata_json = serde_json::to_vec_pretty(&metadata)?;
+    let contents = build_package(
+        map,
+        map_name,
+        textures,
+        wall_texture,
+        splat_pngs,
+        lightmap_png,
+        smoothing,
+    )?;
+    let metadata_json = serde_json::to_vec_pretty(&contents.metadata)?;
 
     let file = File::create(output_path)
         .with_context(|| format!("Failed to create export file {}", output_path.display()))?;
     let mut zip = zip::ZipWriter::new(file);
     let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
 
-    zip.start_file("tilemap.json", options)?;
-    zip.write_all(&tilemap_json)?;
-
-    zip.start_file("mesh.glb", options)?;
-    zip.write_all(&mesh_bytes)?;
-
-    zip.start_file("splatmap.png", options)?;
-    zip.write_all(&splat_png)?;
+    for (path, bytes) in &contents.files {
+        zip.start_file(path, options)?;
+        zip.write_all(bytes)?;
+    }
 
     zip.start_file("metadata.json", options)?;
     zip.write_all(&metadata_json)?;
 
-    if !texture_files.is_empty() {
+    if !contents.textures.is_empty() {
         zip.add_directory("textures/", options)?;
     }
 
-    for (path, bytes) in texture_files {
+    for (path, bytes) in contents.textures {
         zip.start_file(path, options)?;
         zip.write_all(&bytes)?;
     }
@@ -230,6 +471,368 @@ pub fn export_package(
     Ok(())
 }
 
+/// Writes the files of `export_package` into `output_dir` instead of a zip,
+/// for build pipelines that consume them directly. The metadata is written
+/// both as `metadata.json` and as `metadata.ron`.
+#[allow(clippy::too_many_arguments)]
+pub fn export_directory(
+    output_dir: &Path,
+    map: TileMap,
+    map_name: String,
+    textures: Vec<TextureExportDescriptor>,
+    wall_texture: Option<WallTextureExportDescriptor>,
+    splat_pngs: Vec<Vec<u8>>,
+    lightmap_png: Option<Vec<u8>>,
+    smoothing: NormalSmoothing,
+) -> Result<()> {
+    let contents = build_package(
+        map,
+        map_name,
+        textures,
+        wall_texture,
+        splat_pngs,
+        lightmap_png,
+        smoothing,
+    )?;
+    let metadata_json = serde_json::to_vec_pretty(&contents.metadata)?;
+    let metadata_ron =
+        ron::ser::to_string_pretty(&contents.metadata, ron::ser::PrettyConfig::default())?;
+
+    let metadata = [
+        ("metadata.json".to_string(), metadata_json),
+        ("metadata.ron".to_string(), metadata_ron.into_bytes()),
+    ];
+    write_files(
+        output_dir,
+        contents
+            .files
+            .iter()
+            .chain(&metadata)
+            .chain(&contents.textures),
+    )
+}
+
+/// Writes the combined terrain mesh as glTF for other engines and DCC tools.
+/// A `.glb` path gets a binary file; any other gets a `.gltf` document with
+/// its buffer in a `.bin` beside it.
+///
+/// Each texture layer becomes a material, its images copied into a
+/// `textures` folder next to the output along with the splatmap pages. The
+/// mesh uses the first layer's material; the mesh's `extras` list the layers
+/// and splatmaps needed to blend the rest, and what `TEXCOORD_1` holds.
+pub fn export_gltf(
+    output_path: &Path,
+    map: &TileMap,
+    textures: Vec<TextureExportDescriptor>,
+    splat_pngs: Vec<Vec<u8>>,
+    smoothing: NormalSmoothing,
+) -> Result<()> {
+    let file_name = output_path
+        .file_name()
+        .ok_or_else(|| anyhow!("Export path {} has no file name", output_path.display()))?
+        .to_string_lossy()
+        .into_owned();
+    let output_dir = output_path.parent().unwrap_or(Path::new(""));
+
+    let heights = terrain::CornerHeightCache::from_map(map);
+    let mesh = terrain::build_combined_mesh(map, &heights, smoothing);
+    let (mut root, bin) = mesh_to_gltf(&mesh)?;
+
+    let (entries, mut files, _) = build_metadata_and_files(&textures, None)?;
+    add_gltf_materials(&mut root, &entries);
+    root["meshes"][0]["extras"] = json!({
+        "layers": entries.iter().map(|entry| &entry.id).collect::<Vec<_>>(),
+        "splatmaps": (0..splat_pngs.len()).map(splatmap_file).collect::<Vec<_>>(),
+        "texcoord1": "x: texture layer, y: cliff seam height",
+    });
+    for (page, splat_png) in splat_pngs.into_iter().enumerate() {
+        files.push((splatmap_file(page), splat_png));
+    }
+
+    let binary = output_path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("glb"));
+    if binary {
+        files.push((file_name, glb_bytes(&root, bin)?));
+    } else {
+        let stem = output_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let bin_name = format!("{stem}.bin");
+        root["buffers"][0]["uri"] = json!(bin_name);
+        files.push((bin_name, bin));
+        files.push((file_name, serde_json::to_vec_pretty(&root)?));
+    }
+    write_files(output_dir, &files)
+}
+
+/// Gives `root` a material per texture layer, in layer order, with images
+/// referenced by their paths in `entries`, and puts the mesh in the first.
+/// Metalness is zero, so the roughness images work as glTF's
+/// metallic-roughness texture, which reads roughness from green. Images glTF
+/// cannot hold, anything but PNG and JPEG, are left out.
+fn add_gltf_materials(root: &mut serde_json::Value, entries: &[MetadataTextureEntry]) {
+    if entries.is_empty() {
+        return;
+    }
+    let mut images = Vec::new();
+    let mut materials = Vec::new();
+    for entry in entries {
+        let mut image = |uri: &str| {
+            let supported = Path::new(uri).extension().is_some_and(|extension| {
+                ["png", "jpg", "jpeg"]
+                    .iter()
+                    .any(|supported| extension.eq_ignore_ascii_case(supported))
+            });
+            supported.then(|| {
+                images.push(json!({ "uri": uri }));
+                json!({ "index": images.len() - 1 })
+            })
+        };
+        let mut material = json!({
+            "name": entry.id,
+            "pbrMetallicRoughness": {
+                "metallicFactor": 0.0,
+            },
+            "extras": {
+                "splatmapPage": entry.splatmap_page,
+                "splatmapChannel": entry.splatmap_channel,
+            },
+        });
+        if let Some(base_color) = image(&entry.diffuse) {
+            material["pbrMetallicRoughness"]["baseColorTexture"] = base_color;
+        }
+        if let Some(normal) = entry.normal.as_deref().and_then(&mut image) {
+            material["normalTexture"] = normal;
+        }
+        if let Some(roughness) = entry.roughness.as_deref().and_then(&mut image) {
+            material["pbrMetallicRoughness"]["metallicRoughnessTexture"] = roughness;
+        }
+        materials.push(material);
+    }
+    let textures: Vec<_> = (0..images.len())
+        .map(|source| json!({ "source": source }))
+        .collect();
+    root["images"] = json!(images);
+    root["textures"] = json!(textures);
+    root["materials"] = json!(materials);
+    root["meshes"][0]["primitives"][0]["material"] = json!(0);
+}
+
+/// Writes the terrain as Wavefront OBJ with an MTL file beside it, for a
+/// quick look in Blender. Faces are grouped by texture layer, each group
+/// using a material named after its layer whose images are copied into a
+/// `textures` folder next to the output.
+pub fn export_obj(
+    output_path: &Path,
+    map: &TileMap,
+    textures: Vec<TextureExportDescriptor>,
+    smoothing: NormalSmoothing,
+) -> Result<()> {
+    let file_name = output_path
+        .file_name()
+        .ok_or_else(|| anyhow!("Export path {} has no file name", output_path.display()))?
+        .to_string_lossy()
+        .into_owned();
+    let output_dir = output_path.parent().unwrap_or(Path::new(""));
+    let mtl_name = Path::new(&file_name)
+        .with_extension("mtl")
+        .to_string_lossy()
+        .into_owned();
+
+    let (entries, mut files, _) = build_metadata_and_files(&textures, None)?;
+    let entries: HashMap<TileType, &MetadataTextureEntry> = textures
+        .iter()
+        .map(|descriptor| descriptor.tile_type)
+        .zip(&entries)
+        .collect();
+
+    let heights = terrain::CornerHeightCache::from_map(map);
+    let mut meshes: Vec<_> = terrain::build_map_meshes(map, &heights, smoothing)
+        .into_iter()
+        .collect();
+    meshes.sort_by_key(|(tile_type, _)| tile_type.as_index());
+
+    let mut obj = format!("# {file_name}\nmtllib {mtl_name}\n");
+    let mut mtl = String::new();
+    // OBJ indices are 1-based and count every vertex written so far.
+    let mut first_vertex = 1;
+    for (tile_type, mesh) in &meshes {
+        let positions = extract_vec3(mesh, Mesh::ATTRIBUTE_POSITION, "POSITION")?;
+        let normals = extract_vec3(mesh, Mesh::ATTRIBUTE_NORMAL, "NORMAL")?;
+        let texcoords = extract_vec2(mesh, Mesh::ATTRIBUTE_UV_0, "TEXCOORD_0")?;
+        let indices = extract_indices(mesh)?;
+        ensure!(
+            positions.len() == normals.len() && positions.len() == texcoords.len(),
+            "Mesh export requires matching position, normal and UV counts"
+        );
+        if indices.is_empty() {
+            continue;
+        }
+
+        let entry = entries.get(tile_type);
+        let material = entry.map_or(tile_type.identifier(), |entry| &entry.id);
+        writeln!(obj, "g {material}\nusemtl {material}")?;
+        for [x, y, z] in &positions {
+            writeln!(obj, "v {x} {y} {z}")?;
+        }
+        // OBJ texture coordinates start at the bottom left.
+        for [u, v] in &texcoords {
+            writeln!(obj, "vt {u} {}", 1.0 - v)?;
+        }
+        for [x, y, z] in &normals {
+            writeln!(obj, "vn {x} {y} {z}")?;
+        }
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|corner| triangle[corner] as usize + first_vertex);
+            writeln!(obj, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+        }
+        first_vertex += positions.len();
+
+        writeln!(mtl, "newmtl {material}\nKd 1 1 1\nKs 0 0 0\nillum 1")?;
+        if let Some(entry) = entry {
+            writeln!(mtl, "map_Kd {}", entry.diffuse)?;
+            if let Some(normal) = &entry.normal {
+                writeln!(mtl, "map_Bump {normal}")?;
+            }
+            if let Some(roughness) = &entry.roughness {
+                writeln!(mtl, "map_Pr {roughness}")?;
+            }
+        }
+        mtl.push('\n');
+    }
+
+    files.push((mtl_name, mtl.into_bytes()));
+    files.push((file_name, obj.into_bytes()));
+    write_files(output_dir, &files)
+}
+
+/// Writes `files` under `output_dir` by their relative paths, creating
+/// folders as needed.
+fn write_files<'a>(
+    output_dir: &Path,
+    files: impl IntoIterator<Item = &'a (String, Vec<u8>)>,
+) -> Result<()> {
+    for (path, bytes) in files {
+        let target = output_dir.join(path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create export directory {}", parent.display())
+            })?;
+        }
+        std::fs::write(&target, bytes)
+            .with_context(|| format!("Failed to write {}", target.display()))?;
+    }
+    Ok(())
+}
+
+fn build_package(
+    map: TileMap,
+    map_name: String,
+    textures: Vec<TextureExportDescriptor>,
+    wall_texture: Option<WallTextureExportDescriptor>,
+    splat_pngs: Vec<Vec<u8>>,
+    lightmap_png: Option<Vec<u8>>,
+    smoothing: NormalSmoothing,
+) -> Result<PackageContents> {
+    players::validate_players(&map)?;
+
+    let heights = terrain::CornerHeightCache::from_map(&map);
+    let mesh = terrain::build_combined_mesh(&map, &heights, smoothing);
+    let mesh_bytes = mesh_to_glb(&mesh)?;
+
+    let tilemap_json = serde_json::to_vec_pretty(&map)?;
+    let movement_cost_json = serde_json::to_vec(&MovementCostGrid::from_map(&map))?;
+    let navigation_json = serde_json::to_vec(&NavigationGraph::from_map(&map))?;
+    let visibility_png = encode_rgba8_png(&visibility_mask::create(&map))?;
+    let road_network = RoadNetwork::from_map(&map, &heights);
+    let road_network_json = if road_network.nodes.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_vec_pretty(&road_network)?)
+    };
+    let vegetation_png = if map.vegetation.is_empty() {
+        None
+    } else {
+        Some(build_vegetation_png(&map)?)
+    };
+
+    let (metadata, texture_files, wall_texture_metadata) =
+        build_metadata_and_files(&textures, wall_texture)?;
+    let metadata = ExportMetadata {
+        name: map_name,
+        width: map.width,
+        height: map.height,
+        tile_size: map.tile_size,
+        textures: metadata,
+        splatmap: splatmap_file(0),
+        splatmaps: (0..splat_pngs.len()).map(splatmap_file).collect(),
+        mesh: "mesh.glb".to_string(),
+        tilemap: Some("tilemap.json".to_string()),
+        wall_texture: wall_texture_metadata,
+        lightmap: lightmap_png.as_ref().map(|_| LIGHTMAP_FILE.to_string()),
+        movement_cost: "movement_cost.json".to_string(),
+        navigation: "navigation.json".to_string(),
+        visibility_mask: "visibility.png".to_string(),
+        players: map
+            .starts
+            .iter()
+            .map(|start| MetadataPlayer {
+                index: start.player,
+                team: start.team,
+                start: MetadataStart {
+                    tile: [start.x, start.y],
+                    position: [
+                        (start.x as f32 + 0.5) * map.tile_size,
+                        (start.y as f32 + 0.5) * map.tile_size,
+                    ],
+                    facing: start.facing,
+                },
+            })
+            .collect(),
+        lights: map
+            .lights
+            .iter()
+            .filter_map(|light| MetadataLight::new(light, &heights))
+            .collect(),
+        zones: map.triggers.iter().map(MetadataZone::new).collect(),
+        weather: MetadataWeather::new(&map.weather),
+        vegetation: vegetation_png
+            .as_ref()
+            .map(|_| "vegetation.png".to_string()),
+        roads: road_network_json
+            .as_ref()
+            .map(|_| "road_network.json".to_string()),
+    };
+
+    let mut files = vec![
+        ("tilemap.json".to_string(), tilemap_json),
+        ("movement_cost.json".to_string(), movement_cost_json),
+        ("navigation.json".to_string(), navigation_json),
+    ];
+    if let Some(road_network_json) = road_network_json {
+        files.push(("road_network.json".to_string(), road_network_json));
+    }
+    files.push(("visibility.png".to_string(), visibility_png));
+    files.push(("mesh.glb".to_string(), mesh_bytes));
+    for (page, splat_png) in splat_pngs.into_iter().enumerate() {
+        files.push((splatmap_file(page), splat_png));
+    }
+    if let Some(lightmap_png) = lightmap_png {
+        files.push((LIGHTMAP_FILE.to_string(), lightmap_png));
+    }
+    if let Some(vegetation_png) = vegetation_png {
+        files.push(("vegetation.png".to_string(), vegetation_png));
+    }
+
+    Ok(PackageContents {
+        files,
+        metadata,
+        textures: texture_files,
+    })
+}
+
 fn build_metadata_and_files(
     textures: &[TextureExportDescriptor],
     wall_texture: Option<WallTextureExportDescriptor>,
@@ -268,7 +871,8 @@ fn build_metadata_and_files(
             diffuse: diffuse_path,
             normal: normal_path,
             roughness: roughness_path,
-            splatmap_channel: descriptor.tile_type.as_index(),
+            splatmap_page: descriptor.tile_type.as_index() / splatmap::LAYERS_PER_PAGE,
+            splatmap_channel: descriptor.tile_type.as_index() % splatmap::LAYERS_PER_PAGE,
         });
     }
 
@@ -359,7 +963,7 @@ fn texture_target_path(id: &str, kind: &str, source: &Path) -> String {
     format!("textures/{file_name}")
 }
 
-fn resolve_asset_path(path: &str) -> Result<PathBuf> {
+pub(crate) fn resolve_asset_path(path: &str) -> Result<PathBuf> {
     let raw_path = Path::new(path);
     let resolved = if raw_path.is_absolute() {
         raw_path.to_path_buf()
@@ -374,12 +978,53 @@ fn resolve_asset_path(path: &str) -> Result<PathBuf> {
     Ok(resolved)
 }
 
-pub fn encode_splatmap_png(image: &Image) -> Result<Vec<u8>> {
+/// File name of one splatmap page in the package; the first keeps the name
+/// used before maps had more than one.
+fn splatmap_file(page: usize) -> String {
+    if page == 0 {
+        "splatmap.png".to_string()
+    } else {
+        format!("splatmap_{page}.png")
+    }
+}
+
+/// One PNG per page of the splatmap array.
+pub fn encode_splatmap_pngs(image: &Image) -> Result<Vec<Vec<u8>>> {
     ensure!(
         image.texture_descriptor.format == bevy::render::render_resource::TextureFormat::Rgba8Unorm,
         "Splatmap must be RGBA8 format for export"
     );
+    let size = image.texture_descriptor.size;
+    let page_len = splatmap::page_len(size);
+    ensure!(
+        image.data.len() == page_len * size.depth_or_array_layers as usize,
+        "Splatmap data does not match its size"
+    );
+    image
+        .data
+        .chunks_exact(page_len)
+        .map(|page| {
+            let mut buffer = Vec::new();
+            PngEncoder::new(&mut buffer).write_image(
+                page,
+                size.width,
+                size.height,
+                ExtendedColorType::Rgba8,
+            )?;
+            Ok(buffer)
+        })
+        .collect()
+}
+
+pub fn encode_lightmap_png(image: &Image) -> Result<Vec<u8>> {
+    ensure!(
+        image.texture_descriptor.format == bevy::render::render_resource::TextureFormat::Rgba8Unorm,
+        "Lightmap must be RGBA8 format for export"
+    );
+    encode_rgba8_png(image)
+}
 
+fn encode_rgba8_png(image: &Image) -> Result<Vec<u8>> {
     let width = image.texture_descriptor.size.width;
     let height = image.texture_descriptor.size.height;
     let mut buffer = Vec::new();
@@ -392,12 +1037,98 @@ pub fn encode_splatmap_png(image: &Image) -> Result<Vec<u8>> {
     Ok(buffer)
 }
 
-pub fn build_map_splatmap_png(map: &TileMap) -> Result<Vec<u8>> {
+/// Vegetation density as an 8-bit grayscale PNG, one pixel per tile with
+/// row 0 at tile row 0. Holes are always 0.
+fn build_vegetation_png(map: &TileMap) -> Result<Vec<u8>> {
+    let densities: Vec<u8> = (0..map.height)
+        .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            if map.get(x, y).kind == TileKind::Empty {
+                0
+            } else {
+                map.vegetation(x, y)
+            }
+        })
+        .collect();
+    let mut buffer = Vec::new();
+    PngEncoder::new(&mut buffer).write_image(
+        &densities,
+        map.width,
+        map.height,
+        ExtendedColorType::L8,
+    )?;
+    Ok(buffer)
+}
+
+pub fn build_map_splatmap_pngs(map: &TileMap) -> Result<Vec<Vec<u8>>> {
     let image = splatmap::create(map);
-    encode_splatmap_png(&image)
+    encode_splatmap_pngs(&image)
+}
+
+/// Elevation levels at black and white in `build_heightmap_png`: from the
+/// floor of holes to the highest corner the editor can raise. The range is
+/// fixed so heightmaps of different maps line up.
+pub const HEIGHTMAP_MIN_LEVEL: i8 = MIN_ELEVATION - HOLE_DEPTH;
+pub const HEIGHTMAP_MAX_LEVEL: i8 = MAX_ELEVATION + MAX_CORNER_OFFSET / CORNER_OFFSET_STEPS;
+
+/// `terrain::corner_grid` as a 16-bit grayscale PNG, one pixel per tile
+/// corner. A pixel's height is `HEIGHTMAP_MIN_LEVEL` plus its value over
+/// 65535 times the level range, in units of `map.tile_height()`.
+pub fn build_heightmap_png(map: &TileMap) -> Result<Vec<u8>> {
+    let heights = terrain::CornerHeightCache::from_map(map);
+    let min = HEIGHTMAP_MIN_LEVEL as f32 * map.tile_height();
+    let range = (HEIGHTMAP_MAX_LEVEL - HEIGHTMAP_MIN_LEVEL) as f32 * map.tile_height();
+    let pixels: Vec<u8> = terrain::corner_grid(map, &heights)
+        .into_iter()
+        .flat_map(|height| {
+            let value = ((height - min) / range).clamp(0.0, 1.0) * u16::MAX as f32;
+            (value.round() as u16).to_ne_bytes()
+        })
+        .collect();
+    let mut buffer = Vec::new();
+    PngEncoder::new(&mut buffer).write_image(
+        &pixels,
+        map.width + 1,
+        map.height + 1,
+        ExtendedColorType::L16,
+    )?;
+    Ok(buffer)
+}
+
+/// Writes the map's elevation to `output_path` with `build_heightmap_png`.
+pub fn export_heightmap(output_path: &Path, map: &TileMap) -> Result<()> {
+    std::fs::write(output_path, build_heightmap_png(map)?)
+        .with_context(|| format!("Failed to write {}", output_path.display()))
+}
+
+/// Writes the map's splatmap to `output_path` as an RGBA PNG, one channel
+/// per texture layer. Maps with more layers than fit in one image get a
+/// further PNG per page beside it, named with a `_1`, `_2`, ... suffix.
+pub fn export_splatmap(output_path: &Path, map: &TileMap) -> Result<()> {
+    let stem = output_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    for (page, png) in build_map_splatmap_pngs(map)?.into_iter().enumerate() {
+        let target = if page == 0 {
+            output_path.to_path_buf()
+        } else {
+            output_path.with_file_name(format!("{stem}_{page}.png"))
+        };
+        std::fs::write(&target, png)
+            .with_context(|| format!("Failed to write {}", target.display()))?;
+    }
+    Ok(())
 }
 
 fn mesh_to_glb(mesh: &Mesh) -> Result<Vec<u8>> {
+    let (root, bin) = mesh_to_gltf(mesh)?;
+    glb_bytes(&root, bin)
+}
+
+/// The glTF document for `mesh` as a single node, and its binary buffer. The
+/// buffer has no `uri`, as the GLB container expects.
+fn mesh_to_gltf(mesh: &Mesh) -> Result<(serde_json::Value, Vec<u8>)> {
     let positions = extract_vec3(mesh, Mesh::ATTRIBUTE_POSITION, "POSITION")?;
     let normals = extract_vec3(mesh, Mesh::ATTRIBUTE_NORMAL, "NORMAL")?;
     let texcoords = extract_vec2(mesh, Mesh::ATTRIBUTE_UV_0, "TEXCOORD_0")?;
@@ -440,7 +1171,7 @@ fn mesh_to_glb(mesh: &Mesh) -> Result<Vec<u8>> {
         .transpose()?;
     let index_accessor = writer.push_indices(&indices)?;
 
-    let (mut bin, buffer_views, accessors) = writer.finish();
+    let (bin, buffer_views, accessors) = writer.finish();
 
     let mut attributes = serde_json::Map::new();
     attributes.insert("POSITION".to_string(), json!(position_accessor));
@@ -483,8 +1214,11 @@ fn mesh_to_glb(mesh: &Mesh) -> Result<Vec<u8>> {
         }],
         "scene": 0,
     });
+    Ok((root, bin))
+}
 
-    let mut json_bytes = serde_json::to_vec(&root)?;
+fn glb_bytes(root: &serde_json::Value, mut bin: Vec<u8>) -> Result<Vec<u8>> {
+    let mut json_bytes = serde_json::to_vec(root)?;
     pad_to_four(&mut json_bytes, b' ');
     pad_to_four(&mut bin, 0);
 
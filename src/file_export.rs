@@ -0,0 +1,202 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use bevy::prelude::*;
+use bevy::tasks::{IoTaskPool, Task, block_on};
+use rfd::AsyncFileDialog;
+
+use crate::editor::EditorState;
+use crate::export;
+use crate::localization::Localization;
+use crate::notifications::Notify;
+use crate::terrain::NormalSmoothing;
+use crate::texture::registry::TerrainTextureRegistry;
+
+/// Exports of the current map to formats other tools read, requested from
+/// the File menu with an `ExportFile` event.
+pub struct FileExportPlugin;
+
+impl Plugin for FileExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ExportFile>()
+            .init_resource::<FileExport>()
+            .add_systems(
+                Update,
+                (open_export_dialog, start_file_export, poll_file_export).chain(),
+            );
+    }
+}
+
+/// Asks for a path, then writes the map in the given format there.
+#[derive(Event, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExportFile {
+    /// The terrain mesh and its materials as `.gltf` or `.glb`.
+    Gltf,
+    /// The terrain as Wavefront `.obj` and `.mtl`, one group per layer.
+    Obj,
+    /// The elevation of every tile corner as a 16-bit grayscale `.png`.
+    Heightmap,
+    /// The texture layer weights as an RGBA `.png`.
+    Splatmap,
+}
+
+impl ExportFile {
+    fn title_key(self) -> &'static str {
+        match self {
+            ExportFile::Gltf => "dialog.export_gltf",
+            ExportFile::Obj => "dialog.export_obj",
+            ExportFile::Heightmap => "dialog.export_heightmap",
+            ExportFile::Splatmap => "dialog.export_splatmap",
+        }
+    }
+
+    fn filter_key(self) -> &'static str {
+        match self {
+            ExportFile::Gltf => "dialog.gltf_filter",
+            ExportFile::Obj => "dialog.obj_filter",
+            ExportFile::Heightmap | ExportFile::Splatmap => "dialog.png_filter",
+        }
+    }
+
+    /// Added to the map's name in the suggested file name, to tell apart
+    /// exports sharing an extension.
+    fn file_suffix(self) -> &'static str {
+        match self {
+            ExportFile::Gltf | ExportFile::Obj => "",
+            ExportFile::Heightmap => "_height",
+            ExportFile::Splatmap => "_splat",
+        }
+    }
+
+    /// Accepted extensions, the first being the default.
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            ExportFile::Gltf => &["glb", "gltf"],
+            ExportFile::Obj => &["obj"],
+            ExportFile::Heightmap | ExportFile::Splatmap => &["png"],
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct FileExport {
+    dialog_task: Option<Task<Option<(ExportFile, PathBuf)>>>,
+    write_task: Option<Task<Result<PathBuf>>>,
+}
+
+impl FileExport {
+    fn busy(&self) -> bool {
+        self.dialog_task.is_some() || self.write_task.is_some()
+    }
+}
+
+fn open_export_dialog(
+    mut requests: EventReader<ExportFile>,
+    mut export_state: ResMut<FileExport>,
+    state: Res<EditorState>,
+    localization: Res<Localization>,
+) {
+    let Some(&format) = requests.read().last() else {
+        return;
+    };
+    if export_state.busy() {
+        return;
+    }
+    let extension = format.extensions()[0];
+    let suffix = format.file_suffix();
+    let mut dialog = AsyncFileDialog::new()
+        .set_title(localization.tr(format.title_key()))
+        .add_filter(localization.tr(format.filter_key()), format.extensions());
+    match state.current_file_path.as_ref() {
+        Some(path) => {
+            if let Some(parent) = path.parent() {
+                dialog = dialog.set_directory(parent);
+            }
+            if let Some(stem) = path.file_stem().and_then(|name| name.to_str()) {
+                dialog = dialog.set_file_name(format!("{stem}{suffix}.{extension}"));
+            }
+        }
+        None => dialog = dialog.set_file_name(format!("map{suffix}.{extension}")),
+    }
+    export_state.dialog_task = Some(IoTaskPool::get().spawn(async move {
+        let file = dialog.save_file().await?;
+        Some((format, file.path().to_path_buf()))
+    }));
+}
+
+fn start_file_export(
+    mut export_state: ResMut<FileExport>,
+    state: Res<EditorState>,
+    registry: Res<TerrainTextureRegistry>,
+    smoothing: Res<NormalSmoothing>,
+    mut notify: EventWriter<Notify>,
+    localization: Res<Localization>,
+) {
+    let Some(task) = export_state.dialog_task.as_mut() else {
+        return;
+    };
+    if !task.is_finished() {
+        return;
+    }
+    let Some((format, path)) = block_on(export_state.dialog_task.take().unwrap()) else {
+        return;
+    };
+    let path = if format.extensions().iter().any(|extension| {
+        path.extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+    }) {
+        path
+    } else {
+        path.with_extension(format.extensions()[0])
+    };
+
+    let map = state.map.clone();
+    let smoothing = *smoothing;
+    let textures = match format {
+        ExportFile::Gltf | ExportFile::Obj => {
+            match export::collect_texture_descriptors(&map, &registry) {
+                Ok((textures, _)) => textures,
+                Err(err) => {
+                    notify.send(Notify::error(
+                        localization.format("notify.textures_failed", &[("error", &err)]),
+                    ));
+                    return;
+                }
+            }
+        }
+        ExportFile::Heightmap | ExportFile::Splatmap => Vec::new(),
+    };
+    export_state.write_task = Some(IoTaskPool::get().spawn(async move {
+        match format {
+            ExportFile::Gltf => {
+                let splat_pngs = export::build_map_splatmap_pngs(&map)?;
+                export::export_gltf(&path, &map, textures, splat_pngs, smoothing)?;
+            }
+            ExportFile::Obj => export::export_obj(&path, &map, textures, smoothing)?,
+            ExportFile::Heightmap => export::export_heightmap(&path, &map)?,
+            ExportFile::Splatmap => export::export_splatmap(&path, &map)?,
+        }
+        Ok(path)
+    }));
+}
+
+fn poll_file_export(
+    mut export_state: ResMut<FileExport>,
+    mut notify: EventWriter<Notify>,
+    localization: Res<Localization>,
+) {
+    let Some(task) = export_state.write_task.as_mut() else {
+        return;
+    };
+    if !task.is_finished() {
+        return;
+    }
+    match block_on(export_state.write_task.take().unwrap()) {
+        Ok(path) => notify.send(Notify::info(
+            localization.format("notify.file_exported", &[("path", &path.display())]),
+        )),
+        Err(err) => notify.send(Notify::error(
+            localization.format("notify.file_export_failed", &[("error", &err)]),
+        )),
+    };
+}
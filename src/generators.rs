@@ -0,0 +1,289 @@
+use bevy::prelude::*;
+
+use crate::editor::{self, DirtyRegions, EditorState, MapEdit};
+use crate::selection::Selection;
+use crate::terrain::{CornerHeightCache, TerrainChanged, TerrainMeshSet};
+use crate::tools::noise::value_noise;
+use crate::tools::outline_tile;
+use crate::types::{MAX_ELEVATION, MIN_ELEVATION, TileKind, TileMap};
+
+const PREVIEW_COLOR: Color = Color::srgb(0.4, 0.8, 1.0);
+
+/// Whole-map procedural operations, previewed on the terrain before they are
+/// committed to the edited map.
+pub struct GeneratorPlugin;
+
+impl Plugin for GeneratorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GeneratorPreview>()
+            .add_systems(
+                Update,
+                update_generator_preview
+                    .in_set(TerrainMeshSet::Apply)
+                    .after(editor::apply_map_edits)
+                    .before(editor::sync_runtime_map),
+            )
+            .add_systems(
+                Update,
+                draw_generator_preview.after(TerrainMeshSet::Prepare),
+            );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GeneratorKind {
+    Noise,
+    Erosion,
+    Smooth,
+}
+
+impl GeneratorKind {
+    pub const ALL: [GeneratorKind; 3] = [
+        GeneratorKind::Noise,
+        GeneratorKind::Erosion,
+        GeneratorKind::Smooth,
+    ];
+
+    /// Localization key for the generator's name.
+    pub fn label_key(self) -> &'static str {
+        match self {
+            GeneratorKind::Noise => "generate.noise",
+            GeneratorKind::Erosion => "generate.erosion",
+            GeneratorKind::Smooth => "generate.smooth",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct GeneratorSettings {
+    pub kind: GeneratorKind,
+    pub seed: u64,
+    /// Largest noise offset, in elevation levels.
+    pub amplitude: i8,
+    /// Noise features per tile.
+    pub frequency: f32,
+    /// Erosion and smoothing passes.
+    pub iterations: u32,
+}
+
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+        Self {
+            kind: GeneratorKind::Noise,
+            seed: 0,
+            amplitude: 1,
+            frequency: 0.25,
+            iterations: 1,
+        }
+    }
+}
+
+/// What to do with the preview on the next update.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PreviewAction {
+    Start,
+    Apply,
+    Cancel,
+}
+
+/// A generated map shown in place of the edited one until it is applied or
+/// cancelled. The UI sets `action` and `dirty`; the preview is regenerated
+/// whenever the settings or the edited map change.
+#[derive(Resource, Default)]
+pub struct GeneratorPreview {
+    pub settings: GeneratorSettings,
+    pub action: Option<PreviewAction>,
+    pub dirty: bool,
+    map: Option<TileMap>,
+}
+
+impl GeneratorPreview {
+    pub fn is_active(&self) -> bool {
+        self.map.is_some()
+    }
+
+    /// The map to display instead of the edited one, while previewing.
+    pub fn map(&self) -> Option<&TileMap> {
+        self.map.as_ref()
+    }
+}
+
+fn update_generator_preview(
+    mut state: ResMut<EditorState>,
+    selection: Res<Selection>,
+    mut preview: ResMut<GeneratorPreview>,
+    mut map_edits: EventReader<MapEdit>,
+    mut edits: EventWriter<MapEdit>,
+    mut changed: EventWriter<TerrainChanged>,
+) {
+    let edited = !map_edits.is_empty();
+    map_edits.clear();
+
+    match preview.action.take() {
+        Some(PreviewAction::Start) => preview.dirty = true,
+        Some(PreviewAction::Apply) => {
+            // The displayed terrain already matches, so it only needs to
+            // catch up with the edited map next frame.
+            if let Some(map) = preview.map.take() {
+                edits.send_batch(
+                    map.tiles
+                        .into_iter()
+                        .zip(&state.map.tiles)
+                        .enumerate()
+                        .filter(|(_, (generated, current))| generated != *current)
+                        .map(|(idx, (tile, _))| MapEdit::SetTile {
+                            x: idx as u32 % state.map.width,
+                            y: idx as u32 / state.map.width,
+                            tile,
+                        }),
+                );
+            }
+            return;
+        }
+        Some(PreviewAction::Cancel) => {
+            if let Some(map) = preview.map.take() {
+                let state = &mut *state;
+                mark_differences(&mut state.dirty_regions, &map, &state.map);
+                changed.send(TerrainChanged);
+            }
+            return;
+        }
+        None => {}
+    }
+
+    if !(preview.dirty || edited && preview.is_active()) {
+        return;
+    }
+    preview.dirty = false;
+    let region = region(&state.map, &selection);
+    let generated = generate(&state.map, &region, &preview.settings);
+    let state = &mut *state;
+    let shown = preview.map.as_ref().unwrap_or(&state.map);
+    mark_differences(&mut state.dirty_regions, shown, &generated);
+    preview.map = Some(generated);
+    changed.send(TerrainChanged);
+}
+
+/// Records the tiles where two versions of the displayed map differ.
+fn mark_differences(regions: &mut DirtyRegions, before: &TileMap, after: &TileMap) {
+    if before.width != after.width || before.height != after.height {
+        regions.mark_all();
+        return;
+    }
+    for (idx, (old, new)) in before.tiles.iter().zip(&after.tiles).enumerate() {
+        if old != new {
+            regions.mark(idx as u32 % after.width, idx as u32 / after.width);
+        }
+    }
+}
+
+/// Outlines the tiles the preview would change.
+fn draw_generator_preview(
+    mut gizmos: Gizmos,
+    state: Res<EditorState>,
+    preview: Res<GeneratorPreview>,
+    heights: Res<CornerHeightCache>,
+) {
+    let Some(map) = preview.map() else {
+        return;
+    };
+    if map.width != state.map.width || map.height != state.map.height {
+        return;
+    }
+    for (idx, (generated, current)) in map.tiles.iter().zip(&state.map.tiles).enumerate() {
+        if generated != current {
+            let (x, y) = (idx as u32 % map.width, idx as u32 / map.width);
+            outline_tile(&mut gizmos, &heights, x, y, PREVIEW_COLOR);
+        }
+    }
+}
+
+/// Tiles a generator may change: the selection, or the whole map without
+/// one. Holes and locked tiles are always left alone.
+fn region(map: &TileMap, selection: &Selection) -> Vec<bool> {
+    let mut region = vec![selection.is_empty(); map.tiles.len()];
+    for (x, y) in selection.iter() {
+        if x < map.width && y < map.height {
+            region[map.idx(x, y)] = true;
+        }
+    }
+    for (inside, tile) in region.iter_mut().zip(&map.tiles) {
+        *inside &= tile.kind != TileKind::Empty && !tile.locked;
+    }
+    region
+}
+
+/// Runs a generator over the tiles marked in `region`.
+pub fn generate(map: &TileMap, region: &[bool], settings: &GeneratorSettings) -> TileMap {
+    let mut out = map.clone();
+    match settings.kind {
+        GeneratorKind::Noise => {
+            for (idx, tile) in out.tiles.iter_mut().enumerate() {
+                if !region[idx] {
+                    continue;
+                }
+                let (x, y) = (idx as u32 % map.width, idx as u32 / map.width);
+                let offset = value_noise(
+                    settings.seed,
+                    x as f32 * settings.frequency,
+                    y as f32 * settings.frequency,
+                ) * settings.amplitude as f32;
+                tile.elevation =
+                    (tile.elevation + offset.round() as i8).clamp(MIN_ELEVATION, MAX_ELEVATION);
+            }
+        }
+        GeneratorKind::Erosion => {
+            // Thermal erosion: floors more than one level above a solid
+            // neighbour slump by a level each pass.
+            for _ in 0..settings.iterations {
+                let before = out.clone();
+                for (idx, tile) in out.tiles.iter_mut().enumerate() {
+                    if !region[idx] || tile.kind != TileKind::Floor {
+                        continue;
+                    }
+                    let steep = solid_neighbors(&before, idx)
+                        .any(|elevation| tile.elevation - elevation > 1);
+                    if steep {
+                        tile.elevation = (tile.elevation - 1).max(MIN_ELEVATION);
+                    }
+                }
+            }
+        }
+        GeneratorKind::Smooth => {
+            // Each pass moves floors to the rounded mean of their own and
+            // their solid neighbours' elevations.
+            for _ in 0..settings.iterations {
+                let before = out.clone();
+                for (idx, tile) in out.tiles.iter_mut().enumerate() {
+                    if !region[idx] || tile.kind != TileKind::Floor {
+                        continue;
+                    }
+                    let (sum, count) = solid_neighbors(&before, idx)
+                        .fold((tile.elevation as f32, 1.0), |(sum, count), elevation| {
+                            (sum + elevation as f32, count + 1.0)
+                        });
+                    tile.elevation =
+                        ((sum / count).round() as i8).clamp(MIN_ELEVATION, MAX_ELEVATION);
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Elevations of the non-hole tiles sharing an edge with tile `idx`.
+fn solid_neighbors(map: &TileMap, idx: usize) -> impl Iterator<Item = i8> + '_ {
+    let (x, y) = (
+        (idx as u32 % map.width) as i32,
+        (idx as u32 / map.width) as i32,
+    );
+    [(0, -1), (1, 0), (0, 1), (-1, 0)]
+        .into_iter()
+        .map(move |(dx, dy)| (x + dx, y + dy))
+        .filter(|&(nx, ny)| {
+            nx >= 0 && ny >= 0 && (nx as u32) < map.width && (ny as u32) < map.height
+        })
+        .map(|(nx, ny)| map.get(nx as u32, ny as u32))
+        .filter(|tile| tile.kind != TileKind::Empty)
+        .map(|tile| tile.elevation)
+}
@@ -1,35 +1,125 @@
 use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use bevy::render::render_asset::RenderAssetUsages;
 
-use crate::{editor::EditorState, types::TILE_SIZE};
+use crate::editor::EditorState;
 
 const GRID_COLOR: Color = Color::srgb(0.85, 0.85, 0.85);
+// Lifts the lines off elevation-zero floor tiles so they do not z-fight.
+const GRID_LIFT: f32 = 0.01;
 
-pub fn draw_grid(mut gizmos: Gizmos, state: Res<EditorState>) {
-    if !state.show_grid {
+pub struct GridVisualPlugin;
+
+impl Plugin for GridVisualPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_grid)
+            .add_systems(Update, update_grid);
+    }
+}
+
+/// The grid line mesh plus the map size and tile size it was generated for,
+/// so the mesh is only rebuilt when either changes.
+#[derive(Resource)]
+struct GridVisual {
+    entity: Entity,
+    mesh: Handle<Mesh>,
+    size: Option<(UVec2, f32)>,
+}
+
+fn spawn_grid(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mesh = meshes.add(Mesh::new(
+        PrimitiveTopology::LineList,
+        RenderAssetUsages::default(),
+    ));
+    let material = materials.add(StandardMaterial {
+        base_color: GRID_COLOR,
+        unlit: true,
+        ..default()
+    });
+
+    let entity = commands
+        .spawn((
+            PbrBundle {
+                mesh: mesh.clone(),
+                material,
+                transform: Transform::from_xyz(0.0, GRID_LIFT, 0.0),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            Name::new("EditorGrid"),
+        ))
+        .id();
+
+    commands.insert_resource(GridVisual {
+        entity,
+        mesh,
+        size: None,
+    });
+}
+
+fn update_grid(
+    state: Res<EditorState>,
+    mut grid: ResMut<GridVisual>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut visibility: Query<&mut Visibility>,
+) {
+    if !state.is_changed() {
         return;
     }
 
-    let radius_x = state.map.width as i32;
-    let radius_z = state.map.height as i32;
+    if let Ok(mut visibility) = visibility.get_mut(grid.entity) {
+        let desired = if state.show_grid {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != desired {
+            *visibility = desired;
+        }
+    }
+
+    let size = (
+        UVec2::new(state.map.width, state.map.height),
+        state.map.tile_size,
+    );
+    if !state.show_grid || grid.size == Some(size) {
+        return;
+    }
+
+    if let Some(mesh) = meshes.get_mut(&grid.mesh) {
+        *mesh = build_grid_mesh(size.0, size.1);
+        grid.size = Some(size);
+    }
+}
+
+fn build_grid_mesh(size: UVec2, cell: f32) -> Mesh {
+    let radius_x = size.x as i32;
+    let radius_z = size.y as i32;
 
-    let cell = TILE_SIZE;
     let half_step = cell * 0.5;
 
+    let mut positions: Vec<[f32; 3]> =
+        Vec::with_capacity(((radius_x * 2 + 1) + (radius_z * 2 + 1)) as usize * 2);
+
     for x in -radius_x..=radius_x {
         let position = x as f32 * cell;
-        gizmos.line(
-            Vec3::new(position, 0.0, -radius_z as f32 * cell - half_step),
-            Vec3::new(position, 0.0, radius_z as f32 * cell + half_step),
-            GRID_COLOR,
-        );
+        positions.push([position, 0.0, -radius_z as f32 * cell - half_step]);
+        positions.push([position, 0.0, radius_z as f32 * cell + half_step]);
     }
 
     for z in -radius_z..=radius_z {
         let position = z as f32 * cell;
-        gizmos.line(
-            Vec3::new(-radius_x as f32 * cell - half_step, 0.0, position),
-            Vec3::new(radius_x as f32 * cell + half_step, 0.0, position),
-            GRID_COLOR,
-        );
+        positions.push([-radius_x as f32 * cell - half_step, 0.0, position]);
+        positions.push([radius_x as f32 * cell + half_step, 0.0, position]);
     }
+
+    let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+    let mut mesh = Mesh::new(PrimitiveTopology::LineList, RenderAssetUsages::default());
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh
 }
@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use crate::editor::{self, EditorState, EditorTool};
+use crate::terrain::TerrainMeshSet;
+
+const ERASE_KEYS: [KeyCode; 2] = [KeyCode::ControlLeft, KeyCode::ControlRight];
+const EYEDROPPER_KEYS: [KeyCode; 2] = [KeyCode::AltLeft, KeyCode::AltRight];
+const PAN_KEYS: [KeyCode; 1] = [KeyCode::Space];
+
+/// Modifier keys shared by the editor tools and the camera controls: holding
+/// Space pans, Alt picks a tile's look and Ctrl erases with tools that have
+/// no Ctrl action of their own, returning to the previous tool on release.
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TemporaryTool>().add_systems(
+            Update,
+            switch_temporary_tool
+                .in_set(TerrainMeshSet::Edit)
+                .before(editor::update_hover),
+        );
+    }
+}
+
+/// The tool a held modifier switched to, and the one to return to.
+#[derive(Resource, Default)]
+pub struct TemporaryTool {
+    active: Option<EditorTool>,
+    previous: Option<EditorTool>,
+}
+
+impl TemporaryTool {
+    /// The tool that stays selected once modifiers are released.
+    pub fn base(&self, state: &EditorState) -> EditorTool {
+        self.previous.unwrap_or(state.current_tool)
+    }
+}
+
+/// Whether the erase modifier is held. Tools with their own erase action
+/// (unlock, remove, reset) use it directly instead of switching tools.
+pub fn erase_held(keys: &ButtonInput<KeyCode>) -> bool {
+    keys.any_pressed(ERASE_KEYS)
+}
+
+/// Tools that treat the erase modifier as part of their own action.
+fn erases_itself(tool: EditorTool) -> bool {
+    matches!(
+        tool,
+        EditorTool::Paint
+            | EditorTool::Lock
+            | EditorTool::MovementCost
+            | EditorTool::Walkability
+            | EditorTool::Trigger
+            | EditorTool::Start
+            | EditorTool::Light
+            | EditorTool::Vegetation
+            | EditorTool::Decal
+            | EditorTool::Prop
+            | EditorTool::Sculpt
+            | EditorTool::Vertex
+            | EditorTool::Road
+    )
+}
+
+fn switch_temporary_tool(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut state: ResMut<EditorState>,
+    mut temporary: ResMut<TemporaryTool>,
+    mut egui: EguiContexts,
+) {
+    // Choosing a tool from the toolbar while a modifier is held keeps it.
+    if temporary
+        .active
+        .is_some_and(|active| active != state.current_tool)
+    {
+        temporary.active = None;
+        temporary.previous = None;
+    }
+    if egui.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+
+    let base = temporary.base(&state);
+    let wanted = if keys.any_pressed(PAN_KEYS) {
+        Some(EditorTool::Pan)
+    } else if keys.any_pressed(EYEDROPPER_KEYS) && base != EditorTool::Clone {
+        // Clone keeps Alt-click for picking its source.
+        Some(EditorTool::Eyedropper)
+    } else if erase_held(&keys) && !erases_itself(base) {
+        Some(EditorTool::Erase)
+    } else {
+        None
+    };
+
+    match wanted {
+        Some(tool) if tool == base => {}
+        Some(tool) => {
+            if temporary.previous.is_none() {
+                temporary.previous = Some(state.current_tool);
+            }
+            temporary.active = Some(tool);
+            if state.current_tool != tool {
+                state.current_tool = tool;
+            }
+        }
+        None => {
+            if let Some(previous) = temporary.previous.take() {
+                state.current_tool = previous;
+            }
+            temporary.active = None;
+        }
+    }
+}
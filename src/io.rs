@@ -1,8 +1,31 @@
-use crate::types::TileMap;
-use bincode::{config, decode_from_slice, encode_to_vec};
-use std::path::Path;
+use crate::text_map;
+use crate::types::{
+    DEFAULT_TILE_SIZE, MapDecal, MapLight, MapProp, RampDirection, StartLocation, Tile, TileKind,
+    TileMap, TileType, TriggerArea, Weather,
+};
+use anyhow::Context;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bincode::{Decode, Encode, config, decode_from_slice, encode_to_vec};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 
 const KEY: u8 = 0xAA;
+/// Saves start with this tag followed by a format version byte. Files without
+/// it predate versioning and use the original tile layout.
+const MAGIC: &[u8; 3] = b"TME";
+/// Version 1 added texture variation, version 2 the lock flag, version 3
+/// painted movement costs, version 4 trigger areas, version 5 players,
+/// version 6 lights, version 7 weather, version 8 vegetation, version 9 the
+/// map's tile size, version 10 sculpted tile corners, version 11 the water
+/// level, version 12 upper tile layers, version 13 mirrored tile textures,
+/// version 14 the road layer, version 15 decals, version 16 props, version 17
+/// walkability overrides.
+const FORMAT_VERSION: u8 = 17;
+/// Extension of maps saved as JSON, which can be hand-edited and diffed
+/// like text maps but mirror `TileMap`'s fields one to one.
+pub const JSON_EXTENSION: &str = "json";
 
 fn obfuscate(data: &mut [u8]) {
     for b in data.iter_mut() {
@@ -10,19 +33,2105 @@ fn obfuscate(data: &mut [u8]) {
     }
 }
 
+/// Saves `map`, as text or JSON when the path has the text map or JSON
+/// extension and as compact binary otherwise.
 pub fn save_map(path: impl AsRef<Path>, map: &TileMap) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(text_map::EXTENSION) => {
+            std::fs::write(path, text_map::to_text(map))?;
+            return Ok(());
+        }
+        Some(JSON_EXTENSION) => {
+            std::fs::write(path, serde_json::to_vec_pretty(map)?)?;
+            return Ok(());
+        }
+        _ => {}
+    }
     // pick a config (matches old bincode defaults)
     let cfg = config::standard();
-    let mut bytes = encode_to_vec(map, cfg)?;
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(FORMAT_VERSION);
+    bytes.extend(encode_to_vec(map, cfg)?);
     obfuscate(&mut bytes);
     std::fs::write(path, bytes)?;
     Ok(())
 }
 
+/// Loads a saved map, resolving delta saves against their base maps.
 pub fn load_map(path: impl AsRef<Path>) -> anyhow::Result<TileMap> {
-    let mut bytes = std::fs::read(path)?;
+    let mut path = path.as_ref().to_path_buf();
+    let mut diffs = Vec::new();
+    loop {
+        let bytes =
+            std::fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        match decode_map_file(bytes)? {
+            MapFile::Full(map) => return apply_deltas(map, diffs),
+            MapFile::Delta(delta) => {
+                anyhow::ensure!(
+                    diffs.len() < MAX_DELTA_DEPTH,
+                    "delta map base chain is too deep"
+                );
+                path = path.parent().unwrap_or(Path::new("")).join(&delta.base);
+                diffs.push(delta.diff);
+            }
+        }
+    }
+}
+
+/// Decodes the contents of a saved map file. Delta saves are rejected since
+/// their base can only be resolved relative to the file's location.
+pub fn decode_map(bytes: Vec<u8>) -> anyhow::Result<TileMap> {
+    match decode_map_file(bytes)? {
+        MapFile::Full(map) => Ok(map),
+        MapFile::Delta(delta) => anyhow::bail!(
+            "delta map needs its base map {}; load it from a path instead",
+            delta.base
+        ),
+    }
+}
+
+/// Tag of delta saves: a base map path and the diff that turns the base into
+/// this map, for variants that share most of their content.
+const DELTA_MAGIC: &[u8; 3] = b"TMV";
+/// Longer base chains are assumed to be cyclic.
+const MAX_DELTA_DEPTH: usize = 16;
+
+#[derive(Encode, Decode)]
+struct DeltaFile {
+    /// Base map path, relative to the delta file's directory when possible.
+    base: String,
+    diff: MapDiff,
+}
+
+/// Delta layout of format version 16, before walkability overrides.
+#[derive(Decode)]
+struct DeltaFileV16 {
+    base: String,
+    diff: MapDiffV16,
+}
+
+/// Delta layout of format version 15, before props.
+#[derive(Decode)]
+struct DeltaFileV15 {
+    base: String,
+    diff: MapDiffV15,
+}
+
+/// Delta layout of format version 14, before decals.
+#[derive(Decode)]
+struct DeltaFileV14 {
+    base: String,
+    diff: MapDiffV14,
+}
+
+/// Delta layout of format version 13, before the road layer.
+#[derive(Decode)]
+struct DeltaFileV13 {
+    base: String,
+    diff: MapDiffV13,
+}
+
+/// Delta layout of format version 12, before mirrored textures.
+#[derive(Decode)]
+struct DeltaFileV12 {
+    base: String,
+    diff: MapDiffV12,
+}
+
+/// Delta layout of format version 11, before upper layers.
+#[derive(Decode)]
+struct DeltaFileV11 {
+    base: String,
+    diff: MapDiffV11,
+}
+
+/// Delta layout of format version 10, before the water level.
+#[derive(Decode)]
+struct DeltaFileV10 {
+    base: String,
+    diff: MapDiffV10,
+}
+
+/// Delta layout of format version 9, before sculpted corners.
+#[derive(Decode)]
+struct DeltaFileV9 {
+    base: String,
+    diff: MapDiffV9,
+}
+
+/// Delta layout of format version 8, before the tile size.
+#[derive(Decode)]
+struct DeltaFileV8 {
+    base: String,
+    diff: MapDiffV8,
+}
+
+/// Delta layout of format version 7, before vegetation.
+#[derive(Decode)]
+struct DeltaFileV7 {
+    base: String,
+    diff: MapDiffV7,
+}
+
+/// Delta layout of format version 6, before weather.
+#[derive(Decode)]
+struct DeltaFileV6 {
+    base: String,
+    diff: MapDiffV6,
+}
+
+/// Delta layout of format version 5, before lights.
+#[derive(Decode)]
+struct DeltaFileV5 {
+    base: String,
+    diff: MapDiffV5,
+}
+
+enum MapFile {
+    Full(TileMap),
+    Delta(DeltaFile),
+}
+
+/// Saves `map` as the difference against the map saved at `base_path`.
+pub fn save_delta(
+    path: impl AsRef<Path>,
+    base_path: impl AsRef<Path>,
+    map: &TileMap,
+) -> anyhow::Result<()> {
+    let (path, base_path) = (path.as_ref(), base_path.as_ref());
+    let base = load_map(base_path)?;
+    let delta = DeltaFile {
+        base: relative_base_path(path, base_path)?,
+        diff: MapDiff::between(&base, map)?,
+    };
+    let mut bytes = DELTA_MAGIC.to_vec();
+    bytes.push(FORMAT_VERSION);
+    bytes.extend(encode_to_vec(&delta, config::standard())?);
     obfuscate(&mut bytes);
-    let cfg = config::standard();
-    let (map, _len): (TileMap, usize) = decode_from_slice(&bytes, cfg)?;
+    std::fs::write(path, bytes)?;
+    Ok(())
+}
+
+/// The base map a saved delta resolves against, or `None` for full saves.
+pub fn delta_base(path: impl AsRef<Path>) -> anyhow::Result<Option<PathBuf>> {
+    let path = path.as_ref();
+    Ok(match decode_map_file(std::fs::read(path)?)? {
+        MapFile::Full(_) => None,
+        MapFile::Delta(delta) => Some(path.parent().unwrap_or(Path::new("")).join(delta.base)),
+    })
+}
+
+/// Tag of saved stamps, followed by the map format version their tiles use.
+const STAMP_MAGIC: &[u8; 3] = b"TMS";
+pub const STAMP_EXTENSION: &str = "tmestamp";
+
+/// A named group of tiles saved for placing again, such as a plateau with
+/// its ramps.
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct Stamp {
+    pub name: String,
+    pub tiles: Vec<StampTile>,
+}
+
+/// One tile of a stamp, positioned relative to the stamp's middle.
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct StampTile {
+    pub dx: i32,
+    pub dy: i32,
+    pub tile: Tile,
+}
+
+pub fn save_stamp(path: impl AsRef<Path>, stamp: &Stamp) -> anyhow::Result<()> {
+    let mut bytes = STAMP_MAGIC.to_vec();
+    bytes.push(FORMAT_VERSION);
+    bytes.extend(encode_to_vec(stamp, config::standard())?);
+    obfuscate(&mut bytes);
+    std::fs::write(path, bytes)?;
+    Ok(())
+}
+
+pub fn load_stamp(path: impl AsRef<Path>) -> anyhow::Result<Stamp> {
+    let path = path.as_ref();
+    let mut bytes =
+        std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    obfuscate(&mut bytes);
+    let stamp = match bytes
+        .strip_prefix(STAMP_MAGIC.as_slice())
+        .and_then(|tagged| tagged.split_first())
+    {
+        Some((&FORMAT_VERSION, body)) => decode_from_slice::<Stamp, _>(body, config::standard())?.0,
+        Some((13..=16, body)) => decode_from_slice::<Stamp, _>(body, config::standard())?.0,
+        Some((10..=12, body)) => decode_from_slice::<StampV12, _>(body, config::standard())?
+            .0
+            .into(),
+        Some((9, body)) => decode_from_slice::<StampV9, _>(body, config::standard())?
+            .0
+            .into(),
+        Some((version, _)) => anyhow::bail!("unsupported stamp version {version}"),
+        None => anyhow::bail!("{} is not a stamp", path.display()),
+    };
+    Ok(stamp)
+}
+
+/// Stamp layout of format versions 10 to 12, before mirrored textures.
+#[derive(Decode)]
+struct StampV12 {
+    name: String,
+    tiles: Vec<StampTileV12>,
+}
+
+#[derive(Decode)]
+struct StampTileV12 {
+    dx: i32,
+    dy: i32,
+    tile: TileV12,
+}
+
+impl From<StampV12> for Stamp {
+    fn from(v12: StampV12) -> Self {
+        Stamp {
+            name: v12.name,
+            tiles: v12
+                .tiles
+                .into_iter()
+                .map(|tile| StampTile {
+                    dx: tile.dx,
+                    dy: tile.dy,
+                    tile: tile.tile.into(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Stamp layout of format version 9, before sculpted corners.
+#[derive(Decode)]
+struct StampV9 {
+    name: String,
+    tiles: Vec<StampTileV9>,
+}
+
+#[derive(Decode)]
+struct StampTileV9 {
+    dx: i32,
+    dy: i32,
+    tile: TileV9,
+}
+
+impl From<StampV9> for Stamp {
+    fn from(v9: StampV9) -> Self {
+        Stamp {
+            name: v9.name,
+            tiles: v9
+                .tiles
+                .into_iter()
+                .map(|tile| StampTile {
+                    dx: tile.dx,
+                    dy: tile.dy,
+                    tile: tile.tile.into(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Stores the base as a path below the delta's directory when it lives there,
+/// so a variant and its base can be moved together.
+fn relative_base_path(path: &Path, base_path: &Path) -> anyhow::Result<String> {
+    let base_path = std::fs::canonicalize(base_path)?;
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let dir = std::fs::canonicalize(dir)?;
+    let base = base_path.strip_prefix(&dir).unwrap_or(&base_path);
+    base.to_str()
+        .map(|base| base.replace('\\', "/"))
+        .context("base map path is not valid UTF-8")
+}
+
+fn apply_deltas(mut map: TileMap, diffs: Vec<MapDiff>) -> anyhow::Result<TileMap> {
+    for diff in diffs.iter().rev() {
+        diff.apply(&mut map)
+            .context("base map changed since the delta was saved")?;
+    }
     Ok(map)
 }
+
+fn decode_map_file(mut bytes: Vec<u8>) -> anyhow::Result<MapFile> {
+    if bytes.starts_with(b"tilemap-text ") {
+        let text = std::str::from_utf8(&bytes).context("text map is not valid UTF-8")?;
+        return Ok(MapFile::Full(text_map::from_text(text)?));
+    }
+    // Obfuscated binary saves are not valid UTF-8.
+    if let Some(text) = std::str::from_utf8(&bytes)
+        .ok()
+        .filter(|text| text.trim_start().starts_with('{'))
+    {
+        let map = serde_json::from_str(text).context("invalid JSON map")?;
+        return Ok(MapFile::Full(map));
+    }
+    obfuscate(&mut bytes);
+    let cfg = config::standard();
+    if let Some(tagged) = bytes.strip_prefix(DELTA_MAGIC.as_slice()) {
+        return match tagged.split_first() {
+            Some((&FORMAT_VERSION, body)) => {
+                let (delta, _len): (DeltaFile, usize) = decode_from_slice(body, cfg)?;
+                Ok(MapFile::Delta(delta))
+            }
+            Some((16, body)) => {
+                let (delta, _len): (DeltaFileV16, usize) = decode_from_slice(body, cfg)?;
+                Ok(MapFile::Delta(DeltaFile {
+                    base: delta.base,
+                    diff: delta.diff.into(),
+                }))
+            }
+            Some((15, body)) => {
+                let (delta, _len): (DeltaFileV15, usize) = decode_from_slice(body, cfg)?;
+                Ok(MapFile::Delta(DeltaFile {
+                    base: delta.base,
+                    diff: delta.diff.into(),
+                }))
+            }
+            Some((14, body)) => {
+                let (delta, _len): (DeltaFileV14, usize) = decode_from_slice(body, cfg)?;
+                Ok(MapFile::Delta(DeltaFile {
+                    base: delta.base,
+                    diff: delta.diff.into(),
+                }))
+            }
+            Some((13, body)) => {
+                let (delta, _len): (DeltaFileV13, usize) = decode_from_slice(body, cfg)?;
+                Ok(MapFile::Delta(DeltaFile {
+                    base: delta.base,
+                    diff: delta.diff.into(),
+                }))
+            }
+            Some((12, body)) => {
+                let (delta, _len): (DeltaFileV12, usize) = decode_from_slice(body, cfg)?;
+                Ok(MapFile::Delta(DeltaFile {
+                    base: delta.base,
+                    diff: delta.diff.into(),
+                }))
+            }
+            Some((11, body)) => {
+                let (delta, _len): (DeltaFileV11, usize) = decode_from_slice(body, cfg)?;
+                Ok(MapFile::Delta(DeltaFile {
+                    base: delta.base,
+                    diff: delta.diff.into(),
+                }))
+            }
+            Some((10, body)) => {
+                let (delta, _len): (DeltaFileV10, usize) = decode_from_slice(body, cfg)?;
+                Ok(MapFile::Delta(DeltaFile {
+                    base: delta.base,
+                    diff: delta.diff.into(),
+                }))
+            }
+            Some((9, body)) => {
+                let (delta, _len): (DeltaFileV9, usize) = decode_from_slice(body, cfg)?;
+                Ok(MapFile::Delta(DeltaFile {
+                    base: delta.base,
+                    diff: delta.diff.into(),
+                }))
+            }
+            Some((8, body)) => {
+                let (delta, _len): (DeltaFileV8, usize) = decode_from_slice(body, cfg)?;
+                Ok(MapFile::Delta(DeltaFile {
+                    base: delta.base,
+                    diff: delta.diff.into(),
+                }))
+            }
+            Some((7, body)) => {
+                let (delta, _len): (DeltaFileV7, usize) = decode_from_slice(body, cfg)?;
+                Ok(MapFile::Delta(DeltaFile {
+                    base: delta.base,
+                    diff: delta.diff.into(),
+                }))
+            }
+            Some((6, body)) => {
+                let (delta, _len): (DeltaFileV6, usize) = decode_from_slice(body, cfg)?;
+                Ok(MapFile::Delta(DeltaFile {
+                    base: delta.base,
+                    diff: delta.diff.into(),
+                }))
+            }
+            Some((5, body)) => {
+                let (delta, _len): (DeltaFileV5, usize) = decode_from_slice(body, cfg)?;
+                Ok(MapFile::Delta(DeltaFile {
+                    base: delta.base,
+                    diff: delta.diff.into(),
+                }))
+            }
+            Some((version, _)) => anyhow::bail!("unsupported delta map version {version}"),
+            None => anyhow::bail!("truncated delta map file"),
+        };
+    }
+    let Some(tagged) = bytes.strip_prefix(MAGIC.as_slice()) else {
+        let (map, _len): (LegacyTileMap, usize) =
+            decode_from_slice(&bytes, cfg).context("not a recognised map file")?;
+        return Ok(MapFile::Full(map.into()));
+    };
+    let map = match tagged.split_first() {
+        Some((&FORMAT_VERSION, body)) => decode_from_slice::<TileMap, _>(body, cfg)?.0,
+        Some((16, body)) => decode_from_slice::<TileMapV16, _>(body, cfg)?.0.into(),
+        Some((15, body)) => decode_from_slice::<TileMapV15, _>(body, cfg)?.0.into(),
+        Some((14, body)) => decode_from_slice::<TileMapV14, _>(body, cfg)?.0.into(),
+        Some((13, body)) => decode_from_slice::<TileMapV13, _>(body, cfg)?.0.into(),
+        Some((12, body)) => decode_from_slice::<TileMapV12, _>(body, cfg)?.0.into(),
+        Some((11, body)) => decode_from_slice::<TileMapV11, _>(body, cfg)?.0.into(),
+        Some((10, body)) => decode_from_slice::<TileMapV10, _>(body, cfg)?.0.into(),
+        Some((9, body)) => decode_from_slice::<TileMapV9, _>(body, cfg)?.0.into(),
+        Some((8, body)) => decode_from_slice::<TileMapV8, _>(body, cfg)?.0.into(),
+        Some((7, body)) => decode_from_slice::<TileMapV7, _>(body, cfg)?.0.into(),
+        Some((6, body)) => decode_from_slice::<TileMapV6, _>(body, cfg)?.0.into(),
+        Some((5, body)) => decode_from_slice::<TileMapV5, _>(body, cfg)?.0.into(),
+        Some((4, body)) => decode_from_slice::<TileMapV4, _>(body, cfg)?.0.into(),
+        Some((3, body)) => decode_from_slice::<TileMapV3, _>(body, cfg)?.0.into(),
+        Some((2, body)) => decode_from_slice::<TileMapV2, _>(body, cfg)?.0.into(),
+        Some((1, body)) => decode_from_slice::<TileMapV1, _>(body, cfg)?.0.into(),
+        Some((version, _)) => anyhow::bail!("unsupported map format version {version}"),
+        None => anyhow::bail!("truncated map file"),
+    };
+    Ok(MapFile::Full(map))
+}
+
+/// Tag of serialized diffs, followed by the map format version that defines
+/// their tile layout.
+const DIFF_MAGIC: &[u8; 3] = b"TMD";
+
+/// Stable 64-bit FNV-1a hash of a map's saved contents, used to check that a
+/// diff is applied to the map it was made against. Layers added after format
+/// version 5 only count once they hold something, so older diffs still apply
+/// to maps that never used them.
+pub fn map_hash(map: &TileMap) -> anyhow::Result<u64> {
+    let cfg = config::standard();
+    let tiles: Vec<TileV9> = map.tiles.iter().map(TileV9::from).collect();
+    let mut bytes = encode_to_vec(
+        (map.width, map.height, &tiles, &map.triggers, &map.starts),
+        cfg,
+    )?;
+    if map.tiles.iter().any(|tile| tile.corner_offsets != [0; 4]) {
+        let corners: Vec<[i8; 4]> = map.tiles.iter().map(|tile| tile.corner_offsets).collect();
+        bytes.extend(encode_to_vec(&corners, cfg)?);
+    }
+    if !map.lights.is_empty() {
+        bytes.extend(encode_to_vec(&map.lights, cfg)?);
+    }
+    if map.weather != Weather::default() {
+        bytes.extend(encode_to_vec(&map.weather, cfg)?);
+    }
+    if !map.vegetation.is_empty() {
+        bytes.extend(encode_to_vec(&map.vegetation, cfg)?);
+    }
+    if !map.roads.is_empty() {
+        bytes.extend(encode_to_vec(&map.roads, cfg)?);
+    }
+    if !map.decals.is_empty() {
+        bytes.extend(encode_to_vec(&map.decals, cfg)?);
+    }
+    if !map.props.is_empty() {
+        bytes.extend(encode_to_vec(&map.props, cfg)?);
+    }
+    if !map.walkability.is_empty() {
+        bytes.extend(encode_to_vec(&map.walkability, cfg)?);
+    }
+    if map.tile_size != DEFAULT_TILE_SIZE {
+        bytes.extend(encode_to_vec(map.tile_size, cfg)?);
+    }
+    if let Some(level) = map.water_level {
+        bytes.extend(encode_to_vec(level, cfg)?);
+    }
+    if !map.upper_layers.is_empty() {
+        let upper_layers: Vec<Vec<TileV12>> = map
+            .upper_layers
+            .iter()
+            .map(|layer| layer.iter().map(TileV12::from).collect())
+            .collect();
+        bytes.extend(encode_to_vec(&upper_layers, cfg)?);
+    }
+    let all_tiles = || map.tiles.iter().chain(map.upper_layers.iter().flatten());
+    if all_tiles().any(|tile| tile.mirrored) {
+        let mirrored: Vec<bool> = all_tiles().map(|tile| tile.mirrored).collect();
+        bytes.extend(encode_to_vec(&mirrored, cfg)?);
+    }
+    Ok(bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    }))
+}
+
+/// One tile that differs from the base map.
+#[derive(Serialize, Deserialize, Encode, Decode, Clone, Debug, PartialEq)]
+pub struct TileChange {
+    pub x: u32,
+    pub y: u32,
+    pub tile: Tile,
+}
+
+/// Tile change layout of format versions 10 to 12, before mirrored textures.
+#[derive(Decode)]
+struct TileChangeV12 {
+    x: u32,
+    y: u32,
+    tile: TileV12,
+}
+
+impl From<TileChangeV12> for TileChange {
+    fn from(v12: TileChangeV12) -> Self {
+        TileChange {
+            x: v12.x,
+            y: v12.y,
+            tile: v12.tile.into(),
+        }
+    }
+}
+
+/// Tile change layout of format versions 5 to 9, before sculpted corners.
+#[derive(Decode)]
+struct TileChangeV9 {
+    x: u32,
+    y: u32,
+    tile: TileV9,
+}
+
+impl From<TileChangeV9> for TileChange {
+    fn from(v9: TileChangeV9) -> Self {
+        TileChange {
+            x: v9.x,
+            y: v9.y,
+            tile: v9.tile.into(),
+        }
+    }
+}
+
+/// The changes that turn one map into another of the same size: changed
+/// tiles, plus the trigger, start and light lists, the weather, the
+/// vegetation, road and walkability layers, the decals and props, the tile
+/// size, the water level and the upper tile layers when those differ.
+#[derive(Serialize, Deserialize, Encode, Decode, Clone, Debug, PartialEq)]
+pub struct MapDiff {
+    pub base_hash: u64,
+    pub width: u32,
+    pub height: u32,
+    pub tiles: Vec<TileChange>,
+    pub triggers: Option<Vec<TriggerArea>>,
+    pub starts: Option<Vec<StartLocation>>,
+    pub lights: Option<Vec<MapLight>>,
+    pub weather: Option<Weather>,
+    pub vegetation: Option<Vec<u8>>,
+    pub roads: Option<Vec<u8>>,
+    pub decals: Option<Vec<MapDecal>>,
+    pub props: Option<Vec<MapProp>>,
+    pub walkability: Option<Vec<u8>>,
+    pub tile_size: Option<f32>,
+    /// `Some(None)` drains a map that had water.
+    pub water_level: Option<Option<f32>>,
+    pub upper_layers: Option<Vec<Vec<Tile>>>,
+}
+
+/// Diff layout of format version 16, before walkability overrides.
+#[derive(Decode)]
+struct MapDiffV16 {
+    base_hash: u64,
+    width: u32,
+    height: u32,
+    tiles: Vec<TileChange>,
+    triggers: Option<Vec<TriggerArea>>,
+    starts: Option<Vec<StartLocation>>,
+    lights: Option<Vec<MapLight>>,
+    weather: Option<Weather>,
+    vegetation: Option<Vec<u8>>,
+    roads: Option<Vec<u8>>,
+    decals: Option<Vec<MapDecal>>,
+    props: Option<Vec<MapProp>>,
+    tile_size: Option<f32>,
+    water_level: Option<Option<f32>>,
+    upper_layers: Option<Vec<Vec<Tile>>>,
+}
+
+impl From<MapDiffV16> for MapDiff {
+    fn from(v16: MapDiffV16) -> Self {
+        MapDiff {
+            base_hash: v16.base_hash,
+            width: v16.width,
+            height: v16.height,
+            tiles: v16.tiles,
+            triggers: v16.triggers,
+            starts: v16.starts,
+            lights: v16.lights,
+            weather: v16.weather,
+            vegetation: v16.vegetation,
+            roads: v16.roads,
+            decals: v16.decals,
+            props: v16.props,
+            walkability: None,
+            tile_size: v16.tile_size,
+            water_level: v16.water_level,
+            upper_layers: v16.upper_layers,
+        }
+    }
+}
+
+/// Diff layout of format version 15, before props.
+#[derive(Decode)]
+struct MapDiffV15 {
+    base_hash: u64,
+    width: u32,
+    height: u32,
+    tiles: Vec<TileChange>,
+    triggers: Option<Vec<TriggerArea>>,
+    starts: Option<Vec<StartLocation>>,
+    lights: Option<Vec<MapLight>>,
+    weather: Option<Weather>,
+    vegetation: Option<Vec<u8>>,
+    roads: Option<Vec<u8>>,
+    decals: Option<Vec<MapDecal>>,
+    tile_size: Option<f32>,
+    water_level: Option<Option<f32>>,
+    upper_layers: Option<Vec<Vec<Tile>>>,
+}
+
+impl From<MapDiffV15> for MapDiff {
+    fn from(v15: MapDiffV15) -> Self {
+        MapDiff {
+            base_hash: v15.base_hash,
+            width: v15.width,
+            height: v15.height,
+            tiles: v15.tiles,
+            triggers: v15.triggers,
+            starts: v15.starts,
+            lights: v15.lights,
+            weather: v15.weather,
+            vegetation: v15.vegetation,
+            roads: v15.roads,
+            decals: v15.decals,
+            props: None,
+            walkability: None,
+            tile_size: v15.tile_size,
+            water_level: v15.water_level,
+            upper_layers: v15.upper_layers,
+        }
+    }
+}
+
+/// Diff layout of format version 14, before decals.
+#[derive(Decode)]
+struct MapDiffV14 {
+    base_hash: u64,
+    width: u32,
+    height: u32,
+    tiles: Vec<TileChange>,
+    triggers: Option<Vec<TriggerArea>>,
+    starts: Option<Vec<StartLocation>>,
+    lights: Option<Vec<MapLight>>,
+    weather: Option<Weather>,
+    vegetation: Option<Vec<u8>>,
+    roads: Option<Vec<u8>>,
+    tile_size: Option<f32>,
+    water_level: Option<Option<f32>>,
+    upper_layers: Option<Vec<Vec<Tile>>>,
+}
+
+impl From<MapDiffV14> for MapDiff {
+    fn from(v14: MapDiffV14) -> Self {
+        MapDiff {
+            base_hash: v14.base_hash,
+            width: v14.width,
+            height: v14.height,
+            tiles: v14.tiles,
+            triggers: v14.triggers,
+            starts: v14.starts,
+            lights: v14.lights,
+            weather: v14.weather,
+            vegetation: v14.vegetation,
+            roads: v14.roads,
+            decals: None,
+            props: None,
+            walkability: None,
+            tile_size: v14.tile_size,
+            water_level: v14.water_level,
+            upper_layers: v14.upper_layers,
+        }
+    }
+}
+
+/// Diff layout of format version 13, before the road layer.
+#[derive(Decode)]
+struct MapDiffV13 {
+    base_hash: u64,
+    width: u32,
+    height: u32,
+    tiles: Vec<TileChange>,
+    triggers: Option<Vec<TriggerArea>>,
+    starts: Option<Vec<StartLocation>>,
+    lights: Option<Vec<MapLight>>,
+    weather: Option<Weather>,
+    vegetation: Option<Vec<u8>>,
+    tile_size: Option<f32>,
+    water_level: Option<Option<f32>>,
+    upper_layers: Option<Vec<Vec<Tile>>>,
+}
+
+impl From<MapDiffV13> for MapDiff {
+    fn from(v13: MapDiffV13) -> Self {
+        MapDiff {
+            base_hash: v13.base_hash,
+            width: v13.width,
+            height: v13.height,
+            tiles: v13.tiles,
+            triggers: v13.triggers,
+            starts: v13.starts,
+            lights: v13.lights,
+            weather: v13.weather,
+            vegetation: v13.vegetation,
+            roads: None,
+            decals: None,
+            props: None,
+            walkability: None,
+            tile_size: v13.tile_size,
+            water_level: v13.water_level,
+            upper_layers: v13.upper_layers,
+        }
+    }
+}
+
+/// Diff layout of format version 12, before mirrored textures.
+#[derive(Decode)]
+struct MapDiffV12 {
+    base_hash: u64,
+    width: u32,
+    height: u32,
+    tiles: Vec<TileChangeV12>,
+    triggers: Option<Vec<TriggerArea>>,
+    starts: Option<Vec<StartLocation>>,
+    lights: Option<Vec<MapLight>>,
+    weather: Option<Weather>,
+    vegetation: Option<Vec<u8>>,
+    tile_size: Option<f32>,
+    water_level: Option<Option<f32>>,
+    upper_layers: Option<Vec<Vec<TileV12>>>,
+}
+
+impl From<MapDiffV12> for MapDiff {
+    fn from(v12: MapDiffV12) -> Self {
+        MapDiff {
+            base_hash: v12.base_hash,
+            width: v12.width,
+            height: v12.height,
+            tiles: v12.tiles.into_iter().map(Into::into).collect(),
+            triggers: v12.triggers,
+            starts: v12.starts,
+            lights: v12.lights,
+            weather: v12.weather,
+            vegetation: v12.vegetation,
+            roads: None,
+            decals: None,
+            props: None,
+            walkability: None,
+            tile_size: v12.tile_size,
+            water_level: v12.water_level,
+            upper_layers: v12.upper_layers.map(|layers| {
+                layers
+                    .into_iter()
+                    .map(|layer| layer.into_iter().map(Into::into).collect())
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// Diff layout of format version 11, before upper layers.
+#[derive(Decode)]
+struct MapDiffV11 {
+    base_hash: u64,
+    width: u32,
+    height: u32,
+    tiles: Vec<TileChangeV12>,
+    triggers: Option<Vec<TriggerArea>>,
+    starts: Option<Vec<StartLocation>>,
+    lights: Option<Vec<MapLight>>,
+    weather: Option<Weather>,
+    vegetation: Option<Vec<u8>>,
+    tile_size: Option<f32>,
+    water_level: Option<Option<f32>>,
+}
+
+impl From<MapDiffV11> for MapDiff {
+    fn from(v11: MapDiffV11) -> Self {
+        MapDiff {
+            base_hash: v11.base_hash,
+            width: v11.width,
+            height: v11.height,
+            tiles: v11.tiles.into_iter().map(Into::into).collect(),
+            triggers: v11.triggers,
+            starts: v11.starts,
+            lights: v11.lights,
+            weather: v11.weather,
+            vegetation: v11.vegetation,
+            roads: None,
+            decals: None,
+            props: None,
+            walkability: None,
+            tile_size: v11.tile_size,
+            water_level: v11.water_level,
+            upper_layers: None,
+        }
+    }
+}
+
+/// Diff layout of format version 10, before the water level.
+#[derive(Decode)]
+struct MapDiffV10 {
+    base_hash: u64,
+    width: u32,
+    height: u32,
+    tiles: Vec<TileChangeV12>,
+    triggers: Option<Vec<TriggerArea>>,
+    starts: Option<Vec<StartLocation>>,
+    lights: Option<Vec<MapLight>>,
+    weather: Option<Weather>,
+    vegetation: Option<Vec<u8>>,
+    tile_size: Option<f32>,
+}
+
+impl From<MapDiffV10> for MapDiff {
+    fn from(v10: MapDiffV10) -> Self {
+        MapDiff {
+            base_hash: v10.base_hash,
+            width: v10.width,
+            height: v10.height,
+            tiles: v10.tiles.into_iter().map(Into::into).collect(),
+            triggers: v10.triggers,
+            starts: v10.starts,
+            lights: v10.lights,
+            weather: v10.weather,
+            vegetation: v10.vegetation,
+            roads: None,
+            decals: None,
+            props: None,
+            walkability: None,
+            tile_size: v10.tile_size,
+            water_level: None,
+            upper_layers: None,
+        }
+    }
+}
+
+/// Diff layout of format version 5, before lights.
+#[derive(Decode)]
+struct MapDiffV5 {
+    base_hash: u64,
+    width: u32,
+    height: u32,
+    tiles: Vec<TileChangeV9>,
+    triggers: Option<Vec<TriggerArea>>,
+    starts: Option<Vec<StartLocation>>,
+}
+
+/// Diff layout of format version 6, before weather.
+#[derive(Decode)]
+struct MapDiffV6 {
+    base_hash: u64,
+    width: u32,
+    height: u32,
+    tiles: Vec<TileChangeV9>,
+    triggers: Option<Vec<TriggerArea>>,
+    starts: Option<Vec<StartLocation>>,
+    lights: Option<Vec<MapLight>>,
+}
+
+/// Diff layout of format version 9, before sculpted corners.
+#[derive(Decode)]
+struct MapDiffV9 {
+    base_hash: u64,
+    width: u32,
+    height: u32,
+    tiles: Vec<TileChangeV9>,
+    triggers: Option<Vec<TriggerArea>>,
+    starts: Option<Vec<StartLocation>>,
+    lights: Option<Vec<MapLight>>,
+    weather: Option<Weather>,
+    vegetation: Option<Vec<u8>>,
+    tile_size: Option<f32>,
+}
+
+impl From<MapDiffV9> for MapDiff {
+    fn from(v9: MapDiffV9) -> Self {
+        MapDiff {
+            base_hash: v9.base_hash,
+            width: v9.width,
+            height: v9.height,
+            tiles: v9.tiles.into_iter().map(Into::into).collect(),
+            triggers: v9.triggers,
+            starts: v9.starts,
+            lights: v9.lights,
+            weather: v9.weather,
+            vegetation: v9.vegetation,
+            roads: None,
+            decals: None,
+            props: None,
+            walkability: None,
+            tile_size: v9.tile_size,
+            water_level: None,
+            upper_layers: None,
+        }
+    }
+}
+
+/// Diff layout of format version 8, before the tile size.
+#[derive(Decode)]
+struct MapDiffV8 {
+    base_hash: u64,
+    width: u32,
+    height: u32,
+    tiles: Vec<TileChangeV9>,
+    triggers: Option<Vec<TriggerArea>>,
+    starts: Option<Vec<StartLocation>>,
+    lights: Option<Vec<MapLight>>,
+    weather: Option<Weather>,
+    vegetation: Option<Vec<u8>>,
+}
+
+impl From<MapDiffV8> for MapDiff {
+    fn from(v8: MapDiffV8) -> Self {
+        MapDiff {
+            base_hash: v8.base_hash,
+            width: v8.width,
+            height: v8.height,
+            tiles: v8.tiles.into_iter().map(Into::into).collect(),
+            triggers: v8.triggers,
+            starts: v8.starts,
+            lights: v8.lights,
+            weather: v8.weather,
+            vegetation: v8.vegetation,
+            roads: None,
+            decals: None,
+            props: None,
+            walkability: None,
+            tile_size: None,
+            water_level: None,
+            upper_layers: None,
+        }
+    }
+}
+
+/// Diff layout of format version 7, before vegetation.
+#[derive(Decode)]
+struct MapDiffV7 {
+    base_hash: u64,
+    width: u32,
+    height: u32,
+    tiles: Vec<TileChangeV9>,
+    triggers: Option<Vec<TriggerArea>>,
+    starts: Option<Vec<StartLocation>>,
+    lights: Option<Vec<MapLight>>,
+    weather: Option<Weather>,
+}
+
+impl From<MapDiffV7> for MapDiff {
+    fn from(v7: MapDiffV7) -> Self {
+        MapDiff {
+            base_hash: v7.base_hash,
+            width: v7.width,
+            height: v7.height,
+            tiles: v7.tiles.into_iter().map(Into::into).collect(),
+            triggers: v7.triggers,
+            starts: v7.starts,
+            lights: v7.lights,
+            weather: v7.weather,
+            vegetation: None,
+            roads: None,
+            decals: None,
+            props: None,
+            walkability: None,
+            tile_size: None,
+            water_level: None,
+            upper_layers: None,
+        }
+    }
+}
+
+impl From<MapDiffV6> for MapDiff {
+    fn from(v6: MapDiffV6) -> Self {
+        MapDiff {
+            base_hash: v6.base_hash,
+            width: v6.width,
+            height: v6.height,
+            tiles: v6.tiles.into_iter().map(Into::into).collect(),
+            triggers: v6.triggers,
+            starts: v6.starts,
+            lights: v6.lights,
+            weather: None,
+            vegetation: None,
+            roads: None,
+            decals: None,
+            props: None,
+            walkability: None,
+            tile_size: None,
+            water_level: None,
+            upper_layers: None,
+        }
+    }
+}
+
+impl From<MapDiffV5> for MapDiff {
+    fn from(v5: MapDiffV5) -> Self {
+        MapDiff {
+            base_hash: v5.base_hash,
+            width: v5.width,
+            height: v5.height,
+            tiles: v5.tiles.into_iter().map(Into::into).collect(),
+            triggers: v5.triggers,
+            starts: v5.starts,
+            lights: None,
+            weather: None,
+            vegetation: None,
+            roads: None,
+            decals: None,
+            props: None,
+            walkability: None,
+            tile_size: None,
+            water_level: None,
+            upper_layers: None,
+        }
+    }
+}
+
+impl MapDiff {
+    pub fn between(base: &TileMap, target: &TileMap) -> anyhow::Result<MapDiff> {
+        anyhow::ensure!(
+            base.width == target.width && base.height == target.height,
+            "cannot diff a {}x{} map against a {}x{} map",
+            target.width,
+            target.height,
+            base.width,
+            base.height
+        );
+        let tiles = base
+            .tiles
+            .iter()
+            .zip(&target.tiles)
+            .enumerate()
+            .filter(|(_, (old, new))| old != new)
+            .map(|(idx, (_, new))| TileChange {
+                x: idx as u32 % base.width,
+                y: idx as u32 / base.width,
+                tile: new.clone(),
+            })
+            .collect();
+        Ok(MapDiff {
+            base_hash: map_hash(base)?,
+            width: base.width,
+            height: base.height,
+            tiles,
+            triggers: (base.triggers != target.triggers).then(|| target.triggers.clone()),
+            starts: (base.starts != target.starts).then(|| target.starts.clone()),
+            lights: (base.lights != target.lights).then(|| target.lights.clone()),
+            weather: (base.weather != target.weather).then(|| target.weather.clone()),
+            vegetation: (base.vegetation != target.vegetation).then(|| target.vegetation.clone()),
+            roads: (base.roads != target.roads).then(|| target.roads.clone()),
+            decals: (base.decals != target.decals).then(|| target.decals.clone()),
+            props: (base.props != target.props).then(|| target.props.clone()),
+            walkability: (base.walkability != target.walkability)
+                .then(|| target.walkability.clone()),
+            tile_size: (base.tile_size != target.tile_size).then_some(target.tile_size),
+            water_level: (base.water_level != target.water_level).then_some(target.water_level),
+            upper_layers: (base.upper_layers != target.upper_layers)
+                .then(|| target.upper_layers.clone()),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+            && self.triggers.is_none()
+            && self.starts.is_none()
+            && self.lights.is_none()
+            && self.weather.is_none()
+            && self.vegetation.is_none()
+            && self.roads.is_none()
+            && self.decals.is_none()
+            && self.props.is_none()
+            && self.walkability.is_none()
+            && self.tile_size.is_none()
+            && self.water_level.is_none()
+            && self.upper_layers.is_none()
+    }
+
+    /// Applies the diff, refusing maps other than the one it was made from.
+    pub fn apply(&self, map: &mut TileMap) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            map.width == self.width && map.height == self.height,
+            "diff is for a {}x{} map, not {}x{}",
+            self.width,
+            self.height,
+            map.width,
+            map.height
+        );
+        anyhow::ensure!(
+            map_hash(map)? == self.base_hash,
+            "diff was made against a different base map"
+        );
+        for change in &self.tiles {
+            anyhow::ensure!(
+                change.x < map.width && change.y < map.height,
+                "diff changes tile {},{} outside the map",
+                change.x,
+                change.y
+            );
+        }
+        for change in &self.tiles {
+            map.set(change.x, change.y, change.tile.clone());
+        }
+        if let Some(triggers) = &self.triggers {
+            map.triggers.clone_from(triggers);
+        }
+        if let Some(starts) = &self.starts {
+            map.starts.clone_from(starts);
+        }
+        if let Some(lights) = &self.lights {
+            map.lights.clone_from(lights);
+        }
+        if let Some(weather) = &self.weather {
+            map.weather.clone_from(weather);
+        }
+        if let Some(vegetation) = &self.vegetation {
+            map.vegetation.clone_from(vegetation);
+        }
+        if let Some(roads) = &self.roads {
+            map.roads.clone_from(roads);
+        }
+        if let Some(decals) = &self.decals {
+            map.decals.clone_from(decals);
+        }
+        if let Some(props) = &self.props {
+            map.props.clone_from(props);
+        }
+        if let Some(walkability) = &self.walkability {
+            map.walkability.clone_from(walkability);
+        }
+        if let Some(tile_size) = self.tile_size {
+            map.tile_size = tile_size;
+        }
+        if let Some(water_level) = self.water_level {
+            map.water_level = water_level;
+        }
+        if let Some(upper_layers) = &self.upper_layers {
+            map.upper_layers.clone_from(upper_layers);
+        }
+        Ok(())
+    }
+}
+
+pub fn encode_diff(diff: &MapDiff) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = DIFF_MAGIC.to_vec();
+    bytes.push(FORMAT_VERSION);
+    bytes.extend(encode_to_vec(diff, config::standard())?);
+    Ok(bytes)
+}
+
+pub fn decode_diff(bytes: &[u8]) -> anyhow::Result<MapDiff> {
+    let Some(tagged) = bytes.strip_prefix(DIFF_MAGIC.as_slice()) else {
+        anyhow::bail!("not a map diff");
+    };
+    match tagged.split_first() {
+        Some((&FORMAT_VERSION, body)) => {
+            let (diff, _len): (MapDiff, usize) = decode_from_slice(body, config::standard())?;
+            Ok(diff)
+        }
+        Some((16, body)) => {
+            let (diff, _len): (MapDiffV16, usize) = decode_from_slice(body, config::standard())?;
+            Ok(diff.into())
+        }
+        Some((15, body)) => {
+            let (diff, _len): (MapDiffV15, usize) = decode_from_slice(body, config::standard())?;
+            Ok(diff.into())
+        }
+        Some((14, body)) => {
+            let (diff, _len): (MapDiffV14, usize) = decode_from_slice(body, config::standard())?;
+            Ok(diff.into())
+        }
+        Some((13, body)) => {
+            let (diff, _len): (MapDiffV13, usize) = decode_from_slice(body, config::standard())?;
+            Ok(diff.into())
+        }
+        Some((12, body)) => {
+            let (diff, _len): (MapDiffV12, usize) = decode_from_slice(body, config::standard())?;
+            Ok(diff.into())
+        }
+        Some((11, body)) => {
+            let (diff, _len): (MapDiffV11, usize) = decode_from_slice(body, config::standard())?;
+            Ok(diff.into())
+        }
+        Some((10, body)) => {
+            let (diff, _len): (MapDiffV10, usize) = decode_from_slice(body, config::standard())?;
+            Ok(diff.into())
+        }
+        Some((9, body)) => {
+            let (diff, _len): (MapDiffV9, usize) = decode_from_slice(body, config::standard())?;
+            Ok(diff.into())
+        }
+        Some((8, body)) => {
+            let (diff, _len): (MapDiffV8, usize) = decode_from_slice(body, config::standard())?;
+            Ok(diff.into())
+        }
+        Some((7, body)) => {
+            let (diff, _len): (MapDiffV7, usize) = decode_from_slice(body, config::standard())?;
+            Ok(diff.into())
+        }
+        Some((6, body)) => {
+            let (diff, _len): (MapDiffV6, usize) = decode_from_slice(body, config::standard())?;
+            Ok(diff.into())
+        }
+        Some((5, body)) => {
+            let (diff, _len): (MapDiffV5, usize) = decode_from_slice(body, config::standard())?;
+            Ok(diff.into())
+        }
+        Some((version, _)) => anyhow::bail!("unsupported map diff version {version}"),
+        None => anyhow::bail!("truncated map diff"),
+    }
+}
+
+/// Loads saved maps (`.tme`) through the asset server.
+#[derive(Default)]
+pub struct TileMapLoader;
+
+impl AssetLoader for TileMapLoader {
+    type Asset = TileMap;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> anyhow::Result<TileMap> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let mut path = load_context.path().to_path_buf();
+        let mut diffs = Vec::new();
+        loop {
+            match decode_map_file(bytes)? {
+                MapFile::Full(map) => return apply_deltas(map, diffs),
+                MapFile::Delta(delta) => {
+                    anyhow::ensure!(
+                        diffs.len() < MAX_DELTA_DEPTH,
+                        "delta map base chain is too deep"
+                    );
+                    // Bases outside the asset folder cannot be read here.
+                    path = path.parent().unwrap_or(Path::new("")).join(&delta.base);
+                    bytes = load_context.read_asset_bytes(path.clone()).await?;
+                    diffs.push(delta.diff);
+                }
+            }
+        }
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tme", text_map::EXTENSION, JSON_EXTENSION]
+    }
+}
+
+/// Tile layout of saves written before `variation` and `rotation` existed.
+#[derive(Decode)]
+struct LegacyTile {
+    kind: TileKind,
+    tile_type: TileType,
+    x: u32,
+    y: u32,
+    elevation: i8,
+    ramp_direction: Option<RampDirection>,
+}
+
+#[derive(Decode)]
+struct LegacyTileMap {
+    width: u32,
+    height: u32,
+    tiles: Vec<LegacyTile>,
+}
+
+impl From<LegacyTileMap> for TileMap {
+    fn from(legacy: LegacyTileMap) -> Self {
+        TileMap {
+            width: legacy.width,
+            height: legacy.height,
+            tiles: legacy
+                .tiles
+                .into_iter()
+                .map(|tile| Tile {
+                    kind: tile.kind,
+                    tile_type: tile.tile_type,
+                    x: tile.x,
+                    y: tile.y,
+                    elevation: tile.elevation,
+                    ramp_direction: tile.ramp_direction,
+                    variation: 0,
+                    rotation: 0,
+                    mirrored: false,
+                    locked: false,
+                    movement_cost: None,
+                    corner_offsets: [0; 4],
+                })
+                .collect(),
+            triggers: Vec::new(),
+            starts: Vec::new(),
+            lights: Vec::new(),
+            weather: Weather::default(),
+            vegetation: Vec::new(),
+            roads: Vec::new(),
+            decals: Vec::new(),
+            props: Vec::new(),
+            walkability: Vec::new(),
+            tile_size: DEFAULT_TILE_SIZE,
+            water_level: None,
+            upper_layers: Vec::new(),
+        }
+    }
+}
+
+/// Tile layout of format version 1, before the lock flag.
+#[derive(Decode)]
+struct TileV1 {
+    kind: TileKind,
+    tile_type: TileType,
+    x: u32,
+    y: u32,
+    elevation: i8,
+    ramp_direction: Option<RampDirection>,
+    variation: u8,
+    rotation: u8,
+}
+
+#[derive(Decode)]
+struct TileMapV1 {
+    width: u32,
+    height: u32,
+    tiles: Vec<TileV1>,
+}
+
+impl From<TileMapV1> for TileMap {
+    fn from(v1: TileMapV1) -> Self {
+        TileMap {
+            width: v1.width,
+            height: v1.height,
+            tiles: v1
+                .tiles
+                .into_iter()
+                .map(|tile| Tile {
+                    kind: tile.kind,
+                    tile_type: tile.tile_type,
+                    x: tile.x,
+                    y: tile.y,
+                    elevation: tile.elevation,
+                    ramp_direction: tile.ramp_direction,
+                    variation: tile.variation,
+                    rotation: tile.rotation,
+                    mirrored: false,
+                    locked: false,
+                    movement_cost: None,
+                    corner_offsets: [0; 4],
+                })
+                .collect(),
+            triggers: Vec::new(),
+            starts: Vec::new(),
+            lights: Vec::new(),
+            weather: Weather::default(),
+            vegetation: Vec::new(),
+            roads: Vec::new(),
+            decals: Vec::new(),
+            props: Vec::new(),
+            walkability: Vec::new(),
+            tile_size: DEFAULT_TILE_SIZE,
+            water_level: None,
+            upper_layers: Vec::new(),
+        }
+    }
+}
+
+/// Tile layout of format version 2, before painted movement costs.
+#[derive(Decode)]
+struct TileV2 {
+    kind: TileKind,
+    tile_type: TileType,
+    x: u32,
+    y: u32,
+    elevation: i8,
+    ramp_direction: Option<RampDirection>,
+    variation: u8,
+    rotation: u8,
+    locked: bool,
+}
+
+#[derive(Decode)]
+struct TileMapV2 {
+    width: u32,
+    height: u32,
+    tiles: Vec<TileV2>,
+}
+
+impl From<TileMapV2> for TileMap {
+    fn from(v2: TileMapV2) -> Self {
+        TileMap {
+            width: v2.width,
+            height: v2.height,
+            tiles: v2
+                .tiles
+                .into_iter()
+                .map(|tile| Tile {
+                    kind: tile.kind,
+                    tile_type: tile.tile_type,
+                    x: tile.x,
+                    y: tile.y,
+                    elevation: tile.elevation,
+                    ramp_direction: tile.ramp_direction,
+                    variation: tile.variation,
+                    rotation: tile.rotation,
+                    mirrored: false,
+                    locked: tile.locked,
+                    movement_cost: None,
+                    corner_offsets: [0; 4],
+                })
+                .collect(),
+            triggers: Vec::new(),
+            starts: Vec::new(),
+            lights: Vec::new(),
+            weather: Weather::default(),
+            vegetation: Vec::new(),
+            roads: Vec::new(),
+            decals: Vec::new(),
+            props: Vec::new(),
+            walkability: Vec::new(),
+            tile_size: DEFAULT_TILE_SIZE,
+            water_level: None,
+            upper_layers: Vec::new(),
+        }
+    }
+}
+
+/// Map layout of format version 3, before trigger areas.
+#[derive(Decode)]
+struct TileMapV3 {
+    width: u32,
+    height: u32,
+    tiles: Vec<TileV9>,
+}
+
+impl From<TileMapV3> for TileMap {
+    fn from(v3: TileMapV3) -> Self {
+        TileMap {
+            width: v3.width,
+            height: v3.height,
+            tiles: v3.tiles.into_iter().map(Into::into).collect(),
+            triggers: Vec::new(),
+            starts: Vec::new(),
+            lights: Vec::new(),
+            weather: Weather::default(),
+            vegetation: Vec::new(),
+            roads: Vec::new(),
+            decals: Vec::new(),
+            props: Vec::new(),
+            walkability: Vec::new(),
+            tile_size: DEFAULT_TILE_SIZE,
+            water_level: None,
+            upper_layers: Vec::new(),
+        }
+    }
+}
+
+/// Trigger layout of format version 4, before player ownership.
+#[derive(Decode)]
+struct TriggerAreaV4 {
+    name: String,
+    color: [u8; 3],
+    tiles: BTreeSet<(u32, u32)>,
+}
+
+/// Map layout of format version 4, before start locations.
+#[derive(Decode)]
+struct TileMapV4 {
+    width: u32,
+    height: u32,
+    tiles: Vec<TileV9>,
+    triggers: Vec<TriggerAreaV4>,
+}
+
+impl From<TileMapV4> for TileMap {
+    fn from(v4: TileMapV4) -> Self {
+        TileMap {
+            width: v4.width,
+            height: v4.height,
+            tiles: v4.tiles.into_iter().map(Into::into).collect(),
+            triggers: v4
+                .triggers
+                .into_iter()
+                .map(|area| TriggerArea {
+                    name: area.name,
+                    color: area.color,
+                    tiles: area.tiles,
+                    player: None,
+                })
+                .collect(),
+            starts: Vec::new(),
+            lights: Vec::new(),
+            weather: Weather::default(),
+            vegetation: Vec::new(),
+            roads: Vec::new(),
+            decals: Vec::new(),
+            props: Vec::new(),
+            walkability: Vec::new(),
+            tile_size: DEFAULT_TILE_SIZE,
+            water_level: None,
+            upper_layers: Vec::new(),
+        }
+    }
+}
+
+/// Map layout of format version 5, before lights.
+#[derive(Decode)]
+struct TileMapV5 {
+    width: u32,
+    height: u32,
+    tiles: Vec<TileV9>,
+    triggers: Vec<TriggerArea>,
+    starts: Vec<StartLocation>,
+}
+
+impl From<TileMapV5> for TileMap {
+    fn from(v5: TileMapV5) -> Self {
+        TileMap {
+            width: v5.width,
+            height: v5.height,
+            tiles: v5.tiles.into_iter().map(Into::into).collect(),
+            triggers: v5.triggers,
+            starts: v5.starts,
+            lights: Vec::new(),
+            weather: Weather::default(),
+            vegetation: Vec::new(),
+            roads: Vec::new(),
+            decals: Vec::new(),
+            props: Vec::new(),
+            walkability: Vec::new(),
+            tile_size: DEFAULT_TILE_SIZE,
+            water_level: None,
+            upper_layers: Vec::new(),
+        }
+    }
+}
+
+/// Map layout of format version 6, before weather.
+#[derive(Decode)]
+struct TileMapV6 {
+    width: u32,
+    height: u32,
+    tiles: Vec<TileV9>,
+    triggers: Vec<TriggerArea>,
+    starts: Vec<StartLocation>,
+    lights: Vec<MapLight>,
+}
+
+impl From<TileMapV6> for TileMap {
+    fn from(v6: TileMapV6) -> Self {
+        TileMap {
+            width: v6.width,
+            height: v6.height,
+            tiles: v6.tiles.into_iter().map(Into::into).collect(),
+            triggers: v6.triggers,
+            starts: v6.starts,
+            lights: v6.lights,
+            weather: Weather::default(),
+            vegetation: Vec::new(),
+            roads: Vec::new(),
+            decals: Vec::new(),
+            props: Vec::new(),
+            walkability: Vec::new(),
+            tile_size: DEFAULT_TILE_SIZE,
+            water_level: None,
+            upper_layers: Vec::new(),
+        }
+    }
+}
+
+/// Map layout of format version 7, before vegetation.
+#[derive(Decode)]
+struct TileMapV7 {
+    width: u32,
+    height: u32,
+    tiles: Vec<TileV9>,
+    triggers: Vec<TriggerArea>,
+    starts: Vec<StartLocation>,
+    lights: Vec<MapLight>,
+    weather: Weather,
+}
+
+impl From<TileMapV7> for TileMap {
+    fn from(v7: TileMapV7) -> Self {
+        TileMap {
+            width: v7.width,
+            height: v7.height,
+            tiles: v7.tiles.into_iter().map(Into::into).collect(),
+            triggers: v7.triggers,
+            starts: v7.starts,
+            lights: v7.lights,
+            weather: v7.weather,
+            vegetation: Vec::new(),
+            roads: Vec::new(),
+            decals: Vec::new(),
+            props: Vec::new(),
+            walkability: Vec::new(),
+            tile_size: DEFAULT_TILE_SIZE,
+            water_level: None,
+            upper_layers: Vec::new(),
+        }
+    }
+}
+
+/// Map layout of format version 8, before the tile size.
+#[derive(Decode)]
+struct TileMapV8 {
+    width: u32,
+    height: u32,
+    tiles: Vec<TileV9>,
+    triggers: Vec<TriggerArea>,
+    starts: Vec<StartLocation>,
+    lights: Vec<MapLight>,
+    weather: Weather,
+    vegetation: Vec<u8>,
+}
+
+impl From<TileMapV8> for TileMap {
+    fn from(v8: TileMapV8) -> Self {
+        TileMap {
+            width: v8.width,
+            height: v8.height,
+            tiles: v8.tiles.into_iter().map(Into::into).collect(),
+            triggers: v8.triggers,
+            starts: v8.starts,
+            lights: v8.lights,
+            weather: v8.weather,
+            vegetation: v8.vegetation,
+            roads: Vec::new(),
+            decals: Vec::new(),
+            props: Vec::new(),
+            walkability: Vec::new(),
+            tile_size: DEFAULT_TILE_SIZE,
+            water_level: None,
+            upper_layers: Vec::new(),
+        }
+    }
+}
+
+/// Map layout of format version 16, before walkability overrides.
+#[derive(Decode)]
+struct TileMapV16 {
+    width: u32,
+    height: u32,
+    tiles: Vec<Tile>,
+    triggers: Vec<TriggerArea>,
+    starts: Vec<StartLocation>,
+    lights: Vec<MapLight>,
+    weather: Weather,
+    vegetation: Vec<u8>,
+    roads: Vec<u8>,
+    decals: Vec<MapDecal>,
+    props: Vec<MapProp>,
+    tile_size: f32,
+    water_level: Option<f32>,
+    upper_layers: Vec<Vec<Tile>>,
+}
+
+impl From<TileMapV16> for TileMap {
+    fn from(v16: TileMapV16) -> Self {
+        TileMap {
+            width: v16.width,
+            height: v16.height,
+            tiles: v16.tiles,
+            triggers: v16.triggers,
+            starts: v16.starts,
+            lights: v16.lights,
+            weather: v16.weather,
+            vegetation: v16.vegetation,
+            roads: v16.roads,
+            decals: v16.decals,
+            props: v16.props,
+            walkability: Vec::new(),
+            tile_size: v16.tile_size,
+            water_level: v16.water_level,
+            upper_layers: v16.upper_layers,
+        }
+    }
+}
+
+/// Map layout of format version 15, before props.
+#[derive(Decode)]
+struct TileMapV15 {
+    width: u32,
+    height: u32,
+    tiles: Vec<Tile>,
+    triggers: Vec<TriggerArea>,
+    starts: Vec<StartLocation>,
+    lights: Vec<MapLight>,
+    weather: Weather,
+    vegetation: Vec<u8>,
+    roads: Vec<u8>,
+    decals: Vec<MapDecal>,
+    tile_size: f32,
+    water_level: Option<f32>,
+    upper_layers: Vec<Vec<Tile>>,
+}
+
+impl From<TileMapV15> for TileMap {
+    fn from(v15: TileMapV15) -> Self {
+        TileMap {
+            width: v15.width,
+            height: v15.height,
+            tiles: v15.tiles,
+            triggers: v15.triggers,
+            starts: v15.starts,
+            lights: v15.lights,
+            weather: v15.weather,
+            vegetation: v15.vegetation,
+            roads: v15.roads,
+            decals: v15.decals,
+            props: Vec::new(),
+            walkability: Vec::new(),
+            tile_size: v15.tile_size,
+            water_level: v15.water_level,
+            upper_layers: v15.upper_layers,
+        }
+    }
+}
+
+/// Map layout of format version 14, before decals.
+#[derive(Decode)]
+struct TileMapV14 {
+    width: u32,
+    height: u32,
+    tiles: Vec<Tile>,
+    triggers: Vec<TriggerArea>,
+    starts: Vec<StartLocation>,
+    lights: Vec<MapLight>,
+    weather: Weather,
+    vegetation: Vec<u8>,
+    roads: Vec<u8>,
+    tile_size: f32,
+    water_level: Option<f32>,
+    upper_layers: Vec<Vec<Tile>>,
+}
+
+impl From<TileMapV14> for TileMap {
+    fn from(v14: TileMapV14) -> Self {
+        TileMap {
+            width: v14.width,
+            height: v14.height,
+            tiles: v14.tiles,
+            triggers: v14.triggers,
+            starts: v14.starts,
+            lights: v14.lights,
+            weather: v14.weather,
+            vegetation: v14.vegetation,
+            roads: v14.roads,
+            decals: Vec::new(),
+            props: Vec::new(),
+            walkability: Vec::new(),
+            tile_size: v14.tile_size,
+            water_level: v14.water_level,
+            upper_layers: v14.upper_layers,
+        }
+    }
+}
+
+/// Map layout of format version 13, before the road layer.
+#[derive(Decode)]
+struct TileMapV13 {
+    width: u32,
+    height: u32,
+    tiles: Vec<Tile>,
+    triggers: Vec<TriggerArea>,
+    starts: Vec<StartLocation>,
+    lights: Vec<MapLight>,
+    weather: Weather,
+    vegetation: Vec<u8>,
+    tile_size: f32,
+    water_level: Option<f32>,
+    upper_layers: Vec<Vec<Tile>>,
+}
+
+impl From<TileMapV13> for TileMap {
+    fn from(v13: TileMapV13) -> Self {
+        TileMap {
+            width: v13.width,
+            height: v13.height,
+            tiles: v13.tiles,
+            triggers: v13.triggers,
+            starts: v13.starts,
+            lights: v13.lights,
+            weather: v13.weather,
+            vegetation: v13.vegetation,
+            roads: Vec::new(),
+            decals: Vec::new(),
+            props: Vec::new(),
+            walkability: Vec::new(),
+            tile_size: v13.tile_size,
+            water_level: v13.water_level,
+            upper_layers: v13.upper_layers,
+        }
+    }
+}
+
+/// Map layout of format version 12, before mirrored textures.
+#[derive(Decode)]
+struct TileMapV12 {
+    width: u32,
+    height: u32,
+    tiles: Vec<TileV12>,
+    triggers: Vec<TriggerArea>,
+    starts: Vec<StartLocation>,
+    lights: Vec<MapLight>,
+    weather: Weather,
+    vegetation: Vec<u8>,
+    tile_size: f32,
+    water_level: Option<f32>,
+    upper_layers: Vec<Vec<TileV12>>,
+}
+
+impl From<TileMapV12> for TileMap {
+    fn from(v12: TileMapV12) -> Self {
+        TileMap {
+            width: v12.width,
+            height: v12.height,
+            tiles: v12.tiles.into_iter().map(Into::into).collect(),
+            triggers: v12.triggers,
+            starts: v12.starts,
+            lights: v12.lights,
+            weather: v12.weather,
+            vegetation: v12.vegetation,
+            roads: Vec::new(),
+            decals: Vec::new(),
+            props: Vec::new(),
+            walkability: Vec::new(),
+            tile_size: v12.tile_size,
+            water_level: v12.water_level,
+            upper_layers: v12
+                .upper_layers
+                .into_iter()
+                .map(|layer| layer.into_iter().map(Into::into).collect())
+                .collect(),
+        }
+    }
+}
+
+/// Map layout of format version 11, before upper layers.
+#[derive(Decode)]
+struct TileMapV11 {
+    width: u32,
+    height: u32,
+    tiles: Vec<TileV12>,
+    triggers: Vec<TriggerArea>,
+    starts: Vec<StartLocation>,
+    lights: Vec<MapLight>,
+    weather: Weather,
+    vegetation: Vec<u8>,
+    tile_size: f32,
+    water_level: Option<f32>,
+}
+
+impl From<TileMapV11> for TileMap {
+    fn from(v11: TileMapV11) -> Self {
+        TileMap {
+            width: v11.width,
+            height: v11.height,
+            tiles: v11.tiles.into_iter().map(Into::into).collect(),
+            triggers: v11.triggers,
+            starts: v11.starts,
+            lights: v11.lights,
+            weather: v11.weather,
+            vegetation: v11.vegetation,
+            roads: Vec::new(),
+            decals: Vec::new(),
+            props: Vec::new(),
+            walkability: Vec::new(),
+            tile_size: v11.tile_size,
+            water_level: v11.water_level,
+            upper_layers: Vec::new(),
+        }
+    }
+}
+
+/// Map layout of format version 10, before the water level.
+#[derive(Decode)]
+struct TileMapV10 {
+    width: u32,
+    height: u32,
+    tiles: Vec<TileV12>,
+    triggers: Vec<TriggerArea>,
+    starts: Vec<StartLocation>,
+    lights: Vec<MapLight>,
+    weather: Weather,
+    vegetation: Vec<u8>,
+    tile_size: f32,
+}
+
+impl From<TileMapV10> for TileMap {
+    fn from(v10: TileMapV10) -> Self {
+        TileMap {
+            width: v10.width,
+            height: v10.height,
+            tiles: v10.tiles.into_iter().map(Into::into).collect(),
+            triggers: v10.triggers,
+            starts: v10.starts,
+            lights: v10.lights,
+            weather: v10.weather,
+            vegetation: v10.vegetation,
+            roads: Vec::new(),
+            decals: Vec::new(),
+            props: Vec::new(),
+            walkability: Vec::new(),
+            tile_size: v10.tile_size,
+            water_level: None,
+            upper_layers: Vec::new(),
+        }
+    }
+}
+
+/// Map layout of format version 9, before sculpted corners.
+#[derive(Decode)]
+struct TileMapV9 {
+    width: u32,
+    height: u32,
+    tiles: Vec<TileV9>,
+    triggers: Vec<TriggerArea>,
+    starts: Vec<StartLocation>,
+    lights: Vec<MapLight>,
+    weather: Weather,
+    vegetation: Vec<u8>,
+    tile_size: f32,
+}
+
+impl From<TileMapV9> for TileMap {
+    fn from(v9: TileMapV9) -> Self {
+        TileMap {
+            width: v9.width,
+            height: v9.height,
+            tiles: v9.tiles.into_iter().map(Into::into).collect(),
+            triggers: v9.triggers,
+            starts: v9.starts,
+            lights: v9.lights,
+            weather: v9.weather,
+            vegetation: v9.vegetation,
+            roads: Vec::new(),
+            decals: Vec::new(),
+            props: Vec::new(),
+            walkability: Vec::new(),
+            tile_size: v9.tile_size,
+            water_level: None,
+            upper_layers: Vec::new(),
+        }
+    }
+}
+
+/// Tile layout of format versions 3 to 9, before sculpted corners. Map
+/// hashes still encode tiles this way so older diffs keep applying.
+#[derive(Encode, Decode)]
+struct TileV9 {
+    kind: TileKind,
+    tile_type: TileType,
+    x: u32,
+    y: u32,
+    elevation: i8,
+    ramp_direction: Option<RampDirection>,
+    variation: u8,
+    rotation: u8,
+    locked: bool,
+    movement_cost: Option<u8>,
+}
+
+impl From<TileV9> for Tile {
+    fn from(v9: TileV9) -> Self {
+        Tile {
+            kind: v9.kind,
+            tile_type: v9.tile_type,
+            x: v9.x,
+            y: v9.y,
+            elevation: v9.elevation,
+            ramp_direction: v9.ramp_direction,
+            variation: v9.variation,
+            rotation: v9.rotation,
+            locked: v9.locked,
+            movement_cost: v9.movement_cost,
+            mirrored: false,
+            corner_offsets: [0; 4],
+        }
+    }
+}
+
+impl From<&Tile> for TileV9 {
+    fn from(tile: &Tile) -> Self {
+        TileV9 {
+            kind: tile.kind,
+            tile_type: tile.tile_type,
+            x: tile.x,
+            y: tile.y,
+            elevation: tile.elevation,
+            ramp_direction: tile.ramp_direction,
+            variation: tile.variation,
+            rotation: tile.rotation,
+            locked: tile.locked,
+            movement_cost: tile.movement_cost,
+        }
+    }
+}
+
+/// Tile layout of format versions 10 to 12, before mirrored textures. Map
+/// hashes still encode upper layers this way so older diffs keep applying.
+#[derive(Encode, Decode)]
+struct TileV12 {
+    kind: TileKind,
+    tile_type: TileType,
+    x: u32,
+    y: u32,
+    elevation: i8,
+    ramp_direction: Option<RampDirection>,
+    variation: u8,
+    rotation: u8,
+    locked: bool,
+    movement_cost: Option<u8>,
+    corner_offsets: [i8; 4],
+}
+
+impl From<TileV12> for Tile {
+    fn from(v12: TileV12) -> Self {
+        Tile {
+            kind: v12.kind,
+            tile_type: v12.tile_type,
+            x: v12.x,
+            y: v12.y,
+            elevation: v12.elevation,
+            ramp_direction: v12.ramp_direction,
+            variation: v12.variation,
+            rotation: v12.rotation,
+            mirrored: false,
+            locked: v12.locked,
+            movement_cost: v12.movement_cost,
+            corner_offsets: v12.corner_offsets,
+        }
+    }
+}
+
+impl From<&Tile> for TileV12 {
+    fn from(tile: &Tile) -> Self {
+        TileV12 {
+            kind: tile.kind,
+            tile_type: tile.tile_type,
+            x: tile.x,
+            y: tile.y,
+            elevation: tile.elevation,
+            ramp_direction: tile.ramp_direction,
+            variation: tile.variation,
+            rotation: tile.rotation,
+            locked: tile.locked,
+            movement_cost: tile.movement_cost,
+            corner_offsets: tile.corner_offsets,
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+use crate::editor::{self, EditorState, MapEdit};
+use crate::localization::Localization;
+use crate::terrain::{
+    CORNER_NE, CORNER_NW, CORNER_SE, CORNER_SW, CornerHeightCache, TerrainMeshSet,
+};
+use crate::texture::registry::TerrainTextureRegistry;
+use crate::types::{MAX_ELEVATION, MIN_ELEVATION, Tile, TileKind, TileType};
+
+const TOGGLE_KEY: KeyCode = KeyCode::KeyK;
+const SHIFT_KEYS: [KeyCode; 2] = [KeyCode::ShiftLeft, KeyCode::ShiftRight];
+/// Seconds an arrow key is held before the cursor starts repeating.
+const REPEAT_DELAY: f32 = 0.35;
+/// Seconds between repeated cursor steps while an arrow key stays held.
+const REPEAT_INTERVAL: f32 = 0.07;
+const CURSOR_COLOR: Color = Color::srgb(0.2, 0.9, 1.0);
+
+/// Editing without the mouse: K toggles a tile cursor that the arrow keys
+/// move, with keys to paint, erase, raise, lower and turn ramps under it and
+/// to cycle the paint settings.
+pub struct KeyboardPlugin;
+
+impl Plugin for KeyboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<KeyboardMode>()
+            .add_systems(
+                Update,
+                keyboard_edit
+                    .in_set(TerrainMeshSet::Edit)
+                    .after(editor::update_hover),
+            )
+            .add_systems(
+                Update,
+                (draw_keyboard_cursor, keyboard_legend).after(TerrainMeshSet::Prepare),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct KeyboardMode {
+    pub enabled: bool,
+    pub cursor: (u32, u32),
+}
+
+/// Arrow keys and the cursor step each one makes.
+const ARROWS: [(KeyCode, (i32, i32)); 4] = [
+    (KeyCode::ArrowUp, (0, -1)),
+    (KeyCode::ArrowDown, (0, 1)),
+    (KeyCode::ArrowLeft, (-1, 0)),
+    (KeyCode::ArrowRight, (1, 0)),
+];
+
+fn cycle<T: Copy + PartialEq>(values: &[T], current: T, backwards: bool) -> T {
+    let index = values.iter().position(|&v| v == current).unwrap_or(0);
+    let next = if backwards {
+        (index + values.len() - 1) % values.len()
+    } else {
+        (index + 1) % values.len()
+    };
+    values[next]
+}
+
+/// The tile with its elevation moved by `step`, or `None` for holes and
+/// tiles already at the limit.
+fn stepped_tile(tile: &Tile, step: i8) -> Option<Tile> {
+    let elevation = (tile.elevation + step).clamp(MIN_ELEVATION, MAX_ELEVATION);
+    (tile.kind != TileKind::Empty && elevation != tile.elevation).then(|| Tile {
+        elevation,
+        ..tile.clone()
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn keyboard_edit(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut mode: ResMut<KeyboardMode>,
+    mut state: ResMut<EditorState>,
+    mut edits: EventWriter<MapEdit>,
+    mut egui: EguiContexts,
+    textures: Res<TerrainTextureRegistry>,
+    time: Res<Time>,
+    mut held: Local<f32>,
+) {
+    if egui.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    if keys.just_pressed(TOGGLE_KEY) {
+        mode.enabled = !mode.enabled;
+        if mode.enabled {
+            mode.cursor = state
+                .hover
+                .unwrap_or((state.map.width / 2, state.map.height / 2));
+        }
+    }
+    if !mode.enabled || state.map.width == 0 || state.map.height == 0 {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        mode.enabled = false;
+        return;
+    }
+
+    // Move on the first press, then repeat while the key stays down.
+    let pressed = ARROWS.iter().find(|(key, _)| keys.pressed(*key));
+    let step = match pressed {
+        Some((key, step)) if keys.just_pressed(*key) => {
+            *held = 0.0;
+            Some(*step)
+        }
+        Some((_, step)) => {
+            let before = *held;
+            *held += time.delta_seconds();
+            let repeats = |t: f32| ((t - REPEAT_DELAY) / REPEAT_INTERVAL).floor();
+            (*held > REPEAT_DELAY && repeats(*held) > repeats(before.max(REPEAT_DELAY)))
+                .then_some(*step)
+        }
+        None => None,
+    };
+    let (x, y) = mode.cursor;
+    let (x, y) = match step {
+        Some((dx, dy)) => (
+            x.saturating_add_signed(dx).min(state.map.width - 1),
+            y.saturating_add_signed(dy).min(state.map.height - 1),
+        ),
+        None => (x.min(state.map.width - 1), y.min(state.map.height - 1)),
+    };
+    mode.cursor = (x, y);
+
+    let backwards = keys.any_pressed(SHIFT_KEYS);
+    if keys.just_pressed(KeyCode::KeyE) {
+        let levels: Vec<i8> = (MIN_ELEVATION..=MAX_ELEVATION).collect();
+        state.current_elev = cycle(&levels, state.current_elev, backwards);
+    }
+    if keys.just_pressed(KeyCode::KeyT) {
+        let layers: Vec<TileType> = textures.layers().collect();
+        state.current_texture = cycle(&layers, state.current_texture, backwards);
+    }
+    if keys.just_pressed(KeyCode::KeyQ) {
+        state.current_kind = cycle(
+            &[TileKind::Floor, TileKind::Ramp],
+            state.current_kind,
+            false,
+        );
+    }
+
+    let current = state.map.get(x, y);
+    let tile = if keys.just_pressed(KeyCode::Enter) {
+        editor::painted_tile(&state, x, y)
+    } else if keys.any_just_pressed([KeyCode::Delete, KeyCode::Backspace]) {
+        (current.kind != TileKind::Empty).then(|| Tile {
+            kind: TileKind::Empty,
+            ramp_direction: None,
+            ..current.clone()
+        })
+    } else if keys.just_pressed(KeyCode::PageUp) {
+        stepped_tile(current, 1)
+    } else if keys.just_pressed(KeyCode::PageDown) {
+        stepped_tile(current, -1)
+    } else if keys.just_pressed(KeyCode::KeyR) {
+        editor::rotated_ramp(&state.map, x, y)
+    } else {
+        None
+    };
+    if let Some(tile) = tile {
+        edits.send(MapEdit::SetTile { x, y, tile });
+    }
+}
+
+fn draw_keyboard_cursor(
+    mode: Res<KeyboardMode>,
+    heights: Res<CornerHeightCache>,
+    mut gizmos: Gizmos,
+) {
+    if !mode.enabled {
+        return;
+    }
+    let (x, y) = mode.cursor;
+    // Holes have no corners, so their cursor lies on the ground plane.
+    let corners = heights.corners(x, y).unwrap_or([0.0; 4]);
+    let size = heights.tile_size();
+    let lift = 0.05;
+    let (x0, z0) = (x as f32 * size, y as f32 * size);
+    let (x1, z1) = (x0 + size, z0 + size);
+    gizmos.linestrip(
+        [
+            Vec3::new(x0, corners[CORNER_NW] + lift, z0),
+            Vec3::new(x1, corners[CORNER_NE] + lift, z0),
+            Vec3::new(x1, corners[CORNER_SE] + lift, z1),
+            Vec3::new(x0, corners[CORNER_SW] + lift, z1),
+            Vec3::new(x0, corners[CORNER_NW] + lift, z0),
+        ],
+        CURSOR_COLOR,
+    );
+}
+
+/// Lists the keys while the mode is on, with the paint settings they cycle.
+fn keyboard_legend(
+    mut mode: ResMut<KeyboardMode>,
+    state: Res<EditorState>,
+    mut egui: EguiContexts,
+    localization: Res<Localization>,
+) {
+    if !mode.enabled {
+        return;
+    }
+    let loc = &*localization;
+    let (x, y) = mode.cursor;
+    let kind = match state.current_kind {
+        TileKind::Ramp => loc.tr("kind.ramp"),
+        _ => loc.tr("kind.floor"),
+    };
+    egui::Area::new(egui::Id::new("keyboard_legend"))
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(12.0, -12.0))
+        .show(egui.ctx_mut(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.strong(loc.format("keyboard.title", &[("x", &x), ("y", &y)]));
+                ui.label(loc.format(
+                    "keyboard.settings",
+                    &[
+                        ("kind", &kind),
+                        ("elevation", &state.current_elev),
+                        ("texture", &state.current_texture.identifier()),
+                    ],
+                ));
+                ui.separator();
+                for key in [
+                    "keyboard.move",
+                    "keyboard.paint",
+                    "keyboard.raise",
+                    "keyboard.rotate",
+                    "keyboard.cycle",
+                ] {
+                    ui.small(loc.tr(key));
+                }
+                if ui.small_button(loc.tr("keyboard.exit")).clicked() {
+                    mode.enabled = false;
+                }
+            });
+        });
+}
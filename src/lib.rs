@@ -0,0 +1,108 @@
+//! Tile map editing and rendering. The editor binary assembles every plugin
+//! here; games only need the map types, `RuntimePlugin`, `WaterPlugin`,
+//! `DecalPlugin`, `PropPlugin` and the texture registry to load and draw
+//! maps.
+//!
+//! The `runtime` feature builds just that half. The `editor` feature, on by
+//! default, adds the editor's plugins along with egui and the file dialogs;
+//! games can depend on the crate with `default-features = false, features =
+//! ["runtime"]` to leave them out. The `colliders` feature adds a physics
+//! collider to the runtime terrain.
+//!
+//! Without Bevy's app at all, `io::load_map` or `io::decode_map` read a
+//! `types::TileMap`, `terrain::build_combined_mesh` meshes it,
+//! `terrain::splatmap` paints its texture weights and
+//! `TileMap::reachability` links its walkable tiles, all in the always-built
+//! modules.
+
+#[cfg(feature = "editor")]
+pub mod asset_browser;
+#[cfg(feature = "editor")]
+pub mod bundle;
+#[cfg(feature = "editor")]
+pub mod camera;
+#[cfg(feature = "editor")]
+pub mod collab;
+#[cfg(feature = "colliders")]
+pub mod colliders;
+#[cfg(feature = "editor")]
+pub mod controls;
+#[cfg(feature = "editor")]
+pub mod culling;
+#[cfg(feature = "editor")]
+pub mod debug;
+#[cfg(feature = "runtime")]
+pub mod decals;
+#[cfg(feature = "editor")]
+pub mod editor;
+#[cfg(feature = "editor")]
+pub mod export;
+#[cfg(feature = "editor")]
+pub mod file_export;
+#[cfg(feature = "editor")]
+pub mod generators;
+#[cfg(feature = "editor")]
+pub mod grid_visual;
+#[cfg(feature = "editor")]
+pub mod input;
+pub mod io;
+#[cfg(feature = "editor")]
+pub mod keyboard;
+#[cfg(feature = "runtime")]
+pub mod lightmap;
+#[cfg(feature = "editor")]
+pub mod lights;
+#[cfg(feature = "editor")]
+pub mod localization;
+#[cfg(feature = "editor")]
+pub mod macros;
+#[cfg(feature = "editor")]
+pub mod map_browser;
+#[cfg(feature = "editor")]
+pub mod map_import;
+#[cfg(feature = "editor")]
+pub mod new_map;
+#[cfg(feature = "runtime")]
+pub mod notifications;
+#[cfg(feature = "editor")]
+pub mod ortho_export;
+#[cfg(feature = "runtime")]
+pub mod picking;
+#[cfg(feature = "editor")]
+pub mod players;
+#[cfg(feature = "runtime")]
+pub mod props;
+pub mod reachability;
+#[cfg(feature = "editor")]
+pub mod recent;
+#[cfg(feature = "editor")]
+pub mod reference;
+pub mod roads;
+#[cfg(feature = "editor")]
+pub mod rulers;
+#[cfg(feature = "runtime")]
+pub mod runtime;
+#[cfg(feature = "editor")]
+pub mod selection;
+#[cfg(feature = "runtime")]
+pub mod spatial;
+pub mod stats;
+pub mod terrain;
+pub mod text_map;
+#[cfg(feature = "runtime")]
+pub mod texture;
+#[cfg(feature = "editor")]
+pub mod tools;
+#[cfg(feature = "editor")]
+pub mod triggers;
+pub mod types;
+#[cfg(feature = "editor")]
+pub mod ui;
+#[cfg(feature = "editor")]
+pub mod unsaved;
+#[cfg(feature = "editor")]
+pub mod vegetation;
+#[cfg(feature = "runtime")]
+pub mod water;
+#[cfg(feature = "editor")]
+pub mod weather;
@@ -0,0 +1,218 @@
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
+use bevy::render::texture::{
+    ImageAddressMode, ImageFilterMode, ImageSampler, ImageSamplerDescriptor,
+};
+
+#[cfg(feature = "editor")]
+use crate::editor::EditorState;
+use crate::terrain::CornerHeightCache;
+#[cfg(feature = "editor")]
+use crate::terrain::{TerrainChanged, TerrainMeshSet};
+use crate::types::TileMap;
+
+/// Lightmap texels along each side of a tile.
+const TEXELS_PER_TILE: u32 = 4;
+/// Horizon directions sampled when estimating sky occlusion.
+const SKY_DIRECTIONS: usize = 8;
+/// How far (in tiles) the sky occlusion search looks for occluders.
+const SKY_SEARCH_TILES: f32 = 6.0;
+/// Lift applied to the surface point so a texel does not shadow itself.
+const SURFACE_BIAS: f32 = 0.05;
+/// File name of the lightmap in an export package, next to `tilemap.json`.
+pub const LIGHTMAP_FILE: &str = "lightmap.png";
+
+/// Bakes the editor map on request, and drops the bake once the terrain
+/// changes under it. Games get the lightmap exported next to their map from
+/// `RuntimePlugin` instead.
+#[cfg(feature = "editor")]
+pub struct LightmapPlugin;
+
+#[cfg(feature = "editor")]
+impl Plugin for LightmapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BakeLightmap>().add_systems(
+            Update,
+            bake_requested_lightmap.in_set(TerrainMeshSet::Splat),
+        );
+    }
+}
+
+/// Requests a lightmap bake for the current map using the scene's
+/// directional light as the sun.
+#[cfg(feature = "editor")]
+#[derive(Event, Clone, Copy, Default)]
+pub struct BakeLightmap;
+
+/// The most recently baked lightmap. Red holds direct sun visibility, green
+/// holds sky visibility; both are sampled by the terrain material.
+#[derive(Resource)]
+pub struct BakedLightmap {
+    pub handle: Handle<Image>,
+}
+
+#[cfg(feature = "editor")]
+#[allow(clippy::too_many_arguments)]
+fn bake_requested_lightmap(
+    mut commands: Commands,
+    mut requests: EventReader<BakeLightmap>,
+    mut changed: EventReader<TerrainChanged>,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    lights: Query<&GlobalTransform, With<DirectionalLight>>,
+    mut images: ResMut<Assets<Image>>,
+    existing: Option<Res<BakedLightmap>>,
+) {
+    let terrain_changed = !changed.is_empty();
+    changed.clear();
+    if requests.is_empty() {
+        // A bake of the old terrain would shade the new one wrongly.
+        if terrain_changed && existing.is_some() {
+            commands.remove_resource::<BakedLightmap>();
+        }
+        return;
+    }
+    requests.clear();
+
+    let sun_direction = lights
+        .iter()
+        .next()
+        .map(|transform| -transform.forward().as_vec3())
+        .unwrap_or(Vec3::Y);
+
+    let image = bake(&state.map, &heights, sun_direction);
+    match existing.and_then(|baked| images.get_mut(&baked.handle)) {
+        Some(target) => *target = image,
+        None => {
+            let handle = images.add(image);
+            commands.insert_resource(BakedLightmap { handle });
+        }
+    }
+    info!(
+        "Baked {}x{} lightmap",
+        state.map.width * TEXELS_PER_TILE,
+        state.map.height * TEXELS_PER_TILE
+    );
+}
+
+/// Computes sun and sky visibility for every lightmap texel by marching rays
+/// across the heightfield.
+pub fn bake(map: &TileMap, heights: &CornerHeightCache, sun_direction: Vec3) -> Image {
+    let width = (map.width * TEXELS_PER_TILE).max(1);
+    let height = (map.height * TEXELS_PER_TILE).max(1);
+    let sun_direction = sun_direction.normalize_or_zero();
+    let ceiling = max_surface_height(map, heights);
+    let texel_size = map.tile_size / TEXELS_PER_TILE as f32;
+
+    let mut data = vec![0u8; (width * height * 4) as usize];
+    for ty in 0..height {
+        for tx in 0..width {
+            let world_x = (tx as f32 + 0.5) * texel_size;
+            let world_z = (ty as f32 + 0.5) * texel_size;
+            let Some(surface) = heights.height_at(world_x, world_z) else {
+                continue;
+            };
+            let origin = Vec3::new(world_x, surface + SURFACE_BIAS, world_z);
+
+            let sun = if sun_blocked(heights, origin, sun_direction, ceiling) {
+                0.0
+            } else {
+                1.0
+            };
+            let sky = sky_visibility(heights, origin);
+
+            let idx = ((ty * width + tx) * 4) as usize;
+            data[idx] = (sun * 255.0) as u8;
+            data[idx + 1] = (sky * 255.0).round() as u8;
+            data[idx + 3] = 255;
+        }
+    }
+
+    let mut image = Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST;
+    image.sampler = sampler();
+    image
+}
+
+/// How the terrain material samples a lightmap, baked or loaded.
+pub fn sampler() -> ImageSampler {
+    ImageSampler::Descriptor(ImageSamplerDescriptor {
+        mag_filter: ImageFilterMode::Linear,
+        min_filter: ImageFilterMode::Linear,
+        address_mode_u: ImageAddressMode::ClampToEdge,
+        address_mode_v: ImageAddressMode::ClampToEdge,
+        ..Default::default()
+    })
+}
+
+fn max_surface_height(map: &TileMap, heights: &CornerHeightCache) -> f32 {
+    let mut ceiling = f32::NEG_INFINITY;
+    for y in 0..map.height {
+        for x in 0..map.width {
+            if let Some(corners) = heights.corners(x, y) {
+                ceiling = corners.into_iter().fold(ceiling, f32::max);
+            }
+        }
+    }
+    ceiling
+}
+
+fn sun_blocked(heights: &CornerHeightCache, origin: Vec3, direction: Vec3, ceiling: f32) -> bool {
+    if direction.y <= 0.0 {
+        return true;
+    }
+
+    let step = heights.tile_size() / TEXELS_PER_TILE as f32;
+    let mut distance = step;
+    loop {
+        let point = origin + direction * distance;
+        if point.y > ceiling {
+            return false;
+        }
+        match heights.height_at(point.x, point.z) {
+            Some(height) if height > point.y => return true,
+            Some(_) => {}
+            None => return false,
+        }
+        distance += step;
+    }
+}
+
+/// Fraction of the sky hemisphere left open, estimated from the steepest
+/// horizon angle in a fixed set of directions.
+fn sky_visibility(heights: &CornerHeightCache, origin: Vec3) -> f32 {
+    let step = heights.tile_size() / TEXELS_PER_TILE as f32;
+    let max_distance = SKY_SEARCH_TILES * heights.tile_size();
+    let mut occlusion = 0.0;
+
+    for i in 0..SKY_DIRECTIONS {
+        let angle = i as f32 / SKY_DIRECTIONS as f32 * std::f32::consts::TAU;
+        let direction = Vec2::new(angle.cos(), angle.sin());
+        let mut max_slope = 0.0f32;
+        let mut distance = step;
+        while distance <= max_distance {
+            let x = origin.x + direction.x * distance;
+            let z = origin.z + direction.y * distance;
+            let Some(height) = heights.height_at(x, z) else {
+                break;
+            };
+            max_slope = max_slope.max((height - origin.y) / distance);
+            distance += step;
+        }
+        // sin(atan(slope)) is the portion of this direction's sky arc that is blocked.
+        occlusion += max_slope / (1.0 + max_slope * max_slope).sqrt();
+    }
+
+    (1.0 - occlusion / SKY_DIRECTIONS as f32).clamp(0.0, 1.0)
+}
@@ -0,0 +1,153 @@
+use bevy::prelude::*;
+
+use crate::editor::EditorState;
+use crate::terrain::{CornerHeightCache, TerrainChanged, TerrainMeshSet};
+use crate::types::{LightKind, MapLight};
+
+const MARKER_RADIUS: f32 = 0.25;
+
+/// Spawns a real light for every light placed on the map so its effect on
+/// the terrain can be judged while editing.
+pub struct LightsPlugin;
+
+impl Plugin for LightsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LightPreview>().add_systems(
+            Update,
+            (sync_light_preview, draw_light_markers).after(TerrainMeshSet::Prepare),
+        );
+    }
+}
+
+/// The lights the spawned preview entities were built from.
+#[derive(Resource, Default)]
+struct LightPreview {
+    lights: Vec<MapLight>,
+    visible: bool,
+    entities: Vec<Entity>,
+}
+
+/// World position of a placed light, or `None` when its tile is gone.
+pub fn light_position(light: &MapLight, heights: &CornerHeightCache) -> Option<Vec3> {
+    let corners = heights.corners(light.x, light.y)?;
+    Some(Vec3::new(
+        (light.x as f32 + 0.5) * heights.tile_size(),
+        corners.iter().sum::<f32>() / 4.0 + light.height,
+        (light.y as f32 + 0.5) * heights.tile_size(),
+    ))
+}
+
+/// Orientation that points a spot light's -Z axis along its facing and
+/// pitch.
+pub fn light_rotation(light: &MapLight) -> Quat {
+    Quat::from_euler(
+        EulerRot::YXZ,
+        -light.facing.to_radians(),
+        -light.pitch.to_radians(),
+        0.0,
+    )
+}
+
+/// Unit vector a spot light shines along.
+pub fn light_direction(light: &MapLight) -> Vec3 {
+    light_rotation(light) * Vec3::NEG_Z
+}
+
+fn light_color(light: &MapLight) -> Color {
+    let [r, g, b] = light.color;
+    Color::srgb_u8(r, g, b)
+}
+
+/// Respawns the preview lights when the placed lights, their visibility or
+/// the terrain under them change.
+fn sync_light_preview(
+    mut commands: Commands,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    mut terrain_changed: EventReader<TerrainChanged>,
+    mut preview: ResMut<LightPreview>,
+) {
+    let moved = !terrain_changed.is_empty();
+    terrain_changed.clear();
+    if !moved && preview.visible == state.show_lights && preview.lights == state.map.lights {
+        return;
+    }
+
+    for entity in preview.entities.drain(..) {
+        commands.entity(entity).despawn_recursive();
+    }
+    preview.lights.clone_from(&state.map.lights);
+    preview.visible = state.show_lights;
+    if !state.show_lights {
+        return;
+    }
+
+    for light in &state.map.lights {
+        let Some(position) = light_position(light, &heights) else {
+            continue;
+        };
+        let transform = Transform::from_translation(position).with_rotation(light_rotation(light));
+        let color = light_color(light);
+        let entity = match light.kind {
+            LightKind::Point => commands.spawn(PointLightBundle {
+                point_light: PointLight {
+                    color,
+                    intensity: light.intensity,
+                    range: light.range,
+                    ..default()
+                },
+                transform,
+                ..default()
+            }),
+            LightKind::Spot => {
+                let outer_angle = (light.cone * 0.5).to_radians();
+                commands.spawn(SpotLightBundle {
+                    spot_light: SpotLight {
+                        color,
+                        intensity: light.intensity,
+                        range: light.range,
+                        outer_angle,
+                        inner_angle: outer_angle * 0.8,
+                        ..default()
+                    },
+                    transform,
+                    ..default()
+                })
+            }
+        }
+        .insert(Name::new("MapLightPreview"))
+        .id();
+        preview.entities.push(entity);
+    }
+}
+
+/// Marks each light with a small sphere in its color, plus an arrow for
+/// spot lights.
+fn draw_light_markers(
+    mut gizmos: Gizmos,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+) {
+    if !state.show_lights {
+        return;
+    }
+    for light in &state.map.lights {
+        let Some(position) = light_position(light, &heights) else {
+            continue;
+        };
+        let color = light_color(light);
+        gizmos.sphere(position, Quat::IDENTITY, MARKER_RADIUS, color);
+        gizmos.line(
+            position,
+            position - Vec3::Y * light.height,
+            color.with_alpha(0.4),
+        );
+        if light.kind == LightKind::Spot {
+            gizmos.arrow(
+                position,
+                position + light_direction(light) * heights.tile_size(),
+                color,
+            );
+        }
+    }
+}
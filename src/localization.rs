@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+
+use bevy::prelude::*;
+
+/// Language every key is expected to exist in, and the one missing
+/// translations fall back to.
+pub const FALLBACK_LANGUAGE: &str = "en";
+/// Key holding a language's own name, shown in the language picker.
+const NAME_KEY: &str = "language.name";
+const LANGUAGE_DIR: &str = "assets/lang";
+
+/// Looks up user-facing UI text in per-language string tables. English is
+/// built in; `assets/lang/<code>.json` files add languages or override
+/// entries, and plugins can contribute their own strings with
+/// [`LocalizationAppExt::add_strings`].
+pub struct LocalizationPlugin;
+
+impl Plugin for LocalizationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Localization>()
+            .add_systems(PreStartup, load_language_files);
+    }
+}
+
+#[derive(Resource)]
+pub struct Localization {
+    language: String,
+    tables: HashMap<String, HashMap<String, String>>,
+}
+
+impl Default for Localization {
+    fn default() -> Self {
+        let english: HashMap<String, String> =
+            serde_json::from_str(include_str!("../assets/lang/en.json"))
+                .expect("built-in English strings are valid JSON");
+        Self {
+            language: FALLBACK_LANGUAGE.to_string(),
+            tables: HashMap::from([(FALLBACK_LANGUAGE.to_string(), english)]),
+        }
+    }
+}
+
+impl Localization {
+    /// The text for `key` in the current language, falling back to English
+    /// and then to the key itself so missing strings stay visible.
+    pub fn tr<'a>(&'a self, key: &'a str) -> &'a str {
+        [self.language.as_str(), FALLBACK_LANGUAGE]
+            .into_iter()
+            .find_map(|language| self.tables.get(language)?.get(key))
+            .map_or(key, String::as_str)
+    }
+
+    /// Like [`tr`](Self::tr), replacing each `{name}` placeholder with its
+    /// argument.
+    pub fn format(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        args.iter()
+            .fold(self.tr(key).to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{name}}}"), &value.to_string())
+            })
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Switches the UI language. Unknown languages are ignored.
+    pub fn set_language(&mut self, language: &str) {
+        if self.tables.contains_key(language) {
+            self.language = language.to_string();
+        }
+    }
+
+    /// Available languages as `(code, name)` pairs, sorted by code.
+    pub fn languages(&self) -> Vec<(&str, &str)> {
+        let mut languages: Vec<_> = self
+            .tables
+            .iter()
+            .map(|(code, table)| {
+                let name = table.get(NAME_KEY).map_or(code.as_str(), String::as_str);
+                (code.as_str(), name)
+            })
+            .collect();
+        languages.sort_unstable();
+        languages
+    }
+
+    /// Adds strings to a language, creating it if needed. Existing entries
+    /// with the same key are replaced.
+    pub fn extend<K, V>(&mut self, language: &str, strings: impl IntoIterator<Item = (K, V)>)
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.tables
+            .entry(language.to_string())
+            .or_default()
+            .extend(strings.into_iter().map(|(k, v)| (k.into(), v.into())));
+    }
+}
+
+/// Lets plugins register strings for their own UI while the app is built.
+/// Language files loaded at startup can still override them.
+pub trait LocalizationAppExt {
+    fn add_strings<K, V>(
+        &mut self,
+        language: &str,
+        strings: impl IntoIterator<Item = (K, V)>,
+    ) -> &mut Self
+    where
+        K: Into<String>,
+        V: Into<String>;
+}
+
+impl LocalizationAppExt for App {
+    fn add_strings<K, V>(
+        &mut self,
+        language: &str,
+        strings: impl IntoIterator<Item = (K, V)>,
+    ) -> &mut Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.init_resource::<Localization>();
+        self.world_mut()
+            .resource_mut::<Localization>()
+            .extend(language, strings);
+        self
+    }
+}
+
+/// Reads every `<code>.json` file in the language directory, a flat object
+/// of keys to text.
+fn load_language_files(mut localization: ResMut<Localization>) {
+    let Ok(entries) = std::fs::read_dir(LANGUAGE_DIR) else {
+        return;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(code) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        match read_language_file(&path) {
+            Ok(strings) => localization.extend(code, strings),
+            Err(err) => warn!("Skipping language file {}: {err}", path.display()),
+        }
+    }
+}
+
+fn read_language_file(path: &Path) -> anyhow::Result<HashMap<String, String>> {
+    let bytes = std::fs::read(path)?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
@@ -0,0 +1,387 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+use serde::{Deserialize, Serialize};
+
+use crate::editor::{self, EditorState, EditorTool, MapEdit};
+use crate::localization::Localization;
+use crate::notifications::Notify;
+use crate::terrain::{CornerHeightCache, TerrainMeshSet};
+use crate::tools::outline_tile;
+use crate::types::{MAX_ELEVATION, MIN_ELEVATION, RampDirection, TileKind, TileMap, TileType};
+
+/// Folder macros are saved to, one JSON file each.
+const MACRO_DIR: &str = "assets/macros";
+const PREVIEW_COLOR: Color = Color::srgb(0.6, 1.0, 0.4);
+/// Furthest the elevation offset can shift a replayed macro, in levels.
+const MAX_ELEVATION_OFFSET: i8 = MAX_ELEVATION - MIN_ELEVATION;
+
+/// Records the tile edits made between pressing Record and Stop as a named
+/// macro on disk; the Macro tool replays the chosen one with its first tile
+/// under the cursor.
+pub struct MacrosPlugin;
+
+impl Plugin for MacrosPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MacroLibrary>()
+            .add_systems(Startup, load_macro_library)
+            .add_systems(
+                Update,
+                (
+                    place_macro
+                        .in_set(TerrainMeshSet::Edit)
+                        .after(editor::update_hover),
+                    record_macro_edits
+                        .in_set(TerrainMeshSet::Apply)
+                        .before(editor::apply_map_edits),
+                    macro_window,
+                ),
+            );
+    }
+}
+
+/// A recorded sequence of tile edits, positioned relative to the first tile
+/// it changed (its anchor) so it can be replayed anywhere on the map.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EditMacro {
+    pub name: String,
+    pub steps: Vec<MacroStep>,
+}
+
+/// The final state of one tile touched while recording.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MacroStep {
+    pub dx: i32,
+    pub dy: i32,
+    /// Levels above the anchor tile as it was before recording started.
+    pub elevation: i8,
+    pub kind: TileKind,
+    pub tile_type: TileType,
+    #[serde(default)]
+    pub ramp_direction: Option<RampDirection>,
+    #[serde(default)]
+    pub variation: u8,
+    #[serde(default)]
+    pub rotation: u8,
+    #[serde(default)]
+    pub mirrored: bool,
+}
+
+impl EditMacro {
+    /// Edits replaying the macro with its anchor on `at`. Elevations are
+    /// measured from the tile at `at` and shifted by `elevation_offset`;
+    /// steps falling off the map are dropped.
+    pub fn edits(&self, map: &TileMap, at: (u32, u32), elevation_offset: i8) -> Vec<MapEdit> {
+        let base = map.get(at.0, at.1).elevation + elevation_offset;
+        self.footprint(map, at)
+            .map(|((x, y), step)| {
+                let current = map.get(x, y);
+                let mut tile = current.clone();
+                tile.kind = step.kind;
+                tile.tile_type = step.tile_type;
+                tile.elevation = (base + step.elevation).clamp(MIN_ELEVATION, MAX_ELEVATION);
+                tile.ramp_direction = step.ramp_direction;
+                tile.variation = step.variation;
+                tile.rotation = step.rotation;
+                tile.mirrored = step.mirrored;
+                MapEdit::SetTile { x, y, tile }
+            })
+            .collect()
+    }
+
+    /// The map tiles the macro covers with its anchor on `at`, with the step
+    /// landing on each.
+    fn footprint<'a>(
+        &'a self,
+        map: &'a TileMap,
+        at: (u32, u32),
+    ) -> impl Iterator<Item = ((u32, u32), &'a MacroStep)> + 'a {
+        self.steps.iter().filter_map(move |step| {
+            let (x, y) = (at.0 as i64 + step.dx as i64, at.1 as i64 + step.dy as i64);
+            let on_map = x >= 0 && y >= 0 && (x as u32) < map.width && (y as u32) < map.height;
+            on_map.then_some(((x as u32, y as u32), step))
+        })
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+        let path = dir.join(format!("{}.json", file_stem(&self.name)));
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("invalid macro {}", path.display()))
+    }
+}
+
+/// File name for a macro called `name`, keeping only characters that are
+/// safe in paths on every platform.
+fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Every macro saved in `dir`, sorted by name. Files that fail to parse are
+/// skipped with a warning.
+pub fn load_macros(dir: &Path) -> Vec<EditMacro> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut macros: Vec<EditMacro> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            EditMacro::load(&path)
+                .map_err(|err| warn!("Skipping macro: {err:#}"))
+                .ok()
+        })
+        .collect();
+    macros.sort_by(|a, b| a.name.cmp(&b.name));
+    macros
+}
+
+#[derive(Resource, Default)]
+pub struct MacroLibrary {
+    pub macros: Vec<EditMacro>,
+    /// Index into `macros` of the one the Macro tool places.
+    pub selected: Option<usize>,
+    pub elevation_offset: i8,
+    recording: Option<Recording>,
+    /// Name typed for the next recording.
+    name: String,
+}
+
+#[derive(Default)]
+struct Recording {
+    /// First edited tile and its elevation before it was edited.
+    anchor: Option<((u32, u32), i8)>,
+    steps: Vec<MacroStep>,
+}
+
+impl MacroLibrary {
+    fn selected_macro(&self) -> Option<&EditMacro> {
+        self.selected.and_then(|index| self.macros.get(index))
+    }
+
+    /// Adds `edit`, replacing a macro with the same name, and selects it.
+    fn insert(&mut self, edit: EditMacro) {
+        let name = edit.name.clone();
+        self.macros.retain(|existing| existing.name != name);
+        self.macros.push(edit);
+        self.macros.sort_by(|a, b| a.name.cmp(&b.name));
+        self.selected = self.macros.iter().position(|edit| edit.name == name);
+    }
+}
+
+fn load_macro_library(mut library: ResMut<MacroLibrary>) {
+    library.macros = load_macros(Path::new(MACRO_DIR));
+}
+
+/// Adds tile edits to the recording as they are sent, before they are
+/// applied, so each step is measured against the map it changed.
+fn record_macro_edits(
+    mut edits: EventReader<MapEdit>,
+    mut library: ResMut<MacroLibrary>,
+    state: Res<EditorState>,
+) {
+    let Some(recording) = library.recording.as_mut() else {
+        edits.clear();
+        return;
+    };
+    for edit in edits.read() {
+        let MapEdit::SetTile { x, y, tile } = edit else {
+            continue;
+        };
+        let (x, y) = (*x, *y);
+        if x >= state.map.width || y >= state.map.height {
+            continue;
+        }
+        // Skip edits `apply_map_edits` will ignore.
+        let current = state.map.get(x, y);
+        if current.locked || *current == *tile {
+            continue;
+        }
+        let ((ax, ay), base) = *recording.anchor.get_or_insert(((x, y), current.elevation));
+        let step = MacroStep {
+            dx: x as i32 - ax as i32,
+            dy: y as i32 - ay as i32,
+            elevation: tile.elevation - base,
+            kind: tile.kind,
+            tile_type: tile.tile_type,
+            ramp_direction: tile.ramp_direction,
+            variation: tile.variation,
+            rotation: tile.rotation,
+            mirrored: tile.mirrored,
+        };
+        // Only the last edit of each tile matters on replay.
+        match recording
+            .steps
+            .iter_mut()
+            .find(|existing| (existing.dx, existing.dy) == (step.dx, step.dy))
+        {
+            Some(existing) => *existing = step,
+            None => recording.steps.push(step),
+        }
+    }
+}
+
+/// Outlines where the selected macro would land and replays it on click.
+fn place_macro(
+    buttons: Res<ButtonInput<MouseButton>>,
+    state: Res<EditorState>,
+    library: Res<MacroLibrary>,
+    heights: Res<CornerHeightCache>,
+    mut edits: EventWriter<MapEdit>,
+    mut gizmos: Gizmos,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Macro {
+        return;
+    }
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let (Some(at), Some(edit)) = (state.hover, library.selected_macro()) else {
+        return;
+    };
+
+    if buttons.just_pressed(MouseButton::Left) {
+        edits.send_batch(edit.edits(&state.map, at, library.elevation_offset));
+        return;
+    }
+    for ((x, y), _) in edit.footprint(&state.map, at) {
+        outline_tile(&mut gizmos, &heights, x, y, PREVIEW_COLOR);
+    }
+}
+
+fn macro_window(
+    mut egui: EguiContexts,
+    mut library: ResMut<MacroLibrary>,
+    mut state: ResMut<EditorState>,
+    mut notify: EventWriter<Notify>,
+    localization: Res<Localization>,
+) {
+    let loc = &*localization;
+    let ctx = egui.ctx_mut().clone();
+    let library = &mut *library;
+
+    egui::Window::new(loc.tr("macro.title"))
+        .id(egui::Id::new("macros"))
+        .default_open(false)
+        .show(&ctx, |ui| {
+            match library.recording.as_ref() {
+                None => {
+                    ui.horizontal(|ui| {
+                        ui.label(loc.tr("macro.name"));
+                        ui.text_edit_singleline(&mut library.name);
+                    });
+                    let named = !library.name.trim().is_empty();
+                    if ui
+                        .add_enabled(named, egui::Button::new(loc.tr("macro.record")))
+                        .on_hover_text(loc.tr("macro.record.hint"))
+                        .clicked()
+                    {
+                        library.recording = Some(Recording::default());
+                    }
+                }
+                Some(recording) => {
+                    let count = recording.steps.len();
+                    ui.colored_label(
+                        egui::Color32::from_rgb(230, 80, 80),
+                        loc.format(
+                            "macro.recording",
+                            &[("name", &library.name.trim()), ("count", &count)],
+                        ),
+                    );
+                    ui.horizontal(|ui| {
+                        if ui.button(loc.tr("macro.stop")).clicked() {
+                            let recording = library.recording.take().unwrap_or_default();
+                            if recording.steps.is_empty() {
+                                notify.send(Notify::warning(loc.tr("notify.macro_empty")));
+                                return;
+                            }
+                            let edit = EditMacro {
+                                name: library.name.trim().to_string(),
+                                steps: recording.steps,
+                            };
+                            match edit.save(Path::new(MACRO_DIR)) {
+                                Ok(path) => notify.send(Notify::info(
+                                    loc.format("notify.macro_saved", &[("path", &path.display())]),
+                                )),
+                                Err(err) => notify.send(Notify::error(
+                                    loc.format("notify.macro_save_failed", &[("error", &err)]),
+                                )),
+                            };
+                            library.insert(edit);
+                            library.name.clear();
+                        }
+                        if ui.button(loc.tr("macro.cancel")).clicked() {
+                            library.recording = None;
+                        }
+                    });
+                }
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label(loc.tr("macro.elevation_offset"));
+                ui.add(
+                    egui::DragValue::new(&mut library.elevation_offset)
+                        .range(-MAX_ELEVATION_OFFSET..=MAX_ELEVATION_OFFSET),
+                );
+            });
+            if library.macros.is_empty() {
+                ui.weak(loc.tr("macro.none"));
+            }
+            let mut deleted = None;
+            for (index, edit) in library.macros.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    let label = loc.format(
+                        "macro.entry",
+                        &[("name", &edit.name), ("count", &edit.steps.len())],
+                    );
+                    if ui
+                        .selectable_label(library.selected == Some(index), label)
+                        .on_hover_text(loc.tr("macro.entry.hint"))
+                        .clicked()
+                    {
+                        library.selected = Some(index);
+                        state.current_tool = EditorTool::Macro;
+                    }
+                    if ui.small_button(loc.tr("macro.delete")).clicked() {
+                        deleted = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = deleted {
+                let edit = library.macros.remove(index);
+                let path = Path::new(MACRO_DIR).join(format!("{}.json", file_stem(&edit.name)));
+                if let Err(err) = std::fs::remove_file(&path) {
+                    notify.send(Notify::error(loc.format(
+                        "notify.macro_delete_failed",
+                        &[("path", &path.display()), ("error", &err)],
+                    )));
+                }
+                library.selected = match library.selected {
+                    Some(selected) if selected == index => None,
+                    Some(selected) if selected > index => Some(selected - 1),
+                    selected => selected,
+                };
+            }
+        });
+}
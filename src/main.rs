@@ -1,49 +1,126 @@
-mod camera;
-mod controls;
-mod debug;
-mod editor;
-mod export;
-mod grid_visual;
-mod io;
-mod runtime;
-mod terrain;
-mod texture;
-mod types;
-mod ui;
+use std::path::Path;
 
-use crate::debug::asset::image_inspector::ImageInspectorPlugin;
-use crate::texture::material;
 use bevy::prelude::*;
 use bevy_egui::EguiPlugin;
-use camera::CameraPlugin;
-use controls::ControlsPlugin;
-use editor::EditorPlugin;
-use runtime::RuntimePlugin;
-use texture::TexturePlugin;
-use ui::UiPlugin;
+use dprmapedit::asset_browser::AssetBrowserPlugin;
+use dprmapedit::bundle::BundlePlugin;
+use dprmapedit::camera::CameraPlugin;
+use dprmapedit::collab::CollabPlugin;
+use dprmapedit::controls::ControlsPlugin;
+use dprmapedit::culling::TerrainCullingPlugin;
+use dprmapedit::debug::asset::image_inspector::ImageInspectorPlugin;
+use dprmapedit::decals::DecalPlugin;
+use dprmapedit::editor::EditorPlugin;
+use dprmapedit::export;
+use dprmapedit::file_export::FileExportPlugin;
+use dprmapedit::generators::GeneratorPlugin;
+use dprmapedit::grid_visual::GridVisualPlugin;
+use dprmapedit::input::InputPlugin;
+use dprmapedit::io::load_map;
+use dprmapedit::keyboard::KeyboardPlugin;
+use dprmapedit::lightmap::{self, LightmapPlugin};
+use dprmapedit::lights::LightsPlugin;
+use dprmapedit::localization::LocalizationPlugin;
+use dprmapedit::macros::MacrosPlugin;
+use dprmapedit::map_browser::MapBrowserPlugin;
+use dprmapedit::map_import::MapImportPlugin;
+use dprmapedit::new_map::NewMapPlugin;
+use dprmapedit::notifications::NotificationsPlugin;
+use dprmapedit::ortho_export::OrthoExportPlugin;
+use dprmapedit::picking::HeightfieldPickingPlugin;
+use dprmapedit::players::PlayersPlugin;
+use dprmapedit::props::PropPlugin;
+use dprmapedit::recent::RecentFilesPlugin;
+use dprmapedit::reference::ReferencePlugin;
+use dprmapedit::rulers::RulersPlugin;
+use dprmapedit::runtime::RuntimePlugin;
+use dprmapedit::selection::SelectionPlugin;
+use dprmapedit::spatial::SpatialIndexPlugin;
+use dprmapedit::terrain::{CornerHeightCache, NormalSmoothing};
+use dprmapedit::text_map;
+use dprmapedit::texture::TexturePlugin;
+use dprmapedit::tools::ToolsPlugin;
+use dprmapedit::triggers::TriggerPlugin;
+use dprmapedit::ui::UiPlugin;
+use dprmapedit::unsaved::UnsavedChangesPlugin;
+use dprmapedit::vegetation::VegetationPlugin;
+use dprmapedit::water::WaterPlugin;
+use dprmapedit::weather::WeatherPlugin;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "--diff") {
+        std::process::exit(diff_command(&args[1..]));
+    }
+    if args.first().is_some_and(|arg| arg == "--export") {
+        std::process::exit(export_command(&args[1..]));
+    }
+
     App::new()
-        .add_plugins((DefaultPlugins, EguiPlugin))
-        .configure_sets(
-            Update,
-            terrain::TerrainMeshSet::Rebuild.before(terrain::TerrainMeshSet::Cleanup),
-        )
+        .add_plugins((
+            // Closing asks about unsaved changes first.
+            DefaultPlugins.set(WindowPlugin {
+                close_when_requested: false,
+                ..default()
+            }),
+            EguiPlugin,
+        ))
         .add_plugins((
             TexturePlugin,
             CameraPlugin,
             ControlsPlugin,
             EditorPlugin,
+            ToolsPlugin,
+            SelectionPlugin,
             RuntimePlugin,
+            LightmapPlugin,
+            HeightfieldPickingPlugin,
+            SpatialIndexPlugin,
+            TerrainCullingPlugin,
             UiPlugin,
+            GridVisualPlugin,
             ImageInspectorPlugin,
+            WaterPlugin,
+        ))
+        .add_plugins((
+            TriggerPlugin,
+            PlayersPlugin,
+            GeneratorPlugin,
+            NotificationsPlugin,
+            ReferencePlugin,
+            RulersPlugin,
+            InputPlugin,
+            LocalizationPlugin,
+            LightsPlugin,
+            WeatherPlugin,
+            VegetationPlugin,
+            CollabPlugin,
+            MapBrowserPlugin,
+            AssetBrowserPlugin,
+            OrthoExportPlugin,
+        ))
+        .add_plugins((
+            KeyboardPlugin,
+            MacrosPlugin,
+            DecalPlugin,
+            PropPlugin,
+            FileExportPlugin,
+            BundlePlugin,
+            RecentFilesPlugin,
+            UnsavedChangesPlugin,
+            NewMapPlugin,
+            MapImportPlugin,
         ))
         .add_systems(Startup, setup_light)
-        .add_systems(Update, grid_visual::draw_grid)
         // .add_systems(Update, material::fix_roughness_images_on_load)
         .run();
 }
 
+/// Orientation of the editor's sun, which CLI lightmap bakes light from too.
+fn sun_rotation() -> Quat {
+    Quat::from_euler(EulerRot::XYZ, -1.2, -0.8, 0.0)
+}
+
 fn setup_light(mut commands: Commands) {
     commands.spawn(DirectionalLightBundle {
         directional_light: DirectionalLight {
@@ -51,7 +128,77 @@ fn setup_light(mut commands: Commands) {
             shadows_enabled: false,
             ..default()
         },
-        transform: Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -1.2, -0.8, 0.0)),
+        transform: Transform::from_rotation(sun_rotation()),
         ..default()
     });
 }
+
+/// `--diff <old> <new>`: prints what changed between two saved maps, one
+/// line per changed tile or record. Exits with 0 when they match, 1 when
+/// they differ and 2 on errors, like `diff`.
+fn diff_command(args: &[String]) -> i32 {
+    let [old, new] = args else {
+        eprintln!("usage: dprmapedit --diff <old map> <new map>");
+        return 2;
+    };
+    let load =
+        |path: &String| load_map(path).map_err(|err| eprintln!("failed to load {path}: {err:#}"));
+    let (Ok(old), Ok(new)) = (load(old), load(new)) else {
+        return 2;
+    };
+    let lines = text_map::diff(&old, &new);
+    for line in &lines {
+        println!("{line}");
+    }
+    i32::from(!lines.is_empty())
+}
+
+/// `--export <map> <out dir> [--lightmap]`: writes the export package of a
+/// saved map into a directory without opening a window, with the bundled
+/// textures. `--lightmap` bakes a lightmap into it under the editor's sun.
+/// Exits with 0 on success and 2 on errors.
+fn export_command(args: &[String]) -> i32 {
+    let (args, bake_lightmap) = match args {
+        [rest @ .., flag] if flag == "--lightmap" => (rest, true),
+        _ => (args, false),
+    };
+    let [map_path, out_dir] = args else {
+        eprintln!("usage: dprmapedit --export <map> <out dir> [--lightmap]");
+        return 2;
+    };
+    let result = load_map(map_path).and_then(|map| {
+        let name = Path::new(map_path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "map".to_string());
+        let (textures, wall_texture) = export::default_texture_descriptors(&map)?;
+        let splat_pngs = export::build_map_splatmap_pngs(&map)?;
+        let lightmap_png = bake_lightmap
+            .then(|| {
+                let heights = CornerHeightCache::from_map(&map);
+                let sun_direction = sun_rotation() * Vec3::Z;
+                export::encode_lightmap_png(&lightmap::bake(&map, &heights, sun_direction))
+            })
+            .transpose()?;
+        export::export_directory(
+            Path::new(out_dir),
+            map,
+            name,
+            textures,
+            wall_texture,
+            splat_pngs,
+            lightmap_png,
+            NormalSmoothing::default(),
+        )
+    });
+    match result {
+        Ok(()) => {
+            println!("exported {map_path} to {out_dir}");
+            0
+        }
+        Err(err) => {
+            eprintln!("failed to export {map_path}: {err:#}");
+            2
+        }
+    }
+}
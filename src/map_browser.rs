@@ -0,0 +1,222 @@
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+use image::RgbaImage;
+
+use crate::editor::EditorState;
+use crate::io::{JSON_EXTENSION, load_map};
+use crate::localization::Localization;
+use crate::text_map;
+use crate::types::{MAX_ELEVATION, MIN_ELEVATION, TileKind, TileMap};
+use crate::unsaved::FileAction;
+
+/// Longest side of a thumbnail, in pixels.
+const THUMBNAIL_SIZE: u32 = 128;
+/// Folder browsed before any map has been opened or saved.
+const DEFAULT_DIR: &str = "assets/maps";
+const HOLE_COLOR: [u8; 4] = [20, 20, 24, 255];
+
+/// A window listing the maps next to the current one with their thumbnails;
+/// clicking one opens it.
+pub struct MapBrowserPlugin;
+
+impl Plugin for MapBrowserPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapBrowser>()
+            .add_systems(Update, map_browser_window);
+    }
+}
+
+#[derive(Resource, Default)]
+struct MapBrowser {
+    /// Folder the entries were listed from; `None` until the first scan.
+    dir: Option<PathBuf>,
+    entries: Vec<MapEntry>,
+}
+
+struct MapEntry {
+    path: PathBuf,
+    name: String,
+    thumbnail: Option<egui::TextureHandle>,
+}
+
+/// Where the thumbnail of the map saved at `map_path` lives: beside it, with
+/// `.png` appended to the file name.
+pub fn thumbnail_path(map_path: &Path) -> PathBuf {
+    let mut name = map_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".png");
+    map_path.with_file_name(name)
+}
+
+/// A top-down picture of the map: tiles tinted by type, lighter on high
+/// ground, with a shadow below cliffs facing south.
+pub fn render_thumbnail(map: &TileMap) -> RgbaImage {
+    let scale = THUMBNAIL_SIZE as f32 / map.width.max(map.height).max(1) as f32;
+    let width = ((map.width as f32 * scale).round() as u32).max(1);
+    let height = ((map.height as f32 * scale).round() as u32).max(1);
+    RgbaImage::from_fn(width, height, |px, py| {
+        let x = ((px as f32 / scale) as u32).min(map.width - 1);
+        let y = ((py as f32 / scale) as u32).min(map.height - 1);
+        let tile = map.get(x, y);
+        if tile.kind == TileKind::Empty {
+            return image::Rgba(HOLE_COLOR);
+        }
+        let [r, g, b] = tile.tile_type.preview_color();
+        let level =
+            (tile.elevation - MIN_ELEVATION) as f32 / (MAX_ELEVATION - MIN_ELEVATION).max(1) as f32;
+        let mut shade = 0.55 + 0.45 * level;
+        let shadowed = y > 0 && {
+            let north = map.get(x, y - 1);
+            north.kind != TileKind::Empty && north.elevation > tile.elevation
+        };
+        if shadowed {
+            shade *= 0.75;
+        }
+        let channel = |value: f32| (value * shade * 255.0).round().clamp(0.0, 255.0) as u8;
+        image::Rgba([channel(r), channel(g), channel(b), 255])
+    })
+}
+
+/// Writes the thumbnail of a map that was just saved at `map_path`.
+pub fn save_thumbnail(map_path: &Path, map: &TileMap) -> anyhow::Result<()> {
+    render_thumbnail(map).save(thumbnail_path(map_path))?;
+    Ok(())
+}
+
+fn is_map_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ext.eq_ignore_ascii_case("tme") || ext == text_map::EXTENSION || ext == JSON_EXTENSION
+        })
+}
+
+/// The saved thumbnail, or one rendered from the map for files saved before
+/// thumbnails existed.
+fn load_thumbnail(path: &Path) -> Option<egui::ColorImage> {
+    let image = match image::open(thumbnail_path(path)) {
+        Ok(image) => image.to_rgba8(),
+        Err(_) => render_thumbnail(&load_map(path).ok()?),
+    };
+    let size = [image.width() as usize, image.height() as usize];
+    Some(egui::ColorImage::from_rgba_unmultiplied(
+        size,
+        image.as_raw(),
+    ))
+}
+
+impl MapBrowser {
+    fn scan(&mut self, ctx: &egui::Context, dir: PathBuf) {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| is_map_file(path))
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort();
+        self.entries = paths
+            .into_iter()
+            .map(|path| {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let thumbnail = load_thumbnail(&path).map(|image| {
+                    ctx.load_texture(
+                        format!("map_thumbnail:{}", path.display()),
+                        image,
+                        egui::TextureOptions::NEAREST,
+                    )
+                });
+                MapEntry {
+                    path,
+                    name,
+                    thumbnail,
+                }
+            })
+            .collect();
+        self.dir = Some(dir);
+    }
+}
+
+fn map_browser_window(
+    mut egui: EguiContexts,
+    mut browser: ResMut<MapBrowser>,
+    state: Res<EditorState>,
+    mut file_actions: EventWriter<FileAction>,
+    localization: Res<Localization>,
+) {
+    let loc = &*localization;
+    let ctx = egui.ctx_mut().clone();
+    let dir = state
+        .current_file_path
+        .as_ref()
+        .and_then(|path| path.parent())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map_or_else(|| PathBuf::from(DEFAULT_DIR), Path::to_path_buf);
+
+    let mut open = None;
+    egui::Window::new(loc.tr("browser.title"))
+        .id(egui::Id::new("map_browser"))
+        .default_open(false)
+        .default_width(300.0)
+        .show(&ctx, |ui| {
+            // Scanning reads every thumbnail, so it waits until the window
+            // is first expanded.
+            if browser.dir.as_ref() != Some(&dir) {
+                browser.scan(&ctx, dir.clone());
+            }
+            ui.horizontal(|ui| {
+                ui.label(loc.format("browser.folder", &[("path", &dir.display())]));
+                if ui.small_button(loc.tr("browser.refresh")).clicked() {
+                    browser.scan(&ctx, dir.clone());
+                }
+            });
+            if browser.entries.is_empty() {
+                ui.label(loc.tr("browser.empty"));
+                return;
+            }
+            egui::ScrollArea::vertical()
+                .max_height(400.0)
+                .show(ui, |ui| {
+                    ui.horizontal_wrapped(|ui| {
+                        for entry in &browser.entries {
+                            let current = state.current_file_path.as_ref() == Some(&entry.path);
+                            let response = ui
+                                .vertical(|ui| {
+                                    ui.set_width(THUMBNAIL_SIZE as f32 / 1.5);
+                                    let clicked = match &entry.thumbnail {
+                                        Some(texture) => ui
+                                            .add(
+                                                egui::ImageButton::new(
+                                                    egui::Image::new(texture).max_size(
+                                                        egui::Vec2::splat(
+                                                            THUMBNAIL_SIZE as f32 / 1.5,
+                                                        ),
+                                                    ),
+                                                )
+                                                .selected(current),
+                                            )
+                                            .clicked(),
+                                        None => ui.selectable_label(current, "?").clicked(),
+                                    };
+                                    ui.small(&entry.name);
+                                    clicked
+                                })
+                                .inner;
+                            if response {
+                                open = Some(entry.path.clone());
+                            }
+                        }
+                    });
+                });
+        });
+
+    if let Some(path) = open {
+        file_actions.send(FileAction::Open(path));
+    }
+}
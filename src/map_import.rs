@@ -0,0 +1,333 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy::tasks::{IoTaskPool, Task, block_on};
+use bevy_egui::{EguiContexts, egui};
+use rfd::AsyncFileDialog;
+
+use crate::editor::{self, EditorState, EditorTool, MapEdit};
+use crate::io::{self, JSON_EXTENSION};
+use crate::localization::Localization;
+use crate::notifications::Notify;
+use crate::selection::Selection;
+use crate::terrain::{CornerHeightCache, TerrainMeshSet};
+use crate::text_map;
+use crate::tools::outline_region;
+use crate::types::{StartLocation, Tile, TileMap};
+
+const IMPORT_COLOR: Color = Color::srgba(1.0, 0.6, 0.2, 0.8);
+
+/// "Import map here…": loads another map and stamps it into the edited one
+/// wherever it is clicked, so large maps can be put together from pieces.
+pub struct MapImportPlugin;
+
+impl Plugin for MapImportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ImportMap>()
+            .init_resource::<MapImport>()
+            .add_systems(
+                Update,
+                (open_import_dialog, poll_import_dialog, import_window).chain(),
+            )
+            .add_systems(
+                Update,
+                place_import
+                    .in_set(TerrainMeshSet::Edit)
+                    .after(editor::update_hover),
+            );
+    }
+}
+
+/// Asks for a map file to import.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ImportMap;
+
+#[derive(Resource, Default)]
+struct MapImport {
+    dialog_task: Option<Task<Option<PathBuf>>>,
+    /// The file name and map being placed.
+    source: Option<(String, TileMap)>,
+    /// Where the source's north-west tile last landed.
+    offset: (u32, u32),
+    /// Tool active before placing, restored when done.
+    previous_tool: Option<EditorTool>,
+}
+
+impl MapImport {
+    fn finish(&mut self, state: &mut EditorState) {
+        self.source = None;
+        if state.current_tool == EditorTool::Import {
+            state.current_tool = self.previous_tool.take().unwrap_or(EditorTool::Select);
+        }
+    }
+}
+
+/// Edits stamping `source` into `map` with its north-west tile at `offset`.
+/// Everything in the covered rectangle is replaced, upper layers included;
+/// what falls off the map is left out. Triggers merge into the ones of the
+/// same name, and player starts are only added for players `map` has none
+/// for.
+pub fn import_edits(map: &TileMap, source: &TileMap, offset: (u32, u32)) -> Vec<MapEdit> {
+    let place = |x: u32, y: u32| {
+        let (x, y) = (x + offset.0, y + offset.1);
+        (x < map.width && y < map.height).then_some((x, y))
+    };
+    let place_point = |x: f32, y: f32| {
+        let (x, y) = (x + offset.0 as f32, y + offset.1 as f32);
+        (x >= 0.0 && y >= 0.0 && x < map.width as f32 && y < map.height as f32).then_some((x, y))
+    };
+    let mut edits = Vec::new();
+
+    let layers = map.upper_layers.len().max(source.upper_layers.len());
+    if layers > map.upper_layers.len() {
+        let mut upper_layers = map.upper_layers.clone();
+        upper_layers.resize_with(layers, || map.empty_layer());
+        edits.push(MapEdit::SetUpperLayers(upper_layers));
+    }
+    let open = source.empty_layer();
+    for sy in 0..source.height {
+        for sx in 0..source.width {
+            let Some((x, y)) = place(sx, sy) else {
+                continue;
+            };
+            let idx = source.idx(sx, sy);
+            let tile = Tile {
+                x,
+                y,
+                ..source.tiles[idx].clone()
+            };
+            edits.push(MapEdit::SetTile { x, y, tile });
+            for layer in 1..=layers {
+                let cell = source
+                    .upper_layers
+                    .get(layer - 1)
+                    .map_or(&open[idx], |tiles| &tiles[idx]);
+                let tile = Tile {
+                    x,
+                    y,
+                    ..cell.clone()
+                };
+                edits.push(MapEdit::SetLayerTile { layer, x, y, tile });
+            }
+            edits.push(MapEdit::SetVegetation {
+                x,
+                y,
+                density: source.vegetation(sx, sy),
+            });
+            edits.push(MapEdit::SetRoad {
+                x,
+                y,
+                surface: source.road(sx, sy),
+            });
+            edits.push(MapEdit::SetWalkability {
+                x,
+                y,
+                walkability: source.walkability(sx, sy),
+            });
+        }
+    }
+
+    if !source.triggers.is_empty() {
+        let mut triggers = map.triggers.clone();
+        for trigger in &source.triggers {
+            let tiles = trigger.tiles.iter().filter_map(|&(x, y)| place(x, y));
+            match triggers.iter_mut().find(|other| other.name == trigger.name) {
+                Some(existing) => existing.tiles.extend(tiles),
+                None => {
+                    let mut trigger = trigger.clone();
+                    trigger.tiles = tiles.collect();
+                    if !trigger.tiles.is_empty() {
+                        triggers.push(trigger);
+                    }
+                }
+            }
+        }
+        edits.push(MapEdit::SetTriggers(triggers));
+    }
+    for start in &source.starts {
+        if map.starts.iter().any(|other| other.player == start.player) {
+            continue;
+        }
+        let Some((x, y)) = place(start.x, start.y) else {
+            continue;
+        };
+        edits.push(MapEdit::SetStart {
+            player: start.player,
+            start: Some(StartLocation {
+                x,
+                y,
+                ..start.clone()
+            }),
+        });
+    }
+    for light in &source.lights {
+        let Some((x, y)) = place(light.x, light.y) else {
+            continue;
+        };
+        edits.push(MapEdit::SetLight {
+            x,
+            y,
+            light: Some(light.clone()),
+        });
+    }
+    for decal in &source.decals {
+        let Some((x, y)) = place_point(decal.x, decal.y) else {
+            continue;
+        };
+        let mut decal = decal.clone();
+        (decal.x, decal.y) = (x, y);
+        edits.push(MapEdit::PlaceDecal(decal));
+    }
+    for prop in &source.props {
+        let Some((x, y)) = place_point(prop.x, prop.y) else {
+            continue;
+        };
+        let mut prop = prop.clone();
+        (prop.x, prop.y) = (x, y);
+        edits.push(MapEdit::PlaceProp(prop));
+    }
+    edits
+}
+
+fn open_import_dialog(
+    mut requests: EventReader<ImportMap>,
+    mut import: ResMut<MapImport>,
+    state: Res<EditorState>,
+    localization: Res<Localization>,
+) {
+    if requests.read().last().is_none() || import.dialog_task.is_some() {
+        return;
+    }
+    let mut dialog = AsyncFileDialog::new()
+        .set_title(localization.tr("dialog.import_map"))
+        .add_filter(
+            localization.tr("dialog.map_filter"),
+            &["tme", text_map::EXTENSION, JSON_EXTENSION],
+        );
+    if let Some(parent) = state.current_file_path.as_ref().and_then(|p| p.parent()) {
+        dialog = dialog.set_directory(parent);
+    }
+    import.dialog_task = Some(IoTaskPool::get().spawn(async move {
+        dialog
+            .pick_file()
+            .await
+            .map(|file| file.path().to_path_buf())
+    }));
+}
+
+fn poll_import_dialog(
+    mut import: ResMut<MapImport>,
+    mut state: ResMut<EditorState>,
+    mut notify: EventWriter<Notify>,
+    localization: Res<Localization>,
+) {
+    let Some(task) = import.dialog_task.as_mut() else {
+        return;
+    };
+    if !task.is_finished() {
+        return;
+    }
+    let Some(path) = block_on(import.dialog_task.take().unwrap()) else {
+        return;
+    };
+    match io::load_map(&path) {
+        Ok(map) => {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            import.source = Some((name, map));
+            if state.current_tool != EditorTool::Import {
+                import.previous_tool = Some(state.current_tool);
+            }
+            state.current_tool = EditorTool::Import;
+        }
+        Err(err) => {
+            notify.send(Notify::error(
+                localization.format("notify.load_failed", &[("error", &err)]),
+            ));
+        }
+    }
+}
+
+/// Says what is being placed and where, with a button to stop.
+fn import_window(
+    mut egui: EguiContexts,
+    mut import: ResMut<MapImport>,
+    mut state: ResMut<EditorState>,
+    localization: Res<Localization>,
+) {
+    let Some((name, source)) = import.source.as_ref() else {
+        return;
+    };
+    if state.current_tool != EditorTool::Import {
+        // Another tool was picked while placing.
+        import.source = None;
+        return;
+    }
+    let loc = &*localization;
+    let mut done = false;
+    egui::Window::new(loc.tr("import.title"))
+        .id(egui::Id::new("map_import"))
+        .collapsible(false)
+        .resizable(false)
+        .show(egui.ctx_mut(), |ui| {
+            ui.label(loc.format(
+                "import.placing",
+                &[
+                    ("name", name),
+                    ("width", &source.width),
+                    ("height", &source.height),
+                ],
+            ));
+            ui.label(loc.format(
+                "import.offset",
+                &[("x", &import.offset.0), ("y", &import.offset.1)],
+            ));
+            ui.label(loc.tr("import.hint"));
+            done = ui.button(loc.tr("import.done")).clicked();
+        });
+    if done {
+        import.finish(&mut state);
+    }
+}
+
+/// Outlines the imported map with its north-west tile on the hovered tile
+/// and stamps it on click; it lands in one frame, so it undoes as a single
+/// step and becomes the new selection. Escape stops placing.
+#[allow(clippy::too_many_arguments)]
+fn place_import(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut state: ResMut<EditorState>,
+    mut import: ResMut<MapImport>,
+    mut selection: ResMut<Selection>,
+    heights: Res<CornerHeightCache>,
+    mut edits: EventWriter<MapEdit>,
+    mut gizmos: Gizmos,
+) {
+    if state.current_tool != EditorTool::Import {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        import.finish(&mut state);
+        return;
+    }
+    // `update_hover` clears the hover while the pointer is over a panel.
+    let Some(at) = state.hover else {
+        return;
+    };
+    import.offset = at;
+    let Some((_, source)) = import.source.as_ref() else {
+        return;
+    };
+    let covered = (at.1..(at.1 + source.height).min(state.map.height))
+        .flat_map(|y| (at.0..(at.0 + source.width).min(state.map.width)).map(move |x| (x, y)));
+    if buttons.just_pressed(MouseButton::Left) {
+        selection.clear();
+        selection.extend(covered);
+        edits.send_batch(import_edits(&state.map, source, at));
+        return;
+    }
+    outline_region(&mut gizmos, &heights, covered, 0.06, IMPORT_COLOR);
+}
@@ -0,0 +1,168 @@
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+use crate::generators::{self, GeneratorKind, GeneratorSettings};
+use crate::localization::Localization;
+use crate::texture::registry::TerrainTextureRegistry;
+use crate::types::{MAX_ELEVATION, MIN_ELEVATION, TileMap, TileType};
+use crate::unsaved::FileAction;
+
+/// Longest side of a map made from the dialog, in tiles.
+pub const MAX_NEW_MAP_SIDE: u32 = 1024;
+
+/// The "New map…" window, opened from the File menu. Creating the map goes
+/// through `FileAction::New`, so unsaved changes are asked about first.
+pub struct NewMapPlugin;
+
+impl Plugin for NewMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NewMapDialog>()
+            .add_systems(Update, new_map_window);
+    }
+}
+
+/// What a new map is made of. The editor starts with the default one.
+#[derive(Clone, PartialEq, Debug)]
+pub struct NewMapSettings {
+    pub width: u32,
+    pub height: u32,
+    pub tile_type: TileType,
+    pub elevation: i8,
+    /// Rolling starter terrain around `elevation` instead of a flat map.
+    pub noise: bool,
+    pub seed: u64,
+    /// Largest noise offset, in elevation levels.
+    pub amplitude: i8,
+    /// Noise features per tile.
+    pub frequency: f32,
+}
+
+impl Default for NewMapSettings {
+    fn default() -> Self {
+        Self {
+            width: 64,
+            height: 64,
+            tile_type: TileType::Grass,
+            elevation: 0,
+            noise: false,
+            seed: 0,
+            amplitude: 2,
+            frequency: 0.1,
+        }
+    }
+}
+
+impl NewMapSettings {
+    pub fn build(&self) -> TileMap {
+        let width = self.width.clamp(1, MAX_NEW_MAP_SIDE);
+        let height = self.height.clamp(1, MAX_NEW_MAP_SIDE);
+        let mut map = TileMap::new(width, height);
+        for tile in &mut map.tiles {
+            tile.tile_type = self.tile_type;
+            tile.elevation = self.elevation.clamp(MIN_ELEVATION, MAX_ELEVATION);
+        }
+        if !self.noise {
+            return map;
+        }
+        let settings = GeneratorSettings {
+            kind: GeneratorKind::Noise,
+            seed: self.seed,
+            amplitude: self.amplitude,
+            frequency: self.frequency,
+            iterations: 1,
+        };
+        generators::generate(&map, &vec![true; map.tiles.len()], &settings)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct NewMapDialog {
+    pub open: bool,
+    /// Kept between uses, so the next map starts from the last one's
+    /// settings.
+    pub settings: NewMapSettings,
+}
+
+fn new_map_window(
+    mut egui: EguiContexts,
+    mut dialog: ResMut<NewMapDialog>,
+    mut file_actions: EventWriter<FileAction>,
+    textures: Res<TerrainTextureRegistry>,
+    localization: Res<Localization>,
+) {
+    if !dialog.open {
+        return;
+    }
+    let loc = &*localization;
+    let mut open = true;
+    let mut create = false;
+    let settings = &mut dialog.settings;
+    egui::Window::new(loc.tr("new_map.title"))
+        .id(egui::Id::new("new_map"))
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .show(egui.ctx_mut(), |ui| {
+            egui::Grid::new("new_map_grid").show(ui, |ui| {
+                ui.label(loc.tr("new_map.width"));
+                ui.add(egui::DragValue::new(&mut settings.width).range(1..=MAX_NEW_MAP_SIDE));
+                ui.end_row();
+                ui.label(loc.tr("new_map.height"));
+                ui.add(egui::DragValue::new(&mut settings.height).range(1..=MAX_NEW_MAP_SIDE));
+                ui.end_row();
+                ui.label(loc.tr("new_map.tile_type"));
+                egui::ComboBox::from_id_source("new_map_tile_type")
+                    .selected_text(settings.tile_type.identifier())
+                    .show_ui(ui, |ui| {
+                        for option in textures.layers() {
+                            ui.selectable_value(
+                                &mut settings.tile_type,
+                                option,
+                                option.identifier(),
+                            );
+                        }
+                    });
+                ui.end_row();
+                ui.label(loc.tr("new_map.elevation"));
+                ui.add(
+                    egui::DragValue::new(&mut settings.elevation)
+                        .range(MIN_ELEVATION..=MAX_ELEVATION),
+                );
+                ui.end_row();
+            });
+            ui.checkbox(&mut settings.noise, loc.tr("new_map.noise"));
+            ui.add_enabled_ui(settings.noise, |ui| {
+                egui::Grid::new("new_map_noise_grid").show(ui, |ui| {
+                    ui.label(loc.tr("common.seed"));
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut settings.seed));
+                        if ui
+                            .button("🎲")
+                            .on_hover_text(loc.tr("common.new_seed"))
+                            .clicked()
+                        {
+                            settings.seed = fastrand::u64(..);
+                        }
+                    });
+                    ui.end_row();
+                    ui.label(loc.tr("common.amplitude"));
+                    ui.add(egui::DragValue::new(&mut settings.amplitude).range(1..=8));
+                    ui.end_row();
+                    ui.label(loc.tr("generate.frequency"));
+                    ui.add(
+                        egui::DragValue::new(&mut settings.frequency)
+                            .range(0.01..=1.0)
+                            .speed(0.01),
+                    );
+                    ui.end_row();
+                });
+            });
+            create = ui.button(loc.tr("new_map.create")).clicked();
+        });
+
+    if create {
+        file_actions.send(FileAction::New(dialog.settings.clone()));
+        open = false;
+    }
+    dialog.open = open;
+}
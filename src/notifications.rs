@@ -0,0 +1,196 @@
+use bevy::prelude::*;
+#[cfg(feature = "editor")]
+use bevy_egui::{EguiContexts, egui};
+
+#[cfg(feature = "editor")]
+use crate::localization::Localization;
+
+/// How long a toast stays on screen, in seconds.
+#[cfg(feature = "editor")]
+const TOAST_SECONDS: f64 = 5.0;
+/// Oldest entries are dropped from the log beyond this many.
+#[cfg(feature = "editor")]
+const HISTORY_LIMIT: usize = 200;
+
+/// Shows notifications as toasts in the editor window, with a log window for
+/// earlier ones. Without this plugin notifications are only logged, by
+/// `log_notifications` which `RuntimePlugin` adds.
+#[cfg(feature = "editor")]
+pub struct NotificationsPlugin;
+
+#[cfg(feature = "editor")]
+impl Plugin for NotificationsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Notify>()
+            .init_resource::<Notifications>()
+            .add_systems(Update, (collect_notifications, show_notifications).chain());
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NotificationLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A message for the user. Sent by editor and runtime systems that would
+/// otherwise only write to the terminal.
+#[derive(Event, Clone, Debug)]
+pub struct Notify {
+    pub level: NotificationLevel,
+    pub message: String,
+}
+
+impl Notify {
+    pub fn info(message: impl Into<String>) -> Self {
+        Self {
+            level: NotificationLevel::Info,
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self {
+            level: NotificationLevel::Warning,
+            message: message.into(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            level: NotificationLevel::Error,
+            message: message.into(),
+        }
+    }
+}
+
+#[cfg(feature = "editor")]
+pub struct Notification {
+    pub level: NotificationLevel,
+    pub message: String,
+    /// Times the message was repeated back to back.
+    pub count: u32,
+    /// Elapsed app time when the message first arrived.
+    pub time: f64,
+}
+
+/// Notification history, newest last.
+#[cfg(feature = "editor")]
+#[derive(Resource, Default)]
+pub struct Notifications {
+    pub history: Vec<Notification>,
+    pub show_log: bool,
+}
+
+/// Writes notifications to the log, skipping back-to-back repeats.
+pub fn log_notifications(
+    mut events: EventReader<Notify>,
+    mut last: Local<Option<(NotificationLevel, String)>>,
+) {
+    for event in events.read() {
+        if last
+            .as_ref()
+            .is_some_and(|(level, message)| *level == event.level && *message == event.message)
+        {
+            continue;
+        }
+        match event.level {
+            NotificationLevel::Info => info!("{}", event.message),
+            NotificationLevel::Warning => warn!("{}", event.message),
+            NotificationLevel::Error => error!("{}", event.message),
+        }
+        *last = Some((event.level, event.message.clone()));
+    }
+}
+
+#[cfg(feature = "editor")]
+fn collect_notifications(
+    mut events: EventReader<Notify>,
+    mut notifications: ResMut<Notifications>,
+    time: Res<Time>,
+) {
+    for event in events.read() {
+        // Failures reported every frame collapse into a single entry whose
+        // toast is not refreshed, so they cannot pin a toast on screen.
+        if let Some(last) = notifications
+            .history
+            .last_mut()
+            .filter(|last| last.level == event.level && last.message == event.message)
+        {
+            last.count += 1;
+            continue;
+        }
+
+        notifications.history.push(Notification {
+            level: event.level,
+            message: event.message.clone(),
+            count: 1,
+            time: time.elapsed_seconds_f64(),
+        });
+        let excess = notifications.history.len().saturating_sub(HISTORY_LIMIT);
+        notifications.history.drain(..excess);
+    }
+}
+
+#[cfg(feature = "editor")]
+fn show_notifications(
+    mut egui: EguiContexts,
+    mut notifications: ResMut<Notifications>,
+    localization: Res<Localization>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds_f64();
+    let ctx = egui.ctx_mut();
+
+    egui::Area::new(egui::Id::new("notification_toasts"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -12.0))
+        .order(egui::Order::Foreground)
+        .show(ctx, |ui| {
+            for notification in notifications
+                .history
+                .iter()
+                .filter(|notification| now - notification.time < TOAST_SECONDS)
+            {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.colored_label(level_color(notification.level), label(notification));
+                });
+            }
+        });
+
+    let Notifications { history, show_log } = notifications.as_mut();
+    egui::Window::new(localization.tr("log.title"))
+        .id(egui::Id::new("notification_log"))
+        .open(show_log)
+        .default_width(420.0)
+        .show(ctx, |ui| {
+            if ui.button(localization.tr("common.clear")).clicked() {
+                history.clear();
+            }
+            egui::ScrollArea::vertical()
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for notification in history.iter() {
+                        ui.colored_label(level_color(notification.level), label(notification));
+                    }
+                });
+        });
+}
+
+#[cfg(feature = "editor")]
+fn label(notification: &Notification) -> String {
+    if notification.count > 1 {
+        format!("{} (×{})", notification.message, notification.count)
+    } else {
+        notification.message.clone()
+    }
+}
+
+#[cfg(feature = "editor")]
+fn level_color(level: NotificationLevel) -> egui::Color32 {
+    match level {
+        NotificationLevel::Info => egui::Color32::from_rgb(56, 142, 60),
+        NotificationLevel::Warning => egui::Color32::from_rgb(230, 160, 20),
+        NotificationLevel::Error => egui::Color32::from_rgb(198, 40, 40),
+    }
+}
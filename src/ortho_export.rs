@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, ensure};
+use bevy::prelude::*;
+use bevy::tasks::{IoTaskPool, Task, block_on};
+use bevy_egui::{EguiContexts, egui};
+use image::{Rgba, RgbaImage};
+use rfd::AsyncFileDialog;
+
+use crate::editor::EditorState;
+use crate::export;
+use crate::localization::Localization;
+use crate::notifications::Notify;
+use crate::terrain::{CORNER_NE, CORNER_NW, CORNER_SE, CORNER_SW, CornerHeightCache};
+use crate::texture::material;
+use crate::texture::registry::TerrainTextureRegistry;
+use crate::types::{TileMap, TileType};
+
+pub const MIN_PIXELS_PER_TILE: u32 = 1;
+pub const MAX_PIXELS_PER_TILE: u32 = 256;
+/// Longest side of a rendered image, in pixels.
+pub const MAX_IMAGE_SIDE: u32 = 16384;
+/// Light reaching surfaces facing away from the sun.
+const AMBIENT: f32 = 0.35;
+
+/// A window for rendering the map straight down at a fixed number of pixels
+/// per tile, so every tile covers the same whole-pixel square of the PNG.
+pub struct OrthoExportPlugin;
+
+impl Plugin for OrthoExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OrthoExport>()
+            .add_systems(Update, (ortho_export_window, poll_ortho_export).chain());
+    }
+}
+
+#[derive(Resource)]
+struct OrthoExport {
+    pixels_per_tile: u32,
+    dialog_task: Option<Task<Option<PathBuf>>>,
+    render_task: Option<Task<Result<PathBuf>>>,
+}
+
+impl Default for OrthoExport {
+    fn default() -> Self {
+        Self {
+            pixels_per_tile: 32,
+            dialog_task: None,
+            render_task: None,
+        }
+    }
+}
+
+/// Renders `map` as seen from directly above, tile row 0 at the top. Each
+/// tile is `pixels_per_tile` pixels square and textured with its layer's
+/// base color, tiled the same way the terrain material tiles it, and shaded
+/// by how directly its surface faces `sun` (the direction light travels).
+/// Holes are left transparent.
+pub fn render_orthographic(
+    map: &TileMap,
+    textures: &HashMap<TileType, RgbaImage>,
+    pixels_per_tile: u32,
+    sun: Vec3,
+) -> Result<RgbaImage> {
+    ensure!(
+        (MIN_PIXELS_PER_TILE..=MAX_PIXELS_PER_TILE).contains(&pixels_per_tile),
+        "Pixels per tile must be between {MIN_PIXELS_PER_TILE} and {MAX_PIXELS_PER_TILE}"
+    );
+    let width = map.width * pixels_per_tile;
+    let height = map.height * pixels_per_tile;
+    ensure!(
+        width <= MAX_IMAGE_SIDE && height <= MAX_IMAGE_SIDE,
+        "A {width}x{height} image is larger than {MAX_IMAGE_SIDE} pixels per side"
+    );
+
+    let heights = CornerHeightCache::from_map(map);
+    let tile_size = map.tile_size;
+    let uv_scale = material::uv_scale(tile_size);
+    let to_sun = -sun.normalize_or(Vec3::NEG_Y);
+    Ok(RgbaImage::from_fn(width, height, |px, py| {
+        let (x, y) = (px / pixels_per_tile, py / pixels_per_tile);
+        let Some(corners) = heights.corners(x, y) else {
+            return Rgba([0, 0, 0, 0]);
+        };
+        // Sample at the pixel centre so neighbouring tiles never bleed in.
+        let u = ((px % pixels_per_tile) as f32 + 0.5) / pixels_per_tile as f32;
+        let v = ((py % pixels_per_tile) as f32 + 0.5) / pixels_per_tile as f32;
+        let slope_x = (corners[CORNER_NE] - corners[CORNER_NW]) * (1.0 - v)
+            + (corners[CORNER_SE] - corners[CORNER_SW]) * v;
+        let slope_z = (corners[CORNER_SW] - corners[CORNER_NW]) * (1.0 - u)
+            + (corners[CORNER_SE] - corners[CORNER_NE]) * u;
+        let normal = Vec3::new(-slope_x / tile_size, 1.0, -slope_z / tile_size).normalize();
+        let shade = AMBIENT + (1.0 - AMBIENT) * normal.dot(to_sun).max(0.0);
+
+        let [r, g, b] = match textures.get(&map.get(x, y).tile_type) {
+            Some(texture) => {
+                let world_x = (x as f32 + u) * tile_size;
+                let world_z = (y as f32 + v) * tile_size;
+                let tx = ((world_x * uv_scale).rem_euclid(1.0) * texture.width() as f32) as u32;
+                let ty = ((world_z * uv_scale).rem_euclid(1.0) * texture.height() as f32) as u32;
+                let texel =
+                    texture.get_pixel(tx.min(texture.width() - 1), ty.min(texture.height() - 1));
+                [texel[0], texel[1], texel[2]]
+            }
+            None => [128, 128, 128],
+        };
+        let channel = |value: u8| (value as f32 * shade).round().clamp(0.0, 255.0) as u8;
+        Rgba([channel(r), channel(g), channel(b), 255])
+    }))
+}
+
+/// Loads the base color of every layer used by the map.
+fn load_textures(sources: Vec<(TileType, PathBuf)>) -> Result<HashMap<TileType, RgbaImage>> {
+    sources
+        .into_iter()
+        .map(|(tile_type, path)| {
+            let image = image::open(&path)
+                .with_context(|| format!("Failed to read texture {}", path.display()))?;
+            Ok((tile_type, image.to_rgba8()))
+        })
+        .collect()
+}
+
+fn ortho_export_window(
+    mut egui: EguiContexts,
+    mut export_state: ResMut<OrthoExport>,
+    state: Res<EditorState>,
+    registry: Res<TerrainTextureRegistry>,
+    lights: Query<&GlobalTransform, With<DirectionalLight>>,
+    mut notify: EventWriter<Notify>,
+    localization: Res<Localization>,
+) {
+    let loc = &*localization;
+    let ctx = egui.ctx_mut().clone();
+    let busy = export_state.dialog_task.is_some() || export_state.render_task.is_some();
+
+    let mut export_clicked = false;
+    egui::Window::new(loc.tr("ortho.title"))
+        .id(egui::Id::new("ortho_export"))
+        .default_open(false)
+        .show(&ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(loc.tr("ortho.pixels_per_tile"));
+                ui.add(
+                    egui::DragValue::new(&mut export_state.pixels_per_tile)
+                        .range(MIN_PIXELS_PER_TILE..=MAX_PIXELS_PER_TILE),
+                );
+            });
+            let width = state.map.width * export_state.pixels_per_tile;
+            let height = state.map.height * export_state.pixels_per_tile;
+            ui.label(loc.format("ortho.size", &[("width", &width), ("height", &height)]));
+            let fits = width <= MAX_IMAGE_SIDE && height <= MAX_IMAGE_SIDE;
+            if !fits {
+                ui.colored_label(
+                    egui::Color32::from_rgb(230, 160, 20),
+                    loc.format("ortho.too_large", &[("max", &MAX_IMAGE_SIDE)]),
+                );
+            }
+            export_clicked = ui
+                .add_enabled(!busy && fits, egui::Button::new(loc.tr("ortho.export")))
+                .clicked();
+        });
+
+    if export_clicked {
+        let mut dialog = AsyncFileDialog::new()
+            .set_title(loc.tr("dialog.export_image"))
+            .add_filter(loc.tr("dialog.png_filter"), &["png"]);
+        match state.current_file_path.as_ref() {
+            Some(path) => {
+                if let Some(parent) = path.parent() {
+                    dialog = dialog.set_directory(parent);
+                }
+                if let Some(stem) = path.file_stem().and_then(|name| name.to_str()) {
+                    dialog = dialog.set_file_name(format!("{stem}.png"));
+                }
+            }
+            None => dialog = dialog.set_file_name("map.png"),
+        }
+        export_state.dialog_task = Some(IoTaskPool::get().spawn(async move {
+            dialog
+                .save_file()
+                .await
+                .map(|file| file.path().to_path_buf())
+        }));
+    }
+
+    let Some(task) = export_state.dialog_task.as_mut() else {
+        return;
+    };
+    if !task.is_finished() {
+        return;
+    }
+    let Some(path) = block_on(export_state.dialog_task.take().unwrap()) else {
+        return;
+    };
+    let path = if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+    {
+        path
+    } else {
+        path.with_extension("png")
+    };
+    let sources = match export::collect_texture_descriptors(&state.map, &registry) {
+        Ok((textures, _)) => textures
+            .into_iter()
+            .map(|texture| (texture.tile_type, texture.diffuse.source_path))
+            .collect(),
+        Err(err) => {
+            notify.send(Notify::error(
+                loc.format("notify.textures_failed", &[("error", &err)]),
+            ));
+            return;
+        }
+    };
+    let sun = lights
+        .iter()
+        .next()
+        .map_or(Vec3::NEG_Y, |light| light.forward().as_vec3());
+    let map = state.map.clone();
+    let pixels_per_tile = export_state.pixels_per_tile;
+    export_state.render_task = Some(IoTaskPool::get().spawn(async move {
+        let textures = load_textures(sources)?;
+        render_orthographic(&map, &textures, pixels_per_tile, sun)?
+            .save(&path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }));
+}
+
+fn poll_ortho_export(
+    mut export_state: ResMut<OrthoExport>,
+    mut notify: EventWriter<Notify>,
+    localization: Res<Localization>,
+) {
+    let Some(task) = export_state.render_task.as_mut() else {
+        return;
+    };
+    if !task.is_finished() {
+        return;
+    }
+    match block_on(export_state.render_task.take().unwrap()) {
+        Ok(path) => notify.send(Notify::info(
+            localization.format("notify.image_exported", &[("path", &path.display())]),
+        )),
+        Err(err) => notify.send(Notify::error(
+            localization.format("notify.image_export_failed", &[("error", &err)]),
+        )),
+    };
+}
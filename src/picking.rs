@@ -0,0 +1,242 @@
+use bevy::prelude::*;
+
+use crate::runtime::RuntimeMap;
+use crate::terrain::{
+    CORNER_NE, CORNER_NW, CORNER_SE, CORNER_SW, CornerHeightCache, TerrainChanged, TerrainMeshSet,
+};
+use crate::types::TileMap;
+
+pub struct HeightfieldPickingPlugin;
+
+impl Plugin for HeightfieldPickingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HeightfieldPicker>().add_systems(
+            Update,
+            rebuild_picker
+                .after(TerrainMeshSet::Prepare)
+                .before(TerrainMeshSet::Splat),
+        );
+    }
+}
+
+/// One level of the max-height quadtree. Level 0 holds one cell per tile;
+/// every level above halves the resolution, rounding up.
+struct HeightLevel {
+    width: u32,
+    height: u32,
+    max: Vec<f32>,
+}
+
+/// Max-height quadtree over the terrain, used to skip empty air when casting
+/// pick rays. Rays descend only into cells whose bounds they actually cross,
+/// so a cursor raycast touches a handful of tiles even on very large maps.
+#[derive(Resource, Default)]
+pub struct HeightfieldPicker {
+    levels: Vec<HeightLevel>,
+    corners: Vec<Option<[f32; 4]>>,
+    min_height: f32,
+    tile_size: f32,
+}
+
+impl HeightfieldPicker {
+    pub fn rebuild(&mut self, map: &TileMap, heights: &CornerHeightCache) {
+        self.levels.clear();
+        self.corners.clear();
+        self.corners.reserve((map.width * map.height) as usize);
+        self.min_height = f32::INFINITY;
+        self.tile_size = map.tile_size;
+
+        let mut base = Vec::with_capacity((map.width * map.height) as usize);
+        for y in 0..map.height {
+            for x in 0..map.width {
+                // Holes keep an empty height range so rays pass straight through.
+                let corners = heights.corners(x, y);
+                let (low, high) = corners
+                    .into_iter()
+                    .flatten()
+                    .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), h| {
+                        (low.min(h), high.max(h))
+                    });
+                self.min_height = self.min_height.min(low);
+                self.corners.push(corners);
+                base.push(high);
+            }
+        }
+        if base.is_empty() {
+            return;
+        }
+
+        self.levels.push(HeightLevel {
+            width: map.width,
+            height: map.height,
+            max: base,
+        });
+
+        loop {
+            let below = self.levels.last().unwrap();
+            if below.width == 1 && below.height == 1 {
+                break;
+            }
+            let width = below.width.div_ceil(2);
+            let height = below.height.div_ceil(2);
+            let mut max = vec![f32::NEG_INFINITY; (width * height) as usize];
+            for y in 0..below.height {
+                for x in 0..below.width {
+                    let parent = ((y / 2) * width + x / 2) as usize;
+                    max[parent] = max[parent].max(below.max[(y * below.width + x) as usize]);
+                }
+            }
+            self.levels.push(HeightLevel { width, height, max });
+        }
+    }
+
+    /// First tile the ray meets, counting both top surfaces and cliff walls.
+    pub fn raycast(&self, origin: Vec3, direction: Vec3) -> Option<(u32, u32)> {
+        self.raycast_point(origin, direction).map(|(tile, _)| tile)
+    }
+
+    /// Like [`Self::raycast`], along with the point where the ray hits.
+    pub fn raycast_point(&self, origin: Vec3, direction: Vec3) -> Option<((u32, u32), Vec3)> {
+        let top = self.levels.len().checked_sub(1)?;
+        let mut stack = vec![(top, 0u32, 0u32)];
+
+        while let Some((level, x, y)) = stack.pop() {
+            if level == 0 {
+                if let Some(t) = self.intersect_tile(x, y, origin, direction) {
+                    return Some(((x, y), origin + direction * t));
+                }
+                continue;
+            }
+
+            // Children are pushed far-to-near so the nearest is visited first.
+            // Their XZ footprints are disjoint, so the first tile hit is the
+            // closest one along the ray.
+            let child_level = &self.levels[level - 1];
+            let mut children: Vec<(f32, u32, u32)> = Vec::with_capacity(4);
+            for cy in (y * 2)..(y * 2 + 2).min(child_level.height) {
+                for cx in (x * 2)..(x * 2 + 2).min(child_level.width) {
+                    if let Some((enter, _)) = self.cell_span(level - 1, cx, cy, origin, direction) {
+                        children.push((enter, cx, cy));
+                    }
+                }
+            }
+            children.sort_by(|a, b| b.0.total_cmp(&a.0));
+            stack.extend(children.into_iter().map(|(_, cx, cy)| (level - 1, cx, cy)));
+        }
+
+        None
+    }
+
+    fn cell_span(
+        &self,
+        level: usize,
+        x: u32,
+        y: u32,
+        origin: Vec3,
+        direction: Vec3,
+    ) -> Option<(f32, f32)> {
+        let cells = &self.levels[level];
+        let scale = 1u32 << level;
+        let base = &self.levels[0];
+        let min = Vec3::new(
+            (x * scale) as f32 * self.tile_size,
+            self.min_height,
+            (y * scale) as f32 * self.tile_size,
+        );
+        let max = Vec3::new(
+            ((x + 1) * scale).min(base.width) as f32 * self.tile_size,
+            cells.max[(y * cells.width + x) as usize],
+            ((y + 1) * scale).min(base.height) as f32 * self.tile_size,
+        );
+        ray_box_span(origin, direction, min, max)
+    }
+
+    /// Ray parameter where the ray first meets the tile, if it does.
+    fn intersect_tile(&self, x: u32, y: u32, origin: Vec3, direction: Vec3) -> Option<f32> {
+        let (enter, exit) = self.cell_span(0, x, y, origin, direction)?;
+        let corners = self.corners[(y * self.levels[0].width + x) as usize]?;
+        let x0 = x as f32 * self.tile_size;
+        let x1 = x0 + self.tile_size;
+        let z0 = y as f32 * self.tile_size;
+        let z1 = z0 + self.tile_size;
+
+        // Entering the column below the surface means the ray struck the
+        // tile's side wall.
+        let entry = origin + direction * enter;
+        if enter > 0.0 {
+            let u = ((entry.x - x0) / self.tile_size).clamp(0.0, 1.0);
+            let v = ((entry.z - z0) / self.tile_size).clamp(0.0, 1.0);
+            let north = corners[CORNER_NW] + (corners[CORNER_NE] - corners[CORNER_NW]) * u;
+            let south = corners[CORNER_SW] + (corners[CORNER_SE] - corners[CORNER_SW]) * u;
+            if entry.y <= north + (south - north) * v {
+                return Some(enter);
+            }
+        }
+
+        // Same triangulation as the terrain mesh top face.
+        let nw = Vec3::new(x0, corners[CORNER_NW], z0);
+        let ne = Vec3::new(x1, corners[CORNER_NE], z0);
+        let sw = Vec3::new(x0, corners[CORNER_SW], z1);
+        let se = Vec3::new(x1, corners[CORNER_SE], z1);
+        [
+            ray_triangle(origin, direction, nw, sw, se),
+            ray_triangle(origin, direction, nw, se, ne),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|&t| t >= enter - f32::EPSILON && t <= exit + f32::EPSILON)
+        .min_by(f32::total_cmp)
+    }
+}
+
+fn rebuild_picker(
+    runtime_map: Res<RuntimeMap>,
+    heights: Res<CornerHeightCache>,
+    mut changed: EventReader<TerrainChanged>,
+    mut picker: ResMut<HeightfieldPicker>,
+) {
+    if changed.is_empty() {
+        return;
+    }
+    changed.clear();
+    picker.rebuild(&runtime_map.map, &heights);
+}
+
+/// Parameter range `(enter, exit)` where the ray is inside the box, clamped
+/// to start at the ray origin.
+fn ray_box_span(origin: Vec3, direction: Vec3, min: Vec3, max: Vec3) -> Option<(f32, f32)> {
+    let inverse = direction.recip();
+    let a = (min - origin) * inverse;
+    let b = (max - origin) * inverse;
+    let near = a.min(b);
+    let far = a.max(b);
+    // NaN from a zero direction component on the slab boundary is ignored by
+    // max/min, which keeps axis-aligned rays working.
+    let enter = near.x.max(near.y).max(near.z).max(0.0);
+    let exit = far.x.min(far.y).min(far.z);
+    (enter <= exit).then_some((enter, exit))
+}
+
+/// Möller–Trumbore intersection, returning the ray parameter of the hit.
+fn ray_triangle(origin: Vec3, direction: Vec3, a: Vec3, b: Vec3, c: Vec3) -> Option<f32> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = direction.cross(edge2);
+    let det = edge1.dot(p);
+    if det.abs() < 1e-6 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let s = origin - a;
+    let u = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(edge1);
+    let v = direction.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = edge2.dot(q) * inv_det;
+    (t >= 0.0).then_some(t)
+}
@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+
+use crate::editor::EditorState;
+use crate::terrain::{CornerHeightCache, TerrainMeshSet};
+use crate::types::{StartLocation, TileKind, TileMap};
+
+pub const MAX_PLAYERS: u8 = 8;
+
+/// Marker colors per player index.
+const PLAYER_COLORS: [Color; MAX_PLAYERS as usize] = [
+    Color::srgb(0.9, 0.2, 0.2),
+    Color::srgb(0.2, 0.45, 0.95),
+    Color::srgb(0.2, 0.8, 0.3),
+    Color::srgb(0.95, 0.85, 0.2),
+    Color::srgb(0.7, 0.3, 0.9),
+    Color::srgb(0.95, 0.55, 0.15),
+    Color::srgb(0.2, 0.85, 0.85),
+    Color::srgb(0.95, 0.45, 0.75),
+];
+
+pub struct PlayersPlugin;
+
+impl Plugin for PlayersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, draw_start_locations.after(TerrainMeshSet::Prepare));
+    }
+}
+
+pub fn player_color(player: u8) -> Color {
+    PLAYER_COLORS[player as usize % PLAYER_COLORS.len()]
+}
+
+/// Every reason the map's player setup is unusable by a game. Each player
+/// index up to the highest one referenced by a start or an owned trigger
+/// needs exactly one start location, on a solid tile inside the map.
+pub fn player_problems(map: &TileMap) -> Vec<String> {
+    let highest = map
+        .starts
+        .iter()
+        .map(|start| start.player)
+        .chain(map.triggers.iter().filter_map(|area| area.player))
+        .max();
+    let Some(highest) = highest else {
+        return Vec::new();
+    };
+
+    let mut problems = Vec::new();
+    let mut placed = Vec::new();
+    for player in 0..=highest {
+        let starts: Vec<_> = map
+            .starts
+            .iter()
+            .filter(|start| start.player == player)
+            .collect();
+        match starts.as_slice() {
+            [] => problems.push(format!("Player {} has no start location", player + 1)),
+            [start] => {
+                if start.x >= map.width || start.y >= map.height {
+                    problems.push(format!("Player {} starts outside the map", player + 1));
+                } else if map.get(start.x, start.y).kind == TileKind::Empty {
+                    problems.push(format!("Player {} starts on a hole", player + 1));
+                } else {
+                    placed.push(*start);
+                }
+            }
+            _ => problems.push(format!(
+                "Player {} has {} start locations",
+                player + 1,
+                starts.len()
+            )),
+        }
+    }
+
+    // Every player has to be able to walk to every other player's base.
+    if let Some((first, others)) = placed.split_first() {
+        let regions = map.reachability().regions();
+        let region = |start: &StartLocation| regions[map.idx(start.x, start.y)];
+        for other in others {
+            if region(other) != region(first) {
+                problems.push(format!(
+                    "Player {} cannot reach player {}",
+                    other.player + 1,
+                    first.player + 1
+                ));
+            }
+        }
+    }
+    problems
+}
+
+pub fn validate_players(map: &TileMap) -> anyhow::Result<()> {
+    let problems = player_problems(map);
+    anyhow::ensure!(problems.is_empty(), problems.join("; "));
+    Ok(())
+}
+
+/// Draws a ring and a facing arrow on every start location.
+fn draw_start_locations(
+    mut gizmos: Gizmos,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+) {
+    for start in &state.map.starts {
+        if start.x >= state.map.width || start.y >= state.map.height {
+            continue;
+        }
+        let Some(corners) = heights.corners(start.x, start.y) else {
+            continue;
+        };
+        let lift = 0.08;
+        let tile_size = state.map.tile_size;
+        let center = Vec3::new(
+            (start.x as f32 + 0.5) * tile_size,
+            corners.iter().sum::<f32>() / 4.0 + lift,
+            (start.y as f32 + 0.5) * tile_size,
+        );
+        let color = player_color(start.player);
+        let facing = start.facing.to_radians();
+        let forward = Vec3::new(facing.sin(), 0.0, -facing.cos());
+        gizmos.circle(center, Dir3::Y, tile_size * 0.4, color);
+        gizmos.arrow(center, center + forward * tile_size * 0.6, color);
+    }
+}
@@ -0,0 +1,194 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::runtime::RuntimeMap;
+use crate::spatial::SpatiallyIndexed;
+use crate::terrain::{self, CORNER_NE, CORNER_NW, CORNER_SE, CORNER_SW};
+use crate::types::{MapProp, TileKind, TileMap};
+
+/// Props registered at startup, relative to the asset root. A JSON list of
+/// `PropEntry` records; missing files register nothing.
+pub const PROP_MANIFEST: &str = "props/props.json";
+const ASSET_ROOT: &str = "assets";
+
+/// Spawns the scene of every prop on the map, standing on the terrain under
+/// its centre. Games can register their own scenes in `PropRegistry` instead
+/// of, or on top of, the manifest.
+pub struct PropPlugin;
+
+impl Plugin for PropPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PropRegistry>()
+            .init_resource::<PropScenes>()
+            .add_systems(Startup, load_prop_manifest)
+            .add_systems(
+                Update,
+                sync_prop_scenes.in_set(terrain::TerrainMeshSet::Rebuild),
+            );
+    }
+}
+
+/// A scene that can be placed on maps.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PropEntry {
+    /// What `MapProp::asset` refers to it by.
+    pub id: String,
+    pub name: String,
+    /// Scene asset path, such as `props/pine.glb#Scene0`.
+    pub scene: String,
+}
+
+/// The scenes props can be placed from, in the order the editor lists them.
+#[derive(Resource, Default)]
+pub struct PropRegistry {
+    entries: Vec<PropEntry>,
+}
+
+impl PropRegistry {
+    /// Adds a scene, replacing any registered under the same id.
+    pub fn register(&mut self, entry: PropEntry) {
+        match self.entries.iter_mut().find(|known| known.id == entry.id) {
+            Some(known) => *known = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&PropEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &PropEntry> {
+        self.entries.iter()
+    }
+}
+
+fn load_prop_manifest(mut registry: ResMut<PropRegistry>) {
+    let path = Path::new(ASSET_ROOT).join(PROP_MANIFEST);
+    let Ok(text) = std::fs::read_to_string(&path) else {
+        return;
+    };
+    match serde_json::from_str::<Vec<PropEntry>>(&text) {
+        Ok(entries) => {
+            for entry in entries {
+                registry.register(entry);
+            }
+        }
+        Err(err) => warn!("ignoring prop manifest {}: {err}", path.display()),
+    }
+}
+
+/// World position of a prop's origin on the terrain surface, or `None`
+/// off the map and over holes.
+pub fn prop_position(map: &TileMap, prop: &MapProp) -> Option<Vec3> {
+    if !(prop.x >= 0.0 && prop.y >= 0.0) {
+        return None;
+    }
+    let (x, y) = (prop.x.floor() as u32, prop.y.floor() as u32);
+    if x >= map.width || y >= map.height || map.get(x, y).kind == TileKind::Empty {
+        return None;
+    }
+    let corners = terrain::tile_corner_heights(map, x, y);
+    let (u, v) = (prop.x - x as f32, prop.y - y as f32);
+    let north = corners[CORNER_NW] + (corners[CORNER_NE] - corners[CORNER_NW]) * u;
+    let south = corners[CORNER_SW] + (corners[CORNER_SE] - corners[CORNER_SW]) * u;
+    Some(Vec3::new(
+        prop.x * map.tile_size,
+        north + (south - north) * v,
+        prop.y * map.tile_size,
+    ))
+}
+
+/// Where a prop's scene is placed, or `None` when it has no ground to stand
+/// on.
+pub fn prop_transform(map: &TileMap, prop: &MapProp) -> Option<Transform> {
+    Some(
+        Transform::from_translation(prop_position(map, prop)?)
+            .with_rotation(Quat::from_rotation_y(-prop.rotation.to_radians()))
+            .with_scale(Vec3::splat(prop.scale)),
+    )
+}
+
+/// The map prop a spawned entity stands for, by its index in
+/// `TileMap::props`.
+#[derive(Component, Clone, Copy)]
+pub struct PlacedProp(pub usize);
+
+/// The props the spawned scenes were built from, with the entity of each
+/// one that has ground under it. Props without a registered scene get an
+/// empty entity, so they can still be found through the `SpatialIndex`.
+#[derive(Resource, Default)]
+struct PropScenes {
+    props: Vec<MapProp>,
+    entities: Vec<Option<Entity>>,
+}
+
+/// Moves the spawned scenes when props are dragged or the terrain under
+/// them changes, and respawns them all when props are added, removed or
+/// swapped for other scenes.
+fn sync_prop_scenes(
+    mut commands: Commands,
+    runtime_map: Res<RuntimeMap>,
+    registry: Res<PropRegistry>,
+    asset_server: Res<AssetServer>,
+    mut changed: EventReader<terrain::TerrainChanged>,
+    mut scenes: ResMut<PropScenes>,
+    mut transforms: Query<&mut Transform>,
+) {
+    let moved = !changed.is_empty();
+    changed.clear();
+    let map = &runtime_map.map;
+    if !moved && !registry.is_changed() && scenes.props == map.props {
+        return;
+    }
+
+    let placed: Vec<Option<Transform>> = map
+        .props
+        .iter()
+        .map(|prop| prop_transform(map, prop))
+        .collect();
+    let same_scenes = !registry.is_changed()
+        && scenes.props.len() == map.props.len()
+        && scenes
+            .props
+            .iter()
+            .zip(&map.props)
+            .zip(&scenes.entities)
+            .zip(&placed)
+            .all(|(((old, new), entity), placed)| {
+                old.asset == new.asset && entity.is_some() == placed.is_some()
+            });
+    if same_scenes {
+        for (entity, placed) in scenes.entities.iter().zip(&placed) {
+            let (Some(entity), Some(placed)) = (entity, placed) else {
+                continue;
+            };
+            if let Ok(mut transform) = transforms.get_mut(*entity) {
+                *transform = *placed;
+            }
+        }
+        scenes.props.clone_from(&map.props);
+        return;
+    }
+
+    for entity in scenes.entities.drain(..).flatten() {
+        commands.entity(entity).despawn_recursive();
+    }
+    scenes.props.clone_from(&map.props);
+    for (index, (prop, placed)) in map.props.iter().zip(placed).enumerate() {
+        let entity = placed.map(|transform| {
+            let mut entity = commands.spawn((
+                SpatialBundle::from_transform(transform),
+                PlacedProp(index),
+                SpatiallyIndexed,
+                Name::new(format!("Prop {}", prop.asset)),
+            ));
+            if let Some(entry) = registry.get(&prop.asset) {
+                entity.insert(asset_server.load::<Scene>(entry.scene.clone()));
+            }
+            entity.id()
+        });
+        scenes.entities.push(entity);
+    }
+}
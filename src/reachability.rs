@@ -0,0 +1,251 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+use crate::terrain::{CORNER_NE, CORNER_NW, CORNER_SE, CORNER_SW, tile_corner_heights};
+use crate::types::{RampDirection, TileKind, TileMap, Walkability};
+
+/// Shared edges closer than this are treated as continuous.
+const EDGE_TOLERANCE: f32 = 1e-4;
+/// Elevation levels a tile's surface may climb from its lowest corner to its
+/// highest and still be walked on, enough for a ramp but not for a sculpted
+/// cliff.
+pub const MAX_WALKABLE_RISE: f32 = 1.0;
+
+/// Which orthogonal neighbours a unit can walk to from each tile. Two tiles
+/// are linked when the edge they share has the same height at both ends, so
+/// same-elevation floors connect, ramps connect at their top and bottom, and
+/// cliffs and the slanted sides of a ramp do not. Tiles that are not
+/// `TileMap::walkable` link to nothing.
+pub struct Reachability {
+    width: u32,
+    height: u32,
+    /// Per tile, bit `i` set when the neighbour in `RampDirection::ALL[i]` is
+    /// reachable.
+    edges: Vec<u8>,
+    /// Movement cost per tile, 0 for holes and blocked tiles.
+    costs: Vec<u8>,
+}
+
+impl TileMap {
+    /// Whether units can stand on a tile. A painted `Walkability` decides
+    /// when there is one; otherwise every tile but a hole is walkable unless
+    /// its surface rises more than `MAX_WALKABLE_RISE` levels across it.
+    pub fn walkable(&self, x: u32, y: u32) -> bool {
+        self.get(x, y).kind != TileKind::Empty
+            && self.walkable_with(x, y, tile_corner_heights(self, x, y))
+    }
+
+    /// `walkable` for a solid tile whose corner heights are known.
+    fn walkable_with(&self, x: u32, y: u32, corners: [f32; 4]) -> bool {
+        match self.walkability(x, y) {
+            Some(Walkability::Blocked) => false,
+            Some(Walkability::Walkable) => true,
+            None => {
+                let lowest = corners.iter().copied().fold(f32::INFINITY, f32::min);
+                let highest = corners.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                highest - lowest <= MAX_WALKABLE_RISE * self.tile_height() + EDGE_TOLERANCE
+            }
+        }
+    }
+
+    pub fn reachability(&self) -> Reachability {
+        let corners: Vec<Option<[f32; 4]>> = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                if self.get(x, y).kind == TileKind::Empty {
+                    return None;
+                }
+                Some(tile_corner_heights(self, x, y))
+                    .filter(|&corners| self.walkable_with(x, y, corners))
+            })
+            .collect();
+
+        let mut edges = vec![0u8; corners.len()];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let Some(own) = corners[self.idx(x, y)] else {
+                    continue;
+                };
+                for (bit, dir) in RampDirection::ALL.into_iter().enumerate() {
+                    let Some((nx, ny)) = step(self.width, self.height, x, y, dir) else {
+                        continue;
+                    };
+                    let Some(other) = corners[self.idx(nx, ny)] else {
+                        continue;
+                    };
+                    let (a, b) = edge(own, dir);
+                    let (c, d) = edge(other, opposite(dir));
+                    if (a - c).abs() < EDGE_TOLERANCE && (b - d).abs() < EDGE_TOLERANCE {
+                        edges[self.idx(x, y)] |= 1 << bit;
+                    }
+                }
+            }
+        }
+
+        Reachability {
+            width: self.width,
+            height: self.height,
+            edges,
+            costs: self
+                .tiles
+                .iter()
+                .zip(&corners)
+                .map(|(tile, corners)| corners.map_or(0, |_| tile.movement_cost()))
+                .collect(),
+        }
+    }
+}
+
+impl Reachability {
+    /// Movement cost of entering a tile, 0 where units cannot stand.
+    pub fn cost(&self, x: u32, y: u32) -> u8 {
+        self.costs[(y * self.width + x) as usize]
+    }
+
+    /// Bitmask of reachable neighbours, in `RampDirection::ALL` order.
+    pub fn edge_mask(&self, x: u32, y: u32) -> u8 {
+        self.edges[(y * self.width + x) as usize]
+    }
+
+    pub fn neighbors(&self, x: u32, y: u32) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let mask = self.edge_mask(x, y);
+        RampDirection::ALL
+            .into_iter()
+            .enumerate()
+            .filter(move |(bit, _)| mask & (1 << bit) != 0)
+            .filter_map(move |(_, dir)| step(self.width, self.height, x, y, dir))
+    }
+
+    /// Connected-component label per tile, row-major. Holes and tiles units
+    /// cannot stand on get `None`; every other tile shares a label with the tiles it can walk to.
+    pub fn regions(&self) -> Vec<Option<u32>> {
+        let mut labels = vec![None; self.edges.len()];
+        let mut next = 0;
+        for start in 0..labels.len() {
+            if labels[start].is_some() || self.costs[start] == 0 {
+                continue;
+            }
+            labels[start] = Some(next);
+            let mut queue = VecDeque::from([start]);
+            while let Some(idx) = queue.pop_front() {
+                let (x, y) = (idx as u32 % self.width, idx as u32 / self.width);
+                for (nx, ny) in self.neighbors(x, y) {
+                    let neighbor = (ny * self.width + nx) as usize;
+                    if labels[neighbor].is_none() {
+                        labels[neighbor] = Some(next);
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+            next += 1;
+        }
+        labels
+    }
+
+    /// Index into `sources` of the source each tile is cheapest to walk to
+    /// from, row-major. Holes and tiles no source reaches get `None`.
+    pub fn nearest(&self, sources: &[(u32, u32)]) -> Vec<Option<usize>> {
+        let index = |(x, y): (u32, u32)| (y * self.width + x) as usize;
+        let mut best = vec![u32::MAX; self.edges.len()];
+        let mut owner = vec![None; self.edges.len()];
+        let mut open = BinaryHeap::new();
+        for (source, &tile) in sources.iter().enumerate() {
+            if tile.0 >= self.width || tile.1 >= self.height || self.costs[index(tile)] == 0 {
+                continue;
+            }
+            if best[index(tile)] > 0 {
+                best[index(tile)] = 0;
+                owner[index(tile)] = Some(source);
+                open.push(Reverse((0u32, tile)));
+            }
+        }
+
+        while let Some(Reverse((cost, tile))) = open.pop() {
+            if cost > best[index(tile)] {
+                continue;
+            }
+            for next in self.neighbors(tile.0, tile.1) {
+                let total = cost + self.costs[index(next)].max(1) as u32;
+                if total < best[index(next)] {
+                    best[index(next)] = total;
+                    owner[index(next)] = owner[index(tile)];
+                    open.push(Reverse((total, next)));
+                }
+            }
+        }
+        owner
+    }
+
+    /// Cheapest walk from `from` to `to`, weighted by each entered tile's
+    /// movement cost, including both endpoints.
+    pub fn path(&self, from: (u32, u32), to: (u32, u32)) -> Option<Vec<(u32, u32)>> {
+        let index = |(x, y): (u32, u32)| (y * self.width + x) as usize;
+        if from.0 >= self.width || from.1 >= self.height {
+            return None;
+        }
+        if to.0 >= self.width || to.1 >= self.height {
+            return None;
+        }
+        if self.costs[index(from)] == 0 || self.costs[index(to)] == 0 {
+            return None;
+        }
+
+        let mut best = vec![u32::MAX; self.edges.len()];
+        let mut previous: Vec<Option<(u32, u32)>> = vec![None; self.edges.len()];
+        let mut open = BinaryHeap::from([Reverse((0u32, from))]);
+        best[index(from)] = 0;
+
+        while let Some(Reverse((cost, tile))) = open.pop() {
+            if tile == to {
+                let mut path = vec![to];
+                let mut current = to;
+                while let Some(prev) = previous[index(current)] {
+                    path.push(prev);
+                    current = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if cost > best[index(tile)] {
+                continue;
+            }
+            for next in self.neighbors(tile.0, tile.1) {
+                let step_cost = self.costs[index(next)].max(1) as u32;
+                let total = cost + step_cost;
+                if total < best[index(next)] {
+                    best[index(next)] = total;
+                    previous[index(next)] = Some(tile);
+                    open.push(Reverse((total, next)));
+                }
+            }
+        }
+        None
+    }
+}
+
+fn step(width: u32, height: u32, x: u32, y: u32, dir: RampDirection) -> Option<(u32, u32)> {
+    let (dx, dy) = dir.offset();
+    let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+    (nx >= 0 && ny >= 0 && (nx as u32) < width && (ny as u32) < height)
+        .then_some((nx as u32, ny as u32))
+}
+
+fn opposite(dir: RampDirection) -> RampDirection {
+    dir.next().next()
+}
+
+/// Heights at the two ends of a tile's edge facing `dir`, ordered along the
+/// edge so both sides of a shared edge line up. Diagonal neighbours only
+/// share a corner, which stands in for both ends.
+fn edge(corners: [f32; 4], dir: RampDirection) -> (f32, f32) {
+    match dir {
+        RampDirection::North => (corners[CORNER_NW], corners[CORNER_NE]),
+        RampDirection::South => (corners[CORNER_SW], corners[CORNER_SE]),
+        RampDirection::West => (corners[CORNER_NW], corners[CORNER_SW]),
+        RampDirection::East => (corners[CORNER_NE], corners[CORNER_SE]),
+        RampDirection::NorthEast => (corners[CORNER_NE], corners[CORNER_NE]),
+        RampDirection::SouthEast => (corners[CORNER_SE], corners[CORNER_SE]),
+        RampDirection::SouthWest => (corners[CORNER_SW], corners[CORNER_SW]),
+        RampDirection::NorthWest => (corners[CORNER_NW], corners[CORNER_NW]),
+    }
+}
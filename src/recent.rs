@@ -0,0 +1,124 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::editor::{EditorState, MapEdit};
+use crate::localization::Localization;
+use crate::notifications::Notify;
+
+/// Most paths kept in the recent files list.
+pub const MAX_RECENT_FILES: usize = 10;
+const CONFIG_DIR: &str = "tilemapedit3d";
+const CONFIG_FILE: &str = "recent.json";
+
+/// Remembers the maps opened and saved most recently in the platform's
+/// config folder, and reopens the last one on startup when asked to.
+pub struct RecentFilesPlugin;
+
+impl Plugin for RecentFilesPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RecentFiles::load())
+            .add_systems(PostStartup, reopen_last_map)
+            .add_systems(Update, (remember_current_file, save_recent_files).chain());
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone)]
+pub struct RecentFiles {
+    /// Newest first, without duplicates.
+    pub paths: Vec<PathBuf>,
+    pub reopen_last: bool,
+}
+
+impl RecentFiles {
+    /// Where the list is kept; `None` on platforms without a config folder.
+    pub fn config_path() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join(CONFIG_DIR).join(CONFIG_FILE))
+    }
+
+    /// The saved list, or an empty one when there is none yet or it cannot
+    /// be read.
+    fn load() -> Self {
+        let Some(path) = Self::config_path() else {
+            return Self::default();
+        };
+        match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|err| {
+                warn!("Ignoring unreadable recent files {}: {err}", path.display());
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = Self::config_path().context("no config folder on this platform")?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Moves `path` to the front of the list.
+    pub fn add(&mut self, path: &Path) {
+        self.paths.retain(|recent| recent != path);
+        self.paths.insert(0, path.to_path_buf());
+        self.paths.truncate(MAX_RECENT_FILES);
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.paths.retain(|recent| recent != path);
+    }
+}
+
+/// Adds the edited map's path to the list whenever it changes, which opening
+/// and saving both do.
+fn remember_current_file(
+    state: Res<EditorState>,
+    mut recent: ResMut<RecentFiles>,
+    mut last: Local<Option<PathBuf>>,
+) {
+    if *last == state.current_file_path {
+        return;
+    }
+    last.clone_from(&state.current_file_path);
+    let Some(path) = last.as_deref() else {
+        return;
+    };
+    if recent.paths.first().map(PathBuf::as_path) != Some(path) {
+        recent.add(path);
+    }
+}
+
+fn save_recent_files(recent: Res<RecentFiles>) {
+    if !recent.is_changed() || recent.is_added() {
+        return;
+    }
+    if let Err(err) = recent.save() {
+        warn!("Failed to save recent files: {err:#}");
+    }
+}
+
+fn reopen_last_map(
+    mut state: ResMut<EditorState>,
+    mut recent: ResMut<RecentFiles>,
+    mut edits: EventWriter<MapEdit>,
+    mut notify: EventWriter<Notify>,
+    localization: Res<Localization>,
+) {
+    if !recent.reopen_last {
+        return;
+    }
+    let Some(path) = recent.paths.first().cloned() else {
+        return;
+    };
+    if let Err(err) = state.open_map(path.clone(), &mut edits) {
+        notify.send(Notify::error(
+            localization.format("notify.load_failed", &[("error", &err)]),
+        ));
+        recent.remove(&path);
+    }
+}
@@ -0,0 +1,270 @@
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::tasks::{Task, block_on};
+
+use crate::editor::EditorState;
+use crate::io::{JSON_EXTENSION, load_map};
+use crate::localization::Localization;
+use crate::notifications::Notify;
+use crate::terrain::{self, tile_corner_heights};
+use crate::text_map;
+use crate::types::{MAX_ELEVATION, MIN_ELEVATION, TileKind, TileMap};
+
+// Above the trigger overlay, so both stay readable.
+const REFERENCE_LIFT: f32 = 0.08;
+
+/// Shows another map, or an image, as a translucent onion skin over the
+/// edited map so an existing layout can be traced or remixed.
+pub struct ReferencePlugin;
+
+impl Plugin for ReferencePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReferenceOverlay>()
+            .add_systems(Startup, spawn_reference_overlay)
+            .add_systems(Update, (load_reference, update_reference_overlay).chain());
+    }
+}
+
+/// What the reference overlay shows and how strongly.
+#[derive(Resource)]
+pub struct ReferenceOverlay {
+    pub visible: bool,
+    pub opacity: f32,
+    /// The loaded reference file.
+    pub path: Option<PathBuf>,
+    pub dialog_task: Option<Task<Option<PathBuf>>>,
+    /// Set to drop the loaded reference.
+    pub clear: bool,
+}
+
+impl Default for ReferenceOverlay {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            opacity: 0.4,
+            path: None,
+            dialog_task: None,
+            clear: false,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct ReferenceEntity {
+    entity: Entity,
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+fn spawn_reference_overlay(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mesh = meshes.add(terrain::empty_mesh());
+    let material = materials.add(StandardMaterial {
+        base_color: Color::WHITE,
+        unlit: true,
+        alpha_mode: AlphaMode::Blend,
+        double_sided: true,
+        cull_mode: None,
+        ..default()
+    });
+    let entity = commands
+        .spawn((
+            PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            Name::new("ReferenceOverlay"),
+        ))
+        .id();
+    commands.insert_resource(ReferenceEntity {
+        entity,
+        mesh,
+        material,
+    });
+}
+
+/// Builds the overlay once a picked file arrives: map files become a
+/// tinted copy of that map's surface, anything else is read as an image
+/// stretched over the edited map.
+#[allow(clippy::too_many_arguments)]
+fn load_reference(
+    mut overlay: ResMut<ReferenceOverlay>,
+    state: Res<EditorState>,
+    handles: Res<ReferenceEntity>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    mut notify: EventWriter<Notify>,
+    localization: Res<Localization>,
+) {
+    if std::mem::take(&mut overlay.clear) {
+        overlay.path = None;
+        if let Some(mesh) = meshes.get_mut(&handles.mesh) {
+            *mesh = terrain::empty_mesh();
+        }
+        return;
+    }
+    if !overlay
+        .dialog_task
+        .as_ref()
+        .is_some_and(|task| task.is_finished())
+    {
+        return;
+    }
+    let Some(path) = block_on(overlay.dialog_task.take().unwrap()) else {
+        return;
+    };
+
+    let is_map = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ext.eq_ignore_ascii_case("tme")
+                || ext.eq_ignore_ascii_case(text_map::EXTENSION)
+                || ext.eq_ignore_ascii_case(JSON_EXTENSION)
+        });
+    let built = if is_map {
+        load_map(&path).map(|map| (build_map_mesh(&map), None))
+    } else {
+        load_image(&path).map(|image| {
+            let mesh = build_image_mesh(&state.map);
+            (mesh, Some(images.add(image)))
+        })
+    };
+
+    match built {
+        Ok((mesh, texture)) => {
+            if let Some(target) = meshes.get_mut(&handles.mesh) {
+                *target = mesh;
+            }
+            if let Some(material) = materials.get_mut(&handles.material) {
+                material.base_color_texture = texture;
+            }
+            overlay.path = Some(path);
+        }
+        Err(err) => {
+            notify.send(Notify::error(localization.format(
+                "notify.reference_failed",
+                &[("path", &path.display()), ("error", &err)],
+            )));
+        }
+    }
+}
+
+fn update_reference_overlay(
+    overlay: Res<ReferenceOverlay>,
+    handles: Res<ReferenceEntity>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut visibility: Query<&mut Visibility>,
+) {
+    if !overlay.is_changed() {
+        return;
+    }
+    if let Some(material) = materials.get_mut(&handles.material) {
+        material.base_color = Color::WHITE.with_alpha(overlay.opacity);
+    }
+    let Ok(mut visibility) = visibility.get_mut(handles.entity) else {
+        return;
+    };
+    let desired = if overlay.visible && overlay.path.is_some() {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    if *visibility != desired {
+        *visibility = desired;
+    }
+}
+
+fn load_image(path: &Path) -> anyhow::Result<Image> {
+    let image = image::open(path)?;
+    Ok(Image::from_dynamic(
+        image,
+        true,
+        RenderAssetUsages::default(),
+    ))
+}
+
+/// The reference map's top surfaces at its own heights, tinted by tile type
+/// and shaded darker towards low ground.
+fn build_map_mesh(map: &TileMap) -> Mesh {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut colors: Vec<[f32; 4]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    for y in 0..map.height {
+        for x in 0..map.width {
+            let tile = map.get(x, y);
+            if tile.kind == TileKind::Empty {
+                continue;
+            }
+            let corners = tile_corner_heights(map, x, y);
+            let [r, g, b] = tile.tile_type.preview_color();
+            let level = (tile.elevation - MIN_ELEVATION) as f32
+                / (MAX_ELEVATION - MIN_ELEVATION).max(1) as f32;
+            let shade = 0.5 + 0.5 * level;
+            let color = Color::srgb(r * shade, g * shade, b * shade)
+                .to_linear()
+                .to_f32_array();
+
+            let x0 = x as f32 * map.tile_size;
+            let x1 = x0 + map.tile_size;
+            let z0 = y as f32 * map.tile_size;
+            let z1 = z0 + map.tile_size;
+            let base = positions.len() as u32;
+            positions.extend([
+                [x0, corners[terrain::CORNER_NW] + REFERENCE_LIFT, z0],
+                [x1, corners[terrain::CORNER_NE] + REFERENCE_LIFT, z0],
+                [x1, corners[terrain::CORNER_SE] + REFERENCE_LIFT, z1],
+                [x0, corners[terrain::CORNER_SW] + REFERENCE_LIFT, z1],
+            ]);
+            colors.extend([color; 4]);
+            indices.extend([base, base + 3, base + 2, base, base + 2, base + 1]);
+        }
+    }
+
+    let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.insert_indices(Indices::U32(indices));
+    mesh
+}
+
+/// A sheet over the whole map just above its highest possible surface, like
+/// tracing paper, with the image's top edge along the map's north edge.
+fn build_image_mesh(map: &TileMap) -> Mesh {
+    let (w, d) = (
+        map.width as f32 * map.tile_size,
+        map.height as f32 * map.tile_size,
+    );
+    let y = MAX_ELEVATION as f32 * map.tile_height() + REFERENCE_LIFT;
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        vec![[0.0, y, 0.0], [w, y, 0.0], [w, y, d], [0.0, y, d]],
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; 4]);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_UV_0,
+        vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, vec![[1.0, 1.0, 1.0, 1.0]; 4]);
+    mesh.insert_indices(Indices::U32(vec![0, 3, 2, 0, 2, 1]));
+    mesh
+}
@@ -0,0 +1,262 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::math::Vec3;
+use serde::Serialize;
+
+use crate::terrain::CornerHeightCache;
+use crate::types::{RampDirection, RoadSurface, TileMap};
+
+/// The shape of a road on one tile, chosen by which of its neighbours it
+/// joins. Each is described unturned; `RoadShape` turns it into place. The
+/// terrain shader draws them by their position in `ALL`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RoadPiece {
+    /// A road tile with no road beside it.
+    Single,
+    /// A dead end leading north.
+    End,
+    /// Straight through from north to south.
+    Straight,
+    /// A bend from north to east.
+    Corner,
+    /// A junction joining north, east and south.
+    Tee,
+    Cross,
+}
+
+impl RoadPiece {
+    pub const ALL: [RoadPiece; 6] = [
+        RoadPiece::Single,
+        RoadPiece::End,
+        RoadPiece::Straight,
+        RoadPiece::Corner,
+        RoadPiece::Tee,
+        RoadPiece::Cross,
+    ];
+
+    /// Joined neighbours of the unturned piece, bit `i` standing for
+    /// `RampDirection::ALL[i]`.
+    fn joins(self) -> u8 {
+        match self {
+            RoadPiece::Single => 0b0000,
+            RoadPiece::End => 0b0001,
+            RoadPiece::Straight => 0b0101,
+            RoadPiece::Corner => 0b0011,
+            RoadPiece::Tee => 0b0111,
+            RoadPiece::Cross => 0b1111,
+        }
+    }
+
+    /// The piece and clockwise quarter turns that join exactly the
+    /// neighbours in `joins`.
+    fn fitting(joins: u8) -> (RoadPiece, u8) {
+        for piece in RoadPiece::ALL {
+            for turns in 0..4 {
+                // A clockwise turn carries each direction to the next one in
+                // `RampDirection::ALL`.
+                let mask = piece.joins();
+                if ((mask << turns | mask >> (4 - turns)) & 0b1111) == joins {
+                    return (piece, turns);
+                }
+            }
+        }
+        unreachable!("every set of edge neighbours has a piece")
+    }
+}
+
+/// How the road on one tile is drawn.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RoadShape {
+    pub surface: RoadSurface,
+    pub piece: RoadPiece,
+    /// Clockwise quarter turns applied to the piece.
+    pub quarter_turns: u8,
+}
+
+/// The road shape of every tile, row-major, `None` where there is no road.
+/// Roads join the neighbouring road tiles a unit can walk to, so a road
+/// along a cliff top does not bend down into one below it.
+pub fn road_shapes(map: &TileMap) -> Vec<Option<RoadShape>> {
+    if map.roads.is_empty() {
+        return vec![None; map.tiles.len()];
+    }
+    let reachability = map.reachability();
+    (0..map.height)
+        .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let surface = map.road(x, y)?;
+            let walkable = reachability.edge_mask(x, y);
+            let joins = RampDirection::ALL
+                .into_iter()
+                .enumerate()
+                .filter(|&(bit, dir)| {
+                    let (dx, dy) = dir.offset();
+                    let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                    walkable & (1 << bit) != 0 && map.road(nx as u32, ny as u32).is_some()
+                })
+                .fold(0, |joins, (bit, _)| joins | 1 << bit);
+            let (piece, quarter_turns) = RoadPiece::fitting(joins);
+            Some(RoadShape {
+                surface,
+                piece,
+                quarter_turns,
+            })
+        })
+        .collect()
+}
+
+/// The painted roads as a graph the game's traffic can follow: nodes at
+/// junctions and dead ends, edges along the road tiles between them.
+/// Two road tiles are linked when a unit can walk between them, so a road
+/// running past a cliff edge does not join the road below it.
+#[derive(Serialize)]
+pub struct RoadNetwork {
+    pub nodes: Vec<RoadNode>,
+    pub edges: Vec<RoadEdge>,
+}
+
+#[derive(Serialize)]
+pub struct RoadNode {
+    pub tile: [u32; 2],
+    pub position: [f32; 3],
+}
+
+#[derive(Serialize)]
+pub struct RoadEdge {
+    /// Indices into `nodes`.
+    pub from: usize,
+    pub to: usize,
+    /// World units across; roads are painted one tile wide.
+    pub width: f32,
+    /// World units along the road.
+    pub length: f32,
+    /// Tile-centre positions from `from` to `to`, both ends included.
+    pub points: Vec<[f32; 3]>,
+}
+
+impl RoadNetwork {
+    pub fn from_map(map: &TileMap, heights: &CornerHeightCache) -> Self {
+        let reachability = map.reachability();
+        let links: HashMap<(u32, u32), Vec<(u32, u32)>> = (0..map.height)
+            .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| map.road(x, y).is_some())
+            .map(|(x, y)| {
+                let next = reachability
+                    .neighbors(x, y)
+                    .filter(|&(nx, ny)| map.road(nx, ny).is_some())
+                    .collect();
+                ((x, y), next)
+            })
+            .collect();
+        // Row-major, so the output doesn't depend on hash order.
+        let mut tiles: Vec<(u32, u32)> = links.keys().copied().collect();
+        tiles.sort_unstable_by_key(|&(x, y)| (y, x));
+
+        let mut builder = Builder {
+            heights,
+            links: &links,
+            node_ids: HashMap::new(),
+            walked: HashSet::new(),
+            network: RoadNetwork {
+                nodes: Vec::new(),
+                edges: Vec::new(),
+            },
+        };
+
+        // Every tile that isn't the middle of a run becomes a node.
+        for &tile in &tiles {
+            if links[&tile].len() != 2 {
+                builder.add_node(tile);
+            }
+        }
+        for &tile in &tiles {
+            if builder.node_ids.contains_key(&tile) {
+                for &next in &links[&tile] {
+                    builder.trace(tile, next);
+                }
+            }
+        }
+        // Closed loops have no junction to start from; anchor each at its
+        // first tile.
+        for &tile in &tiles {
+            if builder.node_ids.contains_key(&tile) {
+                continue;
+            }
+            let first = links[&tile][0];
+            if !builder.walked.contains(&(tile, first)) && !builder.walked.contains(&(first, tile))
+            {
+                builder.add_node(tile);
+                builder.trace(tile, first);
+            }
+        }
+
+        builder.network
+    }
+}
+
+struct Builder<'a> {
+    heights: &'a CornerHeightCache,
+    links: &'a HashMap<(u32, u32), Vec<(u32, u32)>>,
+    node_ids: HashMap<(u32, u32), usize>,
+    /// Steps between neighbouring road tiles already covered by an edge,
+    /// recorded in the direction they were walked.
+    walked: HashSet<((u32, u32), (u32, u32))>,
+    network: RoadNetwork,
+}
+
+impl Builder<'_> {
+    fn add_node(&mut self, tile: (u32, u32)) {
+        self.node_ids.insert(tile, self.network.nodes.len());
+        self.network.nodes.push(RoadNode {
+            tile: [tile.0, tile.1],
+            position: tile_center(self.heights, tile).to_array(),
+        });
+    }
+
+    /// Follows the road from the node at `start` through `first` until it
+    /// reaches another node, and records the run as an edge unless it was
+    /// already walked from the other end.
+    fn trace(&mut self, start: (u32, u32), first: (u32, u32)) {
+        if self.walked.contains(&(first, start)) || !self.walked.insert((start, first)) {
+            return;
+        }
+        let mut path = vec![start, first];
+        let (mut previous, mut current) = (start, first);
+        while !self.node_ids.contains_key(&current) {
+            let next = self.links[&current]
+                .iter()
+                .copied()
+                .find(|&next| next != previous)
+                .expect("tiles between nodes have two road neighbours");
+            self.walked.insert((current, next));
+            path.push(next);
+            (previous, current) = (current, next);
+        }
+
+        let points: Vec<Vec3> = path
+            .iter()
+            .map(|&tile| tile_center(self.heights, tile))
+            .collect();
+        self.network.edges.push(RoadEdge {
+            from: self.node_ids[&start],
+            to: self.node_ids[&current],
+            width: self.heights.tile_size(),
+            length: points
+                .windows(2)
+                .map(|pair| pair[0].distance(pair[1]))
+                .sum(),
+            points: points.iter().map(|point| point.to_array()).collect(),
+        });
+    }
+}
+
+fn tile_center(heights: &CornerHeightCache, (x, y): (u32, u32)) -> Vec3 {
+    let height = heights
+        .corners(x, y)
+        .map_or(0.0, |corners| corners.iter().sum::<f32>() / 4.0);
+    Vec3::new(
+        (x as f32 + 0.5) * heights.tile_size(),
+        height,
+        (y as f32 + 0.5) * heights.tile_size(),
+    )
+}
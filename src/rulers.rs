@@ -0,0 +1,325 @@
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+use crate::camera::MainCamera;
+use crate::editor::EditorState;
+use crate::localization::Localization;
+use crate::terrain::{CornerHeightCache, TerrainMeshSet};
+
+/// Ruler thickness in logical pixels.
+const RULER_SIZE: f32 = 18.0;
+const GUIDE_COLOR: Color = Color::srgb(0.2, 0.9, 0.9);
+const GUIDE_MARKER: egui::Color32 = egui::Color32::from_rgb(50, 230, 230);
+/// Labelled tick spacings, in tiles, picked from smallest to fit.
+const LABEL_STEPS: [u32; 6] = [1, 2, 5, 10, 20, 50];
+/// Closest labelled ticks may get, in logical pixels.
+const MIN_LABEL_SPACING: f32 = 24.0;
+
+/// Rulers along the top and left of the view, marked in tile coordinates.
+/// Dragging out of a ruler places a guide on a tile boundary; dragging a
+/// guide back onto its ruler removes it.
+pub struct RulersPlugin;
+
+impl Plugin for RulersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Guides>()
+            .init_gizmo_group::<GuideGizmos>()
+            .add_systems(Startup, configure_guide_gizmos)
+            .add_systems(
+                Update,
+                (
+                    show_rulers.after(TerrainMeshSet::Edit),
+                    draw_guides.after(TerrainMeshSet::Prepare),
+                ),
+            );
+    }
+}
+
+/// Which tile coordinate a guide fixes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GuideAxis {
+    /// A line of constant tile x, placed from the top ruler.
+    X,
+    /// A line of constant tile y, placed from the left ruler.
+    Y,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Guide {
+    pub axis: GuideAxis,
+    /// Tile boundary index, from 0 to the map size inclusive.
+    pub position: u32,
+}
+
+#[derive(Resource, Default)]
+pub struct Guides {
+    pub guides: Vec<Guide>,
+    /// Index of the guide being dragged.
+    dragging: Option<usize>,
+}
+
+#[derive(Default, Reflect, GizmoConfigGroup)]
+struct GuideGizmos;
+
+fn configure_guide_gizmos(mut configs: ResMut<GizmoConfigStore>) {
+    let (config, _) = configs.config_mut::<GuideGizmos>();
+    // Guides stay visible through raised terrain.
+    config.depth_bias = -1.0;
+}
+
+fn show_rulers(
+    mut egui: EguiContexts,
+    state: Res<EditorState>,
+    mut guides: ResMut<Guides>,
+    localization: Res<Localization>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    if !state.show_rulers {
+        guides.dragging = None;
+        return;
+    }
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+    let Some(viewport) = camera.logical_viewport_rect() else {
+        return;
+    };
+    let ctx = egui.ctx_mut();
+    let area = ctx.available_rect().intersect(egui::Rect::from_min_max(
+        egui::pos2(viewport.min.x, viewport.min.y),
+        egui::pos2(viewport.max.x, viewport.max.y),
+    ));
+    if area.width() <= RULER_SIZE || area.height() <= RULER_SIZE {
+        return;
+    }
+
+    // Tile-space point on the ground plane under a screen position.
+    let ground = |pos: egui::Pos2| -> Option<Vec2> {
+        let ray =
+            camera.viewport_to_world(camera_transform, Vec2::new(pos.x, pos.y) - viewport.min)?;
+        let t = -ray.origin.y / ray.direction.y;
+        if !t.is_finite() {
+            return None;
+        }
+        let hit = ray.origin + *ray.direction * t;
+        Some(Vec2::new(hit.x, hit.z) / state.map.tile_size)
+    };
+
+    let size = UVec2::new(state.map.width, state.map.height);
+    let rulers = [
+        (
+            GuideAxis::X,
+            egui::Rect::from_min_size(area.min, egui::vec2(area.width(), RULER_SIZE)),
+        ),
+        (
+            GuideAxis::Y,
+            egui::Rect::from_min_size(
+                area.min + egui::vec2(0.0, RULER_SIZE),
+                egui::vec2(RULER_SIZE, area.height() - RULER_SIZE),
+            ),
+        ),
+    ];
+    for (axis, rect) in rulers {
+        egui::Area::new(egui::Id::new(("ruler", axis == GuideAxis::X)))
+            .fixed_pos(rect.min)
+            .show(ctx, |ui| {
+                ruler(ui, rect, axis, size, &ground, &mut guides, &localization);
+            });
+    }
+}
+
+fn ruler(
+    ui: &mut egui::Ui,
+    rect: egui::Rect,
+    axis: GuideAxis,
+    map_size: UVec2,
+    ground: &dyn Fn(egui::Pos2) -> Option<Vec2>,
+    guides: &mut Guides,
+    localization: &Localization,
+) {
+    let (rect, response) = ui.allocate_exact_size(rect.size(), egui::Sense::drag());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(160));
+
+    let limit = match axis {
+        GuideAxis::X => map_size.x,
+        GuideAxis::Y => map_size.y,
+    };
+    let coordinate = |pos: egui::Pos2| {
+        ground(pos).map(|point| match axis {
+            GuideAxis::X => point.x,
+            GuideAxis::Y => point.y,
+        })
+    };
+
+    // Walk the ruler's inner edge and note where it crosses tile boundaries.
+    let length = match axis {
+        GuideAxis::X => rect.width(),
+        GuideAxis::Y => rect.height(),
+    };
+    let inner = |t: f32| match axis {
+        GuideAxis::X => egui::pos2(rect.left() + t, rect.bottom()),
+        GuideAxis::Y => egui::pos2(rect.right(), rect.top() + t),
+    };
+    let mut crossings: Vec<(f32, u32)> = Vec::new();
+    let mut previous: Option<i64> = None;
+    let mut t = 0.0;
+    while t <= length {
+        if let Some(value) = coordinate(inner(t)) {
+            let cell = value.floor() as i64;
+            if let Some(prev) = previous.filter(|&prev| (prev - cell).abs() == 1) {
+                let boundary = prev.max(cell);
+                if (0..=limit as i64).contains(&boundary) {
+                    crossings.push((t, boundary as u32));
+                }
+            }
+            previous = Some(cell);
+        }
+        t += 1.0;
+    }
+
+    let spacing = crossings
+        .windows(2)
+        .map(|pair| pair[1].0 - pair[0].0)
+        .fold(f32::INFINITY, f32::min);
+    let step = LABEL_STEPS
+        .into_iter()
+        .find(|&step| step as f32 * spacing >= MIN_LABEL_SPACING)
+        .unwrap_or(100);
+
+    let text_color = ui.visuals().text_color();
+    for &(t, boundary) in &crossings {
+        let labelled = boundary % step == 0;
+        let tick = if labelled {
+            RULER_SIZE * 0.5
+        } else {
+            RULER_SIZE * 0.25
+        };
+        let from = inner(t);
+        let (to, label_pos) = match axis {
+            GuideAxis::X => (
+                from - egui::vec2(0.0, tick),
+                egui::pos2(from.x + 2.0, rect.top()),
+            ),
+            GuideAxis::Y => (
+                from - egui::vec2(tick, 0.0),
+                egui::pos2(rect.left() + 1.0, from.y + 1.0),
+            ),
+        };
+        painter.line_segment([from, to], egui::Stroke::new(1.0, text_color));
+        if labelled {
+            painter.text(
+                label_pos,
+                egui::Align2::LEFT_TOP,
+                boundary.to_string(),
+                egui::FontId::monospace(9.0),
+                text_color,
+            );
+        }
+        let guided = guides
+            .guides
+            .iter()
+            .any(|guide| guide.axis == axis && guide.position == boundary);
+        if guided {
+            painter.circle_filled(from, 3.0, GUIDE_MARKER);
+        }
+    }
+
+    let snapped = |pos: egui::Pos2| {
+        coordinate(pos).map(|value| value.round().clamp(0.0, limit as f32) as u32)
+    };
+    let pointer = response.interact_pointer_pos();
+
+    if response.drag_started() {
+        // Grab a guide whose marker is under the pointer, or pull out a new one.
+        let grabbed = pointer.and_then(|pos| {
+            let along = match axis {
+                GuideAxis::X => pos.x - rect.left(),
+                GuideAxis::Y => pos.y - rect.top(),
+            };
+            crossings
+                .iter()
+                .find(|(t, _)| (t - along).abs() <= 4.0)
+                .and_then(|&(_, boundary)| {
+                    guides
+                        .guides
+                        .iter()
+                        .position(|guide| guide.axis == axis && guide.position == boundary)
+                })
+        });
+        guides.dragging = grabbed.or_else(|| {
+            guides.guides.push(Guide { axis, position: 0 });
+            Some(guides.guides.len() - 1)
+        });
+    }
+
+    let Some(index) = guides
+        .dragging
+        .filter(|&index| index < guides.guides.len() && guides.guides[index].axis == axis)
+    else {
+        return;
+    };
+    if let Some(position) = pointer.filter(|_| response.dragged()).and_then(snapped) {
+        guides.guides[index].position = position;
+    }
+    if response.drag_stopped() {
+        guides.dragging = None;
+        // Dropping a guide back on its ruler, or off the map, removes it.
+        if pointer.is_none_or(|pos| rect.contains(pos) || snapped(pos).is_none()) {
+            guides.guides.remove(index);
+        }
+    }
+    response.context_menu(|ui| {
+        if ui.button(localization.tr("rulers.clear_guides")).clicked() {
+            guides.guides.retain(|guide| guide.axis != axis);
+            guides.dragging = None;
+            ui.close_menu();
+        }
+    });
+}
+
+/// Draws each guide along its tile boundary, following the terrain so it
+/// stays on the surface across plateaus.
+fn draw_guides(
+    mut gizmos: Gizmos<GuideGizmos>,
+    state: Res<EditorState>,
+    guides: Res<Guides>,
+    heights: Res<CornerHeightCache>,
+) {
+    if !state.show_rulers {
+        return;
+    }
+    let lift = 0.04;
+    // The higher side of the boundary, so cliffs do not hide the guide.
+    let surface = |x: f32, z: f32, axis: GuideAxis| {
+        [-0.01, 0.01]
+            .into_iter()
+            .filter_map(|offset| match axis {
+                GuideAxis::X => heights.height_at(x + offset, z),
+                GuideAxis::Y => heights.height_at(x, z + offset),
+            })
+            .reduce(f32::max)
+            .unwrap_or(0.0)
+    };
+    for guide in &guides.guides {
+        let tile_size = state.map.tile_size;
+        let boundary = guide.position as f32 * tile_size;
+        let (length, runs_along_x) = match guide.axis {
+            GuideAxis::X => (state.map.height, false),
+            GuideAxis::Y => (state.map.width, true),
+        };
+        let points = (0..length).flat_map(|cell| {
+            let start = cell as f32 * tile_size + 0.01;
+            let end = (cell + 1) as f32 * tile_size - 0.01;
+            [start, end].map(|a| {
+                let (x, z) = if runs_along_x {
+                    (a, boundary)
+                } else {
+                    (boundary, a)
+                };
+                Vec3::new(x, surface(x, z, guide.axis) + lift, z)
+            })
+        });
+        gizmos.linestrip(points, GUIDE_COLOR);
+    }
+}
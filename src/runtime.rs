@@ -1,61 +1,267 @@
-use crate::editor::EditorState;
-use crate::terrain::{self, TerrainMeshSet, splatmap};
+use crate::io::TileMapLoader;
+use crate::lightmap::{self, BakedLightmap, LIGHTMAP_FILE};
+use crate::notifications::{self, Notify};
+use crate::terrain::{
+    self, CornerHeightCache, MeshStorage, TerrainMeshSet, road_map, splatmap, variation_map,
+};
 use crate::texture::material::{self, TerrainMaterial};
 use crate::texture::registry::TerrainTextureRegistry;
-use crate::types::{TILE_SIZE, TileType};
+use crate::types::{MIN_ELEVATION, Tile, TileKind, TileMap, TileType};
 use bevy::asset::{AssetId, LoadState};
 use bevy::math::{UVec2, Vec2};
 use bevy::pbr::MaterialMeshBundle;
 use bevy::prelude::*;
-use bevy::render::texture::Image;
+use bevy::render::primitives::Aabb;
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_resource::{
+    Extent3d, ImageCopyTexture, ImageDataLayout, Origin3d, TextureAspect,
+};
+use bevy::render::renderer::RenderQueue;
+use bevy::render::texture::{GpuImage, Image, ImageLoaderSettings, TextureFormatPixelInfo};
+use bevy::render::{Extract, ExtractSchedule, Render, RenderApp, RenderSet};
+use bevy::tasks::{AsyncComputeTaskPool, Task, block_on};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::path::Path;
+
+/// Where the asset server reads files from, for checking which optional
+/// package files exist.
+const ASSET_ROOT: &str = "assets";
 
 pub struct RuntimePlugin;
 
 impl Plugin for RuntimePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_runtime_mesh).add_systems(
+        app.configure_sets(
             Update,
             (
-                generate_splat_map,
-                rebuild_runtime_mesh,
-                update_runtime_material,
+                TerrainMeshSet::Edit,
+                TerrainMeshSet::Apply,
+                TerrainMeshSet::Prepare,
+                TerrainMeshSet::Splat,
+                TerrainMeshSet::Rebuild,
+                TerrainMeshSet::Material,
             )
+                .chain(),
+        )
+        .init_asset::<TileMap>()
+        .init_asset_loader::<TileMapLoader>()
+        .init_resource::<RuntimeMap>()
+        .init_resource::<CornerHeightCache>()
+        .init_resource::<terrain::NormalSmoothing>()
+        .init_resource::<terrain::CliffTexturing>()
+        .init_resource::<DirtyTiles>()
+        .init_resource::<TerrainBuildTasks>()
+        .init_resource::<TexelUploads>()
+        .init_resource::<TerrainLod>()
+        .add_event::<terrain::TerrainChanged>()
+        .add_event::<TerrainMeshesReady>()
+        .add_event::<TerrainEdit>()
+        .add_event::<Notify>()
+        .add_systems(Update, notifications::log_notifications)
+        .add_systems(Startup, setup_runtime_mesh)
+        .add_systems(
+            Update,
+            (install_loaded_map, apply_terrain_edits)
+                .chain()
+                .in_set(TerrainMeshSet::Apply),
+        )
+        .add_systems(Update, load_package_lightmap.in_set(TerrainMeshSet::Apply))
+        .add_systems(Update, clear_dirty_tiles.after(TerrainMeshSet::Material))
+        .add_systems(
+            Update,
+            refresh_corner_heights.in_set(TerrainMeshSet::Prepare),
+        )
+        .add_systems(Update, generate_splat_map.in_set(TerrainMeshSet::Splat))
+        .add_systems(
+            Update,
+            (rebuild_runtime_mesh, select_terrain_lod)
                 .chain()
                 .in_set(TerrainMeshSet::Rebuild),
+        )
+        .add_systems(
+            Update,
+            update_runtime_material.in_set(TerrainMeshSet::Material),
         );
+        #[cfg(feature = "colliders")]
+        app.add_plugins(crate::colliders::TerrainColliderPlugin);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .init_resource::<PendingTexelUploads>()
+            .add_systems(ExtractSchedule, extract_texel_uploads)
+            .add_systems(
+                Render,
+                write_texel_uploads.in_set(RenderSet::PrepareResources),
+            );
     }
 }
 
+/// The map the runtime terrain is built from. Games change it during play by
+/// sending `TerrainEdit` events, which keep the derived terrain data in sync.
+#[derive(Resource)]
+pub struct RuntimeMap {
+    pub map: TileMap,
+}
+
+impl Default for RuntimeMap {
+    fn default() -> Self {
+        Self {
+            map: TileMap::new(0, 0),
+        }
+    }
+}
+
+/// A map asset to display. Once it finishes loading (and whenever it is
+/// reloaded) it replaces the contents of `RuntimeMap`.
+#[derive(Resource)]
+pub struct RuntimeMapHandle(pub Handle<TileMap>);
+
+/// Tiles changed this frame, so derived data can be patched instead of
+/// regenerated. Cleared once every terrain stage has run.
+#[derive(Resource, Default)]
+pub struct DirtyTiles {
+    tiles: HashSet<(u32, u32)>,
+    all: bool,
+}
+
+impl DirtyTiles {
+    pub fn mark(&mut self, x: u32, y: u32) {
+        if !self.all {
+            self.tiles.insert((x, y));
+        }
+    }
+
+    pub fn mark_all(&mut self) {
+        self.all = true;
+        self.tiles.clear();
+    }
+
+    /// Whether the whole map must be treated as changed.
+    pub fn is_all(&self) -> bool {
+        self.all
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.tiles.iter().copied()
+    }
+}
+
+/// Gameplay changes to the runtime terrain, such as craters or destroyed
+/// ramps. Edits are applied to `RuntimeMap` before the frame's terrain
+/// stages run, and only the touched tiles are rewritten in the splatmap.
+#[derive(Event, Clone)]
+pub enum TerrainEdit {
+    SetTile {
+        x: u32,
+        y: u32,
+        tile: Tile,
+    },
+    /// Lowers every tile within `radius` tiles of the center by `depth`
+    /// levels. Ramps caught in the blast collapse into floors.
+    Crater {
+        x: u32,
+        y: u32,
+        radius: u32,
+        depth: i8,
+    },
+}
+
+/// The runtime terrain: a parent entity, shown once its textures are ready,
+/// with one child per `TERRAIN_CHUNK_SIZE` chunk of tiles, all sharing one
+/// material.
 #[derive(Resource)]
 pub struct RuntimeTerrainVisual {
-    pub mesh: Handle<Mesh>,
     pub material: Handle<TerrainMaterial>,
     pub entity: Entity,
+    /// Chunk entities and meshes by chunk coordinate.
+    pub chunks: HashMap<UVec2, ChunkVisual>,
+    /// Map size the chunks were laid out for.
+    size: UVec2,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChunkVisual {
+    pub entity: Entity,
+    /// The full-detail mesh, which colliders and exports are built from.
+    pub mesh: Handle<Mesh>,
+    /// Simplified meshes, one per `terrain::TERRAIN_LOD_STEPS` entry.
+    pub lods: Vec<Handle<Mesh>>,
+    /// Level drawn: 0 for `mesh`, `n` for `lods[n - 1]`.
+    pub level: usize,
+}
+
+impl ChunkVisual {
+    fn level_mesh(&self, level: usize) -> &Handle<Mesh> {
+        level
+            .checked_sub(1)
+            .and_then(|lod| self.lods.get(lod))
+            .unwrap_or(&self.mesh)
+    }
+}
+
+/// When chunks far from the camera are drawn with simplified meshes.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct TerrainLod {
+    pub enabled: bool,
+    /// Distance in tiles from the nearest 3D camera to a chunk beyond which
+    /// each `terrain::TERRAIN_LOD_STEPS` level is used.
+    pub distances: [f32; terrain::TERRAIN_LOD_STEPS.len()],
+}
+
+impl Default for TerrainLod {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            distances: [64.0, 128.0],
+        }
+    }
+}
+
+impl RuntimeTerrainVisual {
+    /// Chunk meshes in row-major chunk order.
+    pub fn chunk_meshes(&self) -> Vec<&Handle<Mesh>> {
+        let mut chunks: Vec<_> = self.chunks.iter().collect();
+        chunks.sort_by_key(|(coord, _)| (coord.y, coord.x));
+        chunks.into_iter().map(|(_, chunk)| &chunk.mesh).collect()
+    }
+}
+
+/// Marks a chunk entity of the runtime terrain with the tiles it covers,
+/// both inclusive.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TerrainChunk {
+    pub min_tile: UVec2,
+    pub max_tile: UVec2,
 }
 
 #[derive(Resource)]
 pub struct RuntimeSplatMap {
     pub handle: Handle<Image>,
+    pub variation: Handle<Image>,
+    pub roads: Handle<Image>,
     pub size: UVec2,
 }
 
 fn setup_runtime_mesh(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<TerrainMaterial>>,
     mut images: ResMut<Assets<Image>>,
-    state: Res<EditorState>,
+    runtime_map: Res<RuntimeMap>,
 ) {
-    let mesh = meshes.add(terrain::empty_mesh());
     let material = material::create_runtime_material(&mut materials);
-    let splat_image = splatmap::create(&state.map);
+    let map = &runtime_map.map;
+    let splat_image = splatmap::create(map);
     let splat_handle = images.add(splat_image);
+    let variation_handle = images.add(variation_map::create(map));
+    let roads_handle = images.add(road_map::create(map));
     let entity = commands
         .spawn((
-            MaterialMeshBundle {
-                mesh: mesh.clone(),
-                material: material.clone(),
-                transform: Transform::default(),
+            SpatialBundle {
                 visibility: Visibility::Visible,
                 ..default()
             },
@@ -64,57 +270,678 @@ fn setup_runtime_mesh(
         .id();
 
     commands.insert_resource(RuntimeTerrainVisual {
-        mesh,
         material,
         entity,
+        chunks: HashMap::new(),
+        size: UVec2::ZERO,
     });
     commands.insert_resource(RuntimeSplatMap {
         handle: splat_handle,
-        size: UVec2::new(state.map.width.max(1), state.map.height.max(1)),
+        variation: variation_handle,
+        roads: roads_handle,
+        size: UVec2::new(map.width.max(1), map.height.max(1)),
     });
 }
 
-fn rebuild_runtime_mesh(
-    state: Res<EditorState>,
-    runtime: Option<Res<RuntimeTerrainVisual>>,
-    mut meshes: ResMut<Assets<Mesh>>,
+fn install_loaded_map(
+    handle: Option<Res<RuntimeMapHandle>>,
+    mut events: EventReader<AssetEvent<TileMap>>,
+    maps: Res<Assets<TileMap>>,
+    mut runtime_map: ResMut<RuntimeMap>,
+    mut heights: ResMut<CornerHeightCache>,
+    mut dirty: ResMut<DirtyTiles>,
+    mut changed: EventWriter<terrain::TerrainChanged>,
 ) {
-    if !state.map_dirty {
+    let Some(handle) = handle else {
+        events.clear();
+        return;
+    };
+    let loaded = events.read().any(|event| match event {
+        AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => {
+            *id == handle.0.id()
+        }
+        _ => false,
+    });
+    if !loaded {
         return;
     }
+    if let Some(map) = maps.get(&handle.0) {
+        runtime_map.map = map.clone();
+        heights.invalidate_all();
+        dirty.mark_all();
+        changed.send(terrain::TerrainChanged);
+    }
+}
 
-    let Some(runtime) = runtime else {
+/// Shades a map loaded from an export package with the lightmap baked into
+/// it, if the package has one.
+fn load_package_lightmap(
+    mut commands: Commands,
+    handle: Option<Res<RuntimeMapHandle>>,
+    mut events: EventReader<AssetEvent<TileMap>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(handle) = handle else {
+        events.clear();
+        return;
+    };
+    let loaded = events.read().any(|event| match event {
+        AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => {
+            *id == handle.0.id()
+        }
+        _ => false,
+    });
+    let Some(lightmap) = handle
+        .0
+        .path()
+        .filter(|_| loaded)
+        .and_then(|path| path.resolve_embed(LIGHTMAP_FILE).ok())
+    else {
         return;
     };
+    if !Path::new(ASSET_ROOT).join(lightmap.path()).is_file() {
+        commands.remove_resource::<BakedLightmap>();
+        return;
+    }
+    let handle = asset_server.load_with_settings(lightmap, |settings: &mut ImageLoaderSettings| {
+        // The channels are visibility fractions, not colors.
+        settings.is_srgb = false;
+        settings.sampler = lightmap::sampler();
+    });
+    commands.insert_resource(BakedLightmap { handle });
+}
 
-    let combined = terrain::build_combined_mesh(&state.map);
+fn apply_terrain_edits(
+    mut edits: EventReader<TerrainEdit>,
+    mut runtime_map: ResMut<RuntimeMap>,
+    mut heights: ResMut<CornerHeightCache>,
+    mut dirty: ResMut<DirtyTiles>,
+    mut changed: EventWriter<terrain::TerrainChanged>,
+) {
+    if edits.is_empty() {
+        return;
+    }
+    let map = &mut runtime_map.map;
+    let mut touched = Vec::new();
+    for edit in edits.read() {
+        match edit {
+            TerrainEdit::SetTile { x, y, tile } => {
+                if *x < map.width && *y < map.height {
+                    map.set(*x, *y, tile.clone());
+                    touched.push((*x, *y));
+                }
+            }
+            TerrainEdit::Crater {
+                x,
+                y,
+                radius,
+                depth,
+            } => {
+                let reach = *radius as i64;
+                for dy in -reach..=reach {
+                    for dx in -reach..=reach {
+                        let (tx, ty) = (*x as i64 + dx, *y as i64 + dy);
+                        if dx * dx + dy * dy > reach * reach
+                            || tx < 0
+                            || ty < 0
+                            || tx >= map.width as i64
+                            || ty >= map.height as i64
+                        {
+                            continue;
+                        }
+                        let idx = map.idx(tx as u32, ty as u32);
+                        let tile = &mut map.tiles[idx];
+                        if tile.kind == TileKind::Empty {
+                            continue;
+                        }
+                        tile.elevation = tile.elevation.saturating_sub(*depth).max(MIN_ELEVATION);
+                        if tile.kind == TileKind::Ramp {
+                            tile.kind = TileKind::Floor;
+                            tile.ramp_direction = None;
+                        }
+                        touched.push((tx as u32, ty as u32));
+                    }
+                }
+            }
+        }
+    }
+
+    for &(x, y) in &touched {
+        heights.invalidate_tile(x, y);
+        dirty.mark(x, y);
+    }
+    if !touched.is_empty() {
+        changed.send(terrain::TerrainChanged);
+    }
+}
+
+fn clear_dirty_tiles(mut dirty: ResMut<DirtyTiles>) {
+    if dirty.all || !dirty.tiles.is_empty() {
+        *dirty = DirtyTiles::default();
+    }
+}
+
+fn refresh_corner_heights(runtime_map: Res<RuntimeMap>, mut heights: ResMut<CornerHeightCache>) {
+    heights.refresh(&runtime_map.map);
+}
+
+/// Sent on the frame rebuilt chunk meshes are swapped in, for anything
+/// derived from the meshes themselves rather than from the map.
+#[derive(Event, Clone, Copy, Debug, Default)]
+pub struct TerrainMeshesReady;
+
+/// Splatmap and chunk mesh rebuilds running on the `AsyncComputeTaskPool`
+/// against a snapshot of the map, so large maps rebuild without stalling
+/// the frame. Changes arriving while a task runs are queued for the next.
+#[derive(Resource, Default)]
+struct TerrainBuildTasks {
+    splat: Option<Task<SplatImages>>,
+    mesh: Option<Task<ChunkMeshes>>,
+    splat_tiles: Pending<(u32, u32)>,
+    /// Whether the road map needs rewriting, which it does on every change
+    /// since a road's shape follows its neighbours.
+    splat_roads: bool,
+    mesh_chunks: Pending<UVec2>,
+    /// Buffers of replaced chunk meshes, refilled by the next mesh task.
+    spare_buffers: Vec<MeshStorage>,
+    /// The splatmap and variation map as last written. Partial rewrites
+    /// reach the GPU as `TexelUploads` and leave the image assets alone, so
+    /// the assets' own data is only current after a full rewrite.
+    splat_images: Option<(Image, Image)>,
+}
 
-    if let Some(existing) = meshes.get_mut(&runtime.mesh) {
-        *existing = combined;
+/// Most replaced mesh buffers kept for reuse: enough for an edit touching
+/// a few chunks at every level, without holding on to a whole map's worth
+/// after a full rebuild.
+const MAX_SPARE_BUFFERS: usize = 64;
+
+impl TerrainBuildTasks {
+    fn keep_spare(&mut self, storage: impl FnOnce() -> MeshStorage) {
+        if self.spare_buffers.len() < MAX_SPARE_BUFFERS {
+            self.spare_buffers.push(storage());
+        }
     }
 }
 
+#[derive(Default)]
+struct Pending<T> {
+    items: HashSet<T>,
+    all: bool,
+}
+
+impl<T: Copy + Eq + Hash> Pending<T> {
+    fn is_empty(&self) -> bool {
+        !self.all && self.items.is_empty()
+    }
+
+    fn mark_all(&mut self) {
+        self.all = true;
+        self.items.clear();
+    }
+
+    fn extend(&mut self, items: impl IntoIterator<Item = T>) {
+        if !self.all {
+            self.items.extend(items);
+        }
+    }
+
+    /// Empties the queue, returning `None` when everything was queued.
+    fn take(&mut self) -> Option<Vec<T>> {
+        let items = std::mem::take(&mut self.items);
+        (!std::mem::take(&mut self.all)).then(|| items.into_iter().collect())
+    }
+}
+
+struct SplatImages {
+    splat: Option<Image>,
+    variation: Option<Image>,
+    roads: Option<Image>,
+    size: UVec2,
+    /// The rows of the splatmap and variation map that were rewritten, or
+    /// `None` when they were rewritten in full.
+    rows: Option<Vec<terrain::TileRect>>,
+}
+
+/// Rows of the splatmap and variation map rewritten this frame, for the
+/// render world to write into the textures already on the GPU instead of
+/// uploading the images again.
+#[derive(Resource, Default)]
+struct TexelUploads {
+    uploads: Vec<TexelUpload>,
+    /// Images replaced in full this frame, which makes any of their uploads
+    /// still waiting in the render world out of date.
+    replaced: Vec<AssetId<Image>>,
+}
+
+#[derive(Clone)]
+struct TexelUpload {
+    image: AssetId<Image>,
+    /// First texel and the array page it is on.
+    origin: UVec3,
+    width: u32,
+    data: Vec<u8>,
+}
+
+impl TexelUploads {
+    fn queue(&mut self, image: &Handle<Image>, source: &Image, rows: &[terrain::TileRect]) {
+        let size = source.texture_descriptor.size;
+        let texel_len = source.texture_descriptor.format.pixel_size();
+        let row_len = size.width as usize * texel_len;
+        let page_len = row_len * size.height as usize;
+        for page in 0..size.depth_or_array_layers {
+            for row in rows {
+                let start = page as usize * page_len
+                    + row.min.y as usize * row_len
+                    + row.min.x as usize * texel_len;
+                let width = row.max.x - row.min.x + 1;
+                self.uploads.push(TexelUpload {
+                    image: image.id(),
+                    origin: row.min.extend(page),
+                    width,
+                    data: source.data[start..start + width as usize * texel_len].to_vec(),
+                });
+            }
+        }
+    }
+}
+
+/// Uploads waiting in the render world until their image is on the GPU.
+#[derive(Resource, Default)]
+struct PendingTexelUploads(Vec<TexelUpload>);
+
+fn extract_texel_uploads(
+    uploads: Extract<Res<TexelUploads>>,
+    mut pending: ResMut<PendingTexelUploads>,
+) {
+    pending
+        .0
+        .retain(|upload| !uploads.replaced.contains(&upload.image));
+    pending.0.extend(uploads.uploads.iter().cloned());
+}
+
+fn write_texel_uploads(
+    mut pending: ResMut<PendingTexelUploads>,
+    gpu_images: Res<RenderAssets<GpuImage>>,
+    queue: Res<RenderQueue>,
+) {
+    pending.0.retain(|upload| {
+        let Some(gpu_image) = gpu_images.get(upload.image) else {
+            return true;
+        };
+        queue.write_texture(
+            ImageCopyTexture {
+                texture: &gpu_image.texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: upload.origin.x,
+                    y: upload.origin.y,
+                    z: upload.origin.z,
+                },
+                aspect: TextureAspect::All,
+            },
+            &upload.data,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(upload.data.len() as u32),
+                rows_per_image: None,
+            },
+            Extent3d {
+                width: upload.width,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+        false
+    });
+}
+
+struct ChunkMeshes {
+    /// Map size the chunks were built for.
+    size: UVec2,
+    chunks: Vec<BuiltChunk>,
+    /// Spare buffers the task did not use.
+    spares: Vec<MeshStorage>,
+}
+
+struct BuiltChunk {
+    coord: UVec2,
+    tiles: TerrainChunk,
+    mesh: Mesh,
+    lods: Vec<Mesh>,
+    /// Bounds of every level together, so switching levels never leaves
+    /// the chunk culled by stale bounds.
+    bounds: Option<Aabb>,
+}
+
+/// The box holding all of `meshes`, or `None` if none have positions.
+fn combined_bounds<'a>(meshes: impl IntoIterator<Item = &'a Mesh>) -> Option<Aabb> {
+    meshes
+        .into_iter()
+        .filter_map(Mesh::compute_aabb)
+        .reduce(|a, b| Aabb::from_min_max(a.min().min(b.min()).into(), a.max().max(b.max()).into()))
+}
+
+/// Queues the chunks around the dirty tiles for remeshing, or every chunk
+/// when the whole map changed, and swaps in finished meshes, respawning
+/// the chunks when the map was resized.
+#[allow(clippy::too_many_arguments)]
+fn rebuild_runtime_mesh(
+    mut commands: Commands,
+    runtime_map: Res<RuntimeMap>,
+    heights: Res<terrain::CornerHeightCache>,
+    dirty: Res<DirtyTiles>,
+    mut changed: EventReader<terrain::TerrainChanged>,
+    mut ready: EventWriter<TerrainMeshesReady>,
+    smoothing: Res<terrain::NormalSmoothing>,
+    mut tasks: ResMut<TerrainBuildTasks>,
+    runtime: Option<ResMut<RuntimeTerrainVisual>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let Some(mut runtime) = runtime else {
+        changed.clear();
+        return;
+    };
+    let map = &runtime_map.map;
+    if !changed.is_empty() || smoothing.is_changed() {
+        changed.clear();
+        if dirty.is_all() || smoothing.is_changed() || runtime.chunks.is_empty() {
+            tasks.mesh_chunks.mark_all();
+        } else {
+            tasks
+                .mesh_chunks
+                .extend(terrain::chunks_touching(map, dirty.iter()));
+        }
+    }
+
+    if tasks.mesh.as_ref().is_some_and(Task::is_finished) {
+        let built = block_on(tasks.mesh.take().unwrap());
+        if runtime.size != built.size {
+            for (_, chunk) in runtime.chunks.drain() {
+                commands.entity(chunk.entity).despawn_recursive();
+                for handle in std::iter::once(&chunk.mesh).chain(&chunk.lods) {
+                    if let Some(mut mesh) = meshes.remove(handle) {
+                        tasks.keep_spare(|| MeshStorage::reclaim(&mut mesh));
+                    }
+                }
+            }
+            runtime.size = built.size;
+        }
+        for BuiltChunk {
+            coord,
+            tiles,
+            mesh,
+            lods,
+            bounds,
+        } in built.chunks
+        {
+            // Bevy works out bounds once per entity and never again, so
+            // rebuilt chunks carry their own.
+            if let Some(chunk) = runtime.chunks.get(&coord) {
+                match bounds {
+                    Some(bounds) => commands.entity(chunk.entity).insert(bounds),
+                    None => commands.entity(chunk.entity).remove::<Aabb>(),
+                };
+                for (handle, mesh) in std::iter::once(&chunk.mesh)
+                    .chain(&chunk.lods)
+                    .zip(std::iter::once(mesh).chain(lods))
+                {
+                    if let Some(existing) = meshes.get_mut(handle) {
+                        tasks.keep_spare(|| MeshStorage::reclaim(existing));
+                        *existing = mesh;
+                    }
+                }
+                continue;
+            }
+            let mesh = meshes.add(mesh);
+            let lods = lods.into_iter().map(|lod| meshes.add(lod)).collect();
+            let entity = commands
+                .spawn((
+                    MaterialMeshBundle {
+                        mesh: mesh.clone(),
+                        material: runtime.material.clone(),
+                        ..default()
+                    },
+                    tiles,
+                    Name::new(format!("TerrainChunk({}, {})", coord.x, coord.y)),
+                ))
+                .id();
+            if let Some(bounds) = bounds {
+                commands.entity(entity).insert(bounds);
+            }
+            commands.entity(runtime.entity).add_child(entity);
+            runtime.chunks.insert(
+                coord,
+                ChunkVisual {
+                    entity,
+                    mesh,
+                    lods,
+                    level: 0,
+                },
+            );
+        }
+        for spare in built.spares {
+            tasks.keep_spare(|| spare);
+        }
+        ready.send(TerrainMeshesReady);
+    }
+
+    if tasks.mesh.is_some() || tasks.mesh_chunks.is_empty() {
+        return;
+    }
+    let chunks = tasks.mesh_chunks.take();
+    let map = map.clone();
+    let heights = heights.clone();
+    let smoothing = *smoothing;
+    let mut spares = std::mem::take(&mut tasks.spare_buffers);
+    tasks.mesh = Some(AsyncComputeTaskPool::get().spawn(async move {
+        let chunks = chunks.unwrap_or_else(|| {
+            let grid = terrain::chunk_grid(&map);
+            (0..grid.y)
+                .flat_map(|y| (0..grid.x).map(move |x| UVec2::new(x, y)))
+                .collect()
+        });
+        let lods = terrain::build_chunk_lods(
+            &map,
+            &heights,
+            smoothing,
+            chunks.iter().copied(),
+            &mut spares,
+        );
+        let chunks = terrain::build_chunk_meshes(&map, &heights, smoothing, chunks, &mut spares)
+            .into_iter()
+            .zip(lods)
+            .map(|((coord, mesh), (_, lods))| {
+                let (min_tile, max_tile) = terrain::chunk_tiles(&map, coord);
+                let bounds = combined_bounds(std::iter::once(&mesh).chain(&lods));
+                BuiltChunk {
+                    coord,
+                    tiles: TerrainChunk { min_tile, max_tile },
+                    mesh,
+                    lods,
+                    bounds,
+                }
+            })
+            .collect();
+        ChunkMeshes {
+            size: UVec2::new(map.width, map.height),
+            chunks,
+            spares,
+        }
+    }));
+}
+
+/// Draws each chunk at the level its distance from the nearest active 3D
+/// camera calls for.
+fn select_terrain_lod(
+    lod: Res<TerrainLod>,
+    runtime_map: Res<RuntimeMap>,
+    runtime: Option<ResMut<RuntimeTerrainVisual>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut chunk_meshes: Query<&mut Handle<Mesh>, With<TerrainChunk>>,
+    chunks: Query<&TerrainChunk>,
+) {
+    let Some(mut runtime) = runtime else {
+        return;
+    };
+    let eyes: Vec<Vec3> = cameras
+        .iter()
+        .filter(|(camera, _)| camera.is_active)
+        .map(|(_, transform)| transform.translation())
+        .collect();
+    let tile_size = runtime_map.map.tile_size;
+    for chunk in runtime.chunks.values_mut() {
+        let Ok(tiles) = chunks.get(chunk.entity) else {
+            continue;
+        };
+        let level = if lod.enabled {
+            // Distance to the nearest point of the chunk's footprint.
+            let min = tiles.min_tile.as_vec2() * tile_size;
+            let max = (tiles.max_tile + UVec2::ONE).as_vec2() * tile_size;
+            let distance = eyes
+                .iter()
+                .map(|eye| {
+                    let nearest = eye.xz().clamp(min, max);
+                    Vec3::new(nearest.x, 0.0, nearest.y).distance(*eye)
+                })
+                .fold(f32::INFINITY, f32::min)
+                / tile_size;
+            lod.distances
+                .iter()
+                .take(chunk.lods.len())
+                .filter(|&&beyond| distance > beyond)
+                .count()
+        } else {
+            0
+        };
+        if level == chunk.level {
+            continue;
+        }
+        let Ok(mut mesh) = chunk_meshes.get_mut(chunk.entity) else {
+            continue;
+        };
+        chunk.level = level;
+        *mesh = chunk.level_mesh(level).clone();
+    }
+}
+
+/// Queues the dirty tiles for the splatmap, variation and road maps and
+/// hands the rewritten texels to the GPU once their task finishes: just the
+/// rewritten rows after a partial rewrite, the whole images otherwise.
 fn generate_splat_map(
-    state: Res<EditorState>,
+    runtime_map: Res<RuntimeMap>,
+    dirty: Res<DirtyTiles>,
+    mut changed: EventReader<terrain::TerrainChanged>,
+    mut tasks: ResMut<TerrainBuildTasks>,
     runtime_splat: Option<ResMut<RuntimeSplatMap>>,
     mut images: ResMut<Assets<Image>>,
+    mut uploads: ResMut<TexelUploads>,
 ) {
-    if !state.map_dirty {
-        return;
+    if !uploads.uploads.is_empty() || !uploads.replaced.is_empty() {
+        *uploads = TexelUploads::default();
     }
-
     let Some(mut runtime_splat) = runtime_splat else {
+        changed.clear();
         return;
     };
+    if !changed.is_empty() {
+        changed.clear();
+        tasks.splat_roads = true;
+        if dirty.is_all() {
+            tasks.splat_tiles.mark_all();
+        } else {
+            tasks.splat_tiles.extend(dirty.iter());
+        }
+    }
+
+    if tasks.splat.as_ref().is_some_and(Task::is_finished) {
+        let written = block_on(tasks.splat.take().unwrap());
+        if let (Some(splat), Some(variation)) = (written.splat, written.variation) {
+            for (handle, image) in [
+                (&runtime_splat.handle, &splat),
+                (&runtime_splat.variation, &variation),
+            ] {
+                match &written.rows {
+                    Some(rows) => uploads.queue(handle, image, rows),
+                    None => {
+                        if let Some(existing) = images.get_mut(handle) {
+                            *existing = image.clone();
+                        }
+                        uploads.replaced.push(handle.id());
+                    }
+                }
+            }
+            tasks.splat_images = Some((splat, variation));
+        }
+        if let (Some(image), Some(existing)) = (written.roads, images.get_mut(&runtime_splat.roads))
+        {
+            *existing = image;
+        }
+        runtime_splat.size = written.size;
+    }
 
-    let Some(image) = images.get_mut(&runtime_splat.handle) else {
+    if tasks.splat.is_some() || !tasks.splat_roads && tasks.splat_tiles.is_empty() {
         return;
+    }
+    tasks.splat_roads = false;
+    let rewrite = !tasks.splat_tiles.is_empty();
+    // `None` rewrites the whole map.
+    let rows = tasks.splat_tiles.take().map(|tiles| {
+        terrain::TileRect::rows(tiles)
+            .into_iter()
+            .filter_map(|row| row.clamped(&runtime_map.map))
+            .collect::<Vec<_>>()
+    });
+    let (mut splat, mut variation) = match tasks.splat_images.take() {
+        Some((splat, variation)) if rewrite => (Some(splat), Some(variation)),
+        _ if rewrite => (
+            images.get(&runtime_splat.handle).cloned(),
+            images.get(&runtime_splat.variation).cloned(),
+        ),
+        kept => {
+            tasks.splat_images = kept;
+            (None, None)
+        }
     };
-
-    splatmap::write(&state.map, image);
-    runtime_splat.size = UVec2::new(state.map.width.max(1), state.map.height.max(1));
+    let map = runtime_map.map.clone();
+    let mut roads = images.get(&runtime_splat.roads).cloned();
+    tasks.splat = Some(AsyncComputeTaskPool::get().spawn(async move {
+        let size = splat.as_ref().map(|image| image.texture_descriptor.size);
+        if let Some(image) = splat.as_mut() {
+            match &rows {
+                Some(rows) => {
+                    for &row in rows {
+                        splatmap::write_region(&map, image, row);
+                    }
+                }
+                None => splatmap::write(&map, image),
+            }
+        }
+        if let Some(image) = variation.as_mut() {
+            match &rows {
+                Some(rows) => {
+                    for &row in rows {
+                        variation_map::write_region(&map, image, row);
+                    }
+                }
+                None => variation_map::write(&map, image),
+            }
+        }
+        // A resized map recreates both images, which then go up in full.
+        let resized = splat.as_ref().map(|image| image.texture_descriptor.size) != size;
+        if let Some(image) = roads.as_mut() {
+            road_map::write(&map, image);
+        }
+        SplatImages {
+            splat,
+            variation,
+            roads,
+            size: UVec2::new(map.width.max(1), map.height.max(1)),
+            rows: rows.filter(|_| !resized),
+        }
+    }));
 }
 
+#[allow(clippy::too_many_arguments)]
 fn update_runtime_material(
     mut textures: ResMut<TerrainTextureRegistry>,
     mut images: ResMut<Assets<Image>>,
@@ -123,6 +950,10 @@ fn update_runtime_material(
     runtime: Option<Res<RuntimeTerrainVisual>>,
     mut visibility_query: Query<&mut Visibility>,
     splat: Option<Res<RuntimeSplatMap>>,
+    lightmap: Option<Res<BakedLightmap>>,
+    runtime_map: Res<RuntimeMap>,
+    cliffs: Res<terrain::CliffTexturing>,
+    mut notify: EventWriter<Notify>,
 ) {
     let Some(runtime) = runtime else {
         return;
@@ -143,6 +974,7 @@ fn update_runtime_material(
                 entry.preview.id(),
                 entry.tile_type,
                 &mut encountered_failure,
+                &mut notify,
                 "Terrain preview texture failed to load",
             );
 
@@ -152,6 +984,7 @@ fn update_runtime_material(
                     normal.id(),
                     entry.tile_type,
                     &mut encountered_failure,
+                    &mut notify,
                     "Terrain normal map failed to load",
                 );
             }
@@ -162,6 +995,7 @@ fn update_runtime_material(
                     roughness.id(),
                     entry.tile_type,
                     &mut encountered_failure,
+                    &mut notify,
                     "Terrain roughness map failed to load",
                 );
             }
@@ -172,6 +1006,7 @@ fn update_runtime_material(
                 &asset_server,
                 wall.base_color.id(),
                 &mut encountered_failure,
+                &mut notify,
                 "Wall base color texture failed to load",
             );
 
@@ -180,6 +1015,7 @@ fn update_runtime_material(
                     &asset_server,
                     normal.id(),
                     &mut encountered_failure,
+                    &mut notify,
                     "Wall normal map failed to load",
                 );
             }
@@ -189,6 +1025,7 @@ fn update_runtime_material(
                     &asset_server,
                     roughness.id(),
                     &mut encountered_failure,
+                    &mut notify,
                     "Wall roughness map failed to load",
                 );
             }
@@ -216,7 +1053,9 @@ fn update_runtime_material(
     };
 
     let Some(arrays) = textures.ensure_texture_arrays(&mut images) else {
-        error!("Failed to assemble terrain texture arrays after previews loaded");
+        notify.send(Notify::error(
+            "Failed to assemble terrain texture arrays after previews loaded",
+        ));
         *visibility = Visibility::Hidden;
         return;
     };
@@ -294,13 +1133,37 @@ fn update_runtime_material(
         material.extension.splat_map = Some(splat.handle.clone());
     }
 
+    if material.extension.variation_map.as_ref() != Some(&splat.variation) {
+        material.extension.variation_map = Some(splat.variation.clone());
+    }
+
+    if material.extension.road_map.as_ref() != Some(&splat.roads) {
+        material.extension.road_map = Some(splat.roads.clone());
+    }
+
+    match lightmap.as_ref() {
+        Some(baked) => {
+            if material.extension.lightmap.as_ref() != Some(&baked.handle) {
+                material.extension.lightmap = Some(baked.handle.clone());
+            }
+            material.extension.params.lightmap_enabled = 1;
+        }
+        None => {
+            material.extension.lightmap = None;
+            material.extension.params.lightmap_enabled = 0;
+        }
+    }
+
     material.extension.params.map_size = Vec2::new(splat.size.x as f32, splat.size.y as f32);
-    material.extension.params.tile_size = TILE_SIZE;
+    let tile_size = runtime_map.map.tile_size;
+    material.extension.params.tile_size = tile_size;
+    material.extension.params.uv_scale = material::uv_scale(tile_size);
     // The editor mesh already bakes the desired world-space scaling into the
     // vertex data, so the shader inputs remain at a neutral scale.
     material.extension.params.height_uv_scale = 1.0;
     material.extension.params.height_world_scale = 1.0;
     material.extension.params.cliff_blend_height = 0.2;
+    material.extension.params.cliff_slope = cliffs.slope_cosines();
     material.extension.params.wall_enabled = arrays.wall_layer_index.map(|_| 1u32).unwrap_or(0);
     material.extension.params.wall_layer_index = arrays.wall_layer_index.unwrap_or(u32::MAX);
     material.extension.params.wall_has_normal = if arrays.wall_has_normal { 1 } else { 0 };
@@ -314,12 +1177,16 @@ fn check_handle_state(
     id: AssetId<Image>,
     tile_type: TileType,
     encountered_failure: &mut bool,
+    notify: &mut EventWriter<Notify>,
     message: &str,
 ) -> bool {
     match asset_server.get_load_state(id) {
         Some(LoadState::Loaded) => false,
         Some(LoadState::Failed(_)) => {
-            error!(tile_type = ?tile_type, message);
+            notify.send(Notify::error(format!(
+                "{message} ({})",
+                tile_type.identifier()
+            )));
             *encountered_failure = true;
             false
         }
@@ -331,12 +1198,13 @@ fn check_image_handle_state(
     asset_server: &AssetServer,
     id: AssetId<Image>,
     encountered_failure: &mut bool,
+    notify: &mut EventWriter<Notify>,
     message: &str,
 ) -> bool {
     match asset_server.get_load_state(id) {
         Some(LoadState::Loaded) => false,
         Some(LoadState::Failed(_)) => {
-            error!("{message}");
+            notify.send(Notify::error(message));
             *encountered_failure = true;
             false
         }
@@ -0,0 +1,399 @@
+use std::collections::{HashSet, VecDeque};
+
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use crate::editor::{self, EditorState, EditorTool, MapEdit};
+use crate::input::TemporaryTool;
+use crate::io::{Stamp, StampTile};
+use crate::terrain::{self, CornerHeightCache, TerrainMeshSet};
+use crate::tools::outline_region;
+use crate::types::{RampDirection, Tile, TileKind, TileMap};
+
+const SELECTION_COLOR: Color = Color::srgb(1.0, 1.0, 0.2);
+const PASTE_COLOR: Color = Color::srgba(0.3, 0.8, 1.0, 0.6);
+const CONTROL_KEYS: [KeyCode; 2] = [KeyCode::ControlLeft, KeyCode::ControlRight];
+
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Selection>()
+            .init_resource::<Clipboard>()
+            .add_systems(
+                Update,
+                (
+                    select_rect,
+                    select_wand,
+                    clear_selection,
+                    clipboard_shortcuts,
+                    paste_clipboard,
+                )
+                    .in_set(TerrainMeshSet::Edit)
+                    .after(editor::update_hover),
+            )
+            .add_systems(Update, draw_selection.after(TerrainMeshSet::Prepare));
+    }
+}
+
+/// The set of tiles that region operations act on.
+#[derive(Resource, Default)]
+pub struct Selection {
+    tiles: HashSet<(u32, u32)>,
+}
+
+impl Selection {
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        self.tiles.contains(&(x, y))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.tiles.iter().copied()
+    }
+
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+    }
+
+    pub fn set_rect(&mut self, a: (u32, u32), b: (u32, u32)) {
+        self.tiles.clear();
+        for y in a.1.min(b.1)..=a.1.max(b.1) {
+            for x in a.0.min(b.0)..=a.0.max(b.0) {
+                self.tiles.insert((x, y));
+            }
+        }
+    }
+
+    pub fn extend(&mut self, tiles: impl IntoIterator<Item = (u32, u32)>) {
+        self.tiles.extend(tiles);
+    }
+}
+
+/// Tiles copied from a selection, or loaded from a stamp, for the Paste
+/// tool to place.
+#[derive(Resource, Default)]
+pub struct Clipboard {
+    tiles: Vec<StampTile>,
+    /// Tool active before switching to pasting, restored by Escape.
+    previous_tool: Option<EditorTool>,
+}
+
+impl Clipboard {
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Copies the selected tiles of `map`.
+    pub fn copy(&mut self, map: &TileMap, selection: &Selection) {
+        self.tiles = stamp_tiles(map, selection);
+    }
+
+    /// Loads `stamp` and switches to the Paste tool, remembering `base_tool`
+    /// to return to afterwards.
+    pub fn paste_stamp(&mut self, stamp: &Stamp, state: &mut EditorState, base_tool: EditorTool) {
+        self.tiles = stamp.tiles.clone();
+        self.start_pasting(state, base_tool);
+    }
+
+    fn start_pasting(&mut self, state: &mut EditorState, base_tool: EditorTool) {
+        if state.current_tool != EditorTool::Paste {
+            self.previous_tool = Some(base_tool);
+        }
+        state.current_tool = EditorTool::Paste;
+    }
+
+    /// Where each copied tile lands when pasted centred on `at`, leaving out
+    /// tiles that fall off the map.
+    pub fn placed(&self, map: &TileMap, at: (u32, u32)) -> Vec<(u32, u32, Tile)> {
+        self.tiles
+            .iter()
+            .filter_map(|stamp_tile| {
+                let (x, y) = (at.0 as i32 + stamp_tile.dx, at.1 as i32 + stamp_tile.dy);
+                if x < 0 || y < 0 || x as u32 >= map.width || y as u32 >= map.height {
+                    return None;
+                }
+                let (x, y) = (x as u32, y as u32);
+                Some((
+                    x,
+                    y,
+                    Tile {
+                        x,
+                        y,
+                        ..stamp_tile.tile.clone()
+                    },
+                ))
+            })
+            .collect()
+    }
+}
+
+/// The selected tiles of `map`, positioned relative to the middle of their
+/// bounding box so a paste lands centred on the cursor.
+pub fn stamp_tiles(map: &TileMap, selection: &Selection) -> Vec<StampTile> {
+    let xs = || selection.iter().map(|(x, _)| x);
+    let ys = || selection.iter().map(|(_, y)| y);
+    let (Some(min_x), Some(max_x), Some(min_y), Some(max_y)) =
+        (xs().min(), xs().max(), ys().min(), ys().max())
+    else {
+        return Vec::new();
+    };
+    let (mid_x, mid_y) = ((min_x + max_x) / 2, (min_y + max_y) / 2);
+    let mut tiles: Vec<StampTile> = selection
+        .iter()
+        .map(|(x, y)| StampTile {
+            dx: x as i32 - mid_x as i32,
+            dy: y as i32 - mid_y as i32,
+            tile: map.get(x, y).clone(),
+        })
+        .collect();
+    // Selections are unordered; keep saved stamps stable.
+    tiles.sort_by_key(|tile| (tile.dy, tile.dx));
+    tiles
+}
+
+/// Which tile properties the magic wand compares against the clicked tile.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WandSettings {
+    pub match_type: bool,
+    pub match_elevation: bool,
+    /// Largest elevation difference still counted as a match.
+    pub tolerance: u8,
+    /// Flood out from the clicked tile; otherwise select every similar tile
+    /// on the map.
+    pub contiguous: bool,
+}
+
+impl Default for WandSettings {
+    fn default() -> Self {
+        Self {
+            match_type: true,
+            match_elevation: true,
+            tolerance: 0,
+            contiguous: true,
+        }
+    }
+}
+
+impl WandSettings {
+    fn matches(&self, reference: &Tile, tile: &Tile) -> bool {
+        // Holes have no surface, so they only ever match other holes.
+        if (reference.kind == TileKind::Empty) != (tile.kind == TileKind::Empty) {
+            return false;
+        }
+        let type_ok = !self.match_type || reference.tile_type == tile.tile_type;
+        let elevation_ok =
+            !self.match_elevation || reference.elevation.abs_diff(tile.elevation) <= self.tolerance;
+        type_ok && elevation_ok
+    }
+}
+
+/// Tiles similar to the one at `(x, y)` under `settings`.
+pub fn wand_tiles(map: &TileMap, x: u32, y: u32, settings: &WandSettings) -> Vec<(u32, u32)> {
+    let reference = map.get(x, y);
+    if !settings.contiguous {
+        return (0..map.height)
+            .flat_map(|ty| (0..map.width).map(move |tx| (tx, ty)))
+            .filter(|&(tx, ty)| settings.matches(reference, map.get(tx, ty)))
+            .collect();
+    }
+
+    let mut visited = vec![false; map.tiles.len()];
+    visited[map.idx(x, y)] = true;
+    let mut queue = VecDeque::from([(x, y)]);
+    let mut found = Vec::new();
+    while let Some((cx, cy)) = queue.pop_front() {
+        found.push((cx, cy));
+        for dir in RampDirection::ALL {
+            let (dx, dy) = dir.offset();
+            let (nx, ny) = (cx as i32 + dx, cy as i32 + dy);
+            if nx < 0 || ny < 0 || nx >= map.width as i32 || ny >= map.height as i32 {
+                continue;
+            }
+            let (nx, ny) = (nx as u32, ny as u32);
+            let idx = map.idx(nx, ny);
+            if !visited[idx] && settings.matches(reference, &map.tiles[idx]) {
+                visited[idx] = true;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+    found
+}
+
+fn select_rect(
+    buttons: Res<ButtonInput<MouseButton>>,
+    state: Res<EditorState>,
+    mut selection: ResMut<Selection>,
+    mut anchor: Local<Option<(u32, u32)>>,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Select || !buttons.pressed(MouseButton::Left) {
+        *anchor = None;
+        return;
+    }
+    if buttons.just_pressed(MouseButton::Left) && !egui.ctx_mut().wants_pointer_input() {
+        *anchor = state.hover;
+    }
+    let (Some(start), Some(hover)) = (*anchor, state.hover) else {
+        return;
+    };
+    selection.set_rect(start, hover);
+}
+
+/// Click to select tiles similar to the clicked one; Shift-click adds to the
+/// current selection.
+fn select_wand(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<EditorState>,
+    mut selection: ResMut<Selection>,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Wand
+        || !buttons.just_pressed(MouseButton::Left)
+        || egui.ctx_mut().wants_pointer_input()
+    {
+        return;
+    }
+    let Some((x, y)) = state.hover else {
+        return;
+    };
+    if !keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        selection.clear();
+    }
+    selection.extend(wand_tiles(&state.map, x, y, &state.wand));
+}
+
+fn clear_selection(keys: Res<ButtonInput<KeyCode>>, mut selection: ResMut<Selection>) {
+    if keys.just_pressed(KeyCode::Escape) && !selection.is_empty() {
+        selection.clear();
+    }
+}
+
+/// Ctrl+C copies the selection, Ctrl+X copies it and leaves holes behind,
+/// and Ctrl+V switches to the Paste tool.
+fn clipboard_shortcuts(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut state: ResMut<EditorState>,
+    selection: Res<Selection>,
+    mut clipboard: ResMut<Clipboard>,
+    temporary: Res<TemporaryTool>,
+    mut edits: EventWriter<MapEdit>,
+    mut egui: EguiContexts,
+) {
+    if egui.ctx_mut().wants_keyboard_input() || !keys.any_pressed(CONTROL_KEYS) {
+        return;
+    }
+    let cut = keys.just_pressed(KeyCode::KeyX);
+    if (cut || keys.just_pressed(KeyCode::KeyC)) && !selection.is_empty() {
+        clipboard.copy(&state.map, &selection);
+        if cut {
+            edits.send_batch(selection.iter().map(|(x, y)| MapEdit::SetTile {
+                x,
+                y,
+                tile: Tile {
+                    kind: TileKind::Empty,
+                    ramp_direction: None,
+                    ..state.map.get(x, y).clone()
+                },
+            }));
+        }
+    }
+    if keys.just_pressed(KeyCode::KeyV) && !clipboard.is_empty() {
+        // Holding Ctrl has switched to the eraser for now.
+        let base_tool = temporary.base(&state);
+        clipboard.start_pasting(&mut state, base_tool);
+    }
+}
+
+/// Previews the clipboard centred on the hovered tile and stamps it on
+/// click; the stamp lands in one frame, so it undoes as a single step and
+/// becomes the new selection. Escape returns to the previous tool.
+#[allow(clippy::too_many_arguments)]
+fn paste_clipboard(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut state: ResMut<EditorState>,
+    mut clipboard: ResMut<Clipboard>,
+    mut selection: ResMut<Selection>,
+    heights: Res<CornerHeightCache>,
+    mut edits: EventWriter<MapEdit>,
+    mut gizmos: Gizmos,
+) {
+    if state.current_tool != EditorTool::Paste {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        state.current_tool = clipboard.previous_tool.take().unwrap_or(EditorTool::Select);
+        return;
+    }
+    // `update_hover` clears the hover while the pointer is over a panel.
+    let Some(at) = state.hover else {
+        return;
+    };
+    let placed = clipboard.placed(&state.map, at);
+    if buttons.just_pressed(MouseButton::Left) {
+        selection.clear();
+        selection.extend(placed.iter().map(|&(x, y, _)| (x, y)));
+        edits.send_batch(
+            placed
+                .into_iter()
+                .map(|(x, y, tile)| MapEdit::SetTile { x, y, tile }),
+        );
+        return;
+    }
+    outline_region(
+        &mut gizmos,
+        &heights,
+        placed.iter().map(|&(x, y, _)| (x, y)),
+        0.06,
+        PASTE_COLOR,
+    );
+}
+
+/// Outlines the selection's boundary, skipping edges shared by two selected
+/// tiles so large selections stay cheap and readable.
+fn draw_selection(mut gizmos: Gizmos, selection: Res<Selection>, heights: Res<CornerHeightCache>) {
+    let lift = 0.04;
+    for (x, y) in selection.iter() {
+        let Some(corners) = heights.corners(x, y) else {
+            continue;
+        };
+        let x0 = x as f32 * heights.tile_size();
+        let x1 = x0 + heights.tile_size();
+        let z0 = y as f32 * heights.tile_size();
+        let z1 = z0 + heights.tile_size();
+        let nw = Vec3::new(x0, corners[terrain::CORNER_NW] + lift, z0);
+        let ne = Vec3::new(x1, corners[terrain::CORNER_NE] + lift, z0);
+        let sw = Vec3::new(x0, corners[terrain::CORNER_SW] + lift, z1);
+        let se = Vec3::new(x1, corners[terrain::CORNER_SE] + lift, z1);
+
+        for dir in RampDirection::ALL {
+            let (dx, dy) = dir.offset();
+            let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+            if nx >= 0 && ny >= 0 && selection.contains(nx as u32, ny as u32) {
+                continue;
+            }
+            let (a, b) = match dir {
+                RampDirection::North => (nw, ne),
+                RampDirection::East => (ne, se),
+                RampDirection::South => (sw, se),
+                RampDirection::West => (nw, sw),
+                // Only edge neighbours are visited.
+                RampDirection::NorthEast
+                | RampDirection::SouthEast
+                | RampDirection::SouthWest
+                | RampDirection::NorthWest => continue,
+            };
+            gizmos.line(a, b, SELECTION_COLOR);
+        }
+    }
+}
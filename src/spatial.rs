@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::runtime::RuntimeMap;
+use crate::types::DEFAULT_TILE_SIZE;
+
+/// Tiles per side of a spatial bucket. Large enough that a pick touches
+/// a single bucket, small enough that a bucket never holds a whole map.
+const BUCKET_TILES: i32 = 8;
+
+pub struct SpatialIndexPlugin;
+
+impl Plugin for SpatialIndexPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpatialIndex>().add_systems(
+            PostUpdate,
+            (
+                follow_map_tile_size,
+                unindex_removed_entities,
+                index_moved_entities,
+            )
+                .chain()
+                .after(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Marks an entity placed on the map, such as a prop, so it can be looked
+/// up by position without scanning every entity.
+#[derive(Component, Default, Clone, Copy)]
+pub struct SpatiallyIndexed;
+
+/// Grid-bucketed lookup of indexed entities by their world XZ position.
+#[derive(Resource)]
+pub struct SpatialIndex {
+    buckets: HashMap<(i32, i32), Vec<Entity>>,
+    positions: HashMap<Entity, Vec3>,
+    /// World units per tile of the map the entities stand on.
+    tile_size: f32,
+}
+
+impl Default for SpatialIndex {
+    fn default() -> Self {
+        Self {
+            buckets: HashMap::new(),
+            positions: HashMap::new(),
+            tile_size: DEFAULT_TILE_SIZE,
+        }
+    }
+}
+
+impl SpatialIndex {
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn position(&self, entity: Entity) -> Option<Vec3> {
+        self.positions.get(&entity).copied()
+    }
+
+    /// Switches to a map with a different tile size, re-bucketing every
+    /// entity.
+    pub fn set_tile_size(&mut self, tile_size: f32) {
+        if self.tile_size == tile_size {
+            return;
+        }
+        self.tile_size = tile_size;
+        self.buckets.clear();
+        let entries: Vec<(Entity, Vec3)> = self.positions.drain().collect();
+        for (entity, position) in entries {
+            self.insert(entity, position);
+        }
+    }
+
+    /// Closest entity to `point` on the XZ plane within `radius` world units.
+    pub fn nearest(&self, point: Vec3, radius: f32) -> Option<Entity> {
+        let min = self.tile_of(point - Vec3::new(radius, 0.0, radius));
+        let max = self.tile_of(point + Vec3::new(radius, 0.0, radius));
+        let (bx0, by0) = bucket_of_tile(min.0, min.1);
+        let (bx1, by1) = bucket_of_tile(max.0, max.1);
+        let radius_sq = radius * radius;
+        let mut best: Option<(Entity, f32)> = None;
+        for by in by0..=by1 {
+            for bx in bx0..=bx1 {
+                let Some(bucket) = self.buckets.get(&(bx, by)) else {
+                    continue;
+                };
+                for entity in bucket {
+                    let Some(position) = self.positions.get(entity) else {
+                        continue;
+                    };
+                    let offset = Vec2::new(position.x - point.x, position.z - point.z);
+                    let distance_sq = offset.length_squared();
+                    if distance_sq > radius_sq {
+                        continue;
+                    }
+                    if best.is_none_or(|(_, current)| distance_sq < current) {
+                        best = Some((*entity, distance_sq));
+                    }
+                }
+            }
+        }
+        best.map(|(entity, _)| entity)
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec3) {
+        if let Some(previous) = self.positions.insert(entity, position) {
+            let old_bucket = self.bucket_of(previous);
+            if old_bucket == self.bucket_of(position) {
+                return;
+            }
+            self.remove_from_bucket(entity, old_bucket);
+        }
+        self.buckets
+            .entry(self.bucket_of(position))
+            .or_default()
+            .push(entity);
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        if let Some(position) = self.positions.remove(&entity) {
+            self.remove_from_bucket(entity, self.bucket_of(position));
+        }
+    }
+
+    fn tile_of(&self, position: Vec3) -> (i32, i32) {
+        (
+            (position.x / self.tile_size).floor() as i32,
+            (position.z / self.tile_size).floor() as i32,
+        )
+    }
+
+    fn bucket_of(&self, position: Vec3) -> (i32, i32) {
+        let (x, y) = self.tile_of(position);
+        bucket_of_tile(x, y)
+    }
+
+    fn remove_from_bucket(&mut self, entity: Entity, bucket: (i32, i32)) {
+        if let Some(entities) = self.buckets.get_mut(&bucket) {
+            entities.retain(|candidate| *candidate != entity);
+            if entities.is_empty() {
+                self.buckets.remove(&bucket);
+            }
+        }
+    }
+}
+
+fn bucket_of_tile(x: i32, y: i32) -> (i32, i32) {
+    (x.div_euclid(BUCKET_TILES), y.div_euclid(BUCKET_TILES))
+}
+
+type MovedIndexedEntities<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static GlobalTransform),
+    (With<SpatiallyIndexed>, Changed<GlobalTransform>),
+>;
+
+fn follow_map_tile_size(runtime_map: Res<RuntimeMap>, mut index: ResMut<SpatialIndex>) {
+    if runtime_map.is_changed() {
+        index.set_tile_size(runtime_map.map.tile_size);
+    }
+}
+
+fn index_moved_entities(mut index: ResMut<SpatialIndex>, moved: MovedIndexedEntities) {
+    for (entity, transform) in &moved {
+        index.insert(entity, transform.translation());
+    }
+}
+
+fn unindex_removed_entities(
+    mut index: ResMut<SpatialIndex>,
+    mut removed: RemovedComponents<SpatiallyIndexed>,
+) {
+    for entity in removed.read() {
+        index.remove(entity);
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::reachability::Reachability;
+use crate::types::{MAX_ELEVATION, MIN_ELEVATION, TileKind, TileMap, TileType};
+
+/// Number of elevation levels the editor tools produce.
+pub const ELEVATION_LEVELS: usize = (MAX_ELEVATION - MIN_ELEVATION) as usize + 1;
+
+/// Tile counts per elevation and per tile type. Holes are counted apart and
+/// left out of both distributions.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Histogram {
+    /// Indexed by `elevation - MIN_ELEVATION`.
+    pub elevations: [u32; ELEVATION_LEVELS],
+    /// Indexed by `TileType::as_index`.
+    pub tile_types: [u32; TileType::ALL.len()],
+    pub holes: u32,
+}
+
+impl Histogram {
+    pub fn of(map: &TileMap) -> Histogram {
+        let mut histogram = Histogram::default();
+        for tile in &map.tiles {
+            if tile.kind == TileKind::Empty {
+                histogram.holes += 1;
+                continue;
+            }
+            let level =
+                (tile.elevation.clamp(MIN_ELEVATION, MAX_ELEVATION) - MIN_ELEVATION) as usize;
+            histogram.elevations[level] += 1;
+            histogram.tile_types[tile.tile_type.as_index()] += 1;
+        }
+        histogram
+    }
+
+    /// Tiles with a surface, i.e. everything but holes.
+    pub fn solid(&self) -> u32 {
+        self.elevations.iter().sum()
+    }
+}
+
+/// A reflection or rotation a competitive map may be built around.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Symmetry {
+    MirrorX,
+    MirrorY,
+    Rotate180,
+    Rotate90,
+    Diagonal,
+}
+
+impl Symmetry {
+    /// Localization key for the symmetry's name.
+    pub fn label_key(self) -> &'static str {
+        match self {
+            Symmetry::MirrorX => "symmetry.mirror_x",
+            Symmetry::MirrorY => "symmetry.mirror_y",
+            Symmetry::Rotate180 => "symmetry.rotate_180",
+            Symmetry::Rotate90 => "symmetry.rotate_90",
+            Symmetry::Diagonal => "symmetry.diagonal",
+        }
+    }
+
+    /// Where the tile at `(x, y)` lands; the quarter-turn and diagonal
+    /// symmetries only apply to square maps.
+    fn map(self, map: &TileMap, x: u32, y: u32) -> (u32, u32) {
+        let (w, h) = (map.width - 1, map.height - 1);
+        match self {
+            Symmetry::MirrorX => (w - x, y),
+            Symmetry::MirrorY => (x, h - y),
+            Symmetry::Rotate180 => (w - x, h - y),
+            Symmetry::Rotate90 => (h - y, x),
+            Symmetry::Diagonal => (y, x),
+        }
+    }
+}
+
+/// Walkable tiles closer to one player's start than to any other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpawnZone {
+    pub player: u8,
+    pub walkable: u32,
+}
+
+/// A connected area of floor at one elevation above the lowest ground.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plateau {
+    pub elevation: i8,
+    pub tiles: u32,
+    /// Ramps that walk onto the plateau.
+    pub ramps: u32,
+    /// One of the plateau's tiles, to find it by.
+    pub anchor: (u32, u32),
+}
+
+/// Numbers competitive-map authors check balance against.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BalanceReport {
+    pub spawn_zones: Vec<SpawnZone>,
+    pub plateaus: Vec<Plateau>,
+    /// Fraction of solid tiles below elevation 0.
+    pub water_coverage: f32,
+    /// The symmetry the map follows most closely, and the fraction of tiles
+    /// whose kind and elevation match their counterpart under it.
+    pub symmetry: Option<(Symmetry, f32)>,
+}
+
+impl BalanceReport {
+    pub fn of(map: &TileMap) -> BalanceReport {
+        let reachability = map.reachability();
+
+        let sources: Vec<(u32, u32)> = map.starts.iter().map(|start| (start.x, start.y)).collect();
+        let nearest = reachability.nearest(&sources);
+        let spawn_zones = map
+            .starts
+            .iter()
+            .enumerate()
+            .map(|(source, start)| SpawnZone {
+                player: start.player,
+                walkable: nearest.iter().filter(|&&n| n == Some(source)).count() as u32,
+            })
+            .collect();
+
+        let histogram = Histogram::of(map);
+        let underwater: u32 = histogram.elevations[..(0 - MIN_ELEVATION) as usize]
+            .iter()
+            .sum();
+
+        BalanceReport {
+            spawn_zones,
+            plateaus: plateaus(map, &reachability),
+            water_coverage: underwater as f32 / histogram.solid().max(1) as f32,
+            symmetry: best_symmetry(map),
+        }
+    }
+}
+
+fn plateaus(map: &TileMap, reachability: &Reachability) -> Vec<Plateau> {
+    let floors = || map.tiles.iter().filter(|tile| tile.kind == TileKind::Floor);
+    let Some(ground) = floors().map(|tile| tile.elevation).min() else {
+        return Vec::new();
+    };
+
+    let mut seen = vec![false; map.tiles.len()];
+    let mut plateaus = Vec::new();
+    for start in 0..map.tiles.len() {
+        let tile = &map.tiles[start];
+        if seen[start] || tile.kind != TileKind::Floor || tile.elevation <= ground {
+            continue;
+        }
+        let elevation = tile.elevation;
+        let anchor = (start as u32 % map.width, start as u32 / map.width);
+        let mut tiles = 0;
+        let mut ramps = HashSet::new();
+        seen[start] = true;
+        let mut queue = VecDeque::from([anchor]);
+        while let Some((x, y)) = queue.pop_front() {
+            tiles += 1;
+            for (nx, ny) in reachability.neighbors(x, y) {
+                let idx = map.idx(nx, ny);
+                let neighbor = &map.tiles[idx];
+                if neighbor.kind == TileKind::Ramp {
+                    ramps.insert(idx);
+                } else if !seen[idx]
+                    && neighbor.kind == TileKind::Floor
+                    && neighbor.elevation == elevation
+                {
+                    seen[idx] = true;
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+        plateaus.push(Plateau {
+            elevation,
+            tiles,
+            ramps: ramps.len() as u32,
+            anchor,
+        });
+    }
+    plateaus
+}
+
+fn best_symmetry(map: &TileMap) -> Option<(Symmetry, f32)> {
+    if map.tiles.is_empty() {
+        return None;
+    }
+    let mut candidates = vec![Symmetry::MirrorX, Symmetry::MirrorY, Symmetry::Rotate180];
+    if map.width == map.height {
+        candidates.extend([Symmetry::Rotate90, Symmetry::Diagonal]);
+    }
+    candidates
+        .into_iter()
+        .map(|symmetry| {
+            let matching = (0..map.height)
+                .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+                .filter(|&(x, y)| {
+                    let (mx, my) = symmetry.map(map, x, y);
+                    let (a, b) = (map.get(x, y), map.get(mx, my));
+                    a.kind == b.kind && (a.kind == TileKind::Empty || a.elevation == b.elevation)
+                })
+                .count();
+            (symmetry, matching as f32 / map.tiles.len() as f32)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
@@ -1,9 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::types::{RampDirection, TILE_HEIGHT, TILE_SIZE, TileKind, TileMap, TileType};
+use crate::types::{
+    CORNER_OFFSET_STEPS, DEFAULT_TILE_SIZE, HOLE_DEPTH, LAYER_THICKNESS, MIN_ELEVATION,
+    RampDirection, TileKind, TileMap, TileType,
+};
 use bevy::ecs::schedule::SystemSet;
 use bevy::prelude::*;
-use bevy::render::mesh::{Indices, Mesh};
+use bevy::render::mesh::{Indices, Mesh, VertexAttributeValues};
 use bevy::render::render_asset::RenderAssetUsages;
 use bevy::render::render_resource::{
     AddressMode, FilterMode, PrimitiveTopology, SamplerDescriptor, TextureDimension, TextureFormat,
@@ -16,15 +19,256 @@ pub const CORNER_NE: usize = 1;
 pub const CORNER_SW: usize = 2;
 pub const CORNER_SE: usize = 3;
 
+/// Tiles along each side of a terrain chunk. The runtime terrain is meshed
+/// chunk by chunk, so an edit only rebuilds the chunks around it.
+pub const TERRAIN_CHUNK_SIZE: u32 = 16;
+/// Tiles per side of a cell of each simplified level of a chunk's mesh,
+/// from the nearest level to the farthest.
+pub const TERRAIN_LOD_STEPS: [u32; 2] = [2, 4];
+/// How far, in tiles, a tile's change reaches into its neighbours' meshes.
+const CHUNK_REACH: u32 = 2;
+
+/// Stages of the per-frame edit pipeline, chained in this order: tools and UI
+/// emit edits, the map is mutated, cached heights are refreshed, then the
+/// splatmap, meshes and material are updated from the new map.
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
 pub enum TerrainMeshSet {
+    Edit,
+    Apply,
+    Prepare,
+    Splat,
     Rebuild,
-    Cleanup,
+    Material,
+}
+
+/// Sent after edits have been applied to the map. Derived terrain data
+/// (splatmap, meshes) is rebuilt in response rather than by polling a flag.
+#[derive(Event, Clone, Copy, Debug, Default)]
+pub struct TerrainChanged;
+
+/// Whether top surfaces are shaded smoothly. When enabled, each top vertex
+/// averages the normals of the top faces meeting at its corner that bend
+/// away from its own face by at most `max_angle` degrees; cliff walls always
+/// keep their flat normals, so the crease along a cliff edge stays hard.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct NormalSmoothing {
+    pub enabled: bool,
+    pub max_angle: f32,
+}
+
+impl Default for NormalSmoothing {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_angle: 45.0,
+        }
+    }
+}
+
+/// Slopes at which top faces take on the cliff texture, so steep ramps and
+/// sculpted slopes read as rock without being painted by hand. Faces steeper
+/// than `start` degrees fade into the cliff layer and are fully cliff from
+/// `full` degrees on; side faces are always cliff.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct CliffTexturing {
+    pub enabled: bool,
+    pub start: f32,
+    pub full: f32,
+}
+
+impl Default for CliffTexturing {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            start: 30.0,
+            full: 50.0,
+        }
+    }
+}
+
+impl CliffTexturing {
+    /// Cosines of the `start` and `full` angles for the terrain shader, or
+    /// zeros when disabled.
+    pub fn slope_cosines(&self) -> Vec2 {
+        if !self.enabled {
+            return Vec2::ZERO;
+        }
+        let start = self.start.clamp(0.0, 89.0);
+        let full = self.full.clamp(start + 1.0, 90.0);
+        Vec2::new(start.to_radians().cos(), full.to_radians().cos())
+    }
+}
+
+impl NormalSmoothing {
+    /// Cosine of the largest angle between faces that are smoothed together,
+    /// or `None` when smoothing is off.
+    fn min_cos(&self) -> Option<f32> {
+        self.enabled
+            .then(|| self.max_angle.clamp(0.0, 180.0).to_radians().cos())
+    }
+}
+
+/// Corner heights for every tile, kept between rebuilds so edits only pay for
+/// the tiles they touch. Anything that needs the rendered surface height
+/// (meshing, hover gizmos, height queries) should read from here.
+#[derive(Resource, Clone)]
+pub struct CornerHeightCache {
+    width: u32,
+    height: u32,
+    tile_size: f32,
+    corners: Vec<[f32; 4]>,
+    holes: Vec<bool>,
+    pending: HashSet<(u32, u32)>,
+    rebuild_all: bool,
+}
+
+impl Default for CornerHeightCache {
+    fn default() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            tile_size: DEFAULT_TILE_SIZE,
+            corners: Vec::new(),
+            holes: Vec::new(),
+            pending: HashSet::new(),
+            rebuild_all: false,
+        }
+    }
+}
+
+impl CornerHeightCache {
+    pub fn from_map(map: &TileMap) -> Self {
+        let mut cache = Self::default();
+        cache.rebuild(map);
+        cache
+    }
+
+    /// Marks a tile as changed. A ramp's corners depend on the elevation of
+    /// its neighbours, diagonal ones included, so those are refreshed as well.
+    pub fn invalidate_tile(&mut self, x: u32, y: u32) {
+        self.pending.insert((x, y));
+        for dir in RampDirection::RAMPS {
+            let (dx, dy) = dir.offset();
+            let nx = x as i32 + dx;
+            let ny = y as i32 + dy;
+            if nx >= 0 && ny >= 0 {
+                self.pending.insert((nx as u32, ny as u32));
+            }
+        }
+    }
+
+    pub fn invalidate_all(&mut self) {
+        self.pending.clear();
+        self.rebuild_all = true;
+    }
+
+    /// Brings the cache up to date with `map`, recomputing only invalidated
+    /// tiles unless the map was replaced or resized.
+    pub fn refresh(&mut self, map: &TileMap) {
+        if self.rebuild_all
+            || self.width != map.width
+            || self.height != map.height
+            || self.tile_size != map.tile_size
+        {
+            self.rebuild(map);
+            return;
+        }
+
+        for (x, y) in self.pending.drain() {
+            if x < map.width && y < map.height {
+                let idx = map.idx(x, y);
+                self.corners[idx] = tile_corner_heights(map, x, y);
+                self.holes[idx] = map.tiles[idx].kind == TileKind::Empty;
+            }
+        }
+    }
+
+    /// World units per tile of the map the cache was built from.
+    pub fn tile_size(&self) -> f32 {
+        self.tile_size
+    }
+
+    /// Corner heights of a tile, or `None` outside the map and over holes.
+    pub fn corners(&self, x: u32, y: u32) -> Option<[f32; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let idx = (y * self.width + x) as usize;
+        if self.holes.get(idx).copied().unwrap_or(false) {
+            return None;
+        }
+        self.corners.get(idx).copied()
+    }
+
+    /// Height of the top surface at a world-space XZ position, interpolated
+    /// across the tile's corners.
+    pub fn height_at(&self, world_x: f32, world_z: f32) -> Option<f32> {
+        let tx = (world_x / self.tile_size).floor();
+        let tz = (world_z / self.tile_size).floor();
+        if tx < 0.0 || tz < 0.0 {
+            return None;
+        }
+        let corners = self.corners(tx as u32, tz as u32)?;
+        let u = (world_x / self.tile_size - tx).clamp(0.0, 1.0);
+        let v = (world_z / self.tile_size - tz).clamp(0.0, 1.0);
+        let north = corners[CORNER_NW] + (corners[CORNER_NE] - corners[CORNER_NW]) * u;
+        let south = corners[CORNER_SW] + (corners[CORNER_SE] - corners[CORNER_SW]) * u;
+        Some(north + (south - north) * v)
+    }
+
+    fn rebuild(&mut self, map: &TileMap) {
+        self.width = map.width;
+        self.height = map.height;
+        self.tile_size = map.tile_size;
+        self.corners.clear();
+        self.corners.reserve((map.width * map.height) as usize);
+        for y in 0..map.height {
+            for x in 0..map.width {
+                self.corners.push(tile_corner_heights(map, x, y));
+            }
+        }
+        self.holes = map
+            .tiles
+            .iter()
+            .map(|tile| tile.kind == TileKind::Empty)
+            .collect();
+        self.pending.clear();
+        self.rebuild_all = false;
+    }
+}
+
+/// Elevations of the solid tiles among the eight around (`x`, `y`).
+pub fn neighbor_elevations(map: &TileMap, x: u32, y: u32) -> impl Iterator<Item = i8> + '_ {
+    (-1i32..=1)
+        .flat_map(|dy| (-1i32..=1).map(move |dx| (dx, dy)))
+        .filter(|&offset| offset != (0, 0))
+        .filter_map(move |(dx, dy)| {
+            let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+            if nx < 0 || ny < 0 || nx as u32 >= map.width || ny as u32 >= map.height {
+                return None;
+            }
+            let (nx, ny) = (nx as u32, ny as u32);
+            is_solid(map, nx, ny).then(|| map.get(nx, ny).elevation)
+        })
+}
+
+/// Mean elevation of a solid tile and its solid neighbours, or `None` for a
+/// hole.
+pub fn neighborhood_mean_elevation(map: &TileMap, x: u32, y: u32) -> Option<f32> {
+    if !is_solid(map, x, y) {
+        return None;
+    }
+    let (sum, count) = neighbor_elevations(map, x, y)
+        .chain(std::iter::once(map.get(x, y).elevation))
+        .fold((0i32, 0u32), |(sum, count), elevation| {
+            (sum + elevation as i32, count + 1)
+        });
+    Some(sum as f32 / count as f32)
 }
 
 pub fn tile_corner_heights(map: &TileMap, x: u32, y: u32) -> [f32; 4] {
     let tile = map.get(x, y);
-    let base = tile.elevation as f32 * TILE_HEIGHT;
+    let base = tile.elevation as f32 * map.tile_height();
     let mut corners = [base; 4];
 
     if tile.kind == TileKind::Ramp {
@@ -54,10 +298,18 @@ pub fn tile_corner_heights(map: &TileMap, x: u32, y: u32) -> [f32; 4] {
                     corners[CORNER_NE] = neighbor_height;
                     corners[CORNER_SE] = neighbor_height;
                 }
+                RampDirection::NorthEast => corners[CORNER_NE] = neighbor_height,
+                RampDirection::SouthEast => corners[CORNER_SE] = neighbor_height,
+                RampDirection::SouthWest => corners[CORNER_SW] = neighbor_height,
+                RampDirection::NorthWest => corners[CORNER_NW] = neighbor_height,
             }
         }
     }
 
+    let step = map.tile_height() / CORNER_OFFSET_STEPS as f32;
+    for (corner, offset) in corners.iter_mut().zip(tile.corner_offsets) {
+        *corner += offset as f32 * step;
+    }
     corners
 }
 
@@ -68,23 +320,364 @@ pub fn empty_mesh() -> Mesh {
     )
 }
 
-pub fn build_map_meshes(map: &TileMap) -> HashMap<TileType, Mesh> {
+pub fn build_map_meshes(
+    map: &TileMap,
+    heights: &CornerHeightCache,
+    smoothing: NormalSmoothing,
+) -> HashMap<TileType, Mesh> {
     let mut buffers: HashMap<TileType, MeshBuffers> = HashMap::new();
-    populate_mesh_buffers(map, Some(&mut buffers), None);
+    let layers = UpperLayers::new(map);
+    populate_mesh_buffers(
+        map,
+        heights,
+        &layers,
+        TileArea::whole(map),
+        Some(&mut buffers),
+        None,
+    );
+    if let Some(min_cos) = smoothing.min_cos() {
+        smooth_top_normals(buffers.values_mut().collect(), min_cos);
+    }
     buffers
         .into_iter()
         .map(|(tile_type, buffer)| (tile_type, buffer.into_mesh()))
         .collect()
 }
 
-pub fn build_combined_mesh(map: &TileMap) -> Mesh {
+pub fn build_combined_mesh(
+    map: &TileMap,
+    heights: &CornerHeightCache,
+    smoothing: NormalSmoothing,
+) -> Mesh {
     let mut buffer = MeshBuffers::with_tile_types();
-    populate_mesh_buffers(map, None, Some(&mut buffer));
+    let layers = UpperLayers::new(map);
+    populate_mesh_buffers(
+        map,
+        heights,
+        &layers,
+        TileArea::whole(map),
+        None,
+        Some(&mut buffer),
+    );
+    if let Some(min_cos) = smoothing.min_cos() {
+        smooth_top_normals(vec![&mut buffer], min_cos);
+    }
     buffer.into_mesh()
 }
 
+/// The tiles from `min` to `max`, both inclusive, such as the area an edit
+/// touched.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TileRect {
+    pub min: UVec2,
+    pub max: UVec2,
+}
+
+impl TileRect {
+    /// `tiles` as runs of neighbouring tiles along each row, covering the
+    /// tiles and nothing else, top row first.
+    pub fn rows(tiles: impl IntoIterator<Item = (u32, u32)>) -> Vec<Self> {
+        let mut tiles: Vec<(u32, u32)> = tiles.into_iter().map(|(x, y)| (y, x)).collect();
+        tiles.sort_unstable();
+        tiles.dedup();
+        let mut rows: Vec<TileRect> = Vec::new();
+        for (y, x) in tiles {
+            match rows.last_mut() {
+                Some(row) if row.max.y == y && row.max.x + 1 == x => row.max.x = x,
+                _ => rows.push(TileRect {
+                    min: UVec2::new(x, y),
+                    max: UVec2::new(x, y),
+                }),
+            }
+        }
+        rows
+    }
+
+    /// The part of the rectangle on `map`, or `None` if it lies off it.
+    pub fn clamped(self, map: &TileMap) -> Option<Self> {
+        if self.min.x >= map.width || self.min.y >= map.height {
+            return None;
+        }
+        Some(TileRect {
+            min: self.min,
+            max: self.max.min(UVec2::new(map.width - 1, map.height - 1)),
+        })
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = (u32, u32)> {
+        (self.min.y..=self.max.y).flat_map(move |y| (self.min.x..=self.max.x).map(move |x| (x, y)))
+    }
+}
+
+/// Chunks along each axis of `map`, the last ones cut short by its edges.
+pub fn chunk_grid(map: &TileMap) -> UVec2 {
+    UVec2::new(
+        map.width.div_ceil(TERRAIN_CHUNK_SIZE),
+        map.height.div_ceil(TERRAIN_CHUNK_SIZE),
+    )
+}
+
+/// First and last tile of chunk `chunk`, both inclusive.
+pub fn chunk_tiles(map: &TileMap, chunk: UVec2) -> (UVec2, UVec2) {
+    let min = chunk * TERRAIN_CHUNK_SIZE;
+    let max = (min + UVec2::splat(TERRAIN_CHUNK_SIZE - 1))
+        .min(UVec2::new(map.width, map.height).saturating_sub(UVec2::ONE));
+    (min, max)
+}
+
+/// Chunks whose meshes change when `tiles` do. A tile's corners follow its
+/// neighbours, and a tile's walls and smoothed normals follow its
+/// neighbours' corners, so the chunks within two tiles are included.
+pub fn chunks_touching(
+    map: &TileMap,
+    tiles: impl IntoIterator<Item = (u32, u32)>,
+) -> HashSet<UVec2> {
+    let grid = chunk_grid(map);
+    let mut chunks = HashSet::new();
+    for (x, y) in tiles {
+        let min = UVec2::new(x, y).saturating_sub(UVec2::splat(CHUNK_REACH)) / TERRAIN_CHUNK_SIZE;
+        let max = ((UVec2::new(x, y) + UVec2::splat(CHUNK_REACH)) / TERRAIN_CHUNK_SIZE)
+            .min(grid.saturating_sub(UVec2::ONE));
+        for cy in min.y..=max.y {
+            for cx in min.x..=max.x {
+                chunks.insert(UVec2::new(cx, cy));
+            }
+        }
+    }
+    chunks
+}
+
+/// Meshes `chunks` of `map` like `build_combined_mesh`, each holding only
+/// its own tiles. Tiles just outside a chunk still take part in smoothing,
+/// so normals match across chunk seams.
+/// Buffers are taken from `spares` while it lasts, and the temporary ones
+/// used for smoothing are put back.
+pub fn build_chunk_meshes(
+    map: &TileMap,
+    heights: &CornerHeightCache,
+    smoothing: NormalSmoothing,
+    chunks: impl IntoIterator<Item = UVec2>,
+    spares: &mut Vec<MeshStorage>,
+) -> Vec<(UVec2, Mesh)> {
+    let layers = UpperLayers::new(map);
+    chunks
+        .into_iter()
+        .map(|chunk| {
+            let (min, max) = chunk_tiles(map, chunk);
+            let area = TileArea {
+                min,
+                max,
+                skip: None,
+            };
+            let mut buffer = MeshBuffers::reusing(spares.pop().unwrap_or_default());
+            populate_mesh_buffers(map, heights, &layers, area, None, Some(&mut buffer));
+            if let Some(min_cos) = smoothing.min_cos() {
+                let apron = TileArea {
+                    min: min.saturating_sub(UVec2::ONE),
+                    max: (max + UVec2::ONE)
+                        .min(UVec2::new(map.width, map.height).saturating_sub(UVec2::ONE)),
+                    skip: Some((min, max)),
+                };
+                let mut neighbours = MeshBuffers::reusing(spares.pop().unwrap_or_default());
+                populate_mesh_buffers(map, heights, &layers, apron, None, Some(&mut neighbours));
+                smooth_top_normals(vec![&mut buffer, &mut neighbours], min_cos);
+                spares.push(neighbours.into_storage());
+            }
+            (chunk, buffer.into_mesh())
+        })
+        .collect()
+}
+
+/// Simplified meshes of `chunks` for drawing far away, one per
+/// `TERRAIN_LOD_STEPS` entry. The ground becomes a height field sampled
+/// every few tiles, so cliffs turn into steep slopes; upper layers keep
+/// their full detail. Points along a chunk's border take the highest corner
+/// near them and a skirt hangs from every border, so no gap opens against
+/// a neighbour drawn at another level. Buffers are taken from `spares`
+/// while it lasts.
+pub fn build_chunk_lods(
+    map: &TileMap,
+    heights: &CornerHeightCache,
+    smoothing: NormalSmoothing,
+    chunks: impl IntoIterator<Item = UVec2>,
+    spares: &mut Vec<MeshStorage>,
+) -> Vec<(UVec2, Vec<Mesh>)> {
+    if map.width == 0 || map.height == 0 {
+        return Vec::new();
+    }
+    let layers = UpperLayers::new(map);
+    let grid = corner_grid(map, heights);
+    chunks
+        .into_iter()
+        .map(|chunk| {
+            let (min, max) = chunk_tiles(map, chunk);
+            let lods = TERRAIN_LOD_STEPS
+                .iter()
+                .map(|&step| {
+                    let mut buffer = MeshBuffers::reusing(spares.pop().unwrap_or_default());
+                    append_lod_ground(map, &grid, min, max, step, &mut buffer);
+                    let area = TileArea {
+                        min,
+                        max,
+                        skip: None,
+                    };
+                    append_upper_layers(&layers, area, &mut buffer);
+                    if let Some(min_cos) = smoothing.min_cos() {
+                        smooth_top_normals(vec![&mut buffer], min_cos);
+                    }
+                    buffer.into_mesh()
+                })
+                .collect();
+            (chunk, lods)
+        })
+        .collect()
+}
+
+/// The ground of tiles `min` to `max` as one quad per `step` by `step`
+/// cell of the corner grid, skipping cells of holes, with skirts down to
+/// the pit floor along the edges.
+fn append_lod_ground(
+    map: &TileMap,
+    grid: &[f32],
+    min: UVec2,
+    max: UVec2,
+    step: u32,
+    buffer: &mut MeshBuffers,
+) {
+    let columns = map.width + 1;
+    // Corner coordinates of the area, the last ones cut short by its edges.
+    let end = max + UVec2::ONE;
+    let axis = |from: u32, to: u32| {
+        let mut points: Vec<u32> = (from..to).step_by(step as usize).collect();
+        points.push(to);
+        points
+    };
+    let xs = axis(min.x, end.x);
+    let ys = axis(min.y, end.y);
+    let height = |cx: u32, cy: u32| {
+        let mut height = grid[(cy * columns + cx) as usize];
+        if cx == min.x || cx == end.x {
+            for y in cy.saturating_sub(step).max(min.y)..=(cy + step).min(end.y) {
+                height = height.max(grid[(y * columns + cx) as usize]);
+            }
+        }
+        if cy == min.y || cy == end.y {
+            for x in cx.saturating_sub(step).max(min.x)..=(cx + step).min(end.x) {
+                height = height.max(grid[(cy * columns + x) as usize]);
+            }
+        }
+        height
+    };
+    let point = |cx: u32, cy: u32| {
+        Vec3::new(
+            cx as f32 * map.tile_size,
+            height(cx, cy),
+            cy as f32 * map.tile_size,
+        )
+    };
+    let pit = hole_floor(map);
+    let drop = |top: Vec3| Vec3::new(top.x, pit, top.z);
+
+    for (row, pair) in ys.windows(2).enumerate() {
+        let (y0, y1) = (pair[0], pair[1]);
+        for (column, pair) in xs.windows(2).enumerate() {
+            let (x0, x1) = (pair[0], pair[1]);
+            let solid = (y0..y1)
+                .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+                .find(|&(x, y)| map.get(x, y).kind != TileKind::Empty);
+            let Some((x, y)) = solid else {
+                continue;
+            };
+            let tile_layer = Some(map.get(x, y).tile_type.as_index() as f32);
+            let nw = point(x0, y0);
+            let ne = point(x1, y0);
+            let sw = point(x0, y1);
+            let se = point(x1, y1);
+            let top_height = nw.y.max(ne.y).max(sw.y).max(se.y);
+            buffer.push_quad(
+                [nw, sw, se, ne],
+                [[0.0, 0.0]; 4],
+                tile_layer,
+                top_height,
+                None,
+            );
+            let last_row = row + 2 == ys.len();
+            let last_column = column + 2 == xs.len();
+            for (on_edge, a, b, direction) in [
+                (row == 0, nw, ne, RampDirection::North),
+                (last_column, ne, se, RampDirection::East),
+                (last_row, se, sw, RampDirection::South),
+                (column == 0, sw, nw, RampDirection::West),
+            ] {
+                if on_edge {
+                    buffer.add_side_face(
+                        a,
+                        b,
+                        drop(a),
+                        drop(b),
+                        direction,
+                        tile_layer,
+                        a.y.max(b.y),
+                        None,
+                        false,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The tiles from `min` to `max`, both inclusive, except those from
+/// `skip.0` to `skip.1`.
+#[derive(Clone, Copy)]
+struct TileArea {
+    min: UVec2,
+    max: UVec2,
+    skip: Option<(UVec2, UVec2)>,
+}
+
+impl TileArea {
+    fn whole(map: &TileMap) -> Self {
+        Self {
+            min: UVec2::ZERO,
+            max: UVec2::new(map.width, map.height).saturating_sub(UVec2::ONE),
+            skip: None,
+        }
+    }
+
+    fn iter(self) -> impl Iterator<Item = (u32, u32)> {
+        (self.min.y..=self.max.y)
+            .flat_map(move |y| (self.min.x..=self.max.x).map(move |x| (x, y)))
+            .filter(move |&(x, y)| {
+                self.skip
+                    .is_none_or(|(min, max)| x < min.x || y < min.y || x > max.x || y > max.y)
+            })
+    }
+}
+
+/// Each upper layer as a map of its own with its corner heights, worked out
+/// once per rebuild however many chunks it is split into.
+struct UpperLayers(Vec<(TileMap, CornerHeightCache)>);
+
+impl UpperLayers {
+    fn new(map: &TileMap) -> Self {
+        Self(
+            (1..map.layer_count())
+                .filter_map(|layer| map.layer_map(layer))
+                .map(|layer_map| {
+                    let heights = CornerHeightCache::from_map(&layer_map);
+                    (layer_map, heights)
+                })
+                .collect(),
+        )
+    }
+}
+
 fn populate_mesh_buffers(
     map: &TileMap,
+    heights: &CornerHeightCache,
+    layers: &UpperLayers,
+    area: TileArea,
     mut per_type: Option<&mut HashMap<TileType, MeshBuffers>>,
     mut combined: Option<&mut MeshBuffers>,
 ) {
@@ -92,29 +685,173 @@ fn populate_mesh_buffers(
         return;
     }
 
-    let mut corner_cache = vec![[0.0f32; 4]; (map.width * map.height) as usize];
-    for y in 0..map.height {
-        for x in 0..map.width {
-            let idx = map.idx(x, y);
-            corner_cache[idx] = tile_corner_heights(map, x, y);
+    if heights.width != map.width || heights.height != map.height {
+        warn!("Corner height cache does not match the map size; skipping mesh rebuild");
+        return;
+    }
+    let corner_cache = heights.corners.as_slice();
+
+    for (x, y) in area.iter() {
+        if map.get(x, y).kind == TileKind::Empty {
+            continue;
+        }
+
+        if let Some(buffers) = per_type.as_mut() {
+            let tile_type = map.get(x, y).tile_type;
+            let buffer = buffers.entry(tile_type).or_default();
+            append_tile_geometry(map, corner_cache, x, y, buffer, None);
+        }
+
+        if let Some(combined_buffer) = combined.as_mut() {
+            let tile_layer = map.get(x, y).tile_type.as_index() as f32;
+            append_tile_geometry(map, corner_cache, x, y, combined_buffer, Some(tile_layer));
         }
     }
 
-    for y in 0..map.height {
-        for x in 0..map.width {
-            if let Some(buffers) = per_type.as_mut() {
-                let tile_type = map.get(x, y).tile_type;
-                let buffer = buffers.entry(tile_type).or_default();
-                append_tile_geometry(map, &corner_cache, x, y, buffer, None);
+    if let Some(buffers) = per_type {
+        for (layer_map, layer_heights) in &layers.0 {
+            let corner_cache = layer_heights.corners.as_slice();
+            for (x, y) in area.iter() {
+                let tile = layer_map.get(x, y);
+                if tile.kind == TileKind::Empty {
+                    continue;
+                }
+                let buffer = buffers.entry(tile.tile_type).or_default();
+                append_layer_tile_geometry(layer_map, corner_cache, x, y, buffer, None);
+            }
+        }
+    }
+    if let Some(combined_buffer) = combined {
+        append_upper_layers(layers, area, combined_buffer);
+    }
+}
+
+/// Every upper-layer tile of `area`, tagged with its texture layer.
+fn append_upper_layers(layers: &UpperLayers, area: TileArea, buffer: &mut MeshBuffers) {
+    for (layer_map, layer_heights) in &layers.0 {
+        let corner_cache = layer_heights.corners.as_slice();
+        for (x, y) in area.iter() {
+            let tile = layer_map.get(x, y);
+            if tile.kind == TileKind::Empty {
+                continue;
             }
+            let tile_layer = tile.tile_type.as_index() as f32;
+            append_layer_tile_geometry(layer_map, corner_cache, x, y, buffer, Some(tile_layer));
+        }
+    }
+}
 
-            if let Some(combined_buffer) = combined.as_mut() {
-                let tile_layer = map.get(x, y).tile_type.as_index() as f32;
+/// A tile on an upper layer, drawn as a slab `LAYER_THICKNESS` levels deep:
+/// its top, its underside and walls wherever the layer has no tile to meet.
+/// Its vertices are flagged so the shader textures them from the tile
+/// rather than from the ground's splat map beneath.
+fn append_layer_tile_geometry(
+    layer: &TileMap,
+    corner_cache: &[[f32; 4]],
+    x: u32,
+    y: u32,
+    buffer: &mut MeshBuffers,
+    tile_layer: Option<f32>,
+) {
+    let first_vertex = buffer.positions.len();
+    let corners = corner_cache[layer.idx(x, y)];
+    let depth = LAYER_THICKNESS as f32 * layer.tile_height();
+    let corner_position = |corner: usize| {
+        let (dx, dz) = match corner {
+            CORNER_NW => (0, 0),
+            CORNER_NE => (1, 0),
+            CORNER_SW => (0, 1),
+            _ => (1, 1),
+        };
+        Vec3::new(
+            (x + dx) as f32 * layer.tile_size,
+            corners[corner],
+            (y + dz) as f32 * layer.tile_size,
+        )
+    };
+    let top = [CORNER_NW, CORNER_NE, CORNER_SW, CORNER_SE].map(corner_position);
+    let bottom = top.map(|corner| corner - Vec3::Y * depth);
+    let top_height = max_corner_height(corners);
 
-                // dbg!(map.get(x, y).tile_type);
+    buffer.push_quad(
+        [
+            top[CORNER_NW],
+            top[CORNER_SW],
+            top[CORNER_SE],
+            top[CORNER_NE],
+        ],
+        [[0.0, 0.0]; 4],
+        tile_layer,
+        top_height,
+        None,
+    );
+    // The underside faces down, so it winds the other way round.
+    buffer.push_quad(
+        [
+            bottom[CORNER_NW],
+            bottom[CORNER_NE],
+            bottom[CORNER_SE],
+            bottom[CORNER_SW],
+        ],
+        [[0.0, 0.0]; 4],
+        tile_layer,
+        top_height,
+        None,
+    );
 
-                append_tile_geometry(map, &corner_cache, x, y, combined_buffer, Some(tile_layer));
-            }
+    // Each side's two top corners, from left to right seen from outside,
+    // and the corners of the neighbour they meet.
+    for (direction, (a, b), (facing_a, facing_b)) in [
+        (
+            RampDirection::North,
+            (CORNER_NW, CORNER_NE),
+            (CORNER_SW, CORNER_SE),
+        ),
+        (
+            RampDirection::South,
+            (CORNER_SE, CORNER_SW),
+            (CORNER_NE, CORNER_NW),
+        ),
+        (
+            RampDirection::West,
+            (CORNER_SW, CORNER_NW),
+            (CORNER_SE, CORNER_NE),
+        ),
+        (
+            RampDirection::East,
+            (CORNER_NE, CORNER_SE),
+            (CORNER_NW, CORNER_SW),
+        ),
+    ] {
+        let (dx, dy) = direction.offset();
+        let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+        let inside = nx >= 0 && ny >= 0 && (nx as u32) < layer.width && (ny as u32) < layer.height;
+        let (bottom_a, bottom_b) = if inside && is_solid(layer, nx as u32, ny as u32) {
+            // Walls only cover the part of the slab its neighbour leaves open.
+            let neighbor = corner_cache[layer.idx(nx as u32, ny as u32)];
+            (
+                top[a].with_y(neighbor[facing_a].clamp(bottom[a].y, top[a].y)),
+                top[b].with_y(neighbor[facing_b].clamp(bottom[b].y, top[b].y)),
+            )
+        } else {
+            (bottom[a], bottom[b])
+        };
+        buffer.add_side_face(
+            top[a],
+            top[b],
+            bottom_a,
+            bottom_b,
+            direction,
+            tile_layer,
+            top[a].y.max(top[b].y),
+            None,
+            false,
+        );
+    }
+
+    if let Some(colors) = buffer.colors.as_mut() {
+        for color in &mut colors[first_vertex..] {
+            color[3] = 1.0;
         }
     }
 }
@@ -130,10 +867,10 @@ fn append_tile_geometry(
     let idx = map.idx(x, y);
     let corners = corner_cache[idx];
     let tile_kind = map.get(x, y).kind;
-    let x0 = x as f32 * TILE_SIZE;
-    let x1 = x0 + TILE_SIZE;
-    let z0 = y as f32 * TILE_SIZE;
-    let z1 = z0 + TILE_SIZE;
+    let x0 = x as f32 * map.tile_size;
+    let x1 = x0 + map.tile_size;
+    let z0 = y as f32 * map.tile_size;
+    let z1 = z0 + map.tile_size;
 
     let nw = Vec3::new(x0, corners[CORNER_NW], z0);
     let ne = Vec3::new(x1, corners[CORNER_NE], z0);
@@ -141,6 +878,7 @@ fn append_tile_geometry(
     let se = Vec3::new(x1, corners[CORNER_SE], z1);
 
     let top_height = max_corner_height(corners);
+    let pit = hole_floor(map);
 
     let top_color_info = if tile_layer.is_some() {
         Some(tile_top_blend_mask(map, corner_cache, x, y, top_height))
@@ -156,7 +894,7 @@ fn append_tile_geometry(
         top_color_info,
     );
 
-    let (bnw, bne, north_neighbor_kind, north_bottom_layer) = if y > 0 {
+    let (bnw, bne, north_neighbor_kind, north_bottom_layer) = if y > 0 && is_solid(map, x, y - 1) {
         let neighbor_idx = map.idx(x, y - 1);
         let neighbor = corner_cache[neighbor_idx];
         let neighbor_tile = map.get(x, y - 1);
@@ -167,7 +905,7 @@ fn append_tile_geometry(
             Some(neighbor_tile.tile_type.as_index() as f32),
         )
     } else {
-        (0.0, 0.0, None, None)
+        open_side(y > 0, pit)
     };
     let north_bottom_a_y = bnw.min(nw.y);
     let north_bottom_b_y = bne.min(ne.y);
@@ -197,19 +935,20 @@ fn append_tile_geometry(
         north_force_cliff,
     );
 
-    let (bsw, bse, south_neighbor_kind, south_bottom_layer) = if y + 1 < map.height {
-        let neighbor_idx = map.idx(x, y + 1);
-        let neighbor = corner_cache[neighbor_idx];
-        let neighbor_tile = map.get(x, y + 1);
-        (
-            neighbor[CORNER_NW],
-            neighbor[CORNER_NE],
-            Some(neighbor_tile.kind),
-            Some(neighbor_tile.tile_type.as_index() as f32),
-        )
-    } else {
-        (0.0, 0.0, None, None)
-    };
+    let (bsw, bse, south_neighbor_kind, south_bottom_layer) =
+        if y + 1 < map.height && is_solid(map, x, y + 1) {
+            let neighbor_idx = map.idx(x, y + 1);
+            let neighbor = corner_cache[neighbor_idx];
+            let neighbor_tile = map.get(x, y + 1);
+            (
+                neighbor[CORNER_NW],
+                neighbor[CORNER_NE],
+                Some(neighbor_tile.kind),
+                Some(neighbor_tile.tile_type.as_index() as f32),
+            )
+        } else {
+            open_side(y + 1 < map.height, pit)
+        };
     let south_bottom_a_y = bse.min(se.y);
     let south_bottom_b_y = bsw.min(sw.y);
     let south_bottom_a = Vec3::new(x1, south_bottom_a_y, z1);
@@ -238,7 +977,7 @@ fn append_tile_geometry(
         south_force_cliff,
     );
 
-    let (bnw, bsw, west_neighbor_kind, west_bottom_layer) = if x > 0 {
+    let (bnw, bsw, west_neighbor_kind, west_bottom_layer) = if x > 0 && is_solid(map, x - 1, y) {
         let neighbor_idx = map.idx(x - 1, y);
         let neighbor = corner_cache[neighbor_idx];
         let neighbor_tile = map.get(x - 1, y);
@@ -249,7 +988,7 @@ fn append_tile_geometry(
             Some(neighbor_tile.tile_type.as_index() as f32),
         )
     } else {
-        (0.0, 0.0, None, None)
+        open_side(x > 0, pit)
     };
     let west_bottom_a_y = bsw.min(sw.y);
     let west_bottom_b_y = bnw.min(nw.y);
@@ -279,19 +1018,20 @@ fn append_tile_geometry(
         west_force_cliff,
     );
 
-    let (bne, bse, east_neighbor_kind, east_bottom_layer) = if x + 1 < map.width {
-        let neighbor_idx = map.idx(x + 1, y);
-        let neighbor = corner_cache[neighbor_idx];
-        let neighbor_tile = map.get(x + 1, y);
-        (
-            neighbor[CORNER_NW],
-            neighbor[CORNER_SW],
-            Some(neighbor_tile.kind),
-            Some(neighbor_tile.tile_type.as_index() as f32),
-        )
-    } else {
-        (0.0, 0.0, None, None)
-    };
+    let (bne, bse, east_neighbor_kind, east_bottom_layer) =
+        if x + 1 < map.width && is_solid(map, x + 1, y) {
+            let neighbor_idx = map.idx(x + 1, y);
+            let neighbor = corner_cache[neighbor_idx];
+            let neighbor_tile = map.get(x + 1, y);
+            (
+                neighbor[CORNER_NW],
+                neighbor[CORNER_SW],
+                Some(neighbor_tile.kind),
+                Some(neighbor_tile.tile_type.as_index() as f32),
+            )
+        } else {
+            open_side(x + 1 < map.width, pit)
+        };
     let east_bottom_a_y = bne.min(ne.y);
     let east_bottom_b_y = bse.min(se.y);
     let east_bottom_a = Vec3::new(x1, east_bottom_a_y, z0);
@@ -354,10 +1094,61 @@ fn ramp_neighbor_height(
         return None;
     }
     let neighbor = map.get(ux, uy);
-    let height = neighbor.elevation as f32 * TILE_HEIGHT;
+    if neighbor.kind == TileKind::Empty {
+        return None;
+    }
+    let height = neighbor.elevation as f32 * map.tile_height();
     if height < base { Some(height) } else { None }
 }
 
+/// Vertex and index vectors taken back from a mesh that is being replaced,
+/// so the next build fills them instead of allocating new ones.
+#[derive(Default)]
+pub struct MeshStorage {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    tile_layers: Vec<[f32; 2]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl MeshStorage {
+    /// Moves `mesh`'s attributes and indices out, leaving it empty.
+    pub fn reclaim(mesh: &mut Mesh) -> Self {
+        let mut storage = Self::default();
+        if let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.remove_attribute(Mesh::ATTRIBUTE_POSITION)
+        {
+            storage.positions = positions;
+        }
+        if let Some(VertexAttributeValues::Float32x3(normals)) =
+            mesh.remove_attribute(Mesh::ATTRIBUTE_NORMAL)
+        {
+            storage.normals = normals;
+        }
+        if let Some(VertexAttributeValues::Float32x2(uvs)) =
+            mesh.remove_attribute(Mesh::ATTRIBUTE_UV_0)
+        {
+            storage.uvs = uvs;
+        }
+        if let Some(VertexAttributeValues::Float32x2(layers)) =
+            mesh.remove_attribute(Mesh::ATTRIBUTE_UV_1)
+        {
+            storage.tile_layers = layers;
+        }
+        if let Some(VertexAttributeValues::Float32x4(colors)) =
+            mesh.remove_attribute(Mesh::ATTRIBUTE_COLOR)
+        {
+            storage.colors = colors;
+        }
+        if let Some(Indices::U32(indices)) = mesh.remove_indices() {
+            storage.indices = indices;
+        }
+        storage
+    }
+}
+
 #[derive(Default)]
 struct MeshBuffers {
     positions: Vec<[f32; 3]>,
@@ -367,17 +1158,53 @@ struct MeshBuffers {
     colors: Option<Vec<[f32; 4]>>,
     indices: Vec<u32>,
     next_index: u32,
+    /// First vertex of each top-surface quad; the quad's six vertices follow.
+    top_quads: Vec<u32>,
 }
 
 impl MeshBuffers {
     fn with_tile_types() -> Self {
+        Self::reusing(MeshStorage::default())
+    }
+
+    /// Buffers like `with_tile_types` filling `storage`'s emptied vectors.
+    fn reusing(mut storage: MeshStorage) -> Self {
+        storage.positions.clear();
+        storage.normals.clear();
+        storage.uvs.clear();
+        storage.tile_layers.clear();
+        storage.colors.clear();
+        storage.indices.clear();
         Self {
-            tile_layers: Some(Vec::new()),
-            colors: Some(Vec::new()),
-            ..Default::default()
+            positions: storage.positions,
+            normals: storage.normals,
+            uvs: storage.uvs,
+            tile_layers: Some(storage.tile_layers),
+            colors: Some(storage.colors),
+            indices: storage.indices,
+            next_index: 0,
+            top_quads: Vec::new(),
+        }
+    }
+
+    fn into_storage(self) -> MeshStorage {
+        MeshStorage {
+            positions: self.positions,
+            normals: self.normals,
+            uvs: self.uvs,
+            tile_layers: self.tile_layers.unwrap_or_default(),
+            colors: self.colors.unwrap_or_default(),
+            indices: self.indices,
         }
     }
 
+    /// Indices of every vertex belonging to a top-surface quad.
+    fn top_vertices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.top_quads
+            .iter()
+            .flat_map(|&start| start as usize..start as usize + 6)
+    }
+
     fn push_quad(
         &mut self,
         verts: [Vec3; 4],
@@ -386,6 +1213,7 @@ impl MeshBuffers {
         seam_height: f32,
         bottom_layer: Option<[f32; 4]>,
     ) {
+        self.top_quads.push(self.next_index);
         push_quad(
             &mut self.positions,
             &mut self.normals,
@@ -455,6 +1283,43 @@ impl MeshBuffers {
     }
 }
 
+/// Replaces the flat normals of top-surface vertices with the average of the
+/// face normals meeting at the same point, across all `buffers`, skipping
+/// faces whose normals differ from the vertex's own by more than
+/// `acos(min_cos)`.
+fn smooth_top_normals(mut buffers: Vec<&mut MeshBuffers>, min_cos: f32) {
+    // Corners are shared exactly by neighbouring tiles, so a fine grid is
+    // enough to match them up despite float noise.
+    let key = |position: [f32; 3]| position.map(|value| (value * 1024.0).round() as i32);
+
+    let mut faces: HashMap<[i32; 3], Vec<Vec3>> = HashMap::new();
+    for buffer in &buffers {
+        for vertex in buffer.top_vertices() {
+            faces
+                .entry(key(buffer.positions[vertex]))
+                .or_default()
+                .push(Vec3::from(buffer.normals[vertex]));
+        }
+    }
+
+    for buffer in &mut buffers {
+        let smoothed: Vec<(usize, Vec3)> = buffer
+            .top_vertices()
+            .map(|vertex| {
+                let own = Vec3::from(buffer.normals[vertex]);
+                let sum: Vec3 = faces[&key(buffer.positions[vertex])]
+                    .iter()
+                    .filter(|normal| own.dot(**normal) >= min_cos)
+                    .sum();
+                (vertex, sum.normalize_or(own))
+            })
+            .collect();
+        for (vertex, normal) in smoothed {
+            buffer.normals[vertex] = normal.to_array();
+        }
+    }
+}
+
 fn push_quad(
     positions: &mut Vec<[f32; 3]>,
     normals: &mut Vec<[f32; 3]>,
@@ -529,28 +1394,28 @@ fn tile_top_blend_mask(
 
     let mut mask_bits: u32 = 0;
 
-    if y > 0 {
+    if y > 0 && is_solid(map, x, y - 1) {
         let neighbor = corner_cache[map.idx(x, y - 1)];
         if (top_height - max_corner_height(neighbor)).abs() < HEIGHT_EPSILON {
             mask_bits |= 0b0001;
         }
     }
 
-    if y + 1 < map.height {
+    if y + 1 < map.height && is_solid(map, x, y + 1) {
         let neighbor = corner_cache[map.idx(x, y + 1)];
         if (top_height - max_corner_height(neighbor)).abs() < HEIGHT_EPSILON {
             mask_bits |= 0b0010;
         }
     }
 
-    if x > 0 {
+    if x > 0 && is_solid(map, x - 1, y) {
         let neighbor = corner_cache[map.idx(x - 1, y)];
         if (top_height - max_corner_height(neighbor)).abs() < HEIGHT_EPSILON {
             mask_bits |= 0b0100;
         }
     }
 
-    if x + 1 < map.width {
+    if x + 1 < map.width && is_solid(map, x + 1, y) {
         let neighbor = corner_cache[map.idx(x + 1, y)];
         if (top_height - max_corner_height(neighbor)).abs() < HEIGHT_EPSILON {
             mask_bits |= 0b1000;
@@ -560,6 +1425,51 @@ fn tile_top_blend_mask(
     [-2.0, mask_bits as f32, 0.0, 0.0]
 }
 
+/// Height the walls around a hole reach down to, so pits and chasms show
+/// their sides.
+pub fn hole_floor(map: &TileMap) -> f32 {
+    (MIN_ELEVATION - HOLE_DEPTH) as f32 * map.tile_height()
+}
+
+/// Row-major heights of the `(width + 1) x (height + 1)` tile corner grid,
+/// row 0 along the north edge. Where tiles of different heights meet, a
+/// corner takes the highest; corners touching only holes sit at
+/// `hole_floor`.
+pub fn corner_grid(map: &TileMap, heights: &CornerHeightCache) -> Vec<f32> {
+    let columns = map.width + 1;
+    let mut grid = vec![hole_floor(map); (columns * (map.height + 1)) as usize];
+    for y in 0..map.height {
+        for x in 0..map.width {
+            let Some(corners) = heights.corners(x, y) else {
+                continue;
+            };
+            for (corner, (cx, cy)) in [
+                (CORNER_NW, (x, y)),
+                (CORNER_NE, (x + 1, y)),
+                (CORNER_SW, (x, y + 1)),
+                (CORNER_SE, (x + 1, y + 1)),
+            ] {
+                let height = &mut grid[(cy * columns + cx) as usize];
+                *height = height.max(corners[corner]);
+            }
+        }
+    }
+    grid
+}
+
+/// Wall bottom heights and neighbour info for a side with no solid
+/// neighbour: a hole inside the map drops to the pit floor, the map edge to
+/// the ground plane.
+fn open_side(inside_map: bool, pit: f32) -> (f32, f32, Option<TileKind>, Option<f32>) {
+    let floor = if inside_map { pit } else { 0.0 };
+    (floor, floor, None, None)
+}
+
+/// Holes have no geometry, so neighbouring tiles treat them like the map edge.
+fn is_solid(map: &TileMap, x: u32, y: u32) -> bool {
+    map.get(x, y).kind != TileKind::Empty
+}
+
 fn max_corner_height(corners: [f32; 4]) -> f32 {
     corners
         .into_iter()
@@ -594,6 +1504,11 @@ fn add_side_face(
         RampDirection::South => ([top_a, top_b, bottom_b, bottom_a], [[0.0, 0.0]; 4]),
         RampDirection::West => ([top_a, top_b, bottom_b, bottom_a], [[0.0, 0.0]; 4]),
         RampDirection::East => ([top_a, top_b, bottom_b, bottom_a], [[0.0, 0.0]; 4]),
+        // Side faces only ever face an edge neighbour.
+        RampDirection::NorthEast
+        | RampDirection::SouthEast
+        | RampDirection::SouthWest
+        | RampDirection::NorthWest => ([top_a, top_b, bottom_b, bottom_a], [[0.0, 0.0]; 4]),
     };
 
     let mut color_info = bottom_info;
@@ -640,11 +1555,24 @@ fn should_force_cliff_face(
 pub mod splatmap {
     use super::*;
     use bevy::render::render_asset::RenderAssetUsages;
-    use bevy::render::render_resource::Extent3d;
+    use bevy::render::render_resource::{Extent3d, TextureViewDescriptor, TextureViewDimension};
     use bevy::render::texture::{ImageAddressMode, ImageFilterMode, ImageSamplerDescriptor};
 
     const CHANNELS: usize = 4;
+    /// Layers weighted by one page of the splatmap, one per RGBA channel.
+    pub const LAYERS_PER_PAGE: usize = CHANNELS;
+    /// Most layers the terrain shader blends, `MAX_TERRAIN_LAYERS` in
+    /// `terrain_pbr_extension.wgsl`.
+    pub const MAX_LAYERS: usize = 16;
+    const _: () = assert!(TileType::ALL.len() <= MAX_LAYERS);
+
+    /// Pages needed to weight `layer_count` layers; layer `n` lives in channel
+    /// `n % LAYERS_PER_PAGE` of page `n / LAYERS_PER_PAGE`.
+    pub fn page_count(layer_count: usize) -> u32 {
+        layer_count.div_ceil(LAYERS_PER_PAGE).max(1) as u32
+    }
 
+    /// The splatmap as an RGBA texture array with one page per four layers.
     pub fn create(map: &TileMap) -> Image {
         let extent = extent_from_map(map);
         let mut image = Image::new_fill(
@@ -670,8 +1598,7 @@ pub mod splatmap {
 
         configure_image(image);
 
-        let width = extent.width as usize;
-        let required_len = width * (extent.height as usize) * CHANNELS;
+        let required_len = page_len(extent) * extent.depth_or_array_layers as usize;
         if image.data.len() != required_len {
             image.data.resize(required_len, 0);
         }
@@ -681,32 +1608,73 @@ pub mod splatmap {
             return;
         }
 
-        for y in 0..map.height as usize {
-            for x in 0..map.width as usize {
-                let mut pixel = [0u8; CHANNELS];
-                let tile = map.get(x as u32, y as u32);
-                let layer = tile.tile_type.as_index();
-                if layer < CHANNELS {
-                    pixel[layer] = 255;
-                }
-
-                let idx = (y * width + x) * CHANNELS;
-                image.data[idx..idx + CHANNELS].copy_from_slice(&pixel);
+        for y in 0..map.height {
+            for x in 0..map.width {
+                write_texel(map, &mut image.data, page_len(extent), x, y);
             }
         }
     }
 
+    /// Rewrites only the texels of `region`, leaving the rest of the image
+    /// as it was. Falls back to a full write when the image no longer
+    /// matches the map.
+    pub fn write_region(map: &TileMap, image: &mut Image, region: TileRect) {
+        let extent = extent_from_map(map);
+        let required_len = page_len(extent) * extent.depth_or_array_layers as usize;
+        if image.texture_descriptor.size != extent || image.data.len() != required_len {
+            write(map, image);
+            return;
+        }
+        let Some(region) = region.clamped(map) else {
+            return;
+        };
+        for (x, y) in region.iter() {
+            write_texel(map, &mut image.data, page_len(extent), x, y);
+        }
+    }
+
+    /// Bytes in one page of the splatmap.
+    pub fn page_len(extent: Extent3d) -> usize {
+        (extent.width * extent.height) as usize * CHANNELS
+    }
+
+    /// Sets the tile's texel on every page: full weight in its layer's
+    /// channel, zero everywhere else.
+    fn write_texel(map: &TileMap, data: &mut [u8], page_len: usize, x: u32, y: u32) {
+        let tile = map.get(x, y);
+        let layer = (tile.kind != TileKind::Empty).then(|| tile.tile_type.as_index());
+        let idx = map.idx(x, y) * CHANNELS;
+        for (page, texels) in data.chunks_exact_mut(page_len).enumerate() {
+            texels[idx..idx + CHANNELS].copy_from_slice(&texel(layer, page));
+        }
+    }
+
+    /// The texel of `layer` on `page`; `None` weights no layer at all.
+    fn texel(layer: Option<usize>, page: usize) -> [u8; CHANNELS] {
+        let mut pixel = [0u8; CHANNELS];
+        if let Some(layer) = layer.filter(|layer| layer / LAYERS_PER_PAGE == page) {
+            pixel[layer % LAYERS_PER_PAGE] = 255;
+        }
+        pixel
+    }
+
     fn extent_from_map(map: &TileMap) -> Extent3d {
         Extent3d {
             width: map.width.max(1),
             height: map.height.max(1),
-            depth_or_array_layers: 1,
+            depth_or_array_layers: page_count(TileType::ALL.len()),
         }
     }
 
     fn configure_image(image: &mut Image) {
         image.texture_descriptor.mip_level_count = 1;
         image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST;
+        // A single page still has to be viewed as an array to match the
+        // material's binding.
+        image.texture_view_descriptor = Some(TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::D2Array),
+            ..Default::default()
+        });
         image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
             mag_filter: ImageFilterMode::Linear,
             min_filter: ImageFilterMode::Linear,
@@ -716,4 +1684,284 @@ pub mod splatmap {
             ..Default::default()
         });
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn every_layer_up_to_the_shader_limit_gets_its_own_channel() {
+            // `MAX_SPLAT_PAGES` in `terrain_pbr_extension.wgsl`.
+            assert_eq!(page_count(MAX_LAYERS), 4);
+            let pages = page_count(MAX_LAYERS) as usize;
+            for layer in 0..MAX_LAYERS {
+                let weights: Vec<u8> = (0..pages)
+                    .flat_map(|page| texel(Some(layer), page))
+                    .collect();
+                let mut expected = vec![0u8; pages * LAYERS_PER_PAGE];
+                expected[layer] = 255;
+                assert_eq!(weights, expected, "layer {layer}");
+            }
+            for page in 0..pages {
+                assert_eq!(texel(None, page), [0; CHANNELS]);
+            }
+        }
+    }
+}
+
+/// Per-tile texture variation for the terrain shader: red holds the offset
+/// seed, green the quarter-turn rotation and blue whether it is mirrored.
+pub mod variation_map {
+    use super::*;
+    use bevy::render::render_asset::RenderAssetUsages;
+    use bevy::render::render_resource::Extent3d;
+    use bevy::render::texture::{ImageAddressMode, ImageFilterMode, ImageSamplerDescriptor};
+
+    const CHANNELS: usize = 4;
+
+    pub fn create(map: &TileMap) -> Image {
+        let mut image = Image::new_fill(
+            extent_from_map(map),
+            TextureDimension::D2,
+            &[0u8; CHANNELS],
+            TextureFormat::Rgba8Unorm,
+            RenderAssetUsages::default(),
+        );
+        image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST;
+        // Values are per tile, so they must never be interpolated.
+        image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+            mag_filter: ImageFilterMode::Nearest,
+            min_filter: ImageFilterMode::Nearest,
+            address_mode_u: ImageAddressMode::ClampToEdge,
+            address_mode_v: ImageAddressMode::ClampToEdge,
+            ..Default::default()
+        });
+        write(map, &mut image);
+        image
+    }
+
+    pub fn write(map: &TileMap, image: &mut Image) {
+        if image.texture_descriptor.size != extent_from_map(map) {
+            *image = create(map);
+            return;
+        }
+
+        let width = map.width.max(1) as usize;
+        for y in 0..map.height as usize {
+            for x in 0..map.width as usize {
+                let idx = (y * width + x) * CHANNELS;
+                image.data[idx..idx + CHANNELS].copy_from_slice(&texel(map, x as u32, y as u32));
+            }
+        }
+    }
+
+    /// Rewrites only the texels of `region`, falling back to a full write
+    /// when the image no longer matches the map.
+    pub fn write_region(map: &TileMap, image: &mut Image, region: TileRect) {
+        if image.texture_descriptor.size != extent_from_map(map) {
+            write(map, image);
+            return;
+        }
+        let Some(region) = region.clamped(map) else {
+            return;
+        };
+        for (x, y) in region.iter() {
+            let idx = map.idx(x, y) * CHANNELS;
+            image.data[idx..idx + CHANNELS].copy_from_slice(&texel(map, x, y));
+        }
+    }
+
+    fn texel(map: &TileMap, x: u32, y: u32) -> [u8; CHANNELS] {
+        let tile = map.get(x, y);
+        [
+            tile.variation,
+            tile.rotation % 4,
+            u8::from(tile.mirrored),
+            255,
+        ]
+    }
+
+    fn extent_from_map(map: &TileMap) -> Extent3d {
+        Extent3d {
+            width: map.width.max(1),
+            height: map.height.max(1),
+            depth_or_array_layers: 1,
+        }
+    }
+}
+
+/// Per-tile roads for the terrain shader: red holds the road surface's
+/// `RoadSurface::code` (0 for none), green the index of its piece in
+/// `RoadPiece::ALL` and blue the piece's quarter turns.
+pub mod road_map {
+    use super::*;
+    use crate::roads::{self, RoadPiece};
+    use bevy::render::render_asset::RenderAssetUsages;
+    use bevy::render::render_resource::Extent3d;
+    use bevy::render::texture::{ImageAddressMode, ImageFilterMode, ImageSamplerDescriptor};
+
+    const CHANNELS: usize = 4;
+
+    pub fn create(map: &TileMap) -> Image {
+        let mut image = Image::new_fill(
+            extent_from_map(map),
+            TextureDimension::D2,
+            &[0u8; CHANNELS],
+            TextureFormat::Rgba8Unorm,
+            RenderAssetUsages::default(),
+        );
+        image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST;
+        image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+            mag_filter: ImageFilterMode::Nearest,
+            min_filter: ImageFilterMode::Nearest,
+            address_mode_u: ImageAddressMode::ClampToEdge,
+            address_mode_v: ImageAddressMode::ClampToEdge,
+            ..Default::default()
+        });
+        write(map, &mut image);
+        image
+    }
+
+    /// Rewrites every texel. A road's shape depends on its neighbours and on
+    /// whether they can be walked to, so edits anywhere nearby can change it.
+    pub fn write(map: &TileMap, image: &mut Image) {
+        if image.texture_descriptor.size != extent_from_map(map) {
+            *image = create(map);
+            return;
+        }
+
+        for (idx, shape) in roads::road_shapes(map).into_iter().enumerate() {
+            let texel = match shape {
+                Some(shape) => [
+                    shape.surface.code(),
+                    RoadPiece::ALL
+                        .iter()
+                        .position(|&piece| piece == shape.piece)
+                        .unwrap_or(0) as u8,
+                    shape.quarter_turns,
+                    255,
+                ],
+                None => [0, 0, 0, 255],
+            };
+            image.data[idx * CHANNELS..(idx + 1) * CHANNELS].copy_from_slice(&texel);
+        }
+    }
+
+    fn extent_from_map(map: &TileMap) -> Extent3d {
+        Extent3d {
+            width: map.width.max(1),
+            height: map.height.max(1),
+            depth_or_array_layers: 1,
+        }
+    }
+}
+
+/// Per-tile sight data for a game's fog-of-war. Red holds the height tier
+/// (elevation above `MIN_ELEVATION`, as a raw value), green marks cliff-edge
+/// tiles that block sight from the tiles below them, blue marks ramps, which
+/// join two tiers, and alpha is zero for holes.
+pub mod visibility_mask {
+    use super::*;
+    use crate::types::MIN_ELEVATION;
+    use bevy::render::render_asset::RenderAssetUsages;
+    use bevy::render::render_resource::Extent3d;
+
+    const CHANNELS: usize = 4;
+
+    pub fn create(map: &TileMap) -> Image {
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: map.width.max(1),
+                height: map.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0u8; CHANNELS],
+            TextureFormat::Rgba8Unorm,
+            RenderAssetUsages::default(),
+        );
+
+        let width = map.width.max(1) as usize;
+        for y in 0..map.height {
+            for x in 0..map.width {
+                let idx = (y as usize * width + x as usize) * CHANNELS;
+                image.data[idx..idx + CHANNELS].copy_from_slice(&texel(map, x, y));
+            }
+        }
+        image
+    }
+
+    fn texel(map: &TileMap, x: u32, y: u32) -> [u8; CHANNELS] {
+        let tile = map.get(x, y);
+        if tile.kind == TileKind::Empty {
+            return [0; CHANNELS];
+        }
+        let tier = (tile.elevation - MIN_ELEVATION).max(0) as u8;
+        let ramp = tile.kind == TileKind::Ramp;
+        [
+            tier,
+            if blocks_sight(map, x, y) { 255 } else { 0 },
+            if ramp { 255 } else { 0 },
+            255,
+        ]
+    }
+
+    /// A tile blocks sight when it overlooks a lower neighbour across a cliff
+    /// rather than sloping down to it.
+    fn blocks_sight(map: &TileMap, x: u32, y: u32) -> bool {
+        let tile = map.get(x, y);
+        RampDirection::ALL.into_iter().any(|dir| {
+            let (dx, dy) = dir.offset();
+            let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+            if nx < 0 || ny < 0 || nx >= map.width as i32 || ny >= map.height as i32 {
+                return false;
+            }
+            let (nx, ny) = (nx as u32, ny as u32);
+            let sloped = tile.kind == TileKind::Ramp && tile.ramp_direction == Some(dir);
+            is_solid(map, nx, ny) && map.get(nx, ny).elevation < tile.elevation && !sloped
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walls_around_an_interior_hole_reach_the_pit_floor() {
+        let mut map = TileMap::new(3, 3);
+        for y in 0..3 {
+            for x in 0..3 {
+                let mut tile = map.get(x, y).clone();
+                tile.elevation = 2;
+                if (x, y) == (1, 1) {
+                    tile.kind = TileKind::Empty;
+                }
+                map.set(x, y, tile);
+            }
+        }
+        let heights = CornerHeightCache::from_map(&map);
+        let mesh = build_combined_mesh(&map, &heights, NormalSmoothing::default());
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            panic!("mesh has no positions");
+        };
+
+        // The hole spans one tile inward from every map edge.
+        let (near, far) = (map.tile_size, 2.0 * map.tile_size);
+        let around_hole =
+            |&&[x, _, z]: &&[f32; 3]| (near..=far).contains(&x) && (near..=far).contains(&z);
+        let top = 2.0 * map.tile_height();
+        let bottoms: Vec<f32> = positions
+            .iter()
+            .filter(around_hole)
+            .map(|&[_, y, _]| y)
+            .filter(|&y| y < top)
+            .collect();
+        assert!(!bottoms.is_empty(), "the hole has no walls");
+        for y in bottoms {
+            assert_eq!(y, hole_floor(&map));
+        }
+    }
 }
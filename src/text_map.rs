@@ -0,0 +1,618 @@
+//! A line-oriented text form of maps for version control: one record per
+//! line, in a fixed order, so a changed tile shows up as one changed line in
+//! a diff. Saves with the [`EXTENSION`] extension use it.
+
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::{Context, bail, ensure};
+
+use crate::types::{
+    DecalKind, LightKind, MapDecal, MapLight, MapProp, RampDirection, RoadSurface, StartLocation,
+    Tile, TileKind, TileMap, TileType, TriggerArea, Walkability, Weather, WeatherKind,
+};
+
+pub const EXTENSION: &str = "tmt";
+/// First line of every text map; the number is the text format's version.
+pub const HEADER: &str = "tilemap-text 1";
+
+/// The map as text: the header, its size, tile size, weather and water
+/// level if it has one, then one line per tile in row-major order, sculpted
+/// corners, mirrored textures, painted vegetation, roads and walkability
+/// overrides, the upper layers and their solid tiles, triggers and their
+/// tiles, starts, lights, decals and props.
+pub fn to_text(map: &TileMap) -> String {
+    let mut text = String::new();
+    for line in lines(map) {
+        text.push_str(&line);
+        text.push('\n');
+    }
+    text
+}
+
+fn lines(map: &TileMap) -> Vec<String> {
+    let mut lines = vec![
+        HEADER.to_string(),
+        format!("size {} {}", map.width, map.height),
+        format!("tile-size {}", map.tile_size),
+        weather_line(&map.weather),
+    ];
+    lines.extend(map.water_level.map(|level| format!("water {level}")));
+    lines.extend(map.tiles.iter().map(tile_line));
+    lines.extend(
+        map.tiles
+            .iter()
+            .filter(|tile| tile.corner_offsets != [0; 4])
+            .map(|tile| {
+                let [nw, ne, sw, se] = tile.corner_offsets;
+                format!("corners {} {} {nw} {ne} {sw} {se}", tile.x, tile.y)
+            }),
+    );
+    lines.extend(
+        map.tiles
+            .iter()
+            .filter(|tile| tile.mirrored)
+            .map(|tile| format!("mirrored {} {}", tile.x, tile.y)),
+    );
+    lines.extend(
+        (0..map.height)
+            .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| map.vegetation(x, y) > 0)
+            .map(|(x, y)| format!("vegetation {x} {y} {}", map.vegetation(x, y))),
+    );
+    lines.extend(
+        (0..map.height)
+            .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+            .filter_map(|(x, y)| Some((x, y, map.road(x, y)?)))
+            .map(|(x, y, surface)| format!("road {x} {y} {}", surface.identifier())),
+    );
+    lines.extend(
+        (0..map.height)
+            .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+            .filter_map(|(x, y)| Some((x, y, map.walkability(x, y)?)))
+            .map(|(x, y, walkability)| format!("walk {x} {y} {}", walkability.identifier())),
+    );
+    if !map.upper_layers.is_empty() {
+        lines.push(format!("layers {}", map.upper_layers.len()));
+    }
+    for (index, layer) in map.upper_layers.iter().enumerate() {
+        let solid = layer.iter().filter(|tile| tile.kind != TileKind::Empty);
+        for tile in solid.clone() {
+            lines.push(format!("layer-tile {} {}", index + 1, tile_fields(tile)));
+        }
+        for tile in solid.clone().filter(|tile| tile.corner_offsets != [0; 4]) {
+            let [nw, ne, sw, se] = tile.corner_offsets;
+            lines.push(format!(
+                "layer-corners {} {} {} {nw} {ne} {sw} {se}",
+                index + 1,
+                tile.x,
+                tile.y
+            ));
+        }
+        for tile in solid.filter(|tile| tile.mirrored) {
+            lines.push(format!(
+                "layer-mirrored {} {} {}",
+                index + 1,
+                tile.x,
+                tile.y
+            ));
+        }
+    }
+    for (index, trigger) in map.triggers.iter().enumerate() {
+        let [r, g, b] = trigger.color;
+        lines.push(format!(
+            "trigger {index} {r} {g} {b} {} {}",
+            optional(trigger.player),
+            serde_json::to_string(&trigger.name).expect("strings serialize"),
+        ));
+        lines.extend(
+            trigger
+                .tiles
+                .iter()
+                .map(|(x, y)| format!("trigger-tile {index} {x} {y}")),
+        );
+    }
+    lines.extend(map.starts.iter().map(|start| {
+        format!(
+            "start {} {} {} {} {}",
+            start.player, start.team, start.x, start.y, start.facing
+        )
+    }));
+    lines.extend(map.lights.iter().map(light_line));
+    lines.extend(map.decals.iter().map(|decal| {
+        format!(
+            "decal {} {} {} {} {} {}",
+            decal.kind.identifier(),
+            decal.x,
+            decal.y,
+            decal.size,
+            decal.rotation,
+            decal.opacity
+        )
+    }));
+    lines.extend(map.props.iter().map(|prop| {
+        format!(
+            "prop {} {} {} {} {}",
+            prop.x,
+            prop.y,
+            prop.rotation,
+            prop.scale,
+            serde_json::to_string(&prop.asset).expect("strings serialize"),
+        )
+    }));
+    lines
+}
+
+fn tile_line(tile: &Tile) -> String {
+    format!("tile {}", tile_fields(tile))
+}
+
+fn tile_fields(tile: &Tile) -> String {
+    format!(
+        "{} {} {} {} {} {} {} {} {} {}",
+        tile.x,
+        tile.y,
+        kind_name(tile.kind),
+        tile.tile_type.identifier(),
+        tile.elevation,
+        tile.ramp_direction.map_or("-", direction_name),
+        tile.variation,
+        tile.rotation,
+        u8::from(tile.locked),
+        optional(tile.movement_cost),
+    )
+}
+
+fn weather_line(weather: &Weather) -> String {
+    format!(
+        "weather {} {} {} {}",
+        weather_name(weather.kind),
+        weather.intensity,
+        weather.wind_direction,
+        weather.wind_speed
+    )
+}
+
+fn light_line(light: &MapLight) -> String {
+    let [r, g, b] = light.color;
+    format!(
+        "light {} {} {} {} {r} {g} {b} {} {} {} {} {}",
+        match light.kind {
+            LightKind::Point => "point",
+            LightKind::Spot => "spot",
+        },
+        light.x,
+        light.y,
+        light.height,
+        light.intensity,
+        light.range,
+        light.facing,
+        light.pitch,
+        light.cone,
+    )
+}
+
+fn optional(value: Option<u8>) -> String {
+    value.map_or_else(|| "-".to_string(), |value| value.to_string())
+}
+
+fn kind_name(kind: TileKind) -> &'static str {
+    match kind {
+        TileKind::Floor => "floor",
+        TileKind::Ramp => "ramp",
+        TileKind::Empty => "hole",
+    }
+}
+
+fn direction_name(direction: RampDirection) -> &'static str {
+    match direction {
+        RampDirection::North => "north",
+        RampDirection::East => "east",
+        RampDirection::South => "south",
+        RampDirection::West => "west",
+        RampDirection::NorthEast => "north-east",
+        RampDirection::SouthEast => "south-east",
+        RampDirection::SouthWest => "south-west",
+        RampDirection::NorthWest => "north-west",
+    }
+}
+
+fn weather_name(kind: WeatherKind) -> &'static str {
+    match kind {
+        WeatherKind::Clear => "clear",
+        WeatherKind::Rain => "rain",
+        WeatherKind::Snow => "snow",
+        WeatherKind::Dust => "dust",
+    }
+}
+
+/// Parses a map written by [`to_text`]. Blank lines and lines starting with
+/// `#` are ignored.
+pub fn from_text(text: &str) -> anyhow::Result<TileMap> {
+    let mut records = text
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+    match records.next() {
+        Some((_, HEADER)) => {}
+        Some((_, header)) if header.starts_with("tilemap-text ") => {
+            bail!("unsupported text map version: {header}")
+        }
+        _ => bail!("not a text map"),
+    }
+
+    let mut map: Option<TileMap> = None;
+    let mut triggers: Vec<TriggerArea> = Vec::new();
+    for (number, line) in records {
+        parse_record(line, &mut map, &mut triggers)
+            .with_context(|| format!("line {number}: {line}"))?;
+    }
+    let mut map = map.context("text map has no size line")?;
+    map.triggers = triggers;
+    Ok(map)
+}
+
+fn parse_record(
+    line: &str,
+    map: &mut Option<TileMap>,
+    triggers: &mut Vec<TriggerArea>,
+) -> anyhow::Result<()> {
+    let (record, rest) = line.split_once(' ').unwrap_or((line, ""));
+    if record == "size" {
+        ensure!(map.is_none(), "duplicate size line");
+        let [width, height] = fields(rest)?;
+        let (width, height) = (width.parse()?, height.parse()?);
+        ensure!(width > 0 && height > 0, "map size must not be zero");
+        *map = Some(TileMap::new(width, height));
+        return Ok(());
+    }
+    let map = map.as_mut().context("size line must come first")?;
+
+    match record {
+        "tile-size" => {
+            let [tile_size] = fields(rest)?;
+            let tile_size: f32 = tile_size.parse()?;
+            ensure!(
+                tile_size.is_finite() && tile_size > 0.0,
+                "tile size must be positive"
+            );
+            map.tile_size = tile_size;
+        }
+        "water" => {
+            let [level] = fields(rest)?;
+            let level: f32 = level.parse()?;
+            ensure!(level.is_finite(), "water level must be a number");
+            map.water_level = Some(level);
+        }
+        "weather" => {
+            let [kind, intensity, direction, speed] = fields(rest)?;
+            map.weather = Weather {
+                kind: parse_name(
+                    kind,
+                    &[
+                        WeatherKind::Clear,
+                        WeatherKind::Rain,
+                        WeatherKind::Snow,
+                        WeatherKind::Dust,
+                    ],
+                    weather_name,
+                )?,
+                intensity: intensity.parse()?,
+                wind_direction: direction.parse()?,
+                wind_speed: speed.parse()?,
+            };
+        }
+        "tile" => {
+            let tile = parse_tile(map, rest)?;
+            map.set(tile.x, tile.y, tile);
+        }
+        "layers" => {
+            let [count] = fields(rest)?;
+            let count: usize = count.parse()?;
+            ensure!(map.upper_layers.is_empty(), "duplicate layers line");
+            map.upper_layers = vec![map.empty_layer(); count];
+        }
+        "layer-tile" => {
+            let (layer, rest) = rest.split_once(' ').context("missing field")?;
+            let layer = upper_layer(map, layer)?;
+            let tile = parse_tile(map, rest)?;
+            let idx = map.idx(tile.x, tile.y);
+            map.upper_layers[layer][idx] = tile;
+        }
+        "layer-corners" => {
+            let [layer, x, y, nw, ne, sw, se] = fields(rest)?;
+            let layer = upper_layer(map, layer)?;
+            let (x, y) = position(map, x, y)?;
+            let idx = map.idx(x, y);
+            map.upper_layers[layer][idx].corner_offsets =
+                [nw.parse()?, ne.parse()?, sw.parse()?, se.parse()?];
+        }
+        "layer-mirrored" => {
+            let [layer, x, y] = fields(rest)?;
+            let layer = upper_layer(map, layer)?;
+            let (x, y) = position(map, x, y)?;
+            let idx = map.idx(x, y);
+            map.upper_layers[layer][idx].mirrored = true;
+        }
+        "corners" => {
+            let [x, y, nw, ne, sw, se] = fields(rest)?;
+            let (x, y) = position(map, x, y)?;
+            let mut tile = map.get(x, y).clone();
+            tile.corner_offsets = [nw.parse()?, ne.parse()?, sw.parse()?, se.parse()?];
+            map.set(x, y, tile);
+        }
+        "mirrored" => {
+            let [x, y] = fields(rest)?;
+            let (x, y) = position(map, x, y)?;
+            let mut tile = map.get(x, y).clone();
+            tile.mirrored = true;
+            map.set(x, y, tile);
+        }
+        "vegetation" => {
+            let [x, y, density] = fields(rest)?;
+            let (x, y) = position(map, x, y)?;
+            map.set_vegetation(x, y, density.parse()?);
+        }
+        "road" => {
+            let [x, y, surface] = fields(rest)?;
+            let (x, y) = position(map, x, y)?;
+            let surface = parse_name(surface, &RoadSurface::ALL, RoadSurface::identifier)?;
+            map.set_road(x, y, Some(surface));
+        }
+        "walk" => {
+            let [x, y, walkability] = fields(rest)?;
+            let (x, y) = position(map, x, y)?;
+            let walkability = parse_name(walkability, &Walkability::ALL, Walkability::identifier)?;
+            map.set_walkability(x, y, Some(walkability));
+        }
+        "trigger" => {
+            let mut parts = rest.splitn(6, ' ');
+            let mut next = || parts.next().context("missing field");
+            let index: usize = next()?.parse()?;
+            ensure!(
+                index == triggers.len(),
+                "triggers must be numbered in order"
+            );
+            let color = [next()?.parse()?, next()?.parse()?, next()?.parse()?];
+            let player = parse_optional(next()?, |player| Ok(player.parse()?))?;
+            let name = serde_json::from_str(next()?).context("trigger name")?;
+            triggers.push(TriggerArea {
+                name,
+                color,
+                tiles: BTreeSet::new(),
+                player,
+            });
+        }
+        "trigger-tile" => {
+            let [index, x, y] = fields(rest)?;
+            let index: usize = index.parse()?;
+            let tile = position(map, x, y)?;
+            triggers
+                .get_mut(index)
+                .with_context(|| format!("no trigger {index}"))?
+                .tiles
+                .insert(tile);
+        }
+        "start" => {
+            let [player, team, x, y, facing] = fields(rest)?;
+            let (x, y) = position(map, x, y)?;
+            map.starts.push(StartLocation {
+                player: player.parse()?,
+                team: team.parse()?,
+                x,
+                y,
+                facing: facing.parse()?,
+            });
+        }
+        "light" => {
+            let [
+                kind,
+                x,
+                y,
+                height,
+                r,
+                g,
+                b,
+                intensity,
+                range,
+                facing,
+                pitch,
+                cone,
+            ] = fields(rest)?;
+            let (x, y) = position(map, x, y)?;
+            map.lights.push(MapLight {
+                kind: match kind {
+                    "point" => LightKind::Point,
+                    "spot" => LightKind::Spot,
+                    _ => bail!("unknown light kind {kind}"),
+                },
+                x,
+                y,
+                height: height.parse()?,
+                color: [r.parse()?, g.parse()?, b.parse()?],
+                intensity: intensity.parse()?,
+                range: range.parse()?,
+                facing: facing.parse()?,
+                pitch: pitch.parse()?,
+                cone: cone.parse()?,
+            });
+        }
+        "decal" => {
+            let [kind, x, y, size, rotation, opacity] = fields(rest)?;
+            let decal = MapDecal {
+                kind: parse_name(kind, &DecalKind::ALL, DecalKind::identifier)?,
+                x: x.parse()?,
+                y: y.parse()?,
+                size: size.parse()?,
+                rotation: rotation.parse()?,
+                opacity: opacity.parse()?,
+            };
+            ensure!(
+                [decal.x, decal.y, decal.size, decal.rotation, decal.opacity]
+                    .iter()
+                    .all(|value| value.is_finite()),
+                "decal fields must be numbers"
+            );
+            map.decals.push(decal);
+        }
+        "prop" => {
+            let mut parts = rest.splitn(5, ' ');
+            let mut next = || parts.next().context("missing field");
+            let prop = MapProp {
+                x: next()?.parse()?,
+                y: next()?.parse()?,
+                rotation: next()?.parse()?,
+                scale: next()?.parse()?,
+                asset: serde_json::from_str(next()?).context("prop asset")?,
+            };
+            ensure!(
+                [prop.x, prop.y, prop.rotation, prop.scale]
+                    .iter()
+                    .all(|value| value.is_finite()),
+                "prop fields must be numbers"
+            );
+            map.props.push(prop);
+        }
+        _ => bail!("unknown record {record}"),
+    }
+    Ok(())
+}
+
+/// A tile from the fields of a `tile` line.
+fn parse_tile(map: &TileMap, rest: &str) -> anyhow::Result<Tile> {
+    let [
+        x,
+        y,
+        kind,
+        tile_type,
+        elevation,
+        ramp,
+        variation,
+        rotation,
+        locked,
+        cost,
+    ] = fields(rest)?;
+    let (x, y) = position(map, x, y)?;
+    Ok(Tile {
+        kind: parse_name(
+            kind,
+            &[TileKind::Floor, TileKind::Ramp, TileKind::Empty],
+            kind_name,
+        )?,
+        tile_type: parse_name(tile_type, &TileType::ALL, TileType::identifier)?,
+        x,
+        y,
+        elevation: elevation.parse()?,
+        ramp_direction: parse_optional(ramp, |ramp| {
+            parse_name(ramp, &RampDirection::RAMPS, direction_name)
+        })?,
+        variation: variation.parse()?,
+        rotation: rotation.parse()?,
+        mirrored: false,
+        locked: match locked {
+            "0" => false,
+            "1" => true,
+            _ => bail!("locked must be 0 or 1"),
+        },
+        movement_cost: parse_optional(cost, |cost| Ok(cost.parse()?))?,
+        corner_offsets: [0; 4],
+    })
+}
+
+/// Index into `upper_layers` of a layer number from a `layer-tile` line.
+fn upper_layer(map: &TileMap, layer: &str) -> anyhow::Result<usize> {
+    let layer: usize = layer.parse()?;
+    ensure!(
+        (1..map.layer_count()).contains(&layer),
+        "layer {layer} is not an upper layer of the map"
+    );
+    Ok(layer - 1)
+}
+
+/// Exactly `N` whitespace-separated fields.
+fn fields<const N: usize>(rest: &str) -> anyhow::Result<[&str; N]> {
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let count = fields.len();
+    fields
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("expected {N} fields, found {count}"))
+}
+
+fn position(map: &TileMap, x: &str, y: &str) -> anyhow::Result<(u32, u32)> {
+    let (x, y) = (x.parse()?, y.parse()?);
+    ensure!(
+        x < map.width && y < map.height,
+        "tile {x},{y} is outside the map"
+    );
+    Ok((x, y))
+}
+
+fn parse_optional<T>(
+    field: &str,
+    parse: impl FnOnce(&str) -> anyhow::Result<T>,
+) -> anyhow::Result<Option<T>> {
+    if field == "-" {
+        Ok(None)
+    } else {
+        parse(field).map(Some)
+    }
+}
+
+fn parse_name<T: Copy>(
+    field: &str,
+    values: &[T],
+    name: fn(T) -> &'static str,
+) -> anyhow::Result<T> {
+    values
+        .iter()
+        .copied()
+        .find(|&value| name(value) == field)
+        .with_context(|| format!("unknown value {field}"))
+}
+
+/// The differences between two maps as unified-diff-style lines: changed
+/// tiles as a `-` line and a `+` line at their position, then any other
+/// records only one of the maps has.
+pub fn diff(old: &TileMap, new: &TileMap) -> Vec<String> {
+    let mut output = Vec::new();
+    let old_lines = lines(old);
+    let new_lines = lines(new);
+    let is_tile = |line: &&String| line.starts_with("tile ");
+
+    let old_tiles: HashMap<(u32, u32), &Tile> = old.tiles.iter().map(|t| ((t.x, t.y), t)).collect();
+    let new_tiles: HashMap<(u32, u32), &Tile> = new.tiles.iter().map(|t| ((t.x, t.y), t)).collect();
+    let mut positions: Vec<(u32, u32)> =
+        old_tiles.keys().chain(new_tiles.keys()).copied().collect();
+    positions.sort_unstable_by_key(|&(x, y)| (y, x));
+    positions.dedup();
+    for position in positions {
+        let (before, after) = (old_tiles.get(&position), new_tiles.get(&position));
+        if before == after {
+            continue;
+        }
+        output.extend(before.map(|tile| format!("-{}", tile_line(tile))));
+        output.extend(after.map(|tile| format!("+{}", tile_line(tile))));
+    }
+
+    let mut remaining: HashMap<&str, usize> = HashMap::new();
+    for line in new_lines.iter().filter(|line| !is_tile(line)) {
+        *remaining.entry(line).or_default() += 1;
+    }
+    let mut kept: HashMap<&str, usize> = HashMap::new();
+    for line in old_lines.iter().filter(|line| !is_tile(line)) {
+        match remaining.get_mut(line.as_str()) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                *kept.entry(line).or_default() += 1;
+            }
+            _ => output.push(format!("-{line}")),
+        }
+    }
+    for line in new_lines.iter().filter(|line| !is_tile(line)) {
+        match kept.get_mut(line.as_str()) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => output.push(format!("+{line}")),
+        }
+    }
+    output
+}
@@ -11,10 +11,73 @@ use bevy::render::render_resource::{
 };
 use bevy::render::texture::{Image, ImageLoaderSettings};
 
-use crate::types::TILE_SIZE;
+use crate::types::DEFAULT_TILE_SIZE;
 
 pub type TerrainMaterial = ExtendedMaterial<StandardMaterial, TerrainMaterialExtension>;
 
+/// Asset path of the built-in shader hooks, which are compiled in.
+const BUILTIN_HOOKS_PATH: &str = "shaders/terrain_user_overrides.wgsl";
+
+/// Where the terrain shader's hook functions come from. With no `path` the
+/// built-in hooks, which change nothing, are used; otherwise the WGSL file at
+/// `path` (relative to the asset folder) replaces them. It must declare the
+/// same import path and functions as `BUILTIN_HOOKS_PATH`.
+/// Insert this before adding `TexturePlugin`.
+#[derive(Resource, Default)]
+pub struct TerrainShaderHooks {
+    pub path: Option<String>,
+    /// Keeps the hooks shader loaded.
+    handle: Option<Handle<Shader>>,
+}
+
+impl TerrainShaderHooks {
+    pub fn from_path(path: impl Into<String>) -> Self {
+        Self {
+            path: Some(path.into()),
+            handle: None,
+        }
+    }
+}
+
+/// Four values passed to every terrain material as `user` in the shader
+/// hooks, for games to drive their own effects.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Debug)]
+pub struct TerrainUserUniforms {
+    pub slots: [Vec4; 4],
+}
+
+pub(crate) fn load_shader_hooks(
+    mut hooks: ResMut<TerrainShaderHooks>,
+    asset_server: Res<AssetServer>,
+    mut shaders: ResMut<Assets<Shader>>,
+) {
+    let handle = match hooks.path.as_ref() {
+        Some(path) => asset_server.load(path.clone()),
+        None => {
+            let source = include_str!("../../assets/shaders/terrain_user_overrides.wgsl");
+            shaders.add(Shader::from_wgsl(source, BUILTIN_HOOKS_PATH))
+        }
+    };
+    hooks.handle = Some(handle);
+}
+
+/// Copies the user uniforms into every terrain material that has stale ones.
+pub(crate) fn apply_user_uniforms(
+    uniforms: Res<TerrainUserUniforms>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+) {
+    let stale: Vec<AssetId<TerrainMaterial>> = materials
+        .iter()
+        .filter(|(_, material)| material.extension.user != uniforms.slots)
+        .map(|(id, _)| id)
+        .collect();
+    for id in stale {
+        if let Some(material) = materials.get_mut(id) {
+            material.extension.user = uniforms.slots;
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TerrainMaterialHandles {
     pub material: Handle<TerrainMaterial>,
@@ -26,8 +89,10 @@ pub struct TerrainMaterialHandles {
 
 const TILE_REPEAT: f32 = 4.0;
 
-fn default_uv_scale() -> f32 {
-    1.0 / (TILE_SIZE * TILE_REPEAT)
+/// World-to-UV scale that repeats a texture every `TILE_REPEAT` tiles of
+/// `tile_size` world units.
+pub fn uv_scale(tile_size: f32) -> f32 {
+    1.0 / (tile_size * TILE_REPEAT)
 }
 
 fn default_height_uv_scale() -> f32 {
@@ -56,17 +121,20 @@ pub struct TerrainMaterialParams {
     pub wall_enabled: u32,
     pub wall_has_normal: u32,
     pub wall_has_roughness: u32,
-    #[allow(dead_code)]
-    pub _padding: Vec2,
+    pub lightmap_enabled: u32,
+    /// Cosines of the slopes where top faces start fading into the cliff
+    /// layer and where they are fully cliff; zeros leave top faces alone.
+    /// See `terrain::CliffTexturing`.
+    pub cliff_slope: Vec2,
 }
 
 impl Default for TerrainMaterialParams {
     fn default() -> Self {
         Self {
-            uv_scale: default_uv_scale(),
+            uv_scale: uv_scale(DEFAULT_TILE_SIZE),
             layer_count: 0,
             map_size: Vec2::splat(1.0),
-            tile_size: TILE_SIZE,
+            tile_size: DEFAULT_TILE_SIZE,
             height_uv_scale: default_height_uv_scale(),
             height_world_scale: default_height_world_scale(),
             cliff_blend_height: 0.2,
@@ -74,7 +142,8 @@ impl Default for TerrainMaterialParams {
             wall_enabled: 0,
             wall_has_normal: 0,
             wall_has_roughness: 0,
-            _padding: Vec2::ZERO,
+            lightmap_enabled: 0,
+            cliff_slope: Vec2::ZERO,
         }
     }
 }
@@ -96,9 +165,27 @@ pub struct TerrainMaterialExtension {
     #[sampler(106)]
     pub roughness_array: Option<Handle<Image>>,
 
-    #[texture(107, dimension = "2d")]
+    /// Layer weights, four layers per array page; see `terrain::splatmap`.
+    #[texture(107, dimension = "2d_array")]
     #[sampler(108)]
     pub splat_map: Option<Handle<Image>>,
+
+    #[texture(109, dimension = "2d")]
+    #[sampler(110)]
+    pub lightmap: Option<Handle<Image>>,
+
+    #[texture(111, dimension = "2d")]
+    #[sampler(112)]
+    pub variation_map: Option<Handle<Image>>,
+
+    /// Copied from `TerrainUserUniforms` for the shader hooks.
+    #[uniform(113)]
+    pub user: [Vec4; 4],
+
+    /// Roads drawn over the splat; see `terrain::road_map`.
+    #[texture(114, dimension = "2d")]
+    #[sampler(115)]
+    pub road_map: Option<Handle<Image>>,
 }
 
 impl Default for TerrainMaterialExtension {
@@ -109,6 +196,10 @@ impl Default for TerrainMaterialExtension {
             normal_array: None,
             roughness_array: None,
             splat_map: None,
+            lightmap: None,
+            variation_map: None,
+            user: [Vec4::ZERO; 4],
+            road_map: None,
         }
     }
 }
@@ -150,6 +241,15 @@ impl MaterialExtension for TerrainMaterialExtension {
             frag.shader_defs
                 .push("TERRAIN_MATERIAL_EXTENSION_SPLAT_MAP".into());
 
+            frag.shader_defs
+                .push("TERRAIN_MATERIAL_EXTENSION_LIGHTMAP".into());
+
+            frag.shader_defs
+                .push("TERRAIN_MATERIAL_EXTENSION_VARIATION_MAP".into());
+
+            frag.shader_defs
+                .push("TERRAIN_MATERIAL_EXTENSION_ROAD_MAP".into());
+
             // frag.shader_defs.push("DEBUG_ROUGHNESS".into());
             // frag.shader_defs.push("DEBUG_NORMALS".into());
         }
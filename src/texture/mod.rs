@@ -9,6 +9,10 @@ pub struct TexturePlugin;
 impl Plugin for TexturePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(MaterialPlugin::<material::TerrainMaterial>::default())
-            .init_resource::<registry::TerrainTextureRegistry>();
+            .init_resource::<registry::TerrainTextureRegistry>()
+            .init_resource::<material::TerrainShaderHooks>()
+            .init_resource::<material::TerrainUserUniforms>()
+            .add_systems(Startup, material::load_shader_hooks)
+            .add_systems(Update, material::apply_user_uniforms);
     }
 }
@@ -35,6 +35,63 @@ pub struct WallTextureEntry {
     pub roughness_path: Option<String>,
 }
 
+/// Asset paths of the maps of a bundled terrain texture.
+#[derive(Debug, Clone, Copy)]
+pub struct BundledTexture {
+    pub tile_type: TileType,
+    pub name: &'static str,
+    pub base_color: &'static str,
+    pub normal: Option<&'static str>,
+    pub roughness: Option<&'static str>,
+    pub dispersion: Option<&'static str>,
+}
+
+/// The terrain textures `load_defaults` registers.
+pub const DEFAULT_TEXTURES: [BundledTexture; TileType::BUILTIN.len()] = [
+    BundledTexture {
+        tile_type: TileType::Grass,
+        name: "Rocky Terrain",
+        base_color: "textures/terrain/rocky_terrain_02_diff_1k.png",
+        normal: Some("textures/terrain/rocky_terrain_02_nor_gl_1k_fixed.exr"),
+        roughness: Some("textures/terrain/roughness_l8.png"),
+        dispersion: Some("textures/terrain/rocky_terrain_02_disp_1k.png"),
+    },
+    BundledTexture {
+        tile_type: TileType::Dirt,
+        name: "Worn Soil",
+        base_color: "textures/terrain/rocky_terrain_02_diff_1k.png",
+        normal: Some("textures/terrain/rocky_terrain_02_nor_gl_1k_fixed.exr"),
+        roughness: Some("textures/terrain/roughness_l8.png"),
+        dispersion: Some("textures/terrain/rocky_terrain_02_disp_1k.png"),
+    },
+    BundledTexture {
+        tile_type: TileType::Sand,
+        name: "Sandstone",
+        base_color: "textures/terrain/rock/aerial_ground_rock_diff_1k.png",
+        normal: Some("textures/terrain/rock/aerial_ground_rock_nor_gl_1k_fixed.exr"),
+        roughness: Some("textures/terrain/rock/roughness_in_G.png"),
+        dispersion: Some("textures/terrain/rock/aerial_ground_rock_disp_1k.png"),
+    },
+    BundledTexture {
+        tile_type: TileType::Rock,
+        name: "Ground Rock",
+        base_color: "textures/terrain/rock/aerial_ground_rock_diff_1k.png",
+        normal: Some("textures/terrain/rock/aerial_ground_rock_nor_gl_1k_fixed.exr"),
+        roughness: Some("textures/terrain/rock/roughness_in_G.png"),
+        dispersion: Some("textures/terrain/rock/aerial_ground_rock_disp_1k.png"),
+    },
+];
+
+/// The bundled cliff wall texture: id, display name and the asset paths of
+/// its base color, normal and roughness maps.
+pub const DEFAULT_WALL_TEXTURE: (&str, &str, &str, Option<&str>, Option<&str>) = (
+    "wall",
+    "Cliff Wall",
+    "textures/terrain/rock/aerial_ground_rock_diff_1k.png",
+    Some("textures/terrain/rock/aerial_ground_rock_nor_gl_1k_fixed.exr"),
+    Some("textures/terrain/rock/roughness_in_G.png"),
+);
+
 #[derive(Resource, Default)]
 pub struct TerrainTextureRegistry {
     entries: Vec<TerrainTextureEntry>,
@@ -118,6 +175,29 @@ impl TerrainTextureRegistry {
         material
     }
 
+    /// Registers the bundled terrain and cliff wall textures.
+    pub fn load_defaults(
+        &mut self,
+        asset_server: &AssetServer,
+        materials: &mut Assets<TerrainMaterial>,
+    ) {
+        for texture in DEFAULT_TEXTURES {
+            self.load_and_register(
+                texture.tile_type,
+                texture.name,
+                asset_server,
+                materials,
+                texture.base_color,
+                texture.normal,
+                texture.roughness,
+                texture.dispersion,
+            );
+        }
+
+        let (id, name, base, normal, roughness) = DEFAULT_WALL_TEXTURE;
+        self.load_and_register_wall(id, name, asset_server, base, normal, roughness);
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &TerrainTextureEntry> {
         self.entries.iter()
     }
@@ -128,6 +208,27 @@ impl TerrainTextureRegistry {
             .and_then(|index| self.entries.get(*index))
     }
 
+    /// The layers tiles can be painted with, in order: every layer up to the
+    /// first without a texture, since the texture arrays stack them without
+    /// gaps.
+    pub fn layers(&self) -> impl Iterator<Item = TileType> + '_ {
+        TileType::ALL
+            .into_iter()
+            .take_while(|tile_type| self.lookup.contains_key(tile_type))
+    }
+
+    /// The layer a newly added texture goes to, or `None` once every layer
+    /// the shader blends has one.
+    pub fn next_layer(&self) -> Option<TileType> {
+        TileType::ALL.get(self.layers().count()).copied()
+    }
+
+    fn layer_entries(&self) -> Vec<&TerrainTextureEntry> {
+        self.layers()
+            .filter_map(|tile_type| self.get(tile_type))
+            .collect()
+    }
+
     pub fn load_and_register_wall(
         &mut self,
         id: impl Into<String>,
@@ -224,13 +325,11 @@ impl TerrainTextureRegistry {
         self.wall_normal_available = false;
         self.wall_roughness_available = false;
 
-        let mut base_layers: Vec<&Image> = Vec::with_capacity(
-            TileType::ALL.len() + if self.wall_texture.is_some() { 1 } else { 0 },
-        );
+        let layers = self.layer_entries();
+        let mut base_layers: Vec<&Image> =
+            Vec::with_capacity(layers.len() + if self.wall_texture.is_some() { 1 } else { 0 });
 
-        for tile_type in TileType::ALL {
-            let entry_index = *self.lookup.get(&tile_type)?;
-            let entry = self.entries.get(entry_index)?;
+        for entry in &layers {
             let image = images.get(&entry.preview)?;
             base_layers.push(image);
         }
@@ -246,8 +345,7 @@ impl TerrainTextureRegistry {
         let base_handle = images.add(base_array);
 
         let (normal_handle, wall_has_normal) = ensure_optional_array(
-            &self.entries,
-            &self.lookup,
+            &layers,
             images,
             |entry| entry.normal.as_ref(),
             [0.5, 0.5, 1.0, 1.0],
@@ -257,8 +355,7 @@ impl TerrainTextureRegistry {
         )?;
 
         let (roughness_handle, wall_has_roughness) = ensure_optional_array(
-            &self.entries,
-            &self.lookup,
+            &layers,
             images,
             |entry| entry.roughness.as_ref(),
             [1.0, 1.0, 1.0, 1.0],
@@ -300,8 +397,7 @@ pub struct TerrainTextureArrays {
 }
 
 fn ensure_optional_array<F>(
-    entries: &[TerrainTextureEntry],
-    lookup: &HashMap<TileType, usize>,
+    layers: &[&TerrainTextureEntry],
     images: &mut Assets<Image>,
     accessor: F,
     fallback_color: [f32; 4],
@@ -310,15 +406,7 @@ fn ensure_optional_array<F>(
 where
     F: Fn(&TerrainTextureEntry) -> Option<&Handle<Image>>,
 {
-    let mut has_texture = false;
-    for tile_type in TileType::ALL {
-        let entry_index = *lookup.get(&tile_type)?;
-        let entry = entries.get(entry_index)?;
-        if accessor(entry).is_some() {
-            has_texture = true;
-            break;
-        }
-    }
+    let has_texture = layers.iter().any(|entry| accessor(entry).is_some());
     let extra_has_handle = extra_layer
         .as_ref()
         .and_then(|extra| extra.handle)
@@ -328,7 +416,7 @@ where
         return Some((None, false));
     }
 
-    let template_image = match find_template_image(entries, lookup, images, &accessor) {
+    let template_image = match find_template_image(layers, images, &accessor) {
         Some(image) => image.clone(),
         None => {
             if let Some(extra) = &extra_layer {
@@ -349,11 +437,8 @@ where
 
     // --- pass 1: resolve handles (may mutate images) ---
     let mut handles: Vec<Handle<Image>> =
-        Vec::with_capacity(TileType::ALL.len() + if extra_layer.is_some() { 1 } else { 0 });
-    for tile_type in TileType::ALL {
-        let entry_index = *lookup.get(&tile_type)?;
-        let entry = entries.get(entry_index)?;
-
+        Vec::with_capacity(layers.len() + if extra_layer.is_some() { 1 } else { 0 });
+    for entry in layers {
         if let Some(handle) = accessor(entry) {
             // only record handle, check later
             handles.push(handle.clone());
@@ -399,17 +484,14 @@ where
 }
 
 fn find_template_image<'a, F>(
-    entries: &'a [TerrainTextureEntry],
-    lookup: &HashMap<TileType, usize>,
+    layers: &[&TerrainTextureEntry],
     images: &'a bevy::prelude::Assets<bevy::prelude::Image>,
     accessor: &F,
 ) -> Option<&'a Image>
 where
     F: Fn(&TerrainTextureEntry) -> Option<&Handle<Image>>,
 {
-    for tile_type in TileType::ALL {
-        let entry_index = *lookup.get(&tile_type)?;
-        let entry = entries.get(entry_index)?;
+    for entry in layers {
         if let Some(handle) = accessor(entry) {
             if let Some(image) = images.get(handle) {
                 return Some(image);
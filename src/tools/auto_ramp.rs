@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use super::{orthogonal_steps, outline_tile};
+use crate::editor::{self, EditorState, EditorTool, MapEdit};
+use crate::terrain::CornerHeightCache;
+use crate::types::{RampDirection, TileKind, TileMap};
+
+const PREVIEW_COLOR: Color = Color::srgb(0.4, 0.9, 1.0);
+/// Shown when the path is too short to climb one level per tile.
+const STEEP_COLOR: Color = Color::srgb(1.0, 0.35, 0.3);
+
+#[derive(Clone, Copy)]
+pub struct AutoRampDrag {
+    start: (u32, u32),
+    end: (u32, u32),
+}
+
+/// The dragged path, both ends included.
+fn ramp_path(start: (u32, u32), end: (u32, u32)) -> Vec<(u32, u32)> {
+    let mut path = vec![start];
+    path.extend(orthogonal_steps(start, end));
+    path
+}
+
+/// Levels to climb between the ends of `path`, and whether there are enough
+/// tiles to do it one level at a time.
+fn climb(map: &TileMap, path: &[(u32, u32)]) -> (i8, i8, bool) {
+    let (first, last) = (path[0], path[path.len() - 1]);
+    let from = map.get(first.0, first.1).elevation;
+    let to = map.get(last.0, last.1).elevation;
+    let walkable = ((to - from).unsigned_abs() as usize) < path.len();
+    (from, to, walkable)
+}
+
+/// Drag from one tile to another to lay a walkable slope between their
+/// elevations along the dragged path. Tiles in between are spread evenly
+/// over the levels, and each tile one level above the next one down the path
+/// becomes a ramp facing it. Paths too short to climb one level per tile are
+/// previewed in red and left with a cliff at the top.
+pub fn connect_with_ramps(
+    buttons: Res<ButtonInput<MouseButton>>,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    mut drag: Local<Option<AutoRampDrag>>,
+    mut edits: EventWriter<MapEdit>,
+    mut gizmos: Gizmos,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::AutoRamp {
+        *drag = None;
+        return;
+    }
+
+    if buttons.just_pressed(MouseButton::Left) && !egui.ctx_mut().wants_pointer_input() {
+        *drag = state.hover.map(|tile| AutoRampDrag {
+            start: tile,
+            end: tile,
+        });
+    }
+    let Some(active) = drag.as_mut() else {
+        return;
+    };
+    if let Some(hover) = state.hover {
+        active.end = hover;
+    }
+    let path = ramp_path(active.start, active.end);
+
+    if buttons.pressed(MouseButton::Left) {
+        let (_, _, walkable) = climb(&state.map, &path);
+        let color = if walkable { PREVIEW_COLOR } else { STEEP_COLOR };
+        for &(x, y) in &path {
+            outline_tile(&mut gizmos, &heights, x, y, color);
+        }
+        return;
+    }
+
+    edits.send_batch(ramp_edits(&state.map, &path));
+    *drag = None;
+}
+
+fn ramp_edits(map: &TileMap, path: &[(u32, u32)]) -> Vec<MapEdit> {
+    if path.len() < 2 {
+        return Vec::new();
+    }
+    let (from, to, _) = climb(map, path);
+    let steps = (path.len() - 1) as f32;
+    let levels = (to - from) as f32;
+
+    // Lay the elevations first so ramps can see their finished neighbours.
+    let mut sloped = map.clone();
+    let last = path.len() - 1;
+    for (i, &(x, y)) in path.iter().enumerate() {
+        // Never climb more than a level per tile, except onto the end tile.
+        let rise = if i == last {
+            levels
+        } else {
+            (levels * i as f32 / steps)
+                .round()
+                .clamp(-(i as f32), i as f32)
+        };
+        let mut tile = sloped.get(x, y).clone();
+        tile.elevation = from + rise as i8;
+        if tile.kind == TileKind::Empty {
+            tile.kind = TileKind::Floor;
+        }
+        sloped.set(x, y, tile);
+    }
+
+    let elevation = |(x, y): (u32, u32)| sloped.get(x, y).elevation;
+    let mut tiles = Vec::with_capacity(path.len());
+    for (i, &(x, y)) in path.iter().enumerate() {
+        let mut tile = sloped.get(x, y).clone();
+        // The path neighbour one level down, if either of them is.
+        let below = [i.checked_sub(1), Some(i + 1)]
+            .into_iter()
+            .flatten()
+            .filter_map(|j| path.get(j).copied())
+            .find(|&next| elevation(next) == tile.elevation - 1);
+        let direction = below.and_then(|(nx, ny)| {
+            let towards = RampDirection::from_offset(nx as i32 - x as i32, ny as i32 - y as i32)?;
+            let base = tile.elevation as f32 * sloped.tile_height();
+            editor::ramp_targets(&sloped, x, y, base)
+                .contains(&towards)
+                .then_some(towards)
+        });
+        // Tiles left at their height keep whatever shape they had.
+        if direction.is_some() || tile.elevation != map.get(x, y).elevation {
+            tile.kind = if direction.is_some() {
+                TileKind::Ramp
+            } else {
+                TileKind::Floor
+            };
+            tile.ramp_direction = direction;
+        }
+        tiles.push(tile);
+    }
+
+    path.iter()
+        .zip(tiles)
+        .filter(|((x, y), tile)| tile != map.get(*x, *y))
+        .map(|(&(x, y), tile)| MapEdit::SetTile { x, y, tile })
+        .collect()
+}
@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use super::outline_tile;
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::terrain::CornerHeightCache;
+
+const SOURCE_COLOR: Color = Color::srgb(1.0, 0.3, 0.9);
+
+#[derive(Default)]
+pub struct CloneSource {
+    anchor: Option<(u32, u32)>,
+    /// Source minus destination, fixed by the first paint after choosing an
+    /// anchor and kept for later strokes, like an aligned clone brush.
+    offset: Option<(i32, i32)>,
+}
+
+/// Copies kind, elevation, texture and ramp direction from an offset source.
+/// Alt-click picks the source anchor; the first click afterwards ties that
+/// anchor to the painted tile, and painting copies from the same offset.
+#[allow(clippy::too_many_arguments)]
+pub fn paint_clone(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    mut source: Local<CloneSource>,
+    mut edits: EventWriter<MapEdit>,
+    mut gizmos: Gizmos,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Clone {
+        return;
+    }
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let Some((x, y)) = state.hover else {
+        return;
+    };
+
+    if keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]) {
+        if buttons.just_pressed(MouseButton::Left) {
+            *source = CloneSource {
+                anchor: Some((x, y)),
+                offset: None,
+            };
+        }
+        outline_tile(&mut gizmos, &heights, x, y, SOURCE_COLOR);
+        return;
+    }
+
+    let offset = match (source.offset, source.anchor) {
+        (Some(offset), _) => offset,
+        (None, Some((ax, ay))) => {
+            outline_tile(&mut gizmos, &heights, ax, ay, SOURCE_COLOR);
+            if !buttons.just_pressed(MouseButton::Left) {
+                return;
+            }
+            let offset = (ax as i32 - x as i32, ay as i32 - y as i32);
+            source.offset = Some(offset);
+            offset
+        }
+        (None, None) => return,
+    };
+
+    let (sx, sy) = (x as i32 + offset.0, y as i32 + offset.1);
+    if sx < 0 || sy < 0 || sx as u32 >= state.map.width || sy as u32 >= state.map.height {
+        return;
+    }
+    let (sx, sy) = (sx as u32, sy as u32);
+    outline_tile(&mut gizmos, &heights, sx, sy, SOURCE_COLOR);
+
+    if !buttons.pressed(MouseButton::Left) {
+        return;
+    }
+    let mut tile = state.map.get(sx, sy).clone();
+    tile.x = x;
+    tile.y = y;
+    if *state.map.get(x, y) != tile {
+        edits.send(MapEdit::SetTile { x, y, tile });
+    }
+}
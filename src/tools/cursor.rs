@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use bevy::window::{CursorIcon, PrimaryWindow};
+use bevy_egui::EguiContexts;
+
+use crate::editor::{EditorState, EditorTool};
+use crate::terrain::CornerHeightCache;
+
+const BRUSH_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const ERASE_COLOR: Color = Color::srgb(0.95, 0.3, 0.3);
+
+/// Window cursor shown over the terrain while `tool` is active.
+fn tool_cursor(tool: EditorTool) -> CursorIcon {
+    match tool {
+        EditorTool::Paint
+        | EditorTool::Gradient
+        | EditorTool::Noise
+        | EditorTool::Sculpt
+        | EditorTool::Smooth
+        | EditorTool::Vertex
+        | EditorTool::Scatter
+        | EditorTool::Lock
+        | EditorTool::MovementCost
+        | EditorTool::Walkability
+        | EditorTool::Trigger
+        | EditorTool::Vegetation
+        | EditorTool::Erase => CursorIcon::Cell,
+        EditorTool::RotateRamp | EditorTool::RotateTexture | EditorTool::Prop => CursorIcon::Move,
+        EditorTool::Select
+        | EditorTool::Wand
+        | EditorTool::Road
+        | EditorTool::EdgeRamp
+        | EditorTool::Plateau
+        | EditorTool::RectFill
+        | EditorTool::AutoRamp => CursorIcon::Crosshair,
+        EditorTool::Clone | EditorTool::Macro | EditorTool::Paste | EditorTool::Import => {
+            CursorIcon::Copy
+        }
+        EditorTool::Start | EditorTool::Path | EditorTool::Light | EditorTool::Decal => {
+            CursorIcon::Pointer
+        }
+        EditorTool::Eyedropper => CursorIcon::Crosshair,
+        EditorTool::Pan => CursorIcon::Grab,
+    }
+}
+
+/// Sets the window cursor for the active tool. Runs after egui has applied
+/// its own cursor, and leaves that one alone while the pointer is over a
+/// panel.
+pub fn update_tool_cursor(
+    state: Res<EditorState>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut egui: EguiContexts,
+) {
+    if egui.ctx_mut().is_pointer_over_area() {
+        return;
+    }
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    let icon = tool_cursor(state.current_tool);
+    if window.cursor.icon != icon {
+        window.cursor.icon = icon;
+    }
+}
+
+/// Rings the hovered tile while painting or erasing, sized to the brush.
+pub fn draw_brush_cursor(
+    mut gizmos: Gizmos,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+) {
+    let color = match state.current_tool {
+        EditorTool::Paint => BRUSH_COLOR,
+        EditorTool::Erase => ERASE_COLOR,
+        _ => return,
+    };
+    let Some((x, y)) = state.hover else {
+        return;
+    };
+    let Some(corners) = heights.corners(x, y) else {
+        return;
+    };
+    let top = corners.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let centre = Vec3::new(
+        (x as f32 + 0.5) * heights.tile_size(),
+        top + 0.05,
+        (y as f32 + 0.5) * heights.tile_size(),
+    );
+    let radius = match state.current_tool {
+        EditorTool::Paint => state.paint_radius as f32 - 0.4,
+        _ => 0.6,
+    };
+    gizmos.circle(centre, Dir3::Y, heights.tile_size() * radius, color);
+}
@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::input;
+use crate::types::{MapDecal, TileMap};
+
+const PREVIEW_COLOR: Color = Color::srgb(0.85, 0.75, 0.5);
+const ERASE_COLOR: Color = Color::srgb(0.95, 0.3, 0.3);
+
+/// Click to place a decal with the toolbar settings centred on the cursor;
+/// Ctrl-click removes the topmost decal under it. The decal's outline
+/// follows the cursor.
+pub fn place_decal(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<EditorState>,
+    mut edits: EventWriter<MapEdit>,
+    mut gizmos: Gizmos,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Decal {
+        return;
+    }
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let Some(point) = state.hover_point else {
+        return;
+    };
+    let (x, y) = (point.x / state.map.tile_size, point.z / state.map.tile_size);
+
+    let erase = input::erase_held(&keys);
+    if erase {
+        if let Some(index) = decal_at(&state.map, x, y) {
+            outline_decal(
+                &mut gizmos,
+                &state.map,
+                &state.map.decals[index],
+                point.y,
+                ERASE_COLOR,
+            );
+        }
+    } else {
+        let preview = MapDecal {
+            x,
+            y,
+            ..state.decal.clone()
+        };
+        outline_decal(&mut gizmos, &state.map, &preview, point.y, PREVIEW_COLOR);
+    }
+    if !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    if erase {
+        if let Some(index) = decal_at(&state.map, x, y) {
+            let mut decals = state.map.decals.clone();
+            decals.remove(index);
+            edits.send(MapEdit::SetDecals(decals));
+        }
+        return;
+    }
+    let rotation = if state.decal_random_rotation {
+        fastrand::f32() * 360.0
+    } else {
+        state.decal.rotation
+    };
+    edits.send(MapEdit::PlaceDecal(MapDecal {
+        x,
+        y,
+        rotation,
+        ..state.decal.clone()
+    }));
+}
+
+/// Index of the last-drawn decal whose square covers (`x`, `y`), in tiles.
+fn decal_at(map: &TileMap, x: f32, y: f32) -> Option<usize> {
+    map.decals.iter().rposition(|decal| {
+        let (sin, cos) = decal.rotation.to_radians().sin_cos();
+        let (dx, dy) = (x - decal.x, y - decal.y);
+        let half_size = decal.size * 0.5;
+        (dx * cos + dy * sin).abs() <= half_size && (dy * cos - dx * sin).abs() <= half_size
+    })
+}
+
+/// Outlines a decal's square at `height`, with a tick on its north edge to
+/// show which way it is turned.
+fn outline_decal(gizmos: &mut Gizmos, map: &TileMap, decal: &MapDecal, height: f32, color: Color) {
+    let tile_size = map.tile_size;
+    let centre = Vec3::new(decal.x * tile_size, height + 0.05, decal.y * tile_size);
+    let turn = Quat::from_rotation_y(-decal.rotation.to_radians());
+    let half_size = decal.size * tile_size * 0.5;
+    let corner = |east: f32, south: f32| centre + turn * Vec3::new(east, 0.0, south) * half_size;
+    gizmos.linestrip(
+        [
+            corner(-1.0, -1.0),
+            corner(1.0, -1.0),
+            corner(1.0, 1.0),
+            corner(-1.0, 1.0),
+            corner(-1.0, -1.0),
+        ],
+        color,
+    );
+    gizmos.line(corner(0.0, -1.0), corner(0.0, -1.3), color);
+}
@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use super::outline_tile;
+use crate::editor::{self, EditorState, EditorTool, MapEdit};
+use crate::terrain::CornerHeightCache;
+use crate::types::{RampDirection, TileKind, TileMap};
+
+const PREVIEW_COLOR: Color = Color::srgb(1.0, 0.75, 0.1);
+
+/// An edge-ramp drag in progress. The downhill direction is fixed by the
+/// tile the drag started on.
+#[derive(Clone, Copy)]
+pub struct EdgeRampDrag {
+    anchor: (u32, u32),
+    current: (u32, u32),
+    direction: RampDirection,
+}
+
+/// Converts cliff-edge tiles into ramps. Dragging along a cliff selects the
+/// edge tiles it passes; dragging away from the edge widens the selection
+/// into the terraces behind it, which turns stepped cliffs into one broad
+/// staircase. The ramps are previewed while dragging and applied on release.
+pub fn paint_edge_ramps(
+    buttons: Res<ButtonInput<MouseButton>>,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    mut drag: Local<Option<EdgeRampDrag>>,
+    mut edits: EventWriter<MapEdit>,
+    mut gizmos: Gizmos,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::EdgeRamp {
+        *drag = None;
+        return;
+    }
+
+    if buttons.just_pressed(MouseButton::Left) && !egui.ctx_mut().wants_pointer_input() {
+        *drag = state.hover.and_then(|(x, y)| {
+            let tile = state.map.get(x, y);
+            let base = tile.elevation as f32 * state.map.tile_height();
+            let candidates = editor::ramp_targets(&state.map, x, y, base);
+            let direction = tile
+                .ramp_direction
+                .filter(|dir| candidates.contains(dir))
+                .or_else(|| candidates.first().copied())?;
+            Some(EdgeRampDrag {
+                anchor: (x, y),
+                current: (x, y),
+                direction,
+            })
+        });
+    }
+
+    let Some(active) = drag.as_mut() else {
+        return;
+    };
+    if let Some(hover) = state.hover {
+        active.current = hover;
+    }
+    let tiles = edge_tiles(&state.map, active.anchor, active.current, active.direction);
+
+    if buttons.pressed(MouseButton::Left) {
+        for &(x, y) in &tiles {
+            outline_tile(&mut gizmos, &heights, x, y, PREVIEW_COLOR);
+        }
+        return;
+    }
+
+    for (x, y) in tiles {
+        let mut tile = state.map.get(x, y).clone();
+        if tile.kind == TileKind::Ramp && tile.ramp_direction == Some(active.direction) {
+            continue;
+        }
+        tile.kind = TileKind::Ramp;
+        tile.ramp_direction = Some(active.direction);
+        edits.send(MapEdit::SetTile { x, y, tile });
+    }
+    *drag = None;
+}
+
+/// Tiles in the rectangle spanned by `a` and `b` that sit above a lower
+/// neighbour in `direction`.
+fn edge_tiles(
+    map: &TileMap,
+    a: (u32, u32),
+    b: (u32, u32),
+    direction: RampDirection,
+) -> Vec<(u32, u32)> {
+    let (dx, dy) = direction.offset();
+    let mut tiles = Vec::new();
+    for y in a.1.min(b.1)..=a.1.max(b.1) {
+        for x in a.0.min(b.0)..=a.0.max(b.0) {
+            let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+            if nx < 0 || ny < 0 || nx as u32 >= map.width || ny as u32 >= map.height {
+                continue;
+            }
+            let (tile, below) = (map.get(x, y), map.get(nx as u32, ny as u32));
+            if tile.kind != TileKind::Empty
+                && below.kind != TileKind::Empty
+                && below.elevation < tile.elevation
+            {
+                tiles.push((x, y));
+            }
+        }
+    }
+    tiles
+}
@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use super::outline_tile;
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::selection::Selection;
+use crate::terrain::CornerHeightCache;
+use crate::types::{RampDirection, TileKind, TileMap};
+
+const PREVIEW_COLOR: Color = Color::srgb(0.4, 1.0, 0.5);
+
+#[derive(Clone, Copy)]
+pub struct GradientDrag {
+    start: (u32, u32),
+    end: (u32, u32),
+}
+
+/// Slopes a region from `EditorState::gradient_from` at the start of the drag
+/// to `gradient_to` at its end. Elevations are quantized per tile and every
+/// resulting one-level step facing down the gradient becomes a ramp. The
+/// region is the selection when there is one, otherwise the dragged
+/// rectangle.
+#[allow(clippy::too_many_arguments)]
+pub fn apply_gradient(
+    buttons: Res<ButtonInput<MouseButton>>,
+    state: Res<EditorState>,
+    selection: Res<Selection>,
+    heights: Res<CornerHeightCache>,
+    mut drag: Local<Option<GradientDrag>>,
+    mut edits: EventWriter<MapEdit>,
+    mut gizmos: Gizmos,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Gradient {
+        *drag = None;
+        return;
+    }
+
+    if buttons.just_pressed(MouseButton::Left) && !egui.ctx_mut().wants_pointer_input() {
+        *drag = state.hover.map(|tile| GradientDrag {
+            start: tile,
+            end: tile,
+        });
+    }
+    let Some(active) = drag.as_mut() else {
+        return;
+    };
+    if let Some(hover) = state.hover {
+        active.end = hover;
+    }
+
+    let region: Vec<(u32, u32)> = if selection.is_empty() {
+        let (a, b) = (active.start, active.end);
+        (a.1.min(b.1)..=a.1.max(b.1))
+            .flat_map(|y| (a.0.min(b.0)..=a.0.max(b.0)).map(move |x| (x, y)))
+            .collect()
+    } else {
+        selection.iter().collect()
+    };
+
+    if buttons.pressed(MouseButton::Left) {
+        for &(x, y) in &region {
+            outline_tile(&mut gizmos, &heights, x, y, PREVIEW_COLOR);
+        }
+        let lift = Vec3::Y * 0.1;
+        let endpoint = |(x, y): (u32, u32)| {
+            let centre = tile_centre(x, y, heights.tile_size());
+            let height = heights.height_at(centre.x, centre.y).unwrap_or(0.0);
+            Vec3::new(centre.x, height, centre.y) + lift
+        };
+        gizmos.arrow(endpoint(active.start), endpoint(active.end), PREVIEW_COLOR);
+        return;
+    }
+
+    let elevations = gradient_elevations(
+        &region,
+        active.start,
+        active.end,
+        state.gradient_from,
+        state.gradient_to,
+    );
+    edits.send_batch(gradient_edits(
+        &state.map,
+        &elevations,
+        active.start,
+        active.end,
+    ));
+    *drag = None;
+}
+
+fn tile_centre(x: u32, y: u32, tile_size: f32) -> Vec2 {
+    Vec2::new(x as f32 + 0.5, y as f32 + 0.5) * tile_size
+}
+
+/// Target elevation of every region tile, from projecting its centre onto the
+/// dragged line.
+fn gradient_elevations(
+    region: &[(u32, u32)],
+    start: (u32, u32),
+    end: (u32, u32),
+    from: i8,
+    to: i8,
+) -> Vec<((u32, u32), i8)> {
+    // The projection is the same at any scale, so tile units will do.
+    let origin = tile_centre(start.0, start.1, 1.0);
+    let axis = tile_centre(end.0, end.1, 1.0) - origin;
+    let length_sq = axis.length_squared();
+    region
+        .iter()
+        .map(|&(x, y)| {
+            let t = if length_sq > 0.0 {
+                ((tile_centre(x, y, 1.0) - origin).dot(axis) / length_sq).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let elevation = from as f32 + (to as f32 - from as f32) * t;
+            ((x, y), elevation.round() as i8)
+        })
+        .collect()
+}
+
+fn gradient_edits(
+    map: &TileMap,
+    elevations: &[((u32, u32), i8)],
+    start: (u32, u32),
+    end: (u32, u32),
+) -> Vec<MapEdit> {
+    let lookup: std::collections::HashMap<(u32, u32), i8> = elevations.iter().copied().collect();
+    let elevation_of = |x: i32, y: i32| -> Option<i8> {
+        if x < 0 || y < 0 || x as u32 >= map.width || y as u32 >= map.height {
+            return None;
+        }
+        let key = (x as u32, y as u32);
+        Some(
+            lookup
+                .get(&key)
+                .copied()
+                .unwrap_or_else(|| map.get(key.0, key.1).elevation),
+        )
+    };
+
+    // Ramps face along the dominant axis of the drag, towards lower ground.
+    let (dx, dy) = (end.0 as i32 - start.0 as i32, end.1 as i32 - start.1 as i32);
+    let along = if dx.abs() >= dy.abs() {
+        RampDirection::from_offset(dx.signum(), 0)
+    } else {
+        RampDirection::from_offset(0, dy.signum())
+    };
+    let downhill = along.map(|dir| {
+        let (ox, oy) = dir.offset();
+        [dir, RampDirection::from_offset(-ox, -oy).unwrap_or(dir)]
+    });
+
+    elevations
+        .iter()
+        .filter_map(|&((x, y), elevation)| {
+            let current = map.get(x, y);
+            if current.kind == TileKind::Empty {
+                return None;
+            }
+            let mut tile = current.clone();
+            tile.elevation = elevation;
+            tile.kind = TileKind::Floor;
+            tile.ramp_direction = None;
+
+            for dir in downhill.into_iter().flatten() {
+                let (ox, oy) = dir.offset();
+                if elevation_of(x as i32 + ox, y as i32 + oy) == Some(elevation - 1) {
+                    tile.kind = TileKind::Ramp;
+                    tile.ramp_direction = Some(dir);
+                    break;
+                }
+            }
+
+            (tile != *current).then_some(MapEdit::SetTile { x, y, tile })
+        })
+        .collect()
+}
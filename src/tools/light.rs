@@ -0,0 +1,28 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::input;
+
+/// Click to place a light with the toolbar settings, replacing any light on
+/// the tile; Ctrl-click removes it.
+pub fn place_light(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<EditorState>,
+    mut edits: EventWriter<MapEdit>,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Light || !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let Some((x, y)) = state.hover else {
+        return;
+    };
+
+    let light = (!input::erase_held(&keys)).then(|| state.light.clone());
+    edits.send(MapEdit::SetLight { x, y, light });
+}
@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::input;
+use crate::terrain::{self, CornerHeightCache};
+
+const LOCK_COLOR: Color = Color::srgb(0.95, 0.25, 0.25);
+
+/// Locks tiles under the cursor while dragging; holding Ctrl unlocks instead.
+pub fn paint_locks(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<EditorState>,
+    mut edits: EventWriter<MapEdit>,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Lock || !buttons.pressed(MouseButton::Left) {
+        return;
+    }
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let Some((x, y)) = state.hover else {
+        return;
+    };
+    let locked = !input::erase_held(&keys);
+    if state.map.get(x, y).locked != locked {
+        edits.send(MapEdit::SetLocked { x, y, locked });
+    }
+}
+
+/// Crosses out locked tiles when the lock overlay is enabled, and always
+/// while the lock tool is active.
+pub fn draw_lock_overlay(
+    mut gizmos: Gizmos,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+) {
+    if !state.show_locks && state.current_tool != EditorTool::Lock {
+        return;
+    }
+    let lift = 0.05;
+    for (idx, tile) in state.map.tiles.iter().enumerate() {
+        if !tile.locked {
+            continue;
+        }
+        let x = idx as u32 % state.map.width;
+        let y = idx as u32 / state.map.width;
+        let Some(corners) = heights.corners(x, y) else {
+            continue;
+        };
+        let x0 = x as f32 * heights.tile_size();
+        let x1 = x0 + heights.tile_size();
+        let z0 = y as f32 * heights.tile_size();
+        let z1 = z0 + heights.tile_size();
+        let nw = Vec3::new(x0, corners[terrain::CORNER_NW] + lift, z0);
+        let ne = Vec3::new(x1, corners[terrain::CORNER_NE] + lift, z0);
+        let sw = Vec3::new(x0, corners[terrain::CORNER_SW] + lift, z1);
+        let se = Vec3::new(x1, corners[terrain::CORNER_SE] + lift, z1);
+        gizmos.line(nw, se, LOCK_COLOR);
+        gizmos.line(ne, sw, LOCK_COLOR);
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_egui::EguiSet;
+
+use crate::editor;
+use crate::terrain::{self, CornerHeightCache, TerrainMeshSet};
+use crate::types::TileMap;
+
+pub mod auto_ramp;
+pub mod clone;
+pub mod cursor;
+pub mod decal;
+pub mod edge_ramp;
+pub mod gradient;
+pub mod light;
+pub mod lock;
+pub mod movement_cost;
+pub mod noise;
+pub mod path;
+pub mod plateau;
+pub mod prop;
+pub mod rect_fill;
+pub mod replace;
+pub mod road;
+pub mod scatter;
+pub mod sculpt;
+pub mod smooth;
+pub mod start;
+pub mod transform;
+pub mod trigger;
+pub mod variation;
+pub mod vegetation;
+pub mod vertex;
+pub mod walkability;
+
+/// Stroke-based editing tools that turn a mouse drag into map edits.
+pub struct ToolsPlugin;
+
+impl Plugin for ToolsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                road::draw_road,
+                edge_ramp::paint_edge_ramps,
+                plateau::raise_plateau,
+                rect_fill::fill_rect,
+                clone::paint_clone,
+                gradient::apply_gradient,
+                auto_ramp::connect_with_ramps,
+                noise::paint_noise,
+                sculpt::paint_sculpt,
+                smooth::paint_smooth,
+                vertex::sculpt_vertices,
+                scatter::paint_scatter,
+                lock::paint_locks,
+                (
+                    movement_cost::paint_movement_cost,
+                    walkability::paint_walkability,
+                ),
+                trigger::paint_triggers,
+                start::place_start,
+                light::place_light,
+                vegetation::paint_vegetation,
+                decal::place_decal,
+                prop::edit_props,
+            )
+                .in_set(TerrainMeshSet::Edit)
+                .after(editor::update_hover),
+        )
+        .add_systems(
+            Update,
+            (
+                lock::draw_lock_overlay,
+                movement_cost::draw_movement_cost_overlay,
+                walkability::draw_walkability_overlay,
+                path::preview_path,
+                prop::draw_prop_gizmos,
+                cursor::draw_brush_cursor,
+            )
+                .after(TerrainMeshSet::Prepare),
+        )
+        .add_systems(
+            PostUpdate,
+            cursor::update_tool_cursor.after(EguiSet::ProcessOutput),
+        );
+    }
+}
+
+/// How a sculpting brush's strength fades from its centre to its edge.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Falloff {
+    /// Full strength across the whole brush.
+    Constant,
+    Linear,
+    /// Eases in and out, for rounded hills.
+    #[default]
+    Smooth,
+    /// A dome: nearly full strength until close to the edge.
+    Spherical,
+}
+
+impl Falloff {
+    pub const ALL: [Falloff; 4] = [
+        Falloff::Constant,
+        Falloff::Linear,
+        Falloff::Smooth,
+        Falloff::Spherical,
+    ];
+
+    pub fn identifier(self) -> &'static str {
+        match self {
+            Falloff::Constant => "constant",
+            Falloff::Linear => "linear",
+            Falloff::Smooth => "smooth",
+            Falloff::Spherical => "spherical",
+        }
+    }
+
+    /// Strength at `t`, the distance from the centre as a fraction of the
+    /// brush radius, from 1 at the centre towards 0 at the edge.
+    pub fn weight(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Falloff::Constant => 1.0,
+            Falloff::Linear => 1.0 - t,
+            Falloff::Smooth => 1.0 - t * t * (3.0 - 2.0 * t),
+            Falloff::Spherical => (1.0 - t * t).sqrt(),
+        }
+    }
+}
+
+/// Outline of the paint brush.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum BrushShape {
+    #[default]
+    Square,
+    Circle,
+}
+
+impl BrushShape {
+    pub const ALL: [BrushShape; 2] = [BrushShape::Square, BrushShape::Circle];
+
+    pub fn identifier(self) -> &'static str {
+        match self {
+            BrushShape::Square => "square",
+            BrushShape::Circle => "circle",
+        }
+    }
+}
+
+/// Tiles on the map covered by a brush centred on `centre` reaching
+/// `radius` tiles out, counting the centre; radius 1 is the centre alone.
+pub fn brush_footprint(
+    map: &TileMap,
+    centre: (u32, u32),
+    radius: u32,
+    shape: BrushShape,
+) -> Vec<(u32, u32)> {
+    let r = radius.saturating_sub(1) as i32;
+    (-r..=r)
+        .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+        .filter(|&(dx, dy)| shape == BrushShape::Square || dx * dx + dy * dy <= r * r)
+        .filter_map(|(dx, dy)| {
+            let (x, y) = (centre.0 as i32 + dx, centre.1 as i32 + dy);
+            (x >= 0 && y >= 0 && (x as u32) < map.width && (y as u32) < map.height)
+                .then_some((x as u32, y as u32))
+        })
+        .collect()
+}
+
+/// Tiles of a round brush of `radius` tiles centred on `centre`, each with
+/// its falloff weight. The edge ring sits just inside the falloff's end so it
+/// still gets a little of the brush.
+pub fn brush_weights(
+    map: &TileMap,
+    centre: (u32, u32),
+    radius: u32,
+    falloff: Falloff,
+) -> Vec<((u32, u32), f32)> {
+    let r = radius as i32;
+    let reach = radius as f32 + 1.0;
+    (-r..=r)
+        .flat_map(|dy| {
+            (-r..=r)
+                .filter(move |dx| dx * dx + dy * dy <= r * r)
+                .map(move |dx| (dx, dy))
+        })
+        .filter_map(|(dx, dy)| {
+            let (x, y) = (centre.0 as i32 + dx, centre.1 as i32 + dy);
+            if x < 0 || y < 0 || x as u32 >= map.width || y as u32 >= map.height {
+                return None;
+            }
+            let distance = ((dx * dx + dy * dy) as f32).sqrt();
+            Some(((x as u32, y as u32), falloff.weight(distance / reach)))
+        })
+        .collect()
+}
+
+/// Tiles visited when stepping from `from` to `to` with only orthogonal
+/// moves, excluding `from`. Drags skip tiles when the cursor moves fast, and
+/// 4-connected steps keep every pair of consecutive tiles sharing an edge.
+pub fn orthogonal_steps(from: (u32, u32), to: (u32, u32)) -> Vec<(u32, u32)> {
+    let (mut x, mut y) = (from.0 as i32, from.1 as i32);
+    let (tx, ty) = (to.0 as i32, to.1 as i32);
+    let (dx, dy) = ((tx - x).abs(), (ty - y).abs());
+    let (sx, sy) = ((tx - x).signum(), (ty - y).signum());
+
+    let mut steps = Vec::with_capacity((dx + dy) as usize);
+    let mut error = dx - dy;
+    while (x, y) != (tx, ty) {
+        // Advance along whichever axis keeps the path closest to the line.
+        let step_x = if x == tx {
+            false
+        } else if y == ty {
+            true
+        } else {
+            2 * error > -dy
+        };
+        if step_x {
+            error -= dy;
+            x += sx;
+        } else {
+            error += dx;
+            y += sy;
+        }
+        steps.push((x as u32, y as u32));
+    }
+    steps
+}
+
+/// Draws a tile's outline just above its top surface, for tool previews.
+pub fn outline_tile(
+    gizmos: &mut Gizmos,
+    heights: &CornerHeightCache,
+    x: u32,
+    y: u32,
+    color: Color,
+) {
+    let Some(corners) = heights.corners(x, y) else {
+        return;
+    };
+    let offset = 0.03;
+    let x0 = x as f32 * heights.tile_size();
+    let x1 = x0 + heights.tile_size();
+    let z0 = y as f32 * heights.tile_size();
+    let z1 = z0 + heights.tile_size();
+    gizmos.linestrip(
+        [
+            Vec3::new(x0, corners[terrain::CORNER_NW] + offset, z0),
+            Vec3::new(x1, corners[terrain::CORNER_NE] + offset, z0),
+            Vec3::new(x1, corners[terrain::CORNER_SE] + offset, z1),
+            Vec3::new(x0, corners[terrain::CORNER_SW] + offset, z1),
+            Vec3::new(x0, corners[terrain::CORNER_NW] + offset, z0),
+        ],
+        color,
+    );
+}
+
+/// Draws the boundary of a group of tiles `lift` above their top surfaces,
+/// following the terrain. Holes have no surface, so the boundary runs around
+/// them.
+pub fn outline_region<G: GizmoConfigGroup>(
+    gizmos: &mut Gizmos<G>,
+    heights: &CornerHeightCache,
+    tiles: impl IntoIterator<Item = (u32, u32)>,
+    lift: f32,
+    color: Color,
+) {
+    let inside: HashMap<(u32, u32), [f32; 4]> = tiles
+        .into_iter()
+        .filter_map(|(x, y)| Some(((x, y), heights.corners(x, y)?)))
+        .collect();
+    let is_outside = |x: u32, dx: i32, y: u32, dy: i32| {
+        let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+        nx < 0 || ny < 0 || !inside.contains_key(&(nx as u32, ny as u32))
+    };
+
+    let tile_size = heights.tile_size();
+    for (&(x, y), corners) in &inside {
+        let x0 = x as f32 * tile_size;
+        let x1 = x0 + tile_size;
+        let z0 = y as f32 * tile_size;
+        let z1 = z0 + tile_size;
+        let nw = Vec3::new(x0, corners[terrain::CORNER_NW] + lift, z0);
+        let ne = Vec3::new(x1, corners[terrain::CORNER_NE] + lift, z0);
+        let se = Vec3::new(x1, corners[terrain::CORNER_SE] + lift, z1);
+        let sw = Vec3::new(x0, corners[terrain::CORNER_SW] + lift, z1);
+        for (dx, dy, start, end) in [
+            (0, -1, nw, ne),
+            (1, 0, ne, se),
+            (0, 1, se, sw),
+            (-1, 0, sw, nw),
+        ] {
+            if is_outside(x, dx, y, dy) {
+                gizmos.line(start, end, color);
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use super::outline_tile;
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::input;
+use crate::terrain::CornerHeightCache;
+use crate::types::{MAX_MOVEMENT_COST, MIN_MOVEMENT_COST};
+
+const CHEAP_COLOR: Color = Color::srgb(0.2, 0.9, 0.3);
+const EXPENSIVE_COLOR: Color = Color::srgb(0.9, 0.2, 0.2);
+
+/// Paints the selected movement cost under the cursor while dragging;
+/// holding Ctrl resets tiles to their type's default.
+pub fn paint_movement_cost(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<EditorState>,
+    mut edits: EventWriter<MapEdit>,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::MovementCost || !buttons.pressed(MouseButton::Left) {
+        return;
+    }
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let Some((x, y)) = state.hover else {
+        return;
+    };
+    let cost = if input::erase_held(&keys) {
+        None
+    } else {
+        Some(state.movement_cost)
+    };
+    let current = state.map.get(x, y);
+    if current.movement_cost != cost {
+        let mut tile = current.clone();
+        tile.movement_cost = cost;
+        edits.send(MapEdit::SetTile { x, y, tile });
+    }
+}
+
+/// Tints every tile outline from green to red by its effective cost while the
+/// movement cost tool is active.
+pub fn draw_movement_cost_overlay(
+    mut gizmos: Gizmos,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+) {
+    if state.current_tool != EditorTool::MovementCost {
+        return;
+    }
+    let span = (MAX_MOVEMENT_COST - MIN_MOVEMENT_COST) as f32;
+    for (idx, tile) in state.map.tiles.iter().enumerate() {
+        let cost = tile.movement_cost();
+        if cost == 0 {
+            continue;
+        }
+        let x = idx as u32 % state.map.width;
+        let y = idx as u32 / state.map.width;
+        let t = (cost.saturating_sub(MIN_MOVEMENT_COST)) as f32 / span;
+        let color = CHEAP_COLOR.mix(&EXPENSIVE_COLOR, t);
+        outline_tile(&mut gizmos, &heights, x, y, color);
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use super::{brush_weights, outline_tile};
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::terrain::CornerHeightCache;
+use crate::types::{MAX_ELEVATION, MIN_ELEVATION};
+
+const PREVIEW_COLOR: Color = Color::srgb(0.9, 0.5, 1.0);
+/// Noise features per tile; about one bump every four tiles.
+const FREQUENCY: f32 = 0.25;
+
+/// Tiles already displaced by the stroke in progress.
+#[derive(Default)]
+pub struct NoiseStroke {
+    touched: HashSet<(u32, u32)>,
+}
+
+/// Bumps elevations under the brush by seeded value noise scaled to
+/// `EditorState::noise_amplitude` levels and faded towards the brush edge by
+/// `EditorState::brush_falloff`. Each tile is displaced once per
+/// stroke, and the noise depends only on the seed and position, so
+/// overlapping strokes stay coherent.
+#[allow(clippy::too_many_arguments)]
+pub fn paint_noise(
+    buttons: Res<ButtonInput<MouseButton>>,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    mut stroke: Local<NoiseStroke>,
+    mut edits: EventWriter<MapEdit>,
+    mut gizmos: Gizmos,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Noise {
+        stroke.touched.clear();
+        return;
+    }
+    if !buttons.pressed(MouseButton::Left) {
+        stroke.touched.clear();
+    }
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let Some((cx, cy)) = state.hover else {
+        return;
+    };
+
+    let footprint = brush_weights(
+        &state.map,
+        (cx, cy),
+        state.noise_radius,
+        state.brush_falloff,
+    );
+
+    if !buttons.pressed(MouseButton::Left) {
+        for &((x, y), _) in &footprint {
+            outline_tile(&mut gizmos, &heights, x, y, PREVIEW_COLOR);
+        }
+        return;
+    }
+
+    for ((x, y), weight) in footprint {
+        if !stroke.touched.insert((x, y)) {
+            continue;
+        }
+        let offset = value_noise(state.noise_seed, x as f32 * FREQUENCY, y as f32 * FREQUENCY)
+            * state.noise_amplitude as f32
+            * weight;
+        let offset = offset.round() as i8;
+        if offset == 0 {
+            continue;
+        }
+        let mut tile = state.map.get(x, y).clone();
+        tile.elevation = (tile.elevation + offset).clamp(MIN_ELEVATION, MAX_ELEVATION);
+        if tile != *state.map.get(x, y) {
+            edits.send(MapEdit::SetTile { x, y, tile });
+        }
+    }
+}
+
+/// Smoothly interpolated lattice noise in `-1.0..=1.0`.
+pub fn value_noise(seed: u64, x: f32, y: f32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (sx, sy) = (fx * fx * (3.0 - 2.0 * fx), fy * fy * (3.0 - 2.0 * fy));
+    let (ix, iy) = (x0 as i64, y0 as i64);
+
+    let corner = |dx: i64, dy: i64| -> f32 {
+        let key = ((ix + dx) as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ ((iy + dy) as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+        fastrand::Rng::with_seed(seed ^ key).f32() * 2.0 - 1.0
+    };
+
+    let north = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * sx;
+    let south = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * sx;
+    north + (south - north) * sy
+}
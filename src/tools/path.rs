@@ -0,0 +1,82 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use crate::editor::{EditorState, EditorTool};
+use crate::terrain::{CornerHeightCache, TerrainChanged};
+
+const PATH_COLOR: Color = Color::srgb(0.3, 0.9, 1.0);
+const UNREACHABLE_COLOR: Color = Color::srgb(1.0, 0.3, 0.3);
+
+/// Endpoints picked with the path tool and the route found between them.
+#[derive(Default)]
+pub struct PathPreview {
+    from: Option<(u32, u32)>,
+    to: Option<(u32, u32)>,
+    path: Option<Vec<(u32, u32)>>,
+}
+
+/// Click a start tile, then a goal tile, to preview the cheapest walkable
+/// route between them. The route follows map edits until new endpoints are
+/// picked.
+pub fn preview_path(
+    buttons: Res<ButtonInput<MouseButton>>,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    mut changed: EventReader<TerrainChanged>,
+    mut preview: Local<PathPreview>,
+    mut gizmos: Gizmos,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Path {
+        *preview = PathPreview::default();
+        return;
+    }
+
+    let mut recompute = !changed.is_empty();
+    changed.clear();
+
+    let clicked = state
+        .hover
+        .filter(|_| buttons.just_pressed(MouseButton::Left))
+        .filter(|_| !egui.ctx_mut().wants_pointer_input());
+    if let Some(hover) = clicked {
+        if preview.from.is_none() || preview.to.is_some() {
+            *preview = PathPreview {
+                from: Some(hover),
+                ..default()
+            };
+        } else {
+            preview.to = Some(hover);
+            recompute = true;
+        }
+    }
+
+    let (Some(from), Some(to)) = (preview.from, preview.to) else {
+        if let Some((x, y)) = preview.from {
+            super::outline_tile(&mut gizmos, &heights, x, y, PATH_COLOR);
+        }
+        return;
+    };
+    if recompute {
+        preview.path = state.map.reachability().path(from, to);
+    }
+
+    match preview.path.as_ref() {
+        Some(path) => {
+            let lift = 0.1;
+            gizmos.linestrip(
+                path.iter().filter_map(|&(x, y)| {
+                    let center_x = (x as f32 + 0.5) * heights.tile_size();
+                    let center_z = (y as f32 + 0.5) * heights.tile_size();
+                    let height = heights.height_at(center_x, center_z)?;
+                    Some(Vec3::new(center_x, height + lift, center_z))
+                }),
+                PATH_COLOR,
+            );
+        }
+        None => {
+            super::outline_tile(&mut gizmos, &heights, from.0, from.1, UNREACHABLE_COLOR);
+            super::outline_tile(&mut gizmos, &heights, to.0, to.1, UNREACHABLE_COLOR);
+        }
+    }
+}
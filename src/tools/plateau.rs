@@ -0,0 +1,128 @@
+use std::collections::{HashSet, VecDeque};
+
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use super::{orthogonal_steps, outline_tile};
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::terrain::CornerHeightCache;
+use crate::types::{MAX_ELEVATION, MIN_ELEVATION, RampDirection, TileMap, TileType};
+
+const OUTLINE_COLOR: Color = Color::srgb(0.2, 0.8, 1.0);
+/// Texture given to the rim of a raised plateau. It matches the rock used for
+/// cliff walls, so the rim reads as the top of the cliff.
+const RIM_TEXTURE: TileType = TileType::Rock;
+
+/// Raises a region drawn as a closed outline. The outline is closed
+/// automatically on release; the outline and everything it encloses go up by
+/// `EditorState::plateau_steps` and the rim tiles get the cliff texture.
+pub fn raise_plateau(
+    buttons: Res<ButtonInput<MouseButton>>,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    mut outline: Local<Vec<(u32, u32)>>,
+    mut edits: EventWriter<MapEdit>,
+    mut gizmos: Gizmos,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Plateau {
+        outline.clear();
+        return;
+    }
+
+    if buttons.pressed(MouseButton::Left) {
+        if outline.is_empty() && egui.ctx_mut().wants_pointer_input() {
+            return;
+        }
+        if let Some(hover) = state.hover {
+            match outline.last().copied() {
+                None => outline.push(hover),
+                Some(last) if last != hover => outline.extend(orthogonal_steps(last, hover)),
+                Some(_) => {}
+            }
+        }
+        for &(x, y) in outline.iter() {
+            outline_tile(&mut gizmos, &heights, x, y, OUTLINE_COLOR);
+        }
+        return;
+    }
+
+    if outline.is_empty() {
+        return;
+    }
+    let first = outline[0];
+    let last = outline[outline.len() - 1];
+    let closing = orthogonal_steps(last, first);
+    outline.extend(closing);
+
+    let region = enclosed_region(&state.map, &outline);
+    outline.clear();
+
+    for &(x, y) in &region {
+        let mut tile = state.map.get(x, y).clone();
+        tile.elevation = tile
+            .elevation
+            .saturating_add(state.plateau_steps)
+            .clamp(MIN_ELEVATION, MAX_ELEVATION);
+        if is_rim(&state.map, &region, x, y) {
+            tile.tile_type = RIM_TEXTURE;
+        }
+        edits.send(MapEdit::SetTile { x, y, tile });
+    }
+}
+
+/// The outline tiles plus every tile they enclose, found by flooding in from
+/// the map border and keeping whatever the flood could not reach.
+fn enclosed_region(map: &TileMap, outline: &[(u32, u32)]) -> HashSet<(u32, u32)> {
+    if map.width == 0 || map.height == 0 {
+        return HashSet::new();
+    }
+    let walls: HashSet<(u32, u32)> = outline.iter().copied().collect();
+    let mut outside = HashSet::new();
+    let mut queue = VecDeque::new();
+
+    for x in 0..map.width {
+        for y in [0, map.height - 1] {
+            queue.push_back((x, y));
+        }
+    }
+    for y in 0..map.height {
+        for x in [0, map.width - 1] {
+            queue.push_back((x, y));
+        }
+    }
+
+    while let Some((x, y)) = queue.pop_front() {
+        if walls.contains(&(x, y)) || !outside.insert((x, y)) {
+            continue;
+        }
+        for (nx, ny) in neighbors(map, x, y) {
+            if !outside.contains(&(nx, ny)) {
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+
+    let mut region = HashSet::new();
+    for y in 0..map.height {
+        for x in 0..map.width {
+            if !outside.contains(&(x, y)) {
+                region.insert((x, y));
+            }
+        }
+    }
+    region
+}
+
+fn is_rim(map: &TileMap, region: &HashSet<(u32, u32)>, x: u32, y: u32) -> bool {
+    neighbors(map, x, y).any(|n| !region.contains(&n))
+}
+
+fn neighbors(map: &TileMap, x: u32, y: u32) -> impl Iterator<Item = (u32, u32)> + '_ {
+    RampDirection::ALL.into_iter().filter_map(move |dir| {
+        let (dx, dy) = dir.offset();
+        let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+        (nx >= 0 && ny >= 0 && (nx as u32) < map.width && (ny as u32) < map.height)
+            .then_some((nx as u32, ny as u32))
+    })
+}
@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::input;
+use crate::props::{PlacedProp, prop_position};
+use crate::spatial::SpatialIndex;
+use crate::types::MapProp;
+
+const MARKER_COLOR: Color = Color::srgb(0.6, 0.8, 0.95);
+const SELECTED_COLOR: Color = Color::srgb(1.0, 0.85, 0.3);
+const ERASE_COLOR: Color = Color::srgb(0.95, 0.3, 0.3);
+/// Tiles from a prop's centre within which a click picks it up.
+const PICK_RADIUS: f32 = 0.4;
+/// Tiles from the selected prop's centre to its rotation ring, at scale 1.
+const RING_RADIUS: f32 = 0.9;
+/// Tiles either side of the ring that still grab it.
+const RING_REACH: f32 = 0.15;
+
+/// What the held mouse button is doing to a prop.
+#[derive(Default)]
+pub enum PropDrag {
+    #[default]
+    None,
+    /// Keeps the prop at `offset` tiles from the cursor.
+    Move { index: usize, offset: Vec2 },
+    /// Turns the prop by however far the cursor has swung round it since
+    /// the drag began at `start_angle`.
+    Rotate {
+        index: usize,
+        start_angle: f32,
+        start_rotation: f32,
+    },
+}
+
+/// Click bare ground to place the chosen prop, click a prop to select it and
+/// drag to move it, or drag the selected prop's ring to turn it. Ctrl-click
+/// removes a prop.
+#[allow(clippy::too_many_arguments)]
+pub fn edit_props(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut state: ResMut<EditorState>,
+    mut edits: EventWriter<MapEdit>,
+    mut drag: Local<PropDrag>,
+    mut egui: EguiContexts,
+    spatial: Res<SpatialIndex>,
+    placed: Query<&PlacedProp>,
+) {
+    if state.current_tool != EditorTool::Prop || !buttons.pressed(MouseButton::Left) {
+        *drag = PropDrag::None;
+        return;
+    }
+    let Some(point) = state.hover_point else {
+        return;
+    };
+    let cursor = Vec2::new(point.x, point.z) / state.map.tile_size;
+
+    match *drag {
+        PropDrag::Move { index, offset } => {
+            if let Some(prop) = state.map.props.get(index) {
+                let at = cursor + offset;
+                let prop = MapProp {
+                    x: at.x,
+                    y: at.y,
+                    ..prop.clone()
+                };
+                edits.send(MapEdit::SetProp {
+                    index,
+                    prop: Some(prop),
+                });
+            }
+            return;
+        }
+        PropDrag::Rotate {
+            index,
+            start_angle,
+            start_rotation,
+        } => {
+            if let Some(prop) = state.map.props.get(index) {
+                let angle = heading(cursor - Vec2::new(prop.x, prop.y));
+                let prop = MapProp {
+                    rotation: (start_rotation + angle - start_angle).rem_euclid(360.0),
+                    ..prop.clone()
+                };
+                edits.send(MapEdit::SetProp {
+                    index,
+                    prop: Some(prop),
+                });
+            }
+            return;
+        }
+        PropDrag::None => {}
+    }
+    if !buttons.just_pressed(MouseButton::Left) || egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+
+    // The index lags the map by a frame, so a prop may just have gone.
+    let picked = prop_at(&spatial, &placed, point, state.map.tile_size)
+        .filter(|&index| index < state.map.props.len());
+    if input::erase_held(&keys) {
+        if let Some(index) = picked {
+            edits.send(MapEdit::SetProp { index, prop: None });
+        }
+        return;
+    }
+    let selected = state
+        .selected_prop
+        .and_then(|index| Some((index, state.map.props.get(index)?)));
+    if let Some((index, prop)) = selected {
+        let from_centre = cursor - Vec2::new(prop.x, prop.y);
+        if (from_centre.length() - ring_radius(prop)).abs() <= RING_REACH {
+            *drag = PropDrag::Rotate {
+                index,
+                start_angle: heading(from_centre),
+                start_rotation: prop.rotation,
+            };
+            return;
+        }
+    }
+    if let Some(index) = picked {
+        let prop = &state.map.props[index];
+        *drag = PropDrag::Move {
+            index,
+            offset: Vec2::new(prop.x, prop.y) - cursor,
+        };
+        state.selected_prop = Some(index);
+        return;
+    }
+    if state.prop.asset.is_empty() {
+        return;
+    }
+    state.selected_prop = Some(state.map.props.len());
+    edits.send(MapEdit::PlaceProp(MapProp {
+        x: cursor.x,
+        y: cursor.y,
+        ..state.prop.clone()
+    }));
+}
+
+/// Marks every prop while the Prop tool is active, since props without a
+/// registered scene are otherwise invisible, and draws the selected prop's
+/// handles: a disc to drag it by and a ring to turn it with.
+pub fn draw_prop_gizmos(
+    mut gizmos: Gizmos,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<EditorState>,
+    spatial: Res<SpatialIndex>,
+    placed: Query<&PlacedProp>,
+) {
+    if state.current_tool != EditorTool::Prop {
+        return;
+    }
+    let tile_size = state.map.tile_size;
+    let hovered = state
+        .hover_point
+        .filter(|_| input::erase_held(&keys))
+        .and_then(|point| prop_at(&spatial, &placed, point, tile_size));
+    for (index, prop) in state.map.props.iter().enumerate() {
+        let Some(position) = prop_position(&state.map, prop) else {
+            continue;
+        };
+        let centre = position + Vec3::Y * 0.05;
+        let color = if hovered == Some(index) {
+            ERASE_COLOR
+        } else if state.selected_prop == Some(index) {
+            SELECTED_COLOR
+        } else {
+            MARKER_COLOR
+        };
+        let facing = Quat::from_rotation_y(-prop.rotation.to_radians()) * Vec3::NEG_Z;
+        gizmos.circle(centre, Dir3::Y, PICK_RADIUS * tile_size, color);
+        gizmos.arrow(
+            centre,
+            centre + facing * PICK_RADIUS * 1.5 * tile_size,
+            color,
+        );
+        if state.selected_prop == Some(index) {
+            let ring = ring_radius(prop) * tile_size;
+            gizmos.circle(centre, Dir3::Y, ring, color);
+            gizmos.sphere(
+                centre + facing * ring,
+                Quat::IDENTITY,
+                0.08 * tile_size,
+                color,
+            );
+        }
+    }
+}
+
+/// The prop whose centre is nearest `point`, if one is close enough to
+/// pick. Props over holes have no entity and cannot be picked.
+fn prop_at(
+    spatial: &SpatialIndex,
+    placed: &Query<&PlacedProp>,
+    point: Vec3,
+    tile_size: f32,
+) -> Option<usize> {
+    let entity = spatial.nearest(point, PICK_RADIUS * tile_size)?;
+    placed.get(entity).ok().map(|placed| placed.0)
+}
+
+fn ring_radius(prop: &MapProp) -> f32 {
+    RING_RADIUS * prop.scale.max(0.5)
+}
+
+/// Degrees clockwise from north of a direction across the map.
+fn heading(direction: Vec2) -> f32 {
+    direction.x.atan2(-direction.y).to_degrees()
+}
@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use super::outline_region;
+use crate::editor::{self, EditorState, EditorTool, MapEdit};
+use crate::terrain::CornerHeightCache;
+
+const PREVIEW_COLOR: Color = Color::srgb(1.0, 0.9, 0.2);
+
+/// Corners of the rectangle being dragged out.
+#[derive(Default)]
+pub struct RectDrag {
+    start: Option<(u32, u32)>,
+    /// Last hovered tile, kept while the cursor is off the terrain.
+    end: (u32, u32),
+}
+
+/// Tiles of the rectangle spanned by two opposite corners, inclusive.
+fn rect_tiles(a: (u32, u32), b: (u32, u32)) -> impl Iterator<Item = (u32, u32)> {
+    let (x0, x1) = (a.0.min(b.0), a.0.max(b.0));
+    let (y0, y1) = (a.1.min(b.1), a.1.max(b.1));
+    (y0..=y1).flat_map(move |y| (x0..=x1).map(move |x| (x, y)))
+}
+
+/// Drag to span a rectangle; on release every tile in it is painted with
+/// the current kind, elevation and texture. Escape cancels the drag.
+#[allow(clippy::too_many_arguments)]
+pub fn fill_rect(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    mut drag: Local<RectDrag>,
+    mut edits: EventWriter<MapEdit>,
+    mut gizmos: Gizmos,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::RectFill || keys.just_pressed(KeyCode::Escape) {
+        drag.start = None;
+        return;
+    }
+
+    if buttons.pressed(MouseButton::Left) {
+        let hover = state.hover;
+        match (drag.start, hover) {
+            (None, Some(hover)) if buttons.just_pressed(MouseButton::Left) => {
+                if egui.ctx_mut().wants_pointer_input() {
+                    return;
+                }
+                *drag = RectDrag {
+                    start: Some(hover),
+                    end: hover,
+                };
+            }
+            (Some(_), Some(hover)) => drag.end = hover,
+            _ => {}
+        }
+        if let Some(start) = drag.start {
+            outline_region(
+                &mut gizmos,
+                &heights,
+                rect_tiles(start, drag.end),
+                0.03,
+                PREVIEW_COLOR,
+            );
+        }
+        return;
+    }
+
+    let Some(start) = drag.start.take() else {
+        return;
+    };
+    edits.send_batch(rect_tiles(start, drag.end).filter_map(|(x, y)| {
+        let tile = editor::painted_tile(&state, x, y)?;
+        Some(MapEdit::SetTile { x, y, tile })
+    }));
+}
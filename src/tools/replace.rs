@@ -0,0 +1,28 @@
+use crate::editor::MapEdit;
+use crate::types::{TileKind, TileMap, TileType};
+
+/// Edits turning every `from` tile in `tiles` into `to`, keeping its
+/// height, shape and variation. Holes and locked tiles are skipped.
+pub fn replace_texture(
+    map: &TileMap,
+    tiles: impl IntoIterator<Item = (u32, u32)>,
+    from: TileType,
+    to: TileType,
+) -> Vec<MapEdit> {
+    if from == to {
+        return Vec::new();
+    }
+    tiles
+        .into_iter()
+        .filter(|&(x, y)| x < map.width && y < map.height)
+        .filter_map(|(x, y)| {
+            let current = map.get(x, y);
+            if current.tile_type != from || current.kind == TileKind::Empty || current.locked {
+                return None;
+            }
+            let mut tile = current.clone();
+            tile.tile_type = to;
+            Some(MapEdit::SetTile { x, y, tile })
+        })
+        .collect()
+}
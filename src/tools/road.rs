@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use super::orthogonal_steps;
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::input;
+use crate::types::{ROAD_MOVEMENT_COST, RampDirection, RoadSurface, Tile, TileKind, TileMap};
+
+/// The most recently paved tile of the drag in progress.
+#[derive(Clone, Copy)]
+pub struct RoadTile {
+    x: u32,
+    y: u32,
+    elevation: i8,
+    ramp: bool,
+}
+
+/// Draws the selected road surface along the dragged path; the renderer picks
+/// each tile's straight, bend or junction piece from its neighbours. Each
+/// step may climb or drop at most one elevation level; larger differences
+/// are levelled out and every one-level step becomes a ramp on the higher
+/// tile, so the road stays walkable across cliff lines. Holding Ctrl removes
+/// roads along the drag instead, leaving the ground as it is.
+pub fn draw_road(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<EditorState>,
+    mut last: Local<Option<RoadTile>>,
+    mut edits: EventWriter<MapEdit>,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Road || !buttons.pressed(MouseButton::Left) {
+        *last = None;
+        return;
+    }
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let Some((x, y)) = state.hover else {
+        return;
+    };
+    let surface = state.current_road;
+
+    if input::erase_held(&keys) {
+        let from = last.map_or((x, y), |previous| (previous.x, previous.y));
+        let path = std::iter::once((x, y)).chain(orthogonal_steps(from, (x, y)));
+        edits.send_batch(
+            path.filter(|&(x, y)| state.map.road(x, y).is_some())
+                .map(|(x, y)| MapEdit::SetRoad {
+                    x,
+                    y,
+                    surface: None,
+                }),
+        );
+        *last = Some(RoadTile {
+            x,
+            y,
+            elevation: state.map.get(x, y).elevation,
+            ramp: false,
+        });
+        return;
+    }
+
+    let Some(mut previous) = *last else {
+        let elevation = state.map.get(x, y).elevation;
+        edits.send_batch(road_edits(&state.map, x, y, elevation, surface, None));
+        *last = Some(RoadTile {
+            x,
+            y,
+            elevation,
+            ramp: false,
+        });
+        return;
+    };
+    if (previous.x, previous.y) == (x, y) {
+        return;
+    }
+
+    for (sx, sy) in orthogonal_steps((previous.x, previous.y), (x, y)) {
+        let ground = state.map.get(sx, sy).elevation;
+        let mut elevation = ground.clamp(previous.elevation - 1, previous.elevation + 1);
+        // A ramp can only face one way; a tile that already climbs from the
+        // road behind it cannot also descend to the road ahead.
+        if previous.ramp && elevation < previous.elevation {
+            elevation = previous.elevation;
+        }
+
+        let towards_previous = direction_between((sx, sy), (previous.x, previous.y));
+        let climbs = elevation > previous.elevation;
+        edits.send_batch(road_edits(
+            &state.map,
+            sx,
+            sy,
+            elevation,
+            surface,
+            towards_previous.filter(|_| climbs),
+        ));
+
+        if elevation < previous.elevation {
+            let towards_next = direction_between((previous.x, previous.y), (sx, sy));
+            edits.send_batch(road_edits(
+                &state.map,
+                previous.x,
+                previous.y,
+                previous.elevation,
+                surface,
+                towards_next,
+            ));
+        }
+
+        previous = RoadTile {
+            x: sx,
+            y: sy,
+            elevation,
+            ramp: climbs,
+        };
+    }
+
+    *last = Some(previous);
+}
+
+/// Levels the tile at (`x`, `y`) for the road, keeping its texture, and
+/// draws the road on it.
+fn road_edits(
+    map: &TileMap,
+    x: u32,
+    y: u32,
+    elevation: i8,
+    surface: RoadSurface,
+    ramp_direction: Option<RampDirection>,
+) -> [MapEdit; 2] {
+    let tile = Tile {
+        kind: if ramp_direction.is_some() {
+            TileKind::Ramp
+        } else {
+            TileKind::Floor
+        },
+        elevation,
+        ramp_direction,
+        movement_cost: Some(ROAD_MOVEMENT_COST),
+        corner_offsets: [0; 4],
+        ..map.get(x, y).clone()
+    };
+    [
+        MapEdit::SetTile { x, y, tile },
+        MapEdit::SetRoad {
+            x,
+            y,
+            surface: Some(surface),
+        },
+    ]
+}
+
+fn direction_between(from: (u32, u32), to: (u32, u32)) -> Option<RampDirection> {
+    RampDirection::from_offset(to.0 as i32 - from.0 as i32, to.1 as i32 - from.1 as i32)
+}
@@ -0,0 +1,109 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use super::{BrushShape, brush_footprint, outline_tile};
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::terrain::CornerHeightCache;
+use crate::texture::registry::TerrainTextureRegistry;
+use crate::types::{TileKind, TileType};
+
+const PREVIEW_COLOR: Color = Color::srgb(0.8, 0.6, 1.0);
+
+/// How the scatter brush picks which tiles to paint and with what.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScatterSettings {
+    pub radius: u32,
+    /// Percentage of the tiles under the brush that get painted.
+    pub coverage: u8,
+    /// Draw each texture from `weights` instead of painting the selected one.
+    pub weighted: bool,
+    /// Relative chance of each texture, indexed by `TileType::as_index`.
+    pub weights: [u8; TileType::ALL.len()],
+}
+
+impl Default for ScatterSettings {
+    fn default() -> Self {
+        Self {
+            radius: 3,
+            coverage: 25,
+            weighted: false,
+            weights: [1; TileType::ALL.len()],
+        }
+    }
+}
+
+impl ScatterSettings {
+    /// A texture of `layers` drawn from the weight table, or `selected` when
+    /// scattering unweighted or every weight is zero.
+    fn pick(&self, rng: &mut fastrand::Rng, selected: TileType, layers: &[TileType]) -> TileType {
+        let total: u32 = layers
+            .iter()
+            .map(|layer| self.weights[layer.as_index()] as u32)
+            .sum();
+        if !self.weighted || total == 0 {
+            return selected;
+        }
+        let mut roll = rng.u32(..total);
+        for &tile_type in layers {
+            let weight = self.weights[tile_type.as_index()] as u32;
+            if roll < weight {
+                return tile_type;
+            }
+            roll -= weight;
+        }
+        selected
+    }
+}
+
+/// Paints textures onto a random share of the tiles under a round brush
+/// while dragging, to break up large single-texture areas. Each tile is
+/// rolled once per stroke, so holding the brush still does not fill it in.
+/// Holes are left alone.
+#[allow(clippy::too_many_arguments)]
+pub fn paint_scatter(
+    buttons: Res<ButtonInput<MouseButton>>,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    textures: Res<TerrainTextureRegistry>,
+    mut rolled: Local<HashSet<(u32, u32)>>,
+    mut edits: EventWriter<MapEdit>,
+    mut gizmos: Gizmos,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Scatter || !buttons.pressed(MouseButton::Left) {
+        rolled.clear();
+    }
+    if state.current_tool != EditorTool::Scatter || egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let Some(centre) = state.hover else {
+        return;
+    };
+
+    let settings = &state.scatter;
+    let footprint = brush_footprint(&state.map, centre, settings.radius, BrushShape::Circle);
+    if !buttons.pressed(MouseButton::Left) {
+        for &(x, y) in &footprint {
+            outline_tile(&mut gizmos, &heights, x, y, PREVIEW_COLOR);
+        }
+        return;
+    }
+
+    let layers: Vec<TileType> = textures.layers().collect();
+    let mut rng = fastrand::Rng::new();
+    for (x, y) in footprint {
+        if !rolled.insert((x, y)) || rng.u8(..100) >= settings.coverage {
+            continue;
+        }
+        let current = state.map.get(x, y);
+        let tile_type = settings.pick(&mut rng, state.current_texture, &layers);
+        if current.kind == TileKind::Empty || current.tile_type == tile_type {
+            continue;
+        }
+        let mut tile = current.clone();
+        tile.tile_type = tile_type;
+        edits.send(MapEdit::SetTile { x, y, tile });
+    }
+}
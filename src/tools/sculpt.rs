@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use super::{brush_weights, outline_tile};
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::input;
+use crate::terrain::CornerHeightCache;
+use crate::types::{MAX_ELEVATION, MIN_ELEVATION, TileKind};
+
+const RAISE_COLOR: Color = Color::srgb(1.0, 0.8, 0.3);
+const LOWER_COLOR: Color = Color::srgb(0.3, 0.7, 1.0);
+
+/// Raises tiles under the brush by `EditorState::sculpt_strength` levels at
+/// its centre, fading towards the edge by `EditorState::brush_falloff` and
+/// rounding to whole elevation steps; holding Ctrl lowers instead. Each tile
+/// moves once per stroke, so dragging over a spot doesn't pile it up.
+#[allow(clippy::too_many_arguments)]
+pub fn paint_sculpt(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    mut touched: Local<HashSet<(u32, u32)>>,
+    mut edits: EventWriter<MapEdit>,
+    mut gizmos: Gizmos,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Sculpt {
+        touched.clear();
+        return;
+    }
+    if !buttons.pressed(MouseButton::Left) {
+        touched.clear();
+    }
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let Some(centre) = state.hover else {
+        return;
+    };
+
+    let lower = input::erase_held(&keys);
+    let direction = if lower { -1.0 } else { 1.0 };
+    // Only tiles the rounded offset actually moves are part of the brush.
+    let offsets: Vec<((u32, u32), i8)> =
+        brush_weights(&state.map, centre, state.sculpt_radius, state.brush_falloff)
+            .into_iter()
+            .map(|(tile, weight)| {
+                let offset = (state.sculpt_strength as f32 * weight * direction).round() as i8;
+                (tile, offset)
+            })
+            .filter(|&(_, offset)| offset != 0)
+            .collect();
+
+    if !buttons.pressed(MouseButton::Left) {
+        let color = if lower { LOWER_COLOR } else { RAISE_COLOR };
+        for &((x, y), _) in &offsets {
+            outline_tile(&mut gizmos, &heights, x, y, color);
+        }
+        return;
+    }
+
+    for ((x, y), offset) in offsets {
+        if !touched.insert((x, y)) {
+            continue;
+        }
+        let current = state.map.get(x, y);
+        if current.kind == TileKind::Empty {
+            continue;
+        }
+        let mut tile = current.clone();
+        tile.elevation = (tile.elevation + offset).clamp(MIN_ELEVATION, MAX_ELEVATION);
+        if tile != *current {
+            edits.send(MapEdit::SetTile { x, y, tile });
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use super::{brush_weights, outline_tile};
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::terrain::{self, CornerHeightCache};
+use crate::types::{MAX_ELEVATION, MIN_ELEVATION};
+
+const SMOOTH_COLOR: Color = Color::srgb(0.5, 1.0, 0.7);
+
+/// Pulls tiles under the brush towards the mean elevation of their
+/// neighbourhood by `EditorState::smooth_strength`, scaled by the brush
+/// falloff and rounded to whole steps, so cliffs soften into terraces. Each
+/// tile moves once per stroke; drag again to smooth further.
+pub fn paint_smooth(
+    buttons: Res<ButtonInput<MouseButton>>,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    mut touched: Local<HashSet<(u32, u32)>>,
+    mut edits: EventWriter<MapEdit>,
+    mut gizmos: Gizmos,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Smooth {
+        touched.clear();
+        return;
+    }
+    if !buttons.pressed(MouseButton::Left) {
+        touched.clear();
+    }
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let Some(centre) = state.hover else {
+        return;
+    };
+
+    // Every target is measured on the map as it was before this frame's edits.
+    let targets: Vec<((u32, u32), i8)> =
+        brush_weights(&state.map, centre, state.smooth_radius, state.brush_falloff)
+            .into_iter()
+            .filter_map(|((x, y), weight)| {
+                let mean = terrain::neighborhood_mean_elevation(&state.map, x, y)?;
+                let current = state.map.get(x, y).elevation;
+                let pull = (mean - current as f32) * weight * state.smooth_strength;
+                let target = (current as f32 + pull).round() as i8;
+                let target = target.clamp(MIN_ELEVATION, MAX_ELEVATION);
+                (target != current).then_some(((x, y), target))
+            })
+            .collect();
+
+    if !buttons.pressed(MouseButton::Left) {
+        for &((x, y), _) in &targets {
+            outline_tile(&mut gizmos, &heights, x, y, SMOOTH_COLOR);
+        }
+        return;
+    }
+
+    for ((x, y), elevation) in targets {
+        if !touched.insert((x, y)) {
+            continue;
+        }
+        let mut tile = state.map.get(x, y).clone();
+        tile.elevation = elevation;
+        edits.send(MapEdit::SetTile { x, y, tile });
+    }
+}
@@ -0,0 +1,52 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::input;
+use crate::types::StartLocation;
+
+/// Click to place the active player's start location, replacing any earlier
+/// one; Ctrl-click removes the start on the clicked tile.
+pub fn place_start(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<EditorState>,
+    mut edits: EventWriter<MapEdit>,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Start || !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let Some((x, y)) = state.hover else {
+        return;
+    };
+
+    if input::erase_held(&keys) {
+        edits.send_batch(
+            state
+                .map
+                .starts
+                .iter()
+                .filter(|start| (start.x, start.y) == (x, y))
+                .map(|start| MapEdit::SetStart {
+                    player: start.player,
+                    start: None,
+                }),
+        );
+        return;
+    }
+
+    edits.send(MapEdit::SetStart {
+        player: state.active_player,
+        start: Some(StartLocation {
+            player: state.active_player,
+            team: state.start_team,
+            x,
+            y,
+            facing: state.start_facing,
+        }),
+    });
+}
@@ -0,0 +1,219 @@
+use std::collections::BTreeSet;
+
+use crate::terrain::{CORNER_NE, CORNER_NW, CORNER_SE, CORNER_SW};
+use crate::types::{RampDirection, Tile, TileMap};
+
+/// A turn or flip of the whole map. North is up, so clockwise turns the
+/// north edge to face east.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MapTransform {
+    RotateClockwise,
+    RotateCounterClockwise,
+    Rotate180,
+    /// Swaps the west and east edges.
+    MirrorHorizontal,
+    /// Swaps the north and south edges.
+    MirrorVertical,
+}
+
+impl MapTransform {
+    pub const ALL: [MapTransform; 5] = [
+        MapTransform::RotateClockwise,
+        MapTransform::RotateCounterClockwise,
+        MapTransform::Rotate180,
+        MapTransform::MirrorHorizontal,
+        MapTransform::MirrorVertical,
+    ];
+
+    /// Localization key for the action's name.
+    pub fn label_key(self) -> &'static str {
+        match self {
+            MapTransform::RotateClockwise => "transform.rotate_cw",
+            MapTransform::RotateCounterClockwise => "transform.rotate_ccw",
+            MapTransform::Rotate180 => "transform.rotate_180",
+            MapTransform::MirrorHorizontal => "transform.mirror_horizontal",
+            MapTransform::MirrorVertical => "transform.mirror_vertical",
+        }
+    }
+
+    /// The transform undoing this one.
+    pub fn inverse(self) -> MapTransform {
+        match self {
+            MapTransform::RotateClockwise => MapTransform::RotateCounterClockwise,
+            MapTransform::RotateCounterClockwise => MapTransform::RotateClockwise,
+            other => other,
+        }
+    }
+
+    fn swaps_sides(self) -> bool {
+        matches!(
+            self,
+            MapTransform::RotateClockwise | MapTransform::RotateCounterClockwise
+        )
+    }
+
+    /// Where a point `(x, y)` of a `width` by `height` area ends up, with
+    /// `(0, 0)` at the north-west corner.
+    fn point(self, x: f32, y: f32, width: f32, height: f32) -> (f32, f32) {
+        match self {
+            MapTransform::RotateClockwise => (height - y, x),
+            MapTransform::RotateCounterClockwise => (y, width - x),
+            MapTransform::Rotate180 => (width - x, height - y),
+            MapTransform::MirrorHorizontal => (width - x, y),
+            MapTransform::MirrorVertical => (x, height - y),
+        }
+    }
+
+    /// Where tile `(x, y)` of `map` ends up.
+    fn tile(self, map: &TileMap, x: u32, y: u32) -> (u32, u32) {
+        let (w, h) = (map.width - 1, map.height - 1);
+        match self {
+            MapTransform::RotateClockwise => (h.saturating_sub(y), x),
+            MapTransform::RotateCounterClockwise => (y, w.saturating_sub(x)),
+            MapTransform::Rotate180 => (w.saturating_sub(x), h.saturating_sub(y)),
+            MapTransform::MirrorHorizontal => (w.saturating_sub(x), y),
+            MapTransform::MirrorVertical => (x, h.saturating_sub(y)),
+        }
+    }
+
+    /// A direction with +y pointing south, such as a ramp's offset.
+    fn offset(self, dx: i32, dy: i32) -> (i32, i32) {
+        match self {
+            MapTransform::RotateClockwise => (-dy, dx),
+            MapTransform::RotateCounterClockwise => (dy, -dx),
+            MapTransform::Rotate180 => (-dx, -dy),
+            MapTransform::MirrorHorizontal => (-dx, dy),
+            MapTransform::MirrorVertical => (dx, -dy),
+        }
+    }
+
+    /// A heading in degrees clockwise from north, kept within 0..360.
+    fn heading(self, degrees: f32) -> f32 {
+        let turned = match self {
+            MapTransform::RotateClockwise => degrees + 90.0,
+            MapTransform::RotateCounterClockwise => degrees - 90.0,
+            MapTransform::Rotate180 => degrees + 180.0,
+            MapTransform::MirrorHorizontal => -degrees,
+            MapTransform::MirrorVertical => 180.0 - degrees,
+        };
+        turned.rem_euclid(360.0)
+    }
+
+    /// Turns and flips a tile's ramp, sculpted corners and top-face texture
+    /// so it looks the same from its new place.
+    fn tile_data(self, tile: &mut Tile) {
+        if let Some(direction) = tile.ramp_direction {
+            let (dx, dy) = self.offset(direction.offset().0, direction.offset().1);
+            tile.ramp_direction = RampDirection::from_offset(dx, dy);
+        }
+        let mut corners = [0; 4];
+        for (corner, (dx, dy)) in [
+            (CORNER_NW, (-1, -1)),
+            (CORNER_NE, (1, -1)),
+            (CORNER_SW, (-1, 1)),
+            (CORNER_SE, (1, 1)),
+        ] {
+            let target = match self.offset(dx, dy) {
+                (-1, -1) => CORNER_NW,
+                (1, -1) => CORNER_NE,
+                (-1, 1) => CORNER_SW,
+                _ => CORNER_SE,
+            };
+            corners[target] = tile.corner_offsets[corner];
+        }
+        tile.corner_offsets = corners;
+
+        // The shader mirrors the texture across the tile's north-south axis
+        // before turning it, so the map's turn or flip folds into another
+        // turn and mirror of the texture.
+        let (turns, flip) = match (self, tile.mirrored) {
+            (MapTransform::RotateClockwise, false) => (3, false),
+            (MapTransform::RotateClockwise, true) => (1, false),
+            (MapTransform::RotateCounterClockwise, false) => (1, false),
+            (MapTransform::RotateCounterClockwise, true) => (3, false),
+            (MapTransform::Rotate180, _) => (2, false),
+            (MapTransform::MirrorHorizontal, _) => (0, true),
+            (MapTransform::MirrorVertical, _) => (2, true),
+        };
+        tile.rotation = (tile.rotation % 4 + turns) % 4;
+        tile.mirrored ^= flip;
+    }
+
+    /// Moves every row-major cell of `cells` to its new place.
+    fn cells<T: Clone>(self, map: &TileMap, cells: &[T]) -> Vec<T> {
+        if cells.len() != map.tiles.len() {
+            return cells.to_vec();
+        }
+        let mut out = cells.to_vec();
+        let new_width = if self.swaps_sides() {
+            map.height
+        } else {
+            map.width
+        };
+        for y in 0..map.height {
+            for x in 0..map.width {
+                let (nx, ny) = self.tile(map, x, y);
+                out[(ny * new_width + nx) as usize] = cells[map.idx(x, y)].clone();
+            }
+        }
+        out
+    }
+
+    fn tiles(self, map: &TileMap, tiles: &[Tile]) -> Vec<Tile> {
+        let mut out = self.cells(map, tiles);
+        for tile in &mut out {
+            self.tile_data(tile);
+        }
+        out
+    }
+
+    /// `map` turned or flipped as a whole, including everything placed on
+    /// it. Prop scenes are only turned: a mirrored map keeps them unmirrored,
+    /// facing the mirrored way.
+    pub fn apply(self, map: &TileMap) -> TileMap {
+        let mut out = map.clone();
+        if map.width == 0 || map.height == 0 {
+            return out;
+        }
+        let (width, height) = (map.width as f32, map.height as f32);
+        if self.swaps_sides() {
+            out.width = map.height;
+            out.height = map.width;
+        }
+        out.tiles = self.tiles(map, &map.tiles);
+        out.upper_layers = map
+            .upper_layers
+            .iter()
+            .map(|layer| self.tiles(map, layer))
+            .collect();
+        out.vegetation = self.cells(map, &map.vegetation);
+        out.roads = self.cells(map, &map.roads);
+        out.walkability = self.cells(map, &map.walkability);
+
+        for trigger in &mut out.triggers {
+            trigger.tiles = trigger
+                .tiles
+                .iter()
+                .map(|&(x, y)| self.tile(map, x, y))
+                .collect::<BTreeSet<_>>();
+        }
+        for start in &mut out.starts {
+            (start.x, start.y) = self.tile(map, start.x, start.y);
+            start.facing = self.heading(start.facing);
+        }
+        for light in &mut out.lights {
+            (light.x, light.y) = self.tile(map, light.x, light.y);
+            light.facing = self.heading(light.facing);
+        }
+        for decal in &mut out.decals {
+            (decal.x, decal.y) = self.point(decal.x, decal.y, width, height);
+            decal.rotation = self.heading(decal.rotation);
+        }
+        for prop in &mut out.props {
+            (prop.x, prop.y) = self.point(prop.x, prop.y, width, height);
+            prop.rotation = self.heading(prop.rotation);
+        }
+        out.weather.wind_direction = self.heading(map.weather.wind_direction);
+        out
+    }
+}
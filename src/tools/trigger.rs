@@ -0,0 +1,37 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::input;
+
+/// Paints the active trigger area under the cursor while dragging; holding
+/// Ctrl erases tiles from it instead.
+pub fn paint_triggers(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<EditorState>,
+    mut edits: EventWriter<MapEdit>,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Trigger || !buttons.pressed(MouseButton::Left) {
+        return;
+    }
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let (Some((x, y)), Some(trigger)) = (state.hover, state.active_trigger) else {
+        return;
+    };
+    let Some(area) = state.map.triggers.get(trigger) else {
+        return;
+    };
+    let inside = !input::erase_held(&keys);
+    if area.tiles.contains(&(x, y)) != inside {
+        edits.send(MapEdit::SetTriggerTile {
+            trigger,
+            x,
+            y,
+            inside,
+        });
+    }
+}
@@ -0,0 +1,31 @@
+use crate::editor::MapEdit;
+use crate::types::{TileMap, TileType};
+
+/// Distinct texture offsets a tile can pick from.
+pub const VARIATION_COUNT: u8 = 16;
+
+/// Re-rolls the texture offset and rotation of every `tile_type` tile in
+/// `tiles`. Each tile's roll depends only on the seed and its coordinates, so
+/// the same seed always produces the same result regardless of order.
+pub fn reroll(
+    map: &TileMap,
+    tiles: impl IntoIterator<Item = (u32, u32)>,
+    tile_type: TileType,
+    seed: u64,
+) -> Vec<MapEdit> {
+    tiles
+        .into_iter()
+        .filter(|&(x, y)| x < map.width && y < map.height)
+        .filter_map(|(x, y)| {
+            let current = map.get(x, y);
+            if current.tile_type != tile_type {
+                return None;
+            }
+            let mut rng = fastrand::Rng::with_seed(seed ^ ((x as u64) << 32 | y as u64));
+            let mut tile = current.clone();
+            tile.variation = rng.u8(..VARIATION_COUNT);
+            tile.rotation = rng.u8(..4);
+            (tile != *current).then_some(MapEdit::SetTile { x, y, tile })
+        })
+        .collect()
+}
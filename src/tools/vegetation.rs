@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use super::outline_tile;
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::input;
+use crate::terrain::CornerHeightCache;
+use crate::types::TileKind;
+
+const PREVIEW_COLOR: Color = Color::srgb(0.4, 0.9, 0.3);
+
+/// Paints the selected vegetation density over a round brush while dragging;
+/// holding Ctrl clears it. Holes never grow anything.
+pub fn paint_vegetation(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    mut edits: EventWriter<MapEdit>,
+    mut gizmos: Gizmos,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Vegetation {
+        return;
+    }
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let Some((cx, cy)) = state.hover else {
+        return;
+    };
+
+    let radius = state.vegetation_radius as i32;
+    let footprint: Vec<(u32, u32)> = (-radius..=radius)
+        .flat_map(|dy| {
+            (-radius..=radius)
+                .filter(move |dx| dx * dx + dy * dy <= radius * radius)
+                .map(move |dx| (cx as i32 + dx, cy as i32 + dy))
+        })
+        .filter(|&(x, y)| {
+            x >= 0 && y >= 0 && (x as u32) < state.map.width && (y as u32) < state.map.height
+        })
+        .map(|(x, y)| (x as u32, y as u32))
+        .collect();
+
+    if !buttons.pressed(MouseButton::Left) {
+        for &(x, y) in &footprint {
+            outline_tile(&mut gizmos, &heights, x, y, PREVIEW_COLOR);
+        }
+        return;
+    }
+
+    let density = if input::erase_held(&keys) {
+        0
+    } else {
+        state.vegetation_density
+    };
+    edits.send_batch(
+        footprint
+            .into_iter()
+            .filter(|&(x, y)| {
+                state.map.get(x, y).kind != TileKind::Empty && state.map.vegetation(x, y) != density
+            })
+            .map(|(x, y)| MapEdit::SetVegetation { x, y, density }),
+    );
+}
@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::input;
+use crate::terrain::{CORNER_NE, CORNER_NW, CORNER_SE, CORNER_SW, CornerHeightCache};
+use crate::types::{MAX_CORNER_OFFSET, TileKind, TileMap};
+
+const RAISE_COLOR: Color = Color::srgb(1.0, 0.8, 0.3);
+const LOWER_COLOR: Color = Color::srgb(0.3, 0.7, 1.0);
+
+/// Grid vertex nearest to a world position, with vertex (0, 0) at the
+/// map's north-west corner.
+fn nearest_vertex(map: &TileMap, point: Vec3) -> (u32, u32) {
+    let vx = (point.x / map.tile_size)
+        .round()
+        .clamp(0.0, map.width as f32);
+    let vy = (point.z / map.tile_size)
+        .round()
+        .clamp(0.0, map.height as f32);
+    (vx as u32, vy as u32)
+}
+
+/// The tiles meeting at a grid vertex, each with the index of its corner
+/// there.
+fn vertex_corners(map: &TileMap, (vx, vy): (u32, u32)) -> Vec<((u32, u32), usize)> {
+    [
+        (vx.checked_sub(1), vy.checked_sub(1), CORNER_SE),
+        (Some(vx), vy.checked_sub(1), CORNER_SW),
+        (vx.checked_sub(1), Some(vy), CORNER_NE),
+        (Some(vx), Some(vy), CORNER_NW),
+    ]
+    .into_iter()
+    .filter_map(|(x, y, corner)| Some(((x?, y?), corner)))
+    .filter(|&((x, y), _)| x < map.width && y < map.height)
+    .collect()
+}
+
+/// Raises the tile corner nearest the cursor by one step per click, or per
+/// vertex crossed while dragging; holding Ctrl lowers it. Every tile meeting
+/// at the vertex moves with it, except ones across a cliff, so slopes stay
+/// seamless.
+#[allow(clippy::too_many_arguments)]
+pub fn sculpt_vertices(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    mut touched: Local<HashSet<(u32, u32)>>,
+    mut edits: EventWriter<MapEdit>,
+    mut gizmos: Gizmos,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Vertex || !buttons.pressed(MouseButton::Left) {
+        touched.clear();
+    }
+    if state.current_tool != EditorTool::Vertex || egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let (Some(hover), Some(point)) = (state.hover, state.hover_point) else {
+        return;
+    };
+    let vertex = nearest_vertex(&state.map, point);
+    let corners = vertex_corners(&state.map, vertex);
+    // The hovered tile's corner decides which side of a cliff moves.
+    let Some(level) = corners
+        .iter()
+        .find(|(tile, _)| *tile == hover)
+        .and_then(|&((x, y), corner)| Some(heights.corners(x, y)?[corner]))
+    else {
+        return;
+    };
+    let lower = input::erase_held(&keys);
+
+    if !buttons.pressed(MouseButton::Left) {
+        let color = if lower { LOWER_COLOR } else { RAISE_COLOR };
+        let size = heights.tile_size();
+        let centre = Vec3::new(vertex.0 as f32 * size, level, vertex.1 as f32 * size);
+        gizmos.circle(centre + Vec3::Y * 0.03, Dir3::Y, size * 0.2, color);
+        return;
+    }
+    if !touched.insert(vertex) {
+        return;
+    }
+
+    let step = if lower { -1 } else { 1 };
+    for ((x, y), corner) in corners {
+        let on_level = heights
+            .corners(x, y)
+            .is_some_and(|heights| (heights[corner] - level).abs() < 1e-3);
+        let current = state.map.get(x, y);
+        if !on_level || current.kind == TileKind::Empty {
+            continue;
+        }
+        let mut tile = current.clone();
+        tile.corner_offsets[corner] =
+            (tile.corner_offsets[corner] + step).clamp(-MAX_CORNER_OFFSET, MAX_CORNER_OFFSET);
+        if tile != *current {
+            edits.send(MapEdit::SetTile { x, y, tile });
+        }
+    }
+}
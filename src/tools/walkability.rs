@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use super::outline_tile;
+use crate::editor::{EditorState, EditorTool, MapEdit};
+use crate::input;
+use crate::terrain::CornerHeightCache;
+use crate::types::{StartLocation, TileKind, TileMap};
+
+const UNWALKABLE_COLOR: Color = Color::srgb(0.95, 0.2, 0.2);
+const UNREACHABLE_COLOR: Color = Color::srgb(1.0, 0.6, 0.1);
+
+/// Paints the selected walkability override under the cursor while
+/// dragging; holding Ctrl removes overrides.
+pub fn paint_walkability(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<EditorState>,
+    mut edits: EventWriter<MapEdit>,
+    mut egui: EguiContexts,
+) {
+    if state.current_tool != EditorTool::Walkability || !buttons.pressed(MouseButton::Left) {
+        return;
+    }
+    if egui.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let Some((x, y)) = state.hover else {
+        return;
+    };
+    let walkability = if input::erase_held(&keys) {
+        None
+    } else {
+        Some(state.current_walkability)
+    };
+    if state.map.walkability(x, y) != walkability {
+        edits.send(MapEdit::SetWalkability { x, y, walkability });
+    }
+}
+
+/// The tiles the walkability overlay marks, and what they were found from.
+#[derive(Default)]
+pub struct WalkabilityOverlay {
+    valid: bool,
+    walkability: Vec<u8>,
+    starts: Vec<StartLocation>,
+    unwalkable: Vec<(u32, u32)>,
+    unreachable: Vec<(u32, u32)>,
+}
+
+/// Outlines solid tiles units cannot stand on, and walkable tiles they cannot
+/// reach, while the overlay is enabled or the Walkability tool is active.
+pub fn draw_walkability_overlay(
+    mut gizmos: Gizmos,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    mut overlay: Local<WalkabilityOverlay>,
+) {
+    if !state.show_walkability && state.current_tool != EditorTool::Walkability {
+        // Edits made while hidden don't show up as changes once it is back.
+        overlay.valid = false;
+        return;
+    }
+    let map = &state.map;
+    if !overlay.valid
+        || heights.is_changed()
+        || overlay.walkability != map.walkability
+        || overlay.starts != map.starts
+    {
+        *overlay = WalkabilityOverlay::from_map(map);
+    }
+    for &(x, y) in &overlay.unwalkable {
+        outline_tile(&mut gizmos, &heights, x, y, UNWALKABLE_COLOR);
+    }
+    for &(x, y) in &overlay.unreachable {
+        outline_tile(&mut gizmos, &heights, x, y, UNREACHABLE_COLOR);
+    }
+}
+
+impl WalkabilityOverlay {
+    /// Marks solid tiles that are not walkable, and walkable tiles outside
+    /// every region holding a player start. Without starts, every region but
+    /// the largest counts as unreachable.
+    fn from_map(map: &TileMap) -> Self {
+        let regions = map.reachability().regions();
+        let mut reached: Vec<u32> = map
+            .starts
+            .iter()
+            .filter(|start| start.x < map.width && start.y < map.height)
+            .filter_map(|start| regions[map.idx(start.x, start.y)])
+            .collect();
+        if map.starts.is_empty() {
+            let mut sizes = Vec::new();
+            for &region in regions.iter().flatten() {
+                let region = region as usize;
+                if sizes.len() <= region {
+                    sizes.resize(region + 1, 0u32);
+                }
+                sizes[region] += 1;
+            }
+            let largest = (0..sizes.len()).max_by_key(|&region| sizes[region]);
+            reached.extend(largest.map(|region| region as u32));
+        }
+
+        let mut unwalkable = Vec::new();
+        let mut unreachable = Vec::new();
+        for (idx, (tile, region)) in map.tiles.iter().zip(&regions).enumerate() {
+            let tile_position = (idx as u32 % map.width, idx as u32 / map.width);
+            match region {
+                None if tile.kind != TileKind::Empty => unwalkable.push(tile_position),
+                Some(region) if !reached.contains(region) => unreachable.push(tile_position),
+                _ => {}
+            }
+        }
+        Self {
+            valid: true,
+            walkability: map.walkability.clone(),
+            starts: map.starts.clone(),
+            unwalkable,
+            unreachable,
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+
+use crate::editor::EditorState;
+use crate::terrain::{self, CornerHeightCache, TerrainChanged, TerrainMeshSet};
+use crate::types::TileMap;
+
+const OVERLAY_ALPHA: f32 = 0.35;
+// Sits above the hover and selection gizmos' surfaces without z-fighting.
+const OVERLAY_LIFT: f32 = 0.06;
+
+/// Colors handed to new triggers, cycling once exhausted.
+pub const TRIGGER_PALETTE: [[u8; 3]; 6] = [
+    [230, 80, 80],
+    [80, 160, 230],
+    [240, 200, 60],
+    [120, 210, 110],
+    [190, 110, 220],
+    [240, 140, 60],
+];
+
+pub struct TriggerPlugin;
+
+impl Plugin for TriggerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_trigger_overlay).add_systems(
+            Update,
+            (update_trigger_overlay, toggle_trigger_overlay).in_set(TerrainMeshSet::Rebuild),
+        );
+    }
+}
+
+/// Sent when trigger areas were added, removed or repainted.
+#[derive(Event, Clone, Copy, Debug, Default)]
+pub struct TriggersChanged;
+
+/// One translucent mesh holding every trigger's tiles, tinted per trigger
+/// through vertex colors.
+#[derive(Resource)]
+struct TriggerOverlay {
+    entity: Entity,
+    mesh: Handle<Mesh>,
+}
+
+fn spawn_trigger_overlay(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mesh = meshes.add(terrain::empty_mesh());
+    let material = materials.add(StandardMaterial {
+        base_color: Color::WHITE,
+        unlit: true,
+        alpha_mode: AlphaMode::Blend,
+        ..default()
+    });
+    let entity = commands
+        .spawn((
+            PbrBundle {
+                mesh: mesh.clone(),
+                material,
+                ..default()
+            },
+            Name::new("TriggerOverlay"),
+        ))
+        .id();
+    commands.insert_resource(TriggerOverlay { entity, mesh });
+}
+
+fn update_trigger_overlay(
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    mut terrain_changed: EventReader<TerrainChanged>,
+    mut triggers_changed: EventReader<TriggersChanged>,
+    overlay: Res<TriggerOverlay>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    if terrain_changed.is_empty() && triggers_changed.is_empty() {
+        return;
+    }
+    terrain_changed.clear();
+    triggers_changed.clear();
+
+    if let Some(mesh) = meshes.get_mut(&overlay.mesh) {
+        *mesh = build_overlay_mesh(&state.map, &heights);
+    }
+}
+
+fn toggle_trigger_overlay(
+    state: Res<EditorState>,
+    overlay: Res<TriggerOverlay>,
+    mut visibility: Query<&mut Visibility>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    let Ok(mut visibility) = visibility.get_mut(overlay.entity) else {
+        return;
+    };
+    let desired = if state.show_triggers {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    if *visibility != desired {
+        *visibility = desired;
+    }
+}
+
+fn build_overlay_mesh(map: &TileMap, heights: &CornerHeightCache) -> Mesh {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut colors: Vec<[f32; 4]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    for area in &map.triggers {
+        let [r, g, b] = area.color;
+        let mut color = Color::srgb_u8(r, g, b).to_linear().to_f32_array();
+        color[3] = OVERLAY_ALPHA;
+
+        for &(x, y) in &area.tiles {
+            if x >= map.width || y >= map.height {
+                continue;
+            }
+            let Some(corners) = heights.corners(x, y) else {
+                continue;
+            };
+            let x0 = x as f32 * map.tile_size;
+            let x1 = x0 + map.tile_size;
+            let z0 = y as f32 * map.tile_size;
+            let z1 = z0 + map.tile_size;
+            let base = positions.len() as u32;
+            positions.extend([
+                [x0, corners[terrain::CORNER_NW] + OVERLAY_LIFT, z0],
+                [x1, corners[terrain::CORNER_NE] + OVERLAY_LIFT, z0],
+                [x1, corners[terrain::CORNER_SE] + OVERLAY_LIFT, z1],
+                [x0, corners[terrain::CORNER_SW] + OVERLAY_LIFT, z1],
+            ]);
+            colors.extend([color; 4]);
+            // Same triangulation as the terrain top face.
+            indices.extend([base, base + 3, base + 2, base, base + 2, base + 1]);
+        }
+    }
+
+    let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.insert_indices(Indices::U32(indices));
+    mesh
+}
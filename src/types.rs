@@ -1,3 +1,7 @@
+use std::collections::BTreeSet;
+
+use bevy::asset::Asset;
+use bevy::reflect::TypePath;
 use bincode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 
@@ -5,6 +9,9 @@ use serde::{Deserialize, Serialize};
 pub enum TileKind {
     Floor,
     Ramp,
+    /// A hole: no top face and no splatmap weight. The tiles around it wall
+    /// it in down to `terrain::hole_floor`, so it reads as a pit.
+    Empty,
 }
 
 #[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug, Encode, Decode)]
@@ -13,9 +20,16 @@ pub enum RampDirection {
     East,
     South,
     West,
+    /// Corner ramps, which lower only the one corner facing the diagonal
+    /// neighbour, for the inside and outside corners of cliffs.
+    NorthEast,
+    SouthEast,
+    SouthWest,
+    NorthWest,
 }
 
 impl RampDirection {
+    /// The four edge neighbours.
     pub const ALL: [RampDirection; 4] = [
         RampDirection::North,
         RampDirection::East,
@@ -23,12 +37,29 @@ impl RampDirection {
         RampDirection::West,
     ];
 
+    /// Every way a ramp can face, edge directions first.
+    pub const RAMPS: [RampDirection; 8] = [
+        RampDirection::North,
+        RampDirection::East,
+        RampDirection::South,
+        RampDirection::West,
+        RampDirection::NorthEast,
+        RampDirection::SouthEast,
+        RampDirection::SouthWest,
+        RampDirection::NorthWest,
+    ];
+
+    /// The direction a quarter turn clockwise.
     pub fn next(self) -> RampDirection {
         match self {
             RampDirection::North => RampDirection::East,
             RampDirection::East => RampDirection::South,
             RampDirection::South => RampDirection::West,
             RampDirection::West => RampDirection::North,
+            RampDirection::NorthEast => RampDirection::SouthEast,
+            RampDirection::SouthEast => RampDirection::SouthWest,
+            RampDirection::SouthWest => RampDirection::NorthWest,
+            RampDirection::NorthWest => RampDirection::NorthEast,
         }
     }
 
@@ -38,10 +69,23 @@ impl RampDirection {
             RampDirection::East => (1, 0),
             RampDirection::South => (0, 1),
             RampDirection::West => (-1, 0),
+            RampDirection::NorthEast => (1, -1),
+            RampDirection::SouthEast => (1, 1),
+            RampDirection::SouthWest => (-1, 1),
+            RampDirection::NorthWest => (-1, -1),
         }
     }
+
+    pub fn from_offset(dx: i32, dy: i32) -> Option<RampDirection> {
+        RampDirection::RAMPS
+            .into_iter()
+            .find(|dir| dir.offset() == (dx, dy))
+    }
 }
 
+/// The texture layer a tile is painted with. The first four have bundled
+/// textures; the `Layer` slots stay unpaintable until a texture is registered
+/// for them, see `TerrainTextureRegistry::layers`.
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, Encode, Decode, PartialEq, Eq, Hash)]
 pub enum TileType {
     Grass,
@@ -49,10 +93,42 @@ pub enum TileType {
     #[serde(alias = "Cliff")]
     Sand,
     Rock,
+    Layer4,
+    Layer5,
+    Layer6,
+    Layer7,
+    Layer8,
+    Layer9,
+    Layer10,
+    Layer11,
+    Layer12,
+    Layer13,
+    Layer14,
+    Layer15,
 }
 
 impl TileType {
-    pub const ALL: [TileType; 4] = [
+    /// Every layer, in splatmap order.
+    pub const ALL: [TileType; 16] = [
+        TileType::Grass,
+        TileType::Dirt,
+        TileType::Sand,
+        TileType::Rock,
+        TileType::Layer4,
+        TileType::Layer5,
+        TileType::Layer6,
+        TileType::Layer7,
+        TileType::Layer8,
+        TileType::Layer9,
+        TileType::Layer10,
+        TileType::Layer11,
+        TileType::Layer12,
+        TileType::Layer13,
+        TileType::Layer14,
+        TileType::Layer15,
+    ];
+    /// Layers with bundled textures.
+    pub const BUILTIN: [TileType; 4] = [
         TileType::Grass,
         TileType::Dirt,
         TileType::Sand,
@@ -60,12 +136,7 @@ impl TileType {
     ];
 
     pub fn as_index(self) -> usize {
-        match self {
-            TileType::Grass => 0,
-            TileType::Dirt => 1,
-            TileType::Sand => 2,
-            TileType::Rock => 3,
-        }
+        self as usize
     }
 
     pub fn identifier(self) -> &'static str {
@@ -74,6 +145,41 @@ impl TileType {
             TileType::Dirt => "dirt",
             TileType::Sand => "sand",
             TileType::Rock => "rock",
+            TileType::Layer4 => "layer4",
+            TileType::Layer5 => "layer5",
+            TileType::Layer6 => "layer6",
+            TileType::Layer7 => "layer7",
+            TileType::Layer8 => "layer8",
+            TileType::Layer9 => "layer9",
+            TileType::Layer10 => "layer10",
+            TileType::Layer11 => "layer11",
+            TileType::Layer12 => "layer12",
+            TileType::Layer13 => "layer13",
+            TileType::Layer14 => "layer14",
+            TileType::Layer15 => "layer15",
+        }
+    }
+
+    /// Movement cost used for tiles without a painted override.
+    pub fn default_movement_cost(self) -> u8 {
+        match self {
+            TileType::Grass => 2,
+            TileType::Dirt => 2,
+            TileType::Sand => 3,
+            TileType::Rock => 4,
+            _ => 2,
+        }
+    }
+
+    /// Flat sRGB color standing in for the texture in thumbnails and
+    /// overlays. Added layers get a grey that brightens with their index.
+    pub fn preview_color(self) -> [f32; 3] {
+        match self {
+            TileType::Grass => [0.35, 0.75, 0.3],
+            TileType::Dirt => [0.6, 0.45, 0.3],
+            TileType::Sand => [0.9, 0.8, 0.5],
+            TileType::Rock => [0.55, 0.55, 0.6],
+            layer => [0.3 + 0.05 * (layer.as_index() - Self::BUILTIN.len()) as f32; 3],
         }
     }
 }
@@ -84,7 +190,7 @@ impl Default for TileType {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Encode, Decode)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Encode, Decode)]
 pub struct Tile {
     pub kind: TileKind,
     pub tile_type: TileType,
@@ -93,13 +199,290 @@ pub struct Tile {
     pub elevation: i8, // can be negative for underwater, or positive for cliffs
     #[serde(default)]
     pub ramp_direction: Option<RampDirection>,
+    /// Texture offset seed for the top face, so repeated tiles of one type do
+    /// not line up.
+    #[serde(default)]
+    pub variation: u8,
+    /// Quarter turns applied to the top-face texture.
+    #[serde(default)]
+    pub rotation: u8,
+    /// Mirrors the top-face texture left to right before it is turned.
+    #[serde(default)]
+    pub mirrored: bool,
+    /// Locked tiles ignore edits until unlocked.
+    #[serde(default)]
+    pub locked: bool,
+    /// Painted pathfinding cost; `None` falls back to the tile type's default.
+    #[serde(default)]
+    pub movement_cost: Option<u8>,
+    /// Sculpted height of each top corner above or below the tile's
+    /// elevation, in `CORNER_OFFSET_STEPS`ths of a level, indexed like
+    /// `terrain::CORNER_NW` and friends.
+    #[serde(default)]
+    pub corner_offsets: [i8; 4],
+}
+
+impl Tile {
+    /// Pathfinding cost of crossing this tile, with 0 meaning impassable.
+    pub fn movement_cost(&self) -> u8 {
+        if self.kind == TileKind::Empty {
+            return 0;
+        }
+        self.movement_cost
+            .unwrap_or_else(|| self.tile_type.default_movement_cost())
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Encode, Decode, Clone)]
+/// What a road is paved with. Roads are drawn over the tile's own texture,
+/// and the terrain shader colours each surface by its position in `ALL`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RoadSurface {
+    Dirt,
+    Paved,
+}
+
+impl RoadSurface {
+    pub const ALL: [RoadSurface; 2] = [RoadSurface::Dirt, RoadSurface::Paved];
+
+    pub fn identifier(self) -> &'static str {
+        match self {
+            RoadSurface::Dirt => "dirt",
+            RoadSurface::Paved => "paved",
+        }
+    }
+
+    /// The value stored in `TileMap::roads`; 0 means no road.
+    pub fn code(self) -> u8 {
+        Self::ALL
+            .iter()
+            .position(|&surface| surface == self)
+            .unwrap_or(0) as u8
+            + 1
+    }
+
+    pub fn from_code(code: u8) -> Option<RoadSurface> {
+        Self::ALL.get(usize::from(code).checked_sub(1)?).copied()
+    }
+}
+
+/// A painted override of whether units can stand on a tile, in place of
+/// the walkability derived from the terrain.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Walkability {
+    Blocked,
+    Walkable,
+}
+
+impl Walkability {
+    pub const ALL: [Walkability; 2] = [Walkability::Blocked, Walkability::Walkable];
+
+    pub fn identifier(self) -> &'static str {
+        match self {
+            Walkability::Blocked => "blocked",
+            Walkability::Walkable => "walkable",
+        }
+    }
+
+    /// The value stored in `TileMap::walkability`; 0 means no override.
+    pub fn code(self) -> u8 {
+        Self::ALL
+            .iter()
+            .position(|&walkability| walkability == self)
+            .unwrap_or(0) as u8
+            + 1
+    }
+
+    pub fn from_code(code: u8) -> Option<Walkability> {
+        Self::ALL.get(usize::from(code).checked_sub(1)?).copied()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Encode, Decode, Clone, Asset, TypePath)]
 pub struct TileMap {
     pub width: u32,
     pub height: u32,
     pub tiles: Vec<Tile>, // row-major
+    /// Named regions game scripts watch for units entering.
+    #[serde(default)]
+    pub triggers: Vec<TriggerArea>,
+    /// Player start locations, one per player index.
+    #[serde(default)]
+    pub starts: Vec<StartLocation>,
+    /// Lights placed on tiles, at most one per tile.
+    #[serde(default)]
+    pub lights: Vec<MapLight>,
+    /// Conditions the map is meant to be played in.
+    #[serde(default)]
+    pub weather: Weather,
+    /// Row-major vegetation density per tile, from 0 for bare ground to 255
+    /// for the thickest growth. Empty until something is painted.
+    #[serde(default)]
+    pub vegetation: Vec<u8>,
+    /// Row-major road surface per tile as a `RoadSurface::code`, 0 where
+    /// there is no road. Empty until a road is drawn.
+    #[serde(default)]
+    pub roads: Vec<u8>,
+    /// Decals painted over the terrain, drawn in order.
+    #[serde(default)]
+    pub decals: Vec<MapDecal>,
+    /// Scenes placed on the map, such as trees, rocks and buildings.
+    #[serde(default)]
+    pub props: Vec<MapProp>,
+    /// Row-major walkability override per tile as a `Walkability::code`, 0
+    /// where walkability follows the terrain. Empty until one is painted.
+    #[serde(default)]
+    pub walkability: Vec<u8>,
+    /// World units along each side of a tile. Elevation steps scale with it.
+    #[serde(default = "default_tile_size")]
+    pub tile_size: f32,
+    /// Height of the water surface in elevation levels, or `None` for a dry
+    /// map. Terrain below it is drawn under a water plane.
+    #[serde(default)]
+    pub water_level: Option<f32>,
+    /// Tile layers stacked over the ground for bridges, tunnel roofs and
+    /// overhangs, lowest first. Each is row-major like `tiles`, and its
+    /// holes leave the layer open, so a new layer starts out empty.
+    #[serde(default)]
+    pub upper_layers: Vec<Vec<Tile>>,
+}
+
+fn default_tile_size() -> f32 {
+    DEFAULT_TILE_SIZE
+}
+
+/// A designer-painted set of tiles with a name scripts can refer to.
+#[derive(Serialize, Deserialize, Debug, Encode, Decode, Clone, PartialEq)]
+pub struct TriggerArea {
+    pub name: String,
+    /// sRGB overlay color.
+    pub color: [u8; 3],
+    pub tiles: BTreeSet<(u32, u32)>,
+    /// Player the area belongs to, e.g. a base zone.
+    #[serde(default)]
+    pub player: Option<u8>,
+}
+
+/// Where a player's units spawn at the start of a match.
+#[derive(Serialize, Deserialize, Debug, Encode, Decode, Clone, PartialEq)]
+pub struct StartLocation {
+    pub player: u8,
+    pub team: u8,
+    pub x: u32,
+    pub y: u32,
+    /// Degrees clockwise from north (-Z).
+    pub facing: f32,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug, Encode, Decode)]
+pub enum LightKind {
+    Point,
+    Spot,
+}
+
+/// A light a game should spawn with the map, anchored to a tile.
+#[derive(Serialize, Deserialize, Debug, Encode, Decode, Clone, PartialEq)]
+pub struct MapLight {
+    pub kind: LightKind,
+    pub x: u32,
+    pub y: u32,
+    /// World units above the tile's surface at its center.
+    pub height: f32,
+    /// sRGB color.
+    pub color: [u8; 3],
+    /// Luminous power in lumens.
+    pub intensity: f32,
+    /// World units beyond which the light has no effect.
+    pub range: f32,
+    /// Spot lights only: degrees clockwise from north (-Z).
+    pub facing: f32,
+    /// Spot lights only: degrees below the horizon.
+    pub pitch: f32,
+    /// Spot lights only: full cone angle in degrees.
+    pub cone: f32,
+}
+
+/// Detail a decal paints onto the terrain. The decal shader draws each kind
+/// procedurally, so they need no textures.
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug, Encode, Decode, Hash)]
+pub enum DecalKind {
+    Crater,
+    Crack,
+    Leaves,
+}
+
+impl DecalKind {
+    pub const ALL: [DecalKind; 3] = [DecalKind::Crater, DecalKind::Crack, DecalKind::Leaves];
+
+    pub fn identifier(self) -> &'static str {
+        match self {
+            DecalKind::Crater => "crater",
+            DecalKind::Crack => "crack",
+            DecalKind::Leaves => "leaves",
+        }
+    }
+}
+
+/// A decal projected straight down onto whatever terrain lies under it.
+/// Positions and sizes are in tiles rather than world units, so decals stay
+/// put when the tile size changes.
+#[derive(Serialize, Deserialize, Debug, Encode, Decode, Clone, PartialEq)]
+pub struct MapDecal {
+    pub kind: DecalKind,
+    /// Centre across the map, where (0.5, 0.5) is the middle of the first
+    /// tile.
+    pub x: f32,
+    pub y: f32,
+    /// Width of the decal's square.
+    pub size: f32,
+    /// Degrees clockwise from north (-Z).
+    pub rotation: f32,
+    /// How strongly the decal darkens the ground, from 0 to 1.
+    pub opacity: f32,
+}
+
+/// A scene such as a tree, rock or building standing on the terrain under
+/// its centre. Like decals, props are positioned in tiles.
+#[derive(Serialize, Deserialize, Debug, Encode, Decode, Clone, PartialEq)]
+pub struct MapProp {
+    /// Id of the scene in the prop registry.
+    pub asset: String,
+    pub x: f32,
+    pub y: f32,
+    /// Degrees clockwise from north (-Z).
+    pub rotation: f32,
+    /// Uniform scale applied to the scene.
+    pub scale: f32,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug, Default, Encode, Decode)]
+pub enum WeatherKind {
+    #[default]
+    Clear,
+    Rain,
+    Snow,
+    Dust,
+}
+
+#[derive(Serialize, Deserialize, Debug, Encode, Decode, Clone, PartialEq)]
+pub struct Weather {
+    pub kind: WeatherKind,
+    /// How heavy the weather is, from 0 to 1.
+    pub intensity: f32,
+    /// Degrees clockwise from north (-Z) the wind blows towards.
+    pub wind_direction: f32,
+    /// World units per second.
+    pub wind_speed: f32,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Self {
+            kind: WeatherKind::Clear,
+            intensity: 0.5,
+            wind_direction: 0.0,
+            wind_speed: 0.0,
+        }
+    }
 }
 
 impl TileMap {
@@ -115,10 +498,32 @@ impl TileMap {
                     x: 0,
                     y: 0,
                     ramp_direction: None,
+                    variation: 0,
+                    rotation: 0,
+                    mirrored: false,
+                    locked: false,
+                    movement_cost: None,
+                    corner_offsets: [0; 4],
                 })
                 .collect(),
+            triggers: Vec::new(),
+            starts: Vec::new(),
+            lights: Vec::new(),
+            weather: Weather::default(),
+            vegetation: Vec::new(),
+            roads: Vec::new(),
+            decals: Vec::new(),
+            props: Vec::new(),
+            walkability: Vec::new(),
+            tile_size: DEFAULT_TILE_SIZE,
+            water_level: None,
+            upper_layers: Vec::new(),
         }
     }
+    /// World units per elevation step.
+    pub fn tile_height(&self) -> f32 {
+        self.tile_size * ELEVATION_FRACTION
+    }
     pub fn idx(&self, x: u32, y: u32) -> usize {
         (y * self.width + x) as usize
     }
@@ -129,8 +534,158 @@ impl TileMap {
         let i = self.idx(x, y);
         self.tiles[i] = t;
     }
+    /// Vegetation density at a tile; 0 when the layer was never painted.
+    pub fn vegetation(&self, x: u32, y: u32) -> u8 {
+        if self.vegetation.len() != self.tiles.len() {
+            return 0;
+        }
+        self.vegetation[self.idx(x, y)]
+    }
+    /// Checks that there is one tile per cell, and that every per-tile
+    /// layer is either unpainted or the same length, as indexing the map
+    /// assumes. Maps received from elsewhere may not be.
+    pub fn check_shape(&self) -> anyhow::Result<()> {
+        let cells = self.width as u64 * self.height as u64;
+        anyhow::ensure!(
+            self.tiles.len() as u64 == cells,
+            "{} tiles for a {}x{} map",
+            self.tiles.len(),
+            self.width,
+            self.height
+        );
+        for (name, len) in [
+            ("vegetation", self.vegetation.len()),
+            ("road", self.roads.len()),
+            ("walkability", self.walkability.len()),
+        ] {
+            anyhow::ensure!(
+                len == 0 || len == self.tiles.len(),
+                "{len} {name} entries for {} tiles",
+                self.tiles.len()
+            );
+        }
+        for (upper, layer) in self.upper_layers.iter().enumerate() {
+            anyhow::ensure!(
+                layer.len() == self.tiles.len(),
+                "{} tiles on layer {} for {} on the ground",
+                layer.len(),
+                upper + 1,
+                self.tiles.len()
+            );
+        }
+        Ok(())
+    }
+    /// Layers including the ground, which is layer 0.
+    pub fn layer_count(&self) -> usize {
+        1 + self.upper_layers.len()
+    }
+    /// An upper layer with every cell open.
+    pub fn empty_layer(&self) -> Vec<Tile> {
+        self.tiles
+            .iter()
+            .map(|tile| Tile {
+                kind: TileKind::Empty,
+                ramp_direction: None,
+                locked: false,
+                movement_cost: None,
+                corner_offsets: [0; 4],
+                ..tile.clone()
+            })
+            .collect()
+    }
+    /// A map with `layer`'s tiles in place of the ground, so an upper layer
+    /// can be meshed and painted by the same rules; `None` past the top.
+    pub fn layer_map(&self, layer: usize) -> Option<TileMap> {
+        let tiles = match layer {
+            0 => self.tiles.clone(),
+            _ => self.upper_layers.get(layer - 1)?.clone(),
+        };
+        Some(TileMap {
+            width: self.width,
+            height: self.height,
+            tiles,
+            tile_size: self.tile_size,
+            ..TileMap::new(0, 0)
+        })
+    }
+    pub fn set_vegetation(&mut self, x: u32, y: u32, density: u8) {
+        if self.vegetation.len() != self.tiles.len() {
+            if density == 0 {
+                return;
+            }
+            self.vegetation = vec![0; self.tiles.len()];
+        }
+        let i = self.idx(x, y);
+        self.vegetation[i] = density;
+    }
+    /// The road on a tile, if one was drawn there.
+    pub fn road(&self, x: u32, y: u32) -> Option<RoadSurface> {
+        if self.roads.len() != self.tiles.len() {
+            return None;
+        }
+        RoadSurface::from_code(self.roads[self.idx(x, y)])
+    }
+    pub fn set_road(&mut self, x: u32, y: u32, surface: Option<RoadSurface>) {
+        if self.roads.len() != self.tiles.len() {
+            if surface.is_none() {
+                return;
+            }
+            self.roads = vec![0; self.tiles.len()];
+        }
+        let i = self.idx(x, y);
+        self.roads[i] = surface.map_or(0, RoadSurface::code);
+    }
+    /// The painted walkability override of a tile, if it has one.
+    pub fn walkability(&self, x: u32, y: u32) -> Option<Walkability> {
+        if self.walkability.len() != self.tiles.len() {
+            return None;
+        }
+        Walkability::from_code(self.walkability[self.idx(x, y)])
+    }
+    pub fn set_walkability(&mut self, x: u32, y: u32, walkability: Option<Walkability>) {
+        if self.walkability.len() != self.tiles.len() {
+            if walkability.is_none() {
+                return;
+            }
+            self.walkability = vec![0; self.tiles.len()];
+        }
+        let i = self.idx(x, y);
+        self.walkability[i] = walkability.map_or(0, Walkability::code);
+    }
+    /// The trigger areas covering a tile, in the order they were created.
+    pub fn areas_at(&self, x: u32, y: u32) -> impl Iterator<Item = &TriggerArea> {
+        self.triggers
+            .iter()
+            .filter(move |area| area.tiles.contains(&(x, y)))
+    }
+    /// The trigger area named `name`, if there is one.
+    pub fn area(&self, name: &str) -> Option<&TriggerArea> {
+        self.triggers.iter().find(|area| area.name == name)
+    }
 }
 
-pub const TILE_SIZE: f32 = 2.0; // world units per tile
+/// Elevation range the editor tools produce.
+pub const MIN_ELEVATION: i8 = -1;
+pub const MAX_ELEVATION: i8 = 3;
+/// Levels below `MIN_ELEVATION` that the walls around a hole reach down to.
+pub const HOLE_DEPTH: i8 = 2;
+/// Levels between the top of an upper layer tile and its underside.
+pub const LAYER_THICKNESS: i8 = 1;
+
+/// Corner offsets per elevation level, and the furthest a corner may be
+/// moved from its tile's elevation.
+pub const CORNER_OFFSET_STEPS: i8 = 4;
+pub const MAX_CORNER_OFFSET: i8 = 2 * CORNER_OFFSET_STEPS;
+
+/// Range of painted movement costs; 0 is reserved for impassable holes.
+pub const MIN_MOVEMENT_COST: u8 = 1;
+pub const MAX_MOVEMENT_COST: u8 = 9;
+/// Paved tiles are cheaper to cross than any bare terrain default, so
+/// pathfinding prefers roads.
+pub const ROAD_MOVEMENT_COST: u8 = MIN_MOVEMENT_COST;
+
+pub const DEFAULT_TILE_SIZE: f32 = 2.0; // world units per tile of new maps
 pub const ELEVATION_FRACTION: f32 = 0.4; // fraction of tile width per elevation step
-pub const TILE_HEIGHT: f32 = TILE_SIZE * ELEVATION_FRACTION; // height per elevation step
+/// Smallest and largest tile sizes the editor offers, in world units.
+pub const MIN_TILE_SIZE: f32 = 0.25;
+pub const MAX_TILE_SIZE: f32 = 16.0;
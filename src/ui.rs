@@ -1,14 +1,42 @@
-use crate::editor::{EditorTool, ExportStatus};
+use crate::bundle::BundleRequest;
+use crate::camera::SplitView;
+use crate::editor::{
+    EditHistory, EditorTool, ExportStatus, MAX_HISTORY_DEPTH, MAX_PAINT_RADIUS, MapEdit, Symmetry,
+};
 use crate::export;
-use crate::io::{load_map, save_map};
-use crate::runtime::RuntimeSplatMap;
-use crate::terrain::TerrainMeshSet;
+use crate::file_export::ExportFile;
+use crate::generators::{GeneratorKind, GeneratorPreview, PreviewAction};
+use crate::input::TemporaryTool;
+use crate::io::{
+    JSON_EXTENSION, STAMP_EXTENSION, Stamp, load_stamp, save_delta, save_map, save_stamp,
+};
+use crate::lightmap::{BakeLightmap, BakedLightmap};
+use crate::localization::Localization;
+use crate::map_browser;
+use crate::map_import::ImportMap;
+use crate::new_map::NewMapDialog;
+use crate::notifications::{Notifications, Notify};
+use crate::players::{self, MAX_PLAYERS};
+use crate::props::{PROP_MANIFEST, PropRegistry};
+use crate::recent::RecentFiles;
+use crate::reference::ReferenceOverlay;
+use crate::runtime::TerrainLod;
+use crate::selection::{self, Clipboard, Selection};
+use crate::stats::{BalanceReport, Histogram};
+use crate::terrain::{CliffTexturing, NormalSmoothing, TerrainMeshSet};
+use crate::text_map;
+use crate::tools::transform::MapTransform;
+use crate::tools::{BrushShape, Falloff, replace, variation};
+use crate::triggers::TRIGGER_PALETTE;
 use crate::types::*;
+use crate::unsaved::FileAction;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy::render::texture::Image;
-use bevy::tasks::{IoTaskPool, block_on};
+use bevy::tasks::{IoTaskPool, Task, block_on};
 use bevy_egui::{EguiContexts, egui};
 use rfd::AsyncFileDialog;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
 use crate::texture::registry::TerrainTextureRegistry;
@@ -16,23 +44,185 @@ use crate::texture::registry::TerrainTextureRegistry;
 pub struct UiPlugin;
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, ui_panel.before(TerrainMeshSet::Rebuild));
+        app.init_resource::<PaletteTextures>()
+            .init_resource::<StampLibrary>()
+            .add_systems(
+                Update,
+                (sync_palette_textures, ui_panel, stamps_window)
+                    .chain()
+                    .in_set(TerrainMeshSet::Edit),
+            );
+    }
+}
+
+/// egui texture ids for the palette previews. Registering an image with egui
+/// allocates a new id, so this is only done when the registry gains an entry.
+#[derive(Resource, Default)]
+struct PaletteTextures {
+    entries: HashMap<AssetId<Image>, (Handle<Image>, egui::TextureId)>,
+}
+
+fn sync_palette_textures(
+    mut egui_ctx: EguiContexts,
+    textures: Res<TerrainTextureRegistry>,
+    mut palette: ResMut<PaletteTextures>,
+) {
+    if !textures.is_changed() {
+        return;
+    }
+
+    let wanted: HashSet<AssetId<Image>> = textures.iter().map(|entry| entry.preview.id()).collect();
+    palette.entries.retain(|id, (handle, _)| {
+        if wanted.contains(id) {
+            true
+        } else {
+            egui_ctx.remove_image(handle);
+            false
+        }
+    });
+
+    for entry in textures.iter() {
+        palette
+            .entries
+            .entry(entry.preview.id())
+            .or_insert_with(|| {
+                let handle = entry.preview.clone_weak();
+                let texture = egui_ctx.add_image(handle.clone_weak());
+                (handle, texture)
+            });
     }
 }
 
+/// The string table and notification output, grouped to stay within the
+/// system parameter limit.
+/// Folder stamps are saved to and listed from.
+const STAMP_DIR: &str = "assets/stamps";
+
+/// Stamps saved in `STAMP_DIR`, listed once the stamps window first shows.
+#[derive(Resource, Default)]
+struct StampLibrary {
+    scanned: bool,
+    stamps: Vec<(PathBuf, Stamp)>,
+    /// Name typed for the next stamp.
+    name: String,
+}
+
+impl StampLibrary {
+    fn scan(&mut self) {
+        self.scanned = true;
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(STAMP_DIR)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().is_some_and(|ext| ext == STAMP_EXTENSION))
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort();
+        self.stamps = paths
+            .into_iter()
+            .filter_map(|path| match load_stamp(&path) {
+                Ok(stamp) => Some((path, stamp)),
+                Err(err) => {
+                    warn!("Skipping stamp {}: {err:#}", path.display());
+                    None
+                }
+            })
+            .collect();
+    }
+}
+
+#[derive(SystemParam)]
+struct UiText<'w> {
+    localization: ResMut<'w, Localization>,
+    notify: EventWriter<'w, Notify>,
+}
+
+#[derive(SystemParam)]
+struct Shading<'w> {
+    smoothing: ResMut<'w, NormalSmoothing>,
+    cliffs: ResMut<'w, CliffTexturing>,
+    lod: ResMut<'w, TerrainLod>,
+}
+
+#[derive(SystemParam)]
+struct Registries<'w> {
+    textures: Res<'w, TerrainTextureRegistry>,
+    props: Res<'w, PropRegistry>,
+}
+
+#[derive(SystemParam)]
+struct MapEditing<'w> {
+    edits: EventWriter<'w, MapEdit>,
+    history: ResMut<'w, EditHistory>,
+    recent: ResMut<'w, RecentFiles>,
+    new_map: ResMut<'w, NewMapDialog>,
+}
+
+/// Work the panel hands off to other plugins.
+#[derive(SystemParam)]
+struct Requests<'w> {
+    bake: EventWriter<'w, BakeLightmap>,
+    export: EventWriter<'w, ExportFile>,
+    bundle: EventWriter<'w, BundleRequest>,
+    file: EventWriter<'w, FileAction>,
+    import: EventWriter<'w, ImportMap>,
+}
+
+#[allow(clippy::too_many_arguments)]
 fn ui_panel(
     mut egui_ctx: EguiContexts,
     mut state: ResMut<crate::editor::EditorState>,
-    textures: Res<TerrainTextureRegistry>,
-    runtime_splat: Option<Res<RuntimeSplatMap>>,
+    editing: MapEditing,
+    registries: Registries,
+    palette: Res<PaletteTextures>,
+    lightmap: Option<Res<BakedLightmap>>,
+    requests: Requests,
+    selection: Res<Selection>,
     images: Res<Assets<Image>>,
+    mut split_view: ResMut<SplitView>,
+    mut generator: ResMut<GeneratorPreview>,
+    mut notifications: ResMut<Notifications>,
+    mut reference: ResMut<ReferenceOverlay>,
+    shading: Shading,
+    text: UiText,
 ) {
+    let UiText {
+        localization: mut loc,
+        mut notify,
+    } = text;
+    let MapEditing {
+        mut edits,
+        mut history,
+        mut recent,
+        mut new_map,
+    } = editing;
+    let Shading {
+        mut smoothing,
+        mut cliffs,
+        mut lod,
+    } = shading;
+    let Registries {
+        textures,
+        props: prop_registry,
+    } = registries;
+    let Requests {
+        bake: mut bake_requests,
+        export: mut export_requests,
+        bundle: mut bundle_requests,
+        file: mut file_actions,
+        import: mut import_requests,
+    } = requests;
     let palette_items: Vec<_> = textures
         .iter()
-        .map(|entry| PaletteItem {
-            tile_type: entry.tile_type,
-            name: entry.name.clone(),
-            texture: egui_ctx.add_image(entry.preview.clone_weak()),
+        .filter_map(|entry| {
+            let (_, texture) = palette.entries.get(&entry.preview.id())?;
+            Some(PaletteItem {
+                tile_type: entry.tile_type,
+                name: entry.name.clone(),
+                texture: *texture,
+            })
         })
         .collect();
 
@@ -45,54 +235,618 @@ fn ui_panel(
         }
     }
 
+    // Applied after the panel, which borrows the string table throughout.
+    let mut switch_language = None;
     egui::TopBottomPanel::top("toolbar").show(egui_ctx.ctx_mut(), |ui| {
         ui.horizontal(|ui| {
-            ui.label("Mode:");
-            ui.selectable_value(&mut state.current_tool, EditorTool::Paint, "Paint");
+            ui.label(loc.tr("toolbar.mode"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Paint,
+                loc.tr("tool.paint"),
+            )
+            .on_hover_text(loc.tr("tool.paint.hint"));
             ui.selectable_value(
                 &mut state.current_tool,
                 EditorTool::RotateRamp,
-                "Rotate Ramp",
+                loc.tr("tool.rotate_ramp"),
+            );
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::RotateTexture,
+                loc.tr("tool.rotate_texture"),
+            )
+            .on_hover_text(loc.tr("tool.rotate_texture.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Road,
+                loc.tr("tool.road"),
+            )
+            .on_hover_text(loc.tr("tool.road.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::EdgeRamp,
+                loc.tr("tool.edge_ramp"),
+            );
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Plateau,
+                loc.tr("tool.plateau"),
+            );
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::RectFill,
+                loc.tr("tool.rect_fill"),
+            )
+            .on_hover_text(loc.tr("tool.rect_fill.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Clone,
+                loc.tr("tool.clone"),
+            )
+            .on_hover_text(loc.tr("tool.clone.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Select,
+                loc.tr("tool.select"),
+            )
+            .on_hover_text(loc.tr("tool.select.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Wand,
+                loc.tr("tool.wand"),
+            )
+            .on_hover_text(loc.tr("tool.wand.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Paste,
+                loc.tr("tool.paste"),
+            )
+            .on_hover_text(loc.tr("tool.paste.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Gradient,
+                loc.tr("tool.gradient"),
+            )
+            .on_hover_text(loc.tr("tool.gradient.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::AutoRamp,
+                loc.tr("tool.auto_ramp"),
+            )
+            .on_hover_text(loc.tr("tool.auto_ramp.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Noise,
+                loc.tr("tool.noise"),
             );
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Sculpt,
+                loc.tr("tool.sculpt"),
+            )
+            .on_hover_text(loc.tr("tool.sculpt.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Smooth,
+                loc.tr("tool.smooth"),
+            )
+            .on_hover_text(loc.tr("tool.smooth.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Vertex,
+                loc.tr("tool.vertex"),
+            )
+            .on_hover_text(loc.tr("tool.vertex.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Scatter,
+                loc.tr("tool.scatter"),
+            )
+            .on_hover_text(loc.tr("tool.scatter.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Lock,
+                loc.tr("tool.lock"),
+            )
+            .on_hover_text(loc.tr("tool.lock.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::MovementCost,
+                loc.tr("tool.cost"),
+            )
+            .on_hover_text(loc.tr("tool.cost.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Walkability,
+                loc.tr("tool.walkability"),
+            )
+            .on_hover_text(loc.tr("tool.walkability.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Trigger,
+                loc.tr("tool.trigger"),
+            )
+            .on_hover_text(loc.tr("tool.trigger.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Start,
+                loc.tr("tool.start"),
+            )
+            .on_hover_text(loc.tr("tool.start.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Path,
+                loc.tr("tool.path"),
+            )
+            .on_hover_text(loc.tr("tool.path.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Light,
+                loc.tr("tool.light"),
+            )
+            .on_hover_text(loc.tr("tool.light.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Vegetation,
+                loc.tr("tool.vegetation"),
+            )
+            .on_hover_text(loc.tr("tool.vegetation.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Decal,
+                loc.tr("tool.decal"),
+            )
+            .on_hover_text(loc.tr("tool.decal.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Prop,
+                loc.tr("tool.prop"),
+            )
+            .on_hover_text(loc.tr("tool.prop.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Macro,
+                loc.tr("tool.macro"),
+            )
+            .on_hover_text(loc.tr("tool.macro.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Erase,
+                loc.tr("tool.erase"),
+            )
+            .on_hover_text(loc.tr("tool.erase.hint"));
+            ui.selectable_value(
+                &mut state.current_tool,
+                EditorTool::Eyedropper,
+                loc.tr("tool.pick"),
+            )
+            .on_hover_text(loc.tr("tool.pick.hint"));
+            ui.selectable_value(&mut state.current_tool, EditorTool::Pan, loc.tr("tool.pan"))
+                .on_hover_text(loc.tr("tool.pan.hint"));
+
+            if matches!(state.current_tool, EditorTool::Paint | EditorTool::RectFill) {
+                ui.separator();
+                ui.label(loc.tr("paint.tile"));
+                ui.selectable_value(
+                    &mut state.current_kind,
+                    TileKind::Floor,
+                    loc.tr("kind.floor"),
+                );
+                ui.selectable_value(&mut state.current_kind, TileKind::Ramp, loc.tr("kind.ramp"));
+                ui.selectable_value(
+                    &mut state.current_kind,
+                    TileKind::Empty,
+                    loc.tr("kind.hole"),
+                );
+            }
 
             if state.current_tool == EditorTool::Paint {
                 ui.separator();
-                ui.label("Tile:");
-                ui.selectable_value(&mut state.current_kind, TileKind::Floor, "Floor");
-                ui.selectable_value(&mut state.current_kind, TileKind::Ramp, "Ramp");
+                ui.label(loc.tr("brush.size"));
+                ui.add(egui::DragValue::new(&mut state.paint_radius).range(1..=MAX_PAINT_RADIUS))
+                    .on_hover_text(loc.tr("brush.size.hint"));
+                for shape in BrushShape::ALL {
+                    ui.selectable_value(
+                        &mut state.paint_shape,
+                        shape,
+                        loc.tr(&format!("brush.{}", shape.identifier())),
+                    );
+                }
             }
 
             ui.separator();
-            ui.label("Elevation:");
+            ui.label(loc.tr("mirror.label"));
+            egui::ComboBox::from_id_source("symmetry")
+                .selected_text(loc.tr(&format!("mirror.{}", state.symmetry.identifier())))
+                .show_ui(ui, |ui| {
+                    for symmetry in Symmetry::ALL {
+                        ui.selectable_value(
+                            &mut state.symmetry,
+                            symmetry,
+                            loc.tr(&format!("mirror.{}", symmetry.identifier())),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text(loc.tr("mirror.hint"));
+
+            ui.separator();
+            ui.label(loc.tr("replace.label"));
+            let swap = &mut *state;
+            for (id, tile_type) in [
+                ("replace_from", &mut swap.replace_from),
+                ("replace_to", &mut swap.replace_to),
+            ] {
+                egui::ComboBox::from_id_source(id)
+                    .selected_text(tile_type.identifier())
+                    .width(64.0)
+                    .show_ui(ui, |ui| {
+                        for option in textures.layers() {
+                            ui.selectable_value(tile_type, option, option.identifier());
+                        }
+                    });
+            }
+            let scope = if selection.is_empty() {
+                loc.tr("scope.whole_map").to_string()
+            } else {
+                loc.format("scope.selected", &[("count", &selection.len())])
+            };
+            if ui
+                .add_enabled(
+                    state.replace_from != state.replace_to,
+                    egui::Button::new(loc.tr("replace.apply")),
+                )
+                .on_hover_text(loc.format("replace.apply.hint", &[("scope", &scope)]))
+                .clicked()
+            {
+                let swapped = if selection.is_empty() {
+                    let all = (0..state.map.height)
+                        .flat_map(|y| (0..state.map.width).map(move |x| (x, y)));
+                    replace::replace_texture(&state.map, all, state.replace_from, state.replace_to)
+                } else {
+                    replace::replace_texture(
+                        &state.map,
+                        selection.iter(),
+                        state.replace_from,
+                        state.replace_to,
+                    )
+                };
+                edits.send_batch(swapped);
+            }
+
+            if state.current_tool == EditorTool::Wand {
+                ui.separator();
+                ui.checkbox(&mut state.wand.match_type, loc.tr("wand.type"));
+                ui.checkbox(&mut state.wand.match_elevation, loc.tr("wand.elevation"));
+                ui.add_enabled_ui(state.wand.match_elevation, |ui| {
+                    ui.label(loc.tr("wand.tolerance"));
+                    ui.add(egui::DragValue::new(&mut state.wand.tolerance).range(0..=4));
+                });
+                ui.checkbox(&mut state.wand.contiguous, loc.tr("wand.contiguous"))
+                    .on_hover_text(loc.tr("wand.contiguous.hint"));
+            }
+
+            if state.current_tool == EditorTool::MovementCost {
+                ui.separator();
+                ui.label(loc.tr("cost.value"));
+                ui.add(
+                    egui::DragValue::new(&mut state.movement_cost)
+                        .range(MIN_MOVEMENT_COST..=MAX_MOVEMENT_COST),
+                );
+            }
+
+            if state.current_tool == EditorTool::Walkability {
+                ui.separator();
+                for walkability in Walkability::ALL {
+                    ui.selectable_value(
+                        &mut state.current_walkability,
+                        walkability,
+                        loc.tr(&format!("walkability.{}", walkability.identifier())),
+                    );
+                }
+            }
+
+            if state.current_tool == EditorTool::Light {
+                ui.separator();
+                light_settings(ui, &loc, &mut state.light);
+            }
+
+            if state.current_tool == EditorTool::Vegetation {
+                ui.separator();
+                ui.label(loc.tr("vegetation.density"));
+                ui.add(egui::Slider::new(&mut state.vegetation_density, 1..=255));
+                ui.label(loc.tr("noise.radius"));
+                ui.add(egui::DragValue::new(&mut state.vegetation_radius).range(0..=16));
+            }
+
+            if state.current_tool == EditorTool::Decal {
+                ui.separator();
+                for kind in DecalKind::ALL {
+                    ui.selectable_value(&mut state.decal.kind, kind, loc.tr(decal_label_key(kind)));
+                }
+                ui.label(loc.tr("decal.size"));
+                ui.add(
+                    egui::DragValue::new(&mut state.decal.size)
+                        .range(0.1..=16.0)
+                        .speed(0.05),
+                );
+                ui.checkbox(
+                    &mut state.decal_random_rotation,
+                    loc.tr("decal.random_rotation"),
+                );
+                if !state.decal_random_rotation {
+                    ui.label(loc.tr("decal.rotation"));
+                    ui.add(
+                        egui::DragValue::new(&mut state.decal.rotation)
+                            .range(0.0..=359.0)
+                            .suffix("°"),
+                    );
+                }
+                ui.label(loc.tr("decal.opacity"));
+                ui.add(egui::Slider::new(&mut state.decal.opacity, 0.05..=1.0));
+                let count = state.map.decals.len();
+                if count > 0
+                    && ui
+                        .button(loc.format("decal.clear", &[("count", &count)]))
+                        .clicked()
+                {
+                    edits.send(MapEdit::SetDecals(Vec::new()));
+                }
+            }
+
+            if state.current_tool == EditorTool::Prop {
+                ui.separator();
+                let registered = prop_registry.get(&state.prop.asset);
+                if let (None, Some(first)) = (registered, prop_registry.iter().next()) {
+                    state.prop.asset.clone_from(&first.id);
+                }
+                match prop_registry.get(&state.prop.asset) {
+                    Some(chosen) => {
+                        egui::ComboBox::from_id_source("prop_asset")
+                            .selected_text(&chosen.name)
+                            .show_ui(ui, |ui| {
+                                for entry in prop_registry.iter() {
+                                    ui.selectable_value(
+                                        &mut state.prop.asset,
+                                        entry.id.clone(),
+                                        &entry.name,
+                                    );
+                                }
+                            });
+                    }
+                    None => {
+                        ui.weak(loc.format("prop.none", &[("manifest", &PROP_MANIFEST)]));
+                    }
+                }
+                ui.label(loc.tr("prop.scale"));
+                ui.add(
+                    egui::DragValue::new(&mut state.prop.scale)
+                        .range(0.1..=10.0)
+                        .speed(0.01),
+                );
+                ui.label(loc.tr("decal.rotation"));
+                ui.add(
+                    egui::DragValue::new(&mut state.prop.rotation)
+                        .range(0.0..=359.0)
+                        .suffix("°"),
+                );
+
+                let selected = state
+                    .selected_prop
+                    .and_then(|index| Some((index, state.map.props.get(index)?.clone())));
+                if let Some((index, mut prop)) = selected {
+                    ui.separator();
+                    let name = prop_registry
+                        .get(&prop.asset)
+                        .map_or(prop.asset.as_str(), |entry| entry.name.as_str());
+                    ui.label(loc.format("prop.selected", &[("name", &name)]));
+                    ui.add(
+                        egui::DragValue::new(&mut prop.rotation)
+                            .range(0.0..=359.0)
+                            .suffix("°"),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut prop.scale)
+                            .range(0.1..=10.0)
+                            .speed(0.01),
+                    );
+                    if ui.button(loc.tr("common.remove")).clicked() {
+                        edits.send(MapEdit::SetProp { index, prop: None });
+                    } else if prop != state.map.props[index] {
+                        edits.send(MapEdit::SetProp {
+                            index,
+                            prop: Some(prop),
+                        });
+                    }
+                }
+            }
+
+            if state.current_tool == EditorTool::Start {
+                ui.separator();
+                ui.label(loc.tr("start.player"));
+                let mut player = state.active_player + 1;
+                ui.add(egui::DragValue::new(&mut player).range(1..=MAX_PLAYERS));
+                state.active_player = player - 1;
+                ui.label(loc.tr("start.team"));
+                let mut team = state.start_team + 1;
+                ui.add(egui::DragValue::new(&mut team).range(1..=MAX_PLAYERS));
+                state.start_team = team - 1;
+                ui.label(loc.tr("start.facing"));
+                ui.add(
+                    egui::DragValue::new(&mut state.start_facing)
+                        .range(0.0..=315.0)
+                        .speed(45.0)
+                        .suffix("°"),
+                );
+            }
+
+            if state.current_tool == EditorTool::Gradient {
+                ui.separator();
+                ui.label(loc.tr("gradient.from"));
+                ui.add(egui::DragValue::new(&mut state.gradient_from).range(-1..=3));
+                ui.label(loc.tr("gradient.to"));
+                ui.add(egui::DragValue::new(&mut state.gradient_to).range(-1..=3));
+            }
+
+            if state.current_tool == EditorTool::Noise {
+                ui.separator();
+                ui.label(loc.tr("noise.radius"));
+                ui.add(egui::DragValue::new(&mut state.noise_radius).range(0..=16));
+                ui.label(loc.tr("common.amplitude"));
+                ui.add(egui::DragValue::new(&mut state.noise_amplitude).range(1..=4));
+                ui.label(loc.tr("common.seed"));
+                ui.add(egui::DragValue::new(&mut state.noise_seed));
+            }
+
+            if state.current_tool == EditorTool::Sculpt {
+                ui.separator();
+                ui.label(loc.tr("noise.radius"));
+                ui.add(egui::DragValue::new(&mut state.sculpt_radius).range(0..=16));
+                ui.label(loc.tr("sculpt.strength"));
+                ui.add(egui::DragValue::new(&mut state.sculpt_strength).range(1..=3));
+            }
+
+            if state.current_tool == EditorTool::Smooth {
+                ui.separator();
+                ui.label(loc.tr("noise.radius"));
+                ui.add(egui::DragValue::new(&mut state.smooth_radius).range(0..=16));
+                ui.label(loc.tr("sculpt.strength"));
+                ui.add(egui::Slider::new(&mut state.smooth_strength, 0.1..=1.0));
+            }
+
+            if state.current_tool == EditorTool::Scatter {
+                ui.separator();
+                let scatter = &mut state.scatter;
+                ui.label(loc.tr("brush.size"));
+                ui.add(egui::DragValue::new(&mut scatter.radius).range(1..=MAX_PAINT_RADIUS))
+                    .on_hover_text(loc.tr("brush.size.hint"));
+                ui.label(loc.tr("scatter.coverage"));
+                ui.add(egui::Slider::new(&mut scatter.coverage, 1..=100).suffix("%"));
+                ui.checkbox(&mut scatter.weighted, loc.tr("scatter.weighted"))
+                    .on_hover_text(loc.tr("scatter.weighted.hint"));
+                if scatter.weighted {
+                    // Added layers would not fit in the toolbar.
+                    ui.menu_button(loc.tr("scatter.weights"), |ui| {
+                        egui::Grid::new("scatter_weights").show(ui, |ui| {
+                            for tile_type in textures.layers() {
+                                ui.label(tile_type.identifier());
+                                ui.add(
+                                    egui::DragValue::new(
+                                        &mut scatter.weights[tile_type.as_index()],
+                                    )
+                                    .range(0..=100),
+                                );
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+            }
+
+            if matches!(
+                state.current_tool,
+                EditorTool::Noise | EditorTool::Sculpt | EditorTool::Smooth
+            ) {
+                ui.label(loc.tr("brush.falloff"));
+                egui::ComboBox::from_id_source("brush_falloff")
+                    .selected_text(loc.tr(&format!("falloff.{}", state.brush_falloff.identifier())))
+                    .show_ui(ui, |ui| {
+                        for falloff in Falloff::ALL {
+                            ui.selectable_value(
+                                &mut state.brush_falloff,
+                                falloff,
+                                loc.tr(&format!("falloff.{}", falloff.identifier())),
+                            );
+                        }
+                    });
+            }
+
+            if state.current_tool == EditorTool::Plateau {
+                ui.separator();
+                ui.label(loc.tr("plateau.raise_by"));
+                for steps in 1..=3 {
+                    ui.selectable_value(&mut state.plateau_steps, steps, format!("{steps}"));
+                }
+            }
+
+            ui.separator();
+            ui.label(loc.tr("toolbar.elevation"));
             for e in 0..=3 {
                 ui.selectable_value(&mut state.current_elev, e, format!("{e}"));
             }
 
             ui.separator();
-            if ui.button("Save…").clicked() && state.save_dialog_task.is_none() {
-                let mut dialog = AsyncFileDialog::new().set_title("Save Map");
-                if let Some(path) = state.current_file_path.as_ref() {
-                    if let Some(parent) = path.parent() {
-                        dialog = dialog.set_directory(parent);
-                    }
-                    if let Some(file_name) = path.file_name().and_then(|name| name.to_str()) {
-                        dialog = dialog.set_file_name(file_name);
+            // Layers may have been removed since the last frame.
+            state.active_layer = state.active_layer.min(state.map.layer_count() - 1);
+            let layer_name = |layer: usize| match layer {
+                0 => loc.tr("layer.ground").to_string(),
+                number => loc.format("layer.upper", &[("number", &number)]),
+            };
+            egui::ComboBox::from_id_source("map_layer")
+                .selected_text(layer_name(state.active_layer))
+                .show_ui(ui, |ui| {
+                    for layer in 0..state.map.layer_count() {
+                        ui.selectable_value(&mut state.active_layer, layer, layer_name(layer));
                     }
+                })
+                .response
+                .on_hover_text(loc.tr("layer.hint"));
+            if ui
+                .button(loc.tr("layer.add"))
+                .on_hover_text(loc.tr("layer.add.hint"))
+                .clicked()
+            {
+                let mut layers = state.map.upper_layers.clone();
+                layers.push(state.map.empty_layer());
+                state.active_layer = layers.len();
+                edits.send(MapEdit::SetUpperLayers(layers));
+            }
+            if ui
+                .add_enabled(
+                    !state.map.upper_layers.is_empty(),
+                    egui::Button::new(loc.tr("layer.remove")),
+                )
+                .clicked()
+            {
+                let mut layers = state.map.upper_layers.clone();
+                layers.pop();
+                edits.send(MapEdit::SetUpperLayers(layers));
+            }
+
+            ui.separator();
+            if ui.button(loc.tr("file.new")).clicked() {
+                new_map.open = true;
+            }
+            if ui.button(loc.tr("file.save")).clicked() && state.save_dialog_task.is_none() {
+                state.save_dialog_task = Some(save_map_dialog(&state, &loc));
+            }
+            if ui
+                .button(loc.tr("file.save_delta"))
+                .on_hover_text(loc.tr("file.save_delta.hint"))
+                .clicked()
+                && state.delta_dialog_task.is_none()
+            {
+                let mut base_dialog =
+                    AsyncFileDialog::new().set_title(loc.tr("dialog.choose_base"));
+                let mut save_dialog = AsyncFileDialog::new().set_title(loc.tr("dialog.save_delta"));
+                if let Some(parent) = state.current_file_path.as_ref().and_then(|p| p.parent()) {
+                    base_dialog = base_dialog.set_directory(parent);
+                    save_dialog = save_dialog.set_directory(parent);
                 }
 
-                state.save_dialog_task = Some(IoTaskPool::get().spawn(async move {
-                    dialog
-                        .save_file()
-                        .await
-                        .map(|file| file.path().to_path_buf())
+                state.delta_dialog_task = Some(IoTaskPool::get().spawn(async move {
+                    let base = base_dialog.pick_file().await?.path().to_path_buf();
+                    let path = save_dialog.save_file().await?.path().to_path_buf();
+                    Some((base, path))
                 }));
             }
-            if ui.button("Export…").clicked()
+            if ui.button(loc.tr("file.export")).clicked()
                 && state.export_dialog_task.is_none()
                 && state.export_task.is_none()
             {
-                let mut dialog = AsyncFileDialog::new().set_title("Export Map");
-                dialog = dialog.add_filter("Tile Map Package", &["tmemapdata"]);
+                let mut dialog = AsyncFileDialog::new().set_title(loc.tr("dialog.export_map"));
+                dialog = dialog.add_filter(loc.tr("dialog.export_filter"), &["tmemapdata"]);
                 if let Some(path) = state.current_file_path.as_ref() {
                     if let Some(parent) = path.parent() {
                         dialog = dialog.set_directory(parent);
@@ -111,29 +865,244 @@ fn ui_panel(
                         .map(|file| file.path().to_path_buf())
                 }));
             }
-            if ui.button("Load…").clicked() && state.load_dialog_task.is_none() {
-                let mut dialog = AsyncFileDialog::new().set_title("Open Map");
-                if let Some(path) = state.current_file_path.as_ref() {
-                    if let Some(parent) = path.parent() {
-                        dialog = dialog.set_directory(parent);
+            if ui
+                .button(loc.tr("file.save_bundle"))
+                .on_hover_text(loc.tr("file.save_bundle.hint"))
+                .clicked()
+            {
+                bundle_requests.send(BundleRequest::Save);
+            }
+            if ui
+                .button(loc.tr("file.open_bundle"))
+                .on_hover_text(loc.tr("file.open_bundle.hint"))
+                .clicked()
+            {
+                file_actions.send(FileAction::OpenBundle);
+            }
+            if ui
+                .button(loc.tr("file.export_gltf"))
+                .on_hover_text(loc.tr("file.export_gltf.hint"))
+                .clicked()
+            {
+                export_requests.send(ExportFile::Gltf);
+            }
+            if ui
+                .button(loc.tr("file.export_obj"))
+                .on_hover_text(loc.tr("file.export_obj.hint"))
+                .clicked()
+            {
+                export_requests.send(ExportFile::Obj);
+            }
+            if ui
+                .button(loc.tr("file.export_heightmap"))
+                .on_hover_text(loc.tr("file.export_heightmap.hint"))
+                .clicked()
+            {
+                export_requests.send(ExportFile::Heightmap);
+            }
+            if ui
+                .button(loc.tr("file.export_splatmap"))
+                .on_hover_text(loc.tr("file.export_splatmap.hint"))
+                .clicked()
+            {
+                export_requests.send(ExportFile::Splatmap);
+            }
+            if ui.button(loc.tr("file.load")).clicked() && state.load_dialog_task.is_none() {
+                file_actions.send(FileAction::Load);
+            }
+            if ui
+                .button(loc.tr("file.import_map"))
+                .on_hover_text(loc.tr("file.import_map.hint"))
+                .clicked()
+            {
+                import_requests.send(ImportMap);
+            }
+            ui.menu_button(loc.tr("file.recent"), |ui| {
+                if recent.paths.is_empty() {
+                    ui.weak(loc.tr("file.recent_none"));
+                }
+                for path in &recent.paths {
+                    let name = path.file_name().map(|name| name.to_string_lossy());
+                    if ui
+                        .button(name.unwrap_or_default())
+                        .on_hover_text(path.display().to_string())
+                        .clicked()
+                    {
+                        ui.close_menu();
+                        file_actions.send(FileAction::Open(path.clone()));
                     }
                 }
+                ui.separator();
+                let mut reopen_last = recent.reopen_last;
+                if ui
+                    .checkbox(&mut reopen_last, loc.tr("file.reopen_last"))
+                    .on_hover_text(loc.tr("file.reopen_last.hint"))
+                    .changed()
+                {
+                    recent.reopen_last = reopen_last;
+                }
+                if ui
+                    .add_enabled(
+                        !recent.paths.is_empty(),
+                        egui::Button::new(loc.tr("file.recent_clear")),
+                    )
+                    .clicked()
+                {
+                    recent.paths.clear();
+                }
+            });
 
-                state.load_dialog_task = Some(IoTaskPool::get().spawn(async move {
-                    dialog
-                        .pick_file()
-                        .await
-                        .map(|file| file.path().to_path_buf())
-                }));
+            if let Some(base) = state.delta_base.clone() {
+                let name = base.file_name().map(|name| name.to_string_lossy());
+                ui.label(loc.format("file.delta_of", &[("name", &name.unwrap_or_default())]));
+                if ui
+                    .small_button("✖")
+                    .on_hover_text(loc.tr("file.delta_clear.hint"))
+                    .clicked()
+                {
+                    state.delta_base = None;
+                }
+            }
+
+            ui.menu_button(loc.tr("map.menu"), |ui| {
+                for transform in MapTransform::ALL {
+                    if ui.button(loc.tr(transform.label_key())).clicked() {
+                        ui.close_menu();
+                        edits.send(MapEdit::Transform(transform));
+                    }
+                }
+            });
+
+            ui.separator();
+            let undo = history.undo_label();
+            let mut button =
+                ui.add_enabled(undo.is_some(), egui::Button::new(loc.tr("history.undo")));
+            if let Some(label) = undo {
+                button = button
+                    .on_hover_text(loc.format("history.undo.hint", &[("action", &loc.tr(label))]));
+            }
+            if button.clicked() {
+                edits.send(MapEdit::Undo);
+            }
+            let redo = history.redo_label();
+            let mut button =
+                ui.add_enabled(redo.is_some(), egui::Button::new(loc.tr("history.redo")));
+            if let Some(label) = redo {
+                button = button
+                    .on_hover_text(loc.format("history.redo.hint", &[("action", &loc.tr(label))]));
+            }
+            if button.clicked() {
+                edits.send(MapEdit::Redo);
+            }
+            let mut depth = history.depth();
+            ui.add(
+                egui::DragValue::new(&mut depth)
+                    .range(1..=MAX_HISTORY_DEPTH)
+                    .prefix(loc.tr("history.depth")),
+            )
+            .on_hover_text(loc.tr("history.depth.hint"));
+            if depth != history.depth() {
+                history.set_depth(depth);
             }
 
             ui.separator();
-            ui.checkbox(&mut state.show_grid, "Gridlines");
+            ui.label(loc.tr("map.tile_size"));
+            let mut tile_size = state.map.tile_size;
+            ui.add(
+                egui::DragValue::new(&mut tile_size)
+                    .range(MIN_TILE_SIZE..=MAX_TILE_SIZE)
+                    .speed(0.05),
+            )
+            .on_hover_text(loc.tr("map.tile_size.hint"));
+            if tile_size != state.map.tile_size {
+                edits.send(MapEdit::SetTileSize(tile_size));
+            }
+
+            let mut water = state.map.water_level;
+            ui.horizontal(|ui| {
+                let mut flooded = water.is_some();
+                ui.checkbox(&mut flooded, loc.tr("map.water"))
+                    .on_hover_text(loc.tr("map.water.hint"));
+                if flooded != water.is_some() {
+                    water = flooded.then_some(0.0);
+                }
+                if let Some(level) = water.as_mut() {
+                    ui.add(
+                        egui::DragValue::new(level)
+                            .range((MIN_ELEVATION - HOLE_DEPTH) as f32..=MAX_ELEVATION as f32)
+                            .speed(0.05),
+                    );
+                }
+            });
+            if water != state.map.water_level {
+                edits.send(MapEdit::SetWaterLevel(water));
+            }
+
+            ui.separator();
+            if ui
+                .button(loc.tr("lightmap.bake"))
+                .on_hover_text(loc.tr("lightmap.bake.hint"))
+                .clicked()
+            {
+                bake_requests.send(BakeLightmap);
+            }
+            // Copied so the mesh is only rebuilt when a value really changes.
+            let mut shading = *smoothing;
+            ui.checkbox(&mut shading.enabled, loc.tr("view.smooth_normals"))
+                .on_hover_text(loc.tr("view.smooth_normals.hint"));
+            ui.add_enabled(
+                shading.enabled,
+                egui::DragValue::new(&mut shading.max_angle)
+                    .range(1.0..=89.0)
+                    .suffix("°"),
+            );
+            if shading != *smoothing {
+                *smoothing = shading;
+            }
+            let mut cliff_texturing = *cliffs;
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut cliff_texturing.enabled, loc.tr("view.cliff_slopes"))
+                    .on_hover_text(loc.tr("view.cliff_slopes.hint"));
+                ui.add_enabled_ui(cliff_texturing.enabled, |ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut cliff_texturing.start)
+                            .range(0.0..=89.0)
+                            .suffix("°"),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut cliff_texturing.full)
+                            .range(1.0..=90.0)
+                            .suffix("°"),
+                    );
+                });
+            });
+            if cliff_texturing != *cliffs {
+                *cliffs = cliff_texturing;
+            }
+            let mut lod_enabled = lod.enabled;
+            ui.checkbox(&mut lod_enabled, loc.tr("view.terrain_lod"))
+                .on_hover_text(loc.tr("view.terrain_lod.hint"));
+            if lod_enabled != lod.enabled {
+                lod.enabled = lod_enabled;
+            }
+
+            ui.separator();
+            ui.checkbox(&mut state.show_grid, loc.tr("view.gridlines"));
+            ui.checkbox(&mut state.show_rulers, loc.tr("view.rulers"))
+                .on_hover_text(loc.tr("view.rulers.hint"));
+            ui.checkbox(&mut state.show_walkability, loc.tr("view.walkability"))
+                .on_hover_text(loc.tr("view.walkability.hint"));
+            ui.checkbox(&mut state.show_chunk_culling, loc.tr("view.chunk_culling"))
+                .on_hover_text(loc.tr("view.chunk_culling.hint"));
+            ui.toggle_value(&mut notifications.show_log, loc.tr("view.log"))
+                .on_hover_text(loc.tr("view.log.hint"));
+            ui.checkbox(&mut split_view.enabled, loc.tr("view.split"))
+                .on_hover_text(loc.tr("view.split.hint"));
         });
 
         if !palette_items.is_empty() {
             ui.separator();
-            ui.collapsing("Textures", |ui| {
+            ui.collapsing(loc.tr("panel.textures"), |ui| {
                 const COLUMNS: usize = 4;
                 let grid = egui::Grid::new("texture_palette_grid")
                     .spacing([6.0, 6.0])
@@ -185,11 +1154,514 @@ fn ui_panel(
                         grid_ui.end_row();
                     }
                 });
+
+                // Roads draw over whatever texture is beneath them.
+                ui.horizontal(|ui| {
+                    for surface in RoadSurface::ALL {
+                        let selected =
+                            state.current_tool == EditorTool::Road && state.current_road == surface;
+                        let response = road_swatch(ui, surface, selected)
+                            .on_hover_text(loc.tr(road_label_key(surface)));
+                        if response.clicked() {
+                            state.current_road = surface;
+                            state.current_tool = EditorTool::Road;
+                        }
+                    }
+                });
             });
         }
+        ui.separator();
+        ui.collapsing(loc.tr("panel.triggers"), |ui| {
+            ui.checkbox(&mut state.show_triggers, loc.tr("triggers.show"));
+            let mut triggers = state.map.triggers.clone();
+            let mut removed = None;
+            for (index, area) in triggers.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    let active = state.active_trigger == Some(index);
+                    if ui
+                        .radio(active, "")
+                        .on_hover_text(loc.tr("triggers.paint_target"))
+                        .clicked()
+                    {
+                        state.active_trigger = Some(index);
+                        state.current_tool = EditorTool::Trigger;
+                    }
+                    ui.color_edit_button_srgb(&mut area.color);
+                    ui.add(egui::TextEdit::singleline(&mut area.name).desired_width(90.0));
+                    ui.label(loc.format("triggers.tile_count", &[("count", &area.tiles.len())]));
+                    egui::ComboBox::from_id_source(("trigger_player", index))
+                        .width(70.0)
+                        .selected_text(match area.player {
+                            Some(player) => {
+                                loc.format("common.player", &[("number", &(player + 1))])
+                            }
+                            None => loc.tr("common.neutral").to_string(),
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut area.player, None, loc.tr("common.neutral"));
+                            for player in 0..MAX_PLAYERS {
+                                ui.selectable_value(
+                                    &mut area.player,
+                                    Some(player),
+                                    loc.format("common.player", &[("number", &(player + 1))]),
+                                );
+                            }
+                        });
+                    if ui
+                        .small_button("✖")
+                        .on_hover_text(loc.tr("common.remove"))
+                        .clicked()
+                    {
+                        removed = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = removed {
+                triggers.remove(index);
+                state.active_trigger = match state.active_trigger {
+                    Some(active) if active == index => None,
+                    Some(active) if active > index => Some(active - 1),
+                    other => other,
+                };
+            }
+            ui.horizontal(|ui| {
+                if ui.button(loc.tr("triggers.add")).clicked() {
+                    let index = triggers.len();
+                    triggers.push(TriggerArea {
+                        name: format!("trigger{}", index + 1),
+                        color: TRIGGER_PALETTE[index % TRIGGER_PALETTE.len()],
+                        tiles: Default::default(),
+                        player: None,
+                    });
+                    state.active_trigger = Some(index);
+                    state.current_tool = EditorTool::Trigger;
+                }
+                let active = state.active_trigger.filter(|&index| index < triggers.len());
+                ui.add_enabled_ui(active.is_some() && !selection.is_empty(), |ui| {
+                    if ui.button(loc.tr("triggers.add_selection")).clicked() {
+                        edits.send_batch(active.into_iter().flat_map(|trigger| {
+                            selection.iter().map(move |(x, y)| MapEdit::SetTriggerTile {
+                                trigger,
+                                x,
+                                y,
+                                inside: true,
+                            })
+                        }));
+                    }
+                });
+            });
+            if triggers != state.map.triggers {
+                edits.send(MapEdit::SetTriggers(triggers));
+            }
+        });
+
+        ui.separator();
+        ui.collapsing(loc.tr("panel.players"), |ui| {
+            if state.map.starts.is_empty() {
+                ui.label(loc.tr("players.no_starts"));
+            }
+            for start in &state.map.starts {
+                ui.label(loc.format(
+                    "players.start",
+                    &[
+                        ("player", &(start.player + 1)),
+                        ("team", &(start.team + 1)),
+                        ("x", &start.x),
+                        ("y", &start.y),
+                        ("facing", &start.facing),
+                    ],
+                ));
+            }
+            for problem in players::player_problems(&state.map) {
+                ui.colored_label(egui::Color32::from_rgb(220, 80, 80), problem);
+            }
+        });
+
+        ui.separator();
+        ui.collapsing(loc.tr("panel.lights"), |ui| {
+            ui.checkbox(&mut state.show_lights, loc.tr("lights.show"));
+            if state.map.lights.is_empty() {
+                ui.weak(loc.tr("lights.none"));
+            }
+            let mut lights = state.map.lights.clone();
+            let mut removed = None;
+            for (index, light) in lights.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui
+                        .small_button("✖")
+                        .on_hover_text(loc.tr("common.remove"))
+                        .clicked()
+                    {
+                        removed = Some(index);
+                    }
+                    egui::CollapsingHeader::new(
+                        loc.format("lights.entry", &[("x", &light.x), ("y", &light.y)]),
+                    )
+                    .id_source(("map_light", index))
+                    .show(ui, |ui| {
+                        ui.horizontal_wrapped(|ui| light_settings(ui, &loc, light));
+                    });
+                });
+            }
+            if let Some(index) = removed {
+                lights.remove(index);
+            }
+            if lights != state.map.lights {
+                edits.send(MapEdit::SetLights(lights));
+            }
+        });
+
+        ui.separator();
+        ui.collapsing(loc.tr("panel.weather"), |ui| {
+            let mut weather = state.map.weather.clone();
+            ui.checkbox(&mut state.show_weather, loc.tr("weather.preview"))
+                .on_hover_text(loc.tr("weather.preview.hint"));
+            ui.horizontal(|ui| {
+                for (kind, key) in [
+                    (WeatherKind::Clear, "weather.clear"),
+                    (WeatherKind::Rain, "weather.rain"),
+                    (WeatherKind::Snow, "weather.snow"),
+                    (WeatherKind::Dust, "weather.dust"),
+                ] {
+                    ui.selectable_value(&mut weather.kind, kind, loc.tr(key));
+                }
+            });
+            ui.add_enabled_ui(weather.kind != WeatherKind::Clear, |ui| {
+                ui.add(
+                    egui::Slider::new(&mut weather.intensity, 0.0..=1.0)
+                        .text(loc.tr("weather.intensity")),
+                );
+                ui.horizontal(|ui| {
+                    ui.label(loc.tr("weather.wind"));
+                    ui.add(
+                        egui::DragValue::new(&mut weather.wind_direction)
+                            .range(0.0..=359.0)
+                            .suffix("°"),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut weather.wind_speed)
+                            .range(0.0..=20.0)
+                            .speed(0.1),
+                    );
+                });
+            });
+            if weather != state.map.weather {
+                edits.send(MapEdit::SetWeather(weather));
+            }
+        });
+
+        ui.separator();
+        ui.collapsing(loc.tr("panel.vegetation"), |ui| {
+            ui.checkbox(&mut state.show_vegetation, loc.tr("vegetation.show"));
+            let painted = state.map.vegetation.iter().filter(|&&d| d > 0).count();
+            ui.label(loc.format("vegetation.painted", &[("count", &painted)]));
+            if ui
+                .add_enabled(painted > 0, egui::Button::new(loc.tr("common.clear")))
+                .clicked()
+            {
+                let width = state.map.width;
+                edits.send_batch(
+                    state
+                        .map
+                        .vegetation
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, density)| **density > 0)
+                        .map(|(i, _)| MapEdit::SetVegetation {
+                            x: i as u32 % width,
+                            y: i as u32 / width,
+                            density: 0,
+                        }),
+                );
+            }
+        });
+
+        ui.separator();
+        ui.collapsing(loc.tr("panel.reference"), |ui| {
+            ui.horizontal(|ui| {
+                if ui
+                    .button(loc.tr("file.load"))
+                    .on_hover_text(loc.tr("reference.load.hint"))
+                    .clicked()
+                    && reference.dialog_task.is_none()
+                {
+                    let mut dialog = AsyncFileDialog::new()
+                        .set_title(loc.tr("dialog.open_reference"))
+                        .add_filter(
+                            loc.tr("dialog.reference_filter"),
+                            &[
+                                "tme",
+                                text_map::EXTENSION,
+                                JSON_EXTENSION,
+                                "png",
+                                "jpg",
+                                "jpeg",
+                            ],
+                        );
+                    if let Some(parent) = state.current_file_path.as_ref().and_then(|p| p.parent())
+                    {
+                        dialog = dialog.set_directory(parent);
+                    }
+                    reference.dialog_task = Some(IoTaskPool::get().spawn(async move {
+                        dialog
+                            .pick_file()
+                            .await
+                            .map(|file| file.path().to_path_buf())
+                    }));
+                }
+                if ui
+                    .add_enabled(
+                        reference.path.is_some(),
+                        egui::Button::new(loc.tr("common.clear")),
+                    )
+                    .clicked()
+                {
+                    reference.clear = true;
+                }
+            });
+            if let Some(name) = reference.path.as_ref().and_then(|path| path.file_name()) {
+                ui.label(loc.format("reference.showing", &[("name", &name.to_string_lossy())]));
+            }
+            ui.checkbox(&mut reference.visible, loc.tr("reference.show"));
+            ui.add(
+                egui::Slider::new(&mut reference.opacity, 0.05..=1.0)
+                    .text(loc.tr("reference.opacity")),
+            );
+        });
+
+        ui.separator();
+        ui.collapsing(loc.tr("panel.histogram"), |ui| {
+            let histogram = Histogram::of(&state.map);
+            let solid = histogram.solid().max(1) as f32;
+            let bar = |ui: &mut egui::Ui, label: String, count: u32| {
+                ui.horizontal(|ui| {
+                    ui.add_sized([64.0, 16.0], egui::Label::new(label));
+                    ui.add(
+                        egui::ProgressBar::new(count as f32 / solid)
+                            .desired_width(160.0)
+                            .text(format!("{count} ({:.0}%)", count as f32 / solid * 100.0)),
+                    );
+                });
+            };
+            ui.label(loc.tr("histogram.elevation"));
+            for (level, &count) in histogram.elevations.iter().enumerate().rev() {
+                bar(ui, format!("{}", level as i8 + MIN_ELEVATION), count);
+            }
+            ui.label(loc.tr("histogram.tile_type"));
+            for tile_type in textures.layers() {
+                let count = histogram.tile_types[tile_type.as_index()];
+                bar(ui, tile_type.identifier().to_string(), count);
+            }
+            ui.label(loc.format("histogram.holes", &[("count", &histogram.holes)]));
+        });
+
+        ui.separator();
+        ui.collapsing(loc.tr("panel.statistics"), |ui| {
+            let report = BalanceReport::of(&state.map);
+            ui.label(loc.tr("stats.walkable"));
+            if report.spawn_zones.is_empty() {
+                ui.weak(loc.tr("players.no_starts"));
+            }
+            for zone in &report.spawn_zones {
+                ui.label(loc.format(
+                    "stats.spawn",
+                    &[("player", &(zone.player + 1)), ("tiles", &zone.walkable)],
+                ));
+            }
+            ui.label(loc.tr("stats.ramps"));
+            if report.plateaus.is_empty() {
+                ui.weak(loc.tr("stats.no_plateaus"));
+            }
+            for plateau in &report.plateaus {
+                let text = loc.format(
+                    "stats.plateau",
+                    &[
+                        ("level", &plateau.elevation),
+                        ("x", &plateau.anchor.0),
+                        ("y", &plateau.anchor.1),
+                        ("tiles", &plateau.tiles),
+                        ("ramps", &plateau.ramps),
+                    ],
+                );
+                if plateau.ramps == 0 {
+                    ui.colored_label(egui::Color32::from_rgb(220, 80, 80), text);
+                } else {
+                    ui.label(text);
+                }
+            }
+            ui.label(loc.format(
+                "stats.water",
+                &[("percent", &format!("{:.1}", report.water_coverage * 100.0))],
+            ));
+            if let Some((symmetry, score)) = report.symmetry {
+                ui.label(loc.format(
+                    "stats.symmetry",
+                    &[
+                        ("percent", &format!("{:.1}", score * 100.0)),
+                        ("kind", &loc.tr(symmetry.label_key())),
+                    ],
+                ));
+            }
+        });
+
+        ui.separator();
+        ui.collapsing(loc.tr("panel.locks"), |ui| {
+            ui.checkbox(&mut state.show_locks, loc.tr("locks.show"));
+            ui.horizontal(|ui| {
+                ui.add_enabled_ui(!selection.is_empty(), |ui| {
+                    for (label, locked) in [
+                        ("locks.lock_selection", true),
+                        ("locks.unlock_selection", false),
+                    ] {
+                        if ui.button(loc.tr(label)).clicked() {
+                            edits.send_batch(selection.iter().map(|(x, y)| MapEdit::SetLocked {
+                                x,
+                                y,
+                                locked,
+                            }));
+                        }
+                    }
+                });
+            });
+        });
+
+        ui.separator();
+        ui.collapsing(loc.tr("panel.variation"), |ui| {
+            ui.horizontal(|ui| {
+                ui.label(loc.tr("variation.type"));
+                egui::ComboBox::from_id_source("variation_type")
+                    .selected_text(state.variation_type.identifier())
+                    .show_ui(ui, |ui| {
+                        for tile_type in textures.layers() {
+                            ui.selectable_value(
+                                &mut state.variation_type,
+                                tile_type,
+                                tile_type.identifier(),
+                            );
+                        }
+                    });
+                ui.label(loc.tr("common.seed"));
+                ui.add(egui::DragValue::new(&mut state.variation_seed));
+                if ui
+                    .button("🎲")
+                    .on_hover_text(loc.tr("common.new_seed"))
+                    .clicked()
+                {
+                    state.variation_seed = fastrand::u64(..);
+                }
+            });
+
+            let scope = if selection.is_empty() {
+                loc.tr("scope.whole_map").to_string()
+            } else {
+                loc.format("scope.selected", &[("count", &selection.len())])
+            };
+            if ui
+                .button(loc.format("variation.reroll", &[("scope", &scope)]))
+                .on_hover_text(loc.tr("variation.reroll.hint"))
+                .clicked()
+            {
+                let rolled = if selection.is_empty() {
+                    let all = (0..state.map.height)
+                        .flat_map(|y| (0..state.map.width).map(move |x| (x, y)));
+                    variation::reroll(&state.map, all, state.variation_type, state.variation_seed)
+                } else {
+                    variation::reroll(
+                        &state.map,
+                        selection.iter(),
+                        state.variation_type,
+                        state.variation_seed,
+                    )
+                };
+                edits.send_batch(rolled);
+            }
+        });
+
+        ui.collapsing(loc.tr("panel.generate"), |ui| {
+            let settings = &mut generator.settings;
+            let before = settings.clone();
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("generator_kind")
+                    .selected_text(loc.tr(settings.kind.label_key()))
+                    .show_ui(ui, |ui| {
+                        for kind in GeneratorKind::ALL {
+                            ui.selectable_value(&mut settings.kind, kind, loc.tr(kind.label_key()));
+                        }
+                    });
+                match settings.kind {
+                    GeneratorKind::Noise => {
+                        ui.label(loc.tr("common.amplitude"));
+                        ui.add(egui::DragValue::new(&mut settings.amplitude).range(1..=4));
+                        ui.label(loc.tr("generate.frequency"));
+                        ui.add(
+                            egui::DragValue::new(&mut settings.frequency)
+                                .range(0.05..=1.0)
+                                .speed(0.01),
+                        );
+                        ui.label(loc.tr("common.seed"));
+                        ui.add(egui::DragValue::new(&mut settings.seed));
+                        if ui
+                            .button("🎲")
+                            .on_hover_text(loc.tr("common.new_seed"))
+                            .clicked()
+                        {
+                            settings.seed = fastrand::u64(..);
+                        }
+                    }
+                    GeneratorKind::Erosion | GeneratorKind::Smooth => {
+                        ui.label(loc.tr("generate.passes"));
+                        ui.add(egui::DragValue::new(&mut settings.iterations).range(1..=8));
+                    }
+                }
+            });
+            let tweaked = *settings != before;
+
+            let scope = if selection.is_empty() {
+                loc.tr("scope.whole_map").to_string()
+            } else {
+                loc.format("scope.selected", &[("count", &selection.len())])
+            };
+            if generator.is_active() {
+                generator.dirty |= tweaked;
+                ui.horizontal(|ui| {
+                    ui.label(loc.format("generate.previewing", &[("scope", &scope)]));
+                    if ui.button(loc.tr("common.apply")).clicked() {
+                        generator.action = Some(PreviewAction::Apply);
+                    }
+                    if ui.button(loc.tr("common.cancel")).clicked() {
+                        generator.action = Some(PreviewAction::Cancel);
+                    }
+                });
+            } else if ui
+                .button(loc.format("generate.preview", &[("scope", &scope)]))
+                .on_hover_text(loc.tr("generate.preview.hint"))
+                .clicked()
+            {
+                generator.action = Some(PreviewAction::Start);
+            }
+        });
+
+        ui.collapsing(loc.tr("panel.settings"), |ui| {
+            ui.horizontal(|ui| {
+                ui.label(loc.tr("settings.language"));
+                let current = loc.language().to_string();
+                let mut chosen = current.clone();
+                egui::ComboBox::from_id_source("ui_language")
+                    .selected_text(loc.tr("language.name"))
+                    .show_ui(ui, |ui| {
+                        for (code, name) in loc.languages() {
+                            ui.selectable_value(&mut chosen, code.to_string(), name);
+                        }
+                    });
+                if chosen != current {
+                    switch_language = Some(chosen);
+                }
+            });
+        });
+
         if let Some(path) = state.current_file_path.as_ref() {
             ui.separator();
-            ui.label(format!("Current map: {}", path.display()));
+            ui.label(loc.format("file.current", &[("path", &path.display())]));
         }
 
         if let Some(status) = state.last_export_status.as_ref() {
@@ -204,15 +1676,53 @@ fn ui_panel(
             }
         }
     });
+    if let Some(language) = switch_language {
+        loc.set_language(&language);
+    }
 
     if let Some(task) = state.save_dialog_task.as_mut() {
         if task.is_finished() {
             if let Some(path) = block_on(state.save_dialog_task.take().unwrap()) {
-                if let Err(err) = save_map(&path, &state.map) {
-                    eprintln!("Failed to save map: {err:?}");
+                let result = match state.delta_base.as_ref() {
+                    Some(base) => save_delta(&path, base, &state.map),
+                    None => save_map(&path, &state.map),
+                };
+                if let Err(err) = result {
+                    notify.send(Notify::error(
+                        loc.format("notify.save_failed", &[("error", &err)]),
+                    ));
                 } else {
+                    notify.send(Notify::info(
+                        loc.format("notify.saved", &[("path", &path.display())]),
+                    ));
+                    if let Err(err) = map_browser::save_thumbnail(&path, &state.map) {
+                        warn!("Failed to save thumbnail for {}: {err}", path.display());
+                    }
                     state.current_file_path = Some(path);
+                    state.unsaved_changes = false;
+                }
+            }
+        }
+    }
+
+    if state
+        .delta_dialog_task
+        .as_ref()
+        .is_some_and(|task| task.is_finished())
+    {
+        let picked = block_on(state.delta_dialog_task.take().unwrap());
+        if let Some((base, path)) = picked {
+            if let Err(err) = save_delta(&path, &base, &state.map) {
+                notify.send(Notify::error(
+                    loc.format("notify.delta_failed", &[("error", &err)]),
+                ));
+            } else {
+                if let Err(err) = map_browser::save_thumbnail(&path, &state.map) {
+                    warn!("Failed to save thumbnail for {}: {err}", path.display());
                 }
+                state.current_file_path = Some(path);
+                state.delta_base = Some(base);
+                state.unsaved_changes = false;
             }
         }
     }
@@ -224,20 +1734,31 @@ fn ui_panel(
                 match export::collect_texture_descriptors(&state.map, textures.as_ref()) {
                     Ok((descriptors, wall_descriptor)) => {
                         let map_clone = state.map.clone();
+                        let smoothing = *smoothing;
                         let export_name = infer_export_name(&state, &export_path);
                         let export_path_clone = export_path.clone();
-                        let splat_png_result = if let Some(runtime) = runtime_splat.as_ref() {
-                            if let Some(image) = images.get(&runtime.handle) {
-                                export::encode_splatmap_png(image)
-                            } else {
-                                export::build_map_splatmap_png(&map_clone)
-                            }
-                        } else {
-                            export::build_map_splatmap_png(&map_clone)
+                        // The runtime splatmap asset lags behind partial
+                        // rewrites, which go straight to the GPU.
+                        let splat_png_result = export::build_map_splatmap_pngs(&map_clone);
+
+                        let lightmap_png = match lightmap
+                            .as_ref()
+                            .and_then(|baked| images.get(&baked.handle))
+                        {
+                            Some(image) => match export::encode_lightmap_png(image) {
+                                Ok(bytes) => Some(bytes),
+                                Err(err) => {
+                                    notify.send(Notify::warning(
+                                        loc.format("notify.lightmap_skipped", &[("error", &err)]),
+                                    ));
+                                    None
+                                }
+                            },
+                            None => None,
                         };
 
                         match splat_png_result {
-                            Ok(splat_png) => {
+                            Ok(splat_pngs) => {
                                 state.last_export_status = None;
                                 state.export_task = Some(IoTaskPool::get().spawn(async move {
                                     export::export_package(
@@ -246,22 +1767,30 @@ fn ui_panel(
                                         export_name,
                                         descriptors,
                                         wall_descriptor,
-                                        splat_png,
+                                        splat_pngs,
+                                        lightmap_png,
+                                        smoothing,
                                     )
                                     .map(|_| export_path_clone)
                                 }));
                             }
                             Err(err) => {
-                                eprintln!("Failed to prepare splatmap for export: {err:?}");
-                                state.last_export_status =
-                                    Some(ExportStatus::Failure(format!("Export failed: {err}")));
+                                notify.send(Notify::error(
+                                    loc.format("notify.splat_failed", &[("error", &err)]),
+                                ));
+                                state.last_export_status = Some(ExportStatus::Failure(
+                                    loc.format("notify.export_status_failed", &[("error", &err)]),
+                                ));
                             }
                         }
                     }
                     Err(err) => {
-                        eprintln!("Failed to gather textures for export: {err:?}");
-                        state.last_export_status =
-                            Some(ExportStatus::Failure(format!("Export failed: {err}")));
+                        notify.send(Notify::error(
+                            loc.format("notify.textures_failed", &[("error", &err)]),
+                        ));
+                        state.last_export_status = Some(ExportStatus::Failure(
+                            loc.format("notify.export_status_failed", &[("error", &err)]),
+                        ));
                     }
                 }
             }
@@ -272,15 +1801,17 @@ fn ui_panel(
         if task.is_finished() {
             match block_on(state.export_task.take().unwrap()) {
                 Ok(path) => {
-                    state.last_export_status = Some(ExportStatus::Success(format!(
-                        "Exported map to {}",
-                        path.display()
-                    )));
+                    let message = loc.format("notify.exported", &[("path", &path.display())]);
+                    notify.send(Notify::info(message.clone()));
+                    state.last_export_status = Some(ExportStatus::Success(message));
                 }
                 Err(err) => {
-                    eprintln!("Failed to export map: {err:?}");
-                    state.last_export_status =
-                        Some(ExportStatus::Failure(format!("Export failed: {err}")));
+                    notify.send(Notify::error(
+                        loc.format("notify.export_failed", &[("error", &err)]),
+                    ));
+                    state.last_export_status = Some(ExportStatus::Failure(
+                        loc.format("notify.export_status_failed", &[("error", &err)]),
+                    ));
                 }
             }
         }
@@ -289,18 +1820,169 @@ fn ui_panel(
     if let Some(task) = state.load_dialog_task.as_mut() {
         if task.is_finished() {
             if let Some(path) = block_on(state.load_dialog_task.take().unwrap()) {
-                match load_map(&path) {
-                    Ok(m) => {
-                        state.map = m;
-                        state.map_dirty = true;
-                        state.current_file_path = Some(path);
+                let opened = state.open_map(path, &mut edits);
+                if let Err(err) = opened {
+                    notify.send(Notify::error(
+                        loc.format("notify.load_failed", &[("error", &err)]),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Editors for a light's kind, look and placement, shared by the light tool's
+/// settings and the placed lights list.
+/// Saves the selection as a named stamp and lists saved stamps; choosing
+/// one loads it into the Paste tool.
+#[allow(clippy::too_many_arguments)]
+fn stamps_window(
+    mut egui_ctx: EguiContexts,
+    mut state: ResMut<crate::editor::EditorState>,
+    selection: Res<Selection>,
+    mut clipboard: ResMut<Clipboard>,
+    mut library: ResMut<StampLibrary>,
+    temporary: Res<TemporaryTool>,
+    text: UiText,
+) {
+    let UiText {
+        localization: loc,
+        mut notify,
+    } = text;
+    let library = &mut *library;
+    let ctx = egui_ctx.ctx_mut().clone();
+    egui::Window::new(loc.tr("stamps.title"))
+        .id(egui::Id::new("stamps"))
+        .default_open(false)
+        .show(&ctx, |ui| {
+            if !library.scanned {
+                library.scan();
+            }
+            ui.horizontal(|ui| {
+                ui.label(loc.tr("stamps.name"));
+                ui.text_edit_singleline(&mut library.name);
+            });
+            let name = library.name.trim().to_string();
+            if ui
+                .add_enabled(
+                    !name.is_empty() && !selection.is_empty(),
+                    egui::Button::new(loc.tr("stamps.save")),
+                )
+                .on_hover_text(loc.tr("stamps.save.hint"))
+                .clicked()
+            {
+                let stamp = Stamp {
+                    name: name.clone(),
+                    tiles: selection::stamp_tiles(&state.map, &selection),
+                };
+                let file_name: String = name
+                    .chars()
+                    .map(|c| {
+                        if c.is_alphanumeric() || c == '-' {
+                            c
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect();
+                let path = Path::new(STAMP_DIR).join(format!("{file_name}.{STAMP_EXTENSION}"));
+                let saved = std::fs::create_dir_all(STAMP_DIR)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|()| save_stamp(&path, &stamp));
+                match saved {
+                    Ok(()) => {
+                        notify.send(Notify::info(
+                            loc.format("notify.stamp_saved", &[("path", &path.display())]),
+                        ));
+                        library.name.clear();
+                        library.scan();
                     }
                     Err(err) => {
-                        eprintln!("Failed to load map: {err:?}");
+                        notify.send(Notify::error(
+                            loc.format("notify.stamp_save_failed", &[("error", &err)]),
+                        ));
                     }
                 }
             }
-        }
+
+            ui.separator();
+            if library.stamps.is_empty() {
+                ui.weak(loc.tr("stamps.none"));
+            }
+            let mut deleted = None;
+            for (index, (_, stamp)) in library.stamps.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    let label = loc.format(
+                        "stamps.entry",
+                        &[("name", &stamp.name), ("count", &stamp.tiles.len())],
+                    );
+                    if ui
+                        .button(label)
+                        .on_hover_text(loc.tr("stamps.entry.hint"))
+                        .clicked()
+                    {
+                        let base_tool = temporary.base(&state);
+                        clipboard.paste_stamp(stamp, &mut state, base_tool);
+                    }
+                    if ui.small_button(loc.tr("stamps.delete")).clicked() {
+                        deleted = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = deleted {
+                let (path, _) = library.stamps.remove(index);
+                if let Err(err) = std::fs::remove_file(&path) {
+                    notify.send(Notify::error(loc.format(
+                        "notify.stamp_delete_failed",
+                        &[("path", &path.display()), ("error", &err)],
+                    )));
+                }
+            }
+        });
+}
+
+fn light_settings(ui: &mut egui::Ui, loc: &Localization, light: &mut MapLight) {
+    ui.selectable_value(&mut light.kind, LightKind::Point, loc.tr("lights.point"));
+    ui.selectable_value(&mut light.kind, LightKind::Spot, loc.tr("lights.spot"));
+    ui.color_edit_button_srgb(&mut light.color);
+    ui.label(loc.tr("lights.intensity"));
+    ui.add(
+        egui::DragValue::new(&mut light.intensity)
+            .range(0.0..=10_000_000.0)
+            .speed(1000.0)
+            .suffix(" lm"),
+    );
+    ui.label(loc.tr("lights.range"));
+    ui.add(
+        egui::DragValue::new(&mut light.range)
+            .range(0.5..=100.0)
+            .speed(0.1),
+    );
+    ui.label(loc.tr("lights.height"));
+    ui.add(
+        egui::DragValue::new(&mut light.height)
+            .range(0.0..=20.0)
+            .speed(0.05),
+    );
+    if light.kind == LightKind::Spot {
+        ui.label(loc.tr("start.facing"));
+        ui.add(
+            egui::DragValue::new(&mut light.facing)
+                .range(0.0..=359.0)
+                .suffix("°"),
+        );
+        ui.label(loc.tr("lights.pitch"));
+        ui.add(
+            egui::DragValue::new(&mut light.pitch)
+                .range(0.0..=90.0)
+                .suffix("°"),
+        );
+        ui.label(loc.tr("lights.cone"));
+        ui.add(
+            egui::DragValue::new(&mut light.cone)
+                .range(5.0..=170.0)
+                .suffix("°"),
+        );
     }
 }
 
@@ -310,6 +1992,96 @@ struct PaletteItem {
     texture: egui::TextureId,
 }
 
+fn decal_label_key(kind: DecalKind) -> &'static str {
+    match kind {
+        DecalKind::Crater => "decal.crater",
+        DecalKind::Crack => "decal.crack",
+        DecalKind::Leaves => "decal.leaves",
+    }
+}
+
+fn road_label_key(surface: RoadSurface) -> &'static str {
+    match surface {
+        RoadSurface::Dirt => "road.dirt",
+        RoadSurface::Paved => "road.paved",
+    }
+}
+
+/// A palette button showing a short stretch of road over grass, sized like
+/// the texture previews.
+fn road_swatch(ui: &mut egui::Ui, surface: RoadSurface, selected: bool) -> egui::Response {
+    // Roughly the shader's road colours, in sRGB.
+    let road = match surface {
+        RoadSurface::Dirt => egui::Color32::from_rgb(132, 108, 79),
+        RoadSurface::Paved => egui::Color32::from_rgb(84, 84, 89),
+    };
+    let (rect, response) = ui.allocate_exact_size(egui::Vec2::splat(36.0), egui::Sense::click());
+    let painter = ui.painter();
+    let inner = rect.shrink(2.0);
+    painter.rect_filled(inner, 0.0, egui::Color32::from_rgb(92, 110, 70));
+    painter.rect_filled(
+        egui::Rect::from_center_size(inner.center(), egui::vec2(inner.width(), 12.0)),
+        0.0,
+        road,
+    );
+    if selected {
+        painter.rect_stroke(
+            rect,
+            0.0,
+            egui::Stroke::new(2.0, egui::Color32::from_rgb(0, 122, 204)),
+        );
+    }
+    response
+}
+
+/// Asks where to save the map, starting at its current file.
+pub(crate) fn save_map_dialog(
+    state: &crate::editor::EditorState,
+    loc: &Localization,
+) -> Task<Option<PathBuf>> {
+    let mut dialog = AsyncFileDialog::new()
+        .set_title(loc.tr("dialog.save_map"))
+        .add_filter(loc.tr("dialog.map_filter"), &["tme"])
+        .add_filter(loc.tr("dialog.text_map_filter"), &[text_map::EXTENSION])
+        .add_filter(loc.tr("dialog.json_map_filter"), &[JSON_EXTENSION]);
+    if let Some(path) = state.current_file_path.as_ref() {
+        if let Some(parent) = path.parent() {
+            dialog = dialog.set_directory(parent);
+        }
+        if let Some(file_name) = path.file_name().and_then(|name| name.to_str()) {
+            dialog = dialog.set_file_name(file_name);
+        }
+    }
+    IoTaskPool::get().spawn(async move {
+        dialog
+            .save_file()
+            .await
+            .map(|file| file.path().to_path_buf())
+    })
+}
+
+/// Asks for a map to open, starting in the current file's folder.
+pub(crate) fn open_map_dialog(
+    state: &crate::editor::EditorState,
+    loc: &Localization,
+) -> Task<Option<PathBuf>> {
+    let mut dialog = AsyncFileDialog::new()
+        .set_title(loc.tr("dialog.open_map"))
+        .add_filter(
+            loc.tr("dialog.map_filter"),
+            &["tme", text_map::EXTENSION, JSON_EXTENSION],
+        );
+    if let Some(parent) = state.current_file_path.as_ref().and_then(|p| p.parent()) {
+        dialog = dialog.set_directory(parent);
+    }
+    IoTaskPool::get().spawn(async move {
+        dialog
+            .pick_file()
+            .await
+            .map(|file| file.path().to_path_buf())
+    })
+}
+
 fn ensure_extension(mut path: PathBuf, extension: &str) -> PathBuf {
     let needs_extension = path
         .extension()
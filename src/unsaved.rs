@@ -0,0 +1,218 @@
+use std::path::PathBuf;
+
+use bevy::app::AppExit;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::window::{PrimaryWindow, WindowCloseRequested};
+use bevy_egui::{EguiContexts, egui};
+
+use crate::bundle::BundleRequest;
+use crate::editor::{EditorState, MapEdit};
+use crate::localization::Localization;
+use crate::new_map::NewMapSettings;
+use crate::notifications::Notify;
+use crate::recent::RecentFiles;
+use crate::ui;
+
+/// Asks whether to save, discard or keep unsaved changes before the map is
+/// replaced or the editor closes, and marks them with an asterisk in the
+/// window title. The window must not close on its own when asked to, so
+/// closing goes through here too.
+pub struct UnsavedChangesPlugin;
+
+impl Plugin for UnsavedChangesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FileAction>()
+            .init_resource::<PendingAction>()
+            .add_systems(
+                Update,
+                (request_file_actions, confirm_discard, update_window_title).chain(),
+            );
+    }
+}
+
+/// Something that replaces or closes the edited map, held back while there
+/// are unsaved changes until the user decides what to do with them.
+#[derive(Event, Clone, PartialEq, Debug)]
+pub enum FileAction {
+    /// Ask for a map to open.
+    Load,
+    Open(PathBuf),
+    OpenBundle,
+    /// Start over with a map made from the "New map…" dialog.
+    New(NewMapSettings),
+    Quit,
+}
+
+#[derive(Resource, Default)]
+struct PendingAction {
+    action: Option<FileAction>,
+    /// Whether the save dialog opened from the prompt is still up.
+    saving: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Choice {
+    Save,
+    Discard,
+    Cancel,
+}
+
+#[derive(SystemParam)]
+struct FileActions<'w> {
+    state: ResMut<'w, EditorState>,
+    recent: ResMut<'w, RecentFiles>,
+    edits: EventWriter<'w, MapEdit>,
+    bundles: EventWriter<'w, BundleRequest>,
+    exit: EventWriter<'w, AppExit>,
+    notify: EventWriter<'w, Notify>,
+    localization: Res<'w, Localization>,
+}
+
+impl FileActions<'_> {
+    fn perform(&mut self, action: FileAction) {
+        match action {
+            FileAction::Load => {
+                if self.state.load_dialog_task.is_none() {
+                    let task = ui::open_map_dialog(&self.state, &self.localization);
+                    self.state.load_dialog_task = Some(task);
+                }
+            }
+            FileAction::Open(path) => {
+                if let Err(err) = self.state.open_map(path.clone(), &mut self.edits) {
+                    self.notify.send(Notify::error(
+                        self.localization
+                            .format("notify.load_failed", &[("error", &err)]),
+                    ));
+                    self.recent.remove(&path);
+                }
+            }
+            FileAction::OpenBundle => {
+                self.bundles.send(BundleRequest::Open);
+            }
+            FileAction::New(settings) => {
+                self.edits
+                    .send(MapEdit::Replace(Box::new(settings.build())));
+                self.state.current_file_path = None;
+                self.state.delta_base = None;
+            }
+            FileAction::Quit => {
+                self.exit.send(AppExit::Success);
+            }
+        }
+    }
+}
+
+fn request_file_actions(
+    mut requests: EventReader<FileAction>,
+    mut closes: EventReader<WindowCloseRequested>,
+    mut pending: ResMut<PendingAction>,
+    mut actions: FileActions,
+) {
+    let quit = closes.read().last().map(|_| FileAction::Quit);
+    for action in requests.read().cloned().chain(quit) {
+        if pending.action.is_some() {
+            continue;
+        }
+        if actions.state.unsaved_changes {
+            pending.action = Some(action);
+        } else {
+            actions.perform(action);
+        }
+    }
+}
+
+fn confirm_discard(
+    mut egui: EguiContexts,
+    mut pending: ResMut<PendingAction>,
+    mut actions: FileActions,
+) {
+    if pending.saving {
+        if actions.state.save_dialog_task.is_some() {
+            return;
+        }
+        pending.saving = false;
+        // Cancelling or failing to save leaves the changes unsaved.
+        let action = pending.action.take();
+        if let Some(action) = action.filter(|_| !actions.state.unsaved_changes) {
+            actions.perform(action);
+        }
+        return;
+    }
+    if pending.action.is_none() {
+        return;
+    }
+
+    let loc = &*actions.localization;
+    let mut choice = None;
+    egui::Window::new(loc.tr("unsaved.title"))
+        .id(egui::Id::new("unsaved_changes"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(egui.ctx_mut(), |ui| {
+            ui.label(loc.format(
+                "unsaved.message",
+                &[("name", &file_label(&actions.state, loc))],
+            ));
+            ui.horizontal(|ui| {
+                if ui.button(loc.tr("unsaved.save")).clicked() {
+                    choice = Some(Choice::Save);
+                }
+                if ui.button(loc.tr("unsaved.discard")).clicked() {
+                    choice = Some(Choice::Discard);
+                }
+                if ui.button(loc.tr("unsaved.cancel")).clicked() {
+                    choice = Some(Choice::Cancel);
+                }
+            });
+        });
+
+    match choice {
+        Some(Choice::Save) => {
+            if actions.state.save_dialog_task.is_none() {
+                let task = ui::save_map_dialog(&actions.state, &actions.localization);
+                actions.state.save_dialog_task = Some(task);
+            }
+            pending.saving = true;
+        }
+        Some(Choice::Discard) => {
+            if let Some(action) = pending.action.take() {
+                actions.perform(action);
+            }
+        }
+        Some(Choice::Cancel) => pending.action = None,
+        None => {}
+    }
+}
+
+/// The current file's name, or a placeholder for maps never saved.
+fn file_label(state: &EditorState, loc: &Localization) -> String {
+    state
+        .current_file_path
+        .as_ref()
+        .and_then(|path| path.file_name())
+        .map_or_else(
+            || loc.tr("unsaved.untitled").to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+}
+
+fn update_window_title(
+    state: Res<EditorState>,
+    localization: Res<Localization>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    let title = format!(
+        "{}{} - {}",
+        file_label(&state, &localization),
+        if state.unsaved_changes { "*" } else { "" },
+        localization.tr("app.title"),
+    );
+    if window.title != title {
+        window.title = title;
+    }
+}
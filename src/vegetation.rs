@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+
+use crate::editor::EditorState;
+use crate::terrain::{CornerHeightCache, TerrainChanged, TerrainMeshSet};
+use crate::types::TileKind;
+
+/// Tufts on a tile at full density.
+const MAX_TUFTS: f32 = 8.0;
+const TUFT_WIDTH: f32 = 0.35;
+const TUFT_HEIGHT: f32 = 0.45;
+const BASE_COLOR: [f32; 4] = [0.18, 0.32, 0.1, 1.0];
+const TIP_COLOR: [f32; 4] = [0.55, 0.75, 0.3, 1.0];
+
+/// Previews the painted vegetation density as grass tufts scattered over
+/// each tile. Every tuft shares one mesh and material, so the renderer
+/// draws them as instanced batches.
+pub struct VegetationPlugin;
+
+impl Plugin for VegetationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VegetationPreview>()
+            .add_systems(Startup, setup_tuft_assets)
+            .add_systems(
+                Update,
+                sync_vegetation_preview.after(TerrainMeshSet::Prepare),
+            );
+    }
+}
+
+#[derive(Resource)]
+struct TuftAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+/// Tufts currently spawned, keyed by tile, with the density they were
+/// scattered for.
+#[derive(Resource, Default)]
+struct VegetationPreview {
+    size: (u32, u32),
+    tiles: HashMap<(u32, u32), (u8, Vec<Entity>)>,
+}
+
+fn setup_tuft_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(TuftAssets {
+        mesh: meshes.add(tuft_mesh()),
+        material: materials.add(StandardMaterial {
+            base_color: Color::WHITE,
+            perceptual_roughness: 0.9,
+            double_sided: true,
+            cull_mode: None,
+            ..default()
+        }),
+    });
+}
+
+/// Two crossed upright quads, darker at the root than at the tip.
+fn tuft_mesh() -> Mesh {
+    let half = TUFT_WIDTH * 0.5;
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut colors = Vec::new();
+    let mut indices = Vec::new();
+    for (side, normal) in [(Vec3::X, Vec3::Z), (Vec3::Z, Vec3::X)] {
+        let base = positions.len() as u32;
+        for (offset, y, color) in [
+            (-half, 0.0, BASE_COLOR),
+            (half, 0.0, BASE_COLOR),
+            (half, TUFT_HEIGHT, TIP_COLOR),
+            (-half, TUFT_HEIGHT, TIP_COLOR),
+        ] {
+            positions.push((side * offset + Vec3::Y * y).to_array());
+            normals.push(normal.to_array());
+            colors.push(color);
+        }
+        indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.insert_indices(Indices::U32(indices));
+    mesh
+}
+
+/// Density a tile is drawn with; holes never grow anything.
+fn effective_density(state: &EditorState, x: u32, y: u32) -> u8 {
+    if state.map.get(x, y).kind == TileKind::Empty {
+        0
+    } else {
+        state.map.vegetation(x, y)
+    }
+}
+
+/// Scatters a tile's tufts at spots derived from its coordinates, so the
+/// same density always looks the same and repainting doesn't shuffle them.
+fn tuft_transforms(
+    x: u32,
+    y: u32,
+    density: u8,
+    heights: &CornerHeightCache,
+) -> impl Iterator<Item = Transform> + '_ {
+    let count = (density as f32 / 255.0 * MAX_TUFTS).round() as u64;
+    let mut rng = fastrand::Rng::with_seed(((x as u64) << 32) | y as u64);
+    (0..count).filter_map(move |_| {
+        let world_x = (x as f32 + rng.f32()) * heights.tile_size();
+        let world_z = (y as f32 + rng.f32()) * heights.tile_size();
+        let yaw = rng.f32() * std::f32::consts::TAU;
+        let scale = 0.7 + rng.f32() * 0.6;
+        let ground = heights.height_at(world_x, world_z)?;
+        Some(
+            Transform::from_xyz(world_x, ground, world_z)
+                .with_rotation(Quat::from_rotation_y(yaw))
+                .with_scale(Vec3::splat(scale)),
+        )
+    })
+}
+
+/// Respawns the tufts of tiles whose density changed, all of them when the
+/// terrain moves under them, and none while the preview is hidden.
+fn sync_vegetation_preview(
+    mut commands: Commands,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    assets: Option<Res<TuftAssets>>,
+    mut terrain_changed: EventReader<TerrainChanged>,
+    mut preview: ResMut<VegetationPreview>,
+) {
+    let moved = !terrain_changed.is_empty();
+    terrain_changed.clear();
+    let Some(assets) = assets else {
+        return;
+    };
+
+    let size = (state.map.width, state.map.height);
+    if moved || !state.show_vegetation || preview.size != size {
+        for (_, (_, entities)) in preview.tiles.drain() {
+            for entity in entities {
+                commands.entity(entity).despawn();
+            }
+        }
+        preview.size = size;
+    }
+    if !state.show_vegetation || (state.map.vegetation.is_empty() && preview.tiles.is_empty()) {
+        return;
+    }
+
+    for y in 0..state.map.height {
+        for x in 0..state.map.width {
+            let density = effective_density(&state, x, y);
+            let shown = preview
+                .tiles
+                .get(&(x, y))
+                .map_or(0, |(density, _)| *density);
+            if density == shown {
+                continue;
+            }
+            if let Some((_, entities)) = preview.tiles.remove(&(x, y)) {
+                for entity in entities {
+                    commands.entity(entity).despawn();
+                }
+            }
+            if density == 0 {
+                continue;
+            }
+            let entities = tuft_transforms(x, y, density, &heights)
+                .map(|transform| {
+                    commands
+                        .spawn((
+                            PbrBundle {
+                                mesh: assets.mesh.clone(),
+                                material: assets.material.clone(),
+                                transform,
+                                ..default()
+                            },
+                            Name::new("VegetationTuft"),
+                        ))
+                        .id()
+                })
+                .collect();
+            preview.tiles.insert((x, y), (density, entities));
+        }
+    }
+}
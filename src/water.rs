@@ -0,0 +1,172 @@
+use bevy::pbr::{MaterialMeshBundle, MaterialPlugin, NotShadowCaster};
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{AsBindGroup, ShaderRef};
+
+use crate::runtime::RuntimeMap;
+use crate::terrain::{self, CORNER_NE, CORNER_NW, CORNER_SE, CORNER_SW, CornerHeightCache};
+use crate::types::{TileKind, TileMap};
+
+/// Draws the map's water level as a translucent, animated surface over the
+/// terrain beneath it. Only tiles that reach below the level get water, so
+/// lakes stop at their shores instead of covering the whole map.
+pub struct WaterPlugin;
+
+impl Plugin for WaterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MaterialPlugin::<WaterMaterial>::default())
+            .add_systems(Startup, setup_water)
+            .add_systems(
+                Update,
+                rebuild_water_mesh.in_set(terrain::TerrainMeshSet::Rebuild),
+            );
+    }
+}
+
+/// Looks of the water surface, passed to `shaders/water.wgsl`.
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug)]
+pub struct WaterMaterial {
+    /// Linear color seen looking straight down, with its opacity over deep
+    /// water.
+    #[uniform(0)]
+    pub color: Vec4,
+    /// Linear color reflected at grazing angles.
+    #[uniform(1)]
+    pub sky_color: Vec4,
+    /// How far the ripples drift per second in world units (x, y), ripples
+    /// per world unit (z) and the depth in world units over which the water
+    /// fades in from the shore (w).
+    #[uniform(2)]
+    pub ripples: Vec4,
+}
+
+impl Default for WaterMaterial {
+    fn default() -> Self {
+        Self {
+            color: Vec4::new(0.02, 0.12, 0.16, 0.85),
+            sky_color: Vec4::new(0.45, 0.6, 0.75, 1.0),
+            ripples: Vec4::new(0.15, 0.08, 1.5, 0.8),
+        }
+    }
+}
+
+impl Material for WaterMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/water.wgsl".into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        AlphaMode::Blend
+    }
+}
+
+#[derive(Resource)]
+pub struct WaterVisual {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<WaterMaterial>,
+    pub entity: Entity,
+}
+
+fn setup_water(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<WaterMaterial>>,
+) {
+    let mesh = meshes.add(terrain::empty_mesh());
+    let material = materials.add(WaterMaterial::default());
+    let entity = commands
+        .spawn((
+            MaterialMeshBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            NotShadowCaster,
+            Name::new("Water"),
+        ))
+        .id();
+    commands.insert_resource(WaterVisual {
+        mesh,
+        material,
+        entity,
+    });
+}
+
+fn rebuild_water_mesh(
+    runtime_map: Res<RuntimeMap>,
+    heights: Res<CornerHeightCache>,
+    mut changed: EventReader<terrain::TerrainChanged>,
+    visual: Option<Res<WaterVisual>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut visibility: Query<&mut Visibility>,
+) {
+    if changed.is_empty() {
+        return;
+    }
+    changed.clear();
+    let Some(visual) = visual else {
+        return;
+    };
+
+    let mesh = build_water_mesh(&runtime_map.map, &heights);
+    if let Ok(mut visibility) = visibility.get_mut(visual.entity) {
+        *visibility = if mesh.is_some() {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+    if let (Some(mesh), Some(existing)) = (mesh, meshes.get_mut(&visual.mesh)) {
+        *existing = mesh;
+    }
+}
+
+/// One flat quad per tile that dips below the water level, or `None` when
+/// the map is dry. Each vertex's first UV coordinate holds the water depth
+/// there, which the shader uses to fade the water out towards the shore.
+pub fn build_water_mesh(map: &TileMap, heights: &CornerHeightCache) -> Option<Mesh> {
+    let surface = map.water_level? * map.tile_height();
+    let size = map.tile_size;
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut indices = Vec::new();
+    for y in 0..map.height {
+        for x in 0..map.width {
+            let ground = match heights.corners(x, y) {
+                Some(corners) => corners,
+                None if map.get(x, y).kind == TileKind::Empty => [terrain::hole_floor(map); 4],
+                None => continue,
+            };
+            if ground.iter().all(|&height| height >= surface) {
+                continue;
+            }
+            let base = positions.len() as u32;
+            for (corner, dx, dy) in [
+                (CORNER_NW, 0, 0),
+                (CORNER_NE, 1, 0),
+                (CORNER_SW, 0, 1),
+                (CORNER_SE, 1, 1),
+            ] {
+                positions.push([(x + dx) as f32 * size, surface, (y + dy) as f32 * size]);
+                uvs.push([surface - ground[corner], 0.0]);
+            }
+            indices.extend([0, 2, 1, 1, 2, 3].map(|i| base + i));
+        }
+    }
+    if positions.is_empty() {
+        return None;
+    }
+
+    let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_indices(Indices::U32(indices));
+    Some(mesh)
+}
@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+
+use crate::editor::EditorState;
+use crate::terrain::{CornerHeightCache, TerrainMeshSet};
+use crate::types::{ELEVATION_FRACTION, MAX_ELEVATION, MIN_ELEVATION, Weather, WeatherKind};
+
+/// How far above the highest possible ground rain and snow start falling, in
+/// world units.
+const CEILING_CLEARANCE: f32 = 10.0;
+/// Dust stays within this many world units of the ground.
+const DUST_HEIGHT: f32 = 1.5;
+const RAIN_COLOR: Color = Color::srgba(0.7, 0.8, 1.0, 0.5);
+const SNOW_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.9);
+const DUST_COLOR: Color = Color::srgba(0.8, 0.65, 0.45, 0.35);
+
+/// Previews the map's weather as simple gizmo particles drifting over the
+/// terrain, so textures can be judged under the intended conditions.
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WeatherParticles>().add_systems(
+            Update,
+            (simulate_weather, draw_weather)
+                .chain()
+                .after(TerrainMeshSet::Prepare),
+        );
+    }
+}
+
+struct Particle {
+    position: Vec3,
+    /// Random offset for the particle's sway, so particles don't move in
+    /// lockstep.
+    phase: f32,
+}
+
+#[derive(Resource, Default)]
+struct WeatherParticles {
+    kind: WeatherKind,
+    particles: Vec<Particle>,
+}
+
+/// Particles at full intensity.
+fn particle_budget(kind: WeatherKind) -> usize {
+    match kind {
+        WeatherKind::Clear => 0,
+        WeatherKind::Rain => 4000,
+        WeatherKind::Snow => 2500,
+        WeatherKind::Dust => 1500,
+    }
+}
+
+fn fall_velocity(kind: WeatherKind) -> Vec3 {
+    match kind {
+        WeatherKind::Rain => Vec3::new(0.0, -18.0, 0.0),
+        WeatherKind::Snow => Vec3::new(0.0, -1.5, 0.0),
+        WeatherKind::Clear | WeatherKind::Dust => Vec3::ZERO,
+    }
+}
+
+fn wind_velocity(weather: &Weather) -> Vec3 {
+    let direction = weather.wind_direction.to_radians();
+    Vec3::new(direction.sin(), 0.0, -direction.cos()) * weather.wind_speed
+}
+
+fn elevation_height(heights: &CornerHeightCache, elevation: i8) -> f32 {
+    elevation as f32 * heights.tile_size() * ELEVATION_FRACTION
+}
+
+fn ceiling(heights: &CornerHeightCache) -> f32 {
+    elevation_height(heights, MAX_ELEVATION) + CEILING_CLEARANCE
+}
+
+fn ground_height(heights: &CornerHeightCache, x: f32, z: f32) -> f32 {
+    heights
+        .height_at(x, z)
+        .unwrap_or_else(|| elevation_height(heights, MIN_ELEVATION))
+}
+
+/// A particle at a random spot over the map; rain and snow anywhere below
+/// the ceiling, dust near the ground.
+fn spawn_particle(kind: WeatherKind, extent: Vec2, heights: &CornerHeightCache) -> Particle {
+    let (x, z) = (fastrand::f32() * extent.x, fastrand::f32() * extent.y);
+    let ground = ground_height(heights, x, z);
+    let y = match kind {
+        WeatherKind::Dust => ground + fastrand::f32() * DUST_HEIGHT,
+        _ => ground + fastrand::f32() * (ceiling(heights) - ground).max(0.0),
+    };
+    Particle {
+        position: Vec3::new(x, y, z),
+        phase: fastrand::f32() * std::f32::consts::TAU,
+    }
+}
+
+fn simulate_weather(
+    time: Res<Time>,
+    state: Res<EditorState>,
+    heights: Res<CornerHeightCache>,
+    mut weather: ResMut<WeatherParticles>,
+) {
+    let settings = &state.map.weather;
+    let kind = if state.show_weather {
+        settings.kind
+    } else {
+        WeatherKind::Clear
+    };
+    if weather.kind != kind {
+        weather.kind = kind;
+        weather.particles.clear();
+    }
+    if kind == WeatherKind::Clear {
+        return;
+    }
+
+    let extent = Vec2::new(
+        state.map.width as f32 * state.map.tile_size,
+        state.map.height as f32 * state.map.tile_size,
+    );
+    let target = (particle_budget(kind) as f32 * settings.intensity.clamp(0.0, 1.0)) as usize;
+    weather.particles.truncate(target);
+    while weather.particles.len() < target {
+        let particle = spawn_particle(kind, extent, &heights);
+        weather.particles.push(particle);
+    }
+
+    let drift = fall_velocity(kind) + wind_velocity(settings);
+    let dt = time.delta_seconds();
+    let t = time.elapsed_seconds();
+    for particle in &mut weather.particles {
+        let phase = particle.phase;
+        let sway = match kind {
+            WeatherKind::Snow => {
+                Vec3::new((t * 1.3 + phase).sin(), 0.0, (t * 0.9 + phase).cos()) * 0.6
+            }
+            WeatherKind::Dust => Vec3::new(
+                (t * 0.8 + phase).sin() * 1.5,
+                (t * 0.7 + phase).sin() * 0.3,
+                (t * 0.6 + phase).cos() * 1.5,
+            ),
+            _ => Vec3::ZERO,
+        };
+        let mut position = particle.position + (drift + sway) * dt;
+        // Wind carries particles off one edge of the map and back in on the
+        // other.
+        position.x = position.x.rem_euclid(extent.x.max(f32::EPSILON));
+        position.z = position.z.rem_euclid(extent.y.max(f32::EPSILON));
+
+        let ground = ground_height(&heights, position.x, position.z);
+        let landed = position.y < ground;
+        let blown_away = kind == WeatherKind::Dust && position.y > ground + DUST_HEIGHT * 1.5;
+        if landed || blown_away {
+            let (x, z) = (fastrand::f32() * extent.x, fastrand::f32() * extent.y);
+            let ground = ground_height(&heights, x, z);
+            position = match kind {
+                WeatherKind::Dust => Vec3::new(x, ground + fastrand::f32() * DUST_HEIGHT, z),
+                _ => Vec3::new(x, ceiling(&heights).max(ground), z),
+            };
+        }
+        particle.position = position;
+    }
+}
+
+fn draw_weather(mut gizmos: Gizmos, state: Res<EditorState>, weather: Res<WeatherParticles>) {
+    let settings = &state.map.weather;
+    let wind = wind_velocity(settings);
+    match weather.kind {
+        WeatherKind::Clear => {}
+        WeatherKind::Rain => {
+            // Streaks point along the drop's motion.
+            let streak = (fall_velocity(WeatherKind::Rain) + wind).normalize_or_zero() * 0.5;
+            for particle in &weather.particles {
+                gizmos.line(particle.position, particle.position - streak, RAIN_COLOR);
+            }
+        }
+        WeatherKind::Snow => {
+            for particle in &weather.particles {
+                gizmos
+                    .circle(particle.position, Dir3::Y, 0.06, SNOW_COLOR)
+                    .resolution(5);
+            }
+        }
+        WeatherKind::Dust => {
+            let streak = if wind == Vec3::ZERO {
+                Vec3::X * 0.15
+            } else {
+                wind.normalize() * 0.15
+            };
+            for particle in &weather.particles {
+                gizmos.line(particle.position, particle.position + streak, DUST_COLOR);
+            }
+        }
+    }
+}